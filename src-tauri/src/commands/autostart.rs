@@ -1,39 +1,76 @@
 // Autostart 命令
 use tauri_plugin_autostart::ManagerExt;
 
+use crate::modules::login_item::{self, LoginItemOptions, LoginItemStatus};
+
+/// 清理旧版本通过 autostart 插件注册的启动项，避免与新注册项重复启动
+fn remove_legacy_entry(app: &tauri::AppHandle) {
+    let manager = app.autolaunch();
+    if manager.is_enabled().unwrap_or(false) {
+        match manager.disable() {
+            Ok(_) => crate::modules::logger::log_info("已清理旧版开机自启项"),
+            Err(e) => {
+                crate::modules::logger::log_warn(&format!("清理旧版开机自启项失败: {}", e))
+            }
+        }
+    }
+}
+
 #[tauri::command]
 pub async fn toggle_auto_launch(
     app: tauri::AppHandle,
     enable: bool,
 ) -> Result<(), String> {
+    set_login_item(app, enable, None).await.map(|_| ())
+}
+
+#[tauri::command]
+pub async fn is_auto_launch_enabled(app: tauri::AppHandle) -> Result<bool, String> {
+    if login_item::status()?.enabled {
+        return Ok(true);
+    }
+    // 兼容旧版插件注册的启动项
     let manager = app.autolaunch();
-    
+    manager.is_enabled().map_err(|e| e.to_string())
+}
+
+/// 注册/取消登录启动；options 为空时沿用当前注册项的选项（未注册时使用默认值）
+#[tauri::command]
+pub async fn set_login_item(
+    app: tauri::AppHandle,
+    enable: bool,
+    options: Option<LoginItemOptions>,
+) -> Result<LoginItemStatus, String> {
+    remove_legacy_entry(&app);
+
     if enable {
-        manager.enable().map_err(|e| format!("启用自动启动失败: {}", e))?;
-        crate::modules::logger::log_info("已启用开机自动启动");
-    } else {
-        match manager.disable() {
-            Ok(_) => {
-                crate::modules::logger::log_info("已禁用开机自动启动");
-            },
-            Err(e) => {
-                let err_msg = e.to_string();
-                // 在 Windows 上，如果注册表项不存在，disable() 会返回 "系统找不到指定的文件" (os error 2)
-                // 这种情况应该视为成功，因为目标（禁用）已经达成
-                if err_msg.contains("os error 2") || err_msg.contains("找不到指定的文件") {
-                    crate::modules::logger::log_info("开机自启项已不存在，视为禁用成功");
+        let options = match options {
+            Some(options) => options,
+            None => {
+                let current = login_item::status()?;
+                if current.enabled {
+                    current.options
                 } else {
-                    return Err(format!("禁用自动启动失败: {}", e));
+                    LoginItemOptions::default()
                 }
             }
-        }
+        };
+        login_item::enable(&options).map_err(|e| format!("启用自动启动失败: {}", e))?;
+    } else {
+        login_item::disable().map_err(|e| format!("禁用自动启动失败: {}", e))?;
     }
-    
-    Ok(())
+
+    login_item::status()
 }
 
+/// 查询登录启动状态（注册项位置、启动选项、路径是否过期）
 #[tauri::command]
-pub async fn is_auto_launch_enabled(app: tauri::AppHandle) -> Result<bool, String> {
-    let manager = app.autolaunch();
-    manager.is_enabled().map_err(|e| e.to_string())
+pub async fn get_login_item_status() -> Result<LoginItemStatus, String> {
+    login_item::status()
+}
+
+/// 本次是否以最小化方式启动（前端据此决定是否显示主窗口）
+#[tauri::command]
+pub async fn is_started_minimized() -> Result<bool, String> {
+    Ok(login_item::started_minimized())
 }
@@ -24,13 +24,104 @@ pub async fn list_accounts() -> Result<Vec<Account>, String> {
     modules::list_accounts()
 }
 
+/// 列出账号摘要（仅读取索引，不加载 token 等敏感数据），反代运行时附带熔断状态
+#[tauri::command]
+pub async fn list_account_summaries(
+    proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
+) -> Result<Vec<crate::models::AccountSummary>, String> {
+    let mut summaries = modules::list_account_summaries()?;
+    attach_circuit_states(&proxy_state, &mut summaries).await;
+    Ok(summaries)
+}
+
+/// 用运行中反代的熔断状态填充账号摘要
+async fn attach_circuit_states(
+    proxy_state: &crate::commands::proxy::ProxyServiceState,
+    summaries: &mut [crate::models::AccountSummary],
+) {
+    let circuits = match proxy_state.instance.read().await.as_ref() {
+        Some(instance) => instance.token_manager.circuit_snapshot(),
+        None => return,
+    };
+    for summary in summaries {
+        summary.circuit = circuits.get(&summary.id).cloned();
+    }
+}
+
+/// 按需加载单个账号的完整数据
+#[tauri::command]
+pub async fn get_account(account_id: String) -> Result<Account, String> {
+    modules::load_account(&account_id)
+}
+
+/// 获取账号 / 实例读缓存的命中统计
+#[tauri::command]
+pub async fn get_cache_stats() -> Result<Vec<crate::modules::cache::CacheStats>, String> {
+    Ok(crate::modules::cache::get_cache_stats())
+}
+
+/// 分页查询账号（服务端过滤 / 排序）
+#[tauri::command]
+pub async fn query_accounts(
+    proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
+    query: Option<crate::models::AccountQuery>,
+) -> Result<crate::models::AccountPage, String> {
+    let mut page = modules::query_accounts(&query.unwrap_or_default())?;
+    attach_circuit_states(&proxy_state, &mut page.items).await;
+    Ok(page)
+}
+
+/// 设置账号标签
+#[tauri::command]
+pub async fn set_account_tags(account_id: String, tags: Vec<String>) -> Result<Account, String> {
+    modules::set_account_tags(&account_id, tags)
+}
+
+/// 设置账号的上游代理（OAuth、配额与反代请求），传 None 使用全局代理
+#[tauri::command]
+pub async fn set_account_proxy(
+    proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
+    account_id: String,
+    proxy_url: Option<String>,
+) -> Result<Account, String> {
+    let account = modules::account::set_account_proxy(&account_id, proxy_url)?;
+
+    // 同步到运行中的反代服务（如果已启动）
+    let instance_lock = proxy_state.instance.read().await;
+    if let Some(instance) = instance_lock.as_ref() {
+        let _ = instance.token_manager.reload_account(&account_id).await;
+    }
+
+    Ok(account)
+}
+
+/// 设置临时账号的过期时间（Unix 秒），传 None 取消临时标记
+#[tauri::command]
+pub async fn set_account_expiry(
+    account_id: String,
+    expires_at: Option<i64>,
+) -> Result<Account, String> {
+    modules::account::set_account_expiry(&account_id, expires_at)
+}
+
+/// 获取已过期临时账号的归档记录
+#[tauri::command]
+pub async fn list_archived_accounts() -> Result<Vec<crate::models::ArchivedAccount>, String> {
+    modules::account::list_archived_accounts()
+}
+
 /// 添加账号
+/// `expires_at` 不为空时作为临时账号添加，到期后自动归档并清除凭据
 #[tauri::command]
 pub async fn add_account(
     app: tauri::AppHandle,
     _email: String,
     refresh_token: String,
+    expires_at: Option<i64>,
 ) -> Result<Account, String> {
+    // 过期时间无效时直接拒绝，不发起任何网络请求
+    modules::account::validate_expiry(expires_at)?;
+
     // 1. 使用 refresh_token 获取 access_token
     // 注意：这里我们忽略传入的 _email，而是直接去 Google 获取真实的邮箱
     let token_res = modules::oauth::refresh_access_token(&refresh_token).await?;
@@ -48,14 +139,17 @@ pub async fn add_account(
         None, // session_id
     );
 
-    // 4. 使用真实的 email 添加或更新账号
-    let account =
-        modules::upsert_account(user_info.email.clone(), user_info.get_display_name(), token)?;
+    // 4. 使用真实的 email 添加或更新账号 (临时账号同时记录过期时间)
+    let mut account = modules::account::upsert_account_with_expiry(
+        user_info.email.clone(),
+        user_info.get_display_name(),
+        token,
+        expires_at,
+    )?;
 
     modules::logger::log_info(&format!("添加账号成功: {}", account.email));
 
     // 5. 自动触发刷新额度
-    let mut account = account;
     let _ = internal_refresh_account_quota(&app, &mut account).await;
 
     // 6. If proxy is running, reload token pool so changes take effect immediately.
@@ -283,6 +377,8 @@ pub async fn fetch_account_quota(
     modules::logger::log_info(&format!("手动刷新配额请求: {}", account_id));
     let mut account =
         modules::load_account(&account_id).map_err(crate::error::AppError::Account)?;
+    // 手动刷新总是请求上游，不使用缓存的配额
+    modules::quota::invalidate_quota_cache(&account.email);
 
     // 使用带重试的查询 (Shared logic)
     let quota = modules::account::fetch_quota_with_retry(&mut account).await?;
@@ -307,9 +403,11 @@ pub use modules::account::RefreshStats;
 /// 刷新所有账号配额
 #[tauri::command]
 pub async fn refresh_all_quotas(
+    app: tauri::AppHandle,
     proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
 ) -> Result<RefreshStats, String> {
-    let stats = modules::account::refresh_all_quotas_logic().await?;
+    // 逐账号上报 scheduler://job_progress，前端据此显示刷新进度
+    let stats = modules::scheduler::refresh_all_quotas_tracked(&app).await?;
 
     // 同步到运行中的反代服务（如果已启动）
     let instance_lock = proxy_state.instance.read().await;
@@ -407,19 +505,114 @@ pub async fn load_config() -> Result<AppConfig, String> {
     modules::load_app_config()
 }
 
-/// 保存配置
+/// 校验配置（保存前调用），返回逐项的问题列表
+#[tauri::command]
+pub async fn validate_config(
+    config: serde_json::Value,
+) -> Result<modules::config_validation::ConfigValidation, String> {
+    Ok(modules::config_validation::validate_config(&config))
+}
+
+/// 导出完整配置（默认不包含密钥）
+#[tauri::command]
+pub async fn export_config(file_path: String, include_secrets: Option<bool>) -> Result<(), String> {
+    modules::config_transfer::export_config(&file_path, include_secrets.unwrap_or(false))
+}
+
+/// 导入配置；dry_run 为 true 时只返回将要发生的变更
 #[tauri::command]
-pub async fn save_config(
+pub async fn import_config(
     app: tauri::AppHandle,
-    proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
-    config: AppConfig,
-) -> Result<(), String> {
-    modules::save_app_config(&config)?;
+    file_path: String,
+    strategy: modules::config_transfer::ImportStrategy,
+    dry_run: Option<bool>,
+) -> Result<modules::config_transfer::ConfigImportResult, String> {
+    let (result, applied) =
+        modules::config_transfer::import_config(&file_path, strategy, dry_run.unwrap_or(true))?;
+    if let Some(config) = applied {
+        apply_runtime_config(&app, &config).await;
+    }
+    Ok(result)
+}
+
+/// 查询配置变更审计日志（最新的在前）
+#[tauri::command]
+pub async fn query_config_audit(
+    query: Option<modules::config_audit::ConfigAuditQuery>,
+) -> Result<Vec<modules::config_audit::ConfigAuditEntry>, String> {
+    modules::config_audit::query(&query.unwrap_or_default())
+}
+
+/// 列出自动备份的配置（最新的在前）
+#[tauri::command]
+pub async fn list_config_backups() -> Result<Vec<modules::config_backup::ConfigBackup>, String> {
+    modules::config_backup::list_backups()
+}
+
+/// 从备份恢复配置（恢复前当前配置同样会被备份）
+#[tauri::command]
+pub async fn restore_config_backup(app: tauri::AppHandle, file_name: String) -> Result<AppConfig, String> {
+    let config = modules::config_backup::restore_backup(&file_name)?;
+    apply_runtime_config(&app, &config).await;
+    Ok(config)
+}
+
+/// 与远程端点同步配置；resolve 用于在冲突时指定保留哪一方
+#[tauri::command]
+pub async fn sync_config(
+    app: tauri::AppHandle,
+    resolve: Option<modules::config_sync::SyncResolution>,
+) -> Result<modules::config_sync::ConfigSyncResult, String> {
+    let (result, pulled) = modules::config_sync::sync(resolve).await?;
+    if let Some(config) = pulled {
+        apply_runtime_config(&app, &config).await;
+    }
+    Ok(result)
+}
+
+/// 获取上次同步的状态
+#[tauri::command]
+pub async fn get_config_sync_state() -> Result<modules::config_sync::ConfigSyncState, String> {
+    Ok(modules::config_sync::load_state())
+}
+
+/// 保存配置
+#[tauri::command]
+pub async fn save_config(app: tauri::AppHandle, config: AppConfig) -> Result<(), String> {
+    // 无效的上游代理地址会让请求失败，保存前直接拒绝
+    if let Some(url) = config.proxy.upstream_proxy.proxy_url() {
+        crate::utils::http::validate_proxy_url(&url)?;
+    }
+    modules::save_app_config(&config, "save_config")?;
+    apply_runtime_config(&app, &config).await;
+    Ok(())
+}
+
+/// 将配置中可热更新的部分应用到运行中的服务（保存配置与配置文件热重载共用）
+pub(crate) async fn apply_runtime_config(app: &tauri::AppHandle, config: &AppConfig) {
+    crate::proxy::common::model_capabilities::set_overrides(
+        config.proxy.model_capabilities.clone(),
+    );
+    crate::proxy::common::model_mapping::set_rule_priority(config.proxy.mapping_priority.clone());
+    crate::proxy::mirror::set_config(config.proxy.mirror.clone());
+    crate::proxy::monitor::set_traffic_log_config(config.proxy.traffic_log.clone());
+    crate::proxy::response_cache::set_config(config.proxy.response_cache.clone());
+    crate::proxy::common::pricing::set_pricing(config.proxy.pricing.clone());
+    crate::proxy::concurrency::set_config(config.proxy.concurrency.clone());
+    crate::proxy::upstream::timeouts::set_config(config.proxy.upstream_timeouts.clone());
+    crate::proxy::diagnostics::set_enabled(config.proxy.debug_headers);
+    crate::proxy::token_manager::set_quota_protection_enabled(config.quota_protection.enabled);
+    crate::utils::http::set_global_proxy(&config.proxy.upstream_proxy);
+    modules::process::set_recognition_rules(config.process_recognition.clone());
+    if let Err(e) = modules::logger::set_log_level(&config.log_level) {
+        modules::logger::log_warn(&e);
+    }
 
     // 通知托盘配置已更新
     let _ = app.emit("config://updated", ());
 
     // 热更新正在运行的服务
+    let proxy_state = app.state::<crate::commands::proxy::ProxyServiceState>();
     let instance_lock = proxy_state.instance.read().await;
     if let Some(instance) = instance_lock.as_ref() {
         // 更新模型映射
@@ -440,8 +633,14 @@ pub async fn save_config(
             .await;
         tracing::debug!("已同步热更新反代服务配置");
     }
+}
 
-    Ok(())
+/// 按当前配置的存储后端重写所有账号的 refresh token，返回迁移的账号数
+#[tauri::command]
+pub async fn migrate_token_storage() -> Result<usize, String> {
+    tokio::task::spawn_blocking(modules::token_store::migrate_accounts)
+        .await
+        .map_err(|e| e.to_string())?
 }
 
 // --- OAuth 命令 ---
@@ -450,76 +649,140 @@ pub async fn save_config(
 pub async fn start_oauth_login(app_handle: tauri::AppHandle) -> Result<Account, String> {
     modules::logger::log_info("开始 OAuth 授权流程...");
 
-    // 1. 启动 OAuth 流程获取 Token
+    // 启动 OAuth 流程获取 Token
     let token_res = modules::oauth_server::start_oauth_flow(app_handle.clone()).await?;
+    save_oauth_account(&app_handle, token_res).await
+}
 
-    // 2. 检查 refresh_token
-    let refresh_token = token_res.refresh_token.ok_or_else(|| {
-        "未获取到 Refresh Token。\n\n\
-         可能原因:\n\
-         1. 您之前已授权过此应用,Google 不会再次返回 refresh_token\n\n\
-         解决方案:\n\
-         1. 访问 https://myaccount.google.com/permissions\n\
-         2. 撤销 'Antigravity Tools' 的访问权限\n\
-         3. 重新进行 OAuth 授权\n\n\
-         或者使用 'Refresh Token' 标签页手动添加账号"
-            .to_string()
-    })?;
-
-    // 3. 获取用户信息
-    let user_info = modules::oauth::get_user_info(&token_res.access_token).await?;
-    modules::logger::log_info(&format!("获取用户信息成功: {}", user_info.email));
-
-    // 4. 尝试获取项目ID
-    let project_id = crate::proxy::project_resolver::fetch_project_id(&token_res.access_token)
-        .await
-        .ok();
+/// 完成 OAuth 授权（不自动打开浏览器）
+#[tauri::command]
+pub async fn complete_oauth_login(app_handle: tauri::AppHandle) -> Result<Account, String> {
+    modules::logger::log_info("完成 OAuth 授权流程 (manual)...");
 
-    if let Some(ref pid) = project_id {
-        modules::logger::log_info(&format!("获取项目ID成功: {}", pid));
-    } else {
-        modules::logger::log_warn("未能获取项目ID,将在后续懒加载");
+    // 等待回调并交换 Token（不 open browser）
+    let token_res = modules::oauth_server::complete_oauth_flow(app_handle.clone()).await?;
+    save_oauth_account(&app_handle, token_res).await
+}
+
+/// 并行授权结果
+#[derive(serde::Serialize)]
+pub struct BatchOAuthResult {
+    pub accounts: Vec<Account>,
+    pub errors: Vec<String>,
+}
+
+/// 并行授权：同时打开多个授权会话 (以 state 区分，由同一个回调服务分发)，
+/// 批量重新授权大量账号时无需逐个等待。每个会话结束时发送 oauth-session-completed 事件
+#[tauri::command]
+pub async fn start_batch_oauth_login(
+    app_handle: tauri::AppHandle,
+    count: usize,
+) -> Result<BatchOAuthResult, String> {
+    let count = count.clamp(1, modules::oauth_server::MAX_OAUTH_SESSIONS);
+    modules::logger::log_info(&format!("开始并行 OAuth 授权流程, 会话数: {}", count));
+
+    let mut states = Vec::with_capacity(count);
+    for _ in 0..count {
+        match modules::oauth_server::start_oauth_session(&app_handle, None).await {
+            Ok(state) => states.push(state),
+            Err(e) if states.is_empty() => return Err(e),
+            Err(e) => {
+                modules::logger::log_warn(&format!("打开授权会话失败: {}", e));
+                break;
+            }
+        }
     }
 
-    // 5. 构造 TokenData
-    let token_data = TokenData::new(
-        token_res.access_token,
-        refresh_token,
-        token_res.expires_in,
-        Some(user_info.email.clone()),
-        project_id,
-        None,
-    );
-
-    // 6. 添加或更新到账号列表
-    modules::logger::log_info("正在保存账号信息...");
-    let mut account = modules::upsert_account(
-        user_info.email.clone(),
-        user_info.get_display_name(),
-        token_data,
-    )?;
-
-    // 7. 自动触发刷新额度
-    let _ = internal_refresh_account_quota(&app_handle, &mut account).await;
-
-    // 8. If proxy is running, reload token pool so changes take effect immediately.
-    let _ = crate::commands::proxy::reload_proxy_accounts(
-        app_handle.state::<crate::commands::proxy::ProxyServiceState>(),
-    )
+    let results = futures::future::join_all(states.into_iter().map(|state| {
+        let app_handle = app_handle.clone();
+        async move {
+            let result = match modules::oauth_server::wait_oauth_session(&state).await {
+                Ok(token_res) => save_oauth_account(&app_handle, token_res).await,
+                Err(e) => Err(e),
+            };
+            let _ = app_handle.emit(
+                "oauth-session-completed",
+                serde_json::json!({
+                    "state": state,
+                    "email": result.as_ref().ok().map(|a| a.email.clone()),
+                    "error": result.as_ref().err(),
+                }),
+            );
+            result
+        }
+    }))
     .await;
 
+    let mut batch = BatchOAuthResult {
+        accounts: Vec::new(),
+        errors: Vec::new(),
+    };
+    for result in results {
+        match result {
+            Ok(account) => batch.accounts.push(account),
+            Err(e) => batch.errors.push(e),
+        }
+    }
+    Ok(batch)
+}
+
+/// 重新授权指定账号：打开独立的授权会话并以账号邮箱预填 login_hint，
+/// 由托盘的"需要重新授权"菜单、重新授权链接或前端通知调用
+#[tauri::command]
+pub async fn reauth_account(
+    app_handle: tauri::AppHandle,
+    account_id: String,
+) -> Result<Account, String> {
+    let email = modules::load_account(&account_id)?.email;
+    modules::logger::log_info(&format!("开始重新授权账号: {}", email));
+
+    let state = modules::oauth_server::start_oauth_session(&app_handle, Some(&email)).await?;
+    let token_res = modules::oauth_server::wait_oauth_session(&state).await?;
+    // 登录了其他账号时不保存，避免误把另一个账号加进来而原账号仍处于失效状态
+    let account = save_oauth_account_for(&app_handle, token_res, Some(&email)).await?;
+    crate::modules::tray::update_tray_menus(&app_handle);
     Ok(account)
 }
 
-/// 完成 OAuth 授权（不自动打开浏览器）
+/// 设备码授权：无需本机浏览器回调，用户在任意设备上打开链接并输入代码，适用于无界面服务器与远程机器
 #[tauri::command]
-pub async fn complete_oauth_login(app_handle: tauri::AppHandle) -> Result<Account, String> {
-    modules::logger::log_info("完成 OAuth 授权流程 (manual)...");
+pub async fn start_device_oauth_login(app_handle: tauri::AppHandle) -> Result<Account, String> {
+    modules::logger::log_info("开始设备码授权流程...");
 
-    // 1. 等待回调并交换 Token（不 open browser）
-    let token_res = modules::oauth_server::complete_oauth_flow(app_handle.clone()).await?;
+    let device = modules::oauth::request_device_code().await?;
+    // 无界面运行时用户只能从日志中看到代码
+    modules::logger::log_info(&format!(
+        "请在浏览器中打开 {} 并输入代码: {}",
+        device.verification_url, device.user_code
+    ));
+    let _ = app_handle.emit(
+        "oauth-device-code",
+        serde_json::json!({
+            "user_code": device.user_code,
+            "verification_url": device.verification_url,
+            "expires_in": device.expires_in,
+        }),
+    );
+
+    let token_res = modules::oauth::poll_device_token(&device).await?;
+    save_oauth_account(&app_handle, token_res).await
+}
+
+/// 用 OAuth 获得的 Token 创建或更新账号
+async fn save_oauth_account(
+    app_handle: &tauri::AppHandle,
+    token_res: modules::oauth::TokenResponse,
+) -> Result<Account, String> {
+    save_oauth_account_for(app_handle, token_res, None).await
+}
 
-    // 2. 检查 refresh_token
+/// 同上；`expected_email` 不为空时 (重新授权) 要求登录的正是该账号
+async fn save_oauth_account_for(
+    app_handle: &tauri::AppHandle,
+    token_res: modules::oauth::TokenResponse,
+    expected_email: Option<&str>,
+) -> Result<Account, String> {
+    // 1. 检查 refresh_token
     let refresh_token = token_res.refresh_token.ok_or_else(|| {
         "未获取到 Refresh Token。\n\n\
          可能原因:\n\
@@ -532,12 +795,20 @@ pub async fn complete_oauth_login(app_handle: tauri::AppHandle) -> Result<Accoun
             .to_string()
     })?;
 
-    // 3. 获取用户信息
+    // 2. 获取用户信息
     let user_info = modules::oauth::get_user_info(&token_res.access_token).await?;
     modules::logger::log_info(&format!("获取用户信息成功: {}", user_info.email));
+    if let Some(expected) = expected_email {
+        if !user_info.email.eq_ignore_ascii_case(expected) {
+            return Err(format!(
+                "重新授权时登录了其他账号: {} (需要 {})，请使用原账号登录",
+                user_info.email, expected
+            ));
+        }
+    }
 
-    // 4. 尝试获取项目ID
-    let project_id = crate::proxy::project_resolver::fetch_project_id(&token_res.access_token)
+    // 3. 尝试获取项目ID
+    let project_id = crate::proxy::project_resolver::fetch_project_id(&token_res.access_token, None)
         .await
         .ok();
 
@@ -547,7 +818,7 @@ pub async fn complete_oauth_login(app_handle: tauri::AppHandle) -> Result<Accoun
         modules::logger::log_warn("未能获取项目ID,将在后续懒加载");
     }
 
-    // 5. 构造 TokenData
+    // 4. 构造 TokenData
     let token_data = TokenData::new(
         token_res.access_token,
         refresh_token,
@@ -557,7 +828,7 @@ pub async fn complete_oauth_login(app_handle: tauri::AppHandle) -> Result<Accoun
         None,
     );
 
-    // 6. 添加或更新到账号列表
+    // 5. 添加或更新到账号列表
     modules::logger::log_info("正在保存账号信息...");
     let mut account = modules::upsert_account(
         user_info.email.clone(),
@@ -565,10 +836,10 @@ pub async fn complete_oauth_login(app_handle: tauri::AppHandle) -> Result<Accoun
         token_data,
     )?;
 
-    // 7. 自动触发刷新额度
-    let _ = internal_refresh_account_quota(&app_handle, &mut account).await;
+    // 6. 自动触发刷新额度
+    let _ = internal_refresh_account_quota(app_handle, &mut account).await;
 
-    // 8. If proxy is running, reload token pool so changes take effect immediately.
+    // 7. If proxy is running, reload token pool so changes take effect immediately.
     let _ = crate::commands::proxy::reload_proxy_accounts(
         app_handle.state::<crate::commands::proxy::ProxyServiceState>(),
     )
@@ -577,6 +848,20 @@ pub async fn complete_oauth_login(app_handle: tauri::AppHandle) -> Result<Accoun
     Ok(account)
 }
 
+/// 手动粘贴授权结果：本地回调被防火墙拦截时，用户复制授权链接在浏览器中完成授权，
+/// 再粘贴回跳转后的 URL 或授权码。已有登录调用在等待回调时交给它完成并返回 None
+#[tauri::command]
+pub async fn submit_oauth_code(
+    app_handle: tauri::AppHandle,
+    input: String,
+) -> Result<Option<Account>, String> {
+    modules::logger::log_info("收到手动粘贴的 OAuth 授权结果...");
+    match modules::oauth_server::submit_pasted_code(&input).await? {
+        Some(token_res) => save_oauth_account(&app_handle, token_res).await.map(Some),
+        None => Ok(None),
+    }
+}
+
 /// 预生成 OAuth 授权链接 (不打开浏览器)
 #[tauri::command]
 pub async fn prepare_oauth_url(app_handle: tauri::AppHandle) -> Result<String, String> {
@@ -586,6 +871,7 @@ pub async fn prepare_oauth_url(app_handle: tauri::AppHandle) -> Result<String, S
 #[tauri::command]
 pub async fn cancel_oauth_login() -> Result<(), String> {
     modules::oauth_server::cancel_oauth_flow();
+    modules::oauth::cancel_device_flow();
     Ok(())
 }
 
@@ -621,6 +907,37 @@ pub async fn import_from_db(app: tauri::AppHandle) -> Result<Account, String> {
     Ok(account)
 }
 
+/// 从已有 Antigravity 安装的 user-data-dir 导入当前登录的账号（需用户确认），
+/// 若该目录属于某个实例则同时将账号绑定到该实例
+#[tauri::command]
+pub async fn import_from_user_data_dir(
+    app: tauri::AppHandle,
+    user_data_dir: String,
+    consent: bool,
+) -> Result<Account, String> {
+    let dir = std::path::PathBuf::from(user_data_dir);
+    let mut account = modules::migration::import_from_user_data_dir(&dir, consent).await?;
+
+    if let Some(instance) = modules::instance::list_instances()?
+        .into_iter()
+        .find(|i| i.user_data_dir == dir)
+    {
+        modules::instance::bind_account_to_instance(&account.id, &instance.id)?;
+        modules::logger::log_info(&format!(
+            "已将导入的账号 {} 绑定到实例 {}",
+            account.email, instance.name
+        ));
+    }
+
+    // 自动触发刷新额度
+    let _ = internal_refresh_account_quota(&app, &mut account).await;
+
+    // 刷新托盘图标展示
+    crate::modules::tray::update_tray_menus(&app);
+
+    Ok(account)
+}
+
 #[tauri::command]
 #[allow(dead_code)]
 pub async fn import_custom_db(app: tauri::AppHandle, path: String) -> Result<Account, String> {
@@ -779,6 +1096,9 @@ pub async fn check_for_updates() -> Result<UpdateInfo, String> {
 
 #[tauri::command]
 pub async fn should_check_updates() -> Result<bool, String> {
+    if modules::scheduler::job_paused("update_check") {
+        return Ok(false);
+    }
     let settings = crate::modules::update_checker::load_update_settings()?;
     Ok(crate::modules::update_checker::should_check_for_updates(
         &settings,
@@ -903,11 +1223,103 @@ pub async fn save_http_api_settings(
     crate::modules::http_api::save_settings(&settings)
 }
 
+/// 获取所有账号（或指定标签分组）× 模型系列的配额对比矩阵，含消耗速度、预计耗尽与冷却状态（一次返回，供对比表格使用）
+#[tauri::command]
+pub async fn get_quota_matrix(
+    proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
+    tag: Option<String>,
+) -> Result<crate::modules::quota_matrix::QuotaMatrix, String> {
+    let cooldowns = match proxy_state.instance.read().await.as_ref() {
+        Some(instance) => instance.token_manager.cooldown_snapshot(),
+        None => Vec::new(),
+    };
+    crate::modules::quota_matrix::build_quota_matrix(tag.as_deref(), &cooldowns)
+}
+
+/// 查询配额历史（按账号 × 模型的时间序列，可按时间桶降采样）
+#[tauri::command]
+pub async fn get_quota_history(
+    query: Option<crate::modules::quota_history::QuotaHistoryQuery>,
+) -> Result<crate::modules::quota_history::QuotaHistory, String> {
+    crate::modules::quota_history::query_history(&query.unwrap_or_default())
+}
+
+/// 导出当前配额及指定时间段内的配额历史 (CSV / JSON)
+#[tauri::command]
+pub async fn export_quota_history(
+    file_path: String,
+    format: crate::modules::quota_history::QuotaExportFormat,
+    query: Option<crate::modules::quota_history::QuotaHistoryQuery>,
+) -> Result<usize, String> {
+    crate::modules::quota_history::export_quota(&file_path, format, &query.unwrap_or_default())
+}
+
+/// 按近期消耗速度预测各账号/模型的配额耗尽时间
+#[tauri::command]
+pub async fn get_quota_forecast(
+    account_id: Option<String>,
+) -> Result<Vec<crate::modules::quota_history::QuotaForecast>, String> {
+    crate::modules::quota_history::forecast(account_id.as_deref())
+}
+
+/// 设置/清除账号的手动 Token 预算 (按天/周/月，超出后反代不再选用该账号)
+#[tauri::command]
+pub async fn set_account_token_budget(
+    account_id: String,
+    budget: Option<crate::models::TokenBudget>,
+) -> Result<Account, String> {
+    crate::modules::token_budget::set_budget(&account_id, budget)
+}
+
+/// 获取所有设置了 Token 预算的账号的用量
+#[tauri::command]
+pub async fn get_token_budget_status(
+) -> Result<Vec<crate::modules::token_budget::TokenBudgetStatus>, String> {
+    Ok(crate::modules::token_budget::list_status())
+}
+
+/// 获取当前未解除的配额阈值告警
+#[tauri::command]
+pub async fn get_quota_alerts() -> Result<Vec<crate::modules::quota_alerts::QuotaAlert>, String> {
+    Ok(crate::modules::quota_alerts::active_alerts())
+}
+
+// ============================================================================
+// Quota Incident Journal Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn get_quota_incidents(
+    limit: Option<usize>,
+    offset: Option<usize>,
+    account_id: Option<String>,
+) -> Result<Vec<crate::modules::quota_journal::QuotaIncident>, String> {
+    crate::modules::quota_journal::list_incidents(
+        limit.unwrap_or(100),
+        offset.unwrap_or(0),
+        account_id.as_deref(),
+    )
+}
+
+/// 为配额事件添加备注（空字符串清除备注）
+#[tauri::command]
+pub async fn annotate_quota_incident(id: i64, note: String) -> Result<(), String> {
+    crate::modules::quota_journal::annotate_incident(id, &note)
+}
+
+/// 导出全部配额事件到 JSON 文件
+#[tauri::command]
+pub async fn export_quota_incidents(file_path: String) -> Result<usize, String> {
+    crate::modules::quota_journal::export_incidents(&file_path)
+}
+
 // ============================================================================
 // Token Statistics Commands
 // ============================================================================
 
-pub use crate::modules::token_stats::{AccountTokenStats, TokenStatsAggregated, TokenStatsSummary};
+pub use crate::modules::token_stats::{
+    AccountTokenStats, TokenStatsAggregated, TokenStatsSummary, TokenUsageRecord,
+};
 
 #[tauri::command]
 pub async fn get_token_stats_hourly(hours: i64) -> Result<Vec<TokenStatsAggregated>, String> {
@@ -929,11 +1341,28 @@ pub async fn get_token_stats_by_account(hours: i64) -> Result<Vec<AccountTokenSt
     crate::modules::token_stats::get_account_stats(hours)
 }
 
+/// 按客户端 API Key 统计用量
+#[tauri::command]
+pub async fn get_token_stats_by_api_key(
+    hours: i64,
+) -> Result<Vec<crate::modules::token_stats::ApiKeyTokenStats>, String> {
+    crate::modules::token_stats::get_api_key_stats(hours)
+}
+
 #[tauri::command]
 pub async fn get_token_stats_summary(hours: i64) -> Result<TokenStatsSummary, String> {
     crate::modules::token_stats::get_summary_stats(hours)
 }
 
+/// 最近的逐请求用量记录
+#[tauri::command]
+pub async fn get_token_usage_records(
+    hours: i64,
+    limit: Option<usize>,
+) -> Result<Vec<TokenUsageRecord>, String> {
+    crate::modules::token_stats::get_usage_records(hours, limit.unwrap_or(200))
+}
+
 #[tauri::command]
 pub async fn get_token_stats_by_model(
     hours: i64,
@@ -996,10 +1425,22 @@ pub async fn get_instance(instance_id: String) -> Result<Instance, String> {
     modules::instance::load_instance(&instance_id)
 }
 
-/// 删除实例
+/// 删除实例（运行中的实例需 options.force）
+/// options.purge_data 需配合 preview_instance_purge 返回的确认令牌
 #[tauri::command]
-pub async fn delete_instance(instance_id: String) -> Result<(), String> {
-    modules::instance::delete_instance(&instance_id)
+pub async fn delete_instance(
+    instance_id: String,
+    options: Option<crate::models::DeleteInstanceOptions>,
+) -> Result<(), String> {
+    modules::instance::delete_instance_with_options(&instance_id, &options.unwrap_or_default())
+}
+
+/// 预览删除实例数据目录（大小 + 确认令牌）
+#[tauri::command]
+pub async fn preview_instance_purge(
+    instance_id: String,
+) -> Result<crate::models::InstancePurgePreview, String> {
+    modules::instance::preview_instance_purge(&instance_id)
 }
 
 /// 更新实例
@@ -1008,6 +1449,54 @@ pub async fn update_instance(instance: Instance) -> Result<(), String> {
     modules::instance::update_instance(&instance)
 }
 
+/// 列出所有显示器（用于设置实例窗口位置）
+#[tauri::command]
+pub async fn list_monitors(app: tauri::AppHandle) -> Result<Vec<crate::models::MonitorInfo>, String> {
+    let primary = app
+        .primary_monitor()
+        .map_err(|e| e.to_string())?
+        .map(|m| (m.name().cloned(), *m.position()));
+    let monitors = app.available_monitors().map_err(|e| e.to_string())?;
+
+    Ok(monitors
+        .iter()
+        .map(|m| crate::models::MonitorInfo {
+            name: m.name().cloned(),
+            x: m.position().x,
+            y: m.position().y,
+            width: m.size().width,
+            height: m.size().height,
+            scale_factor: m.scale_factor(),
+            is_primary: primary
+                .as_ref()
+                .is_some_and(|(name, pos)| name.as_ref() == m.name() && pos == m.position()),
+        })
+        .collect())
+}
+
+/// 校验实例的额外启动参数（保存前由编辑器调用）
+/// instance_id 用于读取已有实例的 user_data_dir / is_default；新建实例时传 user_data_dir
+#[tauri::command]
+pub async fn validate_instance_args(
+    extra_args: Vec<String>,
+    instance_id: Option<String>,
+    user_data_dir: Option<String>,
+) -> Result<Vec<crate::models::LaunchArgWarning>, String> {
+    let (dir, is_default) = match instance_id {
+        Some(id) => {
+            let instance = modules::instance::load_instance(&id)?;
+            (Some(instance.user_data_dir), instance.is_default)
+        }
+        None => (user_data_dir.map(std::path::PathBuf::from), false),
+    };
+
+    Ok(modules::instance::validate_extra_args(
+        &extra_args,
+        dir.as_deref(),
+        is_default,
+    ))
+}
+
 /// 绑定账号到实例
 #[tauri::command]
 pub async fn bind_account_to_instance(
@@ -1030,7 +1519,46 @@ pub async fn unbind_account_from_instance(
 #[tauri::command]
 pub async fn start_instance(instance_id: String) -> Result<(), String> {
     let instance = modules::instance::load_instance(&instance_id)?;
+    launch_instance(&instance)
+}
+
+/// 先写入后启动：先将账号凭据与设备指纹写入实例的 user-data-dir 并回读校验，
+/// 校验通过后才启动进程，避免 Antigravity 启动时读到旧凭据。
+/// `account_id` 为空时使用实例当前绑定的账号；`start` 为 false 时只准备不启动。
+#[tauri::command]
+pub async fn prepare_and_start_instance(
+    instance_id: String,
+    account_id: Option<String>,
+    start: Option<bool>,
+) -> Result<modules::account::LaunchPreparation, String> {
+    let mut instance = modules::instance::load_instance(&instance_id)?;
+    let account_id = account_id
+        .or_else(|| instance.current_account_id.clone())
+        .ok_or("实例未绑定账号")?;
+
+    if instance.current_account_id.as_deref() != Some(account_id.as_str()) {
+        instance.current_account_id = Some(account_id.clone());
+        modules::instance::save_instance(&instance)?;
+    }
 
+    let mut report = modules::account::prepare_instance_launch(&account_id, &instance).await?;
+    if start.unwrap_or(true) {
+        launch_instance(&instance)?;
+        report.started = true;
+    }
+    Ok(report)
+}
+
+/// 校验并刷新所有实例的启动参数与可执行文件路径（Antigravity 在外部自动更新后使用）
+#[tauri::command]
+pub async fn revalidate_instance_launch_settings(
+) -> Result<modules::launch_refresh::LaunchRefreshReport, String> {
+    tokio::task::spawn_blocking(modules::launch_refresh::revalidate_launch_settings)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn launch_instance(instance: &Instance) -> Result<(), String> {
     // 如果有保存的启动参数，使用它们；否则使用默认参数
     if let Some(ref saved_args) = instance.last_launch_args {
         // [Fix] 检查参数是否有效（不包含 --type=）
@@ -1040,7 +1568,7 @@ pub async fn start_instance(instance_id: String) -> Result<(), String> {
                 "Starting instance {} with saved args: {:?}",
                 instance.name, saved_args
             ));
-            return modules::process::start_instance_with_args(&instance, saved_args.clone());
+            return modules::process::start_instance_with_args(instance, saved_args.clone());
         } else if args_str.contains("--type=") {
             modules::logger::log_warn(&format!(
                 "Instance {} has invalid saved args (contains --type=), using default args",
@@ -1049,7 +1577,7 @@ pub async fn start_instance(instance_id: String) -> Result<(), String> {
         }
     }
 
-    modules::process::start_instance(&instance)
+    modules::process::start_instance(instance)
 }
 
 /// 停止指定实例
@@ -1251,8 +1779,200 @@ pub async fn switch_account_in_instance(
     Ok(was_running)
 }
 
+/// 扫描未登记的 Antigravity 配置目录（运行中进程 + 常见位置）
+#[tauri::command]
+pub async fn discover_instance_profiles() -> Result<Vec<crate::models::DiscoveredProfile>, String> {
+    modules::instance::discover_profiles()
+}
+
+/// 导入发现的配置目录为实例
+#[derive(serde::Serialize)]
+pub struct ProfileImportResult {
+    pub imported: Vec<Instance>,
+    pub errors: Vec<String>,
+}
+
+#[tauri::command]
+pub async fn import_discovered_profiles(
+    profiles: Vec<crate::models::ProfileImportRequest>,
+) -> Result<ProfileImportResult, String> {
+    let (imported, errors) = modules::instance::import_discovered_profiles(profiles)?;
+    Ok(ProfileImportResult { imported, errors })
+}
+
 /// 获取所有运行中的实例
 #[tauri::command]
 pub async fn get_running_instances() -> Result<Vec<Instance>, String> {
     modules::instance::get_running_instances()
 }
+
+/// 获取实例的 Antigravity 进程树（主进程、各类辅助进程及内存占用），用于排查运行状态检测
+#[tauri::command]
+pub async fn get_instance_process_tree(
+    instance_id: String,
+) -> Result<modules::process::InstanceProcessTree, String> {
+    let instance = modules::instance::load_instance(&instance_id)?;
+    Ok(modules::process::get_instance_process_tree(
+        &instance.user_data_dir,
+        instance.is_default,
+    ))
+}
+
+/// 查找孤儿 Antigravity 辅助进程（主进程崩溃后残留）
+#[tauri::command]
+pub async fn find_orphaned_helpers() -> Result<Vec<modules::process::OrphanedHelper>, String> {
+    tokio::task::spawn_blocking(modules::process::find_orphaned_helpers)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 终止孤儿辅助进程（pids 为空时清理全部）
+#[tauri::command]
+pub async fn cleanup_orphaned_helpers(
+    pids: Option<Vec<u32>>,
+) -> Result<modules::process::OrphanCleanupReport, String> {
+    tokio::task::spawn_blocking(move || {
+        modules::process::cleanup_orphaned_helpers(pids.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 获取实例输出日志（stdout/stderr）的最后 N 行，默认 200 行
+#[tauri::command]
+pub async fn tail_instance_log(
+    instance_id: String,
+    lines: Option<usize>,
+) -> Result<Vec<String>, String> {
+    modules::instance_log::tail(&instance_id, lines.unwrap_or(200))
+}
+
+/// 紧急停止：取消定时任务、关闭反代服务并停止所有运行中的实例
+#[tauri::command]
+pub async fn halt_everything(
+    app: tauri::AppHandle,
+) -> Result<modules::kill_switch::HaltReport, String> {
+    modules::kill_switch::halt_all(&app).await
+}
+
+/// 恢复被紧急停止的定时任务
+#[tauri::command]
+pub async fn resume_scheduled_jobs() -> Result<(), String> {
+    modules::scheduler::resume_jobs();
+    Ok(())
+}
+
+/// 新增或更新 cron 定时任务（id 为空时生成新 id），保存后调度器下一轮即按新表达式执行
+#[tauri::command]
+pub async fn save_cron_job(mut job: crate::models::CronJob) -> Result<crate::models::CronJob, String> {
+    modules::cron::validate(&job.expression, &job.timezone)?;
+    if matches!(
+        job.kind,
+        crate::models::CronJobKind::InstanceStart | crate::models::CronJobKind::InstanceStop
+    ) {
+        let instance_id = job.instance_id.as_deref().ok_or("实例启停任务必须指定实例")?;
+        modules::instance::load_instance(instance_id)?;
+    }
+    if job.id.is_empty() {
+        job.id = Uuid::new_v4().to_string();
+    }
+
+    let mut config = modules::load_app_config()?;
+    match config.cron_jobs.iter_mut().find(|j| j.id == job.id) {
+        Some(existing) => *existing = job.clone(),
+        None => config.cron_jobs.push(job.clone()),
+    }
+    modules::save_app_config(&config, "save_cron_job")?;
+    Ok(job)
+}
+
+/// 删除 cron 定时任务
+#[tauri::command]
+pub async fn delete_cron_job(id: String) -> Result<(), String> {
+    let mut config = modules::load_app_config()?;
+    let before = config.cron_jobs.len();
+    config.cron_jobs.retain(|j| j.id != id);
+    if config.cron_jobs.len() == before {
+        return Err(format!("Cron job not found: {}", id));
+    }
+    modules::save_app_config(&config, "delete_cron_job")
+}
+
+/// 预览 cron 表达式接下来的执行时间 (Unix 秒)
+#[tauri::command]
+pub async fn preview_cron_schedule(
+    expression: String,
+    timezone: Option<String>,
+    count: Option<usize>,
+) -> Result<Vec<i64>, String> {
+    let runs = modules::cron::upcoming(
+        &expression,
+        timezone.as_deref().unwrap_or("local"),
+        chrono::Utc::now(),
+        count.unwrap_or(5).min(50),
+    )?;
+    Ok(runs.iter().map(|t| t.timestamp()).collect())
+}
+
+/// 列出定时任务及其暂停状态
+#[tauri::command]
+pub async fn list_scheduled_jobs() -> Result<Vec<modules::scheduler::ScheduledJobState>, String> {
+    modules::scheduler::list_job_states()
+}
+
+/// 暂停或恢复单个定时任务（内置任务名或 cron 任务 id）
+#[tauri::command]
+pub async fn set_scheduled_job_paused(
+    app: tauri::AppHandle,
+    job_id: String,
+    paused: bool,
+) -> Result<(), String> {
+    modules::scheduler::set_job_paused(&job_id, paused)?;
+    // 刷新托盘中的勾选状态
+    let _ = app.emit("config://updated", ());
+    let _ = app.emit("scheduler://jobs_changed", ());
+    Ok(())
+}
+
+/// 新建一次性延时任务：`run_at` 为执行时间 (Unix 秒)，或用 `delay_secs` 指定延迟
+#[tauri::command]
+pub async fn schedule_delayed_task(
+    action: modules::delayed_tasks::DelayedAction,
+    run_at: Option<i64>,
+    delay_secs: Option<i64>,
+    label: Option<String>,
+) -> Result<modules::delayed_tasks::DelayedTask, String> {
+    modules::delayed_tasks::schedule(action, run_at, delay_secs, label)
+}
+
+/// 列出待执行的延时任务
+#[tauri::command]
+pub async fn list_delayed_tasks() -> Result<Vec<modules::delayed_tasks::DelayedTask>, String> {
+    Ok(modules::delayed_tasks::list())
+}
+
+/// 取消延时任务
+#[tauri::command]
+pub async fn cancel_delayed_task(id: String) -> Result<(), String> {
+    modules::delayed_tasks::cancel(&id)
+}
+
+/// 查询定时任务执行记录（按开始时间倒序）
+#[tauri::command]
+pub async fn query_job_history(
+    query: Option<modules::job_history::JobHistoryQuery>,
+) -> Result<Vec<modules::job_history::JobRun>, String> {
+    modules::job_history::query(&query.unwrap_or_default())
+}
+
+/// 清除实例的崩溃循环故障标记，允许再次启动
+#[tauri::command]
+pub async fn reset_instance_fault(instance_id: String) -> Result<(), String> {
+    modules::crash_loop::reset_fault(&instance_id)
+}
+
+/// 获取 Antigravity 主进程快照（前端可配合 "process://changed" 事件使用）
+#[tauri::command]
+pub async fn get_process_snapshot() -> Result<modules::process::ProcessSnapshot, String> {
+    Ok(modules::process::get_process_snapshot())
+}
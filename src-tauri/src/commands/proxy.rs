@@ -14,6 +14,22 @@ pub struct ProxyStatus {
     pub port: u16,
     pub base_url: String,
     pub active_accounts: usize,
+    /// 所有监听地址 (host:port)
+    #[serde(default)]
+    pub listen_addresses: Vec<String>,
+}
+
+impl ProxyStatus {
+    fn running(config: &ProxyConfig, active_accounts: usize) -> Self {
+        let addrs = config.listen_addresses().unwrap_or_default();
+        Self {
+            running: true,
+            port: config.port,
+            base_url: config.local_base_url(),
+            active_accounts,
+            listen_addresses: addrs.iter().map(|a| a.to_string()).collect(),
+        }
+    }
 }
 
 /// 反代服务全局状态
@@ -77,10 +93,29 @@ pub async fn start_proxy_service(
     token_manager.start_auto_cleanup(); // 启动限流记录自动清理后台任务
     // 同步 UI 传递的调度配置
     token_manager.update_sticky_config(config.scheduling.clone()).await;
+    crate::proxy::common::model_capabilities::set_overrides(config.model_capabilities.clone());
+    crate::proxy::common::model_mapping::set_rule_priority(config.mapping_priority.clone());
+    crate::proxy::mirror::set_config(config.mirror.clone());
+    crate::proxy::monitor::set_traffic_log_config(config.traffic_log.clone());
+    crate::proxy::response_cache::set_config(config.response_cache.clone());
+    crate::proxy::common::pricing::set_pricing(config.pricing.clone());
+    crate::proxy::concurrency::set_config(config.concurrency.clone());
+    crate::proxy::upstream::timeouts::set_config(config.upstream_timeouts.clone());
+    crate::proxy::diagnostics::set_enabled(config.debug_headers);
+    // 配额保护开关在 ProxyConfig 之外，启动时同步一次
+    if let Ok(app_config) = crate::modules::config::load_app_config() {
+        crate::proxy::token_manager::set_quota_protection_enabled(app_config.quota_protection.enabled);
+    }
     
     // 3. 加载账号
     let active_accounts = token_manager.load_accounts().await
         .map_err(|e| format!("加载账号失败: {}", e))?;
+
+    // 恢复上次停止/崩溃前的粘性会话、冷却与签名缓存
+    if let Err(e) = crate::proxy::warm_state::restore(&token_manager) {
+        tracing::warn!("恢复反代状态失败: {}", e);
+    }
+    crate::proxy::warm_state::spawn_autosave(&token_manager);
     
     if active_accounts == 0 {
         let zai_enabled = config.zai.enabled
@@ -91,10 +126,11 @@ pub async fn start_proxy_service(
     }
     
     // 启动 Axum 服务器
+    let listen_addrs = config.listen_addresses()?;
+    let tls = crate::proxy::tls::build_acceptor(&config.tls, &listen_addrs)?;
     let (axum_server, server_handle) =
         match crate::proxy::AxumServer::start(
-            config.get_bind_address().to_string(),
-            config.port,
+            listen_addrs,
             token_manager.clone(),
             config.custom_mapping.clone(),
             config.request_timeout,
@@ -103,7 +139,8 @@ pub async fn start_proxy_service(
             config.zai.clone(),
             monitor.clone(),
             config.experimental.clone(),
-
+            config.cors.clone(),
+            tls,
         ).await {
             Ok((server, handle)) => (server, handle),
             Err(e) => return Err(format!("启动 Axum 服务器失败: {}", e)),
@@ -123,14 +160,9 @@ pub async fn start_proxy_service(
     // 保存配置到全局 AppConfig
     let mut app_config = crate::modules::config::load_app_config().map_err(|e| e)?;
     app_config.proxy = config.clone();
-    crate::modules::config::save_app_config(&app_config).map_err(|e| e)?;
+    crate::modules::config::save_app_config(&app_config, "start_proxy_service").map_err(|e| e)?;
     
-    Ok(ProxyStatus {
-        running: true,
-        port: config.port,
-        base_url: format!("http://127.0.0.1:{}", config.port),
-        active_accounts,
-    })
+    Ok(ProxyStatus::running(&config, active_accounts))
 }
 
 /// 停止反代服务
@@ -149,6 +181,10 @@ pub async fn stop_proxy_service(
         instance.axum_server.stop();
         // 等待服务器任务完成
         instance.server_handle.await.ok();
+        // 保存状态，供下次启动时热恢复
+        if let Err(e) = crate::proxy::warm_state::save(&instance.token_manager) {
+            tracing::warn!("保存反代状态失败: {}", e);
+        }
     }
     
     Ok(())
@@ -162,21 +198,70 @@ pub async fn get_proxy_status(
     let instance_lock = state.instance.read().await;
     
     match instance_lock.as_ref() {
-        Some(instance) => Ok(ProxyStatus {
-            running: true,
-            port: instance.config.port,
-            base_url: format!("http://127.0.0.1:{}", instance.config.port),
-            active_accounts: instance.token_manager.len(),
-        }),
+        Some(instance) => Ok(ProxyStatus::running(
+            &instance.config,
+            instance.token_manager.len(),
+        )),
         None => Ok(ProxyStatus {
             running: false,
             port: 0,
             base_url: String::new(),
             active_accounts: 0,
+            listen_addresses: Vec::new(),
         }),
     }
 }
 
+/// 按已保存的配置重新绑定监听地址 (端口 / 监听地址 / 局域网访问)，不重启反代服务，
+/// 已建立的连接与账号状态不受影响
+#[tauri::command]
+pub async fn restart_proxy_listeners(
+    state: State<'_, ProxyServiceState>,
+) -> Result<ProxyStatus, String> {
+    let app_config = crate::modules::config::load_app_config()?;
+    rebind_proxy_listeners(&state, &app_config.proxy).await
+}
+
+/// 按给定配置重新绑定监听地址；自签名证书随新的监听地址重新生成
+pub async fn rebind_proxy_listeners(
+    state: &ProxyServiceState,
+    config: &ProxyConfig,
+) -> Result<ProxyStatus, String> {
+    let listen_addrs = config.listen_addresses()?;
+    let tls = crate::proxy::tls::build_acceptor(&config.tls, &listen_addrs)?;
+
+    let mut instance_lock = state.instance.write().await;
+    let instance = instance_lock.as_mut().ok_or("服务未运行")?;
+    instance.axum_server.rebind(listen_addrs, tls).await?;
+
+    instance.config.port = config.port;
+    instance.config.bind_address = config.bind_address.clone();
+    instance.config.extra_listeners = config.extra_listeners.clone();
+    instance.config.allow_lan_access = config.allow_lan_access;
+    instance.config.tls = config.tls.clone();
+    // 监听范围变化会影响 auto 认证模式
+    instance.axum_server.update_security(&instance.config).await;
+
+    Ok(ProxyStatus::running(
+        &instance.config,
+        instance.token_manager.len(),
+    ))
+}
+
+/// 使用本地 CA 签发一张反代客户端证书 (双向 TLS)，返回证书、私钥与 CA 证书的 PEM
+#[tauri::command]
+pub async fn issue_proxy_client_certificate(
+    name: String,
+    valid_days: Option<u32>,
+) -> Result<crate::proxy::tls::ClientCertificateBundle, String> {
+    let valid_days = valid_days.unwrap_or(crate::proxy::tls::DEFAULT_CLIENT_CERT_DAYS);
+    tokio::task::spawn_blocking(move || {
+        crate::proxy::tls::issue_client_certificate(&name, valid_days)
+    })
+    .await
+    .map_err(|e| format!("签发客户端证书失败: {}", e))?
+}
+
 /// 获取反代服务统计
 #[tauri::command]
 pub async fn get_proxy_stats(
@@ -234,6 +319,24 @@ pub async fn clear_proxy_logs(
     Ok(())
 }
 
+/// 按条件查询反代请求日志 (模型 / 账号 / 协议 / 状态码 / 时间范围)
+#[tauri::command]
+pub async fn query_proxy_logs(
+    query: crate::modules::proxy_db::ProxyLogQuery,
+) -> Result<Vec<ProxyRequestLog>, String> {
+    tokio::task::spawn_blocking(move || crate::modules::proxy_db::query_logs(&query))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// 删除早于 before (毫秒时间戳) 的请求日志，before 为空时删除全部，返回删除条数
+#[tauri::command]
+pub async fn purge_proxy_logs(before: Option<i64>) -> Result<usize, String> {
+    tokio::task::spawn_blocking(move || crate::modules::proxy_db::purge_logs(before))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
 /// 获取反代请求日志 (分页)
 #[tauri::command]
 pub async fn get_proxy_logs_paginated(
@@ -324,6 +427,117 @@ pub fn generate_api_key() -> String {
     format!("sk-{}", uuid::Uuid::new_v4().simple())
 }
 
+/// 客户端 API Key 可授权的协议
+const API_KEY_SCOPES: [&str; 4] = ["openai", "anthropic", "gemini", "mcp"];
+
+/// 保存客户端 API Key 列表，并热更新运行中服务的认证配置
+async fn save_api_keys(
+    state: &State<'_, ProxyServiceState>,
+    update: impl FnOnce(&mut Vec<crate::proxy::config::ProxyApiKey>) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut app_config = crate::modules::config::load_app_config()?;
+    update(&mut app_config.proxy.api_keys)?;
+    crate::modules::config::save_app_config(&app_config, "proxy_api_keys")?;
+
+    let instance_lock = state.instance.read().await;
+    if let Some(instance) = instance_lock.as_ref() {
+        instance.axum_server.update_security(&app_config.proxy).await;
+    }
+    Ok(())
+}
+
+/// 列出客户端 API Key (含已吊销)
+#[tauri::command]
+pub async fn list_proxy_api_keys() -> Result<Vec<crate::proxy::config::ProxyApiKey>, String> {
+    Ok(crate::modules::config::load_app_config()?.proxy.api_keys)
+}
+
+/// 新建的客户端 API Key，明文 `key` 只在此时返回一次
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CreatedProxyApiKey {
+    #[serde(flatten)]
+    pub api_key: crate::proxy::config::ProxyApiKey,
+    pub key: String,
+}
+
+/// 创建客户端 API Key，scopes 为空表示允许所有协议；限流额度为空时使用默认值
+#[tauri::command]
+pub async fn create_proxy_api_key(
+    state: State<'_, ProxyServiceState>,
+    name: String,
+    scopes: Vec<String>,
+    requests_per_minute: Option<u32>,
+    tokens_per_minute: Option<u32>,
+) -> Result<CreatedProxyApiKey, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("API Key 名称不能为空".to_string());
+    }
+    if let Some(scope) = scopes.iter().find(|s| !API_KEY_SCOPES.contains(&s.as_str())) {
+        return Err(format!("未知的 scope: {}", scope));
+    }
+
+    let secret = generate_api_key();
+    let key = crate::proxy::config::ProxyApiKey {
+        id: uuid::Uuid::new_v4().to_string(),
+        name,
+        key_hash: crate::proxy::config::ProxyApiKey::hash_key(&secret),
+        key_prefix: crate::proxy::config::ProxyApiKey::key_prefix_of(&secret),
+        scopes,
+        created_at: chrono::Utc::now().timestamp(),
+        revoked: false,
+        requests_per_minute,
+        tokens_per_minute,
+    };
+    let created = CreatedProxyApiKey {
+        api_key: key.clone(),
+        key: secret,
+    };
+    save_api_keys(&state, move |keys| {
+        keys.push(key);
+        Ok(())
+    })
+    .await?;
+    Ok(created)
+}
+
+/// 吊销客户端 API Key，立即对运行中的服务生效
+#[tauri::command]
+pub async fn revoke_proxy_api_key(
+    state: State<'_, ProxyServiceState>,
+    id: String,
+) -> Result<(), String> {
+    save_api_keys(&state, |keys| {
+        let key = keys
+            .iter_mut()
+            .find(|k| k.id == id)
+            .ok_or_else(|| format!("API Key 不存在: {}", id))?;
+        key.revoked = true;
+        Ok(())
+    })
+    .await
+}
+
+/// 修改客户端 API Key 的限流额度 (None 表示使用默认值，0 表示不限制)
+#[tauri::command]
+pub async fn update_proxy_api_key_limits(
+    state: State<'_, ProxyServiceState>,
+    id: String,
+    requests_per_minute: Option<u32>,
+    tokens_per_minute: Option<u32>,
+) -> Result<(), String> {
+    save_api_keys(&state, |keys| {
+        let key = keys
+            .iter_mut()
+            .find(|k| k.id == id)
+            .ok_or_else(|| format!("API Key 不存在: {}", id))?;
+        key.requests_per_minute = requests_per_minute;
+        key.tokens_per_minute = tokens_per_minute;
+        Ok(())
+    })
+    .await
+}
+
 /// 重新加载账号（当主应用添加/删除账号时调用）
 #[tauri::command]
 pub async fn reload_proxy_accounts(
@@ -362,13 +576,74 @@ pub async fn update_model_mapping(
     }
     
     // 2. 无论是否运行，都保存到全局配置持久化
+    crate::proxy::common::model_mapping::set_rule_priority(config.mapping_priority.clone());
     let mut app_config = crate::modules::config::load_app_config().map_err(|e| e)?;
     app_config.proxy.custom_mapping = config.custom_mapping;
-    crate::modules::config::save_app_config(&app_config).map_err(|e| e)?;
+    app_config.proxy.mapping_priority = config.mapping_priority;
+    crate::modules::config::save_app_config(&app_config, "update_model_mapping").map_err(|e| e)?;
     
     Ok(())
 }
 
+/// 测试模型路由：返回给定模型名命中的规则 (精确 / 通配符 / 正则 / 系统默认) 与目标模型
+/// 传入 mapping / priority 时使用它们 (用于编辑器中未保存的规则)，否则使用已保存的配置
+#[tauri::command]
+pub async fn test_model_route(
+    model: String,
+    mapping: Option<std::collections::HashMap<String, String>>,
+    priority: Option<Vec<String>>,
+) -> Result<crate::proxy::common::model_mapping::RouteMatch, String> {
+    let (mapping, priority) = match (mapping, priority) {
+        (Some(m), Some(p)) => (m, p),
+        (mapping, priority) => {
+            let saved = crate::modules::config::load_app_config()?.proxy;
+            (
+                mapping.unwrap_or(saved.custom_mapping),
+                priority.unwrap_or(saved.mapping_priority),
+            )
+        }
+    };
+    Ok(crate::proxy::common::model_mapping::explain_model_route_with(
+        &model, &mapping, &priority,
+    ))
+}
+
+/// 获取生效中的模型能力表 (内置 + 配置覆盖)
+#[tauri::command]
+pub async fn get_model_capabilities(
+) -> Result<Vec<crate::proxy::common::model_capabilities::CapabilityEntry>, String> {
+    Ok(crate::proxy::common::model_capabilities::list_effective())
+}
+
+/// 获取已检测到的上游模型 (首次发现时间、发现账号、是否已有能力规则)
+#[tauri::command]
+pub async fn get_discovered_models() -> Result<
+    std::collections::BTreeMap<String, crate::modules::model_discovery::KnownModel>,
+    String,
+> {
+    crate::modules::model_discovery::list_known_models()
+}
+
+/// 获取请求镜像样本 (主请求 vs 镜像请求)，with_bodies 为 true 时包含请求/响应内容
+#[tauri::command]
+pub async fn get_mirror_samples(
+    limit: Option<usize>,
+    offset: Option<usize>,
+    with_bodies: Option<bool>,
+) -> Result<Vec<crate::proxy::mirror::MirrorSample>, String> {
+    crate::modules::proxy_db::get_mirror_samples(
+        limit.unwrap_or(100),
+        offset.unwrap_or(0),
+        with_bodies.unwrap_or(false),
+    )
+}
+
+/// 清空请求镜像样本
+#[tauri::command]
+pub async fn clear_mirror_samples() -> Result<(), String> {
+    crate::modules::proxy_db::clear_mirror_samples()
+}
+
 fn join_base_url(base: &str, path: &str) -> String {
     let base = base.trim_end_matches('/');
     let path = if path.starts_with('/') {
@@ -444,8 +719,8 @@ pub async fn fetch_zai_models(
     let url = join_base_url(&zai.base_url, "/v1/models");
 
     let mut builder = reqwest::Client::builder().timeout(Duration::from_secs(request_timeout.max(5)));
-    if upstream_proxy.enabled && !upstream_proxy.url.is_empty() {
-        let proxy = reqwest::Proxy::all(&upstream_proxy.url)
+    if let Some(url) = upstream_proxy.proxy_url() {
+        let proxy = reqwest::Proxy::all(&url)
             .map_err(|e| format!("Invalid upstream proxy url: {}", e))?;
         builder = builder.proxy(proxy);
     }
@@ -522,6 +797,19 @@ pub async fn clear_proxy_session_bindings(
     }
 }
 
+/// 获取各账号当前的限流冷却状态 (剩余冷却时间、原因、关联模型)
+#[tauri::command]
+pub async fn get_proxy_account_cooldowns(
+    state: State<'_, ProxyServiceState>,
+) -> Result<Vec<crate::proxy::token_manager::AccountCooldown>, String> {
+    let instance_lock = state.instance.read().await;
+    if let Some(instance) = instance_lock.as_ref() {
+        Ok(instance.token_manager.cooldown_snapshot())
+    } else {
+        Ok(Vec::new())
+    }
+}
+
 // ===== [FIX #820] 固定账号模式命令 =====
 
 /// 设置优先使用的账号（固定账号模式）
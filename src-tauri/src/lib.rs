@@ -56,15 +56,78 @@ pub fn run() {
     #[cfg(target_os = "macos")]
     increase_nofile_limit();
 
+    // 自定义数据目录（--data-dir），必须在任何模块访问数据目录之前设置
+    if let Err(e) = modules::account::init_data_dir_from_args() {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    }
+
     // Initialize logger
     logger::init_logger();
 
+    // 无界面子命令（self-update 等），处理完直接退出
+    if let Some(code) = modules::self_update::handle_cli() {
+        std::process::exit(code);
+    }
+    modules::login_item::init_from_args();
+
+    // 自定义数据目录的多个配置档可以同时运行，但同一目录只允许一个管理器
+    if let Err(e) = modules::account::lock_data_dir_override() {
+        error!("{}", e);
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    if let Some(dir) = modules::account::data_dir_override() {
+        info!("Using custom data directory: {}", dir.display());
+    }
+
+    if let Err(e) = modules::config_audit::init_db() {
+        error!("Failed to initialize config audit database: {}", e);
+    }
+
+    if let Err(e) = modules::quota_history::init_db() {
+        error!("Failed to initialize quota history database: {}", e);
+    }
+
+    if let Err(e) = modules::quota_journal::init_db() {
+        error!("Failed to initialize quota journal database: {}", e);
+    }
+
+    if let Err(e) = modules::job_history::init_db() {
+        error!("Failed to initialize job history database: {}", e);
+    }
+
     // Initialize token stats database
     if let Err(e) = modules::token_stats::init_db() {
         error!("Failed to initialize token stats database: {}", e);
+    } else {
+        match modules::token_stats::recover_unfinished_intents() {
+            Ok(0) => {}
+            Ok(n) => info!("Recovered {} in-flight requests from last run as unknown outcome", n),
+            Err(e) => error!("Failed to recover usage intents: {}", e),
+        }
+        modules::token_budget::init();
     }
 
-    tauri::Builder::default()
+    let builder = tauri::Builder::default();
+    // 单实例插件按应用标识加锁，自定义数据目录的配置档改用数据目录锁
+    let builder = if modules::account::data_dir_override().is_none() {
+        builder.plugin(tauri_plugin_single_instance::init(|app, args, _cwd| {
+            let _ = app.get_webview_window("main").map(|window| {
+                let _ = window.show();
+                let _ = window.set_focus();
+                #[cfg(target_os = "macos")]
+                app.set_activation_policy(tauri::ActivationPolicy::Regular)
+                    .unwrap_or(());
+            });
+            // Re-authorization links opened while the app is running
+            modules::reauth::handle_args(&args);
+        }))
+    } else {
+        builder
+    };
+
+    builder
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_opener::init())
@@ -74,15 +137,7 @@ pub fn run() {
         ))
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_process::init())
-        .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
-            let _ = app.get_webview_window("main").map(|window| {
-                let _ = window.show();
-                let _ = window.set_focus();
-                #[cfg(target_os = "macos")]
-                app.set_activation_policy(tauri::ActivationPolicy::Regular)
-                    .unwrap_or(());
-            });
-        }))
+        .plugin(tauri_plugin_deep_link::init())
         .manage(commands::proxy::ProxyServiceState::new())
         .manage(crate::mitm::MitmServiceState::new())
         .setup(|app| {
@@ -110,6 +165,13 @@ pub fn run() {
                 }
             }
 
+            modules::account::mark_app_started();
+
+            // Older account indexes lack the display fields of the summaries
+            if let Err(e) = modules::account::migrate_account_index() {
+                warn!("Failed to migrate account index: {}", e);
+            }
+
             modules::tray::create_tray(app.handle())?;
             info!("Tray created");
 
@@ -118,7 +180,7 @@ pub fn run() {
             tauri::async_runtime::spawn(async move {
                 // Load config
                 if let Ok(config) = modules::config::load_app_config() {
-                    if config.proxy.auto_start {
+                    if config.proxy.auto_start || modules::login_item::start_proxy_requested() {
                         let state = handle.state::<commands::proxy::ProxyServiceState>();
                         // Attempt to start service
                         if let Err(e) = commands::proxy::start_proxy_service(
@@ -152,6 +214,34 @@ pub fn run() {
             // Start smart scheduler
             modules::scheduler::start_scheduler(app.handle().clone());
 
+            // Apply edits made to the config file outside the app
+            modules::config_watcher::start(app.handle().clone());
+
+            // Report instance profile changes made outside the manager
+            modules::profile_watcher::start(app.handle().clone());
+
+            // Start process watcher (pushes Antigravity start/stop events)
+            modules::crash_loop::init(app.handle().clone());
+            modules::model_discovery::init(app.handle().clone());
+            modules::quota_alerts::init(app.handle().clone());
+            modules::reauth::init(app.handle().clone());
+            modules::reauth::handle_args(&std::env::args().collect::<Vec<_>>());
+            {
+                use tauri_plugin_deep_link::DeepLinkExt;
+                // Linux / Windows 开发构建需要在运行时注册 URL scheme (安装包在安装时注册)
+                #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+                if let Err(e) = app.deep_link().register_all() {
+                    warn!("Failed to register deep link schemes: {}", e);
+                }
+                // macOS 通过系统事件投递链接，不经过启动参数
+                app.deep_link().on_open_url(|event| {
+                    let urls: Vec<String> = event.urls().iter().map(|u| u.to_string()).collect();
+                    modules::reauth::handle_args(&urls);
+                });
+            }
+            modules::process::start_process_watcher(app.handle().clone());
+            modules::instance::spawn_process_event_sync();
+
             // Start HTTP API server (for external calls, e.g. VS Code plugin)
             match modules::http_api::load_settings() {
                 Ok(settings) if settings.enabled => {
@@ -195,6 +285,14 @@ pub fn run() {
             greet,
             // Account management commands
             commands::list_accounts,
+            commands::list_account_summaries,
+            commands::get_account,
+            commands::query_accounts,
+            commands::set_account_tags,
+            commands::set_account_proxy,
+            commands::set_account_expiry,
+            commands::list_archived_accounts,
+            commands::get_cache_stats,
             commands::add_account,
             commands::delete_account,
             commands::delete_accounts,
@@ -219,13 +317,27 @@ pub fn run() {
             // Config commands
             commands::load_config,
             commands::save_config,
+            commands::validate_config,
+            commands::export_config,
+            commands::import_config,
+            commands::query_config_audit,
+            commands::list_config_backups,
+            commands::restore_config_backup,
+            commands::sync_config,
+            commands::get_config_sync_state,
+            commands::migrate_token_storage,
             // Additional commands
             commands::prepare_oauth_url,
             commands::start_oauth_login,
             commands::complete_oauth_login,
+            commands::start_batch_oauth_login,
+            commands::reauth_account,
+            commands::start_device_oauth_login,
+            commands::submit_oauth_code,
             commands::cancel_oauth_login,
             commands::import_v1_accounts,
             commands::import_from_db,
+            commands::import_from_user_data_dir,
             commands::import_custom_db,
             commands::sync_account_from_db,
             commands::save_text_file,
@@ -246,9 +358,13 @@ pub fn run() {
             commands::proxy::start_proxy_service,
             commands::proxy::stop_proxy_service,
             commands::proxy::get_proxy_status,
+            commands::proxy::restart_proxy_listeners,
+            commands::proxy::issue_proxy_client_certificate,
             commands::proxy::get_proxy_stats,
             commands::proxy::get_proxy_logs,
             commands::proxy::get_proxy_logs_paginated,
+            commands::proxy::query_proxy_logs,
+            commands::proxy::purge_proxy_logs,
             commands::proxy::get_proxy_log_detail,
             commands::proxy::get_proxy_logs_count,
             commands::proxy::export_proxy_logs,
@@ -258,17 +374,30 @@ pub fn run() {
             commands::proxy::set_proxy_monitor_enabled,
             commands::proxy::clear_proxy_logs,
             commands::proxy::generate_api_key,
+            commands::proxy::list_proxy_api_keys,
+            commands::proxy::create_proxy_api_key,
+            commands::proxy::revoke_proxy_api_key,
+            commands::proxy::update_proxy_api_key_limits,
             commands::proxy::reload_proxy_accounts,
             commands::proxy::update_model_mapping,
+            commands::proxy::test_model_route,
+            commands::proxy::get_model_capabilities,
+            commands::proxy::get_discovered_models,
+            commands::proxy::get_mirror_samples,
+            commands::proxy::clear_mirror_samples,
             commands::proxy::fetch_zai_models,
             commands::proxy::get_proxy_scheduling_config,
             commands::proxy::update_proxy_scheduling_config,
             commands::proxy::clear_proxy_session_bindings,
+            commands::proxy::get_proxy_account_cooldowns,
             commands::proxy::set_preferred_account,
             commands::proxy::get_preferred_account,
             // Autostart commands
             commands::autostart::toggle_auto_launch,
             commands::autostart::is_auto_launch_enabled,
+            commands::autostart::set_login_item,
+            commands::autostart::get_login_item_status,
+            commands::autostart::is_started_minimized,
             // Warmup commands
             commands::warm_up_all_accounts,
             commands::warm_up_account,
@@ -276,11 +405,23 @@ pub fn run() {
             commands::get_http_api_settings,
             commands::save_http_api_settings,
             // Token 统计命令
+            commands::get_quota_matrix,
+            commands::get_quota_history,
+            commands::get_quota_forecast,
+            commands::export_quota_history,
+            commands::set_account_token_budget,
+            commands::get_token_budget_status,
+            commands::get_quota_alerts,
+            commands::get_quota_incidents,
+            commands::annotate_quota_incident,
+            commands::export_quota_incidents,
             commands::get_token_stats_hourly,
             commands::get_token_stats_daily,
             commands::get_token_stats_weekly,
             commands::get_token_stats_by_account,
+            commands::get_token_stats_by_api_key,
             commands::get_token_stats_summary,
+            commands::get_token_usage_records,
             commands::get_token_stats_by_model,
             commands::get_token_stats_model_trend_hourly,
             commands::get_token_stats_model_trend_daily,
@@ -295,10 +436,15 @@ pub fn run() {
             commands::create_instance,
             commands::get_instance,
             commands::delete_instance,
+            commands::preview_instance_purge,
             commands::update_instance,
+            commands::validate_instance_args,
+            commands::list_monitors,
             commands::bind_account_to_instance,
             commands::unbind_account_from_instance,
             commands::start_instance,
+            commands::prepare_and_start_instance,
+            commands::revalidate_instance_launch_settings,
             commands::stop_instance,
             commands::get_instance_status,
             commands::ensure_default_instance,
@@ -307,6 +453,25 @@ pub fn run() {
             commands::set_current_account_for_instance,
             commands::switch_account_in_instance,
             commands::get_running_instances,
+            commands::get_process_snapshot,
+            commands::get_instance_process_tree,
+            commands::tail_instance_log,
+            commands::reset_instance_fault,
+            commands::halt_everything,
+            commands::resume_scheduled_jobs,
+            commands::save_cron_job,
+            commands::delete_cron_job,
+            commands::preview_cron_schedule,
+            commands::query_job_history,
+            commands::schedule_delayed_task,
+            commands::list_delayed_tasks,
+            commands::cancel_delayed_task,
+            commands::list_scheduled_jobs,
+            commands::set_scheduled_job_paused,
+            commands::find_orphaned_helpers,
+            commands::cleanup_orphaned_helpers,
+            commands::discover_instance_profiles,
+            commands::import_discovered_profiles,
             // MITM proxy commands
             commands::mitm::start_mitm_proxy_service,
             commands::mitm::stop_mitm_proxy_service,
@@ -322,6 +487,20 @@ pub fn run() {
         .build(tauri::generate_context!())
         .expect("error while building tauri application")
         .run(|app_handle, event| {
+            // Orderly shutdown: hold the exit until proxy/DB/scheduler state is flushed
+            if let tauri::RunEvent::ExitRequested { api, code, .. } = &event {
+                if modules::shutdown::begin() {
+                    api.prevent_exit();
+                    let handle = app_handle.clone();
+                    let code = code.unwrap_or(0);
+                    tauri::async_runtime::spawn(async move {
+                        modules::shutdown::run(&handle).await;
+                        handle.exit(code);
+                    });
+                    return;
+                }
+            }
+
             // Handle macOS dock icon click to reopen window
             #[cfg(target_os = "macos")]
             if let tauri::RunEvent::Reopen { .. } = event {
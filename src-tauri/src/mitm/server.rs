@@ -98,7 +98,10 @@ pub async fn start_mitm_service(
 
     if let Some(ref proxy_url) = upstream {
         if proxy_url.starts_with("socks5://") || proxy_url.starts_with("socks5h://") {
-            tracing::info!("[MITM] 上游代理 (SOCKS5): {}", proxy_url);
+            tracing::info!(
+                "[MITM] 上游代理 (SOCKS5): {}",
+                crate::utils::http::redact_proxy_url(proxy_url)
+            );
 
             let socks = crate::mitm::connector::Socks5Connector::new(proxy_url);
             let https = hyper_rustls_mitm::HttpsConnectorBuilder::new()
@@ -133,7 +136,10 @@ pub async fn start_mitm_service(
                 }
             });
         } else {
-            tracing::warn!("[MITM] 不支持的上游代理协议: {}，回退为直连", proxy_url);
+            tracing::warn!(
+                "[MITM] 不支持的上游代理协议: {}，回退为直连",
+                crate::utils::http::redact_proxy_url(proxy_url)
+            );
             spawn_direct_proxy(addr, ca, handler, rx);
         }
     } else {
@@ -182,13 +188,11 @@ fn spawn_direct_proxy(
 }
 
 fn get_upstream_proxy() -> Option<String> {
-    if let Ok(config) = crate::modules::config::load_app_config() {
-        let proxy = &config.proxy.upstream_proxy;
-        if proxy.enabled && !proxy.url.is_empty() {
-            return Some(proxy.url.clone());
-        }
-    }
-    None
+    crate::modules::config::load_app_config()
+        .ok()?
+        .proxy
+        .upstream_proxy
+        .proxy_url()
 }
 
 /// 获取 MITM 服务状态
@@ -25,6 +25,10 @@ pub struct Account {
     /// Unix timestamp when the account was disabled.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub disabled_at: Option<i64>,
+    /// The refresh token was rejected (invalid_grant): the user must sign in again.
+    /// Transient refresh failures never set this.
+    #[serde(default)]
+    pub needs_reauth: bool,
     /// User manually disabled proxy feature (does not affect app usage).
     #[serde(default)]
     pub proxy_disabled: bool,
@@ -37,6 +41,26 @@ pub struct Account {
     /// 受配额保护禁用的模型列表 [NEW #621]
     #[serde(default, skip_serializing_if = "HashSet::is_empty")]
     pub protected_models: HashSet<String>,
+    /// Every monitored model is below the protection threshold: the account is paused in
+    /// proxy rotation until a quota refresh shows it recovered
+    #[serde(default)]
+    pub quota_protected: bool,
+    /// User-defined tags for grouping/filtering accounts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Temporary accounts: Unix timestamp after which the account is archived and wiped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    /// Upstream proxy for this account's OAuth and quota requests (http://, https://, socks5://);
+    /// the global upstream proxy is used when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_proxy: Option<String>,
+    /// Manual token budget tracked from the manager's own token stats
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_budget: Option<TokenBudget>,
+    /// Estimated Unix time the bottleneck model runs out at the recent burn rate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depletes_at: Option<i64>,
     pub created_at: i64,
     pub last_used: i64,
 }
@@ -55,10 +79,17 @@ impl Account {
             disabled: false,
             disabled_reason: None,
             disabled_at: None,
+            needs_reauth: false,
             proxy_disabled: false,
             proxy_disabled_reason: None,
             proxy_disabled_at: None,
             protected_models: HashSet::new(),
+            quota_protected: false,
+            tags: Vec::new(),
+            expires_at: None,
+            upstream_proxy: None,
+            token_budget: None,
+            depletes_at: None,
             created_at: now,
             last_used: now,
         }
@@ -71,8 +102,119 @@ impl Account {
     pub fn update_quota(&mut self, quota: QuotaData) {
         self.quota = Some(quota);
     }
+
+    /// Lowest remaining percentage across all models (the bottleneck model).
+    /// Returns None when quota has never been fetched.
+    pub fn quota_remaining(&self) -> Option<i32> {
+        self.quota
+            .as_ref()
+            .and_then(|q| q.models.iter().map(|m| m.percentage).min())
+    }
+
+}
+
+/// Budget period of a manual token budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    Day,
+    Week,
+    Month,
+}
+
+/// Manual token budget of an account, enforced in proxy account selection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenBudget {
+    /// Total (input + output) tokens allowed per period
+    pub limit_tokens: u64,
+    pub period: BudgetPeriod,
+}
+
+/// Sort key for paginated account queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountSortKey {
+    /// Keep the user-defined order from accounts.json
+    #[default]
+    Index,
+    QuotaRemaining,
+    LastUsed,
+    CreatedAt,
+    Email,
+}
+
+/// Status filter for paginated account queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountStatusFilter {
+    /// Not disabled, not proxy-disabled, not forbidden, not paused by quota protection
+    Active,
+    Disabled,
+    ProxyDisabled,
+    Forbidden,
+    /// At least one model is paused by quota protection
+    Protected,
 }
 
+/// Paginated account query parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountQuery {
+    /// 1-based page number
+    #[serde(default = "default_page")]
+    pub page: usize,
+    #[serde(default = "default_page_size")]
+    pub page_size: usize,
+    #[serde(default)]
+    pub sort_by: AccountSortKey,
+    #[serde(default)]
+    pub descending: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<AccountStatusFilter>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_id: Option<String>,
+    /// Case-insensitive substring match on email / name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search: Option<String>,
+}
+
+fn default_page() -> usize {
+    1
+}
+
+fn default_page_size() -> usize {
+    50
+}
+
+impl Default for AccountQuery {
+    fn default() -> Self {
+        Self {
+            page: default_page(),
+            page_size: default_page_size(),
+            sort_by: AccountSortKey::default(),
+            descending: false,
+            tag: None,
+            status: None,
+            instance_id: None,
+            search: None,
+        }
+    }
+}
+
+/// One page of query results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountPage {
+    pub items: Vec<AccountSummary>,
+    /// Number of accounts matching the filters (before pagination)
+    pub total: usize,
+    pub page: usize,
+    pub page_size: usize,
+}
+
+/// 当前索引版本；2.1 起 AccountSummary 带有展示字段
+pub const ACCOUNT_INDEX_VERSION: &str = "2.1";
+
 /// 账号索引数据（accounts.json）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccountIndex {
@@ -82,19 +224,95 @@ pub struct AccountIndex {
 }
 
 /// 账号摘要信息
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// 只包含列表展示所需的非敏感字段，由 save_account 同步，列表场景无需读取账号文件
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct AccountSummary {
     pub id: String,
     pub email: String,
     pub name: Option<String>,
     pub created_at: i64,
     pub last_used: i64,
+    #[serde(default)]
+    pub disabled: bool,
+    /// 需要重新登录授权 (refresh token 失效)
+    #[serde(default)]
+    pub needs_reauth: bool,
+    #[serde(default)]
+    pub proxy_disabled: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// 最低剩余配额百分比（未获取过配额时为空）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota_remaining: Option<i32>,
+    #[serde(default)]
+    pub is_forbidden: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscription_tier: Option<String>,
+    /// 受配额保护暂停的模型数
+    #[serde(default)]
+    pub protected_model_count: usize,
+    /// 因配额保护暂停轮换
+    #[serde(default)]
+    pub quota_protected: bool,
+    /// 临时账号的过期时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    /// 按近期消耗速度预计配额耗尽的时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depletes_at: Option<i64>,
+    /// 反代中的熔断状态 (运行时填充，不写入索引；反代未运行或账号无请求记录时为空)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit: Option<crate::proxy::circuit_breaker::CircuitBreakerStatus>,
+}
+
+impl AccountSummary {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+}
+
+impl From<&Account> for AccountSummary {
+    fn from(account: &Account) -> Self {
+        Self {
+            id: account.id.clone(),
+            email: account.email.clone(),
+            name: account.name.clone(),
+            created_at: account.created_at,
+            last_used: account.last_used,
+            disabled: account.disabled,
+            needs_reauth: account.needs_reauth,
+            proxy_disabled: account.proxy_disabled,
+            tags: account.tags.clone(),
+            quota_remaining: account.quota_remaining(),
+            is_forbidden: account.quota.as_ref().map_or(false, |q| q.is_forbidden),
+            subscription_tier: account
+                .quota
+                .as_ref()
+                .and_then(|q| q.subscription_tier.clone()),
+            protected_model_count: account.protected_models.len(),
+            quota_protected: account.quota_protected,
+            expires_at: account.expires_at,
+            depletes_at: account.depletes_at,
+            circuit: None,
+        }
+    }
+}
+
+/// 已过期临时账号的归档记录（不含任何凭据）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedAccount {
+    pub id: String,
+    pub email: String,
+    pub name: Option<String>,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub archived_at: i64,
 }
 
 impl AccountIndex {
     pub fn new() -> Self {
         Self {
-            version: "2.0".to_string(),
+            version: ACCOUNT_INDEX_VERSION.to_string(),
             accounts: Vec::new(),
             current_account_id: None,
         }
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use crate::proxy::ProxyConfig;
 
 /// Application configuration
@@ -25,6 +26,170 @@ pub struct AppConfig {
     pub pinned_quota_models: PinnedQuotaModelsConfig, // [NEW] Pinned quota models list
     #[serde(default)]
     pub mitm: crate::mitm::config::MitmConfig, // [NEW] MITM Proxy Config
+    #[serde(default)]
+    pub shutdown: ShutdownConfig, // [NEW] App exit behaviour
+    #[serde(default)]
+    pub process_recognition: ProcessRecognitionConfig, // [NEW] Rules for detecting Antigravity processes
+    #[serde(default)]
+    pub crash_loop: CrashLoopConfig, // [NEW] Crash-loop detection for instance launches
+    #[serde(default)]
+    pub model_discovery: ModelDiscoveryConfig, // [NEW] Detection of new upstream models
+    #[serde(default)]
+    pub token_storage: TokenStorageConfig, // [NEW] Where OAuth refresh tokens are persisted
+    #[serde(default = "default_log_level")]
+    pub log_level: String, // [NEW] tracing filter, e.g. "info" or "debug" (RUST_LOG overrides)
+    #[serde(default)]
+    pub remote_sync: RemoteSyncConfig, // [NEW] Sync of the config to a remote endpoint
+    #[serde(default = "default_config_backup_retention")]
+    pub config_backup_retention: u32, // [NEW] Number of automatic config backups to keep
+    #[serde(default)]
+    pub quota_refresh_rules: Vec<QuotaRefreshRule>, // [NEW] Per-account/tag quota refresh intervals
+    #[serde(default = "default_quota_refresh_concurrency")]
+    pub quota_refresh_concurrency: u32, // [NEW] Accounts refreshed in parallel during a batch refresh
+    #[serde(default = "default_quota_refresh_timeout_secs")]
+    pub quota_refresh_timeout_secs: u64, // [NEW] Per-account timeout of a batch refresh
+    #[serde(default = "default_quota_cache_ttl_secs")]
+    pub quota_cache_ttl_secs: u64, // [NEW] Reuse a quota response younger than this, 0 = always fetch
+    #[serde(default)]
+    pub cron_jobs: Vec<CronJob>, // [NEW] Jobs run on cron schedules
+    #[serde(default = "default_job_history_retention_days")]
+    pub job_history_retention_days: u32, // [NEW] Days of scheduled job runs kept in the history
+    #[serde(default)]
+    pub paused_jobs: Vec<String>, // [NEW] Scheduled jobs paused by the user (see scheduler::PAUSABLE_JOBS)
+    #[serde(default = "default_scheduler_jitter_secs")]
+    pub scheduler_jitter_secs: u32, // [NEW] Random delay (0..=N s) before each periodic job run, 0 = off
+    #[serde(default = "default_scheduler_max_parallel_jobs")]
+    pub scheduler_max_parallel_jobs: u32, // [NEW] Scheduled jobs allowed to run at the same time
+    #[serde(default = "default_scheduler_long_job_parallel")]
+    pub scheduler_long_job_parallel: u32, // [NEW] Long-running jobs (backups, warmup) allowed at once
+    #[serde(default)]
+    pub job_catch_up: HashMap<String, CatchUpPolicy>, // [NEW] Catch-up policy of built-in jobs after sleep
+    #[serde(default)]
+    pub oauth: OAuthConfig, // [NEW] OAuth sign-in settings
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn default_config_backup_retention() -> u32 {
+    20
+}
+
+fn default_quota_refresh_concurrency() -> u32 {
+    5
+}
+
+fn default_quota_refresh_timeout_secs() -> u64 {
+    30
+}
+
+fn default_quota_cache_ttl_secs() -> u64 {
+    60
+}
+
+fn default_job_history_retention_days() -> u32 {
+    30
+}
+
+fn default_scheduler_jitter_secs() -> u32 {
+    30
+}
+
+fn default_scheduler_max_parallel_jobs() -> u32 {
+    3
+}
+
+fn default_scheduler_long_job_parallel() -> u32 {
+    1
+}
+
+/// What a cron job does when it fires
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CronJobKind {
+    QuotaRefresh,
+    ConfigBackup,
+    InstanceStart,
+    InstanceStop,
+}
+
+/// What to do with occurrences missed while the machine was asleep
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CatchUpPolicy {
+    /// Run once right after wake-up, however many occurrences were missed
+    #[default]
+    RunOnce,
+    /// Drop missed occurrences and wait for the next one
+    Skip,
+    /// Run every missed occurrence (capped); interval jobs treat this like `RunOnce`
+    RunAll,
+}
+
+/// A job run on a cron schedule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CronJob {
+    pub id: String,
+
+    pub kind: CronJobKind,
+
+    /// Five-field cron expression (minute hour day-of-month month day-of-week) or an
+    /// alias such as "@daily"
+    pub expression: String,
+
+    /// "local", "UTC" or a fixed UTC offset such as "+08:00"
+    #[serde(default = "default_cron_timezone")]
+    pub timezone: String,
+
+    /// Target instance of instance_start / instance_stop jobs
+    #[serde(default)]
+    pub instance_id: Option<String>,
+
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    #[serde(default)]
+    pub catch_up: CatchUpPolicy,
+}
+
+impl CronJobKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CronJobKind::QuotaRefresh => "quota_refresh",
+            CronJobKind::ConfigBackup => "config_backup",
+            CronJobKind::InstanceStart => "instance_start",
+            CronJobKind::InstanceStop => "instance_stop",
+        }
+    }
+
+    /// Scheduled job whose pause switch also holds cron jobs of this kind
+    pub fn pause_group(&self) -> &'static str {
+        match self {
+            CronJobKind::QuotaRefresh => "quota_refresh",
+            CronJobKind::ConfigBackup => "config_backup",
+            CronJobKind::InstanceStart | CronJobKind::InstanceStop => "instance_schedule",
+        }
+    }
+}
+
+fn default_cron_timezone() -> String {
+    "local".to_string()
+}
+
+/// Quota refresh interval override for a group of accounts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaRefreshRule {
+    /// Accounts carrying this tag
+    #[serde(default)]
+    pub tag: Option<String>,
+
+    /// Explicit account ids (take precedence over tag rules)
+    #[serde(default)]
+    pub account_ids: Vec<String>,
+
+    /// Refresh interval in minutes
+    pub interval_minutes: u32,
 }
 
 /// Scheduled warmup configuration
@@ -74,6 +239,26 @@ pub struct QuotaProtectionConfig {
     /// List of monitored models (e.g. gemini-3-flash, gemini-3-pro-high, claude-sonnet-4-5)
     #[serde(default = "default_monitored_models")]
     pub monitored_models: Vec<String>,
+
+    /// Whether to alert when a monitored model drops to or below the alert threshold
+    #[serde(default)]
+    pub alert_enabled: bool,
+
+    /// Global alert threshold in remaining percentage (1-99)
+    #[serde(default = "default_alert_threshold")]
+    pub alert_threshold_percentage: u32,
+
+    /// Per-account alert thresholds (account id -> percentage), overriding the global one
+    #[serde(default)]
+    pub account_alert_thresholds: HashMap<String, u32>,
+
+    /// Whether alerts also show a desktop notification
+    #[serde(default = "default_true")]
+    pub alert_notification: bool,
+}
+
+fn default_alert_threshold() -> u32 {
+    10
 }
 
 fn default_monitored_models() -> Vec<String> {
@@ -90,8 +275,20 @@ impl QuotaProtectionConfig {
             enabled: false,
             threshold_percentage: 10, // Default 10% reserve
             monitored_models: default_monitored_models(),
+            alert_enabled: false,
+            alert_threshold_percentage: default_alert_threshold(),
+            account_alert_thresholds: HashMap::new(),
+            alert_notification: true,
         }
     }
+
+    /// Alert threshold for the given account (per-account override, else global)
+    pub fn alert_threshold_for(&self, account_id: &str) -> u32 {
+        self.account_alert_thresholds
+            .get(account_id)
+            .copied()
+            .unwrap_or(self.alert_threshold_percentage)
+    }
 }
 
 impl Default for QuotaProtectionConfig {
@@ -130,6 +327,401 @@ impl Default for PinnedQuotaModelsConfig {
     }
 }
 
+/// App shutdown configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShutdownConfig {
+    /// Stop instances launched by the manager during this session when the app exits
+    #[serde(default)]
+    pub stop_instances_on_exit: bool,
+
+    /// Max seconds to wait for in-flight proxy requests before exiting
+    #[serde(default = "default_drain_timeout_secs")]
+    pub drain_timeout_secs: u64,
+}
+
+fn default_drain_timeout_secs() -> u64 {
+    10
+}
+
+impl ShutdownConfig {
+    pub fn new() -> Self {
+        Self {
+            stop_instances_on_exit: false,
+            drain_timeout_secs: default_drain_timeout_secs(),
+        }
+    }
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Process recognition rules, editable for renamed or forked Antigravity builds.
+/// All matching is case-insensitive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessRecognitionConfig {
+    /// Exact main executable process names (e.g. "antigravity.exe")
+    #[serde(default = "default_process_names")]
+    pub process_names: Vec<String>,
+
+    /// Process name prefixes, used by the looser instance detection
+    #[serde(default = "default_name_prefixes")]
+    pub name_prefixes: Vec<String>,
+
+    /// Substrings of the executable path (e.g. "antigravity.app" on macOS)
+    #[serde(default = "default_path_patterns")]
+    pub path_patterns: Vec<String>,
+
+    /// Name keywords marking helper processes (renderer, gpu, crashpad...)
+    #[serde(default = "default_helper_keywords")]
+    pub helper_keywords: Vec<String>,
+}
+
+fn default_process_names() -> Vec<String> {
+    if cfg!(target_os = "windows") {
+        vec!["antigravity.exe".to_string()]
+    } else {
+        vec!["antigravity".to_string()]
+    }
+}
+
+fn default_name_prefixes() -> Vec<String> {
+    vec!["antigravity".to_string()]
+}
+
+fn default_path_patterns() -> Vec<String> {
+    // Only the app bundle on macOS: a bare "/antigravity" also matches unrelated processes there
+    if cfg!(target_os = "macos") {
+        vec!["antigravity.app".to_string()]
+    } else if cfg!(target_os = "windows") {
+        vec!["antigravity.exe".to_string()]
+    } else {
+        vec!["/antigravity".to_string()]
+    }
+}
+
+fn default_helper_keywords() -> Vec<String> {
+    [
+        "helper",
+        "plugin",
+        "renderer",
+        "gpu",
+        "crashpad",
+        "utility",
+        "audio",
+        "sandbox",
+        "language_server",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+impl ProcessRecognitionConfig {
+    pub fn new() -> Self {
+        Self {
+            process_names: default_process_names(),
+            name_prefixes: default_name_prefixes(),
+            path_patterns: default_path_patterns(),
+            helper_keywords: default_helper_keywords(),
+        }
+    }
+
+    /// Name equals one of `process_names`
+    pub fn matches_name_exact(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.process_names.iter().any(|n| n.to_lowercase() == name)
+    }
+
+    /// Name equals one of `process_names` or starts with one of `name_prefixes`
+    pub fn matches_name(&self, name: &str) -> bool {
+        let lower = name.to_lowercase();
+        self.matches_name_exact(name)
+            || self
+                .name_prefixes
+                .iter()
+                .any(|p| !p.is_empty() && lower.starts_with(&p.to_lowercase()))
+    }
+
+    /// Executable path contains one of `path_patterns`
+    pub fn matches_path(&self, exe_path: &str) -> bool {
+        let lower = exe_path.to_lowercase();
+        self.path_patterns
+            .iter()
+            .any(|p| !p.is_empty() && lower.contains(&p.to_lowercase()))
+    }
+
+    /// Name contains one of `helper_keywords`
+    pub fn is_helper_name(&self, name: &str) -> bool {
+        let lower = name.to_lowercase();
+        self.helper_keywords
+            .iter()
+            .any(|k| !k.is_empty() && lower.contains(&k.to_lowercase()))
+    }
+}
+
+impl Default for ProcessRecognitionConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Crash-loop detection for instance launches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashLoopConfig {
+    /// Mark instances as faulted after repeated fast exits
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// A launch that exits sooner than this counts as a fast exit
+    #[serde(default = "default_min_uptime_secs")]
+    pub min_uptime_secs: u64,
+
+    /// Consecutive fast exits before the instance is marked as faulted
+    #[serde(default = "default_max_fast_exits")]
+    pub max_fast_exits: u32,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_min_uptime_secs() -> u64 {
+    20
+}
+
+fn default_max_fast_exits() -> u32 {
+    3
+}
+
+impl CrashLoopConfig {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            min_uptime_secs: default_min_uptime_secs(),
+            max_fast_exits: default_max_fast_exits(),
+        }
+    }
+}
+
+impl Default for CrashLoopConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Detection of new upstream models, fed by the per-account quota refresh
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelDiscoveryConfig {
+    /// Compare upstream model lists against known models and emit events
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Add passthrough routing aliases (model -> model) for newly seen models
+    #[serde(default)]
+    pub auto_add_aliases: bool,
+}
+
+impl ModelDiscoveryConfig {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            auto_add_aliases: false,
+        }
+    }
+}
+
+impl Default for ModelDiscoveryConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Backend used to persist OAuth refresh tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenStorageBackend {
+    /// Stored inline in the account file (plain text)
+    #[default]
+    File,
+    /// OS credential store (macOS Keychain, Secret Service on Linux, DPAPI on Windows)
+    Keychain,
+    /// External secrets manager driven by user-defined shell commands
+    Command,
+}
+
+/// Refresh token storage configuration
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TokenStorageConfig {
+    #[serde(default)]
+    pub backend: TokenStorageBackend,
+
+    /// Prints the refresh token on stdout, e.g. `pass show antigravity/{account_id}`
+    #[serde(default)]
+    pub get_command: String,
+
+    /// Reads the refresh token from stdin, e.g. `pass insert -m -f antigravity/{account_id}`
+    #[serde(default)]
+    pub set_command: String,
+
+    /// Removes the stored refresh token, e.g. `pass rm -f antigravity/{account_id}`
+    #[serde(default)]
+    pub delete_command: String,
+}
+
+/// OAuth sign-in settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthConfig {
+    /// First port tried for the loopback callback server, 0 = any free port
+    #[serde(default)]
+    pub callback_port_start: u16,
+
+    /// Last port of the callback range (inclusive); 0 or below the start = only the start port
+    #[serde(default)]
+    pub callback_port_end: u16,
+
+    /// Refresh access tokens of accounts in use this many seconds before expiry, 0 = off
+    #[serde(default = "default_proactive_refresh_margin_secs")]
+    pub proactive_refresh_margin_secs: u64,
+
+    /// Browser opening the authorization URL: executable name on PATH, full path or macOS
+    /// application name; empty = system default browser
+    #[serde(default)]
+    pub browser: String,
+
+    /// Browser profile (Chromium profile directory or Firefox profile name)
+    #[serde(default)]
+    pub browser_profile: String,
+
+    /// Open the authorization URL in a private / incognito window
+    #[serde(default)]
+    pub browser_private: bool,
+
+    /// Serve the loopback callback over HTTPS with a self-signed certificate
+    /// (only for OAuth clients whose registered redirect URIs accept an https loopback)
+    #[serde(default)]
+    pub callback_tls: bool,
+
+    /// Minutes after which an unfinished authorization flow stops its callback server
+    #[serde(default = "default_callback_timeout_minutes")]
+    pub callback_timeout_minutes: u32,
+
+    /// Client id of a "TVs and Limited Input devices" OAuth client for the device flow;
+    /// empty = device flow unavailable (the built-in desktop client cannot use it)
+    #[serde(default)]
+    pub device_client_id: String,
+
+    /// Client secret of the device flow client
+    #[serde(default)]
+    pub device_client_secret: String,
+
+    /// Scopes requested by the device flow; empty = openid, email and profile
+    #[serde(default)]
+    pub device_scopes: Vec<String>,
+}
+
+fn default_callback_timeout_minutes() -> u32 {
+    10
+}
+
+fn default_proactive_refresh_margin_secs() -> u64 {
+    600
+}
+
+impl Default for OAuthConfig {
+    fn default() -> Self {
+        Self {
+            callback_port_start: 0,
+            callback_port_end: 0,
+            proactive_refresh_margin_secs: default_proactive_refresh_margin_secs(),
+            browser: String::new(),
+            browser_profile: String::new(),
+            browser_private: false,
+            callback_tls: false,
+            callback_timeout_minutes: default_callback_timeout_minutes(),
+            device_client_id: String::new(),
+            device_client_secret: String::new(),
+            device_scopes: Vec::new(),
+        }
+    }
+}
+
+impl OAuthConfig {
+    /// Callback ports to try in order; empty when any free port will do
+    pub fn callback_ports(&self) -> Vec<u16> {
+        if self.callback_port_start == 0 {
+            return Vec::new();
+        }
+        let end = self.callback_port_end.max(self.callback_port_start);
+        (self.callback_port_start..=end).collect()
+    }
+}
+
+/// Remote endpoint type for config sync
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteSyncBackend {
+    /// File URL on a WebDAV server
+    #[default]
+    Webdav,
+    /// Object in an S3 compatible bucket
+    S3,
+    /// File in a Git repository (uses the local `git` and its credentials)
+    Git,
+}
+
+/// Remote config sync configuration. This section itself is never synced.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RemoteSyncConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default)]
+    pub backend: RemoteSyncBackend,
+
+    /// WebDAV file URL, S3 endpoint (empty for AWS) or Git repository URL
+    #[serde(default)]
+    pub url: String,
+
+    /// WebDAV user name or S3 access key id
+    #[serde(default)]
+    pub username: String,
+
+    /// WebDAV password or S3 secret access key
+    #[serde(default)]
+    pub password: String,
+
+    /// S3 bucket
+    #[serde(default)]
+    pub bucket: String,
+
+    /// S3 region (default us-east-1)
+    #[serde(default)]
+    pub region: String,
+
+    /// Git branch (default main)
+    #[serde(default)]
+    pub branch: String,
+
+    /// S3 object key or file path in the Git repository (default antigravity-tools/sync.json)
+    #[serde(default)]
+    pub path: String,
+
+    /// Encrypts secrets and accounts before upload; without it only the plain config is synced
+    #[serde(default)]
+    pub passphrase: String,
+
+    /// Also sync accounts (requires a passphrase)
+    #[serde(default)]
+    pub include_accounts: bool,
+
+    /// Automatic sync interval, 0 = manual only
+    #[serde(default)]
+    pub interval_minutes: u32,
+}
+
 impl AppConfig {
     pub fn new() -> Self {
         Self {
@@ -148,8 +740,57 @@ impl AppConfig {
             quota_protection: QuotaProtectionConfig::default(),
             pinned_quota_models: PinnedQuotaModelsConfig::default(),
             mitm: crate::mitm::config::MitmConfig::default(),
+            shutdown: ShutdownConfig::default(),
+            process_recognition: ProcessRecognitionConfig::default(),
+            crash_loop: CrashLoopConfig::default(),
+            model_discovery: ModelDiscoveryConfig::default(),
+            token_storage: TokenStorageConfig::default(),
+            log_level: default_log_level(),
+            remote_sync: RemoteSyncConfig::default(),
+            config_backup_retention: default_config_backup_retention(),
+            quota_refresh_rules: Vec::new(),
+            quota_refresh_concurrency: default_quota_refresh_concurrency(),
+            quota_refresh_timeout_secs: default_quota_refresh_timeout_secs(),
+            quota_cache_ttl_secs: default_quota_cache_ttl_secs(),
+            cron_jobs: Vec::new(),
+            job_history_retention_days: default_job_history_retention_days(),
+            paused_jobs: Vec::new(),
+            scheduler_jitter_secs: default_scheduler_jitter_secs(),
+            scheduler_max_parallel_jobs: default_scheduler_max_parallel_jobs(),
+            scheduler_long_job_parallel: default_scheduler_long_job_parallel(),
+            job_catch_up: HashMap::new(),
+            oauth: OAuthConfig::default(),
         }
     }
+
+    /// Whether the user paused a scheduled job
+    pub fn job_paused(&self, job: &str) -> bool {
+        self.paused_jobs.iter().any(|j| j == job)
+    }
+
+    /// Catch-up policy of a built-in job after the machine wakes up
+    pub fn catch_up_policy(&self, job: &str) -> CatchUpPolicy {
+        self.job_catch_up.get(job).copied().unwrap_or_default()
+    }
+
+    /// Quota refresh interval in minutes for an account: an account-id rule wins over a tag
+    /// rule, and accounts matching no rule use the global `refresh_interval`
+    pub fn quota_refresh_interval_for(&self, account_id: &str, tags: &[String]) -> i64 {
+        let by_account = self
+            .quota_refresh_rules
+            .iter()
+            .find(|r| r.account_ids.iter().any(|id| id == account_id));
+        let by_tag = || {
+            self.quota_refresh_rules.iter().find(|r| {
+                r.tag
+                    .as_ref()
+                    .map_or(false, |t| tags.iter().any(|tag| tag.eq_ignore_ascii_case(t)))
+            })
+        };
+        by_account
+            .or_else(by_tag)
+            .map_or(self.refresh_interval as i64, |r| r.interval_minutes as i64)
+    }
 }
 
 impl Default for AppConfig {
@@ -157,3 +798,36 @@ impl Default for AppConfig {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(target_os = "macos")]
+    fn process_recognition_defaults_macos() {
+        let rules = ProcessRecognitionConfig::default();
+        assert!(rules.matches_path("/applications/antigravity.app/contents/macos/antigravity"));
+        assert!(!rules.matches_path("/usr/local/bin/antigravity-cli"));
+        assert!(!rules.matches_name_exact("antigravity.exe"));
+    }
+
+    #[test]
+    #[cfg(target_os = "windows")]
+    fn process_recognition_defaults_windows() {
+        let rules = ProcessRecognitionConfig::default();
+        assert!(rules.matches_name_exact("Antigravity.exe"));
+        assert!(!rules.matches_name_exact("antigravity"));
+        assert!(!rules.matches_path("c:\\program files\\antigravity.app\\x.exe"));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn process_recognition_defaults_linux() {
+        let rules = ProcessRecognitionConfig::default();
+        assert!(rules.matches_name_exact("antigravity"));
+        assert!(!rules.matches_name_exact("antigravity.exe"));
+        assert!(rules.matches_path("/usr/share/antigravity/antigravity"));
+        assert!(!rules.matches_path("/usr/bin/python3"));
+    }
+}
@@ -29,13 +29,135 @@ pub struct Instance {
     /// 上次启动时的命令行参数（用于停止后重新启动）
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub last_launch_args: Option<Vec<String>>,
+    /// 保存启动参数时的 Antigravity 版本（用于检测外部自动更新后参数失效）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_exe_version: Option<String>,
     /// 上次检测到的主进程 PID（用于快速验证实例是否运行）
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub last_root_pid: Option<u32>,
+    /// 进程优先级（为空时使用系统默认）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<ProcessPriority>,
+    /// CPU 亲和性掩码（第 n 位表示允许运行在第 n 个逻辑核心；仅 Windows/Linux 生效）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_affinity: Option<u64>,
+    /// 崩溃循环故障信息；存在时拒绝启动，需手动重置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fault: Option<InstanceFault>,
+    /// 窗口位置偏好（每次启动前写入该实例的窗口状态）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window_placement: Option<WindowPlacement>,
     /// 创建时间戳
     pub created_at: i64,
 }
 
+/// 实例进程优先级
+/// Windows 映射为优先级类，Unix 映射为 nice 值（提高优先级通常需要管理员权限）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessPriority {
+    Idle,
+    BelowNormal,
+    Normal,
+    AboveNormal,
+    High,
+}
+
+impl ProcessPriority {
+    /// Unix nice 值
+    #[cfg(unix)]
+    pub fn nice_value(self) -> i32 {
+        match self {
+            Self::Idle => 19,
+            Self::BelowNormal => 10,
+            Self::Normal => 0,
+            Self::AboveNormal => -5,
+            Self::High => -10,
+        }
+    }
+
+    /// Windows 优先级类（CreateProcess 创建标志）
+    #[cfg(target_os = "windows")]
+    pub fn windows_priority_class(self) -> u32 {
+        match self {
+            Self::Idle => 0x0000_0040,
+            Self::BelowNormal => 0x0000_4000,
+            Self::Normal => 0x0000_0020,
+            Self::AboveNormal => 0x0000_8000,
+            Self::High => 0x0000_0080,
+        }
+    }
+}
+
+/// 窗口显示模式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowMode {
+    #[default]
+    Normal,
+    Maximized,
+    Fullscreen,
+}
+
+impl WindowMode {
+    /// VS Code windowsState 中 uiState.mode 的取值
+    pub fn ui_state_mode(self) -> u8 {
+        match self {
+            Self::Maximized => 0,
+            Self::Normal => 1,
+            Self::Fullscreen => 3,
+        }
+    }
+}
+
+/// 实例窗口位置偏好
+/// Electron 不支持 --window-position 一类的参数，启动前改写 storage.json 中的窗口状态实现
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowPlacement {
+    /// 窗口左上角坐标（虚拟桌面坐标，多显示器时由坐标决定出现在哪个显示器）
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub mode: WindowMode,
+    /// 显示器名称提示（list_monitors 返回的 name，仅用于界面展示）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monitor: Option<String>,
+}
+
+/// 显示器信息（物理像素）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitorInfo {
+    pub name: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+    pub is_primary: bool,
+}
+
+/// 实例连续快速退出后记录的故障信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceFault {
+    /// 标记为故障的时间戳
+    pub faulted_at: i64,
+    /// 连续快速退出次数
+    pub consecutive_failures: u32,
+    /// 最后一次退出码（被信号终止时为空）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// 最后一次终止信号（仅 Unix）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_signal: Option<i32>,
+    /// 最后一次从启动到退出的时长（毫秒）
+    pub uptime_ms: u64,
+    /// 实例输出日志末尾若干行
+    #[serde(default)]
+    pub log_tail: Vec<String>,
+}
+
 impl Instance {
     pub fn new(id: String, name: String, user_data_dir: PathBuf) -> Self {
         Self {
@@ -48,7 +170,12 @@ impl Instance {
             current_account_id: None,
             is_default: false,
             last_launch_args: None,
+            last_exe_version: None,
             last_root_pid: None,
+            priority: None,
+            cpu_affinity: None,
+            fault: None,
+            window_placement: None,
             created_at: chrono::Utc::now().timestamp(),
         }
     }
@@ -140,3 +267,89 @@ impl Default for InstanceIndex {
         Self::new()
     }
 }
+
+/// 删除实例选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeleteInstanceOptions {
+    /// 同时删除 user_data_dir（需要先调用预览获取确认令牌）
+    #[serde(default)]
+    pub purge_data: bool,
+    /// preview_instance_purge 返回的确认令牌
+    #[serde(default)]
+    pub confirm_token: Option<String>,
+    /// 实例运行中时先强制关闭再删除
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// 删除数据目录前的预览信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstancePurgePreview {
+    pub instance_id: String,
+    pub user_data_dir: PathBuf,
+    pub exists: bool,
+    pub size_bytes: u64,
+    pub file_count: u64,
+    pub running: bool,
+    /// 一次性确认令牌，需原样传回 delete_instance
+    pub confirm_token: String,
+    /// 令牌过期时间（Unix 秒）
+    pub expires_at: i64,
+}
+
+/// 自动发现的、尚未登记为实例的 Antigravity 配置目录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoveredProfile {
+    pub user_data_dir: PathBuf,
+    /// 建议的实例名称（取目录名）
+    pub suggested_name: String,
+    /// 发现来源："process" | "filesystem"
+    pub source: String,
+    /// 是否有进程正在使用该目录
+    pub running: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// 是否为系统默认目录（导入时转为默认实例）
+    pub is_default_location: bool,
+    /// 是否存在 state.vscdb（曾经登录/使用过）
+    pub has_state_db: bool,
+    /// 目录最后修改时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<i64>,
+}
+
+/// 导入发现的配置目录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileImportRequest {
+    pub user_data_dir: PathBuf,
+    /// 为空时使用目录名
+    #[serde(default)]
+    pub name: Option<String>,
+    /// 沿用运行中进程的启动参数作为 extra_args
+    #[serde(default)]
+    pub keep_running_args: bool,
+}
+
+/// 启动参数校验问题级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LaunchArgSeverity {
+    /// 会导致启动失败或实例隔离失效，保存前应修正
+    Error,
+    /// 可能不是用户本意，但不会阻止启动
+    Warning,
+}
+
+/// 启动参数校验结果（单条）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LaunchArgWarning {
+    /// 机器可读的问题代码（如 "user_data_dir_conflict"）
+    pub code: String,
+    pub severity: LaunchArgSeverity,
+    /// 问题参数在 extra_args 中的下标
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    /// 问题参数原文
+    pub arg: String,
+    pub message: String,
+}
@@ -4,8 +4,19 @@ pub mod instance;
 pub mod quota;
 pub mod token;
 
-pub use account::{Account, AccountIndex, AccountSummary, DeviceProfile, DeviceProfileVersion};
-pub use config::{AppConfig, QuotaProtectionConfig};
-pub use instance::{Instance, InstanceIndex, InstanceSummary};
+pub use account::{
+    Account, AccountIndex, AccountPage, AccountQuery, AccountSortKey, AccountStatusFilter,
+    AccountSummary, ArchivedAccount, BudgetPeriod, DeviceProfile, DeviceProfileVersion,
+    TokenBudget,
+};
+pub use config::{
+    AppConfig, CatchUpPolicy, CronJob, CronJobKind, ProcessRecognitionConfig, QuotaProtectionConfig,
+};
+pub use instance::{
+    DeleteInstanceOptions, DiscoveredProfile, Instance, InstanceFault, InstanceIndex,
+    InstancePurgePreview,
+    InstanceSummary, LaunchArgSeverity, LaunchArgWarning, MonitorInfo, ProfileImportRequest,
+    WindowPlacement,
+};
 pub use quota::QuotaData;
 pub use token::TokenData;
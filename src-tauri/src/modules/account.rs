@@ -4,27 +4,97 @@ use std::fs;
 use std::path::PathBuf;
 use uuid::Uuid;
 
+use crate::models::account::ACCOUNT_INDEX_VERSION;
 use crate::models::{
-    Account, AccountIndex, AccountSummary, DeviceProfile, DeviceProfileVersion, QuotaData,
-    TokenData,
+    Account, AccountIndex, AccountPage, AccountQuery, AccountSortKey, AccountStatusFilter,
+    AccountSummary, ArchivedAccount, DeviceProfile, DeviceProfileVersion, QuotaData, TokenData,
 };
 use crate::modules;
+use crate::modules::cache;
 use once_cell::sync::Lazy;
-use std::sync::Mutex;
+use parking_lot::ReentrantMutex;
 
-/// Global account write lock to prevent corruption during concurrent operations
-static ACCOUNT_INDEX_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+/// Global account write lock to prevent corruption during concurrent operations.
+/// Reentrant because save_account syncs the index summary and may be called while the lock is held.
+static ACCOUNT_INDEX_LOCK: Lazy<ReentrantMutex<()>> = Lazy::new(|| ReentrantMutex::new(()));
 
 // ... existing constants ...
 const DATA_DIR: &str = ".antigravity_tools";
 const ACCOUNTS_INDEX: &str = "accounts.json";
 const ACCOUNTS_DIR: &str = "accounts";
+const ARCHIVED_ACCOUNTS_FILE: &str = "archived_accounts.json";
+
+/// Data directory given with `--data-dir`, replaces `~/.antigravity_tools` for the whole run
+static DATA_DIR_OVERRIDE: once_cell::sync::OnceCell<PathBuf> = once_cell::sync::OnceCell::new();
+
+/// Command line flag selecting a custom data directory (separate manager profile)
+pub const DATA_DIR_ARG: &str = "--data-dir";
+
+/// Read `--data-dir <path>` / `--data-dir=<path>` from the command line.
+/// Must run before anything touches the data directory.
+pub fn init_data_dir_from_args() -> Result<(), String> {
+    let args: Vec<String> = std::env::args().collect();
+    let value = args.iter().enumerate().find_map(|(i, arg)| {
+        if arg == DATA_DIR_ARG {
+            args.get(i + 1).cloned()
+        } else {
+            arg.strip_prefix(&format!("{}=", DATA_DIR_ARG)).map(|s| s.to_string())
+        }
+    });
+    let Some(value) = value else {
+        return Ok(());
+    };
+    if value.is_empty() || value.starts_with("--") {
+        return Err(format!("missing_value_for: {}", DATA_DIR_ARG));
+    }
+
+    let path = PathBuf::from(value);
+    fs::create_dir_all(&path).map_err(|e| format!("failed_to_create_data_dir: {}", e))?;
+    let path = fs::canonicalize(&path).map_err(|e| format!("invalid_data_dir: {}", e))?;
+    DATA_DIR_OVERRIDE
+        .set(path)
+        .map_err(|_| "data_dir_already_initialized".to_string())
+}
+
+/// Keep a second manager from running on the same custom data directory. The default
+/// profile is guarded by the single-instance plugin, which would also block other profiles.
+pub fn lock_data_dir_override() -> Result<(), String> {
+    let Some(dir) = DATA_DIR_OVERRIDE.get() else {
+        return Ok(());
+    };
+    let lock_path = dir.join("manager.pid");
+    if let Some(pid) = fs::read_to_string(&lock_path)
+        .ok()
+        .and_then(|c| c.trim().parse::<u32>().ok())
+    {
+        let mut system = sysinfo::System::new();
+        let pid_value = sysinfo::Pid::from_u32(pid);
+        system.refresh_processes(sysinfo::ProcessesToUpdate::Some(&[pid_value]));
+        let current_exe = std::env::current_exe().ok();
+        let running = system
+            .process(pid_value)
+            .is_some_and(|p| p.exe().map(|e| e.to_path_buf()) == current_exe);
+        if running && pid != std::process::id() {
+            return Err(format!("data_dir_in_use: {} (pid {})", dir.display(), pid));
+        }
+    }
+    fs::write(&lock_path, std::process::id().to_string())
+        .map_err(|e| format!("failed_to_lock_data_dir: {}", e))
+}
+
+/// Custom data directory of this run, None when using the default
+pub fn data_dir_override() -> Option<&'static PathBuf> {
+    DATA_DIR_OVERRIDE.get()
+}
 
-// ... existing functions get_data_dir, get_accounts_dir, load_account_index, save_account_index ...
 /// Get data directory path
 pub fn get_data_dir() -> Result<PathBuf, String> {
-    let home = dirs::home_dir().ok_or("failed_to_get_home_dir")?;
-    let data_dir = home.join(DATA_DIR);
+    let data_dir = match DATA_DIR_OVERRIDE.get() {
+        Some(path) => path.clone(),
+        None => dirs::home_dir()
+            .ok_or("failed_to_get_home_dir")?
+            .join(DATA_DIR),
+    };
 
     // Ensure directory exists
     if !data_dir.exists() {
@@ -60,22 +130,49 @@ pub fn load_account_index() -> Result<AccountIndex, String> {
     let content = fs::read_to_string(&index_path)
         .map_err(|e| format!("failed_to_read_account_index: {}", e))?;
 
+    if let Some(index) = cache::ACCOUNT_INDEX_CACHE.get(&index_path, content.as_bytes()) {
+        return Ok(index);
+    }
+
     // If file content is empty, treat as new index
     if content.trim().is_empty() {
         crate::modules::logger::log_warn("Account index is empty, initializing new index");
         return Ok(AccountIndex::new());
     }
 
-    let index: AccountIndex = serde_json::from_str(&content)
+    let mut index: AccountIndex = serde_json::from_str(&content)
         .map_err(|e| format!("failed_to_parse_account_index: {}", e))?;
 
     crate::modules::logger::log_info(&format!(
         "Successfully loaded index with {} accounts",
         index.accounts.len()
     ));
+    cache::ACCOUNT_INDEX_CACHE.put(&index_path, content.as_bytes(), index.clone());
     Ok(index)
 }
 
+/// Older indexes only carry id/email/name; backfill the display fields once.
+/// Called at startup so that loading the index never writes.
+pub fn migrate_account_index() -> Result<(), String> {
+    let _lock = ACCOUNT_INDEX_LOCK.lock();
+    let mut index = load_account_index()?;
+    if index.version == ACCOUNT_INDEX_VERSION {
+        return Ok(());
+    }
+    for summary in index.accounts.iter_mut() {
+        if let Ok(account) = load_account(&summary.id) {
+            *summary = AccountSummary::from(&account);
+        }
+    }
+    index.version = ACCOUNT_INDEX_VERSION.to_string();
+    save_account_index(&index)?;
+    crate::modules::logger::log_info(&format!(
+        "Account index migrated to version {}",
+        ACCOUNT_INDEX_VERSION
+    ));
+    Ok(())
+}
+
 /// Save account index (atomic write)
 pub fn save_account_index(index: &AccountIndex) -> Result<(), String> {
     let data_dir = get_data_dir()?;
@@ -86,11 +183,15 @@ pub fn save_account_index(index: &AccountIndex) -> Result<(), String> {
         .map_err(|e| format!("failed_to_serialize_account_index: {}", e))?;
 
     // Write to temporary file
-    fs::write(&temp_path, content)
+    fs::write(&temp_path, &content)
         .map_err(|e| format!("failed_to_write_temp_index_file: {}", e))?;
 
     // Atomic rename
-    fs::rename(temp_path, index_path).map_err(|e| format!("failed_to_replace_index_file: {}", e))
+    fs::rename(temp_path, &index_path)
+        .map_err(|e| format!("failed_to_replace_index_file: {}", e))?;
+
+    cache::ACCOUNT_INDEX_CACHE.put(&index_path, content.as_bytes(), index.clone());
+    Ok(())
 }
 
 /// Load account data
@@ -105,7 +206,21 @@ pub fn load_account(account_id: &str) -> Result<Account, String> {
     let content = fs::read_to_string(&account_path)
         .map_err(|e| format!("failed_to_read_account_data: {}", e))?;
 
-    serde_json::from_str(&content).map_err(|e| format!("failed_to_parse_account_data: {}", e))
+    if let Some(account) = cache::ACCOUNT_CACHE.get(&account_path, content.as_bytes()) {
+        return Ok(account);
+    }
+
+    let mut account: Account = serde_json::from_str(&content)
+        .map_err(|e| format!("failed_to_parse_account_data: {}", e))?;
+    account.token.refresh_token =
+        modules::token_store::resolve(&account.id, &account.token.refresh_token)?;
+    account.upstream_proxy = account
+        .upstream_proxy
+        .as_deref()
+        .map(modules::config_secrets::open_value)
+        .transpose()?;
+    cache::ACCOUNT_CACHE.put(&account_path, content.as_bytes(), account.clone());
+    Ok(account)
 }
 
 /// Save account data
@@ -113,10 +228,111 @@ pub fn save_account(account: &Account) -> Result<(), String> {
     let accounts_dir = get_accounts_dir()?;
     let account_path = accounts_dir.join(format!("{}.json", account.id));
 
-    let content = serde_json::to_string_pretty(account)
+    // The refresh token may live in an external secret store; only its reference goes to disk
+    // If the store is unavailable the token stays inline, so a save never loses account data
+    let mut stored = account.clone();
+    match modules::token_store::persist(&account.id, &account.token.refresh_token) {
+        Ok(value) => stored.token.refresh_token = value,
+        Err(e) => modules::logger::log_warn(&format!(
+            "Token store unavailable for {}, keeping the refresh token in the account file: {}",
+            account.id, e
+        )),
+    }
+    // The proxy URL may carry credentials, it is encrypted with the config key
+    stored.upstream_proxy = account
+        .upstream_proxy
+        .as_deref()
+        .map(modules::config_secrets::seal_value)
+        .transpose()?;
+    let content = serde_json::to_string_pretty(&stored)
         .map_err(|e| format!("failed_to_serialize_account_data: {}", e))?;
 
-    fs::write(&account_path, content).map_err(|e| format!("failed_to_save_account_data: {}", e))
+    fs::write(&account_path, &content).map_err(|e| format!("failed_to_save_account_data: {}", e))?;
+    cache::ACCOUNT_CACHE.put(&account_path, content.as_bytes(), account.clone());
+
+    sync_account_summary(account)
+}
+
+/// Accounts owned by a running bulk operation: account id -> (open batches owning it,
+/// summary waiting to be written when the last of them ends)
+static DEFERRED_SUMMARIES: Lazy<
+    parking_lot::Mutex<std::collections::HashMap<String, (usize, Option<AccountSummary>)>>,
+> = Lazy::new(|| parking_lot::Mutex::new(std::collections::HashMap::new()));
+
+/// Keep the index summary in sync with the account file (only writes when something changed)
+fn sync_account_summary(account: &Account) -> Result<(), String> {
+    let summary = AccountSummary::from(account);
+    if let Some(entry) = DEFERRED_SUMMARIES.lock().get_mut(&summary.id) {
+        entry.1 = Some(summary);
+        return Ok(());
+    }
+    apply_summaries(vec![summary])
+}
+
+fn apply_summaries(summaries: Vec<AccountSummary>) -> Result<(), String> {
+    let _lock = ACCOUNT_INDEX_LOCK.lock();
+    let mut index = load_account_index()?;
+    let mut changed = false;
+    for summary in summaries {
+        match index.accounts.iter_mut().find(|s| s.id == summary.id) {
+            Some(existing) if *existing != summary => {
+                *existing = summary;
+                changed = true;
+            }
+            // Not indexed yet (add_account pushes the summary itself) or unchanged
+            _ => {}
+        }
+    }
+    if changed {
+        save_account_index(&index)?;
+    }
+    Ok(())
+}
+
+/// While alive, saves of the accounts it owns only collect their index summaries; the index
+/// is written once when the batch ends. Saves of other accounts are written right away.
+/// Used by bulk operations touching many accounts.
+pub struct IndexBatch {
+    ids: Vec<String>,
+}
+
+pub fn begin_index_batch(ids: impl IntoIterator<Item = String>) -> IndexBatch {
+    let ids: Vec<String> = ids.into_iter().collect();
+    let mut deferred = DEFERRED_SUMMARIES.lock();
+    for id in &ids {
+        deferred.entry(id.clone()).or_insert((0, None)).0 += 1;
+    }
+    IndexBatch { ids }
+}
+
+impl Drop for IndexBatch {
+    fn drop(&mut self) {
+        let pending: Vec<AccountSummary> = {
+            let mut deferred = DEFERRED_SUMMARIES.lock();
+            self.ids
+                .iter()
+                .filter_map(|id| {
+                    let entry = deferred.get_mut(id)?;
+                    entry.0 -= 1;
+                    if entry.0 > 0 {
+                        return None;
+                    }
+                    deferred.remove(id)?.1
+                })
+                .collect()
+        };
+        if pending.is_empty() {
+            return;
+        }
+        if let Err(e) = apply_summaries(pending) {
+            crate::modules::logger::log_warn(&format!("Failed to update account index: {}", e));
+        }
+    }
+}
+
+/// List account summaries from the index only (no account files are read)
+pub fn list_account_summaries() -> Result<Vec<AccountSummary>, String> {
+    Ok(load_account_index()?.accounts)
 }
 
 /// List all accounts
@@ -171,15 +387,155 @@ pub fn list_accounts() -> Result<Vec<Account>, String> {
     Ok(accounts)
 }
 
+/// Query accounts with server-side filtering, sorting and pagination
+pub fn query_accounts(query: &AccountQuery) -> Result<AccountPage, String> {
+    let accounts = list_account_summaries()?;
+
+    // Resolve instance filter once instead of per account
+    let instance_account_ids = match &query.instance_id {
+        Some(instance_id) => Some(modules::instance::load_instance(instance_id)?.account_ids),
+        None => None,
+    };
+    let search = query.search.as_ref().map(|s| s.trim().to_lowercase());
+
+    let mut matched: Vec<AccountSummary> = accounts
+        .into_iter()
+        .filter(|a| query.tag.as_ref().map_or(true, |t| a.has_tag(t)))
+        .filter(|a| {
+            instance_account_ids
+                .as_ref()
+                .map_or(true, |ids| ids.contains(&a.id))
+        })
+        .filter(|a| query.status.map_or(true, |s| account_matches_status(a, s)))
+        .filter(|a| match &search {
+            Some(s) if !s.is_empty() => {
+                a.email.to_lowercase().contains(s)
+                    || a.name.as_ref().map_or(false, |n| n.to_lowercase().contains(s))
+            }
+            _ => true,
+        })
+        .collect();
+
+    match query.sort_by {
+        AccountSortKey::Index => {}
+        AccountSortKey::QuotaRemaining => {
+            // Accounts without quota data always go last, regardless of direction
+            matched.sort_by(|a, b| match (a.quota_remaining, b.quota_remaining) {
+                (Some(x), Some(y)) if query.descending => y.cmp(&x),
+                (Some(x), Some(y)) => x.cmp(&y),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            });
+        }
+        AccountSortKey::LastUsed => matched.sort_by_key(|a| a.last_used),
+        AccountSortKey::CreatedAt => matched.sort_by_key(|a| a.created_at),
+        AccountSortKey::Email => matched.sort_by_key(|a| a.email.to_lowercase()),
+    }
+    if query.descending
+        && !matches!(
+            query.sort_by,
+            AccountSortKey::Index | AccountSortKey::QuotaRemaining
+        )
+    {
+        matched.reverse();
+    }
+
+    let total = matched.len();
+    let page_size = query.page_size.max(1);
+    let page = query.page.max(1);
+    let items = matched
+        .into_iter()
+        .skip((page - 1) * page_size)
+        .take(page_size)
+        .collect();
+
+    Ok(AccountPage {
+        items,
+        total,
+        page,
+        page_size,
+    })
+}
+
+fn account_matches_status(account: &AccountSummary, status: AccountStatusFilter) -> bool {
+    match status {
+        AccountStatusFilter::Active => {
+            !account.disabled
+                && !account.proxy_disabled
+                && !account.is_forbidden
+                && !account.quota_protected
+        }
+        AccountStatusFilter::Disabled => account.disabled,
+        AccountStatusFilter::ProxyDisabled => account.proxy_disabled,
+        AccountStatusFilter::Forbidden => account.is_forbidden,
+        AccountStatusFilter::Protected => account.protected_model_count > 0,
+    }
+}
+
+/// Replace the tags of an account (trimmed, de-duplicated, empty tags dropped)
+pub fn set_account_tags(account_id: &str, tags: Vec<String>) -> Result<Account, String> {
+    let mut account = load_account(account_id)?;
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_string();
+        if !tag.is_empty() && !normalized.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+            normalized.push(tag);
+        }
+    }
+    account.tags = normalized;
+    save_account(&account)?;
+    Ok(account)
+}
+
+/// An account expiry must lie in the future
+pub fn validate_expiry(expires_at: Option<i64>) -> Result<(), String> {
+    match expires_at {
+        Some(ts) if ts <= chrono::Utc::now().timestamp() => {
+            Err("expiry_must_be_in_the_future".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Mark an account as temporary (expires at the given Unix timestamp) or make it permanent again
+pub fn set_account_expiry(account_id: &str, expires_at: Option<i64>) -> Result<Account, String> {
+    validate_expiry(expires_at)?;
+    let mut account = load_account(account_id)?;
+    account.expires_at = expires_at;
+    save_account(&account)?;
+    Ok(account)
+}
+
+/// Set or clear the upstream proxy used for an account's OAuth, quota and API proxy requests
+pub fn set_account_proxy(account_id: &str, proxy_url: Option<String>) -> Result<Account, String> {
+    let proxy_url = proxy_url
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty());
+    if let Some(url) = &proxy_url {
+        crate::utils::http::validate_proxy_url(url)?;
+    }
+    let mut account = load_account(account_id)?;
+    account.upstream_proxy = proxy_url;
+    save_account(&account)?;
+    Ok(account)
+}
+
+/// Upstream proxy configured for the account with this email, if any
+pub fn upstream_proxy_for_email(email: &str) -> Option<String> {
+    let index = load_account_index().ok()?;
+    let summary = index.accounts.iter().find(|s| s.email == email)?;
+    load_account(&summary.id).ok()?.upstream_proxy
+}
+
 /// Add account
 pub fn add_account(
     email: String,
     name: Option<String>,
     token: TokenData,
+    expires_at: Option<i64>,
 ) -> Result<Account, String> {
-    let _lock = ACCOUNT_INDEX_LOCK
-        .lock()
-        .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+    let _lock = ACCOUNT_INDEX_LOCK.lock();
     let mut index = load_account_index()?;
 
     // Check if account already exists
@@ -191,18 +547,13 @@ pub fn add_account(
     let account_id = Uuid::new_v4().to_string();
     let mut account = Account::new(account_id.clone(), email.clone(), token);
     account.name = name.clone();
+    account.expires_at = expires_at;
 
     // Save account data
     save_account(&account)?;
 
     // Update index
-    index.accounts.push(AccountSummary {
-        id: account_id.clone(),
-        email: email.clone(),
-        name: name.clone(),
-        created_at: account.created_at,
-        last_used: account.last_used,
-    });
+    index.accounts.push(AccountSummary::from(&account));
 
     // If first account, set as current
     if index.current_account_id.is_none() {
@@ -220,9 +571,19 @@ pub fn upsert_account(
     name: Option<String>,
     token: TokenData,
 ) -> Result<Account, String> {
-    let _lock = ACCOUNT_INDEX_LOCK
-        .lock()
-        .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+    upsert_account_with_expiry(email, name, token, None)
+}
+
+/// Add or update account; `expires_at` marks it as temporary (validated before anything is
+/// written, an existing expiry is kept when None)
+pub fn upsert_account_with_expiry(
+    email: String,
+    name: Option<String>,
+    token: TokenData,
+    expires_at: Option<i64>,
+) -> Result<Account, String> {
+    validate_expiry(expires_at)?;
+    let _lock = ACCOUNT_INDEX_LOCK.lock();
     let mut index = load_account_index()?;
 
     // Find account ID if exists
@@ -250,12 +611,18 @@ pub fn upsert_account(
                     account.disabled_reason = None;
                     account.disabled_at = None;
                 }
+                if account.token.refresh_token != old_refresh_token {
+                    account.needs_reauth = false;
+                }
+                if expires_at.is_some() {
+                    account.expires_at = expires_at;
+                }
                 account.update_last_used();
                 save_account(&account)?;
 
-                // Sync name in index
+                // Sync summary in index
                 if let Some(idx_summary) = index.accounts.iter_mut().find(|s| s.id == account_id) {
-                    idx_summary.name = name;
+                    *idx_summary = AccountSummary::from(&account);
                     save_account_index(&index)?;
                 }
 
@@ -269,11 +636,12 @@ pub fn upsert_account(
                 // Index exists but file is missing, recreating
                 let mut account = Account::new(account_id.clone(), email.clone(), token);
                 account.name = name.clone();
+                account.expires_at = expires_at;
                 save_account(&account)?;
 
-                // Sync name in index
+                // Sync summary in index
                 if let Some(idx_summary) = index.accounts.iter_mut().find(|s| s.id == account_id) {
-                    idx_summary.name = name;
+                    *idx_summary = AccountSummary::from(&account);
                     save_account_index(&index)?;
                 }
 
@@ -288,14 +656,12 @@ pub fn upsert_account(
 
     // Release lock, let add_account handle it
     drop(_lock);
-    add_account(email, name, token)
+    add_account(email, name, token, expires_at)
 }
 
 /// Delete account
 pub fn delete_account(account_id: &str) -> Result<(), String> {
-    let _lock = ACCOUNT_INDEX_LOCK
-        .lock()
-        .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+    let _lock = ACCOUNT_INDEX_LOCK.lock();
     let mut index = load_account_index()?;
 
     // Remove from index
@@ -318,18 +684,18 @@ pub fn delete_account(account_id: &str) -> Result<(), String> {
     let account_path = accounts_dir.join(format!("{}.json", account_id));
 
     if account_path.exists() {
+        modules::token_store::forget(account_id, &account_path);
         fs::remove_file(&account_path)
             .map_err(|e| format!("failed_to_delete_account_file: {}", e))?;
     }
+    cache::ACCOUNT_CACHE.invalidate(&account_path);
 
     Ok(())
 }
 
 /// Batch delete accounts (atomic index operation)
 pub fn delete_accounts(account_ids: &[String]) -> Result<(), String> {
-    let _lock = ACCOUNT_INDEX_LOCK
-        .lock()
-        .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+    let _lock = ACCOUNT_INDEX_LOCK.lock();
     let mut index = load_account_index()?;
 
     let accounts_dir = get_accounts_dir()?;
@@ -346,8 +712,10 @@ pub fn delete_accounts(account_ids: &[String]) -> Result<(), String> {
         // Delete account file
         let account_path = accounts_dir.join(format!("{}.json", account_id));
         if account_path.exists() {
+            modules::token_store::forget(account_id, &account_path);
             let _ = fs::remove_file(&account_path);
         }
+        cache::ACCOUNT_CACHE.invalidate(&account_path);
     }
 
     // If current account is empty, use first one as default
@@ -358,12 +726,90 @@ pub fn delete_accounts(account_ids: &[String]) -> Result<(), String> {
     save_account_index(&index)
 }
 
+/// List the archive records of expired temporary accounts
+pub fn list_archived_accounts() -> Result<Vec<ArchivedAccount>, String> {
+    let path = get_data_dir()?.join(ARCHIVED_ACCOUNTS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("failed_to_read_archived_accounts: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("failed_to_parse_archived_accounts: {}", e))
+}
+
+fn append_archived_accounts(records: &[ArchivedAccount]) -> Result<(), String> {
+    let mut archived = list_archived_accounts().unwrap_or_default();
+    archived.extend_from_slice(records);
+    let content = serde_json::to_string_pretty(&archived)
+        .map_err(|e| format!("failed_to_serialize_archived_accounts: {}", e))?;
+    fs::write(get_data_dir()?.join(ARCHIVED_ACCOUNTS_FILE), content)
+        .map_err(|e| format!("failed_to_save_archived_accounts: {}", e))
+}
+
+/// Overwrite a file with zeros before it is removed, so tokens are not left in freed blocks
+fn shred_file(path: &PathBuf) -> Result<(), String> {
+    use std::io::Write;
+
+    let len = fs::metadata(path)
+        .map_err(|e| format!("failed_to_stat_account_file: {}", e))?
+        .len() as usize;
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("failed_to_open_account_file: {}", e))?;
+    file.write_all(&vec![0u8; len])
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("failed_to_shred_account_file: {}", e))
+}
+
+/// Archive and wipe temporary accounts whose expiry has passed.
+/// Only non-sensitive metadata is kept; tokens are removed from the secret store and shredded on disk.
+pub fn expire_temporary_accounts() -> Result<Vec<ArchivedAccount>, String> {
+    let now = chrono::Utc::now().timestamp();
+    let expired: Vec<AccountSummary> = list_account_summaries()?
+        .into_iter()
+        .filter(|s| s.expires_at.is_some_and(|ts| ts <= now))
+        .collect();
+    if expired.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let _lock = ACCOUNT_INDEX_LOCK.lock();
+    let accounts_dir = get_accounts_dir()?;
+    let mut records = Vec::new();
+
+    for summary in expired {
+        let account_path = accounts_dir.join(format!("{}.json", summary.id));
+        if account_path.exists() {
+            modules::token_store::forget(&summary.id, &account_path);
+            if let Err(e) = shred_file(&account_path) {
+                modules::logger::log_warn(&format!(
+                    "Failed to shred temporary account {}: {}",
+                    summary.email, e
+                ));
+            }
+        }
+        delete_account(&summary.id)?;
+
+        modules::logger::log_info(&format!("Temporary account expired and wiped: {}", summary.email));
+        records.push(ArchivedAccount {
+            id: summary.id,
+            email: summary.email,
+            name: summary.name,
+            created_at: summary.created_at,
+            expires_at: summary.expires_at.unwrap_or(now),
+            archived_at: now,
+        });
+    }
+
+    append_archived_accounts(&records)?;
+    Ok(records)
+}
+
 /// Reorder account list
 /// Update account order in index file based on provided IDs
 pub fn reorder_accounts(account_ids: &[String]) -> Result<(), String> {
-    let _lock = ACCOUNT_INDEX_LOCK
-        .lock()
-        .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+    let _lock = ACCOUNT_INDEX_LOCK.lock();
     let mut index = load_account_index()?;
 
     // Create a map of account ID to summary
@@ -404,9 +850,7 @@ pub async fn switch_account(account_id: &str) -> Result<(), String> {
     use crate::modules::{db, device, instance, oauth, process};
 
     let index = {
-        let _lock = ACCOUNT_INDEX_LOCK
-            .lock()
-            .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+        let _lock = ACCOUNT_INDEX_LOCK.lock();
         load_account_index()?
     };
 
@@ -526,9 +970,7 @@ pub async fn switch_account(account_id: &str) -> Result<(), String> {
 
     // 9. Update tool internal state
     {
-        let _lock = ACCOUNT_INDEX_LOCK
-            .lock()
-            .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+        let _lock = ACCOUNT_INDEX_LOCK.lock();
         let mut index = load_account_index()?;
         index.current_account_id = Some(account_id.to_string());
         save_account_index(&index)?;
@@ -561,9 +1003,7 @@ pub async fn switch_account_for_instance(
     use crate::modules::{db, device, oauth, process};
 
     let index = {
-        let _lock = ACCOUNT_INDEX_LOCK
-            .lock()
-            .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+        let _lock = ACCOUNT_INDEX_LOCK.lock();
         load_account_index()?
     };
 
@@ -717,6 +1157,77 @@ pub async fn switch_account_for_instance(
     Ok(())
 }
 
+/// Result of preparing an instance's user-data-dir before launch
+#[derive(Debug, Serialize)]
+pub struct LaunchPreparation {
+    pub instance_id: String,
+    pub account_id: String,
+    pub email: String,
+    pub device_profile: Option<DeviceProfile>,
+    /// Whether the process was started after the credentials were verified
+    pub started: bool,
+}
+
+/// Write the account's credentials and device profile into a stopped instance and read them back.
+/// Creates storage.json / state.vscdb for instances that have never been launched, so the first
+/// start already sees the selected account instead of stale or missing credentials.
+pub async fn prepare_instance_launch(
+    account_id: &str,
+    instance: &crate::models::Instance,
+) -> Result<LaunchPreparation, String> {
+    use crate::modules::{db, device, process};
+
+    let running = if instance.is_default {
+        process::is_default_instance_running()
+    } else {
+        process::is_instance_running(&instance.user_data_dir)
+    };
+    if running {
+        return Err("instance_running: stop the instance before preparing a launch".to_string());
+    }
+
+    device::ensure_instance_data_dir(&instance.user_data_dir)?;
+    let storage_path = device::ensure_instance_storage(&instance.user_data_dir)?;
+    let db_path = db::ensure_instance_db(&instance.user_data_dir)?;
+
+    switch_account_for_instance(account_id, instance, false).await?;
+
+    // Read back what was written (the token may have been refreshed during the switch)
+    let account = load_account(account_id)?;
+    db::verify_injected_token(
+        &db_path,
+        &account.token.access_token,
+        &account.token.refresh_token,
+        account.token.expiry_timestamp,
+    )?;
+
+    let written = device::read_profile(&storage_path).ok();
+    if let Some(expected) = &account.device_profile {
+        let matches = written.as_ref().is_some_and(|p| {
+            p.machine_id == expected.machine_id
+                && p.mac_machine_id == expected.mac_machine_id
+                && p.dev_device_id == expected.dev_device_id
+                && p.sqm_id == expected.sqm_id
+        });
+        if !matches {
+            return Err("device_profile_verification_failed: storage.json does not match the bound profile".to_string());
+        }
+    }
+
+    crate::modules::logger::log_info(&format!(
+        "Instance {} prepared for launch with account {}",
+        instance.name, account.email
+    ));
+
+    Ok(LaunchPreparation {
+        instance_id: instance.id.clone(),
+        account_id: account.id,
+        email: account.email,
+        device_profile: written,
+        started: false,
+    })
+}
+
 /// Get device profile info: current storage.json + account bound profile
 #[derive(Debug, Serialize)]
 pub struct DeviceProfiles {
@@ -895,9 +1406,7 @@ pub fn get_current_account() -> Result<Option<Account>, String> {
 
 /// Set current active account ID
 pub fn set_current_account_id(account_id: &str) -> Result<(), String> {
-    let _lock = ACCOUNT_INDEX_LOCK
-        .lock()
-        .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+    let _lock = ACCOUNT_INDEX_LOCK.lock();
     let mut index = load_account_index()?;
     index.current_account_id = Some(account_id.to_string());
     save_account_index(&index)
@@ -906,13 +1415,51 @@ pub fn set_current_account_id(account_id: &str) -> Result<(), String> {
 /// Update account quota
 pub fn update_account_quota(account_id: &str, quota: QuotaData) -> Result<(), String> {
     let mut account = load_account(account_id)?;
+    // A response served from the quota cache is not a new observation: journal and history skip it
+    let is_new_snapshot = match &account.quota {
+        Some(previous) => quota.last_updated > previous.last_updated,
+        None => true,
+    };
+    if is_new_snapshot {
+        if let Some(previous) = &account.quota {
+            if let Err(e) = modules::quota_journal::record_quota_change(
+                &account.id,
+                &account.email,
+                previous,
+                &quota,
+            ) {
+                modules::logger::log_warn(&format!("Failed to record quota incidents: {}", e));
+            }
+        }
+        if let Err(e) = modules::quota_history::record_snapshot(&account.id, &quota) {
+            modules::logger::log_warn(&format!("Failed to record quota history: {}", e));
+        }
+    }
+    let app_config = crate::modules::config::load_app_config();
+    if let Ok(config) = &app_config {
+        modules::quota_alerts::check(
+            &account.id,
+            &account.email,
+            account.quota.as_ref(),
+            &quota,
+            &config.quota_protection,
+        );
+    }
     account.update_quota(quota);
+    match modules::quota_history::account_depletes_at(&account.id) {
+        Ok(depletes_at) => account.depletes_at = depletes_at,
+        Err(e) => modules::logger::log_warn(&format!("Failed to forecast quota depletion: {}", e)),
+    }
 
     // --- Quota protection logic start ---
-    if let Ok(config) = crate::modules::config::load_app_config() {
+    // Account is paused once every monitored model it reports is protected
+    let mut paused = false;
+    if let Ok(config) = app_config {
         if config.quota_protection.enabled {
             if let Some(ref q) = account.quota {
                 let threshold = config.quota_protection.threshold_percentage as i32;
+                let mut monitored = 0;
+                let mut protected = 0;
 
                 for model in &q.models {
                     // Normalize model name to standard ID
@@ -933,7 +1480,9 @@ pub fn update_account_quota(account_id: &str, quota: QuotaData) -> Result<(), St
                         continue;
                     }
 
+                    monitored += 1;
                     if model.percentage <= threshold {
+                        protected += 1;
                         // Trigger model-level protection
                         if !account.protected_models.contains(&standard_id) {
                             crate::modules::logger::log_info(&format!(
@@ -953,6 +1502,7 @@ pub fn update_account_quota(account_id: &str, quota: QuotaData) -> Result<(), St
                         }
                     }
                 }
+                paused = monitored > 0 && protected == monitored;
 
                 // [Compatibility] Migrate from account-level to model-level protection if previously disabled for quota
                 if account.proxy_disabled
@@ -972,11 +1522,59 @@ pub fn update_account_quota(account_id: &str, quota: QuotaData) -> Result<(), St
             }
         }
     }
+    if paused != account.quota_protected {
+        crate::modules::logger::log_info(&format!(
+            "[Quota] Account {} {} proxy rotation (quota protection)",
+            account.email,
+            if paused { "paused in" } else { "resumed in" }
+        ));
+        account.quota_protected = paused;
+    }
     // --- Quota protection logic end ---
 
     save_account(&account)
 }
 
+/// Disable an account whose refresh token was rejected, flag it for re-authorization and
+/// save it. The user is notified once the flag is persisted (only the first time).
+pub fn mark_needs_reauth(account: &mut Account, error: &str) -> Result<(), String> {
+    let newly_flagged = !account.needs_reauth;
+    account.disabled = true;
+    account.needs_reauth = true;
+    account.disabled_at = Some(chrono::Utc::now().timestamp());
+    account.disabled_reason = Some(if modules::oauth::is_invalid_grant(error) {
+        error.to_string()
+    } else {
+        format!("invalid_grant: {}", error)
+    });
+    save_account(account)?;
+    if newly_flagged {
+        modules::reauth::notify_required(&account.id, &account.email);
+    }
+    Ok(())
+}
+
+/// Refresh an account's access token ahead of expiry when it expires within `margin_secs`.
+/// Returns whether a refresh happened.
+pub async fn refresh_token_ahead(account_id: &str, margin_secs: i64) -> Result<bool, String> {
+    let mut account = load_account(account_id)?;
+    let fresh_token = match modules::oauth::ensure_fresh_token_within(&account.token, margin_secs).await {
+        Ok(token) => token,
+        Err(e) => {
+            if e.is_invalid_grant() {
+                mark_needs_reauth(&mut account, &e.to_string())?;
+            }
+            return Err(e.into());
+        }
+    };
+    if fresh_token.access_token == account.token.access_token {
+        return Ok(false);
+    }
+    account.token = fresh_token;
+    save_account(&account)?;
+    Ok(true)
+}
+
 /// Export all accounts' refresh_tokens
 #[allow(dead_code)]
 pub fn export_accounts() -> Result<Vec<(String, String)>, String> {
@@ -1000,17 +1598,14 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
     let token = match oauth::ensure_fresh_token(&account.token).await {
         Ok(t) => t,
         Err(e) => {
-            if e.contains("invalid_grant") {
+            if e.is_invalid_grant() {
                 modules::logger::log_error(&format!(
                     "Disabling account {} due to invalid_grant during token refresh (quota check)",
                     account.email
                 ));
-                account.disabled = true;
-                account.disabled_at = Some(chrono::Utc::now().timestamp());
-                account.disabled_reason = Some(format!("invalid_grant: {}", e));
-                let _ = save_account(account);
+                let _ = mark_needs_reauth(account, &e.to_string());
             }
-            return Err(AppError::OAuth(e));
+            return Err(AppError::OAuth(e.into()));
         }
     };
 
@@ -1094,22 +1689,22 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
                 ));
 
                 // Force refresh
-                let token_res = match oauth::refresh_access_token(&account.token.refresh_token)
-                    .await
+                let token_res = match oauth::refresh_account_token(
+                    &account.token.refresh_token,
+                    &account.email,
+                )
+                .await
                 {
                     Ok(t) => t,
                     Err(e) => {
-                        if e.contains("invalid_grant") {
+                        if e.is_invalid_grant() {
                             modules::logger::log_error(&format!(
                                 "Disabling account {} due to invalid_grant during forced refresh (quota check)",
                                 account.email
                             ));
-                            account.disabled = true;
-                            account.disabled_at = Some(chrono::Utc::now().timestamp());
-                            account.disabled_reason = Some(format!("invalid_grant: {}", e));
-                            let _ = save_account(account);
+                            let _ = mark_needs_reauth(account, &e.to_string());
                         }
-                        return Err(AppError::OAuth(e));
+                        return Err(AppError::OAuth(e.into()));
                     }
                 };
 
@@ -1183,26 +1778,112 @@ pub struct RefreshStats {
     pub success: usize,
     pub failed: usize,
     pub details: Vec<String>,
+    /// Per-account failures
+    pub errors: Vec<RefreshError>,
+    /// Failure count per kind ("timeout", "network", "oauth", "save", ...)
+    pub error_summary: std::collections::BTreeMap<String, usize>,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RefreshError {
+    pub account_id: String,
+    pub email: String,
+    pub kind: String,
+    pub message: String,
+}
+
+/// Batch refreshes that start within this window after launch use the stricter startup limits
+const STARTUP_REFRESH_WINDOW: std::time::Duration = std::time::Duration::from_secs(120);
+const STARTUP_MAX_CONCURRENT: usize = 2;
+/// Upper bound of the random delay before each account refresh during startup
+const STARTUP_REFRESH_JITTER_MS: u64 = 2000;
+const REFRESH_JITTER_MS: u64 = 300;
+
+static APP_STARTED_AT: Lazy<std::time::Instant> = Lazy::new(std::time::Instant::now);
+
+/// Record the launch time; call once during app setup
+pub fn mark_app_started() {
+    Lazy::force(&APP_STARTED_AT);
+}
+
+/// (concurrency cap, max jitter in ms, per-account timeout) for a batch refresh starting now
+fn batch_refresh_limits() -> (usize, u64, std::time::Duration) {
+    let config = crate::modules::config::load_app_config().unwrap_or_default();
+    let max_concurrent = config.quota_refresh_concurrency.max(1) as usize;
+    let timeout = std::time::Duration::from_secs(config.quota_refresh_timeout_secs.max(1));
+    if APP_STARTED_AT.elapsed() < STARTUP_REFRESH_WINDOW {
+        (
+            max_concurrent.min(STARTUP_MAX_CONCURRENT),
+            STARTUP_REFRESH_JITTER_MS,
+            timeout,
+        )
+    } else {
+        (max_concurrent, REFRESH_JITTER_MS, timeout)
+    }
+}
+
+fn refresh_error_kind(error: &crate::error::AppError) -> &'static str {
+    use crate::error::AppError;
+    match error {
+        AppError::Network(_) => "network",
+        AppError::OAuth(_) => "oauth",
+        AppError::Account(_) => "account",
+        AppError::Io(_) | AppError::Database(_) => "storage",
+        _ => "other",
+    }
 }
 
 /// Core logic to batch refresh all account quotas (decoupled from Tauri status)
 pub async fn refresh_all_quotas_logic() -> Result<RefreshStats, String> {
-    use futures::future::join_all;
-    use std::sync::Arc;
-    use tokio::sync::Semaphore;
+    crate::modules::logger::log_info("Starting batch refresh of all account quotas");
+    let accounts = list_accounts()?;
+    refresh_quotas_logic(accounts).await
+}
+
+/// Batch refresh the quotas of the given accounts with bounded concurrency; each account
+/// is capped by a timeout so one hanging upstream call cannot stall the whole batch
+pub async fn refresh_quotas_logic(accounts: Vec<Account>) -> Result<RefreshStats, String> {
+    refresh_quotas_with_progress(accounts, |_, _, _| {}).await
+}
 
-    const MAX_CONCURRENT: usize = 5;
+/// Same as `refresh_quotas_logic`, calling `on_progress(done, total, email)` after each account
+pub async fn refresh_quotas_with_progress<F>(
+    accounts: Vec<Account>,
+    on_progress: F,
+) -> Result<RefreshStats, String>
+where
+    F: FnMut(usize, usize, &str) + Send,
+{
+    refresh_quotas_spread(accounts, std::time::Duration::ZERO, on_progress).await
+}
+
+/// Same as `refresh_quotas_with_progress`, with the accounts' start times spread randomly
+/// over `spread` so scheduled refreshes of many accounts don't reach the upstream together
+pub async fn refresh_quotas_spread<F>(
+    accounts: Vec<Account>,
+    spread: std::time::Duration,
+    mut on_progress: F,
+) -> Result<RefreshStats, String>
+where
+    F: FnMut(usize, usize, &str) + Send,
+{
+    use futures::stream::{self, StreamExt};
+    use rand::Rng;
+
+    let (max_concurrent, jitter_ms, timeout) = batch_refresh_limits();
     let start = std::time::Instant::now();
+    let batch_start = tokio::time::Instant::now();
 
     crate::modules::logger::log_info(&format!(
-        "Starting batch refresh of all account quotas (Concurrent mode, max: {})",
-        MAX_CONCURRENT
+        "Refreshing quotas of {} accounts (Concurrent mode, max: {}, jitter: {}ms, timeout: {}s)",
+        accounts.len(),
+        max_concurrent,
+        jitter_ms,
+        timeout.as_secs()
     ));
-    let accounts = list_accounts()?;
-
-    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT));
 
-    let tasks: Vec<_> = accounts
+    let accounts: Vec<Account> = accounts
         .into_iter()
         .filter(|account| {
             if account.disabled {
@@ -1223,63 +1904,109 @@ pub async fn refresh_all_quotas_logic() -> Result<RefreshStats, String> {
             }
             true
         })
-        .map(|mut account| {
-            let email = account.email.clone();
-            let account_id = account.id.clone();
-            let permit = semaphore.clone();
-            async move {
-                let _guard = permit.acquire().await.unwrap();
-                crate::modules::logger::log_info(&format!("  - Processing {}", email));
-                match fetch_quota_with_retry(&mut account).await {
-                    Ok(quota) => {
-                        if let Err(e) = update_account_quota(&account_id, quota) {
-                            let msg = format!("Account {}: Save quota failed - {}", email, e);
-                            crate::modules::logger::log_error(&msg);
-                            Err(msg)
-                        } else {
-                            crate::modules::logger::log_info(&format!("    ✅ {} Success", email));
-                            Ok(())
-                        }
-                    }
-                    Err(e) => {
-                        let msg = format!("Account {}: Fetch quota failed - {}", email, e);
-                        crate::modules::logger::log_error(&msg);
-                        Err(msg)
+        .collect();
+    let total = accounts.len();
+    // The index summaries of all refreshed accounts are written once at the end
+    let _index_batch = begin_index_batch(accounts.iter().map(|a| a.id.clone()));
+
+    // Start offsets in ascending order: accounts are started in order, so each one waits for
+    // its own offset from the batch start rather than from when a concurrency slot frees up
+    let mut offsets: Vec<u64> = {
+        let spread_ms = spread.as_millis() as u64;
+        let mut rng = rand::thread_rng();
+        (0..total).map(|_| rng.gen_range(0..=spread_ms)).collect()
+    };
+    offsets.sort_unstable();
+
+    let mut done = 0;
+    let results: Vec<Result<String, RefreshError>> =
+        stream::iter(accounts.into_iter().zip(offsets))
+        .map(|(mut account, offset)| async move {
+            tokio::time::sleep_until(batch_start + std::time::Duration::from_millis(offset)).await;
+            // Stagger requests so a large batch does not hit the upstream in one burst
+            let delay = rand::thread_rng().gen_range(0..=jitter_ms);
+            tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+            crate::modules::logger::log_info(&format!("  - Processing {}", account.email));
+            let fail = |account: &Account, kind: &str, message: String| {
+                crate::modules::logger::log_error(&format!(
+                    "Account {}: {}",
+                    account.email, message
+                ));
+                Err(RefreshError {
+                    account_id: account.id.clone(),
+                    email: account.email.clone(),
+                    kind: kind.to_string(),
+                    message,
+                })
+            };
+            match tokio::time::timeout(timeout, fetch_quota_with_retry(&mut account)).await {
+                Ok(Ok(quota)) => match update_account_quota(&account.id, quota) {
+                    Ok(()) => {
+                        crate::modules::logger::log_info(&format!(
+                            "    ✅ {} Success",
+                            account.email
+                        ));
+                        Ok(account.email)
                     }
-                }
+                    Err(e) => fail(&account, "save", format!("Save quota failed - {}", e)),
+                },
+                Ok(Err(e)) => fail(
+                    &account,
+                    refresh_error_kind(&e),
+                    format!("Fetch quota failed - {}", e),
+                ),
+                Err(_) => fail(
+                    &account,
+                    "timeout",
+                    format!("Fetch quota timed out after {}s", timeout.as_secs()),
+                ),
             }
         })
-        .collect();
-
-    let total = tasks.len();
-    let results = join_all(tasks).await;
+        .buffer_unordered(max_concurrent)
+        .inspect(|result| {
+            done += 1;
+            let email = match result {
+                Ok(email) => email.as_str(),
+                Err(e) => e.email.as_str(),
+            };
+            on_progress(done, total, email);
+        })
+        .collect()
+        .await;
 
     let mut success = 0;
-    let mut failed = 0;
-    let mut details = Vec::new();
-
+    let mut errors = Vec::new();
     for result in results {
         match result {
-            Ok(()) => success += 1,
-            Err(msg) => {
-                failed += 1;
-                details.push(msg);
-            }
+            Ok(_) => success += 1,
+            Err(e) => errors.push(e),
         }
     }
+    let mut error_summary = std::collections::BTreeMap::new();
+    for e in &errors {
+        *error_summary.entry(e.kind.clone()).or_insert(0) += 1;
+    }
+    let details = errors
+        .iter()
+        .map(|e| format!("Account {}: {}", e.email, e.message))
+        .collect();
 
     let elapsed = start.elapsed();
     crate::modules::logger::log_info(&format!(
-        "Batch refresh completed: {} success, {} failed, took: {}ms",
+        "Batch refresh completed: {} success, {} failed {:?}, took: {}ms",
         success,
-        failed,
+        errors.len(),
+        error_summary,
         elapsed.as_millis()
     ));
 
     Ok(RefreshStats {
         total,
         success,
-        failed,
+        failed: errors.len(),
         details,
+        errors,
+        error_summary,
+        elapsed_ms: elapsed.as_millis() as u64,
     })
 }
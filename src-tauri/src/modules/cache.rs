@@ -0,0 +1,167 @@
+//! 账号 / 实例 JSON 文件的内存读缓存
+//!
+//! 缓存以文件内容的哈希为键：读取时仍会读文件，但内容未变时跳过解析与密钥解密。
+//! 写入路径（save_* / delete_*）会主动更新或失效缓存；反代 TokenManager 等绕过 modules
+//! 直接改写文件时内容哈希随之变化，不依赖 mtime 精度，也不会把读取期间的并发写入当作新鲜内容。
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::models::{Account, AccountIndex, Instance, InstanceIndex};
+
+pub static ACCOUNT_INDEX_CACHE: Lazy<JsonFileCache<AccountIndex>> =
+    Lazy::new(|| JsonFileCache::new("account_index"));
+pub static ACCOUNT_CACHE: Lazy<JsonFileCache<Account>> =
+    Lazy::new(|| JsonFileCache::new("accounts"));
+pub static INSTANCE_INDEX_CACHE: Lazy<JsonFileCache<InstanceIndex>> =
+    Lazy::new(|| JsonFileCache::new("instance_index"));
+pub static INSTANCE_CACHE: Lazy<JsonFileCache<Instance>> =
+    Lazy::new(|| JsonFileCache::new("instances"));
+
+/// 缓存命中统计
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats {
+    pub name: String,
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub invalidations: u64,
+    /// 命中率（0.0 - 1.0），无请求时为 0
+    pub hit_rate: f64,
+}
+
+/// 文件内容的 SHA-256
+type ContentHash = [u8; 32];
+
+fn content_hash(content: &[u8]) -> ContentHash {
+    Sha256::digest(content).into()
+}
+
+struct CachedEntry<T> {
+    value: T,
+    hash: ContentHash,
+}
+
+pub struct JsonFileCache<T: Clone> {
+    name: &'static str,
+    entries: RwLock<HashMap<PathBuf, CachedEntry<T>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+impl<T: Clone> JsonFileCache<T> {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            entries: RwLock::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+        }
+    }
+
+    /// 按刚读到的文件内容查找缓存；内容与缓存时不同 (被外部修改) 视为未命中并移除旧条目
+    pub fn get(&self, path: &Path, content: &[u8]) -> Option<T> {
+        {
+            let entries = self.entries.read();
+            match entries.get(path) {
+                Some(entry) if entry.hash == content_hash(content) => {
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    return Some(entry.value.clone());
+                }
+                Some(_) => {}
+                None => {
+                    self.misses.fetch_add(1, Ordering::Relaxed);
+                    return None;
+                }
+            }
+        }
+
+        // 条目已过期
+        self.entries.write().remove(path);
+        self.invalidations.fetch_add(1, Ordering::Relaxed);
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        None
+    }
+
+    /// 写入文件后（或读取解析后）以对应的文件内容更新缓存
+    pub fn put(&self, path: &Path, content: &[u8], value: T) {
+        self.entries.write().insert(
+            path.to_path_buf(),
+            CachedEntry {
+                value,
+                hash: content_hash(content),
+            },
+        );
+    }
+
+    pub fn invalidate(&self, path: &Path) {
+        if self.entries.write().remove(path).is_some() {
+            self.invalidations.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let total = hits + misses;
+        CacheStats {
+            name: self.name.to_string(),
+            entries: self.entries.read().len(),
+            hits,
+            misses,
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            hit_rate: if total == 0 {
+                0.0
+            } else {
+                hits as f64 / total as f64
+            },
+        }
+    }
+}
+
+/// 所有缓存的统计信息
+pub fn get_cache_stats() -> Vec<CacheStats> {
+    vec![
+        ACCOUNT_INDEX_CACHE.stats(),
+        ACCOUNT_CACHE.stats(),
+        INSTANCE_INDEX_CACHE.stats(),
+        INSTANCE_CACHE.stats(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_invalidates_on_external_write() {
+        let dir = std::env::temp_dir().join(format!("ag_cache_test_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.json");
+        std::fs::write(&path, "1").unwrap();
+
+        let cache: JsonFileCache<String> = JsonFileCache::new("test");
+        let content = std::fs::read(&path).unwrap();
+        assert!(cache.get(&path, &content).is_none());
+        cache.put(&path, &content, "one".to_string());
+        assert_eq!(cache.get(&path, &content).as_deref(), Some("one"));
+
+        // 外部改写（即使大小与 mtime 不变）后应失效
+        std::fs::write(&path, "2").unwrap();
+        assert!(cache.get(&path, &std::fs::read(&path).unwrap()).is_none());
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.invalidations, 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
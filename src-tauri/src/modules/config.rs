@@ -6,6 +6,17 @@ use super::account::get_data_dir;
 
 const CONFIG_FILE: &str = "gui_config.json";
 
+/// Path of the configuration file
+pub fn get_config_path() -> Result<std::path::PathBuf, String> {
+    Ok(get_data_dir()?.join(CONFIG_FILE))
+}
+
+/// Back up the current config file now (scheduled backups)
+pub fn backup_app_config() -> Result<(), String> {
+    let config = load_app_config()?;
+    super::config_backup::backup_current(&get_config_path()?, config.config_backup_retention)
+}
+
 /// Load application configuration
 pub fn load_app_config() -> Result<AppConfig, String> {
     let data_dir = get_data_dir()?;
@@ -64,25 +75,153 @@ pub fn load_app_config() -> Result<AppConfig, String> {
         }
     }
 
-    let config: AppConfig = serde_json::from_value(v)
-        .map_err(|e| format!("failed_to_convert_config_after_migration: {}", e))?;
+    // Restore secrets from the encrypted section
+    if let Err(e) = super::config_secrets::unseal(&mut v) {
+        // The blob stays in the file (save_app_config carries it over); only the parsed config drops it
+        if let Some(root) = v.as_object_mut() {
+            root.remove(super::config_secrets::SECRETS_KEY);
+        }
+        static WARNED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+        if !WARNED.swap(true, std::sync::atomic::Ordering::Relaxed) {
+            super::logger::log_warn(&format!(
+                "Failed to restore encrypted config secrets, they need to be set again: {}",
+                e
+            ));
+        }
+    }
+
+    // Client API keys used to be stored in plain text, only their hash is kept now
+    if let Some(keys) = v.pointer_mut("/proxy/api_keys").and_then(|k| k.as_array_mut()) {
+        for entry in keys.iter_mut().filter_map(|e| e.as_object_mut()) {
+            // An empty key may still be sealed in a blob that cannot be decrypted right now
+            let Some(key) = entry.get("key").and_then(|k| k.as_str()).filter(|k| !k.is_empty()) else {
+                continue;
+            };
+            let key = key.to_string();
+            entry.remove("key");
+            modified = true;
+            if entry.contains_key("key_hash") {
+                continue;
+            }
+            use crate::proxy::config::ProxyApiKey;
+            entry.insert("key_hash".to_string(), ProxyApiKey::hash_key(&key).into());
+            entry.insert("key_prefix".to_string(), ProxyApiKey::key_prefix_of(&key).into());
+        }
+    }
+
+    let (config, validation) = super::config_validation::validate_and_parse(v);
+    let Some(config) = config else {
+        return Err(format!("invalid_config: {}", validation.error_summary()));
+    };
+    warn_unknown_keys(&validation);
     
     // If migration occurred, auto-save once to clean up the file
     if modified {
-        let _ = save_app_config(&config);
+        let _ = save_app_config(&config, "migration");
     }
 
     Ok(config)
 }
 
-/// Save application configuration
-pub fn save_app_config(config: &AppConfig) -> Result<(), String> {
+/// Log unknown settings once per distinct set (the config is loaded very often)
+fn warn_unknown_keys(validation: &super::config_validation::ConfigValidation) {
+    static WARNED: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+
+    let unknown: Vec<String> = validation
+        .issues
+        .iter()
+        .filter(|i| i.code == "unknown_key")
+        .map(|i| i.path.clone())
+        .collect();
+    let mut warned = WARNED.lock().unwrap_or_else(|e| e.into_inner());
+    if unknown.is_empty() || *warned == unknown {
+        return;
+    }
+    super::logger::log_warn(&format!(
+        "Ignoring unknown settings in {}: {}",
+        CONFIG_FILE,
+        unknown.join(", ")
+    ));
+    *warned = unknown;
+}
+
+/// Current file content with secrets restored, without migration or validation.
+/// The second value is the encrypted section when it could not be decrypted.
+fn read_raw_config(config_path: &std::path::Path) -> (Option<serde_json::Value>, Option<serde_json::Value>) {
+    let Some(mut value) = fs::read_to_string(config_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+    else {
+        return (None, None);
+    };
+    let undecryptable = match super::config_secrets::unseal(&mut value) {
+        Ok(()) => None,
+        Err(_) => value
+            .as_object_mut()
+            .and_then(|root| root.remove(super::config_secrets::SECRETS_KEY)),
+    };
+    (Some(value), undecryptable)
+}
+
+/// Serializes config writes so read-modify-write updates don't interleave with saves
+static CONFIG_WRITE_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// Save application configuration. `source` names the command or task making the change
+/// and is recorded in the audit log.
+pub fn save_app_config(config: &AppConfig, source: &str) -> Result<(), String> {
+    let _guard = CONFIG_WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    write_app_config(config, source)
+}
+
+/// Load, modify and save the configuration without another save slipping in between.
+/// `update` returns whether it changed anything; the saved config is returned when it did.
+pub fn update_app_config(
+    source: &str,
+    update: impl FnOnce(&mut AppConfig) -> bool,
+) -> Result<Option<AppConfig>, String> {
+    let _guard = CONFIG_WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut config = load_app_config()?;
+    if !update(&mut config) {
+        return Ok(None);
+    }
+    write_app_config(&config, source)?;
+    Ok(Some(config))
+}
+
+fn write_app_config(config: &AppConfig, source: &str) -> Result<(), String> {
     let data_dir = get_data_dir()?;
     let config_path = data_dir.join(CONFIG_FILE);
     
-    let content = serde_json::to_string_pretty(config)
+    let mut value = serde_json::to_value(config)
         .map_err(|e| format!("failed_to_serialize_config: {}", e))?;
-    
-    fs::write(&config_path, content)
-        .map_err(|e| format!("failed_to_save_config: {}", e))
+    let new_value = value.clone();
+    let (old_value, undecryptable) = read_raw_config(&config_path);
+    super::config_secrets::seal(&mut value)?;
+    // Secrets that cannot be decrypted right now (key file missing or unreadable) are kept
+    // as they are unless new secrets were entered, so they can still be recovered
+    if let (Some(blob), Some(root)) = (undecryptable, value.as_object_mut()) {
+        root.entry(super::config_secrets::SECRETS_KEY.to_string())
+            .or_insert(blob);
+    }
+    let content = serde_json::to_string_pretty(&value)
+        .map_err(|e| format!("failed_to_serialize_config: {}", e))?;
+
+    if let Err(e) = super::config_backup::backup_current(&config_path, config.config_backup_retention) {
+        super::logger::log_warn(&format!("Failed to back up config: {}", e));
+    }
+
+    // Write to a temporary file first so an interrupted write cannot truncate the config
+    let tmp_path = config_path.with_extension("json.tmp");
+    fs::write(&tmp_path, content)
+        .map_err(|e| format!("failed_to_save_config: {}", e))?;
+    fs::rename(&tmp_path, &config_path)
+        .map_err(|e| format!("failed_to_save_config: {}", e))?;
+
+    if let Err(e) = super::config_audit::record(source, old_value.as_ref(), &new_value) {
+        super::logger::log_warn(&format!("Failed to record config audit entry: {}", e));
+    }
+
+    // Saves made by the app itself are not reported as external edits
+    super::config_watcher::remember(config);
+    Ok(())
 }
@@ -0,0 +1,167 @@
+//! Configuration change audit log.
+//!
+//! Every config write records one row per changed setting with the old and new value, the
+//! time and what caused it (the command or background task that saved the config, or
+//! `external_edit` for changes picked up from the file). Secret values are masked.
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+
+use crate::modules::config_transfer;
+
+/// Oldest rows beyond this count are dropped
+const MAX_ENTRIES: i64 = 20_000;
+
+/// Source of changes detected in the config file
+pub const SOURCE_EXTERNAL_EDIT: &str = "external_edit";
+
+/// A changed setting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigAuditEntry {
+    pub id: i64,
+    pub timestamp: i64,
+    /// Command or task that wrote the config
+    pub source: String,
+    pub path: String,
+    /// None when the setting did not exist before
+    pub old_value: Option<Value>,
+    /// None when the setting was removed
+    pub new_value: Option<Value>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ConfigAuditQuery {
+    /// Setting path or section prefix, e.g. `proxy.port` or `proxy`
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Unix timestamp (seconds)
+    #[serde(default)]
+    pub since: Option<i64>,
+    #[serde(default)]
+    pub until: Option<i64>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: Option<usize>,
+}
+
+fn get_db_path() -> Result<PathBuf, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    Ok(data_dir.join("config_audit.db"))
+}
+
+fn connect_db() -> Result<Connection, String> {
+    let conn = Connection::open(get_db_path()?).map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "busy_timeout", 5000)
+        .map_err(|e| e.to_string())?;
+    Ok(conn)
+}
+
+/// Initialize the audit database
+pub fn init_db() -> Result<(), String> {
+    let conn = connect_db()?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS config_audit (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp INTEGER NOT NULL,
+            source TEXT NOT NULL,
+            path TEXT NOT NULL,
+            old_value TEXT,
+            new_value TEXT
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_config_audit_path ON config_audit (path, timestamp DESC)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Record the settings that differ between two serialized configs
+pub fn record(source: &str, old: Option<&Value>, new: &Value) -> Result<usize, String> {
+    let mut changes = Vec::new();
+    config_transfer::diff(old, Some(new), "", &mut changes);
+    config_transfer::mask_secrets(&mut changes);
+    if changes.is_empty() {
+        return Ok(0);
+    }
+
+    let encode = |v: &Option<Value>| v.as_ref().map(|v| v.to_string());
+    let timestamp = chrono::Utc::now().timestamp();
+    let mut conn = connect_db()?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for change in &changes {
+        tx.execute(
+            "INSERT INTO config_audit (timestamp, source, path, old_value, new_value)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                timestamp,
+                source,
+                change.path,
+                encode(&change.local),
+                encode(&change.imported)
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.execute(
+        "DELETE FROM config_audit WHERE id <= (SELECT MAX(id) FROM config_audit) - ?1",
+        params![MAX_ENTRIES],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(changes.len())
+}
+
+/// Query the audit log, newest first
+pub fn query(query: &ConfigAuditQuery) -> Result<Vec<ConfigAuditEntry>, String> {
+    let conn = connect_db()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, timestamp, source, path, old_value, new_value
+             FROM config_audit
+             WHERE (?1 IS NULL OR path = ?1 OR path LIKE ?1 || '.%')
+               AND (?2 IS NULL OR source = ?2)
+               AND (?3 IS NULL OR timestamp >= ?3)
+               AND (?4 IS NULL OR timestamp <= ?4)
+             ORDER BY timestamp DESC, id DESC
+             LIMIT ?5 OFFSET ?6",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let decode = |v: Option<String>| v.and_then(|s| serde_json::from_str(&s).ok());
+    let rows = stmt
+        .query_map(
+            params![
+                query.path,
+                query.source,
+                query.since,
+                query.until,
+                query.limit.unwrap_or(200) as i64,
+                query.offset.unwrap_or(0) as i64
+            ],
+            |row| {
+                Ok(ConfigAuditEntry {
+                    id: row.get(0)?,
+                    timestamp: row.get(1)?,
+                    source: row.get(2)?,
+                    path: row.get(3)?,
+                    old_value: decode(row.get(4)?),
+                    new_value: decode(row.get(5)?),
+                })
+            },
+        )
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
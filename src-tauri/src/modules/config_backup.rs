@@ -0,0 +1,138 @@
+//! Automatic backups of the configuration file.
+//!
+//! The current `gui_config.json` is copied into `config_backups/` before every write, so a
+//! truncated or broken config can be rolled back instead of starting over. Only the newest
+//! `config_backup_retention` backups are kept.
+
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::AppConfig;
+use crate::modules::{account, config_secrets, config_validation};
+
+const BACKUP_DIR: &str = "config_backups";
+const BACKUP_PREFIX: &str = "gui_config-";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigBackup {
+    pub file_name: String,
+    /// Unix timestamp (ms) of the backup
+    pub created_at: i64,
+    pub size: u64,
+    /// Whether the backup parses as a valid config
+    pub valid: bool,
+}
+
+fn backup_dir() -> Result<PathBuf, String> {
+    Ok(account::get_data_dir()?.join(BACKUP_DIR))
+}
+
+/// Backup files, newest first
+fn backup_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| {
+                    p.file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|n| n.starts_with(BACKUP_PREFIX) && n.ends_with(".json"))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    // Names embed a sortable timestamp
+    files.sort();
+    files.reverse();
+    files
+}
+
+/// Copy the current config file into the backup folder and prune old backups.
+/// Unparsable files and content identical to the newest backup are skipped.
+pub fn backup_current(config_path: &Path, retention: u32) -> Result<(), String> {
+    let Ok(content) = fs::read(config_path) else {
+        return Ok(());
+    };
+    if serde_json::from_slice::<serde_json::Value>(&content).is_err() {
+        return Ok(());
+    }
+
+    let dir = backup_dir()?;
+    fs::create_dir_all(&dir).map_err(|e| format!("failed_to_create_backup_dir: {}", e))?;
+    let existing = backup_files(&dir);
+    let unchanged = existing
+        .first()
+        .and_then(|newest| fs::read(newest).ok())
+        .is_some_and(|newest| newest == content);
+
+    if !unchanged {
+        let name = format!(
+            "{}{}.json",
+            BACKUP_PREFIX,
+            chrono::Local::now().format("%Y%m%d-%H%M%S-%3f")
+        );
+        fs::write(dir.join(name), &content)
+            .map_err(|e| format!("failed_to_write_backup: {}", e))?;
+    }
+
+    for old in backup_files(&dir)
+        .into_iter()
+        .skip(retention.max(1) as usize)
+    {
+        let _ = fs::remove_file(old);
+    }
+    Ok(())
+}
+
+/// Parse a backup the same way the config file is loaded
+fn parse_backup(path: &Path) -> Result<AppConfig, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("failed_to_read_backup: {}", e))?;
+    let mut value: serde_json::Value =
+        serde_json::from_str(&content).map_err(|e| format!("failed_to_parse_backup: {}", e))?;
+    // Secrets that can no longer be decrypted are simply left empty
+    if config_secrets::unseal(&mut value).is_err() {
+        if let Some(root) = value.as_object_mut() {
+            root.remove(config_secrets::SECRETS_KEY);
+        }
+    }
+    let (config, validation) = config_validation::validate_and_parse(value);
+    config.ok_or_else(|| format!("invalid_config: {}", validation.error_summary()))
+}
+
+/// Available backups, newest first
+pub fn list_backups() -> Result<Vec<ConfigBackup>, String> {
+    Ok(backup_files(&backup_dir()?)
+        .into_iter()
+        .filter_map(|path| {
+            let meta = fs::metadata(&path).ok()?;
+            let created_at = meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as i64)
+                .unwrap_or(0);
+            Some(ConfigBackup {
+                file_name: path.file_name()?.to_string_lossy().to_string(),
+                created_at,
+                size: meta.len(),
+                valid: parse_backup(&path).is_ok(),
+            })
+        })
+        .collect())
+}
+
+/// Restore a backup as the current config (the current file is backed up first)
+pub fn restore_backup(file_name: &str) -> Result<AppConfig, String> {
+    if file_name.contains(['/', '\\']) || !file_name.starts_with(BACKUP_PREFIX) {
+        return Err(format!("invalid_backup_name: {}", file_name));
+    }
+    let path = backup_dir()?.join(file_name);
+    if !path.exists() {
+        return Err(format!("backup_not_found: {}", file_name));
+    }
+    let config = parse_backup(&path)?;
+    crate::modules::config::save_app_config(&config, "restore_config_backup")?;
+    crate::modules::logger::log_info(&format!("Restored config from backup {}", file_name));
+    Ok(config)
+}
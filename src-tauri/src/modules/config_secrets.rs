@@ -0,0 +1,393 @@
+//! Encrypted secrets section of the configuration file.
+//!
+//! Secret settings (proxy API keys, upstream proxy URLs that may embed credentials) are
+//! moved out of the plain config into an AES-256-GCM encrypted `secrets` blob when the
+//! config is written, and restored when it is loaded. The key lives in a separate file
+//! of the data directory, so a shared copy of `gui_config.json` does not leak credentials.
+//! Single values stored elsewhere (per-account upstream proxies) use the same key through
+//! [`seal_value`] / [`open_value`].
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::{engine::general_purpose, Engine as _};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+/// Top-level key of the encrypted blob in the config file
+pub const SECRETS_KEY: &str = "secrets";
+
+/// Settings stored in the encrypted blob (dotted paths, `*` matches every array element)
+pub const SECRET_PATHS: &[&str] = &[
+    "proxy.api_key",
+    // Plaintext client API keys of older configs, migrated to hashes when loaded
+    "proxy.api_keys.*.key",
+    "proxy.zai.api_key",
+    "proxy.upstream_proxy.url",
+    "proxy.upstream_proxy.password",
+    "remote_sync.password",
+    "remote_sync.passphrase",
+    "oauth.device_client_secret",
+];
+
+const KEY_FILE: &str = "config.key";
+const BLOB_VERSION: u32 = 1;
+/// Prefix of single sealed values: `sealed:v1:<nonce>:<ciphertext>`
+const SEALED_VALUE_PREFIX: &str = "sealed:v1:";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SecretsBlob {
+    version: u32,
+    nonce: String,
+    ciphertext: String,
+}
+
+fn key_path() -> Result<PathBuf, String> {
+    Ok(crate::modules::account::get_data_dir()?.join(KEY_FILE))
+}
+
+fn read_key(path: &Path) -> Result<[u8; 32], String> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            "config_key_missing".to_string()
+        } else {
+            format!("failed_to_read_config_key: {}", e)
+        }
+    })?;
+    let bytes = general_purpose::STANDARD
+        .decode(content.trim())
+        .map_err(|e| format!("invalid_config_key: {}", e))?;
+    bytes
+        .try_into()
+        .map_err(|_| "invalid_config_key: wrong length".to_string())
+}
+
+/// Load the existing encryption key. Decryption never creates a key: a new key could not
+/// open the existing blob and would make the loss permanent on the next save.
+fn load_key() -> Result<[u8; 32], String> {
+    read_key(&key_path()?)
+}
+
+/// Load the encryption key, creating it on first use (only when encrypting)
+fn load_or_create_key() -> Result<[u8; 32], String> {
+    let path = key_path()?;
+    if path.exists() {
+        return read_key(&path);
+    }
+
+    let mut key = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut key);
+    std::fs::write(&path, general_purpose::STANDARD.encode(key))
+        .map_err(|e| format!("failed_to_write_config_key: {}", e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600));
+    }
+    Ok(key)
+}
+
+fn get_path_mut<'a>(value: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+    path.split('.').try_fold(value, |current, key| match current {
+        Value::Array(items) => items.get_mut(key.parse::<usize>().ok()?),
+        other => other.get_mut(key),
+    })
+}
+
+fn expand(value: &Value, prefix: &str, segments: &[&str], out: &mut Vec<String>) {
+    let Some((first, rest)) = segments.split_first() else {
+        out.push(prefix.to_string());
+        return;
+    };
+    let join = |key: &str| {
+        if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", prefix, key)
+        }
+    };
+    match (*first, value) {
+        ("*", Value::Array(items)) => {
+            for (index, item) in items.iter().enumerate() {
+                expand(item, &join(&index.to_string()), rest, out);
+            }
+        }
+        (key, Value::Object(map)) => {
+            if let Some(child) = map.get(key) {
+                expand(child, &join(key), rest, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Concrete secret paths present in a serialized config (array wildcards expanded)
+pub fn secret_paths(config: &Value) -> Vec<String> {
+    let mut out = Vec::new();
+    for pattern in SECRET_PATHS {
+        let segments: Vec<&str> = pattern.split('.').collect();
+        expand(config, "", &segments, &mut out);
+    }
+    out
+}
+
+/// Whether a concrete dotted path is a secret setting
+pub fn is_secret_path(path: &str) -> bool {
+    SECRET_PATHS.iter().any(|pattern| {
+        let mut actual = path.split('.');
+        let mut expected = pattern.split('.');
+        loop {
+            match (expected.next(), actual.next()) {
+                (None, None) => return true,
+                (Some("*"), Some(a)) if a.parse::<usize>().is_ok() => {}
+                (Some(e), Some(a)) if e == a => {}
+                _ => return false,
+            }
+        }
+    })
+}
+
+/// Move the secret settings of a serialized config into the encrypted blob
+pub fn seal(config: &mut Value) -> Result<(), String> {
+    if !has_secrets(config) {
+        return Ok(());
+    }
+    seal_with_key(config, &load_or_create_key()?)
+}
+
+fn has_secrets(config: &Value) -> bool {
+    secret_paths(config).iter().any(|path| {
+        config
+            .pointer(&format!("/{}", path.replace('.', "/")))
+            .and_then(|v| v.as_str())
+            .is_some_and(|s| !s.is_empty())
+    })
+}
+
+fn seal_with_key(config: &mut Value, key: &[u8; 32]) -> Result<(), String> {
+    let mut secrets = Map::new();
+    for path in secret_paths(config) {
+        if let Some(slot) = get_path_mut(config, &path) {
+            if slot.as_str().is_some_and(|s| !s.is_empty()) {
+                secrets.insert(path, std::mem::replace(slot, Value::String(String::new())));
+            }
+        }
+    }
+    if secrets.is_empty() {
+        return Ok(());
+    }
+
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| e.to_string())?;
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let plaintext = serde_json::to_vec(&secrets).map_err(|e| e.to_string())?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_ref())
+        .map_err(|_| "failed_to_encrypt_secrets".to_string())?;
+
+    let blob = SecretsBlob {
+        version: BLOB_VERSION,
+        nonce: general_purpose::STANDARD.encode(nonce),
+        ciphertext: general_purpose::STANDARD.encode(ciphertext),
+    };
+    if let Some(root) = config.as_object_mut() {
+        root.insert(
+            SECRETS_KEY.to_string(),
+            serde_json::to_value(blob).map_err(|e| e.to_string())?,
+        );
+    }
+    Ok(())
+}
+
+fn decrypt(blob: &SecretsBlob, key: &[u8; 32]) -> Result<Map<String, Value>, String> {
+    let plaintext = decrypt_bytes(blob, key)?;
+    serde_json::from_slice(&plaintext).map_err(|e| e.to_string())
+}
+
+fn decrypt_bytes(blob: &SecretsBlob, key: &[u8; 32]) -> Result<Vec<u8>, String> {
+    if blob.version != BLOB_VERSION {
+        return Err(format!("unsupported_secrets_version: {}", blob.version));
+    }
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| e.to_string())?;
+    let nonce = general_purpose::STANDARD
+        .decode(&blob.nonce)
+        .map_err(|e| format!("invalid_secrets_nonce: {}", e))?;
+    if nonce.len() != 12 {
+        return Err("invalid_secrets_nonce: wrong length".to_string());
+    }
+    let ciphertext = general_purpose::STANDARD
+        .decode(&blob.ciphertext)
+        .map_err(|e| format!("invalid_secrets_ciphertext: {}", e))?;
+    cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+        // Wrong key: the config was copied from another machine / data directory
+        .map_err(|_| "failed_to_decrypt_secrets".to_string())
+}
+
+/// Restore the secret settings from the encrypted blob (the blob itself is removed).
+/// When the blob cannot be decrypted it is left in place and an error is returned, so the
+/// caller can keep it instead of overwriting it with empty secrets.
+pub fn unseal(config: &mut Value) -> Result<(), String> {
+    if config.get(SECRETS_KEY).is_none() {
+        return Ok(());
+    }
+    unseal_with_key(config, load_key())
+}
+
+fn unseal_with_key(config: &mut Value, key: Result<[u8; 32], String>) -> Result<(), String> {
+    let Some(blob) = config.get(SECRETS_KEY) else {
+        return Ok(());
+    };
+    let blob: SecretsBlob = serde_json::from_value(blob.clone())
+        .map_err(|e| format!("invalid_secrets_blob: {}", e))?;
+    let secrets = decrypt(&blob, &key?)?;
+
+    if let Some(root) = config.as_object_mut() {
+        root.remove(SECRETS_KEY);
+    }
+    for (path, secret) in secrets {
+        // Only known paths are restored, the blob cannot inject arbitrary settings
+        if !is_secret_path(&path) {
+            continue;
+        }
+        if let Some(slot) = get_path_mut(config, &path) {
+            *slot = secret;
+        }
+    }
+    Ok(())
+}
+
+/// Encrypt a single value stored outside the config file. Empty values stay empty.
+pub fn seal_value(value: &str) -> Result<String, String> {
+    if value.is_empty() || value.starts_with(SEALED_VALUE_PREFIX) {
+        return Ok(value.to_string());
+    }
+    seal_value_with_key(value, &load_or_create_key()?)
+}
+
+fn seal_value_with_key(value: &str, key: &[u8; 32]) -> Result<String, String> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| e.to_string())?;
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), value.as_bytes())
+        .map_err(|_| "failed_to_encrypt_secrets".to_string())?;
+    Ok(format!(
+        "{}{}:{}",
+        SEALED_VALUE_PREFIX,
+        general_purpose::STANDARD.encode(nonce),
+        general_purpose::STANDARD.encode(ciphertext)
+    ))
+}
+
+/// Decrypt a value written by [`seal_value`]; plain values (older files) are returned as-is
+pub fn open_value(value: &str) -> Result<String, String> {
+    if !value.starts_with(SEALED_VALUE_PREFIX) {
+        return Ok(value.to_string());
+    }
+    open_value_with_key(value, &load_key()?)
+}
+
+fn open_value_with_key(value: &str, key: &[u8; 32]) -> Result<String, String> {
+    let Some((nonce, ciphertext)) = value
+        .strip_prefix(SEALED_VALUE_PREFIX)
+        .and_then(|rest| rest.split_once(':'))
+    else {
+        return Ok(value.to_string());
+    };
+    let blob = SecretsBlob {
+        version: BLOB_VERSION,
+        nonce: nonce.to_string(),
+        ciphertext: ciphertext.to_string(),
+    };
+    let plaintext = decrypt_bytes(&blob, key)?;
+    String::from_utf8(plaintext).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_path_mut() {
+        let mut value = serde_json::json!({"proxy": {"zai": {"api_key": "k"}}});
+        assert_eq!(
+            get_path_mut(&mut value, "proxy.zai.api_key").cloned(),
+            Some(Value::String("k".to_string()))
+        );
+        assert!(get_path_mut(&mut value, "proxy.upstream_proxy.url").is_none());
+    }
+
+    fn sample() -> Value {
+        serde_json::json!({
+            "proxy": {
+                "api_key": "sk-main",
+                "api_keys": [{"id": "a", "key": "sk-a"}, {"id": "b", "key": ""}],
+                "upstream_proxy": {"url": "http://proxy:8080", "password": "pw"}
+            },
+            "language": "en"
+        })
+    }
+
+    #[test]
+    fn test_seal_unseal_round_trip() {
+        let key = [7u8; 32];
+        let original = sample();
+        let mut value = original.clone();
+        seal_with_key(&mut value, &key).unwrap();
+
+        assert_eq!(value["proxy"]["api_key"], "");
+        assert_eq!(value["proxy"]["api_keys"][0]["key"], "");
+        assert_eq!(value["proxy"]["upstream_proxy"]["password"], "");
+        assert!(value.get(SECRETS_KEY).is_some());
+
+        unseal_with_key(&mut value, Ok(key)).unwrap();
+        assert_eq!(value, original);
+    }
+
+    #[test]
+    fn test_unseal_keeps_blob_without_key() {
+        let mut value = sample();
+        seal_with_key(&mut value, &[7u8; 32]).unwrap();
+        let sealed = value.clone();
+
+        let missing = read_key(Path::new("/nonexistent/antigravity/config.key"));
+        assert_eq!(missing, Err("config_key_missing".to_string()));
+        assert!(unseal_with_key(&mut value, missing).is_err());
+        assert_eq!(value, sealed);
+
+        // A different key must not restore anything either
+        assert!(unseal_with_key(&mut value, Ok([8u8; 32])).is_err());
+        assert_eq!(value, sealed);
+    }
+
+    #[test]
+    fn test_secret_path_patterns() {
+        assert!(is_secret_path("proxy.api_keys.3.key"));
+        assert!(!is_secret_path("proxy.api_keys.3.name"));
+        assert!(!is_secret_path("proxy.api_keys.x.key"));
+        assert!(is_secret_path("proxy.api_key"));
+        assert_eq!(
+            secret_paths(&sample())
+                .into_iter()
+                .filter(|p| p.starts_with("proxy.api_keys"))
+                .collect::<Vec<_>>(),
+            vec!["proxy.api_keys.0.key", "proxy.api_keys.1.key"]
+        );
+    }
+
+    #[test]
+    fn test_sealed_value_round_trip() {
+        let key = [9u8; 32];
+        let sealed = seal_value_with_key("socks5://user:pw@host:1080", &key).unwrap();
+        assert!(sealed.starts_with(SEALED_VALUE_PREFIX));
+        assert!(!sealed.contains("pw@"));
+        assert_eq!(
+            open_value_with_key(&sealed, &key).unwrap(),
+            "socks5://user:pw@host:1080"
+        );
+        assert!(open_value_with_key(&sealed, &[1u8; 32]).is_err());
+        assert_eq!(open_value("http://plain:8080").unwrap(), "http://plain:8080");
+    }
+}
@@ -0,0 +1,1046 @@
+//! Remote config sync.
+//!
+//! The configuration (and optionally the accounts) is kept as a single JSON document on a
+//! user-provided WebDAV server, S3 bucket or Git repository. Each sync compares the local
+//! content fingerprint and the remote revision with the ones recorded at the previous sync:
+//! if only one side changed it is pushed or pulled, if both changed the sync stops with a
+//! conflict until the user decides which side to keep. The sync section itself stays on the
+//! machine; secrets and accounts are only uploaded encrypted with the sync passphrase.
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+use crate::models::config::{RemoteSyncBackend, RemoteSyncConfig};
+use crate::models::{Account, AppConfig};
+use crate::modules::config_transfer::{self, ConfigChange};
+use crate::modules::{account, config, config_secrets, config_validation, logger};
+
+const SYNC_FORMAT: &str = "antigravity-tools-sync";
+const SYNC_VERSION: u32 = 1;
+const STATE_FILE: &str = "config_sync_state.json";
+const DEFAULT_REMOTE_PATH: &str = "antigravity-tools/sync.json";
+const PBKDF2_ROUNDS: u32 = 100_000;
+/// Config sections that are never synced (dotted paths)
+const LOCAL_ONLY_KEYS: &[&str] = &[
+    "remote_sync",
+    // Paths and startup settings of this machine
+    "default_export_path",
+    "antigravity_executable",
+    "antigravity_args",
+    "auto_launch",
+    "token_storage",
+    "oauth.browser",
+    "oauth.browser_profile",
+    "mitm.root_ca_path",
+    "mitm.root_ca_key_path",
+    // Listeners and certificates of the local proxy
+    "proxy.bind_address",
+    "proxy.extra_listeners",
+    "proxy.tls.cert_path",
+    "proxy.tls.key_path",
+    // Client API keys belong to the proxy of this machine
+    "proxy.api_keys",
+];
+
+/// Only one sync at a time (manual and scheduled)
+static SYNC_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SyncDocument {
+    format: String,
+    version: u32,
+    updated_at: i64,
+    device: String,
+    /// Fingerprint of the plain content, identical content means nothing to sync
+    fingerprint: String,
+    config: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sealed: Option<SealedPayload>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SealedPayload {
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Secrets and accounts, only uploaded encrypted
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SensitivePayload {
+    secrets: Map<String, Value>,
+    #[serde(default)]
+    accounts: Vec<Account>,
+}
+
+/// Persisted between syncs to tell local from remote changes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConfigSyncState {
+    /// Remote revision (ETag / commit) seen at the last sync
+    pub remote_revision: Option<String>,
+    /// Local content fingerprint right after the last sync
+    pub fingerprint: Option<String>,
+    pub last_sync: Option<i64>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncResolution {
+    /// Overwrite the remote document with the local config
+    KeepLocal,
+    /// Replace the local config with the remote document
+    KeepRemote,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncStatus {
+    UpToDate,
+    Pushed,
+    Pulled,
+    /// Both sides changed since the last sync; nothing was written
+    Conflict,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigSyncResult {
+    pub status: SyncStatus,
+    pub revision: Option<String>,
+    /// Differences local -> remote (pulled or conflicting settings)
+    pub changes: Vec<ConfigChange>,
+    pub accounts_added: usize,
+    pub remote_device: Option<String>,
+    pub remote_updated_at: Option<i64>,
+}
+
+impl ConfigSyncResult {
+    fn new(status: SyncStatus, revision: Option<String>) -> Self {
+        Self {
+            status,
+            revision,
+            changes: Vec::new(),
+            accounts_added: 0,
+            remote_device: None,
+            remote_updated_at: None,
+        }
+    }
+}
+
+// ============================================================================
+// Backends
+// ============================================================================
+
+enum StoreOutcome {
+    Stored(String),
+    /// The remote changed since `expected`
+    Conflict,
+}
+
+#[async_trait]
+trait SyncBackend: Send + Sync {
+    /// Current remote document and its revision, None when it does not exist yet
+    async fn fetch(&self) -> Result<Option<(Vec<u8>, String)>, String>;
+    /// Write the document if the remote is still at `expected` (None = must not exist)
+    async fn store(&self, data: &[u8], expected: Option<&str>) -> Result<StoreOutcome, String>;
+}
+
+fn remote_path(cfg: &RemoteSyncConfig) -> String {
+    let path = cfg.path.trim().trim_matches('/');
+    if path.is_empty() {
+        DEFAULT_REMOTE_PATH.to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+fn header(response: &reqwest::Response, name: &str) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string())
+}
+
+struct WebDavBackend {
+    client: reqwest::Client,
+    url: String,
+    username: String,
+    password: String,
+}
+
+impl WebDavBackend {
+    fn request(&self, method: reqwest::Method) -> reqwest::RequestBuilder {
+        let builder = self.client.request(method, &self.url);
+        if self.username.is_empty() {
+            builder
+        } else {
+            builder.basic_auth(&self.username, Some(&self.password))
+        }
+    }
+}
+
+#[async_trait]
+impl SyncBackend for WebDavBackend {
+    async fn fetch(&self) -> Result<Option<(Vec<u8>, String)>, String> {
+        let response = self
+            .request(reqwest::Method::GET)
+            .send()
+            .await
+            .map_err(|e| format!("webdav_request_failed: {}", e))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response
+            .error_for_status()
+            .map_err(|e| format!("webdav_request_failed: {}", e))?;
+        let etag = header(&response, "etag");
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| format!("webdav_request_failed: {}", e))?;
+        // Servers without ETags fall back to a content hash, compared by re-reading before writes
+        let revision = etag.unwrap_or_else(|| format!("sha256:{}", sha256_hex(&body)));
+        Ok(Some((body.to_vec(), revision)))
+    }
+
+    async fn store(&self, data: &[u8], expected: Option<&str>) -> Result<StoreOutcome, String> {
+        let mut request = self
+            .request(reqwest::Method::PUT)
+            .header("Content-Type", "application/json")
+            .body(data.to_vec());
+        match expected {
+            Some(rev) if rev.starts_with("sha256:") => {
+                let current = self.fetch().await?.map(|(_, r)| r);
+                if current.as_deref() != Some(rev) {
+                    return Ok(StoreOutcome::Conflict);
+                }
+            }
+            Some(rev) => request = request.header("If-Match", rev),
+            None => request = request.header("If-None-Match", "*"),
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("webdav_request_failed: {}", e))?;
+        match response.status() {
+            reqwest::StatusCode::PRECONDITION_FAILED => return Ok(StoreOutcome::Conflict),
+            reqwest::StatusCode::CONFLICT => {
+                return Err("webdav_parent_collection_missing".to_string())
+            }
+            status if !status.is_success() => {
+                return Err(format!("webdav_request_failed: {}", status))
+            }
+            _ => {}
+        }
+        if let Some(etag) = header(&response, "etag") {
+            return Ok(StoreOutcome::Stored(etag));
+        }
+        let revision = self
+            .fetch()
+            .await?
+            .map(|(_, r)| r)
+            .unwrap_or_else(|| format!("sha256:{}", sha256_hex(data)));
+        Ok(StoreOutcome::Stored(revision))
+    }
+}
+
+struct S3Backend {
+    client: reqwest::Client,
+    endpoint: String,
+    bucket: String,
+    key: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac =
+        <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// RFC 3986 encoding as required by SigV4 (slashes kept)
+fn uri_encode_path(path: &str) -> String {
+    path.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+impl S3Backend {
+    fn endpoint(&self) -> String {
+        if self.endpoint.is_empty() {
+            format!("https://s3.{}.amazonaws.com", self.region)
+        } else {
+            self.endpoint.trim_end_matches('/').to_string()
+        }
+    }
+
+    /// Path-style request signed with AWS Signature V4
+    fn signed(
+        &self,
+        method: reqwest::Method,
+        body: &[u8],
+    ) -> Result<reqwest::RequestBuilder, String> {
+        let canonical_uri = format!("/{}/{}", self.bucket, uri_encode_path(&self.key));
+        let url = url::Url::parse(&format!("{}{}", self.endpoint(), canonical_uri))
+            .map_err(|e| format!("invalid_s3_endpoint: {}", e))?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            _ => return Err("invalid_s3_endpoint: missing host".to_string()),
+        };
+
+        let now = chrono::Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = sha256_hex(body);
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method, canonical_uri, host, payload_hash, amz_date, signed_headers, payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            sha256_hex(canonical_request.as_bytes())
+        );
+
+        let mut key = hmac_sha256(
+            format!("AWS4{}", self.secret_key).as_bytes(),
+            date.as_bytes(),
+        );
+        for part in [self.region.as_str(), "s3", "aws4_request"] {
+            key = hmac_sha256(&key, part.as_bytes());
+        }
+        let signature: String = hmac_sha256(&key, string_to_sign.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+
+        Ok(self
+            .client
+            .request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header(
+                "Authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    self.access_key, scope, signed_headers, signature
+                ),
+            ))
+    }
+}
+
+#[async_trait]
+impl SyncBackend for S3Backend {
+    async fn fetch(&self) -> Result<Option<(Vec<u8>, String)>, String> {
+        let response = self
+            .signed(reqwest::Method::GET, b"")?
+            .send()
+            .await
+            .map_err(|e| format!("s3_request_failed: {}", e))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response
+            .error_for_status()
+            .map_err(|e| format!("s3_request_failed: {}", e))?;
+        let etag = header(&response, "etag");
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| format!("s3_request_failed: {}", e))?;
+        let revision = etag.unwrap_or_else(|| format!("sha256:{}", sha256_hex(&body)));
+        Ok(Some((body.to_vec(), revision)))
+    }
+
+    async fn store(&self, data: &[u8], expected: Option<&str>) -> Result<StoreOutcome, String> {
+        let mut request = self
+            .signed(reqwest::Method::PUT, data)?
+            .header("Content-Type", "application/json")
+            .body(data.to_vec());
+        request = match expected {
+            Some(rev) => request.header("If-Match", rev),
+            None => request.header("If-None-Match", "*"),
+        };
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("s3_request_failed: {}", e))?;
+        match response.status() {
+            // 409: a concurrent conditional write won
+            reqwest::StatusCode::PRECONDITION_FAILED | reqwest::StatusCode::CONFLICT => {
+                Ok(StoreOutcome::Conflict)
+            }
+            status if !status.is_success() => {
+                let body = response.text().await.unwrap_or_default();
+                Err(format!("s3_request_failed: {} {}", status, body))
+            }
+            _ => Ok(StoreOutcome::Stored(
+                header(&response, "etag").unwrap_or_else(|| format!("sha256:{}", sha256_hex(data))),
+            )),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct GitBackend {
+    repo_url: String,
+    branch: String,
+    file: String,
+    dir: PathBuf,
+}
+
+impl GitBackend {
+    fn git(&self, args: &[&str]) -> Result<String, String> {
+        let mut command = std::process::Command::new("git");
+        command.args(args).current_dir(&self.dir);
+        #[cfg(target_os = "windows")]
+        {
+            use std::os::windows::process::CommandExt;
+            command.creation_flags(0x08000000);
+        }
+        let output = command
+            .output()
+            .map_err(|e| format!("failed_to_run_git: {}", e))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Fetch the branch into the local work repo; None when the branch does not exist yet
+    fn fetch_head(&self) -> Result<Option<String>, String> {
+        if !self.dir.join(".git").exists() {
+            std::fs::create_dir_all(&self.dir)
+                .map_err(|e| format!("failed_to_create_sync_repo: {}", e))?;
+            self.git(&["init", "-q"])?;
+            self.git(&["remote", "add", "origin", &self.repo_url])?;
+        } else {
+            self.git(&["remote", "set-url", "origin", &self.repo_url])?;
+        }
+        match self.git(&["fetch", "-q", "origin", &self.branch]) {
+            Ok(_) => self.git(&["rev-parse", "FETCH_HEAD"]).map(Some),
+            Err(e) if e.contains("couldn't find remote ref") => Ok(None),
+            Err(e) => Err(format!("git_fetch_failed: {}", e)),
+        }
+    }
+
+    fn read_file(&self, revision: &str) -> Option<Vec<u8>> {
+        self.git(&["show", &format!("{}:{}", revision, self.file)])
+            .ok()
+            .map(|s| s.into_bytes())
+    }
+
+    fn fetch_blocking(&self) -> Result<Option<(Vec<u8>, String)>, String> {
+        Ok(self
+            .fetch_head()?
+            .and_then(|rev| self.read_file(&rev).map(|data| (data, rev))))
+    }
+
+    fn store_blocking(&self, data: &[u8], expected: Option<&str>) -> Result<StoreOutcome, String> {
+        let head = self.fetch_head()?;
+        let file_exists = head
+            .as_deref()
+            .is_some_and(|rev| self.read_file(rev).is_some());
+        let up_to_date = match expected {
+            Some(rev) => head.as_deref() == Some(rev),
+            None => !file_exists,
+        };
+        if !up_to_date {
+            return Ok(StoreOutcome::Conflict);
+        }
+
+        match &head {
+            Some(rev) => self.git(&["checkout", "-q", "-f", "-B", &self.branch, rev])?,
+            None => {
+                self.git(&[
+                    "symbolic-ref",
+                    "HEAD",
+                    &format!("refs/heads/{}", self.branch),
+                ])?;
+                self.git(&["rm", "-r", "-q", "--cached", "--ignore-unmatch", "."])?
+            }
+        };
+        let path = self.dir.join(&self.file);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("failed_to_write_file: {}", e))?;
+        }
+        std::fs::write(&path, data).map_err(|e| format!("failed_to_write_file: {}", e))?;
+        self.git(&["add", &self.file])?;
+        self.git(&[
+            "-c",
+            "user.name=Antigravity Tools",
+            "-c",
+            "user.email=antigravity-tools@localhost",
+            "commit",
+            "-q",
+            "-m",
+            &format!("Sync config from {}", device_name()),
+        ])?;
+
+        match self.git(&[
+            "push",
+            "-q",
+            "origin",
+            &format!("HEAD:refs/heads/{}", self.branch),
+        ]) {
+            Ok(_) => self.git(&["rev-parse", "HEAD"]).map(StoreOutcome::Stored),
+            Err(e) if e.contains("rejected") || e.contains("fetch first") => {
+                Ok(StoreOutcome::Conflict)
+            }
+            Err(e) => Err(format!("git_push_failed: {}", e)),
+        }
+    }
+}
+
+#[async_trait]
+impl SyncBackend for GitBackend {
+    async fn fetch(&self) -> Result<Option<(Vec<u8>, String)>, String> {
+        let backend = self.clone();
+        tokio::task::spawn_blocking(move || backend.fetch_blocking())
+            .await
+            .map_err(|e| e.to_string())?
+    }
+
+    async fn store(&self, data: &[u8], expected: Option<&str>) -> Result<StoreOutcome, String> {
+        let backend = self.clone();
+        let data = data.to_vec();
+        let expected = expected.map(|s| s.to_string());
+        tokio::task::spawn_blocking(move || backend.store_blocking(&data, expected.as_deref()))
+            .await
+            .map_err(|e| e.to_string())?
+    }
+}
+
+fn backend_for(cfg: &RemoteSyncConfig) -> Result<Box<dyn SyncBackend>, String> {
+    let client = crate::utils::http::get_long_client()?;
+    match cfg.backend {
+        RemoteSyncBackend::Webdav => {
+            if cfg.url.is_empty() {
+                return Err("config_sync_not_configured: url".to_string());
+            }
+            Ok(Box::new(WebDavBackend {
+                client,
+                url: cfg.url.clone(),
+                username: cfg.username.clone(),
+                password: cfg.password.clone(),
+            }))
+        }
+        RemoteSyncBackend::S3 => {
+            if cfg.bucket.is_empty() || cfg.username.is_empty() || cfg.password.is_empty() {
+                return Err("config_sync_not_configured: bucket and credentials".to_string());
+            }
+            Ok(Box::new(S3Backend {
+                client,
+                endpoint: cfg.url.clone(),
+                bucket: cfg.bucket.clone(),
+                key: remote_path(cfg),
+                region: if cfg.region.is_empty() {
+                    "us-east-1".to_string()
+                } else {
+                    cfg.region.clone()
+                },
+                access_key: cfg.username.clone(),
+                secret_key: cfg.password.clone(),
+            }))
+        }
+        RemoteSyncBackend::Git => {
+            if cfg.url.is_empty() {
+                return Err("config_sync_not_configured: url".to_string());
+            }
+            Ok(Box::new(GitBackend {
+                repo_url: cfg.url.clone(),
+                branch: if cfg.branch.is_empty() {
+                    "main".to_string()
+                } else {
+                    cfg.branch.clone()
+                },
+                file: remote_path(cfg),
+                dir: account::get_data_dir()?.join("config_sync_repo"),
+            }))
+        }
+    }
+}
+
+// ============================================================================
+// Content
+// ============================================================================
+
+fn device_name() -> String {
+    sysinfo::System::host_name().unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Sort object keys so fingerprints do not depend on map ordering
+fn canonical(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            Value::Object(
+                keys.into_iter()
+                    .map(|k| (k.clone(), canonical(&map[k])))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonical).collect()),
+        other => other.clone(),
+    }
+}
+
+fn fingerprint(config: &Value, sensitive: Option<&SensitivePayload>) -> String {
+    // Accounts are compared by identity and refresh token, access tokens rotate constantly
+    let (secrets, accounts) = match sensitive {
+        Some(s) => {
+            let mut accounts: Vec<String> = s
+                .accounts
+                .iter()
+                .map(|a| {
+                    format!(
+                        "{}:{}",
+                        a.email,
+                        sha256_hex(a.token.refresh_token.as_bytes())
+                    )
+                })
+                .collect();
+            accounts.sort();
+            (Value::Object(s.secrets.clone()), accounts)
+        }
+        None => (Value::Null, Vec::new()),
+    };
+    let content = serde_json::json!({
+        "config": canonical(config),
+        "secrets": canonical(&secrets),
+        "accounts": accounts,
+    });
+    sha256_hex(content.to_string().as_bytes())
+}
+
+fn parent_and_key<'a>(
+    value: &'a mut Value,
+    path: &'a str,
+) -> Option<(&'a mut Map<String, Value>, &'a str)> {
+    let (parent, key) = match path.rsplit_once('.') {
+        Some((parent, key)) => (config_transfer::pointer_mut(value, parent)?, key),
+        None => (value, path),
+    };
+    Some((parent.as_object_mut()?, key))
+}
+
+fn remove_path(value: &mut Value, path: &str) {
+    if let Some((parent, key)) = parent_and_key(value, path) {
+        parent.remove(key);
+    }
+}
+
+fn set_path(value: &mut Value, path: &str, section: Value) {
+    if let Some((parent, key)) = parent_and_key(value, path) {
+        parent.insert(key.to_string(), section);
+    }
+}
+
+/// Local config without machine-local sections and secrets, plus the sensitive payload
+/// when a passphrase is set
+fn local_content(cfg: &RemoteSyncConfig) -> Result<(Value, Option<SensitivePayload>), String> {
+    let mut value = serde_json::to_value(config::load_app_config()?)
+        .map_err(|e| format!("failed_to_serialize_config: {}", e))?;
+    for path in LOCAL_ONLY_KEYS {
+        remove_path(&mut value, path);
+    }
+
+    let mut secrets = Map::new();
+    for path in config_secrets::secret_paths(&value) {
+        if let Some(slot) = config_transfer::pointer_mut(&mut value, &path) {
+            if slot.as_str().is_some_and(|s| !s.is_empty()) {
+                secrets.insert(path, std::mem::replace(slot, Value::String(String::new())));
+            }
+        }
+    }
+
+    if cfg.passphrase.is_empty() {
+        return Ok((value, None));
+    }
+    let accounts = if cfg.include_accounts {
+        account::list_accounts()?
+    } else {
+        Vec::new()
+    };
+    Ok((value, Some(SensitivePayload { secrets, accounts })))
+}
+
+/// PBKDF2-HMAC-SHA256, 32-byte key
+fn derive_key(passphrase: &str, salt: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+    key
+}
+
+fn seal(payload: &SensitivePayload, passphrase: &str) -> Result<SealedPayload, String> {
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let cipher =
+        Aes256Gcm::new_from_slice(&derive_key(passphrase, &salt)).map_err(|e| e.to_string())?;
+    let plaintext = serde_json::to_vec(payload).map_err(|e| e.to_string())?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_ref())
+        .map_err(|_| "failed_to_encrypt_sync_payload".to_string())?;
+    Ok(SealedPayload {
+        salt: general_purpose::STANDARD.encode(salt),
+        nonce: general_purpose::STANDARD.encode(nonce),
+        ciphertext: general_purpose::STANDARD.encode(ciphertext),
+    })
+}
+
+fn open(sealed: &SealedPayload, passphrase: &str) -> Result<SensitivePayload, String> {
+    let decode = |s: &str| {
+        general_purpose::STANDARD
+            .decode(s)
+            .map_err(|e| format!("invalid_sync_payload: {}", e))
+    };
+    let salt = decode(&sealed.salt)?;
+    let nonce = decode(&sealed.nonce)?;
+    if nonce.len() != 12 {
+        return Err("invalid_sync_payload: wrong nonce length".to_string());
+    }
+    let cipher =
+        Aes256Gcm::new_from_slice(&derive_key(passphrase, &salt)).map_err(|e| e.to_string())?;
+    let plaintext = cipher
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            decode(&sealed.ciphertext)?.as_ref(),
+        )
+        .map_err(|_| "config_sync_wrong_passphrase".to_string())?;
+    serde_json::from_slice(&plaintext).map_err(|e| format!("invalid_sync_payload: {}", e))
+}
+
+fn parse_document(data: &[u8]) -> Result<SyncDocument, String> {
+    let doc: SyncDocument =
+        serde_json::from_slice(data).map_err(|e| format!("invalid_sync_document: {}", e))?;
+    if doc.format != SYNC_FORMAT {
+        return Err(format!(
+            "invalid_sync_document: unknown format {}",
+            doc.format
+        ));
+    }
+    if doc.version > SYNC_VERSION {
+        return Err(format!("unsupported_sync_version: {}", doc.version));
+    }
+    Ok(doc)
+}
+
+/// Settings that differ between the local and the remote config (secrets masked)
+fn changes(local: &Value, remote: &Value) -> Vec<ConfigChange> {
+    let mut changes = Vec::new();
+    config_transfer::diff(Some(local), Some(remote), "", &mut changes);
+    config_transfer::mask_secrets(&mut changes);
+    changes
+}
+
+/// Write the remote document into the local config. Returns the saved config and the
+/// number of accounts added.
+fn apply_remote(doc: &SyncDocument, cfg: &RemoteSyncConfig) -> Result<(AppConfig, usize), String> {
+    let local = serde_json::to_value(config::load_app_config()?)
+        .map_err(|e| format!("failed_to_serialize_config: {}", e))?;
+    let sensitive = match (&doc.sealed, cfg.passphrase.is_empty()) {
+        (Some(sealed), false) => Some(open(sealed, &cfg.passphrase)?),
+        _ => None,
+    };
+
+    let mut merged = doc.config.clone();
+    for path in LOCAL_ONLY_KEYS {
+        remove_path(&mut merged, path);
+        if let Some(section) = config_transfer::pointer(&local, path) {
+            set_path(&mut merged, path, section.clone());
+        }
+    }
+    // Secrets come from the encrypted payload, otherwise the local ones are kept
+    for path in config_secrets::secret_paths(&merged) {
+        let value = sensitive
+            .as_ref()
+            .and_then(|s| s.secrets.get(&path))
+            .or_else(|| config_transfer::pointer(&local, &path));
+        if let (Some(slot), Some(value)) = (config_transfer::pointer_mut(&mut merged, &path), value)
+        {
+            *slot = value.clone();
+        }
+    }
+
+    let (parsed, validation) = config_validation::validate_and_parse(merged);
+    let parsed = parsed.ok_or_else(|| format!("invalid_config: {}", validation.error_summary()))?;
+    config::save_app_config(&parsed, "sync_config")?;
+
+    // Accounts are only added; tokens of existing accounts refresh on each machine
+    let mut added = 0;
+    if let Some(sensitive) = sensitive.filter(|_| cfg.include_accounts) {
+        let existing: Vec<String> = account::list_accounts()?
+            .into_iter()
+            .map(|a| a.email)
+            .collect();
+        for remote in sensitive.accounts {
+            if existing.contains(&remote.email) {
+                continue;
+            }
+            match account::upsert_account(remote.email.clone(), remote.name.clone(), remote.token) {
+                Ok(_) => added += 1,
+                Err(e) => logger::log_warn(&format!(
+                    "[ConfigSync] Failed to add synced account {}: {}",
+                    remote.email, e
+                )),
+            }
+        }
+    }
+    Ok((parsed, added))
+}
+
+// ============================================================================
+// Sync
+// ============================================================================
+
+fn state_path() -> Result<PathBuf, String> {
+    Ok(account::get_data_dir()?.join(STATE_FILE))
+}
+
+pub fn load_state() -> ConfigSyncState {
+    state_path()
+        .ok()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(state: &ConfigSyncState) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
+    std::fs::write(state_path()?, content).map_err(|e| format!("failed_to_save_sync_state: {}", e))
+}
+
+/// Whether an automatic sync is due
+pub fn is_due() -> bool {
+    let Ok(config) = config::load_app_config() else {
+        return false;
+    };
+    let cfg = config.remote_sync;
+    if !cfg.enabled || cfg.interval_minutes == 0 {
+        return false;
+    }
+    let last = load_state().last_sync.unwrap_or(0);
+    chrono::Utc::now().timestamp() - last >= i64::from(cfg.interval_minutes) * 60
+}
+
+/// Run a sync. Returns the config written locally when the remote was pulled.
+pub async fn sync(
+    resolve: Option<SyncResolution>,
+) -> Result<(ConfigSyncResult, Option<AppConfig>), String> {
+    let _guard = SYNC_LOCK.lock().await;
+    let mut state = load_state();
+    let result = run(&mut state, resolve).await;
+    match &result {
+        Ok((r, _)) if r.status != SyncStatus::Conflict => {
+            state.last_sync = Some(chrono::Utc::now().timestamp());
+            state.last_error = None;
+        }
+        Ok(_) => state.last_error = Some("conflict".to_string()),
+        Err(e) => state.last_error = Some(e.clone()),
+    }
+    if let Err(e) = save_state(&state) {
+        logger::log_warn(&format!("[ConfigSync] {}", e));
+    }
+    result
+}
+
+async fn run(
+    state: &mut ConfigSyncState,
+    resolve: Option<SyncResolution>,
+) -> Result<(ConfigSyncResult, Option<AppConfig>), String> {
+    let cfg = config::load_app_config()?.remote_sync;
+    if cfg.include_accounts && cfg.passphrase.is_empty() {
+        return Err("config_sync_passphrase_required".to_string());
+    }
+    let backend = backend_for(&cfg)?;
+
+    let (local_config, sensitive) = local_content(&cfg)?;
+    let local_fp = fingerprint(&local_config, sensitive.as_ref());
+    let remote = match backend.fetch().await? {
+        Some((data, revision)) => Some((parse_document(&data)?, revision)),
+        None => None,
+    };
+
+    let action = match (&remote, resolve) {
+        (None, _) => Some(SyncResolution::KeepLocal),
+        (Some(_), Some(resolution)) => Some(resolution),
+        (Some((doc, revision)), None) => {
+            let local_changed = state.fingerprint.as_deref() != Some(local_fp.as_str());
+            let remote_changed = state.remote_revision.as_deref() != Some(revision.as_str());
+            if doc.fingerprint == local_fp {
+                None
+            } else {
+                match (local_changed, remote_changed) {
+                    (false, false) => None,
+                    (true, false) => Some(SyncResolution::KeepLocal),
+                    (false, true) => Some(SyncResolution::KeepRemote),
+                    (true, true) => {
+                        let mut result =
+                            ConfigSyncResult::new(SyncStatus::Conflict, Some(revision.clone()));
+                        result.changes = changes(&local_config, &doc.config);
+                        result.remote_device = Some(doc.device.clone());
+                        result.remote_updated_at = Some(doc.updated_at);
+                        logger::log_warn(&format!(
+                            "[ConfigSync] Conflict: both local and remote ({}) changed",
+                            doc.device
+                        ));
+                        return Ok((result, None));
+                    }
+                }
+            }
+        }
+    };
+
+    match (action, remote) {
+        (None, remote) => {
+            let revision = remote.map(|(_, r)| r);
+            state.remote_revision = revision.clone();
+            state.fingerprint = Some(local_fp);
+            Ok((ConfigSyncResult::new(SyncStatus::UpToDate, revision), None))
+        }
+        (Some(SyncResolution::KeepRemote), Some((doc, revision))) => {
+            let (applied, accounts_added) = apply_remote(&doc, &cfg)?;
+            // Record the local state after the pull so it does not count as a local change
+            let (config_after, sensitive_after) = local_content(&cfg)?;
+            state.remote_revision = Some(revision.clone());
+            state.fingerprint = Some(fingerprint(&config_after, sensitive_after.as_ref()));
+
+            let mut result = ConfigSyncResult::new(SyncStatus::Pulled, Some(revision));
+            result.changes = changes(&local_config, &doc.config);
+            result.accounts_added = accounts_added;
+            result.remote_device = Some(doc.device);
+            result.remote_updated_at = Some(doc.updated_at);
+            logger::log_info(&format!(
+                "[ConfigSync] Pulled config ({} change(s), {} account(s) added)",
+                result.changes.len(),
+                accounts_added
+            ));
+            Ok((result, Some(applied)))
+        }
+        (Some(_), remote) => {
+            let expected = remote.as_ref().map(|(_, r)| r.clone());
+            let document = SyncDocument {
+                format: SYNC_FORMAT.to_string(),
+                version: SYNC_VERSION,
+                updated_at: chrono::Utc::now().timestamp(),
+                device: device_name(),
+                fingerprint: local_fp.clone(),
+                config: local_config,
+                sealed: sensitive
+                    .as_ref()
+                    .map(|s| seal(s, &cfg.passphrase))
+                    .transpose()?,
+            };
+            let data = serde_json::to_vec_pretty(&document).map_err(|e| e.to_string())?;
+
+            match backend.store(&data, expected.as_deref()).await? {
+                StoreOutcome::Stored(revision) => {
+                    state.remote_revision = Some(revision.clone());
+                    state.fingerprint = Some(local_fp);
+                    logger::log_info("[ConfigSync] Pushed local config");
+                    Ok((
+                        ConfigSyncResult::new(SyncStatus::Pushed, Some(revision)),
+                        None,
+                    ))
+                }
+                StoreOutcome::Conflict => {
+                    logger::log_warn("[ConfigSync] Remote changed during push");
+                    Ok((ConfigSyncResult::new(SyncStatus::Conflict, expected), None))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231 test case 2
+        let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(
+            mac.iter().map(|b| format!("{:02x}", b)).collect::<String>(),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_seal_roundtrip_and_fingerprint() {
+        let mut secrets = Map::new();
+        secrets.insert(
+            "proxy.api_key".to_string(),
+            Value::String("sk-test".to_string()),
+        );
+        let payload = SensitivePayload {
+            secrets,
+            accounts: Vec::new(),
+        };
+
+        let sealed = seal(&payload, "correct horse").unwrap();
+        assert_eq!(
+            open(&sealed, "correct horse").unwrap().secrets["proxy.api_key"],
+            "sk-test"
+        );
+        assert!(open(&sealed, "wrong").is_err());
+
+        let a = serde_json::json!({"b": 1, "a": {"y": 2, "x": 3}});
+        let b = serde_json::json!({"a": {"x": 3, "y": 2}, "b": 1});
+        assert_eq!(fingerprint(&a, None), fingerprint(&b, None));
+        assert_ne!(fingerprint(&a, None), fingerprint(&a, Some(&payload)));
+    }
+
+    #[test]
+    fn test_local_only_keys_are_not_synced() {
+        let mut value = serde_json::json!({
+            "language": "en",
+            "antigravity_executable": "/opt/antigravity",
+            "proxy": {
+                "port": 8045,
+                "extra_listeners": ["0.0.0.0:9000"],
+                "tls": {"enabled": true, "cert_path": "/etc/cert.pem"},
+            },
+        });
+        for path in LOCAL_ONLY_KEYS {
+            remove_path(&mut value, path);
+        }
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "language": "en",
+                "proxy": {"port": 8045, "tls": {"enabled": true}},
+            })
+        );
+    }
+}
@@ -0,0 +1,329 @@
+//! Configuration export / import.
+//!
+//! Exports wrap the config in a small envelope. Imports are merged into the local config with
+//! one of three strategies and can be previewed as a dry run listing every setting that would
+//! change. Secrets are left out of exports unless explicitly requested; an import without
+//! secrets never clears the local ones.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::models::AppConfig;
+use crate::modules::{config, config_secrets, config_validation};
+
+const EXPORT_FORMAT: &str = "antigravity-tools-config";
+const EXPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStrategy {
+    /// Replace the local config with the imported one
+    Overwrite,
+    /// Only add settings that do not exist locally (e.g. new model mappings)
+    MergeKeepLocal,
+    /// Merge, imported values win on conflicts
+    MergePreferImport,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ConfigExport {
+    format: String,
+    version: u32,
+    exported_at: i64,
+    app_version: String,
+    config: Value,
+}
+
+/// A setting changed by an import
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigChange {
+    pub path: String,
+    /// None when the setting does not exist locally
+    pub local: Option<Value>,
+    /// None when the import removes the setting
+    pub imported: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigImportResult {
+    pub strategy: ImportStrategy,
+    pub dry_run: bool,
+    pub changes: Vec<ConfigChange>,
+    pub validation: config_validation::ConfigValidation,
+    /// Whether the merged config was saved
+    pub applied: bool,
+}
+
+fn is_secret(path: &str) -> bool {
+    config_secrets::is_secret_path(path)
+}
+
+fn strip_secrets(value: &mut Value) {
+    for path in config_secrets::secret_paths(value) {
+        if let Some(slot) = pointer_mut(value, &path) {
+            *slot = Value::String(String::new());
+        }
+    }
+}
+
+fn join(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+/// Export the current configuration to a file
+pub fn export_config(file_path: &str, include_secrets: bool) -> Result<(), String> {
+    let mut value = serde_json::to_value(config::load_app_config()?)
+        .map_err(|e| format!("failed_to_serialize_config: {}", e))?;
+    if !include_secrets {
+        strip_secrets(&mut value);
+    }
+
+    let export = ConfigExport {
+        format: EXPORT_FORMAT.to_string(),
+        version: EXPORT_VERSION,
+        exported_at: chrono::Utc::now().timestamp(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        config: value,
+    };
+    let content = serde_json::to_string_pretty(&export)
+        .map_err(|e| format!("failed_to_serialize_config: {}", e))?;
+    std::fs::write(file_path, content).map_err(|e| format!("failed_to_write_file: {}", e))
+}
+
+/// Read an export file (a plain `gui_config.json` is accepted as well)
+fn read_import(file_path: &str) -> Result<Value, String> {
+    let content =
+        std::fs::read_to_string(file_path).map_err(|e| format!("failed_to_read_file: {}", e))?;
+    let value: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("failed_to_parse_import_file: {}", e))?;
+
+    let mut imported = match value.get("format").and_then(|f| f.as_str()) {
+        Some(EXPORT_FORMAT) => {
+            let export: ConfigExport = serde_json::from_value(value)
+                .map_err(|e| format!("failed_to_parse_import_file: {}", e))?;
+            if export.version > EXPORT_VERSION {
+                return Err(format!("unsupported_export_version: {}", export.version));
+            }
+            export.config
+        }
+        _ => value,
+    };
+    if !imported.is_object() {
+        return Err("failed_to_parse_import_file: config is not an object".to_string());
+    }
+
+    // A raw config file from this machine still carries the encrypted section
+    if config_secrets::unseal(&mut imported).is_err() {
+        if let Some(root) = imported.as_object_mut() {
+            root.remove(config_secrets::SECRETS_KEY);
+        }
+    }
+    Ok(imported)
+}
+
+/// Deep merge `incoming` into `base`; `prefer_incoming` decides conflicts
+fn merge(base: &mut Value, incoming: &Value, prefer_incoming: bool, prefix: &str) {
+    match (base, incoming) {
+        (Value::Object(local), Value::Object(other)) => {
+            for (key, value) in other {
+                let path = join(prefix, key);
+                match local.get_mut(key) {
+                    Some(existing) => merge(existing, value, prefer_incoming, &path),
+                    None => {
+                        local.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (slot, value) => {
+            // Empty imported secrets mean "not exported", never "clear"
+            let empty_secret = is_secret(prefix) && value.as_str() == Some("");
+            if prefer_incoming && !empty_secret {
+                *slot = value.clone();
+            }
+        }
+    }
+}
+
+/// Keep local secrets wherever the import left them out
+fn restore_local_secrets(merged: &mut Value, imported: &Value, local: &Value) {
+    for path in config_secrets::secret_paths(merged) {
+        let imported_secret = pointer(imported, &path).and_then(|v| v.as_str());
+        if imported_secret.map_or(true, |s| s.is_empty()) {
+            if let (Some(slot), Some(value)) = (pointer_mut(merged, &path), pointer(local, &path)) {
+                *slot = value.clone();
+            }
+        }
+    }
+}
+
+/// Leaf-level differences between two configs (arrays are compared as a whole)
+pub(crate) fn diff(local: Option<&Value>, imported: Option<&Value>, path: &str, out: &mut Vec<ConfigChange>) {
+    match (local, imported) {
+        (Some(Value::Object(a)), Some(Value::Object(b))) => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                diff(a.get(key), b.get(key), &join(path, key), out);
+            }
+        }
+        (a, b) if a != b => out.push(ConfigChange {
+            path: path.to_string(),
+            local: a.cloned(),
+            imported: b.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+fn mask_value(value: &mut Value, path: &str) {
+    match value {
+        Value::String(s) if is_secret(path) && !s.is_empty() => *s = "********".to_string(),
+        Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                mask_value(child, &join(path, key));
+            }
+        }
+        // Arrays are diffed as a whole, secrets inside them (API keys) are masked per element
+        Value::Array(items) => {
+            for (index, child) in items.iter_mut().enumerate() {
+                mask_value(child, &join(path, &index.to_string()));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Mask secret values in a change list
+pub(crate) fn mask_secrets(changes: &mut [ConfigChange]) {
+    for change in changes.iter_mut() {
+        for value in [&mut change.local, &mut change.imported].into_iter().flatten() {
+            mask_value(value, &change.path);
+        }
+    }
+}
+
+/// Merge an import into the local config. With `dry_run` only the diff is returned.
+/// Returns the merged config when it was saved.
+pub fn import_config(
+    file_path: &str,
+    strategy: ImportStrategy,
+    dry_run: bool,
+) -> Result<(ConfigImportResult, Option<AppConfig>), String> {
+    let imported = read_import(file_path)?;
+    let local = serde_json::to_value(config::load_app_config()?)
+        .map_err(|e| format!("failed_to_serialize_config: {}", e))?;
+
+    let merged = match strategy {
+        ImportStrategy::Overwrite => {
+            // Start from the defaults so settings missing in the import are reset,
+            // but keep local secrets the export left out
+            let mut merged = serde_json::to_value(AppConfig::new()).map_err(|e| e.to_string())?;
+            merge(&mut merged, &imported, true, "");
+            restore_local_secrets(&mut merged, &imported, &local);
+            merged
+        }
+        ImportStrategy::MergeKeepLocal => {
+            let mut merged = local.clone();
+            merge(&mut merged, &imported, false, "");
+            merged
+        }
+        ImportStrategy::MergePreferImport => {
+            let mut merged = local.clone();
+            merge(&mut merged, &imported, true, "");
+            // Arrays are replaced as a whole, secrets inside them are restored by position
+            restore_local_secrets(&mut merged, &imported, &local);
+            merged
+        }
+    };
+
+    let mut changes = Vec::new();
+    diff(Some(&local), Some(&merged), "", &mut changes);
+    mask_secrets(&mut changes);
+
+    let (parsed, validation) = config_validation::validate_and_parse(merged);
+    let mut result = ConfigImportResult {
+        strategy,
+        dry_run,
+        changes,
+        validation,
+        applied: false,
+    };
+
+    let Some(parsed) = parsed else {
+        return Ok((result, None));
+    };
+    if dry_run || result.changes.is_empty() {
+        return Ok((result, None));
+    }
+
+    config::save_app_config(&parsed, "import_config")?;
+    result.applied = true;
+    crate::modules::logger::log_info(&format!(
+        "Imported config from {} ({:?}, {} change(s))",
+        file_path,
+        strategy,
+        result.changes.len()
+    ));
+    Ok((result, Some(parsed)))
+}
+
+fn json_pointer(path: &str) -> String {
+    format!("/{}", path.replace('.', "/"))
+}
+
+pub(crate) fn pointer<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    value.pointer(&json_pointer(path))
+}
+
+pub(crate) fn pointer_mut<'a>(value: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+    value.pointer_mut(&json_pointer(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_strategies() {
+        let local = serde_json::json!({
+            "language": "zh",
+            "proxy": {"api_key": "sk-local", "custom_mapping": {"a": "1"}}
+        });
+        let imported = serde_json::json!({
+            "language": "en",
+            "proxy": {"api_key": "", "custom_mapping": {"a": "2", "b": "3"}}
+        });
+
+        let mut keep = local.clone();
+        merge(&mut keep, &imported, false, "");
+        assert_eq!(keep["language"], "zh");
+        assert_eq!(
+            keep["proxy"]["custom_mapping"],
+            serde_json::json!({"a": "1", "b": "3"})
+        );
+
+        let mut prefer = local.clone();
+        merge(&mut prefer, &imported, true, "");
+        assert_eq!(prefer["language"], "en");
+        assert_eq!(prefer["proxy"]["api_key"], "sk-local");
+        assert_eq!(prefer["proxy"]["custom_mapping"]["a"], "2");
+
+        let mut changes = Vec::new();
+        diff(Some(&local), Some(&prefer), "", &mut changes);
+        let paths: Vec<&str> = changes.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "language",
+                "proxy.custom_mapping.a",
+                "proxy.custom_mapping.b"
+            ]
+        );
+    }
+}
@@ -0,0 +1,356 @@
+//! Structural validation of the configuration file.
+//!
+//! The expected shape is derived from `AppConfig::new()`: every key present in the default
+//! config is known, objects that are empty by default (model mappings, capability overrides)
+//! accept arbitrary keys. Type errors are located with `serde_path_to_error`, so users get
+//! `proxy.port: invalid type ...` instead of a bare serde message.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::models::AppConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigIssueSeverity {
+    /// The config cannot be loaded / saved as is
+    Error,
+    /// Ignored by the app, most likely a typo or a setting from another version
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigIssue {
+    /// Dotted path of the offending setting, e.g. "proxy.port" (empty for the root)
+    pub path: String,
+    /// Machine readable code: "unknown_key" | "wrong_type" | "invalid_value" | "invalid_path"
+    pub code: String,
+    pub severity: ConfigIssueSeverity,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConfigValidation {
+    pub valid: bool,
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl ConfigValidation {
+    pub fn errors(&self) -> impl Iterator<Item = &ConfigIssue> {
+        self.issues
+            .iter()
+            .filter(|i| i.severity == ConfigIssueSeverity::Error)
+    }
+
+    /// One-line summary of the errors, used as the load error message
+    pub fn error_summary(&self) -> String {
+        self.errors()
+            .map(|i| format!("{}: {}", display_path(&i.path), i.message))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+/// Default config as JSON, used as the schema
+static SCHEMA: Lazy<Value> =
+    Lazy::new(|| serde_json::to_value(AppConfig::new()).unwrap_or(Value::Null));
+
+fn display_path(path: &str) -> &str {
+    if path.is_empty() {
+        "(root)"
+    } else {
+        path
+    }
+}
+
+fn kind_of(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn issue(path: &str, code: &str, severity: ConfigIssueSeverity, message: String) -> ConfigIssue {
+    ConfigIssue {
+        path: path.to_string(),
+        code: code.to_string(),
+        severity,
+        message,
+    }
+}
+
+/// Compare a value with the schema: unknown keys and JSON type mismatches
+fn check_shape(schema: &Value, value: &Value, path: &str, issues: &mut Vec<ConfigIssue>) {
+    // Optional settings (null by default) are left to serde
+    if schema.is_null() || value.is_null() {
+        return;
+    }
+    if kind_of(schema) != kind_of(value) {
+        issues.push(issue(
+            path,
+            "wrong_type",
+            ConfigIssueSeverity::Error,
+            format!("expected {}, found {}", kind_of(schema), kind_of(value)),
+        ));
+        return;
+    }
+
+    let (Value::Object(known), Value::Object(actual)) = (schema, value) else {
+        return;
+    };
+    // Free-form maps
+    if known.is_empty() {
+        return;
+    }
+    for (key, child) in actual {
+        let child_path = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", path, key)
+        };
+        match known.get(key) {
+            Some(child_schema) => check_shape(child_schema, child, &child_path, issues),
+            None => issues.push(issue(
+                &child_path,
+                "unknown_key",
+                ConfigIssueSeverity::Warning,
+                "unknown setting, it will be ignored".to_string(),
+            )),
+        }
+    }
+}
+
+/// Checks that need the parsed config
+fn check_semantics(config: &AppConfig, issues: &mut Vec<ConfigIssue>) {
+    if let Some(path) = config
+        .antigravity_executable
+        .as_deref()
+        .filter(|p| !p.is_empty())
+    {
+        if !std::path::Path::new(path).exists() {
+            issues.push(issue(
+                "antigravity_executable",
+                "invalid_path",
+                ConfigIssueSeverity::Error,
+                format!("file does not exist: {}", path),
+            ));
+        }
+    }
+    if let Some(path) = config
+        .default_export_path
+        .as_deref()
+        .filter(|p| !p.is_empty())
+    {
+        if !std::path::Path::new(path).is_dir() {
+            issues.push(issue(
+                "default_export_path",
+                "invalid_path",
+                ConfigIssueSeverity::Error,
+                format!("directory does not exist: {}", path),
+            ));
+        }
+    }
+    if config.proxy.port == 0 {
+        issues.push(issue(
+            "proxy.port",
+            "invalid_value",
+            ConfigIssueSeverity::Error,
+            "port must be between 1 and 65535".to_string(),
+        ));
+    }
+    if config.oauth.callback_port_end != 0
+        && config.oauth.callback_port_end < config.oauth.callback_port_start
+    {
+        issues.push(issue(
+            "oauth.callback_port_end",
+            "invalid_value",
+            ConfigIssueSeverity::Warning,
+            "end of the callback port range is below its start, only the start port is used"
+                .to_string(),
+        ));
+    }
+    if config.scheduler_jitter_secs > super::scheduler::MAX_JITTER_SECS {
+        issues.push(issue(
+            "scheduler_jitter_secs",
+            "invalid_value",
+            ConfigIssueSeverity::Warning,
+            format!(
+                "jitter above {}s is clamped to {}s",
+                super::scheduler::MAX_JITTER_SECS,
+                super::scheduler::MAX_JITTER_SECS
+            ),
+        ));
+    }
+    let alert_thresholds = std::iter::once((
+        "quota_protection.alert_threshold_percentage".to_string(),
+        config.quota_protection.alert_threshold_percentage,
+    ))
+    .chain(
+        config
+            .quota_protection
+            .account_alert_thresholds
+            .iter()
+            .map(|(id, t)| {
+                (
+                    format!("quota_protection.account_alert_thresholds.{}", id),
+                    *t,
+                )
+            }),
+    );
+    for (path, threshold) in alert_thresholds {
+        if !(1..=99).contains(&threshold) {
+            issues.push(issue(
+                &path,
+                "invalid_value",
+                ConfigIssueSeverity::Error,
+                "alert threshold must be between 1 and 99".to_string(),
+            ));
+        }
+    }
+    if let Some(url) = config.proxy.upstream_proxy.proxy_url() {
+        if let Err(e) = crate::utils::http::validate_proxy_url(&url) {
+            issues.push(issue(
+                "proxy.upstream_proxy.url",
+                "invalid_value",
+                ConfigIssueSeverity::Error,
+                e,
+            ));
+        }
+    }
+    for (i, job) in config.cron_jobs.iter().enumerate() {
+        if let Err(e) = super::cron::validate(&job.expression, &job.timezone) {
+            issues.push(issue(
+                &format!("cron_jobs.{}", i),
+                "invalid_value",
+                ConfigIssueSeverity::Error,
+                e,
+            ));
+        }
+    }
+}
+
+/// Parse a config value, returning the located serde error as an issue
+pub fn parse_config(value: Value) -> Result<AppConfig, ConfigIssue> {
+    serde_path_to_error::deserialize::<_, AppConfig>(value).map_err(|e| {
+        let path = e.path().to_string();
+        let path = if path == "." { String::new() } else { path };
+        issue(
+            &path,
+            "invalid_value",
+            ConfigIssueSeverity::Error,
+            e.into_inner().to_string(),
+        )
+    })
+}
+
+/// Structural validation, returning the parsed config when there are no errors.
+/// Used on load, where a missing executable path must not block startup.
+pub fn validate_and_parse(value: Value) -> (Option<AppConfig>, ConfigValidation) {
+    let mut issues = Vec::new();
+    check_shape(&SCHEMA, &value, "", &mut issues);
+
+    let mut config = None;
+    if !issues
+        .iter()
+        .any(|i| i.severity == ConfigIssueSeverity::Error)
+    {
+        match parse_config(value) {
+            Ok(parsed) => config = Some(parsed),
+            Err(parse_issue) => issues.push(parse_issue),
+        }
+    }
+
+    let validation = ConfigValidation {
+        valid: config.is_some(),
+        issues,
+    };
+    (config, validation)
+}
+
+/// Full validation of a config before it is saved
+pub fn validate_config(value: &Value) -> ConfigValidation {
+    let (config, mut result) = validate_and_parse(value.clone());
+    if let Some(config) = config {
+        check_semantics(&config, &mut result.issues);
+        result.valid = !result
+            .issues
+            .iter()
+            .any(|i| i.severity == ConfigIssueSeverity::Error);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_and_parse() {
+        let mut value = serde_json::to_value(AppConfig::new()).unwrap();
+        assert!(validate_and_parse(value.clone()).1.issues.is_empty());
+
+        value["proxy"]["port"] = Value::String("8045".to_string());
+        value["proxy"]["custom_mapping"] = serde_json::json!({"gpt-4": "gemini-3-flash"});
+        value["theem"] = Value::String("dark".to_string());
+
+        let result = validate_and_parse(value.clone()).1;
+        assert!(!result.valid);
+        let codes: Vec<(&str, &str)> = result
+            .issues
+            .iter()
+            .map(|i| (i.path.as_str(), i.code.as_str()))
+            .collect();
+        assert!(codes.contains(&("proxy.port", "wrong_type")));
+        assert!(codes.contains(&("theem", "unknown_key")));
+        assert!(!codes
+            .iter()
+            .any(|(p, _)| p.starts_with("proxy.custom_mapping")));
+    }
+
+    #[test]
+    fn test_scheduler_jitter_limit() {
+        let mut value = serde_json::to_value(AppConfig::new()).unwrap();
+        value["scheduler_jitter_secs"] = serde_json::json!(3600);
+
+        let result = validate_config(&value);
+        assert!(result.valid);
+        assert!(result
+            .issues
+            .iter()
+            .any(|i| i.path == "scheduler_jitter_secs" && i.code == "invalid_value"));
+    }
+
+    #[test]
+    fn test_alert_threshold_range() {
+        let mut value = serde_json::to_value(AppConfig::new()).unwrap();
+        value["quota_protection"]["alert_threshold_percentage"] = serde_json::json!(0);
+        value["quota_protection"]["account_alert_thresholds"] = serde_json::json!({"acc-1": 100});
+
+        let result = validate_config(&value);
+        assert!(!result.valid);
+        let paths: Vec<&str> = result.issues.iter().map(|i| i.path.as_str()).collect();
+        assert!(paths.contains(&"quota_protection.alert_threshold_percentage"));
+        assert!(paths.contains(&"quota_protection.account_alert_thresholds.acc-1"));
+    }
+
+    #[test]
+    fn test_upstream_proxy_url() {
+        let mut value = serde_json::to_value(AppConfig::new()).unwrap();
+        value["proxy"]["upstream_proxy"]["enabled"] = serde_json::json!(true);
+        value["proxy"]["upstream_proxy"]["url"] = serde_json::json!("127.0.0.1 7890");
+        let result = validate_config(&value);
+        assert!(!result.valid);
+        assert!(result
+            .issues
+            .iter()
+            .any(|i| i.path == "proxy.upstream_proxy.url"));
+
+        value["proxy"]["upstream_proxy"]["url"] = serde_json::json!("socks5://127.0.0.1:7890");
+        assert!(validate_config(&value).valid);
+    }
+}
@@ -0,0 +1,215 @@
+//! Config hot reload.
+//!
+//! Polls the config file and applies edits made outside the app (by hand or by other tools)
+//! without a restart. A `config://reloaded` event lists the changed settings, which of them
+//! were applied and which only take effect after restarting the app.
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::Value;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::models::AppConfig;
+use crate::modules::{config, logger};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Listener settings, applied by rebinding the running proxy in place
+const PROXY_REBIND_KEYS: &[&str] = &[
+    "proxy.port",
+    "proxy.allow_lan_access",
+    "proxy.bind_address",
+    "proxy.extra_listeners",
+];
+/// Settings that need a proxy restart to take effect (done automatically when it is running);
+/// the CORS layer is built when the server starts
+const PROXY_RESTART_KEYS: &[&str] = &["proxy.cors", "proxy.tls"];
+/// Settings that only take effect after restarting the app
+const APP_RESTART_PREFIXES: &[&str] = &["mitm"];
+
+/// Last known config content (as written by the app or last applied)
+static LAST_KNOWN: Lazy<Mutex<Option<Value>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConfigReloadReport {
+    pub changed: Vec<String>,
+    pub applied: Vec<String>,
+    pub requires_restart: Vec<String>,
+}
+
+/// Record a config the app has written itself
+pub fn remember(config: &AppConfig) {
+    if let Ok(value) = serde_json::to_value(config) {
+        *LAST_KNOWN.lock() = Some(value);
+    }
+}
+
+/// Dotted paths of the settings that differ (nested objects are compared two levels deep)
+fn diff_paths(old: &Value, new: &Value, prefix: &str, depth: usize, out: &mut Vec<String>) {
+    match (old, new) {
+        (Value::Object(a), Value::Object(b)) if depth < 2 => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                match (a.get(key), b.get(key)) {
+                    (Some(x), Some(y)) => diff_paths(x, y, &path, depth + 1, out),
+                    _ => out.push(path),
+                }
+            }
+        }
+        _ if old != new => out.push(prefix.to_string()),
+        _ => {}
+    }
+}
+
+fn modified_at() -> Option<SystemTime> {
+    std::fs::metadata(config::get_config_path().ok()?)
+        .and_then(|m| m.modified())
+        .ok()
+}
+
+async fn apply_changes(app: &AppHandle, config: AppConfig, changed: Vec<String>) {
+    let mut report = ConfigReloadReport {
+        changed,
+        ..Default::default()
+    };
+
+    crate::commands::apply_runtime_config(app, &config).await;
+
+    let changed_any = |keys: &[&str]| report.changed.iter().any(|p| keys.contains(&p.as_str()));
+    let needs_proxy_restart = changed_any(PROXY_RESTART_KEYS);
+    let needs_rebind = changed_any(PROXY_REBIND_KEYS);
+    let mut proxy_restart_failed = false;
+    if needs_proxy_restart || needs_rebind {
+        let state = app.state::<crate::commands::proxy::ProxyServiceState>();
+        let running = state.instance.read().await.is_some();
+        if running {
+            let result = if needs_proxy_restart {
+                match crate::commands::proxy::stop_proxy_service(state.clone()).await {
+                    Ok(()) => crate::commands::proxy::start_proxy_service(
+                        config.proxy.clone(),
+                        state,
+                        app.clone(),
+                    )
+                    .await
+                    .map(|_| ()),
+                    Err(e) => Err(e),
+                }
+            } else {
+                crate::commands::proxy::rebind_proxy_listeners(&state, &config.proxy)
+                    .await
+                    .map(|_| ())
+            };
+            if let Err(e) = result {
+                logger::log_error(&format!("[ConfigReload] Failed to restart proxy: {}", e));
+                proxy_restart_failed = true;
+            }
+        }
+    }
+
+    for path in &report.changed {
+        let app_restart = APP_RESTART_PREFIXES
+            .iter()
+            .any(|p| path == p || path.starts_with(&format!("{}.", p)));
+        let proxy_restart = proxy_restart_failed
+            && (PROXY_RESTART_KEYS.contains(&path.as_str())
+                || PROXY_REBIND_KEYS.contains(&path.as_str()));
+        if app_restart || proxy_restart {
+            report.requires_restart.push(path.clone());
+        } else {
+            report.applied.push(path.clone());
+        }
+    }
+
+    logger::log_info(&format!(
+        "[ConfigReload] Applied external config changes: {:?}",
+        report
+    ));
+    let _ = app.emit("config://reloaded", &report);
+}
+
+/// Start watching the config file
+pub fn start(app: AppHandle) {
+    if let Ok(config) = config::load_app_config() {
+        remember(&config);
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let mut last_modified = modified_at();
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            let modified = modified_at();
+            if modified.is_none() || modified == last_modified {
+                continue;
+            }
+            last_modified = modified;
+
+            // A half-written or invalid file is skipped until the next change
+            let config = match config::load_app_config() {
+                Ok(config) => config,
+                Err(e) => {
+                    logger::log_warn(&format!(
+                        "[ConfigReload] Ignoring invalid config file: {}",
+                        e
+                    ));
+                    continue;
+                }
+            };
+            let Ok(new_value) = serde_json::to_value(&config) else {
+                continue;
+            };
+
+            let old_value = LAST_KNOWN.lock().replace(new_value.clone());
+            let mut changed = Vec::new();
+            if let Some(old_value) = &old_value {
+                diff_paths(old_value, &new_value, "", 0, &mut changed);
+            }
+            if !changed.is_empty() {
+                if let Err(e) = crate::modules::config_audit::record(
+                    crate::modules::config_audit::SOURCE_EXTERNAL_EDIT,
+                    old_value.as_ref(),
+                    &new_value,
+                ) {
+                    logger::log_warn(&format!(
+                        "[ConfigReload] Failed to record audit entry: {}",
+                        e
+                    ));
+                }
+                apply_changes(&app, config, changed).await;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_paths() {
+        let old = serde_json::json!({
+            "language": "zh",
+            "proxy": {"port": 8045, "custom_mapping": {"a": "b"}},
+            "quota_protection": {"threshold_percentage": 10}
+        });
+        let new = serde_json::json!({
+            "language": "zh",
+            "proxy": {"port": 8046, "custom_mapping": {"a": "c"}},
+            "quota_protection": {"threshold_percentage": 10},
+            "log_level": "debug"
+        });
+        let mut out = Vec::new();
+        diff_paths(&old, &new, "", 0, &mut out);
+        assert_eq!(out, vec!["log_level", "proxy.custom_mapping", "proxy.port"]);
+    }
+}
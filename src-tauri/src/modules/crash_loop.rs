@@ -0,0 +1,223 @@
+//! 实例崩溃循环检测
+//!
+//! 每次由管理器启动实例时持有子进程句柄并等待其退出：启动后 `min_uptime_secs` 内
+//! 异常退出记为一次快速退出，连续达到 `max_fast_exits` 次即把实例标记为故障，
+//! 之后的启动请求（手动或自动）都会被拒绝，直到用户查看退出状态与日志后手动重置。
+
+use once_cell::sync::{Lazy, OnceCell};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ExitStatus};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+use crate::models::{Instance, InstanceFault};
+use crate::modules::{config, instance, logger};
+
+/// 故障信息中保留的日志行数
+const LOG_TAIL_LINES: usize = 40;
+/// 快速退出后等待输出转存线程写完最后几行
+const LOG_FLUSH_GRACE: Duration = Duration::from_millis(300);
+
+static APP_HANDLE: OnceCell<tauri::AppHandle> = OnceCell::new();
+
+/// 进行中的启动：实例 ID -> 状态
+static LAUNCHES: Lazy<Mutex<HashMap<String, LaunchState>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 连续快速退出计数（实例 ID -> 次数）
+static FAST_EXITS: Lazy<Mutex<HashMap<String, u32>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+struct LaunchState {
+    user_data_dir: PathBuf,
+    /// 由管理器主动关闭（停止/重启/切换账号），此次退出不计入快速退出
+    exit_expected: bool,
+}
+
+/// 保存 AppHandle，用于推送 "instance://faulted" 事件
+pub fn init(app: tauri::AppHandle) {
+    let _ = APP_HANDLE.set(app);
+}
+
+/// 启动前检查：已标记故障的实例拒绝启动
+pub fn ensure_not_faulted(instance: &Instance) -> Result<(), String> {
+    match &instance.fault {
+        Some(fault) => Err(format!(
+            "Instance {} is faulted after {} fast exits (last exit code: {}); reset the fault before starting it again",
+            instance.name,
+            fault.consecutive_failures,
+            fault
+                .exit_code
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| "none".to_string())
+        )),
+        None => Ok(()),
+    }
+}
+
+/// 接管子进程句柄，后台等待其退出并判定是否为快速退出
+pub fn watch_launch(instance: &Instance, mut child: Child) {
+    let instance_id = instance.id.clone();
+    {
+        let mut launches = LAUNCHES.lock().unwrap_or_else(|e| e.into_inner());
+        launches.insert(
+            instance_id.clone(),
+            LaunchState {
+                user_data_dir: instance.user_data_dir.clone(),
+                exit_expected: false,
+            },
+        );
+    }
+
+    let started = Instant::now();
+    std::thread::spawn(move || {
+        let status = child.wait();
+        let uptime = started.elapsed();
+        let expected = LAUNCHES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&instance_id)
+            .map(|s| s.exit_expected)
+            .unwrap_or(false);
+
+        match status {
+            Ok(status) => on_exit(&instance_id, status, uptime, expected),
+            Err(e) => logger::log_warn(&format!(
+                "Failed to wait for instance {} process: {}",
+                instance_id, e
+            )),
+        }
+    });
+}
+
+/// 管理器即将主动关闭该目录对应的实例，本次退出不计为崩溃
+pub fn expect_exit(user_data_dir: &Path) {
+    let key = dir_key(user_data_dir);
+    let mut launches = LAUNCHES.lock().unwrap_or_else(|e| e.into_inner());
+    for state in launches.values_mut() {
+        if dir_key(&state.user_data_dir) == key {
+            state.exit_expected = true;
+        }
+    }
+}
+
+/// 清除故障标记与计数，允许再次启动
+pub fn reset_fault(instance_id: &str) -> Result<(), String> {
+    FAST_EXITS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(instance_id);
+    let mut inst = instance::load_instance(instance_id)?;
+    if inst.fault.take().is_some() {
+        instance::save_instance(&inst)?;
+        logger::log_info(&format!("Instance {} fault cleared", inst.name));
+    }
+    Ok(())
+}
+
+fn on_exit(instance_id: &str, status: ExitStatus, uptime: Duration, expected: bool) {
+    let crash_config = config::load_app_config()
+        .map(|c| c.crash_loop)
+        .unwrap_or_default();
+    if !crash_config.enabled || expected {
+        return;
+    }
+
+    let Ok(inst) = instance::load_instance(instance_id) else {
+        return;
+    };
+
+    // 存活足够久，或启动器把窗口交给了已在运行的主进程（正常退出且实例仍在运行），都视为启动成功
+    let fast = uptime < Duration::from_secs(crash_config.min_uptime_secs);
+    let handed_off = status.success() && {
+        crate::modules::process::invalidate_process_snapshot();
+        if inst.is_default {
+            crate::modules::process::is_default_instance_running()
+        } else {
+            crate::modules::process::is_instance_running(&inst.user_data_dir)
+        }
+    };
+    if !fast || handed_off {
+        FAST_EXITS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(instance_id);
+        return;
+    }
+
+    let failures = {
+        let mut counts = FAST_EXITS.lock().unwrap_or_else(|e| e.into_inner());
+        let count = counts.entry(instance_id.to_string()).or_insert(0);
+        *count += 1;
+        *count
+    };
+    logger::log_warn(&format!(
+        "Instance {} exited after {}ms with {} ({} consecutive fast exits)",
+        inst.name,
+        uptime.as_millis(),
+        status,
+        failures
+    ));
+
+    if failures < crash_config.max_fast_exits {
+        return;
+    }
+
+    let fault = InstanceFault {
+        faulted_at: chrono::Utc::now().timestamp(),
+        consecutive_failures: failures,
+        exit_code: status.code(),
+        exit_signal: exit_signal(&status),
+        uptime_ms: uptime.as_millis() as u64,
+        log_tail: {
+            std::thread::sleep(LOG_FLUSH_GRACE);
+            crate::modules::instance_log::tail(instance_id, LOG_TAIL_LINES).unwrap_or_default()
+        },
+    };
+    if let Err(e) = mark_faulted(instance_id, fault.clone()) {
+        logger::log_error(&format!("Failed to mark instance {} as faulted: {}", inst.name, e));
+        return;
+    }
+    logger::log_error(&format!(
+        "Instance {} marked as faulted after {} fast exits",
+        inst.name, failures
+    ));
+    if let Some(app) = APP_HANDLE.get() {
+        let _ = app.emit(
+            "instance://faulted",
+            serde_json::json!({ "instance_id": instance_id, "fault": fault }),
+        );
+    }
+}
+
+fn mark_faulted(instance_id: &str, fault: InstanceFault) -> Result<(), String> {
+    let mut inst = instance::load_instance(instance_id)?;
+    inst.fault = Some(fault);
+    inst.last_root_pid = None;
+    instance::save_instance(&inst)
+}
+
+#[cfg(unix)]
+fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+fn dir_key(path: &Path) -> String {
+    let s = path
+        .to_string_lossy()
+        .replace('\\', "/")
+        .trim_end_matches('/')
+        .to_string();
+    if cfg!(any(target_os = "windows", target_os = "macos")) {
+        s.to_lowercase()
+    } else {
+        s
+    }
+}
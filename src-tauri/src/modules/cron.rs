@@ -0,0 +1,361 @@
+//! Cron expressions for scheduled jobs.
+//!
+//! Standard five-field expressions (`minute hour day-of-month month day-of-week`) with `*`,
+//! lists, ranges, steps, month/weekday names and the `@hourly`-style aliases. When both
+//! day-of-month and day-of-week are restricted a day matching either one fires, as in
+//! Vixie cron. Schedules are evaluated in local time, UTC, a fixed UTC offset or a named
+//! IANA zone such as "Europe/Berlin" (which follows that zone's DST rules).
+
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike,
+    Utc,
+};
+use chrono_tz::Tz;
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// Days searched for the next occurrence (covers Feb 29 schedules)
+const MAX_SEARCH_DAYS: u32 = 366 * 8;
+
+/// Timezone a cron expression is evaluated in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CronTimezone {
+    Local,
+    Utc,
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl CronTimezone {
+    /// Accepts "local" (or empty), "UTC"/"Z", IANA names like "Europe/Berlin", and offsets
+    /// like "+08:00", "-0530" or "UTC+8"
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        if s.is_empty() || s.eq_ignore_ascii_case("local") {
+            return Ok(CronTimezone::Local);
+        }
+        if s.eq_ignore_ascii_case("utc") || s.eq_ignore_ascii_case("gmt") || s == "Z" {
+            return Ok(CronTimezone::Utc);
+        }
+        if let Ok(tz) = s.parse::<Tz>() {
+            return Ok(CronTimezone::Named(tz));
+        }
+        let offset = s
+            .strip_prefix("UTC")
+            .or_else(|| s.strip_prefix("utc"))
+            .or_else(|| s.strip_prefix("GMT"))
+            .unwrap_or(s);
+        let invalid = || {
+            format!(
+                "unsupported timezone '{}': use \"local\", \"UTC\", an IANA name such as \"Europe/Berlin\" or a UTC offset such as \"+08:00\"",
+                s
+            )
+        };
+        let (sign, rest) = match offset.chars().next() {
+            Some('+') => (1, &offset[1..]),
+            Some('-') => (-1, &offset[1..]),
+            _ => return Err(invalid()),
+        };
+        let (hours, minutes) = match rest.split_once(':') {
+            Some((h, m)) => (h, m),
+            None if rest.len() == 4 => rest.split_at(2),
+            None => (rest, "0"),
+        };
+        let hours: i32 = hours.parse().map_err(|_| invalid())?;
+        let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+        if hours > 14 || minutes >= 60 {
+            return Err(invalid());
+        }
+        FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+            .map(CronTimezone::Fixed)
+            .ok_or_else(invalid)
+    }
+
+    fn to_naive(self, t: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            CronTimezone::Local => t.with_timezone(&Local).naive_local(),
+            CronTimezone::Utc => t.naive_utc(),
+            CronTimezone::Fixed(offset) => t.with_timezone(&offset).naive_local(),
+            CronTimezone::Named(tz) => t.with_timezone(&tz).naive_local(),
+        }
+    }
+
+    /// Wall-clock time to UTC; `None` for local times skipped by a DST change
+    fn from_naive(self, naive: NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            CronTimezone::Local => Local
+                .from_local_datetime(&naive)
+                .earliest()
+                .map(|t| t.with_timezone(&Utc)),
+            CronTimezone::Utc => Some(Utc.from_utc_datetime(&naive)),
+            CronTimezone::Fixed(offset) => offset
+                .from_local_datetime(&naive)
+                .single()
+                .map(|t| t.with_timezone(&Utc)),
+            CronTimezone::Named(tz) => tz
+                .from_local_datetime(&naive)
+                .earliest()
+                .map(|t| t.with_timezone(&Utc)),
+        }
+    }
+}
+
+/// A parsed cron expression; each field is a bitmask of allowed values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    dom_restricted: bool,
+    dow_restricted: bool,
+}
+
+fn parse_value(s: &str, names: &[&str], offset: u32) -> Result<u32, String> {
+    if let Ok(v) = s.parse::<u32>() {
+        return Ok(v);
+    }
+    names
+        .iter()
+        .position(|n| n.eq_ignore_ascii_case(s))
+        .map(|i| i as u32 + offset)
+        .ok_or_else(|| format!("invalid value '{}'", s))
+}
+
+/// Parse one field into a bitmask of values within `min..=max`
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str], name_offset: u32) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| format!("invalid step '{}'", step))?;
+                if step == 0 {
+                    return Err("step must be greater than 0".to_string());
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (
+                parse_value(a, names, name_offset)?,
+                parse_value(b, names, name_offset)?,
+            )
+        } else {
+            let v = parse_value(range, names, name_offset)?;
+            // "5/15" means from 5 to the end of the range
+            (v, if part.contains('/') { max } else { v })
+        };
+        if start < min || end > max || start > end {
+            return Err(format!("'{}' is out of range {}-{}", part, min, max));
+        }
+        for v in (start..=end).step_by(step as usize) {
+            mask |= 1 << v;
+        }
+    }
+    Ok(mask)
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let expression = match expression.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!(
+                "cron expression must have 5 fields (minute hour day month weekday), got {}",
+                fields.len()
+            ));
+        }
+        let field = |i: usize, min, max, names: &[&str], offset| {
+            parse_field(fields[i], min, max, names, offset)
+                .map_err(|e| format!("cron field {}: {}", i + 1, e))
+        };
+        let mut days_of_week = field(4, 0, 7, &WEEKDAY_NAMES, 0)?;
+        // 7 is an alias for Sunday
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week & !(1 << 7)) | 1;
+        }
+        Ok(CronSchedule {
+            minutes: field(0, 0, 59, &[], 0)?,
+            hours: field(1, 0, 23, &[], 0)?,
+            days_of_month: field(2, 1, 31, &[], 0)?,
+            months: field(3, 1, 12, &MONTH_NAMES, 1)?,
+            days_of_week,
+            dom_restricted: fields[2] != "*",
+            dow_restricted: fields[4] != "*",
+        })
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let dom = self.days_of_month & (1 << date.day()) != 0;
+        let dow = self.days_of_week & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.dom_restricted, self.dow_restricted) {
+            (true, true) => dom || dow,
+            _ => dom && dow,
+        }
+    }
+
+    /// First occurrence strictly after `after`, evaluated in `tz`
+    pub fn next_after(&self, after: DateTime<Utc>, tz: CronTimezone) -> Option<DateTime<Utc>> {
+        let start = tz
+            .to_naive(after)
+            .with_second(0)?
+            .with_nanosecond(0)?
+            + Duration::minutes(1);
+        let mut date = start.date();
+        for _ in 0..MAX_SEARCH_DAYS {
+            if self.matches_date(date) {
+                let first_day = date == start.date();
+                for hour in 0..24u32 {
+                    if self.hours & (1 << hour) == 0 || (first_day && hour < start.hour()) {
+                        continue;
+                    }
+                    for minute in 0..60u32 {
+                        if self.minutes & (1 << minute) == 0
+                            || (first_day && hour == start.hour() && minute < start.minute())
+                        {
+                            continue;
+                        }
+                        let Some(t) = date
+                            .and_hms_opt(hour, minute, 0)
+                            .and_then(|naive| tz.from_naive(naive))
+                        else {
+                            continue;
+                        };
+                        if t > after {
+                            return Some(t);
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+}
+
+/// Check that an expression and timezone are valid
+pub fn validate(expression: &str, timezone: &str) -> Result<(), String> {
+    CronSchedule::parse(expression)?;
+    CronTimezone::parse(timezone)?;
+    Ok(())
+}
+
+/// Next `count` occurrences after `after`
+pub fn upcoming(
+    expression: &str,
+    timezone: &str,
+    after: DateTime<Utc>,
+    count: usize,
+) -> Result<Vec<DateTime<Utc>>, String> {
+    let schedule = CronSchedule::parse(expression)?;
+    let tz = CronTimezone::parse(timezone)?;
+    let mut runs = Vec::with_capacity(count);
+    let mut cursor = after;
+    while runs.len() < count {
+        let Some(next) = schedule.next_after(cursor, tz) else {
+            break;
+        };
+        runs.push(next);
+        cursor = next;
+    }
+    Ok(runs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_fields() {
+        let s = CronSchedule::parse("*/15 9-17 * jan-mar mon-fri").unwrap();
+        assert_eq!(s.minutes, (1 << 0) | (1 << 15) | (1 << 30) | (1 << 45));
+        assert_eq!(s.months, 0b1110);
+        assert_eq!(s.days_of_week, 0b111110);
+        assert_eq!(
+            CronSchedule::parse("0 0 * * 7").unwrap().days_of_week,
+            CronSchedule::parse("@weekly").unwrap().days_of_week
+        );
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+    }
+
+    #[test]
+    fn test_next_after() {
+        let s = CronSchedule::parse("30 2 * * *").unwrap();
+        assert_eq!(
+            s.next_after(utc(2024, 5, 16, 1, 0), CronTimezone::Utc),
+            Some(utc(2024, 5, 16, 2, 30))
+        );
+        // Exactly on an occurrence: the next one is a day later
+        assert_eq!(
+            s.next_after(utc(2024, 5, 16, 2, 30), CronTimezone::Utc),
+            Some(utc(2024, 5, 17, 2, 30))
+        );
+        // 02:30 at +08:00 is 18:30 UTC the day before
+        let tz = CronTimezone::parse("+08:00").unwrap();
+        assert_eq!(
+            s.next_after(utc(2024, 5, 16, 1, 0), tz),
+            Some(utc(2024, 5, 16, 18, 30))
+        );
+        // Day-of-month OR day-of-week when both are restricted
+        let s = CronSchedule::parse("0 0 13 * fri").unwrap();
+        assert_eq!(
+            s.next_after(utc(2024, 5, 16, 0, 0), CronTimezone::Utc),
+            Some(utc(2024, 5, 17, 0, 0))
+        );
+        let leap = CronSchedule::parse("0 0 29 2 *").unwrap();
+        assert_eq!(
+            leap.next_after(utc(2024, 3, 1, 0, 0), CronTimezone::Utc),
+            Some(utc(2028, 2, 29, 0, 0))
+        );
+        // Named zones follow DST: 09:00 in Berlin is 08:00 UTC in winter, 07:00 UTC in summer
+        let berlin = CronTimezone::Named(chrono_tz::Europe::Berlin);
+        let s = CronSchedule::parse("0 9 * * *").unwrap();
+        assert_eq!(
+            s.next_after(utc(2024, 1, 15, 0, 0), berlin),
+            Some(utc(2024, 1, 15, 8, 0))
+        );
+        assert_eq!(
+            s.next_after(utc(2024, 7, 1, 0, 0), berlin),
+            Some(utc(2024, 7, 1, 7, 0))
+        );
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(CronTimezone::parse("").unwrap(), CronTimezone::Local);
+        assert_eq!(CronTimezone::parse("UTC").unwrap(), CronTimezone::Utc);
+        let offset = |secs| CronTimezone::Fixed(FixedOffset::east_opt(secs).unwrap());
+        assert_eq!(CronTimezone::parse("UTC+8").unwrap(), offset(8 * 3600));
+        assert_eq!(CronTimezone::parse("-0530").unwrap(), offset(-(5 * 3600 + 1800)));
+        assert_eq!(
+            CronTimezone::parse("Europe/Berlin").unwrap(),
+            CronTimezone::Named(chrono_tz::Europe::Berlin)
+        );
+        assert!(CronTimezone::parse("Mars/Olympus").is_err());
+    }
+}
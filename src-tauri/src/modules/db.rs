@@ -126,18 +126,10 @@ pub fn inject_token(
     }
 }
 
-/// New format injection (>= 1.16.5)
-fn inject_new_format(
-    db_path: &PathBuf,
-    access_token: &str,
-    refresh_token: &str,
-    expiry: i64,
-) -> Result<String, String> {
+/// Encode the value of `antigravityUnifiedStateSync.oauthToken`
+fn new_format_value(access_token: &str, refresh_token: &str, expiry: i64) -> String {
     use base64::{engine::general_purpose, Engine as _};
-    
-    let conn = Connection::open(db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
+
     // Create OAuthTokenInfo (binary)
     let oauth_info = protobuf::create_oauth_info(access_token, refresh_token, expiry);
     let oauth_info_b64 = general_purpose::STANDARD.encode(&oauth_info);
@@ -151,7 +143,20 @@ fn inject_new_format(
     
     // OuterMessage: field 1 = inner
     let outer = protobuf::encode_len_delim_field(1, &inner);
-    let outer_b64 = general_purpose::STANDARD.encode(&outer);
+    general_purpose::STANDARD.encode(&outer)
+}
+
+/// New format injection (>= 1.16.5)
+fn inject_new_format(
+    db_path: &PathBuf,
+    access_token: &str,
+    refresh_token: &str,
+    expiry: i64,
+) -> Result<String, String> {
+    let conn = Connection::open(db_path)
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    
+    let outer_b64 = new_format_value(access_token, refresh_token, expiry);
     
     conn.execute(
         "INSERT OR REPLACE INTO ItemTable (key, value) VALUES (?, ?)",
@@ -252,3 +257,77 @@ pub fn get_db_path_for_instance(user_data_dir: &Path) -> PathBuf {
 pub fn instance_db_exists(user_data_dir: &Path) -> bool {
     get_db_path_for_instance(user_data_dir).exists()
 }
+
+/// 为尚未启动过的实例创建空的 state.vscdb，使首次启动前即可注入 Token
+pub fn ensure_instance_db(user_data_dir: &Path) -> Result<PathBuf, String> {
+    let db_path = get_db_path_for_instance(user_data_dir);
+    let conn = Connection::open(&db_path)
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ItemTable (key TEXT PRIMARY KEY, value TEXT);",
+        [],
+    )
+    .map_err(|e| format!("failed_to_create_item_table: {}", e))?;
+    Ok(db_path)
+}
+
+/// 读取数据库中当前登录的 Refresh Token（新格式优先，回退旧格式）
+pub fn read_refresh_token(db_path: &PathBuf) -> Option<String> {
+    use base64::{engine::general_purpose, Engine as _};
+
+    let conn = Connection::open(db_path).ok()?;
+    let new_format: Option<String> = conn
+        .query_row(
+            "SELECT value FROM ItemTable WHERE key = ?",
+            ["antigravityUnifiedStateSync.oauthToken"],
+            |row| row.get(0),
+        )
+        .ok();
+
+    // Outer(1) -> Inner(2) -> Inner2(1) = base64(OAuthTokenInfo) -> refresh_token(3)
+    let from_new = new_format.and_then(|value| {
+        let outer = general_purpose::STANDARD.decode(value).ok()?;
+        let inner = protobuf::find_field(&outer, 1).ok()??;
+        let inner2 = protobuf::find_field(&inner, 2).ok()??;
+        let info_b64 = protobuf::find_field(&inner2, 1).ok()??;
+        let info = general_purpose::STANDARD.decode(info_b64).ok()?;
+        let refresh = protobuf::find_field(&info, 3).ok()??;
+        String::from_utf8(refresh).ok()
+    });
+
+    from_new.or_else(|| crate::modules::migration::extract_refresh_token_from_file(db_path).ok())
+}
+
+/// 回读数据库，确认注入的 Token 已写入（新格式或旧格式任一匹配即可）
+pub fn verify_injected_token(
+    db_path: &PathBuf,
+    access_token: &str,
+    refresh_token: &str,
+    expiry: i64,
+) -> Result<(), String> {
+    use base64::{engine::general_purpose, Engine as _};
+
+    let conn = Connection::open(db_path)
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    let read = |key: &str| -> Option<String> {
+        conn.query_row("SELECT value FROM ItemTable WHERE key = ?", [key], |row| row.get(0))
+            .ok()
+    };
+
+    if read("antigravityUnifiedStateSync.oauthToken").as_deref()
+        == Some(new_format_value(access_token, refresh_token, expiry).as_str())
+    {
+        return Ok(());
+    }
+
+    if let Some(blob) = read("jetskiStateSync.agentManagerInitState")
+        .and_then(|v| general_purpose::STANDARD.decode(v).ok())
+    {
+        let field = protobuf::create_oauth_field(access_token, refresh_token, expiry);
+        if blob.windows(field.len()).any(|w| w == field.as_slice()) {
+            return Ok(());
+        }
+    }
+
+    Err("token_verification_failed: injected token not found in state.vscdb".to_string())
+}
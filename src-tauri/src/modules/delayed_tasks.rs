@@ -0,0 +1,149 @@
+//! 一次性延时任务
+//!
+//! "2 小时后重新启用该账号"、"今天 18:00 停止该实例" 这类只执行一次的任务。
+//! 任务保存在数据目录的 delayed_tasks.json 中，应用重启后继续等待；
+//! 应用关闭期间到期的任务会在下次启动后立即执行。到期检查与执行由调度器负责。
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::modules::{account, logger};
+
+const TASKS_FILE: &str = "delayed_tasks.json";
+
+/// 延时任务要执行的操作
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DelayedAction {
+    /// 重新启用账号参与反代
+    EnableAccount { account_id: String },
+    /// 禁止账号参与反代
+    DisableAccount { account_id: String },
+    InstanceStart { instance_id: String },
+    InstanceStop { instance_id: String },
+    QuotaRefresh,
+    ConfigBackup,
+}
+
+impl DelayedAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DelayedAction::EnableAccount { .. } => "enable_account",
+            DelayedAction::DisableAccount { .. } => "disable_account",
+            DelayedAction::InstanceStart { .. } => "instance_start",
+            DelayedAction::InstanceStop { .. } => "instance_stop",
+            DelayedAction::QuotaRefresh => "quota_refresh",
+            DelayedAction::ConfigBackup => "config_backup",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DelayedTask {
+    pub id: String,
+    pub action: DelayedAction,
+    /// 执行时间 (Unix 秒)
+    pub run_at: i64,
+    pub created_at: i64,
+    /// 用户备注
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+static TASKS: Lazy<Mutex<Vec<DelayedTask>>> = Lazy::new(|| Mutex::new(load_tasks()));
+
+fn get_tasks_path() -> Result<PathBuf, String> {
+    Ok(account::get_data_dir()?.join(TASKS_FILE))
+}
+
+fn load_tasks() -> Vec<DelayedTask> {
+    match get_tasks_path() {
+        Ok(path) if path.exists() => std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+fn save_tasks(tasks: &[DelayedTask]) -> Result<(), String> {
+    let path = get_tasks_path()?;
+    let content = serde_json::to_string_pretty(tasks)
+        .map_err(|e| format!("序列化延时任务失败: {}", e))?;
+    // 先写临时文件再重命名，避免写入中断导致任务丢失
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, content).map_err(|e| format!("保存延时任务失败: {}", e))?;
+    std::fs::rename(&tmp_path, &path).map_err(|e| format!("保存延时任务失败: {}", e))
+}
+
+/// 新建延时任务：`run_at` 指定执行时间 (Unix 秒)，或 `delay_secs` 指定从现在起的延迟
+pub fn schedule(
+    action: DelayedAction,
+    run_at: Option<i64>,
+    delay_secs: Option<i64>,
+    label: Option<String>,
+) -> Result<DelayedTask, String> {
+    let now = chrono::Utc::now().timestamp();
+    let run_at = match (run_at, delay_secs) {
+        (Some(t), None) => t,
+        (None, Some(d)) if d >= 0 => now + d,
+        (None, Some(_)) => return Err("延迟时间不能为负数".to_string()),
+        _ => return Err("必须且只能指定 run_at 或 delay_secs 之一".to_string()),
+    };
+    if run_at < now - 60 {
+        return Err("执行时间已经过去".to_string());
+    }
+
+    let task = DelayedTask {
+        id: uuid::Uuid::new_v4().to_string(),
+        action,
+        run_at,
+        created_at: now,
+        label,
+    };
+    let mut tasks = TASKS.lock().map_err(|e| e.to_string())?;
+    tasks.push(task.clone());
+    tasks.sort_by_key(|t| t.run_at);
+    save_tasks(&tasks)?;
+    logger::log_info(&format!(
+        "[Scheduler] Delayed task {} ({}) scheduled at {}",
+        task.id,
+        task.action.as_str(),
+        task.run_at
+    ));
+    Ok(task)
+}
+
+/// 取消尚未执行的任务
+pub fn cancel(id: &str) -> Result<(), String> {
+    let mut tasks = TASKS.lock().map_err(|e| e.to_string())?;
+    let before = tasks.len();
+    tasks.retain(|t| t.id != id);
+    if tasks.len() == before {
+        return Err(format!("延时任务不存在: {}", id));
+    }
+    save_tasks(&tasks)
+}
+
+/// 所有待执行的任务（按执行时间排序）
+pub fn list() -> Vec<DelayedTask> {
+    TASKS.lock().map(|tasks| tasks.clone()).unwrap_or_default()
+}
+
+/// 取出已到期的任务；取出即从列表删除，执行失败也不会重复执行
+pub fn take_due(now: i64) -> Vec<DelayedTask> {
+    let Ok(mut tasks) = TASKS.lock() else {
+        return Vec::new();
+    };
+    let (due, pending): (Vec<DelayedTask>, Vec<DelayedTask>) =
+        tasks.drain(..).partition(|t| t.run_at <= now);
+    *tasks = pending;
+    if !due.is_empty() {
+        if let Err(e) = save_tasks(&tasks) {
+            logger::log_warn(&format!("[Scheduler] {}", e));
+        }
+    }
+    due
+}
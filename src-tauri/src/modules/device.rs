@@ -8,16 +8,10 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
-const DATA_DIR: &str = ".antigravity_tools";
 const GLOBAL_BASELINE: &str = "device_original.json";
 
 fn get_data_dir() -> Result<PathBuf, String> {
-    let home = dirs::home_dir().ok_or("failed_to_get_home_dir")?;
-    let data_dir = home.join(DATA_DIR);
-    if !data_dir.exists() {
-        fs::create_dir_all(&data_dir).map_err(|e| format!("failed_to_create_data_dir: {}", e))?;
-    }
-    Ok(data_dir)
+    crate::modules::account::get_data_dir()
 }
 
 /// Find storage.json path (prefer custom/portable paths)
@@ -449,6 +443,72 @@ pub fn instance_storage_exists(user_data_dir: &Path) -> bool {
     get_storage_path_for_instance(user_data_dir).exists()
 }
 
+/// 为尚未启动过的实例创建空的 storage.json，使首次启动前即可写入设备指纹
+pub fn ensure_instance_storage(user_data_dir: &Path) -> Result<PathBuf, String> {
+    let storage_path = get_storage_path_for_instance(user_data_dir);
+    if !storage_path.exists() {
+        fs::write(&storage_path, "{}").map_err(|e| format!("write_failed: {}", e))?;
+    }
+    Ok(storage_path)
+}
+
+/// 将窗口位置写入实例 storage.json 的 windowsState，Antigravity 启动时据此恢复窗口
+pub fn write_window_placement(
+    user_data_dir: &Path,
+    placement: &crate::models::WindowPlacement,
+) -> Result<(), String> {
+    ensure_instance_data_dir(user_data_dir)?;
+    let storage_path = ensure_instance_storage(user_data_dir)?;
+
+    let content = fs::read_to_string(&storage_path).map_err(|e| format!("read_failed: {}", e))?;
+    let mut json: Value =
+        serde_json::from_str(&content).map_err(|e| format!("parse_failed: {}", e))?;
+    let Some(root) = json.as_object_mut() else {
+        return Err("json_top_level_not_object".to_string());
+    };
+
+    let ui_state = serde_json::json!({
+        "mode": placement.mode.ui_state_mode(),
+        "x": placement.x,
+        "y": placement.y,
+        "width": placement.width,
+        "height": placement.height,
+    });
+
+    let windows_state = root
+        .entry("windowsState")
+        .or_insert_with(|| serde_json::json!({}));
+    if !windows_state.is_object() {
+        *windows_state = serde_json::json!({});
+    }
+
+    // 上次活动窗口与所有已打开窗口都使用同一位置，避免恢复多个窗口时落到其他显示器
+    let last_active = windows_state
+        .as_object_mut()
+        .unwrap()
+        .entry("lastActiveWindow")
+        .or_insert_with(|| serde_json::json!({}));
+    if let Some(window) = last_active.as_object_mut() {
+        window.insert("uiState".to_string(), ui_state.clone());
+    } else {
+        *last_active = serde_json::json!({ "uiState": ui_state.clone() });
+    }
+    if let Some(opened) = windows_state
+        .get_mut("openedWindows")
+        .and_then(|v| v.as_array_mut())
+    {
+        for window in opened.iter_mut().filter_map(|w| w.as_object_mut()) {
+            window.insert("uiState".to_string(), ui_state.clone());
+        }
+    }
+
+    let updated =
+        serde_json::to_string_pretty(&json).map_err(|e| format!("serialize_failed: {}", e))?;
+    fs::write(&storage_path, updated)
+        .map_err(|e| format!("write_failed ({:?}): {}", storage_path, e))?;
+    Ok(())
+}
+
 /// 为实例初始化 user_data_dir 目录结构（如果不存在）
 pub fn ensure_instance_data_dir(user_data_dir: &Path) -> Result<(), String> {
     let global_storage_dir = user_data_dir.join("User").join("globalStorage");
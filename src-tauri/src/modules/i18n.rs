@@ -8,11 +8,16 @@ pub struct TrayTexts {
     pub quota: String,
     pub switch_next: String,
     pub refresh_current: String,
+    pub stop_all: String,
     pub show_window: String,
     pub quit: String,
     pub no_account: String,
     pub unknown_quota: String,
     pub forbidden: String,
+    pub scheduled_jobs: String,
+    pub reauth_required: String,
+    /// Label per pausable scheduled job
+    pub jobs: HashMap<String, String>,
 }
 
 /// Load translations from JSON
@@ -47,10 +52,30 @@ pub fn get_tray_texts(lang: &str) -> TrayTexts {
         quota: t.get("quota").cloned().unwrap_or_else(|| "Quota".to_string()),
         switch_next: t.get("switch_next").cloned().unwrap_or_else(|| "Switch to Next Account".to_string()),
         refresh_current: t.get("refresh_current").cloned().unwrap_or_else(|| "Refresh Current Quota".to_string()),
+        stop_all: t.get("stop_all").cloned().unwrap_or_else(|| "Stop Everything".to_string()),
         show_window: t.get("show_window").cloned().unwrap_or_else(|| "Show Main Window".to_string()),
         quit: t.get("quit").cloned().unwrap_or_else(|| "Quit Application".to_string()),
         no_account: t.get("no_account").cloned().unwrap_or_else(|| "No Account".to_string()),
         unknown_quota: t.get("unknown_quota").cloned().unwrap_or_else(|| "Unknown".to_string()),
         forbidden: t.get("forbidden").cloned().unwrap_or_else(|| "Account Forbidden".to_string()),
+        scheduled_jobs: t.get("scheduled_jobs").cloned().unwrap_or_else(|| "Scheduled Jobs".to_string()),
+        reauth_required: t.get("reauth_required").cloned().unwrap_or_else(|| "Re-authorization Required".to_string()),
+        jobs: [
+            ("quota_refresh", "Quota Refresh"),
+            ("warmup", "Smart Warmup"),
+            ("config_backup", "Config Backup"),
+            ("config_sync", "Config Sync"),
+            ("update_check", "Update Check"),
+            ("instance_schedule", "Instance Start/Stop"),
+        ]
+        .into_iter()
+        .map(|(job, default)| {
+            let label = t
+                .get(&format!("job_{}", job))
+                .cloned()
+                .unwrap_or_else(|| default.to_string());
+            (job.to_string(), label)
+        })
+        .collect(),
     }
 }
@@ -1,30 +1,26 @@
 use once_cell::sync::Lazy;
 use serde_json;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
 use std::sync::Mutex;
 use uuid::Uuid;
 
-use crate::models::{Instance, InstanceIndex, InstanceSummary};
-use crate::modules::logger;
+use crate::models::{
+    DeleteInstanceOptions, DiscoveredProfile, Instance, InstanceIndex, InstancePurgePreview,
+    InstanceSummary, LaunchArgSeverity, LaunchArgWarning, ProfileImportRequest,
+};
+use crate::modules::{cache, logger};
 
 /// 全局实例写锁，防止并发操作时数据损坏
 static INSTANCE_INDEX_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
 
-const DATA_DIR: &str = ".antigravity_tools";
 const INSTANCES_INDEX: &str = "instances.json";
 const INSTANCES_DIR: &str = "instances";
 
-/// 获取数据目录路径
+/// 获取数据目录路径（支持 --data-dir 覆盖）
 fn get_data_dir() -> Result<PathBuf, String> {
-    let home = dirs::home_dir().ok_or("failed_to_get_home_dir")?;
-    let data_dir = home.join(DATA_DIR);
-
-    if !data_dir.exists() {
-        fs::create_dir_all(&data_dir).map_err(|e| format!("failed_to_create_data_dir: {}", e))?;
-    }
-
-    Ok(data_dir)
+    crate::modules::account::get_data_dir()
 }
 
 /// 获取实例目录路径
@@ -53,6 +49,10 @@ pub fn load_instance_index() -> Result<InstanceIndex, String> {
     let content = fs::read_to_string(&index_path)
         .map_err(|e| format!("failed_to_read_instance_index: {}", e))?;
 
+    if let Some(index) = cache::INSTANCE_INDEX_CACHE.get(&index_path, content.as_bytes()) {
+        return Ok(index);
+    }
+
     if content.trim().is_empty() {
         logger::log_warn("Instance index is empty, initializing new");
         return Ok(InstanceIndex::new());
@@ -65,6 +65,7 @@ pub fn load_instance_index() -> Result<InstanceIndex, String> {
         "Loaded instance index with {} instances",
         index.instances.len()
     ));
+    cache::INSTANCE_INDEX_CACHE.put(&index_path, content.as_bytes(), index.clone());
     Ok(index)
 }
 
@@ -77,10 +78,14 @@ pub fn save_instance_index(index: &InstanceIndex) -> Result<(), String> {
     let content = serde_json::to_string_pretty(index)
         .map_err(|e| format!("failed_to_serialize_instance_index: {}", e))?;
 
-    fs::write(&temp_path, content)
+    fs::write(&temp_path, &content)
         .map_err(|e| format!("failed_to_write_temp_index_file: {}", e))?;
 
-    fs::rename(temp_path, index_path).map_err(|e| format!("failed_to_replace_index_file: {}", e))
+    fs::rename(temp_path, &index_path)
+        .map_err(|e| format!("failed_to_replace_index_file: {}", e))?;
+
+    cache::INSTANCE_INDEX_CACHE.put(&index_path, content.as_bytes(), index.clone());
+    Ok(())
 }
 
 /// 加载实例完整数据
@@ -95,6 +100,10 @@ pub fn load_instance(instance_id: &str) -> Result<Instance, String> {
     let content = fs::read_to_string(&instance_path)
         .map_err(|e| format!("failed_to_read_instance_data: {}", e))?;
 
+    if let Some(instance) = cache::INSTANCE_CACHE.get(&instance_path, content.as_bytes()) {
+        return Ok(instance);
+    }
+
     let mut instance: Instance = serde_json::from_str(&content)
         .map_err(|e| format!("failed_to_parse_instance_data: {}", e))?;
 
@@ -112,6 +121,7 @@ pub fn load_instance(instance_id: &str) -> Result<Instance, String> {
         }
     }
 
+    cache::INSTANCE_CACHE.put(&instance_path, content.as_bytes(), instance.clone());
     Ok(instance)
 }
 
@@ -123,7 +133,11 @@ pub fn save_instance(instance: &Instance) -> Result<(), String> {
     let content = serde_json::to_string_pretty(instance)
         .map_err(|e| format!("failed_to_serialize_instance_data: {}", e))?;
 
-    fs::write(&instance_path, content).map_err(|e| format!("failed_to_save_instance_data: {}", e))
+    fs::write(&instance_path, &content)
+        .map_err(|e| format!("failed_to_save_instance_data: {}", e))?;
+
+    cache::INSTANCE_CACHE.put(&instance_path, content.as_bytes(), instance.clone());
+    Ok(())
 }
 
 /// 列出所有实例
@@ -184,6 +198,7 @@ pub fn create_instance(
     let instance_id = Uuid::new_v4().to_string();
     let mut instance = Instance::new(instance_id, name, user_data_dir);
     instance.extra_args = extra_args;
+    validate_launch_settings(&instance)?;
 
     // 保存实例数据
     save_instance(&instance)?;
@@ -233,11 +248,197 @@ pub fn delete_instance(instance_id: &str) -> Result<(), String> {
         fs::remove_file(&instance_path)
             .map_err(|e| format!("failed_to_delete_instance_file: {}", e))?;
     }
+    cache::INSTANCE_CACHE.invalidate(&instance_path);
+    crate::modules::instance_log::remove_logs(instance_id);
 
     logger::log_info(&format!("Deleted instance: {}", instance_id));
     Ok(())
 }
 
+/// 删除确认令牌有效期（秒）
+const PURGE_TOKEN_TTL_SECS: i64 = 300;
+
+/// 待确认的数据删除令牌: instance_id -> (token, expires_at)
+static PURGE_TOKENS: Lazy<Mutex<HashMap<String, (String, i64)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 统计目录大小和文件数（不跟随符号链接）
+fn dir_size(path: &std::path::Path) -> (u64, u64) {
+    let mut size = 0u64;
+    let mut count = 0u64;
+    let mut stack = vec![path.to_path_buf()];
+
+    while let Some(dir) = stack.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(e) => e,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let meta = match entry.path().symlink_metadata() {
+                Ok(m) => m,
+                Err(_) => continue,
+            };
+            if meta.is_dir() {
+                stack.push(entry.path());
+            } else {
+                size += meta.len();
+                count += 1;
+            }
+        }
+    }
+
+    (size, count)
+}
+
+fn is_instance_process_running(instance: &Instance) -> bool {
+    if instance.is_default {
+        crate::modules::process::is_default_instance_running()
+    } else {
+        crate::modules::process::is_instance_running(&instance.user_data_dir)
+    }
+}
+
+/// 预览删除实例数据目录：返回目录大小并签发一次性确认令牌
+pub fn preview_instance_purge(instance_id: &str) -> Result<InstancePurgePreview, String> {
+    let instance = load_instance(instance_id)?;
+    let exists = instance.user_data_dir.exists();
+    let (size_bytes, file_count) = if exists {
+        dir_size(&instance.user_data_dir)
+    } else {
+        (0, 0)
+    };
+
+    let token = Uuid::new_v4().simple().to_string();
+    let expires_at = chrono::Utc::now().timestamp() + PURGE_TOKEN_TTL_SECS;
+    PURGE_TOKENS
+        .lock()
+        .map_err(|e| format!("failed_to_acquire_lock: {}", e))?
+        .insert(instance_id.to_string(), (token.clone(), expires_at));
+
+    Ok(InstancePurgePreview {
+        instance_id: instance.id.clone(),
+        running: is_instance_process_running(&instance),
+        user_data_dir: instance.user_data_dir,
+        exists,
+        size_bytes,
+        file_count,
+        confirm_token: token,
+        expires_at,
+    })
+}
+
+/// 校验确认令牌（不消费，删除成功后再调用 consume_purge_token）
+fn verify_purge_token(instance_id: &str, token: Option<&str>) -> Result<(), String> {
+    let token = token.ok_or("purge_confirmation_required")?;
+    let tokens = PURGE_TOKENS
+        .lock()
+        .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+
+    match tokens.get(instance_id) {
+        Some((expected, expires_at)) => {
+            if *expires_at < chrono::Utc::now().timestamp() {
+                return Err("purge_confirmation_expired".to_string());
+            }
+            if expected != token {
+                return Err("purge_confirmation_invalid".to_string());
+            }
+            Ok(())
+        }
+        None => Err("purge_confirmation_required".to_string()),
+    }
+}
+
+/// 消费确认令牌（一次性）
+fn consume_purge_token(instance_id: &str) {
+    if let Ok(mut tokens) = PURGE_TOKENS.lock() {
+        tokens.remove(instance_id);
+    }
+}
+
+/// 防御：拒绝删除家目录或根目录这类明显错误的路径
+fn check_purge_path(dir: &std::path::Path) -> Result<(), String> {
+    let home = dirs::home_dir();
+    if dir.parent().is_none() || home.as_deref() == Some(dir) {
+        return Err(format!("refusing_to_purge_unsafe_path: {}", dir.display()));
+    }
+    Ok(())
+}
+
+/// 删除实例（带选项）
+/// - 运行中的实例默认拒绝删除，force 时先关闭进程
+/// - purge_data 时同时删除 user_data_dir，必须携带预览签发的确认令牌
+pub fn delete_instance_with_options(
+    instance_id: &str,
+    options: &DeleteInstanceOptions,
+) -> Result<(), String> {
+    let instance = load_instance(instance_id)?;
+
+    if instance.is_default {
+        return Err("Cannot delete default instance".to_string());
+    }
+
+    // 先校验路径与令牌，避免关闭进程或移除索引后才发现无法删除
+    if options.purge_data {
+        check_purge_path(&instance.user_data_dir)?;
+        verify_purge_token(instance_id, options.confirm_token.as_deref())?;
+    }
+
+    if is_instance_process_running(&instance) {
+        if !options.force {
+            return Err(format!("instance_running: {}", instance.name));
+        }
+        logger::log_warn(&format!(
+            "Force deleting running instance {}, closing processes first",
+            instance.name
+        ));
+        crate::modules::process::close_instance(&instance.user_data_dir, 20)?;
+        if crate::modules::process::is_instance_running(&instance.user_data_dir) {
+            return Err(format!("failed_to_stop_instance: {}", instance.name));
+        }
+    }
+
+    // 先删除数据目录再移除索引：删除失败 (如 Windows 下文件被占用) 时实例仍在索引中，
+    // 确认令牌也未消费，可以直接重试
+    if options.purge_data {
+        let dir = &instance.user_data_dir;
+        if dir.exists() {
+            fs::remove_dir_all(dir).map_err(|e| format!("failed_to_purge_user_data_dir: {}", e))?;
+        }
+        logger::log_info(&format!("Purged user data dir of instance {}: {:?}", instance_id, dir));
+    }
+
+    delete_instance(instance_id)?;
+
+    if options.purge_data {
+        consume_purge_token(instance_id);
+    }
+
+    Ok(())
+}
+
+/// 校验实例的进程与窗口设置（创建与更新时调用）
+fn validate_launch_settings(instance: &Instance) -> Result<(), String> {
+    if let Some(mask) = instance.cpu_affinity {
+        let cpus = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .min(64);
+        let available = if cpus == 64 { u64::MAX } else { (1u64 << cpus) - 1 };
+        if mask & available == 0 {
+            return Err(format!(
+                "invalid_cpu_affinity: mask {:#x} does not allow any of the {} available CPUs",
+                mask, cpus
+            ));
+        }
+    }
+    if let Some(placement) = &instance.window_placement {
+        if placement.width == 0 || placement.height == 0 {
+            return Err("invalid_window_placement: width and height must be positive".to_string());
+        }
+    }
+    Ok(())
+}
+
 /// 更新实例
 pub fn update_instance(instance: &Instance) -> Result<(), String> {
     let _lock = INSTANCE_INDEX_LOCK
@@ -247,6 +448,8 @@ pub fn update_instance(instance: &Instance) -> Result<(), String> {
     // 确保实例存在
     let _ = load_instance(&instance.id)?;
 
+    validate_launch_settings(instance)?;
+
     // 保存实例数据
     save_instance(instance)?;
 
@@ -396,7 +599,7 @@ pub fn migrate_accounts_to_default_instance() -> Result<(), String> {
     let mut default_instance = ensure_default_instance()?;
 
     // 获取所有账号
-    let accounts = crate::modules::account::list_accounts()?;
+    let accounts = crate::modules::account::list_account_summaries()?;
 
     // 将所有未绑定的账号绑定到默认实例
     let instances = list_instances()?;
@@ -499,14 +702,14 @@ pub fn switch_account_in_instance(instance_id: &str, account_id: &str) -> Result
 /// 获取所有运行中的实例
 pub fn get_running_instances() -> Result<Vec<Instance>, String> {
     let instances = list_instances()?;
+    let snapshot = crate::modules::process::get_process_snapshot();
     let mut running = Vec::new();
 
     for instance in instances {
-        let user_data_path = std::path::Path::new(&instance.user_data_dir);
         let is_running = if instance.is_default {
-            crate::modules::process::is_default_instance_running()
+            snapshot.is_default_running()
         } else {
-            crate::modules::process::is_instance_running(user_data_path)
+            snapshot.is_instance_running(&instance.user_data_dir)
         };
 
         if is_running {
@@ -516,3 +719,482 @@ pub fn get_running_instances() -> Result<Vec<Instance>, String> {
 
     Ok(running)
 }
+
+/// 订阅进程监视器事件：实例启动/退出时同步 last_root_pid，使 PID 快速检测保持准确
+pub fn spawn_process_event_sync() {
+    let rx = crate::modules::process::subscribe_process_events();
+    std::thread::spawn(move || {
+        for event in rx {
+            if let Err(e) = apply_process_event(&event) {
+                logger::log_warn(&format!("Failed to sync instance process state: {}", e));
+            }
+        }
+    });
+}
+
+fn apply_process_event(event: &crate::modules::process::ProcessEvent) -> Result<(), String> {
+    use crate::modules::process::ProcessEventKind;
+
+    let pid = event.process.pid;
+    for mut instance in list_instances()? {
+        let matches = match &event.process.user_data_dir {
+            None => instance.is_default,
+            Some(dir) => !instance.is_default && path_key(dir) == path_key(&instance.user_data_dir),
+        };
+        if !matches {
+            continue;
+        }
+
+        let changed = match event.kind {
+            ProcessEventKind::Started if instance.last_root_pid != Some(pid) => {
+                instance.last_root_pid = Some(pid);
+                true
+            }
+            ProcessEventKind::Stopped if instance.last_root_pid == Some(pid) => {
+                instance.last_root_pid = None;
+                true
+            }
+            _ => false,
+        };
+        if changed {
+            save_instance(&instance)?;
+        }
+    }
+    Ok(())
+}
+
+/// 路径比较用的归一化键（统一分隔符，去掉尾部分隔符；Windows/macOS 不区分大小写）
+fn path_key(path: &std::path::Path) -> String {
+    let s = path
+        .to_string_lossy()
+        .replace('\\', "/")
+        .trim_end_matches('/')
+        .to_string();
+    if cfg!(any(target_os = "windows", target_os = "macos")) {
+        s.to_lowercase()
+    } else {
+        s
+    }
+}
+
+/// 判断目录是否像 Antigravity 的 user-data-dir
+fn looks_like_profile_dir(dir: &std::path::Path) -> bool {
+    crate::modules::db::instance_db_exists(dir) || dir.join("User").join("settings.json").exists()
+}
+
+/// 自动发现尚未登记的配置目录
+/// 来源：运行中的进程参数、系统默认目录、默认目录与已登记实例目录的同级目录
+pub fn discover_profiles() -> Result<Vec<DiscoveredProfile>, String> {
+    let index = load_instance_index()?;
+    let mut known: HashSet<String> = index
+        .instances
+        .iter()
+        .map(|s| path_key(&s.user_data_dir))
+        .collect();
+    let default_dir = get_default_user_data_dir().ok();
+    let has_default_instance = index.instances.iter().any(|s| s.is_default);
+
+    let mut found: Vec<DiscoveredProfile> = Vec::new();
+    let mut push = |dir: PathBuf, source: &str, pid: Option<u32>, known: &mut HashSet<String>| {
+        if !known.insert(path_key(&dir)) {
+            return;
+        }
+        let is_default_location = default_dir
+            .as_ref()
+            .map_or(false, |d| path_key(d) == path_key(&dir));
+        let last_modified = fs::metadata(&dir)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64);
+        found.push(DiscoveredProfile {
+            suggested_name: dir
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| dir.to_string_lossy().to_string()),
+            has_state_db: crate::modules::db::instance_db_exists(&dir),
+            running: pid.is_some(),
+            pid,
+            source: source.to_string(),
+            is_default_location,
+            last_modified,
+            user_data_dir: dir,
+        });
+    };
+
+    // 1. 运行中的进程
+    for (pid, dir) in crate::modules::process::get_running_user_data_dirs() {
+        push(dir, "process", Some(pid), &mut known);
+    }
+
+    // 2. 系统默认目录（尚无默认实例时）
+    if let Some(dir) = &default_dir {
+        if !has_default_instance && looks_like_profile_dir(dir) {
+            push(dir.clone(), "filesystem", None, &mut known);
+        }
+    }
+
+    // 3. 同级目录扫描（用户通常把多个 profile 放在一起）
+    let mut parents: Vec<PathBuf> = Vec::new();
+    if let Some(parent) = default_dir.as_ref().and_then(|d| d.parent()) {
+        parents.push(parent.to_path_buf());
+    }
+    for summary in &index.instances {
+        if let Some(parent) = summary.user_data_dir.parent() {
+            if !parents.iter().any(|p| p == parent) {
+                parents.push(parent.to_path_buf());
+            }
+        }
+    }
+
+    let default_parent = default_dir.as_ref().and_then(|d| d.parent().map(|p| p.to_path_buf()));
+    for parent in parents {
+        let entries = match fs::read_dir(&parent) {
+            Ok(e) => e,
+            Err(_) => continue,
+        };
+        // 系统配置目录下只考虑名称含 antigravity 的子目录，避免误报其他 Electron 应用
+        let only_named = default_parent.as_ref() == Some(&parent);
+        for entry in entries.flatten() {
+            let dir = entry.path();
+            if !dir.is_dir() {
+                continue;
+            }
+            if only_named
+                && !entry
+                    .file_name()
+                    .to_string_lossy()
+                    .to_lowercase()
+                    .contains("antigravity")
+            {
+                continue;
+            }
+            // 默认目录只在没有默认实例时由第 2 步处理
+            if default_dir.as_ref().map_or(false, |d| path_key(d) == path_key(&dir)) {
+                continue;
+            }
+            if looks_like_profile_dir(&dir) {
+                push(dir, "filesystem", None, &mut known);
+            }
+        }
+    }
+
+    logger::log_info(&format!("Discovered {} unregistered profiles", found.len()));
+    Ok(found)
+}
+
+/// 将发现的配置目录导入为实例
+/// 系统默认目录导入为默认实例，其余创建为普通实例；单项失败不影响其他项
+pub fn import_discovered_profiles(
+    requests: Vec<ProfileImportRequest>,
+) -> Result<(Vec<Instance>, Vec<String>), String> {
+    let default_dir = get_default_user_data_dir().ok();
+    let mut imported = Vec::new();
+    let mut errors = Vec::new();
+
+    for req in requests {
+        if !req.user_data_dir.exists() {
+            errors.push(format!("{}: directory not found", req.user_data_dir.display()));
+            continue;
+        }
+
+        let is_default_location = default_dir
+            .as_ref()
+            .map_or(false, |d| path_key(d) == path_key(&req.user_data_dir));
+
+        let result = if is_default_location {
+            ensure_default_instance()
+        } else {
+            let name = req.name.clone().filter(|n| !n.trim().is_empty()).unwrap_or_else(|| {
+                req.user_data_dir
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "Imported".to_string())
+            });
+            let extra_args = if req.keep_running_args {
+                running_extra_args(&req.user_data_dir)
+            } else {
+                Vec::new()
+            };
+            create_instance(name, req.user_data_dir.clone(), extra_args)
+        };
+
+        match result {
+            Ok(instance) => imported.push(instance),
+            Err(e) => errors.push(format!("{}: {}", req.user_data_dir.display(), e)),
+        }
+    }
+
+    logger::log_info(&format!(
+        "Imported {} discovered profiles ({} failed)",
+        imported.len(),
+        errors.len()
+    ));
+    Ok((imported, errors))
+}
+
+/// 读取运行中实例的启动参数，去掉可执行文件、--user-data-dir 和 helper 参数
+fn running_extra_args(user_data_dir: &std::path::Path) -> Vec<String> {
+    let args = match crate::modules::process::get_instance_root_process_args(user_data_dir) {
+        Some(a) => a,
+        None => return Vec::new(),
+    };
+
+    let mut result = Vec::new();
+    let mut skip_next = false;
+    for arg in args.into_iter().skip(1) {
+        if skip_next {
+            skip_next = false;
+            continue;
+        }
+        if arg == "--user-data-dir" {
+            skip_next = true;
+            continue;
+        }
+        if arg.starts_with("--user-data-dir=") || arg.starts_with("--type=") {
+            continue;
+        }
+        result.push(arg);
+    }
+    result
+}
+
+/// 仅由 Chromium/Electron 主进程传递给子进程的参数，手动指定会让实例以 helper 身份启动
+const HELPER_ONLY_FLAGS: &[&str] = &[
+    "--type",
+    "--utility-sub-type",
+    "--renderer-client-id",
+    "--field-trial-handle",
+    "--mojo-platform-channel-handle",
+    "--service-sandbox-type",
+];
+
+/// 校验实例的额外启动参数（保存前调用）
+/// 返回结构化的问题列表，空列表表示参数可用
+pub fn validate_extra_args(
+    extra_args: &[String],
+    user_data_dir: Option<&std::path::Path>,
+    is_default: bool,
+) -> Vec<LaunchArgWarning> {
+    let mut warnings = Vec::new();
+    let mut seen_flags: HashSet<String> = HashSet::new();
+
+    let mut push = |code: &str, severity: LaunchArgSeverity, index: usize, arg: &str, message: String| {
+        warnings.push(LaunchArgWarning {
+            code: code.to_string(),
+            severity,
+            index: Some(index),
+            arg: arg.to_string(),
+            message,
+        });
+    };
+
+    for (i, arg) in extra_args.iter().enumerate() {
+        let trimmed = arg.trim();
+
+        if trimmed.is_empty() {
+            push(
+                "empty_arg",
+                LaunchArgSeverity::Warning,
+                i,
+                arg,
+                "Empty argument will be passed to Antigravity as-is".to_string(),
+            );
+            continue;
+        }
+
+        // 参数不经过 shell，引号会原样传入；双引号不成对几乎都是从命令行复制时残留的，
+        // 单引号常见于普通文本 (如窗口标题中的撇号)，只提示
+        if trimmed.matches('"').count() % 2 != 0 {
+            push(
+                "unbalanced_quotes",
+                LaunchArgSeverity::Error,
+                i,
+                arg,
+                "Argument contains unbalanced quotes".to_string(),
+            );
+        } else if trimmed.matches('\'').count() % 2 != 0 {
+            push(
+                "unbalanced_quotes",
+                LaunchArgSeverity::Warning,
+                i,
+                arg,
+                "Argument contains an unpaired single quote; it is passed to Antigravity literally"
+                    .to_string(),
+            );
+        }
+
+        let flag = trimmed.split('=').next().unwrap_or(trimmed);
+
+        // --user-data-dir 由实例本身管理
+        if flag == "--user-data-dir" {
+            let value = if trimmed.contains('=') {
+                trimmed.splitn(2, '=').nth(1).map(|v| v.trim_matches('"').to_string())
+            } else {
+                extra_args.get(i + 1).map(|v| v.trim_matches('"').to_string())
+            };
+            let same_dir = match (value.as_deref(), user_data_dir) {
+                (Some(v), Some(dir)) => std::path::Path::new(v) == dir,
+                _ => false,
+            };
+            let message = if is_default {
+                "Default instance must not set --user-data-dir; it always uses the standard profile".to_string()
+            } else if same_dir {
+                "--user-data-dir is already added from the instance settings; remove the duplicate".to_string()
+            } else {
+                "--user-data-dir conflicts with the instance's user_data_dir and breaks isolation".to_string()
+            };
+            push(
+                "user_data_dir_conflict",
+                if same_dir { LaunchArgSeverity::Warning } else { LaunchArgSeverity::Error },
+                i,
+                arg,
+                message,
+            );
+            continue;
+        }
+
+        // helper 专用参数（如 --type=renderer）
+        if HELPER_ONLY_FLAGS.contains(&flag) {
+            push(
+                "helper_only_flag",
+                LaunchArgSeverity::Error,
+                i,
+                arg,
+                format!("{} is only used by helper processes and prevents the main window from starting", flag),
+            );
+            continue;
+        }
+
+        if flag.starts_with("--") {
+            // 多个参数被写在同一项里（未加引号的空格）
+            if trimmed.contains(' ') && !trimmed.contains('"') && !trimmed.contains('=') {
+                push(
+                    "combined_args",
+                    LaunchArgSeverity::Warning,
+                    i,
+                    arg,
+                    "Argument contains spaces; each flag should be a separate entry".to_string(),
+                );
+            }
+
+            if !seen_flags.insert(flag.to_string()) {
+                push(
+                    "duplicate_flag",
+                    LaunchArgSeverity::Warning,
+                    i,
+                    arg,
+                    format!("{} is specified more than once", flag),
+                );
+            }
+        }
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn codes(warnings: &[LaunchArgWarning]) -> Vec<&str> {
+        warnings.iter().map(|w| w.code.as_str()).collect()
+    }
+
+    #[test]
+    fn test_valid_args_produce_no_warnings() {
+        let w = validate_extra_args(&args(&["--disable-gpu", "--lang=en"]), None, false);
+        assert!(w.is_empty());
+    }
+
+    #[test]
+    fn test_user_data_dir_conflict() {
+        let dir = std::path::Path::new("/tmp/a");
+        let w = validate_extra_args(&args(&["--user-data-dir", "/tmp/b"]), Some(dir), false);
+        assert_eq!(codes(&w), vec!["user_data_dir_conflict"]);
+        assert_eq!(w[0].severity, LaunchArgSeverity::Error);
+
+        let w = validate_extra_args(&args(&["--user-data-dir=/tmp/a"]), Some(dir), false);
+        assert_eq!(w[0].severity, LaunchArgSeverity::Warning);
+    }
+
+    #[test]
+    fn test_helper_flags_and_quotes() {
+        let w = validate_extra_args(&args(&["--type=renderer", "--title=\"abc"]), None, false);
+        assert_eq!(codes(&w), vec!["helper_only_flag", "unbalanced_quotes"]);
+        assert_eq!(w[1].severity, LaunchArgSeverity::Error);
+
+        let w = validate_extra_args(&args(&["--window-title=Bob's"]), None, false);
+        assert_eq!(codes(&w), vec!["unbalanced_quotes"]);
+        assert_eq!(w[0].severity, LaunchArgSeverity::Warning);
+    }
+
+    #[test]
+    fn test_launch_settings_validation() {
+        let mut instance = Instance::new(
+            "id".to_string(),
+            "test".to_string(),
+            PathBuf::from("/tmp/instance-test"),
+        );
+        assert!(validate_launch_settings(&instance).is_ok());
+
+        instance.cpu_affinity = Some(0);
+        assert!(validate_launch_settings(&instance)
+            .unwrap_err()
+            .starts_with("invalid_cpu_affinity"));
+
+        // 第 0 个核心总是存在
+        instance.cpu_affinity = Some(1);
+        assert!(validate_launch_settings(&instance).is_ok());
+
+        // 只包含不存在的核心
+        let cpus = std::thread::available_parallelism().unwrap().get();
+        if cpus < 64 {
+            instance.cpu_affinity = Some(1u64 << cpus);
+            assert!(validate_launch_settings(&instance).is_err());
+        }
+    }
+
+    #[test]
+    fn test_duplicate_and_combined() {
+        let w = validate_extra_args(&args(&["--a", "--a", "--b --c"]), None, false);
+        assert_eq!(codes(&w), vec!["duplicate_flag", "combined_args"]);
+    }
+
+    #[test]
+    fn test_purge_token_is_kept_until_consumed() {
+        let id = "purge-token-test";
+        let expires_at = chrono::Utc::now().timestamp() + PURGE_TOKEN_TTL_SECS;
+        PURGE_TOKENS
+            .lock()
+            .unwrap()
+            .insert(id.to_string(), ("abc".to_string(), expires_at));
+
+        assert_eq!(
+            verify_purge_token(id, Some("wrong")),
+            Err("purge_confirmation_invalid".to_string())
+        );
+        // 校验失败或删除失败后令牌仍可再次使用
+        assert!(verify_purge_token(id, Some("abc")).is_ok());
+        assert!(verify_purge_token(id, Some("abc")).is_ok());
+
+        consume_purge_token(id);
+        assert_eq!(
+            verify_purge_token(id, Some("abc")),
+            Err("purge_confirmation_required".to_string())
+        );
+    }
+
+    #[test]
+    fn test_purge_path_rejects_root_and_home() {
+        assert!(check_purge_path(std::path::Path::new("/")).is_err());
+        if let Some(home) = dirs::home_dir() {
+            assert!(check_purge_path(&home).is_err());
+            assert!(check_purge_path(&home.join("instance-data")).is_ok());
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! 实例输出日志
+//!
+//! 由管理器启动的实例把 stdout/stderr 直接重定向到数据目录下
+//! `logs/instances/<instance_id>.log`（追加模式打开的文件句柄），实例不依赖管理器进程存活，
+//! 管理器退出或重启后实例仍可继续写日志。轮转在管理器侧进行：启动实例前以及运行期间定期检查，
+//! 超过大小上限时复制为历史文件后截断当前文件（copytruncate，保留若干历史文件），
+//! 便于排查 "实例启动后立即退出" 之类的问题。
+
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Mutex, Once};
+use std::time::Duration;
+
+use crate::models::Instance;
+use crate::modules::logger;
+
+/// 单个日志文件大小上限，超过后轮转
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+/// 保留的历史文件数（<id>.log.1 ... <id>.log.N）
+const MAX_ROTATED_FILES: usize = 3;
+/// tail 最多返回的行数
+pub const MAX_TAIL_LINES: usize = 5000;
+/// tail 时每个文件最多读取的字节数
+const TAIL_READ_BYTES: u64 = 1024 * 1024;
+/// 运行期间检查日志大小的间隔
+const ROTATE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 需要定期检查轮转的实例
+static WATCHED: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+static WATCHER: Once = Once::new();
+/// 串行化轮转与管理器写入的标记行
+static ROTATE_LOCK: Mutex<()> = Mutex::new(());
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+fn log_dir() -> Result<PathBuf, String> {
+    let dir = logger::get_log_dir()?.join("instances");
+    fs::create_dir_all(&dir).map_err(|e| format!("failed_to_create_instance_log_dir: {}", e))?;
+    Ok(dir)
+}
+
+/// 实例当前日志文件路径
+pub fn log_path(instance_id: &str) -> Result<PathBuf, String> {
+    Ok(log_dir()?.join(format!("{}.log", instance_id)))
+}
+
+/// 超过大小上限时轮转。实例持有的是追加模式的句柄，截断后会从文件开头继续写入，
+/// 因此采用复制后截断，而不是重命名（重命名后实例会继续写入已轮转的文件）
+fn rotate_if_needed(path: &Path) -> std::io::Result<()> {
+    let _guard = ROTATE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    if size < MAX_LOG_BYTES {
+        return Ok(());
+    }
+    for i in (1..MAX_ROTATED_FILES).rev() {
+        let from = rotated_path(path, i);
+        if from.exists() {
+            fs::rename(&from, rotated_path(path, i + 1))?;
+        }
+    }
+    fs::copy(path, rotated_path(path, 1))?;
+    OpenOptions::new().write(true).open(path)?.set_len(0)
+}
+
+/// 管理器写入一行标记（启动参数、pid 等）
+fn write_marker(path: &Path, message: &str) {
+    let _guard = ROTATE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let line = format!("{} [manager] {}", chrono::Local::now().to_rfc3339(), message);
+    let result = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{}", line));
+    if let Err(e) = result {
+        logger::log_warn(&format!("Failed to write instance log {:?}: {}", path, e));
+    }
+}
+
+/// 打开供子进程使用的日志文件（追加模式）
+fn open_for_child(path: &Path) -> std::io::Result<(File, File)> {
+    let stdout = OpenOptions::new().create(true).append(true).open(path)?;
+    let stderr = stdout.try_clone()?;
+    Ok((stdout, stderr))
+}
+
+/// 启动前调用：必要时先轮转，写入启动标记，并把子进程 stdout/stderr 重定向到日志文件。
+/// 日志文件不可用时丢弃输出（不使用管道，避免实例依赖管理器读取）
+pub fn prepare_command(cmd: &mut Command, instance: &Instance, args: &[String]) {
+    let opened = log_path(&instance.id).and_then(|path| {
+        if let Err(e) = rotate_if_needed(&path) {
+            logger::log_warn(&format!("Failed to rotate instance log {:?}: {}", path, e));
+        }
+        write_marker(&path, &format!("===== launch args={:?} =====", args));
+        open_for_child(&path).map_err(|e| format!("failed_to_open_instance_log: {}", e))
+    });
+    match opened {
+        Ok((stdout, stderr)) => {
+            cmd.stdout(Stdio::from(stdout)).stderr(Stdio::from(stderr));
+        }
+        Err(e) => {
+            logger::log_warn(&format!(
+                "Instance log unavailable for {}, output will be discarded: {}",
+                instance.name, e
+            ));
+            cmd.stdout(Stdio::null()).stderr(Stdio::null());
+        }
+    }
+}
+
+/// 启动后调用：记录 pid，并登记到后台轮转检查
+pub fn attach(instance: &Instance, child: &Child) {
+    if let Ok(path) = log_path(&instance.id) {
+        write_marker(&path, &format!("pid={}", child.id()));
+    }
+    WATCHED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(instance.id.clone());
+    WATCHER.call_once(|| {
+        std::thread::spawn(|| loop {
+            std::thread::sleep(ROTATE_CHECK_INTERVAL);
+            let ids: Vec<String> = WATCHED
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .iter()
+                .cloned()
+                .collect();
+            for id in ids {
+                let Ok(path) = log_path(&id) else { continue };
+                if let Err(e) = rotate_if_needed(&path) {
+                    logger::log_warn(&format!("Failed to rotate instance log {:?}: {}", path, e));
+                }
+            }
+        });
+    });
+}
+
+/// 读取单个文件末尾的若干行
+fn read_file_tail(path: &Path, max_lines: usize) -> Vec<String> {
+    let Ok(mut file) = File::open(path) else {
+        return Vec::new();
+    };
+    let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let start = len.saturating_sub(TAIL_READ_BYTES);
+    if file.seek(SeekFrom::Start(start)).is_err() {
+        return Vec::new();
+    }
+    let mut buf = Vec::new();
+    if file.read_to_end(&mut buf).is_err() {
+        return Vec::new();
+    }
+    let text = String::from_utf8_lossy(&buf);
+    let mut lines: Vec<&str> = text.lines().collect();
+    // 从文件中间开始读取时第一行可能不完整
+    if start > 0 && !lines.is_empty() {
+        lines.remove(0);
+    }
+    let skip = lines.len().saturating_sub(max_lines);
+    lines[skip..].iter().map(|l| l.to_string()).collect()
+}
+
+/// 获取实例日志最后 N 行（当前文件不足时向前读取已轮转的文件）
+pub fn tail(instance_id: &str, lines: usize) -> Result<Vec<String>, String> {
+    let lines = lines.clamp(1, MAX_TAIL_LINES);
+    let path = log_path(instance_id)?;
+
+    let mut result = read_file_tail(&path, lines);
+    let mut index = 1;
+    while result.len() < lines && index <= MAX_ROTATED_FILES {
+        let rotated = rotated_path(&path, index);
+        if !rotated.exists() {
+            break;
+        }
+        let mut older = read_file_tail(&rotated, lines - result.len());
+        older.append(&mut result);
+        result = older;
+        index += 1;
+    }
+    Ok(result)
+}
+
+/// 删除实例的全部日志文件（删除实例时调用）
+pub fn remove_logs(instance_id: &str) {
+    WATCHED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(instance_id);
+    let Ok(path) = log_path(instance_id) else {
+        return;
+    };
+    let _ = fs::remove_file(&path);
+    for i in 1..=MAX_ROTATED_FILES {
+        let _ = fs::remove_file(rotated_path(&path, i));
+    }
+}
@@ -0,0 +1,317 @@
+//! Scheduled job history.
+//!
+//! Every run of a scheduled job (cron jobs, the per-account quota refresh, smart warmup,
+//! temporary account cleanup) is recorded with its start and end time, result and error,
+//! so users can check whether last night's refresh or backup actually ran. Runs older
+//! than `job_history_retention_days` are pruned.
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use crate::modules::logger;
+
+/// Default page size of a query
+const DEFAULT_LIMIT: i64 = 200;
+/// Minimum time between two prunes triggered by new runs
+const PRUNE_INTERVAL_SECS: i64 = 3600;
+
+static LAST_PRUNE: AtomicI64 = AtomicI64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobRunStatus {
+    Success,
+    Failed,
+    /// Not run, e.g. an occurrence missed while the machine was asleep
+    Skipped,
+}
+
+impl JobRunStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            JobRunStatus::Success => "success",
+            JobRunStatus::Failed => "failed",
+            JobRunStatus::Skipped => "skipped",
+        }
+    }
+
+    fn from_str(value: &str) -> Self {
+        match value {
+            "success" => JobRunStatus::Success,
+            "skipped" => JobRunStatus::Skipped,
+            _ => JobRunStatus::Failed,
+        }
+    }
+}
+
+/// One execution of a scheduled job
+#[derive(Debug, Clone, Serialize)]
+pub struct JobRun {
+    pub id: i64,
+    /// Job type, e.g. "quota_refresh", "config_backup", "warmup"
+    pub job_type: String,
+    /// Id of the cron job or delayed task that triggered the run, if any
+    pub job_id: Option<String>,
+    /// Unix timestamp (ms)
+    pub started_at: i64,
+    /// Unix timestamp (ms)
+    pub finished_at: i64,
+    pub status: JobRunStatus,
+    /// Short summary of what the run did
+    pub detail: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct JobHistoryQuery {
+    #[serde(default)]
+    pub job_type: Option<String>,
+    #[serde(default)]
+    pub job_id: Option<String>,
+    #[serde(default)]
+    pub status: Option<JobRunStatus>,
+    /// Unix timestamp (ms) lower bound of `started_at`
+    #[serde(default)]
+    pub from: Option<i64>,
+    /// Unix timestamp (ms) upper bound of `started_at`
+    #[serde(default)]
+    pub to: Option<i64>,
+    #[serde(default)]
+    pub limit: Option<i64>,
+}
+
+fn get_db_path() -> Result<PathBuf, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    Ok(data_dir.join("job_history.db"))
+}
+
+fn connect_db() -> Result<Connection, String> {
+    let conn = Connection::open(get_db_path()?).map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "busy_timeout", 5000)
+        .map_err(|e| e.to_string())?;
+    Ok(conn)
+}
+
+fn create_schema(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS job_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            job_type TEXT NOT NULL,
+            job_id TEXT,
+            started_at INTEGER NOT NULL,
+            finished_at INTEGER NOT NULL,
+            status TEXT NOT NULL,
+            detail TEXT,
+            error TEXT
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_job_runs_started ON job_runs (started_at DESC)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn prune(conn: &Connection, retention_days: u32) -> Result<usize, String> {
+    let cutoff = chrono::Utc::now().timestamp_millis() - retention_days as i64 * 24 * 3600 * 1000;
+    conn.execute("DELETE FROM job_runs WHERE started_at < ?1", params![cutoff])
+        .map_err(|e| e.to_string())
+}
+
+fn retention_days() -> u32 {
+    crate::modules::config::load_app_config()
+        .map(|c| c.job_history_retention_days)
+        .unwrap_or(30)
+}
+
+/// Initialize the history database and drop expired runs
+pub fn init_db() -> Result<(), String> {
+    let conn = connect_db()?;
+    create_schema(&conn)?;
+    prune(&conn, retention_days())?;
+    LAST_PRUNE.store(chrono::Utc::now().timestamp(), Ordering::Relaxed);
+    Ok(())
+}
+
+fn insert(conn: &Connection, run: &JobRun) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO job_runs (job_type, job_id, started_at, finished_at, status, detail, error)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            run.job_type,
+            run.job_id,
+            run.started_at,
+            run.finished_at,
+            run.status.as_str(),
+            run.detail,
+            run.error
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Record a finished run; `started_at` is in milliseconds. `Ok` carries an optional summary.
+/// Failures to write are only logged so they never affect the job itself.
+pub fn record(
+    job_type: &str,
+    job_id: Option<&str>,
+    started_at: i64,
+    result: &Result<Option<String>, String>,
+) {
+    let run = JobRun {
+        id: 0,
+        job_type: job_type.to_string(),
+        job_id: job_id.map(str::to_string),
+        started_at,
+        finished_at: chrono::Utc::now().timestamp_millis(),
+        status: if result.is_ok() {
+            JobRunStatus::Success
+        } else {
+            JobRunStatus::Failed
+        },
+        detail: result.as_ref().ok().cloned().flatten(),
+        error: result.as_ref().err().cloned(),
+    };
+    write_run(&run);
+}
+
+/// Record an occurrence that was skipped instead of run
+pub fn record_skipped(job_type: &str, job_id: Option<&str>, reason: &str) {
+    let now = chrono::Utc::now().timestamp_millis();
+    write_run(&JobRun {
+        id: 0,
+        job_type: job_type.to_string(),
+        job_id: job_id.map(str::to_string),
+        started_at: now,
+        finished_at: now,
+        status: JobRunStatus::Skipped,
+        detail: Some(reason.to_string()),
+        error: None,
+    });
+}
+
+fn write_run(run: &JobRun) {
+    let write = || -> Result<(), String> {
+        let conn = connect_db()?;
+        insert(&conn, run)?;
+        let now = chrono::Utc::now().timestamp();
+        if now - LAST_PRUNE.load(Ordering::Relaxed) >= PRUNE_INTERVAL_SECS {
+            LAST_PRUNE.store(now, Ordering::Relaxed);
+            prune(&conn, retention_days())?;
+        }
+        Ok(())
+    };
+    if let Err(e) = write() {
+        logger::log_warn(&format!("Failed to record {} job run: {}", run.job_type, e));
+    }
+}
+
+fn query_runs(conn: &Connection, query: &JobHistoryQuery) -> Result<Vec<JobRun>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, job_type, job_id, started_at, finished_at, status, detail, error
+             FROM job_runs
+             WHERE (?1 IS NULL OR job_type = ?1)
+               AND (?2 IS NULL OR job_id = ?2)
+               AND (?3 IS NULL OR status = ?3)
+               AND (?4 IS NULL OR started_at >= ?4)
+               AND (?5 IS NULL OR started_at <= ?5)
+             ORDER BY started_at DESC, id DESC
+             LIMIT ?6",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(
+            params![
+                query.job_type,
+                query.job_id,
+                query.status.map(|s| s.as_str()),
+                query.from,
+                query.to,
+                query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, 5000)
+            ],
+            |row| {
+                Ok(JobRun {
+                    id: row.get(0)?,
+                    job_type: row.get(1)?,
+                    job_id: row.get(2)?,
+                    started_at: row.get(3)?,
+                    finished_at: row.get(4)?,
+                    status: JobRunStatus::from_str(&row.get::<_, String>(5)?),
+                    detail: row.get(6)?,
+                    error: row.get(7)?,
+                })
+            },
+        )
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Runs matching the query, newest first
+pub fn query(query: &JobHistoryQuery) -> Result<Vec<JobRun>, String> {
+    let conn = connect_db()?;
+    query_runs(&conn, query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_filters() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_schema(&conn).unwrap();
+        let run = |job_type: &str, started_at, status| JobRun {
+            id: 0,
+            job_type: job_type.to_string(),
+            job_id: None,
+            started_at,
+            finished_at: started_at + 10,
+            status,
+            detail: None,
+            error: None,
+        };
+        insert(&conn, &run("quota_refresh", 1_000, JobRunStatus::Success)).unwrap();
+        insert(&conn, &run("config_backup", 2_000, JobRunStatus::Failed)).unwrap();
+        insert(&conn, &run("quota_refresh", 3_000, JobRunStatus::Failed)).unwrap();
+
+        let all = query_runs(&conn, &JobHistoryQuery::default()).unwrap();
+        assert_eq!(
+            all.iter().map(|r| r.started_at).collect::<Vec<_>>(),
+            vec![3_000, 2_000, 1_000]
+        );
+
+        let failed_refresh = query_runs(
+            &conn,
+            &JobHistoryQuery {
+                job_type: Some("quota_refresh".to_string()),
+                status: Some(JobRunStatus::Failed),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(failed_refresh.len(), 1);
+        assert_eq!(failed_refresh[0].started_at, 3_000);
+
+        let since = query_runs(
+            &conn,
+            &JobHistoryQuery {
+                from: Some(2_000),
+                limit: Some(1),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(since.len(), 1);
+        assert_eq!(since[0].started_at, 3_000);
+    }
+}
@@ -0,0 +1,98 @@
+//! 全局紧急停止
+//!
+//! 一次操作内依次：停止定时任务（取消进行中的任务）-> 立即关闭反代与 MITM 代理 ->
+//! 关闭所有正在运行的受管实例。用于出现异常时让所有组件立即停下，
+//! 操作期间持有全局锁，重复触发会被拒绝而不是交错执行。
+
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+
+use crate::modules::{instance, logger, process, scheduler};
+
+/// 串行化紧急停止操作
+static HALT_LOCK: once_cell::sync::Lazy<tokio::sync::Mutex<()>> =
+    once_cell::sync::Lazy::new(|| tokio::sync::Mutex::new(()));
+
+/// 关闭单个实例的超时（秒）
+const INSTANCE_CLOSE_TIMEOUT_SECS: u64 = 10;
+
+/// 紧急停止结果
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct HaltReport {
+    /// 被取消的进行中的定时任务数
+    pub jobs_cancelled: usize,
+    pub proxy_stopped: bool,
+    /// 被强制断开的反代连接数
+    pub proxy_connections_aborted: usize,
+    pub mitm_stopped: bool,
+    pub stopped_instances: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// 停止所有受管组件
+pub async fn halt_all<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> Result<HaltReport, String> {
+    let Ok(_guard) = HALT_LOCK.try_lock() else {
+        return Err("halt_in_progress".to_string());
+    };
+    let mut report = HaltReport::default();
+    logger::log_warn("Kill switch triggered: halting scheduler, proxy and all instances");
+
+    // 1. 先停定时任务，避免其在停止过程中再次拉起实例或发起请求
+    report.jobs_cancelled = scheduler::halt_jobs();
+
+    // 2. 立即关闭反代（不等待进行中的请求排空）
+    if let Some(state) = app.try_state::<crate::commands::proxy::ProxyServiceState>() {
+        let instance = state.instance.write().await.take();
+        if let Some(instance) = instance {
+            report.proxy_connections_aborted = instance.axum_server.abort().await;
+            let _ = instance.server_handle.await;
+            if let Err(e) = crate::proxy::warm_state::save(&instance.token_manager) {
+                report.errors.push(format!("proxy warm state: {}", e));
+            }
+            report.proxy_stopped = true;
+        }
+    }
+
+    // 3. 关闭 MITM 代理
+    if let Some(state) = app.try_state::<crate::mitm::MitmServiceState>() {
+        let mut lock = state.instance.write().await;
+        if lock.is_some() {
+            crate::mitm::server::stop_mitm_service(&mut lock).await;
+            report.mitm_stopped = true;
+        }
+    }
+
+    // 4. 并行关闭所有运行中的实例
+    let instances = instance::list_instances().unwrap_or_default();
+    let mut handles = Vec::new();
+    for inst in instances {
+        handles.push(tokio::task::spawn_blocking(move || {
+            let running = if inst.is_default {
+                process::is_default_instance_running()
+            } else {
+                process::is_instance_running(&inst.user_data_dir)
+            };
+            if !running {
+                return None;
+            }
+            Some(
+                process::close_instance(&inst.user_data_dir, INSTANCE_CLOSE_TIMEOUT_SECS)
+                    .map(|_| inst.id.clone())
+                    .map_err(|e| format!("stop instance {}: {}", inst.name, e)),
+            )
+        }));
+    }
+    for handle in handles {
+        match handle.await {
+            Ok(Some(Ok(id))) => report.stopped_instances.push(id),
+            Ok(Some(Err(e))) => report.errors.push(e),
+            Ok(None) => {}
+            Err(e) => report.errors.push(e.to_string()),
+        }
+    }
+
+    logger::log_warn(&format!("Kill switch finished: {:?}", report));
+    let _ = app.emit("app://halted", &report);
+    crate::modules::tray::update_tray_menus(app);
+    Ok(report)
+}
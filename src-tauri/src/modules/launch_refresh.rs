@@ -0,0 +1,250 @@
+//! Antigravity 自动更新后的启动设置校验
+//!
+//! Antigravity 可能在管理器之外自动更新并重启，此时实例保存的 last_launch_args、
+//! 自定义可执行文件路径可能已经失效。这里通过可执行文件指纹（路径 + 大小 + 修改时间）
+//! 低成本地检测变化，变化后重新读取版本号并校验/刷新所有实例的启动设置。
+
+use serde::Serialize;
+use std::sync::Mutex;
+
+use crate::models::Instance;
+use crate::modules::{config, instance, logger, process, version};
+
+/// 上次检查时的可执行文件指纹
+static LAST_FINGERPRINT: once_cell::sync::Lazy<Mutex<Option<String>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(None));
+
+/// 单个实例的校验结果
+#[derive(Debug, Clone, Serialize)]
+pub struct InstanceLaunchRefresh {
+    pub instance_id: String,
+    pub name: String,
+    /// 保存启动参数时的版本
+    pub previous_version: Option<String>,
+    /// 执行的修正："custom_executable_cleared" | "launch_args_recaptured" |
+    /// "launch_args_reset" | "user_data_dir_mismatch"
+    pub actions: Vec<String>,
+}
+
+/// 校验结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LaunchRefreshReport {
+    pub current_version: Option<String>,
+    /// 当前使用的可执行文件路径
+    pub executable_path: Option<String>,
+    /// 全局配置中失效的可执行文件路径（已替换为 executable_path 或清空）
+    pub replaced_global_executable: Option<String>,
+    pub instances: Vec<InstanceLaunchRefresh>,
+}
+
+impl LaunchRefreshReport {
+    pub fn has_changes(&self) -> bool {
+        self.replaced_global_executable.is_some() || !self.instances.is_empty()
+    }
+}
+
+fn fingerprint(path: &std::path::Path) -> Option<String> {
+    // macOS 的 .app 目录修改时间不一定随更新变化，优先使用 Info.plist
+    let target = if path.join("Contents/Info.plist").exists() {
+        path.join("Contents/Info.plist")
+    } else {
+        path.to_path_buf()
+    };
+    let meta = std::fs::metadata(&target).ok()?;
+    let modified = meta
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    Some(format!("{}|{}|{}", path.display(), meta.len(), modified))
+}
+
+/// 当前生效的可执行文件路径（全局配置优先）
+fn current_executable() -> Option<std::path::PathBuf> {
+    config::load_app_config()
+        .ok()
+        .and_then(|c| c.antigravity_executable)
+        .map(std::path::PathBuf::from)
+        .filter(|p| p.exists())
+        .or_else(process::get_antigravity_executable_path)
+}
+
+/// 可执行文件是否与上次检查时不同（首次调用视为变化）
+pub fn executable_changed() -> bool {
+    let current = current_executable().and_then(|p| fingerprint(&p));
+    let mut last = LAST_FINGERPRINT.lock().unwrap();
+    if current.is_none() || *last == current {
+        return false;
+    }
+    *last = current;
+    true
+}
+
+/// 保存的参数中 --user-data-dir 是否指向其他目录
+fn user_data_dir_mismatch(instance: &Instance, args: &[String]) -> bool {
+    match process::extract_user_data_dir(args) {
+        Some(dir) => {
+            process::normalize_dir_for_match(&dir)
+                != process::normalize_dir_for_match(&instance.user_data_dir.to_string_lossy())
+        }
+        // 非默认实例缺少 --user-data-dir 会启动到默认目录
+        None => !instance.is_default,
+    }
+}
+
+fn refresh_instance(
+    instance: &mut Instance,
+    current_version: Option<&str>,
+) -> Option<InstanceLaunchRefresh> {
+    let previous_version = instance.last_exe_version.clone();
+    let mut actions = Vec::new();
+
+    // 1. 自定义可执行文件路径已不存在（更新后安装目录变化），回退到全局配置
+    if let Some(path) = &instance.antigravity_executable {
+        if !std::path::Path::new(path).exists() {
+            logger::log_warn(&format!(
+                "[LaunchRefresh] 实例 {} 的自定义可执行文件已不存在: {}",
+                instance.name, path
+            ));
+            instance.antigravity_executable = None;
+            actions.push("custom_executable_cleared".to_string());
+        }
+    }
+
+    // 2. 保存的参数指向其他数据目录，直接丢弃
+    if let Some(args) = &instance.last_launch_args {
+        if user_data_dir_mismatch(instance, args) {
+            instance.last_launch_args = None;
+            actions.push("user_data_dir_mismatch".to_string());
+        }
+    }
+
+    // 3. 版本变化：运行中的实例重新采集参数，未运行的实例改用实例配置重新生成参数
+    let version_changed = match (previous_version.as_deref(), current_version) {
+        (Some(prev), Some(curr)) => prev != curr,
+        _ => false,
+    };
+    if version_changed && instance.last_launch_args.is_some() {
+        let running = if instance.is_default {
+            process::is_default_instance_running()
+        } else {
+            process::is_instance_running(&instance.user_data_dir)
+        };
+        let recaptured = running
+            .then(|| {
+                process::get_instance_root_pid_and_args(
+                    &instance.user_data_dir,
+                    instance.is_default,
+                    instance.last_root_pid,
+                )
+            })
+            .flatten()
+            .map(|(_, args)| args)
+            .filter(|args| !args.join(" ").contains("--type="));
+
+        match recaptured {
+            Some(args) => {
+                instance.last_launch_args = Some(args);
+                actions.push("launch_args_recaptured".to_string());
+            }
+            None => {
+                instance.last_launch_args = None;
+                actions.push("launch_args_reset".to_string());
+            }
+        }
+    }
+
+    let version_recorded =
+        current_version.is_some() && instance.last_exe_version.as_deref() != current_version;
+    if version_recorded {
+        instance.last_exe_version = current_version.map(|v| v.to_string());
+    }
+
+    if actions.is_empty() && !version_recorded {
+        return None;
+    }
+    if let Err(e) = instance::save_instance(instance) {
+        logger::log_error(&format!(
+            "[LaunchRefresh] 保存实例 {} 失败: {}",
+            instance.name, e
+        ));
+    }
+    if actions.is_empty() {
+        return None;
+    }
+
+    logger::log_info(&format!(
+        "[LaunchRefresh] 实例 {} 启动设置已更新 ({:?} -> {:?}): {:?}",
+        instance.name, previous_version, current_version, actions
+    ));
+    Some(InstanceLaunchRefresh {
+        instance_id: instance.id.clone(),
+        name: instance.name.clone(),
+        previous_version,
+        actions,
+    })
+}
+
+/// 校验并刷新所有实例的启动设置
+pub fn revalidate_launch_settings() -> Result<LaunchRefreshReport, String> {
+    let mut report = LaunchRefreshReport::default();
+
+    // 全局配置的可执行文件路径失效时，改用自动检测到的路径
+    let mut app_config = config::load_app_config()?;
+    if let Some(path) = app_config.antigravity_executable.clone() {
+        if !std::path::Path::new(&path).exists() {
+            let detected =
+                process::get_antigravity_executable_path().map(|p| p.to_string_lossy().to_string());
+            logger::log_warn(&format!(
+                "[LaunchRefresh] 配置的可执行文件已不存在: {}，替换为: {:?}",
+                path, detected
+            ));
+            app_config.antigravity_executable = detected;
+            config::save_app_config(&app_config, "launch_refresh")?;
+            report.replaced_global_executable = Some(path);
+        }
+    }
+
+    report.executable_path = current_executable().map(|p| p.to_string_lossy().to_string());
+    report.current_version = version::get_antigravity_version()
+        .ok()
+        .map(|v| v.short_version);
+
+    for mut inst in instance::list_instances()? {
+        if let Some(entry) = refresh_instance(&mut inst, report.current_version.as_deref()) {
+            report.instances.push(entry);
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_data_dir_mismatch() {
+        let inst = Instance::new(
+            "id".to_string(),
+            "test".to_string(),
+            std::path::PathBuf::from("/tmp/ag-profile"),
+        );
+        let args = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert!(!user_data_dir_mismatch(
+            &inst,
+            &args(&["--user-data-dir", "/tmp/ag-profile/"])
+        ));
+        assert!(!user_data_dir_mismatch(
+            &inst,
+            &args(&["--user-data-dir=/tmp/ag-profile"])
+        ));
+        assert!(user_data_dir_mismatch(
+            &inst,
+            &args(&["--user-data-dir", "/tmp/other"])
+        ));
+        assert!(user_data_dir_mismatch(&inst, &args(&["--new-window"])));
+    }
+}
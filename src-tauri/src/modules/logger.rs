@@ -1,5 +1,5 @@
 use tracing::{info, warn, error};
-use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use tracing_subscriber::{fmt, layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Registry};
 use std::fs;
 use std::path::PathBuf;
 use crate::modules::account::get_data_dir;
@@ -14,6 +14,10 @@ impl tracing_subscriber::fmt::time::FormatTime for LocalTimer {
     }
 }
 
+/// Handle used to change the log level at runtime (set once the logger is initialized)
+static FILTER_HANDLE: once_cell::sync::OnceCell<reload::Handle<EnvFilter, Registry>> =
+    once_cell::sync::OnceCell::new();
+
 pub fn get_log_dir() -> Result<PathBuf, String> {
     let data_dir = get_data_dir()?;
     let log_dir = data_dir.join("logs");
@@ -67,9 +71,14 @@ pub fn init_logger() {
         .with_level(true)
         .with_timer(LocalTimer);
 
-    // 4. Set filtering layer (default to INFO level to reduce log size)
+    // 4. Set filtering layer (RUST_LOG wins, otherwise the configured level, default INFO to reduce log size)
+    let level = crate::modules::config::load_app_config()
+        .map(|c| c.log_level)
+        .unwrap_or_else(|_| "info".to_string());
     let filter_layer = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| EnvFilter::new("info"));
+        .unwrap_or_else(|_| EnvFilter::try_new(&level).unwrap_or_else(|_| EnvFilter::new("info")));
+    let (filter_layer, filter_handle) = reload::Layer::new(filter_layer);
+    let _ = FILTER_HANDLE.set(filter_handle);
 
     // 5. Initialize global subscriber (use try_init to avoid crash on repeated initialization)
     let _ = tracing_subscriber::registry()
@@ -90,6 +99,21 @@ pub fn init_logger() {
     }
 }
 
+/// Change the log level without restarting (e.g. "debug", "info,hyper=warn").
+/// Ignored when RUST_LOG is set, which always takes precedence.
+pub fn set_log_level(level: &str) -> Result<(), String> {
+    if std::env::var("RUST_LOG").is_ok() {
+        return Ok(());
+    }
+    let handle = FILTER_HANDLE.get().ok_or("Logger not initialized")?;
+    let filter = EnvFilter::try_new(level).map_err(|e| format!("Invalid log level '{}': {}", level, e))?;
+    handle
+        .reload(filter)
+        .map_err(|e| format!("Failed to reload log filter: {}", e))?;
+    info!("Log level changed to {}", level);
+    Ok(())
+}
+
 /// Cleanup log files older than specified days
 pub fn cleanup_old_logs(days_to_keep: u64) -> Result<(), String> {
     use std::time::{SystemTime, UNIX_EPOCH};
@@ -0,0 +1,404 @@
+//! 登录时自动启动（按平台注册）
+//!
+//! - Windows：HKCU\Software\Microsoft\Windows\CurrentVersion\Run 注册表项
+//! - macOS：~/Library/LaunchAgents 下的 LaunchAgent plist
+//! - Linux：~/.config/autostart 下的 XDG desktop 文件
+//!
+//! 启动参数决定登录后的行为：`--minimized` 只在托盘运行，`--start-proxy` 无论配置如何都启动反代服务。
+//! 状态直接从系统中的注册项读取，不在配置中另存一份。
+//! 使用 `--data-dir` 的配置档在注册项名称后附加数据目录哈希，各配置档的登录项互不覆盖。
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::modules::logger;
+
+/// 启动后隐藏主窗口
+pub const ARG_MINIMIZED: &str = "--minimized";
+/// 启动后立即启动反代服务
+pub const ARG_START_PROXY: &str = "--start-proxy";
+
+const ENTRY_NAME: &str = "Antigravity Tools";
+#[cfg(target_os = "macos")]
+const LAUNCH_AGENT_LABEL: &str = "com.lbjlaq.antigravity-tools.login";
+#[cfg(target_os = "linux")]
+/// desktop 文件名 (不含扩展名)
+const DESKTOP_FILE: &str = "antigravity-tools";
+
+/// 自定义数据目录配置档的注册项后缀 (数据目录路径哈希的前 8 位)
+fn profile_suffix() -> Option<String> {
+    crate::modules::account::data_dir_override().map(|dir| {
+        let digest = format!("{:x}", Sha256::digest(dir.to_string_lossy().as_bytes()));
+        digest[..8].to_string()
+    })
+}
+
+/// 注册表值 / desktop 文件中的名称
+#[cfg_attr(target_os = "macos", allow(dead_code))]
+fn entry_name() -> String {
+    match profile_suffix() {
+        Some(suffix) => format!("{} ({})", ENTRY_NAME, suffix),
+        None => ENTRY_NAME.to_string(),
+    }
+}
+
+static STARTED_MINIMIZED: AtomicBool = AtomicBool::new(false);
+static START_PROXY_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// 登录启动选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginItemOptions {
+    /// 只在托盘运行，不显示主窗口
+    #[serde(default = "default_true")]
+    pub minimized: bool,
+    /// 同时启动反代服务（不受 proxy.auto_start 影响）
+    #[serde(default)]
+    pub start_proxy: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for LoginItemOptions {
+    fn default() -> Self {
+        Self {
+            minimized: true,
+            start_proxy: false,
+        }
+    }
+}
+
+impl LoginItemOptions {
+    fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.minimized {
+            args.push(ARG_MINIMIZED.to_string());
+        }
+        if self.start_proxy {
+            args.push(ARG_START_PROXY.to_string());
+        }
+        // 自定义数据目录的配置档登录后使用同一目录启动
+        if let Some(dir) = crate::modules::account::data_dir_override() {
+            args.push(format!(
+                "{}={}",
+                crate::modules::account::DATA_DIR_ARG,
+                dir.display()
+            ));
+        }
+        args
+    }
+
+    fn from_args(args: &str) -> Self {
+        Self {
+            minimized: args.contains(ARG_MINIMIZED),
+            start_proxy: args.contains(ARG_START_PROXY),
+        }
+    }
+}
+
+/// 登录启动状态
+#[derive(Debug, Clone, Serialize)]
+pub struct LoginItemStatus {
+    pub enabled: bool,
+    pub options: LoginItemOptions,
+    /// "windows" | "macos" | "linux"
+    pub platform: String,
+    /// 注册项位置（注册表路径或文件路径）
+    pub location: String,
+    /// 注册的可执行文件与当前程序不一致（程序被移动或更新后路径变化）
+    pub stale: bool,
+}
+
+/// 记录本次进程的启动参数
+pub fn init_from_args() {
+    let args: Vec<String> = std::env::args().collect();
+    STARTED_MINIMIZED.store(args.iter().any(|a| a == ARG_MINIMIZED), Ordering::SeqCst);
+    START_PROXY_REQUESTED.store(args.iter().any(|a| a == ARG_START_PROXY), Ordering::SeqCst);
+}
+
+/// 本次是否以最小化方式启动
+pub fn started_minimized() -> bool {
+    STARTED_MINIMIZED.load(Ordering::SeqCst)
+}
+
+/// 本次启动是否要求启动反代服务
+pub fn start_proxy_requested() -> bool {
+    START_PROXY_REQUESTED.load(Ordering::SeqCst)
+}
+
+/// 含空格的参数（如数据目录路径）加引号
+#[cfg_attr(target_os = "macos", allow(dead_code))]
+fn quote_arg(arg: &str) -> String {
+    if arg.contains(' ') {
+        format!("\"{}\"", arg)
+    } else {
+        arg.to_string()
+    }
+}
+
+/// 注册到登录项的程序路径（AppImage 使用镜像本身的路径）
+pub(crate) fn executable_path() -> Result<PathBuf, String> {
+    #[cfg(target_os = "linux")]
+    if let Ok(appimage) = std::env::var("APPIMAGE") {
+        return Ok(PathBuf::from(appimage));
+    }
+    std::env::current_exe().map_err(|e| format!("failed_to_get_current_exe: {}", e))
+}
+
+/// 注册登录启动
+pub fn enable(options: &LoginItemOptions) -> Result<(), String> {
+    let exe = executable_path()?;
+    platform::register(&exe, &options.args())?;
+    logger::log_info(&format!("已注册登录启动: {:?} {:?}", exe, options.args()));
+    Ok(())
+}
+
+/// 取消登录启动（未注册视为成功）
+pub fn disable() -> Result<(), String> {
+    platform::unregister()?;
+    logger::log_info("已取消登录启动");
+    Ok(())
+}
+
+/// 查询登录启动状态
+pub fn status() -> Result<LoginItemStatus, String> {
+    let registered = platform::read()?;
+    let current = executable_path().ok();
+    let (enabled, options, stale) = match registered {
+        Some(command) => {
+            let stale = current
+                .as_ref()
+                .is_some_and(|exe| !command.contains(&exe.to_string_lossy().to_string()));
+            (true, LoginItemOptions::from_args(&command), stale)
+        }
+        None => (false, LoginItemOptions::default(), false),
+    };
+    Ok(LoginItemStatus {
+        enabled,
+        options,
+        platform: std::env::consts::OS.to_string(),
+        location: platform::location(),
+        stale,
+    })
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::entry_name;
+    use std::path::Path;
+    use winreg::enums::{HKEY_CURRENT_USER, KEY_READ, KEY_SET_VALUE};
+    use winreg::RegKey;
+
+    const RUN_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Run";
+
+    // 直接读写注册表 (UTF-16)，非 ASCII 路径不受控制台代码页影响
+    fn run_key(access: u32) -> std::io::Result<RegKey> {
+        RegKey::predef(HKEY_CURRENT_USER).open_subkey_with_flags(RUN_KEY, access)
+    }
+
+    pub fn location() -> String {
+        format!(r"HKCU\{}\{}", RUN_KEY, entry_name())
+    }
+
+    pub fn register(exe: &Path, args: &[String]) -> Result<(), String> {
+        let mut command = format!("\"{}\"", exe.display());
+        for arg in args {
+            command.push(' ');
+            command.push_str(&super::quote_arg(arg));
+        }
+        let (key, _) = RegKey::predef(HKEY_CURRENT_USER)
+            .create_subkey(RUN_KEY)
+            .map_err(|e| format!("failed_to_register_login_item: {}", e))?;
+        key.set_value(entry_name(), &command)
+            .map_err(|e| format!("failed_to_register_login_item: {}", e))
+    }
+
+    pub fn unregister() -> Result<(), String> {
+        let key = match run_key(KEY_SET_VALUE) {
+            Ok(key) => key,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(format!("failed_to_unregister_login_item: {}", e)),
+        };
+        // 注册项不存在视为已取消
+        match key.delete_value(entry_name()) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!("failed_to_unregister_login_item: {}", e)),
+        }
+    }
+
+    pub fn read() -> Result<Option<String>, String> {
+        let key = match run_key(KEY_READ) {
+            Ok(key) => key,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("failed_to_read_login_item: {}", e)),
+        };
+        match key.get_value::<String, _>(entry_name()) {
+            Ok(command) => Ok(Some(command)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("failed_to_read_login_item: {}", e)),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{profile_suffix, LAUNCH_AGENT_LABEL};
+    use std::path::{Path, PathBuf};
+
+    fn label() -> String {
+        match profile_suffix() {
+            Some(suffix) => format!("{}.{}", LAUNCH_AGENT_LABEL, suffix),
+            None => LAUNCH_AGENT_LABEL.to_string(),
+        }
+    }
+
+    fn plist_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join("Library/LaunchAgents")
+            .join(format!("{}.plist", label()))
+    }
+
+    fn escape(value: &str) -> String {
+        value
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    }
+
+    pub fn location() -> String {
+        plist_path().to_string_lossy().to_string()
+    }
+
+    pub fn register(exe: &Path, args: &[String]) -> Result<(), String> {
+        let mut program_args = format!(
+            "        <string>{}</string>\n",
+            escape(&exe.to_string_lossy())
+        );
+        for arg in args {
+            program_args.push_str(&format!("        <string>{}</string>\n", escape(arg)));
+        }
+        let plist = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+             <plist version=\"1.0\">\n\
+             <dict>\n\
+             \x20   <key>Label</key>\n\
+             \x20   <string>{}</string>\n\
+             \x20   <key>ProgramArguments</key>\n\
+             \x20   <array>\n{}\x20   </array>\n\
+             \x20   <key>RunAtLoad</key>\n\
+             \x20   <true/>\n\
+             </dict>\n\
+             </plist>\n",
+            escape(&label()),
+            program_args
+        );
+        let path = plist_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("failed_to_create_launch_agents_dir: {}", e))?;
+        }
+        std::fs::write(&path, plist).map_err(|e| format!("failed_to_register_login_item: {}", e))
+    }
+
+    pub fn unregister() -> Result<(), String> {
+        let path = plist_path();
+        if path.exists() {
+            std::fs::remove_file(&path)
+                .map_err(|e| format!("failed_to_unregister_login_item: {}", e))?;
+        }
+        Ok(())
+    }
+
+    pub fn read() -> Result<Option<String>, String> {
+        let path = plist_path();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let value = plist::Value::from_file(&path)
+            .map_err(|e| format!("failed_to_read_login_item: {}", e))?;
+        let args = value
+            .as_dictionary()
+            .and_then(|d| d.get("ProgramArguments"))
+            .and_then(|a| a.as_array())
+            .map(|a| {
+                a.iter()
+                    .filter_map(|v| v.as_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default();
+        Ok(Some(args))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{entry_name, profile_suffix, DESKTOP_FILE};
+    use std::path::{Path, PathBuf};
+
+    fn desktop_path() -> PathBuf {
+        let file = match profile_suffix() {
+            Some(suffix) => format!("{}-{}.desktop", DESKTOP_FILE, suffix),
+            None => format!("{}.desktop", DESKTOP_FILE),
+        };
+        dirs::config_dir()
+            .unwrap_or_else(|| dirs::home_dir().unwrap_or_default().join(".config"))
+            .join("autostart")
+            .join(file)
+    }
+
+    pub fn location() -> String {
+        desktop_path().to_string_lossy().to_string()
+    }
+
+    pub fn register(exe: &Path, args: &[String]) -> Result<(), String> {
+        let mut exec = format!("\"{}\"", exe.display());
+        for arg in args {
+            exec.push(' ');
+            exec.push_str(&super::quote_arg(arg));
+        }
+        let content = format!(
+            "[Desktop Entry]\nType=Application\nName={}\nExec={}\nTerminal=false\nX-GNOME-Autostart-enabled=true\n",
+            entry_name(),
+            exec
+        );
+        let path = desktop_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("failed_to_create_autostart_dir: {}", e))?;
+        }
+        std::fs::write(&path, content).map_err(|e| format!("failed_to_register_login_item: {}", e))
+    }
+
+    pub fn unregister() -> Result<(), String> {
+        let path = desktop_path();
+        if path.exists() {
+            std::fs::remove_file(&path)
+                .map_err(|e| format!("failed_to_unregister_login_item: {}", e))?;
+        }
+        Ok(())
+    }
+
+    pub fn read() -> Result<Option<String>, String> {
+        let path = desktop_path();
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            return Ok(None);
+        };
+        if content
+            .lines()
+            .any(|l| l.trim() == "X-GNOME-Autostart-enabled=false" || l.trim() == "Hidden=true")
+        {
+            return Ok(None);
+        }
+        Ok(content
+            .lines()
+            .find_map(|l| l.strip_prefix("Exec="))
+            .map(|s| s.to_string()))
+    }
+}
@@ -1,5 +1,5 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use serde_json::Value;
 use base64::{Engine as _, engine::general_purpose};
 use crate::models::{TokenData, Account};
@@ -228,6 +228,24 @@ pub async fn import_from_db() -> Result<Account, String> {
     import_from_custom_db_path(db_path.to_string_lossy().to_string()).await
 }
 
+/// Import the account signed in to an existing Antigravity installation's user-data-dir.
+/// The refresh token found there is only sent to Google when the user consented to the import.
+pub async fn import_from_user_data_dir(user_data_dir: &Path, consent: bool) -> Result<Account, String> {
+    if !consent {
+        return Err("consent_required".to_string());
+    }
+    if !user_data_dir.is_dir() {
+        return Err(format!("User data directory does not exist: {:?}", user_data_dir));
+    }
+
+    let db_path = db::get_db_path_for_instance(user_data_dir);
+    crate::modules::logger::log_info(&format!(
+        "Importing signed-in account from {:?}",
+        db_path
+    ));
+    import_from_custom_db_path(db_path.to_string_lossy().to_string()).await
+}
+
 /// Get current Refresh Token from database (common logic)
 pub fn extract_refresh_token_from_file(db_path: &PathBuf) -> Result<String, String> {
     if !db_path.exists() {
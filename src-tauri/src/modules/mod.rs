@@ -1,19 +1,47 @@
 pub mod account;
+pub mod cache;
 pub mod config;
+pub mod config_audit;
+pub mod config_backup;
+pub mod config_secrets;
+pub mod config_sync;
+pub mod config_transfer;
+pub mod config_validation;
+pub mod config_watcher;
+pub mod crash_loop;
+pub mod cron;
 pub mod db;
+pub mod delayed_tasks;
 pub mod device;
 pub mod http_api;
 pub mod i18n;
 pub mod instance;
+pub mod instance_log;
+pub mod job_history;
+pub mod kill_switch;
+pub mod launch_refresh;
 pub mod logger;
+pub mod login_item;
 pub mod migration;
+pub mod model_discovery;
 pub mod oauth;
 pub mod oauth_server;
 pub mod process;
+pub mod profile_watcher;
 pub mod proxy_db;
 pub mod quota;
+pub mod quota_alerts;
+pub mod quota_history;
+pub mod quota_journal;
+pub mod quota_matrix;
+pub mod reauth;
+pub mod scheduled_tasks;
 pub mod scheduler;
+pub mod self_update;
+pub mod shutdown;
+pub mod token_budget;
 pub mod token_stats;
+pub mod token_store;
 pub mod tray;
 pub mod update_checker;
 pub mod version;
@@ -0,0 +1,233 @@
+//! 上游新模型检测
+//!
+//! 每次为账号拉取配额 (fetchAvailableModels) 时顺带上报上游返回的模型列表，
+//! 与已记录的模型集合比对；出现新模型时推送 "models://discovered" 事件，
+//! 并可按配置自动为其添加直通路由别名，使新模型在新版本发布前即可使用。
+
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+use crate::modules::{account, config, logger};
+
+const KNOWN_MODELS_FILE: &str = "known_models.json";
+/// 已知模型的 last_seen 只在超过该间隔后才更新落盘
+const LAST_SEEN_RESOLUTION_SECS: i64 = 24 * 3600;
+
+static APP_HANDLE: OnceCell<tauri::AppHandle> = OnceCell::new();
+
+/// 串行化对 known_models.json 的读改写（多个账号的配额刷新并发进行）
+static STORE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// 已记录的上游模型
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnownModel {
+    pub first_seen: i64,
+    /// 最近一次在上游列表中出现的时间 (按天精度)
+    pub last_seen: i64,
+    /// 首次发现该模型的账号
+    pub discovered_by: String,
+    /// 能力表中是否有对应规则 (否则使用兜底能力)
+    #[serde(default)]
+    pub has_capability_entry: bool,
+    /// 是否已自动添加直通路由别名
+    #[serde(default)]
+    pub alias_added: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct KnownModelStore {
+    #[serde(default)]
+    models: BTreeMap<String, KnownModel>,
+}
+
+/// 保存 AppHandle，用于推送事件与热更新反代映射
+pub fn init(app: tauri::AppHandle) {
+    let _ = APP_HANDLE.set(app);
+}
+
+fn store_path() -> Result<PathBuf, String> {
+    Ok(account::get_data_dir()?.join(KNOWN_MODELS_FILE))
+}
+
+fn load_store() -> Result<Option<KnownModelStore>, String> {
+    let path = store_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("failed_to_read_known_models: {}", e))?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("failed_to_parse_known_models: {}", e))
+}
+
+fn save_store(store: &KnownModelStore) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(store)
+        .map_err(|e| format!("failed_to_serialize_known_models: {}", e))?;
+    std::fs::write(store_path()?, content)
+        .map_err(|e| format!("failed_to_write_known_models: {}", e))
+}
+
+/// 只关心对话类模型，过滤上游返回的内部模型 ID
+fn is_relevant(model: &str) -> bool {
+    model.contains("gemini") || model.contains("claude")
+}
+
+/// 上报某账号当前可用的上游模型列表
+pub fn observe_models(email: &str, models: &[String]) {
+    let discovery = config::load_app_config()
+        .map(|c| c.model_discovery)
+        .unwrap_or_default();
+    if !discovery.enabled {
+        return;
+    }
+
+    let mut discovered = match record_models(email, models) {
+        Ok(d) => d,
+        Err(e) => {
+            logger::log_warn(&format!("Model discovery failed: {}", e));
+            return;
+        }
+    };
+    if discovered.is_empty() {
+        return;
+    }
+
+    logger::log_info(&format!(
+        "Discovered new upstream models via {}: {:?}",
+        email,
+        discovered.iter().map(|(name, _)| name).collect::<Vec<_>>()
+    ));
+
+    if discovery.auto_add_aliases {
+        match add_passthrough_aliases(&discovered) {
+            Ok(added) => {
+                for (name, model) in discovered.iter_mut() {
+                    model.alias_added = added.contains(name);
+                }
+                if let Err(e) = mark_aliases_added(&added) {
+                    logger::log_warn(&format!("Failed to record discovered model aliases: {}", e));
+                }
+            }
+            Err(e) => logger::log_warn(&format!("Failed to add aliases for new models: {}", e)),
+        }
+    }
+
+    if let Some(app) = APP_HANDLE.get() {
+        let payload: Vec<_> = discovered
+            .iter()
+            .map(|(name, model)| serde_json::json!({ "model": name, "info": model }))
+            .collect();
+        let _ = app.emit("models://discovered", payload);
+    }
+}
+
+/// 合并到已知模型集合，返回首次出现的模型。
+/// 首次运行 (尚无记录) 只建立基线，不视为新模型。
+fn record_models(email: &str, models: &[String]) -> Result<Vec<(String, KnownModel)>, String> {
+    let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (mut store, seeding) = match load_store()? {
+        Some(store) => (store, false),
+        None => (KnownModelStore::default(), true),
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let mut changed = seeding;
+    let mut discovered = Vec::new();
+    for name in models.iter().filter(|m| is_relevant(m)) {
+        if let Some(known) = store.models.get_mut(name) {
+            // last_seen 按天精度更新，避免每次配额刷新都重写文件
+            if now - known.last_seen >= LAST_SEEN_RESOLUTION_SECS {
+                known.last_seen = now;
+                changed = true;
+            }
+            continue;
+        }
+        changed = true;
+        let model = KnownModel {
+            first_seen: now,
+            last_seen: now,
+            discovered_by: email.to_string(),
+            has_capability_entry: crate::proxy::common::model_capabilities::is_known(name),
+            alias_added: false,
+        };
+        store.models.insert(name.clone(), model.clone());
+        if !seeding {
+            discovered.push((name.clone(), model));
+        }
+    }
+
+    if changed {
+        save_store(&store)?;
+    }
+    Ok(discovered)
+}
+
+fn mark_aliases_added(names: &[String]) -> Result<(), String> {
+    if names.is_empty() {
+        return Ok(());
+    }
+    let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(mut store) = load_store()? else {
+        return Ok(());
+    };
+    for name in names {
+        if let Some(model) = store.models.get_mut(name) {
+            model.alias_added = true;
+        }
+    }
+    save_store(&store)
+}
+
+/// 为新模型添加直通映射 (model -> model)，避免被默认路由回退到其他模型。
+/// 已有自定义映射或默认路由本身即直通的模型不做处理。
+/// 在配置写锁内完成读改写，不会与同时进行的保存配置互相覆盖。
+fn add_passthrough_aliases(models: &[(String, KnownModel)]) -> Result<Vec<String>, String> {
+    let mut added = Vec::new();
+    let saved = config::update_app_config("model_discovery", |app_config| {
+        for (name, _) in models {
+            if app_config.proxy.custom_mapping.contains_key(name) {
+                continue;
+            }
+            if crate::proxy::common::model_mapping::map_claude_model_to_gemini(name) == *name {
+                continue;
+            }
+            app_config
+                .proxy
+                .custom_mapping
+                .insert(name.clone(), name.clone());
+            added.push(name.clone());
+        }
+        !added.is_empty()
+    })?;
+    let Some(app_config) = saved else {
+        return Ok(added);
+    };
+
+    logger::log_info(&format!("Added passthrough aliases for new models: {:?}", added));
+
+    // 热更新正在运行的反代
+    if let Some(app) = APP_HANDLE.get() {
+        let app = app.clone();
+        let proxy_config = app_config.proxy.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Some(state) = app.try_state::<crate::commands::proxy::ProxyServiceState>() {
+                if let Some(instance) = state.instance.read().await.as_ref() {
+                    instance.axum_server.update_mapping(&proxy_config).await;
+                }
+            }
+            let _ = app.emit("config://updated", ());
+        });
+    }
+    Ok(added)
+}
+
+/// 列出已记录的上游模型
+pub fn list_known_models() -> Result<BTreeMap<String, KnownModel>, String> {
+    let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    Ok(load_store()?.map(|s| s.models).unwrap_or_default())
+}
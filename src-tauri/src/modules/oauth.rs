@@ -1,4 +1,7 @@
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tokio::sync::watch;
 
 // Google OAuth configuration
 const CLIENT_ID: &str = "1071006060591-tmhssin2h21lcre235vtolojh4g403ep.apps.googleusercontent.com";
@@ -7,8 +10,28 @@ const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
 const USERINFO_URL: &str = "https://www.googleapis.com/oauth2/v2/userinfo";
 
 const AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+const DEVICE_CODE_URL: &str = "https://oauth2.googleapis.com/device/code";
+const DEVICE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
 
-#[derive(Debug, Serialize, Deserialize)]
+const OAUTH_SCOPES: [&str; 5] = [
+    "https://www.googleapis.com/auth/cloud-platform",
+    "https://www.googleapis.com/auth/userinfo.email",
+    "https://www.googleapis.com/auth/userinfo.profile",
+    "https://www.googleapis.com/auth/cclog",
+    "https://www.googleapis.com/auth/experimentsandconfigs",
+];
+
+// Google only grants a handful of scopes to "TVs and Limited Input devices" clients
+const DEVICE_OAUTH_SCOPES: [&str; 3] = ["openid", "email", "profile"];
+
+/// Upper bound of the poll interval while the token endpoint keeps failing
+const DEVICE_POLL_MAX_INTERVAL_SECS: u64 = 60;
+
+// Cancellation signal of the device flow currently being polled
+static DEVICE_FLOW_CANCEL: Lazy<Mutex<Option<watch::Sender<bool>>>> =
+    Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenResponse {
     pub access_token: String,
     pub expires_in: i64,
@@ -48,17 +71,11 @@ impl UserInfo {
 }
 
 
-/// Generate OAuth authorization URL
-pub fn get_auth_url(redirect_uri: &str) -> String {
-    let scopes = vec![
-        "https://www.googleapis.com/auth/cloud-platform",
-        "https://www.googleapis.com/auth/userinfo.email",
-        "https://www.googleapis.com/auth/userinfo.profile",
-        "https://www.googleapis.com/auth/cclog",
-        "https://www.googleapis.com/auth/experimentsandconfigs"
-    ].join(" ");
+/// Generate OAuth authorization URL; `login_hint` pre-selects the Google account to sign in with
+pub fn get_auth_url(redirect_uri: &str, state: &str, login_hint: Option<&str>) -> String {
+    let scopes = OAUTH_SCOPES.join(" ");
 
-    let params = vec![
+    let mut params = vec![
         ("client_id", CLIENT_ID),
         ("redirect_uri", redirect_uri),
         ("response_type", "code"),
@@ -66,15 +83,19 @@ pub fn get_auth_url(redirect_uri: &str) -> String {
         ("access_type", "offline"),
         ("prompt", "consent"),
         ("include_granted_scopes", "true"),
+        ("state", state),
     ];
-    
+    if let Some(hint) = login_hint {
+        params.push(("login_hint", hint));
+    }
+
     let url = url::Url::parse_with_params(AUTH_URL, &params).expect("Invalid Auth URL");
     url.to_string()
 }
 
 /// Exchange authorization code for token
 pub async fn exchange_code(code: &str, redirect_uri: &str) -> Result<TokenResponse, String> {
-    let client = crate::utils::http::get_client();
+    let client = crate::utils::http::get_client()?;
     
     let params = [
         ("client_id", CLIENT_ID),
@@ -120,43 +141,397 @@ pub async fn exchange_code(code: &str, redirect_uri: &str) -> Result<TokenRespon
     }
 }
 
-/// Refresh access_token using refresh_token
-pub async fn refresh_access_token(refresh_token: &str) -> Result<TokenResponse, String> {
-    let client = crate::utils::http::get_client();
-    
+/// Device authorization: the user enters `user_code` at `verification_url` on any device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceCodeResponse {
+    pub device_code: String,
+    pub user_code: String,
+    #[serde(alias = "verification_uri")]
+    pub verification_url: String,
+    /// Seconds until the device code expires
+    pub expires_in: i64,
+    /// Minimum seconds between two polls
+    #[serde(default = "default_device_poll_interval")]
+    pub interval: u64,
+}
+
+fn default_device_poll_interval() -> u64 {
+    5
+}
+
+#[derive(Debug, Deserialize)]
+struct DeviceTokenError {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+/// OAuth client used for the device flow. The built-in client is a desktop client, which
+/// Google does not allow to use the device grant, so a "TVs and Limited Input devices"
+/// client has to be configured (see docs/device-oauth.md).
+struct DeviceClient {
+    id: String,
+    secret: String,
+    scopes: String,
+}
+
+fn device_client() -> Result<DeviceClient, String> {
+    let config = crate::modules::config::load_app_config()
+        .map(|c| c.oauth)
+        .unwrap_or_default();
+    if config.device_client_id.trim().is_empty() {
+        return Err(
+            "device_oauth_client_not_configured: set oauth.device_client_id to a \"TVs and Limited Input devices\" OAuth client"
+                .to_string(),
+        );
+    }
+    let scopes = if config.device_scopes.is_empty() {
+        DEVICE_OAUTH_SCOPES.join(" ")
+    } else {
+        config.device_scopes.join(" ")
+    };
+    Ok(DeviceClient {
+        id: config.device_client_id.trim().to_string(),
+        secret: config.device_client_secret,
+        scopes,
+    })
+}
+
+/// Request a device code for authorizing without a local browser redirect
+pub async fn request_device_code() -> Result<DeviceCodeResponse, String> {
+    let client = crate::utils::http::get_client()?;
+    let device_client = device_client()?;
+
+    let response = client
+        .post(DEVICE_CODE_URL)
+        .form(&[
+            ("client_id", device_client.id.as_str()),
+            ("scope", device_client.scopes.as_str()),
+        ])
+        .send()
+        .await
+        .map_err(|e| format!("Device code request failed: {}", e))?;
+
+    if response.status().is_success() {
+        response
+            .json::<DeviceCodeResponse>()
+            .await
+            .map_err(|e| format!("Device code parsing failed: {}", e))
+    } else {
+        let error_text = response.text().await.unwrap_or_default();
+        Err(format!("Device code request failed: {}", error_text))
+    }
+}
+
+/// Unregisters the cancellation signal when polling ends, unless a newer flow replaced it
+struct DeviceFlowRegistration(watch::Sender<bool>);
+
+impl Drop for DeviceFlowRegistration {
+    fn drop(&mut self) {
+        if let Ok(mut current) = DEVICE_FLOW_CANCEL.lock() {
+            if current.as_ref().is_some_and(|tx| tx.same_channel(&self.0)) {
+                *current = None;
+            }
+        }
+    }
+}
+
+/// Delay after a failed poll: doubled, capped, never shorter than the poll interval
+fn device_poll_backoff(delay: u64, interval: u64) -> u64 {
+    (delay * 2).min(DEVICE_POLL_MAX_INTERVAL_SECS).max(interval)
+}
+
+/// Poll the token endpoint until the user approves or denies the device code,
+/// the code expires or the flow is cancelled with `cancel_device_flow`
+pub async fn poll_device_token(device: &DeviceCodeResponse) -> Result<TokenResponse, String> {
+    let client = crate::utils::http::get_client()?;
+    let device_client = device_client()?;
+    let (cancel_tx, mut cancel_rx) = watch::channel(false);
+    if let Ok(mut current) = DEVICE_FLOW_CANCEL.lock() {
+        // A new device flow replaces any previous one
+        if let Some(previous) = current.replace(cancel_tx.clone()) {
+            let _ = previous.send(true);
+        }
+    }
+    let _registration = DeviceFlowRegistration(cancel_tx);
+
+    let deadline = chrono::Utc::now().timestamp() + device.expires_in;
+    let mut interval = device.interval.max(1);
+    // Next sleep: the poll interval, stretched while the token endpoint keeps failing
+    let mut delay = interval;
     let params = [
-        ("client_id", CLIENT_ID),
-        ("client_secret", CLIENT_SECRET),
+        ("client_id", device_client.id.as_str()),
+        ("client_secret", device_client.secret.as_str()),
+        ("device_code", device.device_code.as_str()),
+        ("grant_type", DEVICE_GRANT_TYPE),
+    ];
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(std::time::Duration::from_secs(delay)) => {}
+            _ = cancel_rx.changed() => return Err("OAuth cancelled".to_string()),
+        }
+        if chrono::Utc::now().timestamp() >= deadline {
+            return Err("Device code expired, please start the authorization again".to_string());
+        }
+
+        // Network errors, 429 and 5xx do not end the flow: back off and poll again
+        let response = match client.post(TOKEN_URL).form(&params).send().await {
+            Ok(response)
+                if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS
+                    || response.status().is_server_error() =>
+            {
+                delay = device_poll_backoff(delay, interval);
+                crate::modules::logger::log_warn(&format!(
+                    "Device token request returned HTTP {}, polling again in {}s",
+                    response.status().as_u16(),
+                    delay
+                ));
+                continue;
+            }
+            Ok(response) => {
+                delay = interval;
+                response
+            }
+            Err(e) => {
+                delay = device_poll_backoff(delay, interval);
+                crate::modules::logger::log_warn(&format!(
+                    "Device token request failed, polling again in {}s: {}",
+                    delay, e
+                ));
+                continue;
+            }
+        };
+
+        if response.status().is_success() {
+            let token_res = response
+                .json::<TokenResponse>()
+                .await
+                .map_err(|e| format!("Token parsing failed: {}", e))?;
+            crate::modules::logger::log_info("Device authorization completed");
+            return Ok(token_res);
+        }
+
+        let error_text = response.text().await.unwrap_or_default();
+        let error = serde_json::from_str::<DeviceTokenError>(&error_text)
+            .map_err(|_| format!("Device token request failed: {}", error_text))?;
+        match error.error.as_str() {
+            "authorization_pending" => {}
+            // The server asks us to poll less often
+            "slow_down" => {
+                interval += 5;
+                delay = interval;
+            }
+            "access_denied" => return Err("Authorization was denied by the user".to_string()),
+            "expired_token" => {
+                return Err("Device code expired, please start the authorization again".to_string())
+            }
+            _ => {
+                return Err(format!(
+                    "Device authorization failed: {}",
+                    error.error_description.unwrap_or(error.error)
+                ))
+            }
+        }
+    }
+}
+
+/// Cancel the device flow currently being polled, if any
+pub fn cancel_device_flow() {
+    if let Ok(mut current) = DEVICE_FLOW_CANCEL.lock() {
+        if let Some(tx) = current.take() {
+            let _ = tx.send(true);
+            crate::modules::logger::log_info("Sent device authorization cancellation signal");
+        }
+    }
+}
+
+/// Attempts of a refresh-token exchange before a transient failure is returned
+const REFRESH_MAX_ATTEMPTS: u32 = 4;
+/// Backoff before the second attempt, doubled for each further attempt
+const REFRESH_BACKOFF_BASE_MS: u64 = 500;
+
+/// Why a refresh-token exchange failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RefreshError {
+    /// Network error, timeout, 429 or 5xx: worth retrying later
+    Transient(String),
+    /// The refresh token was revoked or expired: the user must sign in again
+    InvalidGrant(String),
+    /// Any other rejection by the token endpoint
+    Rejected(String),
+}
+
+impl std::fmt::Display for RefreshError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RefreshError::Transient(e) => write!(f, "Refresh failed (transient): {}", e),
+            RefreshError::InvalidGrant(e) => write!(f, "invalid_grant: {}", e),
+            RefreshError::Rejected(e) => write!(f, "Refresh failed: {}", e),
+        }
+    }
+}
+
+impl RefreshError {
+    /// Whether the refresh token is no longer valid
+    pub fn is_invalid_grant(&self) -> bool {
+        matches!(self, RefreshError::InvalidGrant(_))
+    }
+}
+
+impl From<RefreshError> for String {
+    fn from(e: RefreshError) -> Self {
+        e.to_string()
+    }
+}
+
+/// Whether a refresh error message (e.g. a stored disabled reason) means the refresh token
+/// is no longer valid
+pub fn is_invalid_grant(error: &str) -> bool {
+    error.contains("invalid_grant")
+}
+
+fn classify_refresh_failure(status: reqwest::StatusCode, body: String) -> RefreshError {
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+        RefreshError::Transient(format!("HTTP {}: {}", status.as_u16(), body))
+    } else if is_invalid_grant(&body) {
+        RefreshError::InvalidGrant(body)
+    } else {
+        RefreshError::Rejected(body)
+    }
+}
+
+/// Backoff before retry `attempt` (1-based): exponential with full jitter
+fn refresh_backoff(attempt: u32) -> std::time::Duration {
+    use rand::Rng;
+    let cap = REFRESH_BACKOFF_BASE_MS << (attempt - 1).min(6);
+    let delay = rand::thread_rng().gen_range(cap / 2..=cap);
+    std::time::Duration::from_millis(delay)
+}
+
+async fn refresh_once(
+    client: &reqwest::Client,
+    refresh_token: &str,
+) -> Result<TokenResponse, RefreshError> {
+    match refresh_once_with(client, refresh_token, CLIENT_ID, CLIENT_SECRET).await {
+        // Tokens obtained through the device flow belong to the device client
+        Err(RefreshError::Rejected(body)) if body.contains("unauthorized_client") => {
+            match device_client() {
+                Ok(device) => {
+                    refresh_once_with(client, refresh_token, &device.id, &device.secret).await
+                }
+                Err(_) => Err(RefreshError::Rejected(body)),
+            }
+        }
+        result => result,
+    }
+}
+
+async fn refresh_once_with(
+    client: &reqwest::Client,
+    refresh_token: &str,
+    client_id: &str,
+    client_secret: &str,
+) -> Result<TokenResponse, RefreshError> {
+    let params = [
+        ("client_id", client_id),
+        ("client_secret", client_secret),
         ("refresh_token", refresh_token),
         ("grant_type", "refresh_token"),
     ];
 
-    crate::modules::logger::log_info("Refreshing Token...");
-    
     let response = client
         .post(TOKEN_URL)
         .form(&params)
         .send()
         .await
-        .map_err(|e| format!("Refresh request failed: {}", e))?;
+        .map_err(|e| RefreshError::Transient(format!("Refresh request failed: {}", e)))?;
 
-    if response.status().is_success() {
-        let token_data = response
+    let status = response.status();
+    if status.is_success() {
+        response
             .json::<TokenResponse>()
             .await
-            .map_err(|e| format!("Refresh data parsing failed: {}", e))?;
-        
-        crate::modules::logger::log_info(&format!("Token refreshed successfully! Expires in: {} seconds", token_data.expires_in));
-        Ok(token_data)
+            .map_err(|e| RefreshError::Transient(format!("Refresh data parsing failed: {}", e)))
     } else {
         let error_text = response.text().await.unwrap_or_default();
-        Err(format!("Refresh failed: {}", error_text))
+        Err(classify_refresh_failure(status, error_text))
+    }
+}
+
+/// Refresh access_token using refresh_token, retrying transient failures with jittered backoff.
+/// `first_attempt` is 1-based; a later value continues after attempts already made elsewhere.
+async fn refresh_with_retry(
+    client: &reqwest::Client,
+    refresh_token: &str,
+    first_attempt: u32,
+) -> Result<TokenResponse, RefreshError> {
+    crate::modules::logger::log_info("Refreshing Token...");
+
+    let mut attempt = first_attempt.max(1);
+    loop {
+        if attempt > 1 {
+            tokio::time::sleep(refresh_backoff(attempt - 1)).await;
+        }
+        match refresh_once(client, refresh_token).await {
+            Ok(token_data) => {
+                crate::modules::logger::log_info(&format!("Token refreshed successfully! Expires in: {} seconds", token_data.expires_in));
+                return Ok(token_data);
+            }
+            Err(RefreshError::Transient(e)) if attempt < REFRESH_MAX_ATTEMPTS => {
+                crate::modules::logger::log_warn(&format!(
+                    "Token refresh attempt {}/{} failed, retrying: {}",
+                    attempt, REFRESH_MAX_ATTEMPTS, e
+                ));
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
     }
 }
 
+/// An unusable upstream proxy is not worth retrying
+fn refresh_client(email: Option<&str>) -> Result<reqwest::Client, RefreshError> {
+    match email {
+        Some(email) => crate::utils::http::client_for_account(email),
+        None => crate::utils::http::get_client(),
+    }
+    .map_err(RefreshError::Rejected)
+}
+
+/// Refresh access_token using refresh_token (through the global upstream proxy)
+pub async fn refresh_access_token(refresh_token: &str) -> Result<TokenResponse, RefreshError> {
+    refresh_with_retry(&refresh_client(None)?, refresh_token, 1).await
+}
+
+/// Refresh an account's access_token through the account's upstream proxy
+pub async fn refresh_account_token(
+    refresh_token: &str,
+    email: &str,
+) -> Result<TokenResponse, RefreshError> {
+    refresh_with_retry(&refresh_client(Some(email))?, refresh_token, 1).await
+}
+
+/// A single refresh attempt without backoff, for callers with a tight time budget (the proxy
+/// request path); a transient failure can be retried later with [`retry_refresh_token`]
+pub async fn refresh_token_once(
+    refresh_token: &str,
+    email: Option<&str>,
+) -> Result<TokenResponse, RefreshError> {
+    refresh_once(&refresh_client(email)?, refresh_token).await
+}
+
+/// The remaining retries after a transient failure of [`refresh_token_once`]
+pub async fn retry_refresh_token(
+    refresh_token: &str,
+    email: Option<&str>,
+) -> Result<TokenResponse, RefreshError> {
+    refresh_with_retry(&refresh_client(email)?, refresh_token, 2).await
+}
+
 /// Get user info
 pub async fn get_user_info(access_token: &str) -> Result<UserInfo, String> {
-    let client = crate::utils::http::get_client();
+    let client = crate::utils::http::get_client()?;
     
     let response = client
         .get(USERINFO_URL)
@@ -179,17 +554,28 @@ pub async fn get_user_info(access_token: &str) -> Result<UserInfo, String> {
 /// Returns the latest access_token
 pub async fn ensure_fresh_token(
     current_token: &crate::models::TokenData,
-) -> Result<crate::models::TokenData, String> {
+) -> Result<crate::models::TokenData, RefreshError> {
+    // Refresh when less than 5 minutes valid
+    ensure_fresh_token_within(current_token, 300).await
+}
+
+/// Refresh the Token when it expires within `margin_secs`
+pub async fn ensure_fresh_token_within(
+    current_token: &crate::models::TokenData,
+    margin_secs: i64,
+) -> Result<crate::models::TokenData, RefreshError> {
     let now = chrono::Local::now().timestamp();
-    
-    // If no expiry or more than 5 minutes valid, return direct
-    if current_token.expiry_timestamp > now + 300 {
+
+    if current_token.expiry_timestamp > now + margin_secs {
         return Ok(current_token.clone());
     }
     
     // Need to refresh
     crate::modules::logger::log_info("Token expiring soon, refreshing...");
-    let response = refresh_access_token(&current_token.refresh_token).await?;
+    let response = match &current_token.email {
+        Some(email) => refresh_account_token(&current_token.refresh_token, email).await?,
+        None => refresh_access_token(&current_token.refresh_token).await?,
+    };
     
     // Construct new TokenData
     Ok(crate::models::TokenData::new(
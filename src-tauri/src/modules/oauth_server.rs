@@ -2,21 +2,59 @@ use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpListener;
 use tokio::sync::oneshot;
 use tokio::sync::watch;
-use std::sync::{Mutex, OnceLock};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
 use tauri::Url;
 use crate::modules::oauth;
 
-struct OAuthFlowState {
+/// Upper bound on authorization sessions waiting for a callback at the same time
+pub const MAX_OAUTH_SESSIONS: usize = 16;
+
+/// One authorization attempt, identified by its random `state` parameter
+struct OAuthSession {
     auth_url: String,
+    code_tx: Option<oneshot::Sender<Result<String, String>>>,
+    /// Taken by the login call waiting for this session
+    code_rx: Option<oneshot::Receiver<Result<String, String>>>,
+}
+
+/// Loopback callback server shared by all sessions; callbacks are routed by `state`
+struct CallbackServer {
     redirect_uri: String,
     cancel_tx: watch::Sender<bool>,
-    code_rx: Option<oneshot::Receiver<Result<String, String>>>,
 }
 
-static OAUTH_FLOW_STATE: OnceLock<Mutex<Option<OAuthFlowState>>> = OnceLock::new();
+#[derive(Default)]
+struct OAuthFlows {
+    server: Option<CallbackServer>,
+    sessions: HashMap<String, OAuthSession>,
+    /// Session driven by the add-account dialog (prepare / start / complete)
+    current: Option<String>,
+}
+
+impl OAuthFlows {
+    /// Drop a session; the callback server stops once no session is left
+    fn remove_session(&mut self, state: &str) -> Option<OAuthSession> {
+        let session = self.sessions.remove(state);
+        if self.current.as_deref() == Some(state) {
+            self.current = None;
+        }
+        if self.sessions.is_empty() {
+            if let Some(server) = self.server.take() {
+                let _ = server.cancel_tx.send(true);
+            }
+        }
+        session
+    }
+}
+
+static OAUTH_FLOWS: OnceLock<Mutex<OAuthFlows>> = OnceLock::new();
 
-fn get_oauth_flow_state() -> &'static Mutex<Option<OAuthFlowState>> {
-    OAUTH_FLOW_STATE.get_or_init(|| Mutex::new(None))
+fn lock_flows() -> Result<MutexGuard<'static, OAuthFlows>, String> {
+    OAUTH_FLOWS
+        .get_or_init(|| Mutex::new(OAuthFlows::default()))
+        .lock()
+        .map_err(|_| "OAuth state lock corrupted".to_string())
 }
 
 fn oauth_success_html() -> &'static str {
@@ -40,29 +78,21 @@ fn oauth_fail_html() -> &'static str {
     </html>"
 }
 
-async fn ensure_oauth_flow_prepared(app_handle: &tauri::AppHandle) -> Result<String, String> {
-    use tauri::Emitter;
-
-    // Return URL if flow already exists
-    if let Ok(state) = get_oauth_flow_state().lock() {
-        if let Some(s) = state.as_ref() {
-            return Ok(s.auth_url.clone());
-        }
-    }
-
-    // Create loopback listeners.
-    // Some browsers resolve `localhost` to IPv6 (::1). To avoid "localhost refused connection",
-    // we try to listen on BOTH IPv6 and IPv4 with the same port when possible.
+/// Create loopback listeners on `port` (0 = ephemeral), returning the actual port.
+/// Some browsers resolve `localhost` to IPv6 (::1). To avoid "localhost refused connection",
+/// we try to listen on BOTH IPv6 and IPv4 with the same port when possible.
+async fn bind_callback_listeners(
+    port: u16,
+) -> Result<(u16, Option<TcpListener>, Option<TcpListener>), String> {
     let mut ipv4_listener: Option<TcpListener> = None;
     let mut ipv6_listener: Option<TcpListener> = None;
 
-    // Prefer creating one listener on an ephemeral port first, then bind the other stack to same port.
+    // Bind one stack first (resolving an ephemeral port), then bind the other stack to same port.
     // If both are available -> use `http://localhost:<port>` as redirect URI.
     // If only one is available -> use an explicit IP to force correct stack.
-    let port: u16;
-    match TcpListener::bind("[::1]:0").await {
+    let port = match TcpListener::bind(format!("[::1]:{}", port)).await {
         Ok(l6) => {
-            port = l6
+            let port = l6
                 .local_addr()
                 .map_err(|e| format!("failed_to_get_local_port: {}", e))?
                 .port();
@@ -77,12 +107,13 @@ async fn ensure_oauth_flow_prepared(app_handle: &tauri::AppHandle) -> Result<Str
                     ));
                 }
             }
+            port
         }
         Err(_) => {
-            let l4 = TcpListener::bind("127.0.0.1:0")
+            let l4 = TcpListener::bind(format!("127.0.0.1:{}", port))
                 .await
                 .map_err(|e| format!("failed_to_bind_local_port: {}", e))?;
-            port = l4
+            let port = l4
                 .local_addr()
                 .map_err(|e| format!("failed_to_get_local_port: {}", e))?
                 .port();
@@ -97,214 +128,635 @@ async fn ensure_oauth_flow_prepared(app_handle: &tauri::AppHandle) -> Result<Str
                     ));
                 }
             }
+            port
+        }
+    };
+
+    Ok((port, ipv4_listener, ipv6_listener))
+}
+
+/// Start the shared callback server unless it is already running, returning the redirect URI
+async fn ensure_callback_server(app_handle: &tauri::AppHandle) -> Result<String, String> {
+    if let Some(server) = lock_flows()?.server.as_ref() {
+        return Ok(server.redirect_uri.clone());
+    }
+
+    // Use the configured callback port range when set, falling back to any free port
+    // when every port of the range is occupied.
+    let preferred_ports = crate::modules::config::load_app_config()
+        .map(|c| c.oauth.callback_ports())
+        .unwrap_or_default();
+    let mut bound = None;
+    for candidate in &preferred_ports {
+        match bind_callback_listeners(*candidate).await {
+            Ok(listeners) => {
+                bound = Some(listeners);
+                break;
+            }
+            Err(e) => crate::modules::logger::log_warn(&format!(
+                "OAuth callback port {} unavailable, trying next: {}",
+                candidate, e
+            )),
         }
     }
+    if bound.is_none() && !preferred_ports.is_empty() {
+        crate::modules::logger::log_warn(
+            "All configured OAuth callback ports are occupied, using a random free port",
+        );
+    }
+    let (port, ipv4_listener, ipv6_listener) = match bound {
+        Some(listeners) => listeners,
+        None => bind_callback_listeners(0).await?,
+    };
 
     let has_ipv4 = ipv4_listener.is_some();
     let has_ipv6 = ipv6_listener.is_some();
 
+    // Google only accepts plain http loopback redirects for its desktop clients,
+    // so TLS stays opt-in for clients configured with an https redirect
+    let tls = if load_oauth_settings().callback_tls {
+        Some(self_signed_acceptor()?)
+    } else {
+        None
+    };
+    let scheme = if tls.is_some() { "https" } else { "http" };
+
     let redirect_uri = if has_ipv4 && has_ipv6 {
-        format!("http://localhost:{}/oauth-callback", port)
+        format!("{}://localhost:{}/oauth-callback", scheme, port)
     } else if has_ipv4 {
-        format!("http://127.0.0.1:{}/oauth-callback", port)
+        format!("{}://127.0.0.1:{}/oauth-callback", scheme, port)
     } else {
-        format!("http://[::1]:{}/oauth-callback", port)
+        format!("{}://[::1]:{}/oauth-callback", scheme, port)
     };
 
-    let auth_url = oauth::get_auth_url(&redirect_uri);
+    let mut flows = lock_flows()?;
+    // Another session started the server while we were binding: our listeners are dropped here
+    if let Some(server) = flows.server.as_ref() {
+        return Ok(server.redirect_uri.clone());
+    }
 
-    // Cancellation signal (supports multiple consumers)
     let (cancel_tx, cancel_rx) = watch::channel(false);
+    for listener in [ipv4_listener, ipv6_listener].into_iter().flatten() {
+        tokio::spawn(serve_callback(
+            listener,
+            tls.clone(),
+            cancel_rx.clone(),
+            app_handle.clone(),
+        ));
+    }
+    flows.server = Some(CallbackServer {
+        redirect_uri: redirect_uri.clone(),
+        cancel_tx,
+    });
+    Ok(redirect_uri)
+}
+
+fn load_oauth_settings() -> crate::models::config::OAuthConfig {
+    crate::modules::config::load_app_config()
+        .map(|c| c.oauth)
+        .unwrap_or_default()
+}
+
+/// Register a new authorization session, returning its `state` and authorization URL.
+/// Listeners are started right away: even if the user authorizes before anyone waits,
+/// the browser can still hit our callback and finish the flow.
+async fn create_session(
+    app_handle: &tauri::AppHandle,
+    login_hint: Option<&str>,
+) -> Result<(String, String), String> {
+    use tauri::Emitter;
+
+    // Random per-session state: callbacks carrying an unknown state are rejected
+    let state = uuid::Uuid::new_v4().simple().to_string();
     let (code_tx, code_rx) = oneshot::channel::<Result<String, String>>();
 
-    let code_tx = std::sync::Arc::new(tokio::sync::Mutex::new(Some(code_tx)));
+    let auth_url = loop {
+        let redirect_uri = ensure_callback_server(app_handle).await?;
+        let mut flows = lock_flows()?;
+        // The server may have been torn down by the last session ending in the meantime
+        if flows.server.as_ref().map(|s| &s.redirect_uri) != Some(&redirect_uri) {
+            continue;
+        }
+        if flows.sessions.len() >= MAX_OAUTH_SESSIONS {
+            return Err(format!(
+                "Too many authorization sessions in progress (max {})",
+                MAX_OAUTH_SESSIONS
+            ));
+        }
+        let auth_url = oauth::get_auth_url(&redirect_uri, &state, login_hint);
+        flows.sessions.insert(
+            state.clone(),
+            OAuthSession {
+                auth_url: auth_url.clone(),
+                code_tx: Some(code_tx),
+                code_rx: Some(code_rx),
+            },
+        );
+        break auth_url;
+    };
 
-    // Start listeners immediately: even if the user authorizes before clicking "Start OAuth",
-    // the browser can still hit our callback and finish the flow.
-    let app_handle_for_tasks = app_handle.clone();
+    // Tear the session down when the user does not finish in time
+    let timeout =
+        std::time::Duration::from_secs(load_oauth_settings().callback_timeout_minutes.max(1) as u64 * 60);
+    let app_handle_for_timeout = app_handle.clone();
+    let state_for_timeout = state.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(timeout).await;
+        let expired = lock_flows()
+            .map(|mut flows| flows.remove_session(&state_for_timeout).is_some())
+            .unwrap_or(false);
+        if expired {
+            crate::modules::logger::log_warn("OAuth session timed out, callback no longer accepted");
+            let _ = app_handle_for_timeout.emit("oauth-flow-expired", &state_for_timeout);
+        }
+    });
 
-    if let Some(l4) = ipv4_listener {
-        let tx = code_tx.clone();
-        let mut rx = cancel_rx.clone();
-        let app_handle = app_handle_for_tasks.clone();
-        tokio::spawn(async move {
-            if let Ok((mut stream, _)) = tokio::select! {
-                res = l4.accept() => res.map_err(|e| format!("failed_to_accept_connection: {}", e)),
-                _ = rx.changed() => Err("OAuth cancelled".to_string()),
-            } {
-                // Reuse the existing parsing/response code by constructing a temporary listener task
-                // that sends into the shared oneshot.
-                let mut buffer = [0u8; 4096];
-                let _ = stream.read(&mut buffer).await;
-                let request = String::from_utf8_lossy(&buffer);
-                let code = request
-                    .lines()
-                    .next()
-                    .and_then(|line| line.split_whitespace().nth(1))
-                    .and_then(|path| Url::parse(&format!("http://127.0.0.1:{}{}", port, path)).ok())
-                    .and_then(|url| {
-                        url.query_pairs()
-                            .find(|(k, _)| k == "code")
-                            .map(|(_, v)| v.into_owned())
-                    });
-
-                let (result, response_html) = match code {
-                    Some(code) => (Ok(code), oauth_success_html()),
-                    None => (Err("Failed to get Authorization Code in callback".to_string()), oauth_fail_html()),
-                };
-                let _ = stream.write_all(response_html.as_bytes()).await;
-                let _ = stream.flush().await;
-
-                if let Some(sender) = tx.lock().await.take() {
-                    let _ = app_handle.emit("oauth-callback-received", ());
-                    let _ = sender.send(result);
-                }
+    Ok((state, auth_url))
+}
+
+/// The dialog session, created on first use
+async fn current_session(app_handle: &tauri::AppHandle) -> Result<(String, String), String> {
+    {
+        let flows = lock_flows()?;
+        if let Some(state) = flows.current.as_ref() {
+            if let Some(session) = flows.sessions.get(state) {
+                return Ok((state.clone(), session.auth_url.clone()));
             }
-        });
+        }
+    }
+    let (state, auth_url) = create_session(app_handle, None).await?;
+    lock_flows()?.current = Some(state.clone());
+    Ok((state, auth_url))
+}
+
+/// Wait for the callback of a session, then exchange the code
+async fn wait_for_session(state: &str) -> Result<oauth::TokenResponse, String> {
+    let (code_rx, redirect_uri) = {
+        let mut flows = lock_flows()?;
+        let redirect_uri = flows
+            .server
+            .as_ref()
+            .map(|s| s.redirect_uri.clone())
+            .ok_or_else(|| "OAuth state does not exist".to_string())?;
+        let session = flows
+            .sessions
+            .get_mut(state)
+            .ok_or_else(|| "OAuth state does not exist".to_string())?;
+        let rx = session
+            .code_rx
+            .take()
+            .ok_or_else(|| "OAuth authorization already in progress".to_string())?;
+        (rx, redirect_uri)
+    };
+
+    // Wait for code (if user has already authorized, this returns immediately)
+    let result = code_rx.await;
+
+    // Clean up the session (stops the server when it was the last one)
+    if let Ok(mut flows) = lock_flows() {
+        flows.remove_session(state);
+    }
+
+    let code = match result {
+        Ok(Ok(code)) => code,
+        Ok(Err(e)) => return Err(e),
+        Err(_) => return Err("Failed to wait for OAuth callback".to_string()),
+    };
+    oauth::exchange_code(&code, &redirect_uri).await
+}
+
+/// Self-signed certificate for `localhost` / loopback addresses (optional TLS callback mode)
+fn self_signed_acceptor() -> Result<tokio_rustls::TlsAcceptor, String> {
+    let certified = rcgen::generate_simple_self_signed(vec![
+        "localhost".to_string(),
+        "127.0.0.1".to_string(),
+        "::1".to_string(),
+    ])
+    .map_err(|e| format!("failed_to_generate_callback_certificate: {}", e))?;
+    let cert = rustls::pki_types::CertificateDer::from(certified.cert.der().to_vec());
+    let key = rustls::pki_types::PrivateKeyDer::Pkcs8(certified.key_pair.serialize_der().into());
+    let config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(vec![cert], key)
+        .map_err(|e| format!("failed_to_configure_callback_tls: {}", e))?;
+    Ok(tokio_rustls::TlsAcceptor::from(std::sync::Arc::new(config)))
+}
+
+/// Result of one request to the callback server
+#[derive(Debug, PartialEq)]
+enum CallbackOutcome {
+    /// Not an authorization callback (other path, unknown or already used state)
+    Ignored,
+    /// The session identified by `state` is finished with this code or error
+    Finished {
+        state: String,
+        result: Result<String, String>,
+    },
+}
+
+fn not_found_response() -> &'static str {
+    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+}
+
+/// Route a request target (e.g. `/oauth-callback?state=..&code=..`) to a pending session
+fn route_callback(target: &str, is_pending: impl Fn(&str) -> bool) -> (CallbackOutcome, &'static str) {
+    let Ok(url) = Url::parse(&format!("http://localhost{}", target)) else {
+        return (CallbackOutcome::Ignored, not_found_response());
+    };
+    if url.path() != "/oauth-callback" {
+        return (CallbackOutcome::Ignored, not_found_response());
+    }
+
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+    };
+    let Some(state) = param("state").filter(|s| is_pending(s)) else {
+        crate::modules::logger::log_warn("Rejected OAuth callback with missing or unknown state");
+        return (CallbackOutcome::Ignored, oauth_fail_html());
+    };
+    if let Some(error) = param("error") {
+        let result = Err(format!("Authorization failed: {}", error));
+        return (CallbackOutcome::Finished { state, result }, oauth_fail_html());
     }
+    match param("code").filter(|c| !c.is_empty()) {
+        Some(code) => (
+            CallbackOutcome::Finished { state, result: Ok(code) },
+            oauth_success_html(),
+        ),
+        None => {
+            let result = Err("Failed to get Authorization Code in callback".to_string());
+            (CallbackOutcome::Finished { state, result }, oauth_fail_html())
+        }
+    }
+}
+
+/// A session accepts one callback: its sender is taken on first use
+fn is_pending_session(state: &str) -> bool {
+    lock_flows()
+        .map(|flows| flows.sessions.get(state).is_some_and(|s| s.code_tx.is_some()))
+        .unwrap_or(false)
+}
+
+/// Read one request from a callback connection and answer it
+async fn handle_connection<S>(mut stream: S) -> CallbackOutcome
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    let mut buffer = [0u8; 4096];
+    let read = tokio::time::timeout(std::time::Duration::from_secs(5), stream.read(&mut buffer)).await;
+    let Ok(Ok(len)) = read else {
+        return CallbackOutcome::Ignored;
+    };
+    let request = String::from_utf8_lossy(&buffer[..len]);
+    let target = request
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .unwrap_or_default();
+
+    let (outcome, response) = route_callback(target, is_pending_session);
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.flush().await;
+    let _ = stream.shutdown().await;
+    outcome
+}
 
-    if let Some(l6) = ipv6_listener {
-        let tx = code_tx.clone();
-        let mut rx = cancel_rx;
-        let app_handle = app_handle_for_tasks;
+/// Hand a finished callback to its session (a session accepts only the first one)
+fn deliver_outcome(outcome: CallbackOutcome, app_handle: &tauri::AppHandle) {
+    use tauri::Emitter;
+
+    let CallbackOutcome::Finished { state, result } = outcome else {
+        return;
+    };
+    let Ok(mut flows) = lock_flows() else {
+        return;
+    };
+    let is_current = flows.current.as_deref() == Some(state.as_str());
+    let sender = flows.sessions.get_mut(&state).and_then(|s| s.code_tx.take());
+    drop(flows);
+    if let Some(sender) = sender {
+        // The add-account dialog only follows its own session
+        if is_current {
+            let _ = app_handle.emit("oauth-callback-received", ());
+        }
+        let _ = sender.send(result);
+    }
+}
+
+/// Accept connections from loopback peers and hand callbacks to their sessions until cancelled.
+/// Each connection is served on its own task, so a stalled client cannot hold up the callback.
+async fn serve_callback(
+    listener: TcpListener,
+    tls: Option<tokio_rustls::TlsAcceptor>,
+    mut cancel_rx: watch::Receiver<bool>,
+    app_handle: tauri::AppHandle,
+) {
+    loop {
+        let accepted = tokio::select! {
+            res = listener.accept() => res,
+            // Cancelled, or the server state was dropped
+            _ = cancel_rx.changed() => return,
+        };
+        let Ok((stream, peer)) = accepted else {
+            continue;
+        };
+        if !peer.ip().is_loopback() {
+            crate::modules::logger::log_warn(&format!(
+                "Rejected OAuth callback connection from non-loopback peer {}",
+                peer
+            ));
+            continue;
+        }
+
+        let app_handle = app_handle.clone();
+        let tls = tls.clone();
         tokio::spawn(async move {
-            if let Ok((mut stream, _)) = tokio::select! {
-                res = l6.accept() => res.map_err(|e| format!("failed_to_accept_connection: {}", e)),
-                _ = rx.changed() => Err("OAuth cancelled".to_string()),
-            } {
-                let mut buffer = [0u8; 4096];
-                let _ = stream.read(&mut buffer).await;
-                let request = String::from_utf8_lossy(&buffer);
-                let code = request
-                    .lines()
-                    .next()
-                    .and_then(|line| line.split_whitespace().nth(1))
-                    .and_then(|path| Url::parse(&format!("http://127.0.0.1:{}{}", port, path)).ok())
-                    .and_then(|url| {
-                        url.query_pairs()
-                            .find(|(k, _)| k == "code")
-                            .map(|(_, v)| v.into_owned())
-                    });
-
-                let (result, response_html) = match code {
-                    Some(code) => (Ok(code), oauth_success_html()),
-                    None => (Err("Failed to get Authorization Code in callback".to_string()), oauth_fail_html()),
-                };
-                let _ = stream.write_all(response_html.as_bytes()).await;
-                let _ = stream.flush().await;
-
-                if let Some(sender) = tx.lock().await.take() {
-                    let _ = app_handle.emit("oauth-callback-received", ());
-                    let _ = sender.send(result);
+            let outcome = match tls {
+                Some(acceptor) => {
+                    let handshake = tokio::time::timeout(
+                        std::time::Duration::from_secs(5),
+                        acceptor.accept(stream),
+                    );
+                    match handshake.await {
+                        Ok(Ok(stream)) => handle_connection(stream).await,
+                        _ => return,
+                    }
                 }
-            }
+                None => handle_connection(stream).await,
+            };
+            deliver_outcome(outcome, &app_handle);
         });
     }
+}
 
-    // Save state
-    if let Ok(mut state) = get_oauth_flow_state().lock() {
-        *state = Some(OAuthFlowState {
-            auth_url: auth_url.clone(),
-            redirect_uri,
-            cancel_tx,
-            code_rx: Some(code_rx),
-        });
+/// Browser-specific arguments selecting a profile and/or a private window
+fn browser_args(browser: &str, profile: &str, private: bool) -> Vec<String> {
+    let name = browser.to_lowercase();
+    let mut args = Vec::new();
+    if name.contains("firefox") {
+        if !profile.is_empty() {
+            args.push("-P".to_string());
+            args.push(profile.to_string());
+        }
+        if private {
+            args.push("--private-window".to_string());
+        }
+    } else {
+        // Chromium family: Chrome, Edge, Brave, Vivaldi, Opera...
+        if !profile.is_empty() {
+            args.push(format!("--profile-directory={}", profile));
+        }
+        if private {
+            let flag = if name.contains("edge") {
+                "--inprivate"
+            } else if name.contains("opera") {
+                "--private"
+            } else {
+                "--incognito"
+            };
+            args.push(flag.to_string());
+        }
+    }
+    args
+}
+
+/// Open the authorization URL in the browser chosen in the OAuth settings, or the system default
+fn open_auth_url(app_handle: &tauri::AppHandle, auth_url: &str) -> Result<(), String> {
+    let settings = crate::modules::config::load_app_config()
+        .map(|c| c.oauth)
+        .unwrap_or_default();
+    let browser = settings.browser.trim();
+    if browser.is_empty() {
+        use tauri_plugin_opener::OpenerExt;
+        return app_handle
+            .opener()
+            .open_url(auth_url, None::<String>)
+            .map_err(|e| format!("failed_to_open_browser: {}", e));
     }
 
-    // Send event to frontend (for display/copying link)
-    let _ = app_handle.emit("oauth-url-generated", &auth_url);
+    let args = browser_args(browser, settings.browser_profile.trim(), settings.browser_private);
 
-    Ok(auth_url)
+    // macOS application names (e.g. "Google Chrome") are launched through `open`
+    #[cfg(target_os = "macos")]
+    let mut command = if browser.contains('/') {
+        std::process::Command::new(browser)
+    } else {
+        let mut command = std::process::Command::new("open");
+        command.args(["-na", browser, "--args"]);
+        command
+    };
+    #[cfg(not(target_os = "macos"))]
+    let mut command = std::process::Command::new(browser);
+
+    command.args(&args).arg(auth_url);
+    command
+        .spawn()
+        .map_err(|e| format!("failed_to_open_browser: {} ({})", e, browser))?;
+    crate::modules::logger::log_info(&format!("Opened OAuth URL in {} {:?}", browser, args));
+    Ok(())
 }
 
 /// Pre-generate OAuth URL (does not open browser, does not block waiting for callback)
 pub async fn prepare_oauth_url(app_handle: tauri::AppHandle) -> Result<String, String> {
-    ensure_oauth_flow_prepared(&app_handle).await
+    current_session(&app_handle).await.map(|(_, auth_url)| auth_url)
 }
 
-/// Cancel current OAuth flow
+/// Cancel every OAuth session and stop the callback server
 pub fn cancel_oauth_flow() {
-    if let Ok(mut state) = get_oauth_flow_state().lock() {
-        if let Some(s) = state.take() {
-            let _ = s.cancel_tx.send(true);
+    if let Ok(mut flows) = lock_flows() {
+        if let Some(server) = flows.server.take() {
+            let _ = server.cancel_tx.send(true);
             crate::modules::logger::log_info("Sent OAuth cancellation signal");
         }
+        *flows = OAuthFlows::default();
     }
 }
 
 /// Start OAuth flow and wait for callback, then exchange token
 pub async fn start_oauth_flow(app_handle: tauri::AppHandle) -> Result<oauth::TokenResponse, String> {
     // Ensure URL + listener are ready (this way if the user authorizes first, it won't get stuck)
-    let auth_url = ensure_oauth_flow_prepared(&app_handle).await?;
-
-    // Open default browser
-    use tauri_plugin_opener::OpenerExt;
-    app_handle
-        .opener()
-        .open_url(&auth_url, None::<String>)
-        .map_err(|e| format!("failed_to_open_browser: {}", e))?;
-
-    // Take code_rx to wait for it
-    let (code_rx, redirect_uri) = {
-        let mut lock = get_oauth_flow_state()
-            .lock()
-            .map_err(|_| "OAuth state lock corrupted".to_string())?;
-        let Some(state) = lock.as_mut() else {
-            return Err("OAuth state does not exist".to_string());
-        };
-        let rx = state
-            .code_rx
-            .take()
-            .ok_or_else(|| "OAuth authorization already in progress".to_string())?;
-        (rx, state.redirect_uri.clone())
-    };
+    let (state, auth_url) = current_session(&app_handle).await?;
 
-    // Wait for code (if user has already authorized, this returns immediately)
-    let code = match code_rx.await {
-        Ok(Ok(code)) => code,
-        Ok(Err(e)) => return Err(e),
-        Err(_) => return Err("Failed to wait for OAuth callback".to_string()),
-    };
+    open_auth_url(&app_handle, &auth_url)?;
 
-    // Clean up flow state (release cancel_tx, etc.)
-    if let Ok(mut lock) = get_oauth_flow_state().lock() {
-        *lock = None;
-    }
-
-    oauth::exchange_code(&code, &redirect_uri).await
+    wait_for_session(&state).await
 }
 
 /// Завершить OAuth flow без открытия браузера.
 /// Предполагается, что пользователь открыл ссылку вручную (или ранее была открыта),
 /// а мы только ждём callback и обмениваем code на token.
 pub async fn complete_oauth_flow(app_handle: tauri::AppHandle) -> Result<oauth::TokenResponse, String> {
-    // Ensure URL + listeners exist
-    let _ = ensure_oauth_flow_prepared(&app_handle).await?;
+    let (state, _) = current_session(&app_handle).await?;
+    wait_for_session(&state).await
+}
 
-    // Take receiver to wait for code
-    let (code_rx, redirect_uri) = {
-        let mut lock = get_oauth_flow_state()
-            .lock()
-            .map_err(|_| "OAuth state lock corrupted".to_string())?;
-        let Some(state) = lock.as_mut() else {
-            return Err("OAuth state does not exist".to_string());
-        };
-        let rx = state
-            .code_rx
-            .take()
-            .ok_or_else(|| "OAuth authorization already in progress".to_string())?;
-        (rx, state.redirect_uri.clone())
+/// Open an additional, independent authorization session in the browser and return its `state`.
+/// Sessions run in parallel; finish each one with [`wait_oauth_session`].
+/// `login_hint` (an email) pre-selects the account on the consent screen.
+pub async fn start_oauth_session(
+    app_handle: &tauri::AppHandle,
+    login_hint: Option<&str>,
+) -> Result<String, String> {
+    let (state, auth_url) = create_session(app_handle, login_hint).await?;
+    if let Err(e) = open_auth_url(app_handle, &auth_url) {
+        if let Ok(mut flows) = lock_flows() {
+            flows.remove_session(&state);
+        }
+        return Err(e);
+    }
+    Ok(state)
+}
+
+/// Wait for the callback of a session started with [`start_oauth_session`]
+pub async fn wait_oauth_session(state: &str) -> Result<oauth::TokenResponse, String> {
+    wait_for_session(state).await
+}
+
+/// Extract the authorization code (and `state`, when present) from a pasted redirect URL
+/// (or query string), or accept a bare code
+fn extract_auth_code(input: &str) -> Result<(String, Option<String>), String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("Empty authorization code".to_string());
+    }
+    if !input.contains("code=") && !input.contains("error=") {
+        return Ok((input.to_string(), None));
+    }
+
+    let query = match input.find('?') {
+        Some(i) => &input[i + 1..],
+        None => input,
     };
+    let query = query.split('#').next().unwrap_or_default();
+    let mut code = None;
+    let mut state = None;
+    for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+        match key.as_ref() {
+            "code" => code = Some(value.into_owned()),
+            "state" => state = Some(value.into_owned()),
+            "error" => return Err(format!("Authorization failed: {}", value)),
+            _ => {}
+        }
+    }
+    code.filter(|c| !c.is_empty())
+        .map(|code| (code, state))
+        .ok_or_else(|| "No authorization code found in the pasted URL".to_string())
+}
 
-    let code = match code_rx.await {
-        Ok(Ok(code)) => code,
-        Ok(Err(e)) => return Err(e),
-        Err(_) => return Err("Failed to wait for OAuth callback".to_string()),
+/// Manual fallback when the loopback callback cannot be reached (e.g. blocked by a firewall):
+/// the user copies the auth URL, then pastes back the redirect URL or the code.
+///
+/// When a login call is already waiting for the callback, the code is handed to it and
+/// `None` is returned; otherwise the code is exchanged here.
+pub async fn submit_pasted_code(input: &str) -> Result<Option<oauth::TokenResponse>, String> {
+    let (code, pasted_state) = extract_auth_code(input)?;
+
+    let (state, code_tx, code_rx, redirect_uri) = {
+        let mut flows = lock_flows()?;
+        let redirect_uri = flows.server.as_ref().map(|s| s.redirect_uri.clone());
+        // A pasted URL names its session; a bare code belongs to the dialog session
+        let state = match pasted_state {
+            Some(state) if flows.sessions.contains_key(&state) => state,
+            Some(_) => {
+                return Err("The pasted URL belongs to another authorization attempt".to_string())
+            }
+            None => flows.current.clone().ok_or_else(|| {
+                "OAuth flow not started, generate the authorization link first".to_string()
+            })?,
+        };
+        let (Some(session), Some(redirect_uri)) = (flows.sessions.get_mut(&state), redirect_uri) else {
+            return Err("OAuth flow not started, generate the authorization link first".to_string());
+        };
+        (state, session.code_tx.take(), session.code_rx.take(), redirect_uri)
     };
 
-    if let Ok(mut lock) = get_oauth_flow_state().lock() {
-        *lock = None;
+    if code_rx.is_some() {
+        // Nobody is waiting: drop the session and exchange the code directly
+        if let Ok(mut flows) = lock_flows() {
+            flows.remove_session(&state);
+        }
+        return oauth::exchange_code(&code, &redirect_uri).await.map(Some);
     }
 
-    oauth::exchange_code(&code, &redirect_uri).await
+    match code_tx {
+        Some(sender) => {
+            let _ = sender.send(Ok(code));
+            Ok(None)
+        }
+        None => Err("OAuth callback already received".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_code_from_redirect_url() {
+        let url = "http://localhost:51234/oauth-callback?state=x&code=4%2F0Abc&scope=email";
+        assert_eq!(
+            extract_auth_code(url).unwrap(),
+            ("4/0Abc".to_string(), Some("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn accepts_bare_code() {
+        assert_eq!(extract_auth_code("  4/0Abc  ").unwrap(), ("4/0Abc".to_string(), None));
+    }
+
+    fn pending(state: &str) -> bool {
+        state == "abc" || state == "def"
+    }
+
+    #[test]
+    fn callback_routes_by_state() {
+        let (outcome, _) = route_callback("/oauth-callback?state=abc&code=xyz", pending);
+        assert_eq!(
+            outcome,
+            CallbackOutcome::Finished { state: "abc".to_string(), result: Ok("xyz".to_string()) }
+        );
+
+        let (outcome, _) = route_callback("/oauth-callback?state=def&code=uvw", pending);
+        assert_eq!(
+            outcome,
+            CallbackOutcome::Finished { state: "def".to_string(), result: Ok("uvw".to_string()) }
+        );
+    }
+
+    #[test]
+    fn callback_rejects_unknown_state() {
+        let (outcome, _) = route_callback("/oauth-callback?state=evil&code=xyz", pending);
+        assert_eq!(outcome, CallbackOutcome::Ignored);
+
+        let (outcome, _) = route_callback("/oauth-callback?code=xyz", pending);
+        assert_eq!(outcome, CallbackOutcome::Ignored);
+    }
+
+    #[test]
+    fn callback_ignores_other_paths() {
+        let (outcome, _) = route_callback("/favicon.ico", pending);
+        assert_eq!(outcome, CallbackOutcome::Ignored);
+    }
+
+    #[test]
+    fn reports_authorization_error() {
+        let err = extract_auth_code("http://localhost/oauth-callback?error=access_denied").unwrap_err();
+        assert!(err.contains("access_denied"));
+    }
+
+    #[test]
+    fn browser_args_per_family() {
+        assert_eq!(
+            browser_args("firefox", "work", true),
+            vec!["-P", "work", "--private-window"]
+        );
+        assert_eq!(
+            browser_args("msedge", "Profile 1", true),
+            vec!["--profile-directory=Profile 1", "--inprivate"]
+        );
+        assert_eq!(browser_args("google-chrome", "", true), vec!["--incognito"]);
+    }
 }
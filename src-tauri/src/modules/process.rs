@@ -13,10 +13,199 @@ fn get_current_exe_path() -> Option<std::path::PathBuf> {
         .and_then(|p| p.canonicalize().ok())
 }
 
+/// Why a process could not be terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessKillErrorKind {
+    /// Process already exited (usually harmless)
+    NotFound,
+    PermissionDenied,
+    Failed,
+}
+
+/// Per-PID termination failure
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ProcessKillError {
+    pub pid: u32,
+    pub kind: ProcessKillErrorKind,
+    pub message: String,
+}
+
+impl std::fmt::Display for ProcessKillError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PID {}: {}", self.pid, self.message)
+    }
+}
+
+/// Terminate a single process using OS APIs directly (no taskkill/kill subprocess).
+/// - Unix: SIGTERM (graceful) or SIGKILL (force) via kill(2)
+/// - Windows: WM_CLOSE to the process's top-level windows (graceful) or TerminateProcess (force);
+///   a graceful close fails for processes without windows
+pub fn terminate_process(pid: u32, force: bool) -> Result<(), ProcessKillError> {
+    #[cfg(unix)]
+    {
+        let signal = if force { libc::SIGKILL } else { libc::SIGTERM };
+        // SAFETY: kill(2) has no memory-safety preconditions
+        let ret = unsafe { libc::kill(pid as libc::pid_t, signal) };
+        if ret == 0 {
+            return Ok(());
+        }
+        let err = std::io::Error::last_os_error();
+        let kind = match err.raw_os_error() {
+            Some(libc::ESRCH) => ProcessKillErrorKind::NotFound,
+            Some(libc::EPERM) => ProcessKillErrorKind::PermissionDenied,
+            _ => ProcessKillErrorKind::Failed,
+        };
+        Err(ProcessKillError {
+            pid,
+            kind,
+            message: err.to_string(),
+        })
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::Foundation::{
+            CloseHandle, ERROR_ACCESS_DENIED, ERROR_INVALID_PARAMETER,
+        };
+        use windows_sys::Win32::System::Threading::{
+            OpenProcess, TerminateProcess, PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_TERMINATE,
+        };
+
+        let kill_error = |err: std::io::Error, call: &str| {
+            let kind = match err.raw_os_error().map(|code| code as u32) {
+                // OpenProcess reports an unknown PID as an invalid parameter
+                Some(ERROR_INVALID_PARAMETER) => ProcessKillErrorKind::NotFound,
+                Some(ERROR_ACCESS_DENIED) => ProcessKillErrorKind::PermissionDenied,
+                _ => ProcessKillErrorKind::Failed,
+            };
+            ProcessKillError {
+                pid,
+                kind,
+                message: format!("{} failed: {}", call, err),
+            }
+        };
+
+        let access = if force {
+            PROCESS_TERMINATE
+        } else {
+            PROCESS_QUERY_LIMITED_INFORMATION
+        };
+        // SAFETY: OpenProcess has no memory-safety preconditions; the handle is closed below
+        let handle = unsafe { OpenProcess(access, 0, pid) };
+        if handle.is_null() {
+            return Err(kill_error(std::io::Error::last_os_error(), "OpenProcess"));
+        }
+        let result = if force {
+            // SAFETY: handle is a live process handle opened with PROCESS_TERMINATE
+            if unsafe { TerminateProcess(handle, 1) } != 0 {
+                Ok(())
+            } else {
+                Err(kill_error(
+                    std::io::Error::last_os_error(),
+                    "TerminateProcess",
+                ))
+            }
+        } else if post_close_to_windows(pid) > 0 {
+            Ok(())
+        } else {
+            Err(ProcessKillError {
+                pid,
+                kind: ProcessKillErrorKind::Failed,
+                message: "process has no window to close".to_string(),
+            })
+        };
+        // SAFETY: handle was returned by OpenProcess and is closed exactly once
+        unsafe { CloseHandle(handle) };
+        result
+    }
+}
+
+/// Post WM_CLOSE to every top-level window owned by `pid`; returns how many were asked to close
+#[cfg(target_os = "windows")]
+fn post_close_to_windows(pid: u32) -> usize {
+    use windows_sys::Win32::Foundation::{BOOL, HWND, LPARAM};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        EnumWindows, GetWindowThreadProcessId, PostMessageW, WM_CLOSE,
+    };
+
+    struct Search {
+        pid: u32,
+        posted: usize,
+    }
+
+    unsafe extern "system" fn visit(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        // SAFETY: lparam is the &mut Search passed to EnumWindows below, alive for the whole call
+        let search = unsafe { &mut *(lparam as *mut Search) };
+        let mut owner = 0u32;
+        // SAFETY: hwnd comes from EnumWindows and owner is a valid out pointer
+        unsafe { GetWindowThreadProcessId(hwnd, &mut owner) };
+        // SAFETY: posting a message has no memory-safety preconditions
+        if owner == search.pid && unsafe { PostMessageW(hwnd, WM_CLOSE, 0, 0) } != 0 {
+            search.posted += 1;
+        }
+        1
+    }
+
+    let mut search = Search { pid, posted: 0 };
+    // SAFETY: the callback only dereferences `search`, which outlives the synchronous enumeration
+    unsafe { EnumWindows(Some(visit), &mut search as *mut Search as LPARAM) };
+    search.posted
+}
+
+/// Terminate several processes, returning only the real failures
+/// (processes that already exited are not reported).
+pub fn terminate_pids(pids: &[u32], force: bool) -> Vec<ProcessKillError> {
+    let mut errors = Vec::new();
+    for pid in pids {
+        if let Err(e) = terminate_process(*pid, force) {
+            if e.kind != ProcessKillErrorKind::NotFound {
+                crate::modules::logger::log_warn(&format!("Failed to terminate process: {}", e));
+                errors.push(e);
+            }
+        }
+    }
+    invalidate_process_snapshot();
+    errors
+}
+
+/// Join per-PID failures into a single error string for callers that return `Result<_, String>`
+fn format_kill_errors(errors: &[ProcessKillError]) -> String {
+    let details: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+    format!("failed_to_terminate_processes: {}", details.join("; "))
+}
+
+/// Cached process recognition rules; loaded from the config on first use and replaced
+/// whenever the config is saved, so process queries do not read the config file each time
+static RECOGNITION_RULES: once_cell::sync::Lazy<
+    parking_lot::RwLock<Option<crate::models::ProcessRecognitionConfig>>,
+> = once_cell::sync::Lazy::new(|| parking_lot::RwLock::new(None));
+
+/// Active process recognition rules (AppConfig.process_recognition)
+fn recognition_rules() -> crate::models::ProcessRecognitionConfig {
+    if let Some(rules) = RECOGNITION_RULES.read().as_ref() {
+        return rules.clone();
+    }
+    let rules = crate::modules::config::load_app_config()
+        .map(|c| c.process_recognition)
+        .unwrap_or_default();
+    *RECOGNITION_RULES.write() = Some(rules.clone());
+    rules
+}
+
+/// Replace the cached recognition rules (called when the config is applied)
+pub fn set_recognition_rules(rules: crate::models::ProcessRecognitionConfig) {
+    let changed = RECOGNITION_RULES.read().as_ref() != Some(&rules);
+    *RECOGNITION_RULES.write() = Some(rules);
+    if changed {
+        invalidate_process_snapshot();
+    }
+}
+
 /// Check if Antigravity is running
 pub fn is_antigravity_running() -> bool {
-    let mut system = System::new();
-    system.refresh_processes(sysinfo::ProcessesToUpdate::All);
+    let rules = recognition_rules();
+    let system = shared_process_scan();
 
     let current_exe = get_current_exe_path();
     let current_pid = std::process::id();
@@ -66,14 +255,7 @@ pub fn is_antigravity_running() -> bool {
                             let is_helper_by_args = args
                                 .iter()
                                 .any(|arg| arg.to_string_lossy().contains("--type="));
-                            let is_helper_by_name = name.contains("helper")
-                                || name.contains("plugin")
-                                || name.contains("renderer")
-                                || name.contains("gpu")
-                                || name.contains("crashpad")
-                                || name.contains("utility")
-                                || name.contains("audio")
-                                || name.contains("sandbox");
+                            let is_helper_by_name = rules.is_helper_name(&name);
                             if !is_helper_by_args && !is_helper_by_name {
                                 return true;
                             }
@@ -98,33 +280,26 @@ pub fn is_antigravity_running() -> bool {
             .join(" ");
 
         let is_helper = args_str.contains("--type=")
-            || name.contains("helper")
-            || name.contains("plugin")
-            || name.contains("renderer")
-            || name.contains("gpu")
-            || name.contains("crashpad")
-            || name.contains("utility")
-            || name.contains("audio")
-            || name.contains("sandbox")
+            || rules.is_helper_name(&name)
             || exe_path.contains("crashpad");
 
         #[cfg(target_os = "macos")]
         {
-            if exe_path.contains("antigravity.app") && !is_helper {
+            if rules.matches_path(&exe_path) && !is_helper {
                 return true;
             }
         }
 
         #[cfg(target_os = "windows")]
         {
-            if name == "antigravity.exe" && !is_helper {
+            if rules.matches_name_exact(&name) && !is_helper {
                 return true;
             }
         }
 
         #[cfg(target_os = "linux")]
         {
-            if (name.contains("antigravity") || exe_path.contains("/antigravity"))
+            if (rules.matches_name(&name) || rules.matches_path(&exe_path))
                 && !name.contains("tools")
                 && !is_helper
             {
@@ -192,8 +367,8 @@ fn get_self_family_pids(system: &sysinfo::System) -> std::collections::HashSet<u
 
 /// Get PIDs of all Antigravity processes (including main and helper processes)
 fn get_antigravity_pids() -> Vec<u32> {
-    let mut system = System::new();
-    system.refresh_processes(sysinfo::ProcessesToUpdate::All);
+    let rules = recognition_rules();
+    let system = shared_process_scan();
 
     // Linux: Enable family process tree exclusion
     #[cfg(target_os = "linux")]
@@ -263,14 +438,7 @@ fn get_antigravity_pids() -> Vec<u32> {
                             let is_helper_by_args = args
                                 .iter()
                                 .any(|arg| arg.to_string_lossy().contains("--type="));
-                            let is_helper_by_name = _name.contains("helper")
-                                || _name.contains("plugin")
-                                || _name.contains("renderer")
-                                || _name.contains("gpu")
-                                || _name.contains("crashpad")
-                                || _name.contains("utility")
-                                || _name.contains("audio")
-                                || _name.contains("sandbox");
+                            let is_helper_by_name = rules.is_helper_name(&_name);
                             if !is_helper_by_args && !is_helper_by_name {
                                 pids.push(pid_u32);
                                 continue;
@@ -303,20 +471,13 @@ fn get_antigravity_pids() -> Vec<u32> {
             .join(" ");
 
         let is_helper = args_str.contains("--type=")
-            || _name.contains("helper")
-            || _name.contains("plugin")
-            || _name.contains("renderer")
-            || _name.contains("gpu")
-            || _name.contains("crashpad")
-            || _name.contains("utility")
-            || _name.contains("audio")
-            || _name.contains("sandbox")
+            || rules.is_helper_name(&_name)
             || exe_path.contains("crashpad");
 
         #[cfg(target_os = "macos")]
         {
             // Match processes within Antigravity main app bundle, excluding Helper/Plugin/Renderer etc.
-            if exe_path.contains("antigravity.app") && !is_helper {
+            if rules.matches_path(&exe_path) && !is_helper {
                 pids.push(pid_u32);
             }
         }
@@ -324,7 +485,7 @@ fn get_antigravity_pids() -> Vec<u32> {
         #[cfg(target_os = "windows")]
         {
             let name = process.name().to_string_lossy().to_lowercase();
-            if name == "antigravity.exe" && !is_helper {
+            if rules.matches_name_exact(&name) && !is_helper {
                 pids.push(pid_u32);
             }
         }
@@ -332,7 +493,7 @@ fn get_antigravity_pids() -> Vec<u32> {
         #[cfg(target_os = "linux")]
         {
             let name = process.name().to_string_lossy().to_lowercase();
-            if (name == "antigravity" || exe_path.contains("/antigravity"))
+            if (rules.matches_name_exact(&name) || rules.matches_path(&exe_path))
                 && !name.contains("tools")
                 && !is_helper
             {
@@ -352,12 +513,138 @@ fn get_antigravity_pids() -> Vec<u32> {
     pids
 }
 
+/// Poll until all Antigravity processes have exited or the timeout elapses
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn wait_for_antigravity_exit(timeout: Duration) -> bool {
+    let start = std::time::Instant::now();
+    while start.elapsed() < timeout {
+        if !is_antigravity_running() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+    !is_antigravity_running()
+}
+
+/// macOS: ask the app to quit through AppleScript (same as Cmd+Q), so it can save state
+/// and will not show the crash-restore dialog on next launch
+#[cfg(target_os = "macos")]
+fn request_app_quit_macos() -> bool {
+    // Prefer the bundle name of the manually configured path (e.g. renamed/versioned copies)
+    let app_name = crate::modules::config::load_app_config()
+        .ok()
+        .and_then(|c| c.antigravity_executable)
+        .and_then(|p| {
+            let idx = p.find(".app")?;
+            std::path::Path::new(&p[..idx])
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+        })
+        .unwrap_or_else(|| "Antigravity".to_string());
+
+    let script = format!(
+        "tell application \"{}\" to quit",
+        app_name.replace('"', "\\\"")
+    );
+    crate::modules::logger::log_info(&format!("Requesting app quit via osascript: {}", script));
+
+    match Command::new("osascript").args(["-e", &script]).output() {
+        Ok(output) if output.status.success() => true,
+        Ok(output) => {
+            crate::modules::logger::log_warn(&format!(
+                "osascript quit failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+            false
+        }
+        Err(e) => {
+            crate::modules::logger::log_warn(&format!("Failed to run osascript: {}", e));
+            false
+        }
+    }
+}
+
+/// Windows: post WM_CLOSE to the visible top-level windows owned by the given processes
+#[cfg(target_os = "windows")]
+mod window_close {
+    use std::collections::HashSet;
+    use std::ffi::c_void;
+
+    type Hwnd = *mut c_void;
+    type Bool = i32;
+    type Lparam = isize;
+    type Wparam = usize;
+
+    const WM_CLOSE: u32 = 0x0010;
+    const GW_OWNER: u32 = 4;
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn EnumWindows(
+            callback: unsafe extern "system" fn(Hwnd, Lparam) -> Bool,
+            lparam: Lparam,
+        ) -> Bool;
+        fn GetWindowThreadProcessId(hwnd: Hwnd, process_id: *mut u32) -> u32;
+        fn IsWindowVisible(hwnd: Hwnd) -> Bool;
+        fn GetWindow(hwnd: Hwnd, cmd: u32) -> Hwnd;
+        fn PostMessageW(hwnd: Hwnd, msg: u32, wparam: Wparam, lparam: Lparam) -> Bool;
+    }
+
+    struct EnumContext {
+        pids: HashSet<u32>,
+        posted: usize,
+    }
+
+    unsafe extern "system" fn enum_window(hwnd: Hwnd, lparam: Lparam) -> Bool {
+        let ctx = &mut *(lparam as *mut EnumContext);
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, &mut pid);
+        // Only unowned visible windows are real top-level app windows (skip tooltips/dialogs)
+        if ctx.pids.contains(&pid)
+            && IsWindowVisible(hwnd) != 0
+            && GetWindow(hwnd, GW_OWNER).is_null()
+            && PostMessageW(hwnd, WM_CLOSE, 0, 0) != 0
+        {
+            ctx.posted += 1;
+        }
+        1
+    }
+
+    /// Returns the number of windows WM_CLOSE was posted to
+    pub fn post_close(pids: &[u32]) -> usize {
+        let mut ctx = EnumContext {
+            pids: pids.iter().copied().collect(),
+            posted: 0,
+        };
+        unsafe {
+            EnumWindows(enum_window, &mut ctx as *mut EnumContext as Lparam);
+        }
+        ctx.posted
+    }
+}
+
 /// Close Antigravity processes
 pub fn close_antigravity(#[allow(unused_variables)] timeout_secs: u64) -> Result<(), String> {
     crate::modules::logger::log_info("Closing Antigravity...");
 
     #[cfg(target_os = "windows")]
     {
+        // Phase 0: Ask windows to close (WM_CLOSE) so Antigravity can save state
+        let pids = get_antigravity_pids();
+        if !pids.is_empty() {
+            let posted = window_close::post_close(&pids);
+            if posted > 0 {
+                crate::modules::logger::log_info(&format!(
+                    "Posted WM_CLOSE to {} Antigravity windows, waiting for exit...",
+                    posted
+                ));
+                if wait_for_antigravity_exit(Duration::from_secs((timeout_secs * 3) / 10)) {
+                    crate::modules::logger::log_info("Antigravity closed via WM_CLOSE");
+                    return Ok(());
+                }
+            }
+        }
+
         // Windows: Precise kill by PID to support multiple versions or custom filenames
         let pids = get_antigravity_pids();
         if !pids.is_empty() {
@@ -365,28 +652,35 @@ pub fn close_antigravity(#[allow(unused_variables)] timeout_secs: u64) -> Result
                 "Precisely closing {} identified processes on Windows...",
                 pids.len()
             ));
-            for pid in pids {
-                let _ = Command::new("taskkill")
-                    .args(["/F", "/PID", &pid.to_string()])
-                    .creation_flags(0x08000000) // CREATE_NO_WINDOW
-                    .output();
-            }
+            let errors = terminate_pids(&pids, true);
             // Give some time for system to clean up PIDs
             thread::sleep(Duration::from_millis(200));
+            if !errors.is_empty() && is_antigravity_running() {
+                return Err(format_kill_errors(&errors));
+            }
         }
     }
 
     #[cfg(target_os = "macos")]
     {
+        // Phase 0: UI-level quit through AppleScript, the app saves state and exits cleanly
+        if is_antigravity_running() && request_app_quit_macos() {
+            if wait_for_antigravity_exit(Duration::from_secs((timeout_secs * 3) / 10)) {
+                crate::modules::logger::log_info("Antigravity quit via AppleScript");
+                return Ok(());
+            }
+            crate::modules::logger::log_warn("AppleScript quit timed out, falling back to SIGTERM");
+        }
+
         // macOS: Optimize closing strategy to avoid "Window terminated unexpectedly" popups
         // Strategy: SEND SIGTERM to main process only, let it coordinate closing children
 
         let pids = get_antigravity_pids();
         if !pids.is_empty() {
+            let rules = recognition_rules();
             // 1. Identify main process (PID)
             // Strategy: Principal processes of Electron/Tauri do not have the `--type` parameter, while Helper processes have `--type=renderer/gpu/utility`, etc.
-            let mut system = System::new();
-            system.refresh_processes(sysinfo::ProcessesToUpdate::All);
+            let system = shared_process_scan();
 
             let mut main_pid = None;
 
@@ -424,15 +718,7 @@ pub fn close_antigravity(#[allow(unused_variables)] timeout_secs: u64) -> Result
                                 if m_path_str[..m_idx + 4] == p_path_str[..p_idx + 4] {
                                     // Deep validation: even if path matches, must exclude Helper keywords and arguments
                                     let is_helper_by_args = args_str.contains("--type=");
-                                    let is_helper_by_name = name.to_lowercase().contains("helper")
-                                        || name.to_lowercase().contains("plugin")
-                                        || name.to_lowercase().contains("renderer")
-                                        || name.to_lowercase().contains("gpu")
-                                        || name.to_lowercase().contains("crashpad")
-                                        || name.to_lowercase().contains("utility")
-                                        || name.to_lowercase().contains("audio")
-                                        || name.to_lowercase().contains("sandbox")
-                                        || name.to_lowercase().contains("language_server");
+                                    let is_helper_by_name = rules.is_helper_name(&name);
 
                                     if !is_helper_by_args && !is_helper_by_name {
                                         main_pid = Some(pid_u32);
@@ -447,14 +733,7 @@ pub fn close_antigravity(#[allow(unused_variables)] timeout_secs: u64) -> Result
                     }
 
                     // 2. Feature analysis matching (fallback)
-                    let is_helper_by_name = name.to_lowercase().contains("helper")
-                        || name.to_lowercase().contains("crashpad")
-                        || name.to_lowercase().contains("utility")
-                        || name.to_lowercase().contains("audio")
-                        || name.to_lowercase().contains("sandbox")
-                        || name.to_lowercase().contains("language_server")
-                        || name.to_lowercase().contains("plugin")
-                        || name.to_lowercase().contains("renderer");
+                    let is_helper_by_name = rules.is_helper_name(&name);
 
                     let is_helper_by_args = args_str.contains("--type=");
 
@@ -479,28 +758,17 @@ pub fn close_antigravity(#[allow(unused_variables)] timeout_secs: u64) -> Result
                     "Sending SIGTERM to main process PID: {}",
                     pid
                 ));
-                let output = Command::new("kill")
-                    .args(["-15", &pid.to_string()])
-                    .output();
-
-                if let Ok(result) = output {
-                    if !result.status.success() {
-                        let error = String::from_utf8_lossy(&result.stderr);
-                        crate::modules::logger::log_warn(&format!(
-                            "Main process SIGTERM failed: {}",
-                            error
-                        ));
-                    }
+                if let Err(e) = terminate_process(*pid, false) {
+                    crate::modules::logger::log_warn(&format!(
+                        "Main process SIGTERM failed: {}",
+                        e
+                    ));
                 }
             } else {
                 crate::modules::logger::log_warn(
                     "No clear main process identified, attempting SIGTERM for all processes (may cause popups)",
                 );
-                for pid in &pids {
-                    let _ = Command::new("kill")
-                        .args(["-15", &pid.to_string()])
-                        .output();
-                }
+                terminate_pids(&pids, false);
             }
 
             // Wait for graceful exit (max 70% of timeout_secs)
@@ -522,23 +790,12 @@ pub fn close_antigravity(#[allow(unused_variables)] timeout_secs: u64) -> Result
                         "Graceful exit timeout, force killing {} remaining processes (SIGKILL)",
                         remaining_pids.len()
                     ));
-                    for pid in &remaining_pids {
-                        let output = Command::new("kill").args(["-9", &pid.to_string()]).output();
-
-                        if let Ok(result) = output {
-                            if !result.status.success() {
-                                let error = String::from_utf8_lossy(&result.stderr);
-                                if !error.contains("No such process") {
-                                    // "No matching processes" for killall, "No such process" for kill
-                                    crate::modules::logger::log_error(&format!(
-                                        "SIGKILL process {} failed: {}",
-                                        pid, error
-                                    ));
-                                }
-                            }
-                        }
-                    }
+                    let errors = terminate_pids(&remaining_pids, true);
                     thread::sleep(Duration::from_secs(1));
+
+                    if !errors.is_empty() && is_antigravity_running() {
+                        return Err(format_kill_errors(&errors));
+                    }
                 }
 
                 // Final check
@@ -562,8 +819,8 @@ pub fn close_antigravity(#[allow(unused_variables)] timeout_secs: u64) -> Result
         // Linux: Also attempt to identify main process and delegate exit
         let pids = get_antigravity_pids();
         if !pids.is_empty() {
-            let mut system = System::new();
-            system.refresh_processes(sysinfo::ProcessesToUpdate::All);
+            let rules = recognition_rules();
+            let system = shared_process_scan();
 
             let mut main_pid = None;
 
@@ -598,13 +855,7 @@ pub fn close_antigravity(#[allow(unused_variables)] timeout_secs: u64) -> Result
                             if &p_path == m_path {
                                 // Confirm not a Helper
                                 let is_helper_by_args = args_str.contains("--type=");
-                                let is_helper_by_name = name.contains("helper")
-                                    || name.contains("renderer")
-                                    || name.contains("gpu")
-                                    || name.contains("crashpad")
-                                    || name.contains("utility")
-                                    || name.contains("audio")
-                                    || name.contains("sandbox");
+                                let is_helper_by_name = rules.is_helper_name(&name);
                                 if !is_helper_by_args && !is_helper_by_name {
                                     main_pid = Some(pid_u32);
                                     crate::modules::logger::log_info(&format!(
@@ -618,15 +869,7 @@ pub fn close_antigravity(#[allow(unused_variables)] timeout_secs: u64) -> Result
 
                     // 2. Feature analysis matching
                     let is_helper_by_args = args_str.contains("--type=");
-                    let is_helper_by_name = name.contains("helper")
-                        || name.contains("renderer")
-                        || name.contains("gpu")
-                        || name.contains("crashpad")
-                        || name.contains("utility")
-                        || name.contains("audio")
-                        || name.contains("sandbox")
-                        || name.contains("plugin")
-                        || name.contains("language_server");
+                    let is_helper_by_name = rules.is_helper_name(&name);
 
                     if !is_helper_by_args && !is_helper_by_name {
                         if main_pid.is_none() {
@@ -649,18 +892,17 @@ pub fn close_antigravity(#[allow(unused_variables)] timeout_secs: u64) -> Result
                     "Attempting to gracefully close main process {} (SIGTERM)",
                     pid
                 ));
-                let _ = Command::new("kill")
-                    .args(["-15", &pid.to_string()])
-                    .output();
+                if let Err(e) = terminate_process(*pid, false) {
+                    crate::modules::logger::log_warn(&format!(
+                        "Main process SIGTERM failed: {}",
+                        e
+                    ));
+                }
             } else {
                 crate::modules::logger::log_warn(
                     "No clear Linux main process identified, sending SIGTERM to all associated processes",
                 );
-                for pid in &pids {
-                    let _ = Command::new("kill")
-                        .args(["-15", &pid.to_string()])
-                        .output();
-                }
+                terminate_pids(&pids, false);
             }
 
             // Wait for graceful exit
@@ -682,10 +924,12 @@ pub fn close_antigravity(#[allow(unused_variables)] timeout_secs: u64) -> Result
                         "Graceful exit timeout, force killing {} remaining processes (SIGKILL)",
                         remaining_pids.len()
                     ));
-                    for pid in &remaining_pids {
-                        let _ = Command::new("kill").args(["-9", &pid.to_string()]).output();
-                    }
+                    let errors = terminate_pids(&remaining_pids, true);
                     thread::sleep(Duration::from_secs(1));
+
+                    if !errors.is_empty() && is_antigravity_running() {
+                        return Err(format_kill_errors(&errors));
+                    }
                 }
             }
         } else {
@@ -789,6 +1033,7 @@ pub fn start_antigravity() -> Result<(), String> {
                 cmd.spawn().map_err(|e| format!("Startup failed: {}", e))?;
             }
 
+            invalidate_process_snapshot();
             crate::modules::logger::log_info(&format!(
                 "Antigravity startup command sent (manual path: {}, args: {:?})",
                 path_str, args
@@ -862,6 +1107,7 @@ pub fn start_antigravity() -> Result<(), String> {
         cmd.spawn().map_err(|e| format!("Startup failed: {}", e))?;
     }
 
+    invalidate_process_snapshot();
     crate::modules::logger::log_info(&format!(
         "Antigravity startup command sent (default detection, args: {:?})",
         args
@@ -873,8 +1119,8 @@ pub fn start_antigravity() -> Result<(), String> {
 ///
 /// This is the most reliable method to find installations and startup args anywhere
 fn get_process_info() -> (Option<std::path::PathBuf>, Option<Vec<String>>) {
-    let mut system = System::new_all();
-    system.refresh_all();
+    let rules = recognition_rules();
+    let system = shared_process_scan();
 
     let current_exe = get_current_exe_path();
     let current_pid = std::process::id();
@@ -917,14 +1163,7 @@ fn get_process_info() -> (Option<std::path::PathBuf>, Option<Vec<String>>) {
                 || args_str.contains("nodeipc")
                 || args_str.contains("max-old-space-size")
                 || args_str.contains("node_modules")
-                || name.contains("helper")
-                || name.contains("plugin")
-                || name.contains("renderer")
-                || name.contains("gpu")
-                || name.contains("crashpad")
-                || name.contains("utility")
-                || name.contains("audio")
-                || name.contains("sandbox")
+                || rules.is_helper_name(&name)
                 || exe_path.contains("crashpad");
 
             let path = Some(exe.to_path_buf());
@@ -932,10 +1171,7 @@ fn get_process_info() -> (Option<std::path::PathBuf>, Option<Vec<String>>) {
             #[cfg(target_os = "macos")]
             {
                 // macOS: Exclude helper processes, match main app only, and check Frameworks
-                if exe_path.contains("antigravity.app")
-                    && !is_helper
-                    && !exe_path.contains("frameworks")
-                {
+                if rules.matches_path(&exe_path) && !is_helper && !exe_path.contains("frameworks") {
                     // Try to extract .app path for better open command support
                     if let Some(app_idx) = exe_path.find(".app") {
                         let app_path_str = &exe.to_string_lossy()[..app_idx + 4];
@@ -949,7 +1185,7 @@ fn get_process_info() -> (Option<std::path::PathBuf>, Option<Vec<String>>) {
             #[cfg(target_os = "windows")]
             {
                 // Windows: Strictly match process name and exclude helpers
-                if name == "antigravity.exe" && !is_helper {
+                if rules.matches_name_exact(&name) && !is_helper {
                     return (path, args);
                 }
             }
@@ -957,7 +1193,7 @@ fn get_process_info() -> (Option<std::path::PathBuf>, Option<Vec<String>>) {
             #[cfg(target_os = "linux")]
             {
                 // Linux: Check process name or path for antigravity, excluding helpers and manager
-                if (name == "antigravity" || exe_path.contains("/antigravity"))
+                if (rules.matches_name_exact(&name) || rules.matches_path(&exe_path))
                     && !name.contains("tools")
                     && !is_helper
                 {
@@ -1142,48 +1378,56 @@ use once_cell::sync::Lazy;
 #[cfg(target_os = "windows")]
 use std::collections::HashMap;
 use std::path::Path;
-#[cfg(target_os = "windows")]
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 /// Windows: 缓存所有进程的命令行参数
 #[cfg(target_os = "windows")]
 static PROCESS_CMDLINE_CACHE: Lazy<Mutex<HashMap<u32, String>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
 
-/// Windows: 使用 wmic 批量获取所有进程的命令行参数
+/// Windows: 批量获取所有进程的命令行参数
+/// 通过 sysinfo 直接读取（内部为 NtQueryInformationProcess / PEB），不再依赖新版 Windows 11 已移除的 wmic
 #[cfg(target_os = "windows")]
 fn refresh_process_command_line_cache() {
-    const CREATE_NO_WINDOW: u32 = 0x08000000;
+    use sysinfo::{ProcessRefreshKind, UpdateKind};
 
-    let output = match Command::new("wmic")
-        .args(["process", "get", "ProcessId,CommandLine", "/format:csv"])
-        .creation_flags(CREATE_NO_WINDOW)
-        .output()
-    {
-        Ok(o) => o,
-        Err(_) => return,
-    };
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        sysinfo::ProcessesToUpdate::All,
+        ProcessRefreshKind::new().with_cmd(UpdateKind::Always),
+    );
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
     let mut cache = match PROCESS_CMDLINE_CACHE.lock() {
         Ok(c) => c,
         Err(_) => return,
     };
     cache.clear();
 
-    for line in stdout.lines().skip(1) {
-        let parts: Vec<&str> = line.split(',').collect();
-        if parts.len() >= 3 {
-            if let Ok(pid) = parts.last().unwrap_or(&"").trim().parse::<u32>() {
-                let cmdline = parts[1..parts.len() - 1].join(",");
-                if !cmdline.is_empty() {
-                    cache.insert(pid, cmdline);
-                }
-            }
+    for (pid, process) in system.processes() {
+        let cmd = process.cmd();
+        if cmd.is_empty() {
+            continue;
         }
+        cache.insert(pid.as_u32(), join_cmdline_args(cmd));
     }
 }
 
+/// 将 argv 还原为命令行字符串（含空格的参数加引号，保证 parse_cmdline_to_args 可逆）
+#[cfg(target_os = "windows")]
+fn join_cmdline_args(args: &[std::ffi::OsString]) -> String {
+    args.iter()
+        .map(|arg| {
+            let arg = arg.to_string_lossy();
+            if arg.is_empty() || arg.contains(' ') || arg.contains('\t') {
+                format!("\"{}\"", arg)
+            } else {
+                arg.into_owned()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
 /// Windows: 从缓存获取进程命令行参数
 #[cfg(target_os = "windows")]
 fn get_process_command_line(pid: u32) -> Option<String> {
@@ -1196,19 +1440,23 @@ fn get_process_command_line(_pid: u32) -> Option<String> {
     None
 }
 
-/// 检查默认实例是否正在运行（使用父进程遍历法）
+/// 检查默认实例是否正在运行（监视器快照有效时直接读取，否则使用父进程遍历法）
 pub fn is_default_instance_running() -> bool {
+    let rules = recognition_rules();
+    if let Some(snapshot) = get_watched_snapshot() {
+        return snapshot.is_default_running();
+    }
+
     #[cfg(target_os = "windows")]
     refresh_process_command_line_cache();
 
-    let mut system = System::new();
-    system.refresh_processes(sysinfo::ProcessesToUpdate::All);
+    let system = shared_process_scan();
 
     let current_pid = std::process::id();
 
     let is_antigravity_name = |name: &str| -> bool {
         let name_lower = name.to_lowercase();
-        name_lower == "antigravity.exe" || name_lower.starts_with("antigravity")
+        rules.matches_name(&name_lower)
     };
 
     let find_root_antigravity = |start_pid: sysinfo::Pid| -> Option<sysinfo::Pid> {
@@ -1292,11 +1540,11 @@ pub fn is_default_instance_running() -> bool {
 /// 3. 命令行匹配 user_data_dir（或默认实例无 --user-data-dir）
 /// 4. 命令行不含 --type=（非辅助进程）
 pub fn is_pid_valid_instance_root(pid: u32, user_data_dir: &Path, is_default: bool) -> bool {
+    let rules = recognition_rules();
     #[cfg(target_os = "windows")]
     refresh_process_command_line_cache();
 
-    let mut system = System::new();
-    system.refresh_processes(sysinfo::ProcessesToUpdate::All);
+    let system = shared_process_scan();
 
     let sysinfo_pid = sysinfo::Pid::from_u32(pid);
     let process = match system.process(sysinfo_pid) {
@@ -1306,7 +1554,7 @@ pub fn is_pid_valid_instance_root(pid: u32, user_data_dir: &Path, is_default: bo
 
     // 检查进程名
     let name = process.name().to_string_lossy().to_lowercase();
-    if name != "antigravity.exe" && !name.starts_with("antigravity") {
+    if !rules.matches_name(&name) {
         return false;
     }
 
@@ -1376,8 +1624,7 @@ pub fn get_instance_root_pid_and_args(
             }
             #[cfg(not(target_os = "windows"))]
             {
-                let mut system = System::new();
-                system.refresh_processes(sysinfo::ProcessesToUpdate::All);
+                let system = shared_process_scan();
                 if let Some(process) = system.process(sysinfo::Pid::from_u32(pid)) {
                     let args: Vec<String> = process
                         .cmd()
@@ -1408,18 +1655,18 @@ pub fn get_instance_root_pid_and_args(
 
 /// 获取实例主进程的命令行参数
 pub fn get_instance_root_process_args(user_data_dir: &Path) -> Option<Vec<String>> {
+    let rules = recognition_rules();
     #[cfg(target_os = "windows")]
     refresh_process_command_line_cache();
 
-    let mut system = System::new();
-    system.refresh_processes(sysinfo::ProcessesToUpdate::All);
+    let system = shared_process_scan();
 
     let user_data_dir_str = user_data_dir.to_string_lossy().to_lowercase();
     let user_data_dir_normalized = user_data_dir_str.replace('/', "\\");
 
     let is_antigravity_name = |name: &str| -> bool {
         let name_lower = name.to_lowercase();
-        name_lower == "antigravity.exe" || name_lower.starts_with("antigravity")
+        rules.matches_name(&name_lower)
     };
 
     let find_root_antigravity = |start_pid: sysinfo::Pid| -> Option<sysinfo::Pid> {
@@ -1595,11 +1842,11 @@ fn parse_cmdline_to_args(cmdline: &str) -> Vec<String> {
 
 /// 获取特定实例的所有进程 PID
 pub fn get_instance_pids(user_data_dir: &Path) -> Vec<u32> {
+    let rules = recognition_rules();
     #[cfg(target_os = "windows")]
     refresh_process_command_line_cache();
 
-    let mut system = System::new();
-    system.refresh_processes(sysinfo::ProcessesToUpdate::All);
+    let system = shared_process_scan();
 
     let current_exe = get_current_exe_path();
     let current_pid = std::process::id();
@@ -1633,15 +1880,15 @@ pub fn get_instance_pids(user_data_dir: &Path) -> Vec<u32> {
         let is_antigravity = {
             #[cfg(target_os = "macos")]
             {
-                exe_path.contains("antigravity.app")
+                rules.matches_path(&exe_path)
             }
             #[cfg(target_os = "windows")]
             {
-                name == "antigravity.exe" || name.starts_with("antigravity")
+                rules.matches_name(&name)
             }
             #[cfg(target_os = "linux")]
             {
-                name.contains("antigravity") || exe_path.contains("/antigravity")
+                rules.matches_name(&name) || rules.matches_path(&exe_path)
             }
         };
 
@@ -1688,13 +1935,96 @@ pub fn get_instance_pids(user_data_dir: &Path) -> Vec<u32> {
 
 /// 检查实例是否正在运行
 pub fn is_instance_running(user_data_dir: &Path) -> bool {
+    if let Some(snapshot) = get_watched_snapshot() {
+        return snapshot.is_instance_running(user_data_dir);
+    }
     !get_instance_pids(user_data_dir).is_empty()
 }
 
+/// 扫描所有运行中的 Antigravity 主进程，返回其 --user-data-dir（用于发现未登记的实例）
+/// 未指定 --user-data-dir 的主进程（默认实例）不会出现在结果中
+pub fn get_running_user_data_dirs() -> Vec<(u32, std::path::PathBuf)> {
+    let rules = recognition_rules();
+    if let Some(snapshot) = get_watched_snapshot() {
+        let mut result: Vec<(u32, std::path::PathBuf)> = Vec::new();
+        for root in snapshot.roots {
+            if let Some(dir) = root.user_data_dir {
+                if !result.iter().any(|(_, p)| p == &dir) {
+                    result.push((root.pid, dir));
+                }
+            }
+        }
+        return result;
+    }
+
+    #[cfg(target_os = "windows")]
+    refresh_process_command_line_cache();
+
+    let system = shared_process_scan();
+
+    let current_pid = std::process::id();
+    let mut result: Vec<(u32, std::path::PathBuf)> = Vec::new();
+
+    for (pid, process) in system.processes() {
+        let pid_u32 = pid.as_u32();
+        if pid_u32 == current_pid {
+            continue;
+        }
+
+        let name = process.name().to_string_lossy().to_lowercase();
+        if !rules.matches_name(&name) {
+            continue;
+        }
+
+        let args: Vec<String> = {
+            #[cfg(target_os = "windows")]
+            {
+                get_process_command_line(pid_u32)
+                    .map(|s| parse_cmdline_to_args(&s))
+                    .unwrap_or_default()
+            }
+            #[cfg(not(target_os = "windows"))]
+            {
+                process
+                    .cmd()
+                    .iter()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .collect()
+            }
+        };
+
+        // 辅助进程携带相同的 --user-data-dir，只统计主进程
+        if args.iter().any(|a| a.starts_with("--type=")) {
+            continue;
+        }
+
+        let mut dir: Option<String> = None;
+        for (i, arg) in args.iter().enumerate() {
+            if arg == "--user-data-dir" {
+                dir = args.get(i + 1).cloned();
+                break;
+            } else if let Some(value) = arg.strip_prefix("--user-data-dir=") {
+                dir = Some(value.to_string());
+                break;
+            }
+        }
+
+        if let Some(dir) = dir {
+            let path = std::path::PathBuf::from(dir.trim_matches('"'));
+            if !result.iter().any(|(_, p)| p == &path) {
+                result.push((pid_u32, path));
+            }
+        }
+    }
+
+    result
+}
+
 /// 关闭实例（关闭所有对应的主进程，让 Chromium 优雅关闭子进程）
 pub fn close_instance(user_data_dir: &Path, _timeout_secs: u64) -> Result<(), String> {
     // 获取所有主进程 PID（支持多窗口情况）
     let root_pids = get_all_instance_root_pids(user_data_dir);
+    crate::modules::crash_loop::expect_exit(user_data_dir);
 
     if root_pids.is_empty() {
         crate::modules::logger::log_info("Instance not running, nothing to close");
@@ -1706,30 +2036,16 @@ pub fn close_instance(user_data_dir: &Path, _timeout_secs: u64) -> Result<(), St
         root_pids
     ));
 
-    #[cfg(target_os = "windows")]
-    {
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        for pid in &root_pids {
-            // 使用 /F 强制关闭主进程
-            let _ = Command::new("taskkill")
-                .args(["/F", "/PID", &pid.to_string()])
-                .creation_flags(CREATE_NO_WINDOW)
-                .output();
-        }
-    }
-
-    #[cfg(not(target_os = "windows"))]
-    {
-        for pid in &root_pids {
-            // 发送 SIGTERM 而非 SIGKILL，让进程优雅关闭
-            let _ = Command::new("kill")
-                .args(["-15", &pid.to_string()])
-                .output();
-        }
-    }
+    // Windows 上仍强制结束 (与之前 taskkill /F 一致)；其他平台发送 SIGTERM 让进程优雅关闭
+    let errors = terminate_pids(&root_pids, cfg!(target_os = "windows"));
 
     // 等待进程优雅关闭
     thread::sleep(Duration::from_millis(1000));
+    invalidate_process_snapshot();
+
+    if !errors.is_empty() {
+        return Err(format_kill_errors(&errors));
+    }
     Ok(())
 }
 
@@ -1741,11 +2057,11 @@ pub fn close_instance(user_data_dir: &Path, _timeout_secs: u64) -> Result<(), St
 /// 3. 收集所有唯一的顶层进程（根进程）
 /// 4. 只检查根进程的命令行来判断属于哪个实例
 fn get_all_instance_root_pids(user_data_dir: &Path) -> Vec<u32> {
+    let rules = recognition_rules();
     #[cfg(target_os = "windows")]
     refresh_process_command_line_cache();
 
-    let mut system = System::new();
-    system.refresh_processes(sysinfo::ProcessesToUpdate::All);
+    let system = shared_process_scan();
 
     let user_data_dir_str = user_data_dir.to_string_lossy().to_lowercase();
     let user_data_dir_normalized = user_data_dir_str.replace('/', "\\");
@@ -1765,7 +2081,7 @@ fn get_all_instance_root_pids(user_data_dir: &Path) -> Vec<u32> {
 
     let is_antigravity_name = |name: &str| -> bool {
         let name_lower = name.to_lowercase();
-        name_lower == "antigravity.exe" || name_lower == "antigravity"
+        rules.matches_name_exact(&name_lower)
     };
 
     // 第一步：找到所有 antigravity 进程的根进程
@@ -1865,11 +2181,11 @@ fn get_all_instance_root_pids(user_data_dir: &Path) -> Vec<u32> {
 
 /// 获取实例的主进程 PID（顶层 Antigravity 进程）
 fn get_instance_root_pid(user_data_dir: &Path) -> Option<u32> {
+    let rules = recognition_rules();
     #[cfg(target_os = "windows")]
     refresh_process_command_line_cache();
 
-    let mut system = System::new();
-    system.refresh_processes(sysinfo::ProcessesToUpdate::All);
+    let system = shared_process_scan();
 
     let user_data_dir_str = user_data_dir.to_string_lossy().to_lowercase();
     let user_data_dir_normalized = user_data_dir_str.replace('/', "\\");
@@ -1877,7 +2193,7 @@ fn get_instance_root_pid(user_data_dir: &Path) -> Option<u32> {
     let is_antigravity_name = |name: &str| -> bool {
         let name_lower = name.to_lowercase();
         // 只匹配 antigravity.exe，排除 antigravity_tools.exe 等
-        name_lower == "antigravity.exe" || name_lower == "antigravity"
+        rules.matches_name_exact(&name_lower)
     };
 
     let find_root_antigravity = |start_pid: sysinfo::Pid| -> Option<sysinfo::Pid> {
@@ -1949,24 +2265,7 @@ pub fn start_instance(instance: &Instance) -> Result<(), String> {
         instance.name, args
     ));
 
-    #[cfg(target_os = "windows")]
-    {
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        Command::new(&exe_path)
-            .args(&args)
-            .creation_flags(CREATE_NO_WINDOW)
-            .spawn()
-            .map_err(|e| format!("Failed to start instance: {}", e))?;
-    }
-
-    #[cfg(not(target_os = "windows"))]
-    {
-        Command::new(&exe_path)
-            .args(&args)
-            .spawn()
-            .map_err(|e| format!("Failed to start instance: {}", e))?;
-    }
-
+    spawn_instance_process(instance, &exe_path, &args)?;
     crate::modules::logger::log_info(&format!("Instance startup command sent: {}", instance.name));
     Ok(())
 }
@@ -1984,34 +2283,880 @@ pub fn start_instance_with_args(instance: &Instance, args: Vec<String>) -> Resul
         instance.name, args
     ));
 
+    spawn_instance_process(instance, &exe_path, &args)?;
+    crate::modules::logger::log_info(&format!(
+        "Instance startup command sent: {} (with saved args)",
+        instance.name
+    ));
+    Ok(())
+}
+
+/// 启动实例进程：输出写入启动日志，子进程交给崩溃循环检测跟踪
+fn spawn_instance_process(instance: &Instance, exe_path: &str, args: &[String]) -> Result<(), String> {
+    crate::modules::crash_loop::ensure_not_faulted(instance)?;
+
+    // 写入窗口位置偏好（失败不影响启动）
+    if let Some(placement) = &instance.window_placement {
+        if let Err(e) =
+            crate::modules::device::write_window_placement(&instance.user_data_dir, placement)
+        {
+            crate::modules::logger::log_warn(&format!(
+                "Failed to apply window placement for instance {}: {}",
+                instance.name, e
+            ));
+        }
+    }
+
+    let mut cmd = Command::new(exe_path);
+    cmd.args(args);
+
     #[cfg(target_os = "windows")]
     {
         const CREATE_NO_WINDOW: u32 = 0x08000000;
-        Command::new(&exe_path)
-            .args(&args)
-            .creation_flags(CREATE_NO_WINDOW)
-            .spawn()
-            .map_err(|e| format!("Failed to start instance: {}", e))?;
+        let priority_class = instance
+            .priority
+            .map(|p| p.windows_priority_class())
+            .unwrap_or(0);
+        cmd.creation_flags(CREATE_NO_WINDOW | priority_class);
     }
 
-    #[cfg(not(target_os = "windows"))]
-    {
-        Command::new(&exe_path)
-            .args(&args)
-            .spawn()
-            .map_err(|e| format!("Failed to start instance: {}", e))?;
+    // Unix：在 exec 前设置 nice 值与 CPU 亲和性，Electron 之后派生的辅助进程会继承
+    #[cfg(unix)]
+    apply_unix_launch_limits(&mut cmd, instance);
+
+    crate::modules::instance_log::prepare_command(&mut cmd, instance, args);
+
+    let child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to start instance: {}", e))?;
+    crate::modules::instance_log::attach(instance, &child);
+
+    #[cfg(unix)]
+    if let Some(priority) = instance.priority {
+        check_unix_priority(&child, priority.nice_value(), &instance.name);
     }
 
-    crate::modules::logger::log_info(&format!(
-        "Instance startup command sent: {} (with saved args)",
-        instance.name
-    ));
+    #[cfg(target_os = "windows")]
+    if let Some(mask) = instance.cpu_affinity {
+        set_windows_affinity(&child, mask, &instance.name);
+    }
+
+    crate::modules::crash_loop::watch_launch(instance, child);
+
+    record_launched_instance(&instance.id);
+    invalidate_process_snapshot();
     Ok(())
 }
 
+#[cfg(unix)]
+fn apply_unix_launch_limits(cmd: &mut Command, instance: &Instance) {
+    use std::os::unix::process::CommandExt as _;
+
+    let nice = instance.priority.map(|p| p.nice_value());
+    #[cfg(target_os = "linux")]
+    let affinity = instance.cpu_affinity;
+    #[cfg(not(target_os = "linux"))]
+    if instance.cpu_affinity.is_some() {
+        crate::modules::logger::log_warn("CPU affinity is not supported on this platform, ignoring");
+    }
+    #[cfg(not(target_os = "linux"))]
+    let affinity: Option<u64> = None;
+
+    if nice.is_none() && affinity.is_none() {
+        return;
+    }
+
+    // pre_exec 闭包运行在 fork 之后，只调用 async-signal-safe 的系统调用；失败时忽略，不阻止启动
+    unsafe {
+        cmd.pre_exec(move || {
+            if let Some(nice) = nice {
+                libc::setpriority(libc::PRIO_PROCESS, 0, nice);
+            }
+            #[cfg(target_os = "linux")]
+            if let Some(mask) = affinity {
+                let mut set: libc::cpu_set_t = std::mem::zeroed();
+                for cpu in 0..64usize {
+                    if mask & (1u64 << cpu) != 0 {
+                        libc::CPU_SET(cpu, &mut set);
+                    }
+                }
+                libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set);
+            }
+            Ok(())
+        });
+    }
+}
+
+/// pre_exec 中无法记录日志：spawn 返回时 exec 已完成，在父进程中读回 nice 值确认是否生效
+#[cfg(unix)]
+fn check_unix_priority(child: &std::process::Child, expected: i32, instance_name: &str) {
+    // getpriority 可能合法地返回 -1，需要先清零 errno 再判断
+    #[cfg(target_os = "linux")]
+    unsafe {
+        *libc::__errno_location() = 0
+    };
+    #[cfg(target_os = "macos")]
+    unsafe {
+        *libc::__error() = 0
+    };
+    let actual = unsafe { libc::getpriority(libc::PRIO_PROCESS, child.id() as libc::id_t) };
+    let err = std::io::Error::last_os_error();
+    if actual == -1 && err.raw_os_error().unwrap_or(0) != 0 {
+        // 进程已退出等情况，交给崩溃检测处理
+        return;
+    }
+    if actual != expected {
+        crate::modules::logger::log_warn(&format!(
+            "Failed to set priority (nice {}) for instance {}, running at nice {}; raising priority requires root",
+            expected, instance_name, actual
+        ));
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn set_windows_affinity(child: &std::process::Child, mask: u64, instance_name: &str) {
+    use std::os::windows::io::AsRawHandle;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn SetProcessAffinityMask(process: *mut std::ffi::c_void, mask: usize) -> i32;
+    }
+
+    // 子进程句柄带有 PROCESS_ALL_ACCESS；之后派生的辅助进程继承该亲和性
+    let ok = unsafe { SetProcessAffinityMask(child.as_raw_handle() as *mut _, mask as usize) };
+    if ok == 0 {
+        crate::modules::logger::log_warn(&format!(
+            "Failed to set CPU affinity {:#x} for instance {}: {}",
+            mask,
+            instance_name,
+            std::io::Error::last_os_error()
+        ));
+    }
+}
+
+/// 本次运行期间由管理器启动过的实例 ID（用于退出时可选地关闭）
+static LAUNCHED_INSTANCES: once_cell::sync::Lazy<Mutex<std::collections::HashSet<String>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(std::collections::HashSet::new()));
+
+fn record_launched_instance(instance_id: &str) {
+    if let Ok(mut launched) = LAUNCHED_INSTANCES.lock() {
+        launched.insert(instance_id.to_string());
+    }
+}
+
+/// 获取本次运行期间由管理器启动过的实例 ID
+pub fn get_launched_instance_ids() -> Vec<String> {
+    LAUNCHED_INSTANCES
+        .lock()
+        .map(|l| l.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
 /// 重启实例
 pub fn restart_instance(instance: &Instance, timeout_secs: u64) -> Result<(), String> {
     close_instance(&instance.user_data_dir, timeout_secs)?;
     thread::sleep(Duration::from_secs(1));
     start_instance(instance)
 }
+
+// ==================== 进程监视器 ====================
+// 后台线程复用同一个 System 定期扫描并与上一次结果比对，变化时推送给订阅者；
+// is_instance_running 等查询优先读取快照，避免每次调用都新建 System 全量扫描。
+
+/// 监视器轮询间隔
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+/// 快照超过该时长未更新即视为过期（监视器卡住或未启动），查询回退到完整扫描
+const SNAPSHOT_MAX_AGE: Duration = Duration::from_secs(6);
+
+/// Antigravity 主进程（不含 --type= 辅助进程）
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct RootProcess {
+    pub pid: u32,
+    /// None 表示默认实例（未指定 --user-data-dir）
+    pub user_data_dir: Option<std::path::PathBuf>,
+}
+
+/// 某一时刻所有 Antigravity 主进程
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct ProcessSnapshot {
+    pub roots: Vec<RootProcess>,
+    pub scanned_at: i64,
+}
+
+impl ProcessSnapshot {
+    pub fn is_default_running(&self) -> bool {
+        self.roots.iter().any(|r| r.user_data_dir.is_none())
+    }
+
+    pub fn is_instance_running(&self, user_data_dir: &Path) -> bool {
+        let target = normalize_dir_for_match(&user_data_dir.to_string_lossy());
+        self.roots.iter().any(|r| {
+            r.user_data_dir
+                .as_ref()
+                .map(|d| normalize_dir_for_match(&d.to_string_lossy()) == target)
+                .unwrap_or(false)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProcessEventKind {
+    Started,
+    Stopped,
+}
+
+/// 进程状态变化事件（同时以 "process://changed" 推送给前端）
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ProcessEvent {
+    pub kind: ProcessEventKind,
+    pub process: RootProcess,
+}
+
+struct WatchedSnapshot {
+    snapshot: ProcessSnapshot,
+    taken_at: std::time::Instant,
+    generation: u64,
+}
+
+static WATCHER_STARTED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+/// 管理器自身启动/关闭进程后递增，使扫描开始于此之前的快照失效
+static SNAPSHOT_GENERATION: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+static LATEST_SNAPSHOT: once_cell::sync::Lazy<Mutex<Option<WatchedSnapshot>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(None));
+static SUBSCRIBERS: once_cell::sync::Lazy<Mutex<Vec<std::sync::mpsc::Sender<ProcessEvent>>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(Vec::new()));
+
+pub(crate) fn normalize_dir_for_match(dir: &str) -> String {
+    dir.trim_matches('"')
+        .to_lowercase()
+        .replace('/', "\\")
+        .trim_end_matches('\\')
+        .to_string()
+}
+
+/// 使当前快照失效（管理器刚启动/关闭了进程，快照可能尚未反映）
+pub(crate) fn invalidate_process_snapshot() {
+    SNAPSHOT_GENERATION.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+}
+
+/// 共享的全量进程扫描结果缓存有效期
+const PROCESS_SCAN_TTL: Duration = Duration::from_millis(1000);
+
+struct CachedProcessScan {
+    system: Arc<System>,
+    refreshed_at: std::time::Instant,
+    generation: u64,
+}
+
+static PROCESS_SCAN_CACHE: once_cell::sync::Lazy<Mutex<Option<CachedProcessScan>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(None));
+
+/// 获取全量进程扫描结果：TTL 内或未被失效时复用同一份结果，
+/// 避免前端轮询时每个状态查询都对系统做一次完整扫描。
+/// 并发调用在锁上等待同一次扫描完成，而不是各自扫描。
+fn shared_process_scan() -> Arc<System> {
+    let generation = SNAPSHOT_GENERATION.load(std::sync::atomic::Ordering::SeqCst);
+    let mut cache = PROCESS_SCAN_CACHE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+
+    if let Some(cached) = cache.as_mut() {
+        if cached.generation == generation && cached.refreshed_at.elapsed() < PROCESS_SCAN_TTL {
+            return cached.system.clone();
+        }
+        // 没有调用方持有旧结果时原地增量刷新，否则重新扫描
+        if let Some(system) = Arc::get_mut(&mut cached.system) {
+            refresh_scan(system);
+        } else {
+            let mut system = System::new();
+            refresh_scan(&mut system);
+            cached.system = Arc::new(system);
+        }
+        cached.refreshed_at = std::time::Instant::now();
+        cached.generation = generation;
+        return cached.system.clone();
+    }
+
+    let mut system = System::new();
+    refresh_scan(&mut system);
+    let system = Arc::new(system);
+    *cache = Some(CachedProcessScan {
+        system: system.clone(),
+        refreshed_at: std::time::Instant::now(),
+        generation,
+    });
+    system
+}
+
+/// 全量扫描需要命令行与可执行路径（实例识别依赖 --user-data-dir 与 exe 匹配）
+fn refresh_scan(system: &mut System) {
+    use sysinfo::{ProcessRefreshKind, UpdateKind};
+    system.refresh_processes_specifics(
+        sysinfo::ProcessesToUpdate::All,
+        ProcessRefreshKind::new()
+            .with_cmd(UpdateKind::OnlyIfNotSet)
+            .with_exe(UpdateKind::OnlyIfNotSet),
+    );
+}
+
+/// 按进程名 / 可执行路径判断是否为 Antigravity 进程（含辅助进程）
+fn is_antigravity_process_name(
+    process: &sysinfo::Process,
+    rules: &crate::models::ProcessRecognitionConfig,
+) -> bool {
+    #[allow(unused_variables)]
+    let name = process.name().to_string_lossy().to_lowercase();
+    #[allow(unused_variables)]
+    let exe_path = process
+        .exe()
+        .and_then(|p| p.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    #[cfg(target_os = "macos")]
+    {
+        rules.matches_path(&exe_path)
+    }
+    #[cfg(target_os = "windows")]
+    {
+        rules.matches_name(&name)
+    }
+    #[cfg(target_os = "linux")]
+    {
+        rules.matches_name(&name) || rules.matches_path(&exe_path)
+    }
+}
+
+/// 从命令行参数中提取 --user-data-dir
+pub(crate) fn extract_user_data_dir(args: &[String]) -> Option<String> {
+    for (i, arg) in args.iter().enumerate() {
+        if arg == "--user-data-dir" {
+            return args.get(i + 1).cloned();
+        } else if let Some(value) = arg.strip_prefix("--user-data-dir=") {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// 扫描所有 Antigravity 主进程
+fn scan_root_processes(system: &mut System) -> Vec<RootProcess> {
+    let rules = recognition_rules();
+    use sysinfo::{ProcessRefreshKind, UpdateKind};
+
+    system.refresh_processes_specifics(
+        sysinfo::ProcessesToUpdate::All,
+        ProcessRefreshKind::new()
+            .with_cmd(UpdateKind::OnlyIfNotSet)
+            .with_exe(UpdateKind::OnlyIfNotSet),
+    );
+
+    let current_pid = std::process::id();
+    let current_exe = get_current_exe_path();
+    let mut roots = Vec::new();
+
+    for (pid, process) in system.processes() {
+        let pid_u32 = pid.as_u32();
+        if pid_u32 == current_pid {
+            continue;
+        }
+
+        if let (Some(ref my_path), Some(p_exe)) = (&current_exe, process.exe()) {
+            if let Ok(p_path) = p_exe.canonicalize() {
+                if my_path == &p_path {
+                    continue;
+                }
+            }
+        }
+
+        if !is_antigravity_process_name(process, &rules) {
+            continue;
+        }
+
+        let args: Vec<String> = process
+            .cmd()
+            .iter()
+            .map(|s| s.to_string_lossy().to_string())
+            .collect();
+
+        // 无法读取命令行（权限不足等）时无法判断归属，跳过
+        if args.is_empty() || args.iter().any(|a| a.starts_with("--type=")) {
+            continue;
+        }
+
+        roots.push(RootProcess {
+            pid: pid_u32,
+            user_data_dir: extract_user_data_dir(&args)
+                .map(|d| std::path::PathBuf::from(d.trim_matches('"'))),
+        });
+    }
+
+    roots.sort_by_key(|r| r.pid);
+    roots
+}
+
+/// 比对两次扫描结果
+fn diff_root_processes(previous: &[RootProcess], current: &[RootProcess]) -> Vec<ProcessEvent> {
+    let mut events = Vec::new();
+    for process in previous {
+        if !current.contains(process) {
+            events.push(ProcessEvent {
+                kind: ProcessEventKind::Stopped,
+                process: process.clone(),
+            });
+        }
+    }
+    for process in current {
+        if !previous.contains(process) {
+            events.push(ProcessEvent {
+                kind: ProcessEventKind::Started,
+                process: process.clone(),
+            });
+        }
+    }
+    events
+}
+
+/// 启动后台进程监视器（重复调用无副作用）
+pub fn start_process_watcher(app_handle: tauri::AppHandle) {
+    use std::sync::atomic::Ordering;
+    use tauri::Emitter;
+
+    if WATCHER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let spawned = thread::Builder::new()
+        .name("process-watcher".to_string())
+        .spawn(move || {
+            let mut system = System::new();
+            let mut previous: Vec<RootProcess> = Vec::new();
+            let mut first_scan = true;
+
+            loop {
+                let generation = SNAPSHOT_GENERATION.load(Ordering::SeqCst);
+                let roots = scan_root_processes(&mut system);
+                let events = if first_scan {
+                    Vec::new()
+                } else {
+                    diff_root_processes(&previous, &roots)
+                };
+                first_scan = false;
+
+                if let Ok(mut latest) = LATEST_SNAPSHOT.lock() {
+                    *latest = Some(WatchedSnapshot {
+                        snapshot: ProcessSnapshot {
+                            roots: roots.clone(),
+                            scanned_at: chrono::Utc::now().timestamp(),
+                        },
+                        taken_at: std::time::Instant::now(),
+                        generation,
+                    });
+                }
+
+                if !events.is_empty() {
+                    for event in &events {
+                        crate::modules::logger::log_info(&format!(
+                            "Process watcher: {:?} pid={} user_data_dir={:?}",
+                            event.kind, event.process.pid, event.process.user_data_dir
+                        ));
+                    }
+                    if let Ok(mut subscribers) = SUBSCRIBERS.lock() {
+                        subscribers.retain(|tx| events.iter().all(|e| tx.send(e.clone()).is_ok()));
+                    }
+                    let _ = app_handle.emit("process://changed", &events);
+                }
+
+                previous = roots;
+                thread::sleep(WATCH_INTERVAL);
+            }
+        });
+
+    if let Err(e) = spawned {
+        WATCHER_STARTED.store(false, Ordering::SeqCst);
+        crate::modules::logger::log_error(&format!("Failed to start process watcher: {}", e));
+    }
+}
+
+/// 订阅进程启动/退出事件；接收端被丢弃后自动取消订阅
+pub fn subscribe_process_events() -> std::sync::mpsc::Receiver<ProcessEvent> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    if let Ok(mut subscribers) = SUBSCRIBERS.lock() {
+        subscribers.push(tx);
+    }
+    rx
+}
+
+/// 获取监视器维护的最新快照；监视器未运行、快照过期或已被失效时返回 None
+pub fn get_watched_snapshot() -> Option<ProcessSnapshot> {
+    let latest = LATEST_SNAPSHOT.lock().ok()?;
+    let watched = latest.as_ref()?;
+    if watched.generation != SNAPSHOT_GENERATION.load(std::sync::atomic::Ordering::SeqCst) {
+        return None;
+    }
+    if watched.taken_at.elapsed() > SNAPSHOT_MAX_AGE {
+        return None;
+    }
+    Some(watched.snapshot.clone())
+}
+
+/// 获取当前进程快照：优先使用监视器结果，否则立即扫描一次
+pub fn get_process_snapshot() -> ProcessSnapshot {
+    get_watched_snapshot().unwrap_or_else(|| {
+        let mut system = System::new();
+        ProcessSnapshot {
+            roots: scan_root_processes(&mut system),
+            scanned_at: chrono::Utc::now().timestamp(),
+        }
+    })
+}
+
+// ==================== 进程树诊断 ====================
+
+/// 进程树节点
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ProcessTreeNode {
+    pub pid: u32,
+    pub parent_pid: Option<u32>,
+    pub name: String,
+    pub exe: Option<String>,
+    /// 主进程为 "main"，辅助进程为 --type= 的值（renderer / gpu-process / utility ...）
+    pub process_type: String,
+    pub memory_bytes: u64,
+    pub args: Vec<String>,
+    pub children: Vec<ProcessTreeNode>,
+}
+
+/// 某个实例的完整进程树（用于排查运行状态检测问题）
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct InstanceProcessTree {
+    pub user_data_dir: std::path::PathBuf,
+    pub is_default: bool,
+    /// is_instance_running / is_default_instance_running 当前给出的结论
+    pub detected_running: bool,
+    pub root_pids: Vec<u32>,
+    /// 各类型进程数量（main / renderer / gpu-process ...）
+    pub process_types: std::collections::BTreeMap<String, usize>,
+    pub total_memory_bytes: u64,
+    pub trees: Vec<ProcessTreeNode>,
+}
+
+fn build_process_tree_node(
+    system: &System,
+    pid: sysinfo::Pid,
+    children_of: &std::collections::HashMap<sysinfo::Pid, Vec<sysinfo::Pid>>,
+) -> Option<ProcessTreeNode> {
+    let process = system.process(pid)?;
+    let args: Vec<String> = process
+        .cmd()
+        .iter()
+        .map(|a| a.to_string_lossy().to_string())
+        .collect();
+    let process_type = args
+        .iter()
+        .find_map(|a| a.strip_prefix("--type=").map(|t| t.to_string()))
+        .unwrap_or_else(|| "main".to_string());
+
+    let mut children: Vec<ProcessTreeNode> = children_of
+        .get(&pid)
+        .map(|pids| {
+            pids.iter()
+                .filter_map(|child| build_process_tree_node(system, *child, children_of))
+                .collect()
+        })
+        .unwrap_or_default();
+    children.sort_by_key(|c| c.pid);
+
+    Some(ProcessTreeNode {
+        pid: pid.as_u32(),
+        parent_pid: process.parent().map(|p| p.as_u32()),
+        name: process.name().to_string_lossy().to_string(),
+        exe: process.exe().map(|p| p.to_string_lossy().to_string()),
+        process_type,
+        memory_bytes: process.memory(),
+        args,
+        children,
+    })
+}
+
+fn collect_tree_stats(
+    node: &ProcessTreeNode,
+    types: &mut std::collections::BTreeMap<String, usize>,
+    memory: &mut u64,
+) {
+    *types.entry(node.process_type.clone()).or_insert(0) += 1;
+    *memory += node.memory_bytes;
+    for child in &node.children {
+        collect_tree_stats(child, types, memory);
+    }
+}
+
+/// 获取实例的进程树：以父进程不是 Antigravity 的进程为树根，按树根的 --user-data-dir 归属实例
+pub fn get_instance_process_tree(user_data_dir: &Path, is_default: bool) -> InstanceProcessTree {
+    let rules = recognition_rules();
+    use sysinfo::{ProcessRefreshKind, UpdateKind};
+
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        sysinfo::ProcessesToUpdate::All,
+        ProcessRefreshKind::new()
+            .with_memory()
+            .with_cmd(UpdateKind::Always)
+            .with_exe(UpdateKind::Always),
+    );
+
+    let current_pid = std::process::id();
+    let target = normalize_dir_for_match(&user_data_dir.to_string_lossy());
+
+    let antigravity: std::collections::HashSet<sysinfo::Pid> = system
+        .processes()
+        .iter()
+        .filter(|(pid, process)| {
+            pid.as_u32() != current_pid && is_antigravity_process_name(process, &rules)
+        })
+        .map(|(pid, _)| *pid)
+        .collect();
+
+    let mut children_of: std::collections::HashMap<sysinfo::Pid, Vec<sysinfo::Pid>> =
+        std::collections::HashMap::new();
+    let mut tree_roots = Vec::new();
+    for pid in &antigravity {
+        let parent = system.process(*pid).and_then(|p| p.parent());
+        match parent {
+            Some(parent) if antigravity.contains(&parent) => {
+                children_of.entry(parent).or_default().push(*pid);
+            }
+            _ => tree_roots.push(*pid),
+        }
+    }
+
+    let mut trees: Vec<ProcessTreeNode> = tree_roots
+        .into_iter()
+        .filter_map(|pid| build_process_tree_node(&system, pid, &children_of))
+        .filter(|node| match extract_user_data_dir(&node.args) {
+            Some(dir) => !is_default && normalize_dir_for_match(&dir) == target,
+            None => is_default,
+        })
+        .collect();
+    trees.sort_by_key(|t| t.pid);
+
+    let mut process_types = std::collections::BTreeMap::new();
+    let mut total_memory_bytes = 0;
+    for tree in &trees {
+        collect_tree_stats(tree, &mut process_types, &mut total_memory_bytes);
+    }
+
+    InstanceProcessTree {
+        user_data_dir: user_data_dir.to_path_buf(),
+        is_default,
+        detected_running: if is_default {
+            is_default_instance_running()
+        } else {
+            is_instance_running(user_data_dir)
+        },
+        root_pids: trees
+            .iter()
+            .filter(|t| t.process_type == "main")
+            .map(|t| t.pid)
+            .collect(),
+        process_types,
+        total_memory_bytes,
+        trees,
+    }
+}
+
+// ==================== 孤儿辅助进程清理 ====================
+// Antigravity 崩溃后，renderer / gpu-process 等辅助进程可能残留（父进程已不存在），
+// 它们会累积并干扰 is_instance_running 等检测。
+
+/// 孤儿辅助进程（所在子树的根）
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct OrphanedHelper {
+    pub pid: u32,
+    pub parent_pid: Option<u32>,
+    pub name: String,
+    /// --type= 的值（renderer / gpu-process / utility ...）
+    pub process_type: String,
+    /// 从 --user-data-dir 推断的归属实例目录
+    pub user_data_dir: Option<std::path::PathBuf>,
+    /// 该辅助进程派生的子进程（清理时一并终止）
+    pub descendant_pids: Vec<u32>,
+    pub memory_bytes: u64,
+    /// 已运行时长（秒）
+    pub run_time_secs: u64,
+}
+
+/// 清理结果
+#[derive(Debug, Default, serde::Serialize)]
+pub struct OrphanCleanupReport {
+    pub terminated: Vec<u32>,
+    /// 请求清理但重新扫描时已不是孤儿（或已退出）的 PID
+    pub skipped: Vec<u32>,
+    pub errors: Vec<String>,
+}
+
+/// 刚启动的进程可能尚未完成父子关系建立，运行时间不足时不判定为孤儿
+const ORPHAN_MIN_RUN_TIME_SECS: u64 = 30;
+
+fn collect_descendants(
+    pid: sysinfo::Pid,
+    children_of: &std::collections::HashMap<sysinfo::Pid, Vec<sysinfo::Pid>>,
+    out: &mut Vec<u32>,
+) {
+    if let Some(children) = children_of.get(&pid) {
+        for child in children {
+            out.push(child.as_u32());
+            collect_descendants(*child, children_of, out);
+        }
+    }
+}
+
+/// 查找孤儿辅助进程：父进程不是 Antigravity 的辅助进程（带 --type=），
+/// 且没有运行中的主进程使用同一个 user-data-dir（无法判断目录时，仅在没有任何主进程运行时判定）
+pub fn find_orphaned_helpers() -> Vec<OrphanedHelper> {
+    let rules = recognition_rules();
+    use sysinfo::{ProcessRefreshKind, UpdateKind};
+
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        sysinfo::ProcessesToUpdate::All,
+        ProcessRefreshKind::new()
+            .with_memory()
+            .with_cmd(UpdateKind::Always)
+            .with_exe(UpdateKind::Always),
+    );
+
+    let current_pid = std::process::id();
+    let process_args = |process: &sysinfo::Process| -> Vec<String> {
+        process
+            .cmd()
+            .iter()
+            .map(|a| a.to_string_lossy().to_string())
+            .collect()
+    };
+
+    let antigravity: std::collections::HashSet<sysinfo::Pid> = system
+        .processes()
+        .iter()
+        .filter(|(pid, process)| {
+            pid.as_u32() != current_pid && is_antigravity_process_name(process, &rules)
+        })
+        .map(|(pid, _)| *pid)
+        .collect();
+
+    let mut children_of: std::collections::HashMap<sysinfo::Pid, Vec<sysinfo::Pid>> =
+        std::collections::HashMap::new();
+    let mut tree_roots = Vec::new();
+    // 运行中的主进程目录（None 表示默认实例）
+    let mut main_dirs: Vec<Option<String>> = Vec::new();
+    for pid in &antigravity {
+        let Some(process) = system.process(*pid) else {
+            continue;
+        };
+        let args = process_args(process);
+        if !args.is_empty() && !args.iter().any(|a| a.starts_with("--type=")) {
+            main_dirs.push(extract_user_data_dir(&args).map(|d| normalize_dir_for_match(&d)));
+        }
+        match process.parent() {
+            Some(parent) if antigravity.contains(&parent) => {
+                children_of.entry(parent).or_default().push(*pid);
+            }
+            _ => tree_roots.push(*pid),
+        }
+    }
+
+    let mut orphans = Vec::new();
+    for pid in tree_roots {
+        let Some(process) = system.process(pid) else {
+            continue;
+        };
+        let args = process_args(process);
+        let Some(process_type) = args
+            .iter()
+            .find_map(|a| a.strip_prefix("--type=").map(|t| t.to_string()))
+        else {
+            continue;
+        };
+        if process.run_time() < ORPHAN_MIN_RUN_TIME_SECS {
+            continue;
+        }
+
+        let dir = extract_user_data_dir(&args);
+        let owner_alive = match &dir {
+            Some(d) => {
+                let key = normalize_dir_for_match(d);
+                main_dirs.iter().any(|m| m.as_deref() == Some(key.as_str()))
+            }
+            None => !main_dirs.is_empty(),
+        };
+        if owner_alive {
+            continue;
+        }
+
+        let mut descendant_pids = Vec::new();
+        collect_descendants(pid, &children_of, &mut descendant_pids);
+        let memory_bytes = process.memory()
+            + descendant_pids
+                .iter()
+                .filter_map(|p| system.process(sysinfo::Pid::from_u32(*p)))
+                .map(|p| p.memory())
+                .sum::<u64>();
+
+        orphans.push(OrphanedHelper {
+            pid: pid.as_u32(),
+            parent_pid: process.parent().map(|p| p.as_u32()),
+            name: process.name().to_string_lossy().to_string(),
+            process_type,
+            user_data_dir: dir.map(|d| std::path::PathBuf::from(d.trim_matches('"'))),
+            descendant_pids,
+            memory_bytes,
+            run_time_secs: process.run_time(),
+        });
+    }
+
+    orphans.sort_by_key(|o| o.pid);
+    orphans
+}
+
+/// 终止孤儿辅助进程；pids 为空时清理全部。终止前重新扫描，只处理仍然是孤儿的进程
+pub fn cleanup_orphaned_helpers(pids: Option<&[u32]>) -> OrphanCleanupReport {
+    let orphans = find_orphaned_helpers();
+    let mut report = OrphanCleanupReport::default();
+
+    if let Some(requested) = pids {
+        report.skipped = requested
+            .iter()
+            .filter(|p| !orphans.iter().any(|o| o.pid == **p))
+            .copied()
+            .collect();
+    }
+
+    for orphan in orphans
+        .iter()
+        .filter(|o| pids.map(|p| p.contains(&o.pid)).unwrap_or(true))
+    {
+        // 先终止子进程，再终止子树根
+        let mut targets = orphan.descendant_pids.clone();
+        targets.reverse();
+        targets.push(orphan.pid);
+
+        // 辅助进程在 Windows 上没有窗口可关闭，只能强制结束
+        let errors = terminate_pids(&targets, cfg!(target_os = "windows"));
+        report.errors.extend(errors.iter().map(|e| e.to_string()));
+        report.terminated.extend(
+            targets
+                .iter()
+                .filter(|p| !errors.iter().any(|e| e.pid == **p)),
+        );
+    }
+
+    if !report.terminated.is_empty() {
+        crate::modules::logger::log_info(&format!(
+            "Terminated orphaned Antigravity helper processes: {:?}",
+            report.terminated
+        ));
+        invalidate_process_snapshot();
+    }
+    report
+}
@@ -0,0 +1,229 @@
+//! 实例配置目录漂移检测
+//!
+//! 定期检查每个受管实例 user_data_dir 中的关键文件（state.vscdb 登录状态、storage.json 设备指纹、
+//! settings.json 用户设置）。文件在管理器之外被修改（用户在 Antigravity 内手动登录其他账号、
+//! 设置被重置）时发出 `instances://profile_drift` 事件，并同步修正实例记录的当前账号。
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::Emitter;
+
+use crate::models::Instance;
+use crate::modules::{account, db, device, instance, logger};
+
+/// 轮询间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 漂移事件
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProfileDriftEvent {
+    pub instance_id: String,
+    pub instance_name: String,
+    /// "account_changed" | "account_logged_out" | "device_profile_changed" | "settings_reset"
+    pub kind: String,
+    /// 相关文件
+    pub file: PathBuf,
+    /// 实例记录的账号
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_account_id: Option<String>,
+    /// 数据库中实际登录的已知账号（未知账号为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual_account_id: Option<String>,
+    /// 是否已将实例的当前账号更新为实际登录的账号
+    pub state_updated: bool,
+}
+
+/// 单个实例的监视状态
+#[derive(Default)]
+struct WatchState {
+    /// 文件指纹（大小 + 修改时间），用于跳过未变化的文件
+    fingerprints: HashMap<PathBuf, Option<(u64, u128)>>,
+    /// 上一轮检测到的持续性漂移（文件未变化时沿用）
+    last: Vec<ProfileDriftEvent>,
+    /// 已上报的漂移类型，恢复正常前不重复上报
+    reported: Vec<String>,
+    settings_non_empty: bool,
+}
+
+fn fingerprint(path: &Path) -> Option<(u64, u128)> {
+    let meta = std::fs::metadata(path).ok()?;
+    let modified = meta
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_millis();
+    Some((meta.len(), modified))
+}
+
+fn settings_path(user_data_dir: &Path) -> PathBuf {
+    user_data_dir.join("User").join("settings.json")
+}
+
+/// settings.json 是否有实际内容（只含空对象视为被重置）
+fn settings_has_content(path: &Path) -> bool {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|c| serde_json::from_str::<serde_json::Value>(&c).ok())
+        .and_then(|v| v.as_object().map(|o| !o.is_empty()))
+        .unwrap_or(false)
+}
+
+/// 检查实例，返回（持续性漂移, 一次性事件）
+fn detect(
+    inst: &Instance,
+    state: &mut WatchState,
+) -> (Vec<ProfileDriftEvent>, Vec<ProfileDriftEvent>) {
+    let db_path = db::get_db_path_for_instance(&inst.user_data_dir);
+    let db_wal = db_path.with_extension("vscdb-wal");
+    let storage_path = device::get_storage_path_for_instance(&inst.user_data_dir);
+    let settings = settings_path(&inst.user_data_dir);
+
+    let watched = [
+        db_path.clone(),
+        db_wal,
+        storage_path.clone(),
+        settings.clone(),
+    ];
+    let mut changed = false;
+    for path in &watched {
+        let current = fingerprint(path);
+        if state.fingerprints.get(path) != Some(&current) {
+            state.fingerprints.insert(path.clone(), current);
+            changed = true;
+        }
+    }
+    // 文件未变化时沿用上一轮结果
+    if !changed {
+        return (state.last.clone(), Vec::new());
+    }
+
+    let mut drifts = Vec::new();
+    let event = |kind: &str, file: &Path| ProfileDriftEvent {
+        instance_id: inst.id.clone(),
+        instance_name: inst.name.clone(),
+        kind: kind.to_string(),
+        file: file.to_path_buf(),
+        expected_account_id: inst.current_account_id.clone(),
+        actual_account_id: None,
+        state_updated: false,
+    };
+
+    let expected = inst
+        .current_account_id
+        .as_deref()
+        .and_then(|id| account::load_account(id).ok());
+
+    // 1. 登录状态
+    if let Some(expected) = &expected {
+        if db_path.exists() {
+            match db::read_refresh_token(&db_path) {
+                Some(token) if token == expected.token.refresh_token => {}
+                Some(token) => {
+                    let mut drift = event("account_changed", &db_path);
+                    drift.actual_account_id = account::list_accounts()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .find(|a| a.token.refresh_token == token)
+                        .map(|a| a.id);
+                    drifts.push(drift);
+                }
+                None => drifts.push(event("account_logged_out", &db_path)),
+            }
+        }
+
+        // 2. 设备指纹
+        if let (Some(bound), Ok(actual)) = (
+            &expected.device_profile,
+            device::read_profile(&storage_path),
+        ) {
+            if bound.machine_id != actual.machine_id
+                || bound.mac_machine_id != actual.mac_machine_id
+                || bound.dev_device_id != actual.dev_device_id
+                || bound.sqm_id != actual.sqm_id
+            {
+                drifts.push(event("device_profile_changed", &storage_path));
+            }
+        }
+    }
+
+    // 3. 用户设置被清空
+    let mut one_shot = Vec::new();
+    let has_content = settings_has_content(&settings);
+    if state.settings_non_empty && !has_content {
+        one_shot.push(event("settings_reset", &settings));
+    }
+    state.settings_non_empty = has_content;
+
+    (drifts, one_shot)
+}
+
+/// 将实例的当前账号同步为实际登录的已知账号
+fn sync_instance_account(inst: &Instance, drift: &mut ProfileDriftEvent) {
+    let Some(actual) = drift.actual_account_id.clone() else {
+        return;
+    };
+    let Ok(mut latest) = instance::load_instance(&inst.id) else {
+        return;
+    };
+    latest.current_account_id = Some(actual.clone());
+    latest.bind_account(actual);
+    match instance::update_instance(&latest) {
+        Ok(()) => drift.state_updated = true,
+        Err(e) => logger::log_warn(&format!(
+            "[ProfileWatcher] 同步实例 {} 当前账号失败: {}",
+            inst.name, e
+        )),
+    }
+}
+
+/// 启动配置目录漂移检测
+pub fn start(app: tauri::AppHandle) {
+    std::thread::Builder::new()
+        .name("profile-watcher".to_string())
+        .spawn(move || {
+            let mut states: HashMap<String, WatchState> = HashMap::new();
+
+            loop {
+                let instances = instance::list_instances().unwrap_or_default();
+                states.retain(|id, _| instances.iter().any(|i| &i.id == id));
+
+                for inst in &instances {
+                    let state = states.entry(inst.id.clone()).or_default();
+                    let previous: Vec<String> = state.last.iter().map(|d| d.kind.clone()).collect();
+                    let (drifts, one_shot) = detect(inst, state);
+                    state.last = drifts.clone();
+                    let kinds: Vec<String> = drifts.iter().map(|d| d.kind.clone()).collect();
+
+                    // 持续性漂移需连续两轮出现才上报，避免与管理器自身的写入（切换账号）竞争
+                    let confirmed: Vec<ProfileDriftEvent> = drifts
+                        .into_iter()
+                        .filter(|d| previous.contains(&d.kind) && !state.reported.contains(&d.kind))
+                        .collect();
+                    for mut drift in confirmed.into_iter().chain(one_shot) {
+                        if drift.kind == "account_changed" {
+                            sync_instance_account(inst, &mut drift);
+                            // 当前账号已变化，下一轮重新检测
+                            state.fingerprints.clear();
+                        }
+                        logger::log_warn(&format!(
+                            "[ProfileWatcher] 实例 {} 在管理器之外发生变化: {} ({:?})",
+                            inst.name, drift.kind, drift.file
+                        ));
+                        let _ = app.emit("instances://profile_drift", &drift);
+                        state.reported.push(drift.kind);
+                    }
+                    // 漂移恢复后允许再次上报
+                    state.reported.retain(|k| kinds.contains(k));
+                }
+
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        })
+        .map(|_| ())
+        .unwrap_or_else(|e| {
+            logger::log_error(&format!("Failed to start profile watcher: {}", e));
+        });
+}
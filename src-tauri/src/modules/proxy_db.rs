@@ -1,6 +1,7 @@
 use rusqlite::{params, Connection};
 use std::path::PathBuf;
 use crate::proxy::monitor::ProxyRequestLog;
+use crate::proxy::mirror::MirrorSample;
 
 pub fn get_proxy_db_path() -> Result<PathBuf, String> {
     let data_dir = crate::modules::account::get_data_dir()?;
@@ -61,6 +62,45 @@ pub fn init_db() -> Result<(), String> {
         [],
     ).map_err(|e| e.to_string())?;
 
+    // Mirrored request samples (primary vs mirror responses for offline comparison)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS mirror_samples (
+            id TEXT PRIMARY KEY,
+            timestamp INTEGER NOT NULL,
+            protocol TEXT,
+            client_model TEXT,
+            primary_model TEXT,
+            primary_account TEXT,
+            primary_status INTEGER,
+            primary_latency_ms INTEGER,
+            primary_streamed INTEGER,
+            primary_response TEXT,
+            mirror_model TEXT,
+            mirror_account TEXT,
+            mirror_status INTEGER,
+            mirror_latency_ms INTEGER,
+            mirror_response TEXT,
+            mirror_error TEXT,
+            request_body TEXT
+        )",
+        [],
+    ).map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_mirror_timestamp ON mirror_samples (timestamp DESC)",
+        [],
+    ).map_err(|e| e.to_string())?;
+
+    // Key-value store for proxy runtime state (warm restart)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS proxy_state (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    ).map_err(|e| e.to_string())?;
+
     Ok(())
 }
 
@@ -121,6 +161,7 @@ pub fn get_logs_summary(limit: usize, offset: usize) -> Result<Vec<ProxyRequestL
             response_body: None, // Don't query large fields for list view
             input_tokens: row.get(10).unwrap_or(None),
             output_tokens: row.get(11).unwrap_or(None),
+            cache_read_tokens: None,
             protocol: row.get(14).unwrap_or(None),
         })
     }).map_err(|e| e.to_string())?;
@@ -186,6 +227,7 @@ pub fn get_log_detail(log_id: &str) -> Result<ProxyRequestLog, String> {
             response_body: row.get(9).unwrap_or(None),
             input_tokens: row.get(10).unwrap_or(None),
             output_tokens: row.get(11).unwrap_or(None),
+            cache_read_tokens: None,
             protocol: row.get(14).unwrap_or(None),
         })
     }).map_err(|e| e.to_string())
@@ -208,6 +250,128 @@ pub fn cleanup_old_logs(days: i64) -> Result<usize, String> {
     Ok(deleted)
 }
 
+/// Merge the WAL back into the main database file (called on app exit)
+pub fn checkpoint() -> Result<(), String> {
+    let conn = connect_db()?;
+    conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")
+        .map_err(|e| e.to_string())
+}
+
+/// Save a proxy runtime state blob (JSON), replacing any previous value
+pub fn save_proxy_state(key: &str, value: &str) -> Result<(), String> {
+    let conn = connect_db()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO proxy_state (key, value, updated_at) VALUES (?1, ?2, ?3)",
+        params![key, value, chrono::Utc::now().timestamp()],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Load a proxy runtime state blob with its last update time
+pub fn load_proxy_state(key: &str) -> Result<Option<(String, i64)>, String> {
+    let conn = connect_db()?;
+    let result = conn.query_row(
+        "SELECT value, updated_at FROM proxy_state WHERE key = ?1",
+        [key],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    );
+    match result {
+        Ok(row) => Ok(Some(row)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+pub fn save_mirror_sample(sample: &MirrorSample) -> Result<(), String> {
+    let conn = connect_db()?;
+    conn.execute(
+        "INSERT INTO mirror_samples (id, timestamp, protocol, client_model, primary_model, primary_account, primary_status, primary_latency_ms, primary_streamed, primary_response, mirror_model, mirror_account, mirror_status, mirror_latency_ms, mirror_response, mirror_error, request_body)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+        params![
+            sample.id,
+            sample.timestamp,
+            sample.protocol,
+            sample.client_model,
+            sample.primary_model,
+            sample.primary_account,
+            sample.primary_status,
+            sample.primary_latency_ms,
+            sample.primary_streamed,
+            sample.primary_response,
+            sample.mirror_model,
+            sample.mirror_account,
+            sample.mirror_status,
+            sample.mirror_latency_ms,
+            sample.mirror_response,
+            sample.mirror_error,
+            sample.request_body,
+        ],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Get mirror samples (newest first). Request/response bodies are only included when `with_bodies` is set
+pub fn get_mirror_samples(limit: usize, offset: usize, with_bodies: bool) -> Result<Vec<MirrorSample>, String> {
+    let conn = connect_db()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, timestamp, protocol, client_model, primary_model, primary_account, primary_status, primary_latency_ms, primary_streamed,
+                CASE WHEN ?3 THEN primary_response ELSE NULL END,
+                mirror_model, mirror_account, mirror_status, mirror_latency_ms,
+                CASE WHEN ?3 THEN mirror_response ELSE NULL END,
+                mirror_error,
+                CASE WHEN ?3 THEN request_body ELSE NULL END
+         FROM mirror_samples
+         ORDER BY timestamp DESC
+         LIMIT ?1 OFFSET ?2"
+    ).map_err(|e| e.to_string())?;
+
+    let iter = stmt.query_map(params![limit, offset, with_bodies], |row| {
+        Ok(MirrorSample {
+            id: row.get(0)?,
+            timestamp: row.get(1)?,
+            protocol: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            client_model: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+            primary_model: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+            primary_account: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+            primary_status: row.get(6)?,
+            primary_latency_ms: row.get(7)?,
+            primary_streamed: row.get::<_, Option<bool>>(8)?.unwrap_or(false),
+            primary_response: row.get(9)?,
+            mirror_model: row.get::<_, Option<String>>(10)?.unwrap_or_default(),
+            mirror_account: row.get(11)?,
+            mirror_status: row.get(12)?,
+            mirror_latency_ms: row.get(13)?,
+            mirror_response: row.get(14)?,
+            mirror_error: row.get(15)?,
+            request_body: row.get(16)?,
+        })
+    }).map_err(|e| e.to_string())?;
+
+    let mut samples = Vec::new();
+    for sample in iter {
+        samples.push(sample.map_err(|e| e.to_string())?);
+    }
+    Ok(samples)
+}
+
+/// Keep only the newest N mirror samples
+pub fn limit_mirror_samples(max_count: usize) -> Result<usize, String> {
+    let conn = connect_db()?;
+    conn.execute(
+        "DELETE FROM mirror_samples WHERE id NOT IN (
+            SELECT id FROM mirror_samples ORDER BY timestamp DESC LIMIT ?1
+        )",
+        [max_count],
+    ).map_err(|e| e.to_string())
+}
+
+pub fn clear_mirror_samples() -> Result<(), String> {
+    let conn = connect_db()?;
+    conn.execute("DELETE FROM mirror_samples", []).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
 /// Limit maximum log count (keep newest N records)
 #[allow(dead_code)]
 pub fn limit_max_logs(max_count: usize) -> Result<usize, String> {
@@ -231,6 +395,169 @@ pub fn clear_logs() -> Result<(), String> {
     Ok(())
 }
 
+/// Structured log query; all filters are optional and combined with AND
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ProxyLogQuery {
+    /// Substring match on url / model / mapped model / error
+    #[serde(default)]
+    pub search: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub account_email: Option<String>,
+    #[serde(default)]
+    pub protocol: Option<String>,
+    #[serde(default)]
+    pub status_min: Option<u16>,
+    #[serde(default)]
+    pub status_max: Option<u16>,
+    /// Start of the time range (ms, inclusive)
+    #[serde(default)]
+    pub since: Option<i64>,
+    /// End of the time range (ms, exclusive)
+    #[serde(default)]
+    pub until: Option<i64>,
+    /// Also return request/response bodies
+    #[serde(default)]
+    pub include_bodies: bool,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: Option<usize>,
+}
+
+/// Query logs with structured filters, newest first
+pub fn query_logs(query: &ProxyLogQuery) -> Result<Vec<ProxyRequestLog>, String> {
+    let conn = connect_db()?;
+
+    let mut conditions: Vec<&str> = Vec::new();
+    let mut values: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+    if let Some(search) = query.search.as_ref().filter(|s| !s.is_empty()) {
+        conditions.push(
+            "(url LIKE ? ESCAPE '\\' OR model LIKE ? ESCAPE '\\' \
+             OR mapped_model LIKE ? ESCAPE '\\' OR error LIKE ? ESCAPE '\\')",
+        );
+        // Match the search text literally: `%` and `_` are LIKE wildcards
+        let escaped = search
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let pattern = format!("%{}%", escaped);
+        for _ in 0..4 {
+            values.push(Box::new(pattern.clone()));
+        }
+    }
+    if let Some(model) = &query.model {
+        conditions.push("(model = ? OR mapped_model = ?)");
+        values.push(Box::new(model.clone()));
+        values.push(Box::new(model.clone()));
+    }
+    if let Some(account_email) = &query.account_email {
+        conditions.push("account_email = ?");
+        values.push(Box::new(account_email.clone()));
+    }
+    if let Some(protocol) = &query.protocol {
+        conditions.push("protocol = ?");
+        values.push(Box::new(protocol.clone()));
+    }
+    if let Some(status_min) = query.status_min {
+        conditions.push("status >= ?");
+        values.push(Box::new(status_min));
+    }
+    if let Some(status_max) = query.status_max {
+        conditions.push("status <= ?");
+        values.push(Box::new(status_max));
+    }
+    if let Some(since) = query.since {
+        conditions.push("timestamp >= ?");
+        values.push(Box::new(since));
+    }
+    if let Some(until) = query.until {
+        conditions.push("timestamp < ?");
+        values.push(Box::new(until));
+    }
+    values.push(Box::new(query.limit.unwrap_or(100) as i64));
+    values.push(Box::new(query.offset.unwrap_or(0) as i64));
+
+    let body_columns = if query.include_bodies {
+        "request_body, response_body"
+    } else {
+        "NULL as request_body, NULL as response_body"
+    };
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    let sql = format!(
+        "SELECT id, timestamp, method, url, status, duration, model, error,
+                {}, input_tokens, output_tokens, account_email, mapped_model, protocol
+         FROM request_logs
+         {}
+         ORDER BY timestamp DESC
+         LIMIT ? OFFSET ?",
+        body_columns, where_clause
+    );
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let logs_iter = stmt.query_map(rusqlite::params_from_iter(values.iter()), |row| {
+        Ok(ProxyRequestLog {
+            id: row.get(0)?,
+            timestamp: row.get(1)?,
+            method: row.get(2)?,
+            url: row.get(3)?,
+            status: row.get(4)?,
+            duration: row.get(5)?,
+            model: row.get(6)?,
+            mapped_model: row.get(13).unwrap_or(None),
+            account_email: row.get(12).unwrap_or(None),
+            error: row.get(7)?,
+            request_body: row.get(8).unwrap_or(None),
+            response_body: row.get(9).unwrap_or(None),
+            input_tokens: row.get(10).unwrap_or(None),
+            output_tokens: row.get(11).unwrap_or(None),
+            cache_read_tokens: None,
+            protocol: row.get(14).unwrap_or(None),
+        })
+    }).map_err(|e| e.to_string())?;
+
+    let mut logs = Vec::new();
+    for log in logs_iter {
+        logs.push(log.map_err(|e| e.to_string())?);
+    }
+    Ok(logs)
+}
+
+/// Delete logs older than the given timestamp (ms), or all logs when None
+pub fn purge_logs(before: Option<i64>) -> Result<usize, String> {
+    let conn = connect_db()?;
+
+    let deleted = match before {
+        Some(before) => conn.execute("DELETE FROM request_logs WHERE timestamp < ?1", [before]),
+        None => conn.execute("DELETE FROM request_logs", []),
+    }
+    .map_err(|e| e.to_string())?;
+
+    vacuum_if_fragmented(&conn)?;
+
+    Ok(deleted)
+}
+
+/// Reclaim disk space only once a quarter of the file is free pages;
+/// VACUUM rewrites the whole database, so running it after every purge is wasteful
+fn vacuum_if_fragmented(conn: &Connection) -> Result<(), String> {
+    let pages: i64 = conn
+        .query_row("PRAGMA page_count", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    let free: i64 = conn
+        .query_row("PRAGMA freelist_count", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if pages > 0 && free * 4 >= pages {
+        conn.execute("VACUUM", []).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
 /// Get total count of logs in database
 pub fn get_logs_count() -> Result<u64, String> {
     let conn = connect_db()?;
@@ -323,6 +650,7 @@ pub fn get_logs_filtered(filter: &str, errors_only: bool, limit: usize, offset:
                 response_body: None,
                 input_tokens: row.get(10).unwrap_or(None),
                 output_tokens: row.get(11).unwrap_or(None),
+                cache_read_tokens: None,
                 protocol: row.get(14).unwrap_or(None),
             })
         }).map_err(|e| e.to_string())?;
@@ -345,6 +673,7 @@ pub fn get_logs_filtered(filter: &str, errors_only: bool, limit: usize, offset:
                 response_body: None,
                 input_tokens: row.get(10).unwrap_or(None),
                 output_tokens: row.get(11).unwrap_or(None),
+                cache_read_tokens: None,
                 protocol: row.get(14).unwrap_or(None),
             })
         }).map_err(|e| e.to_string())?;
@@ -367,6 +696,7 @@ pub fn get_logs_filtered(filter: &str, errors_only: bool, limit: usize, offset:
                 response_body: None,
                 input_tokens: row.get(10).unwrap_or(None),
                 output_tokens: row.get(11).unwrap_or(None),
+                cache_read_tokens: None,
                 protocol: row.get(14).unwrap_or(None),
             })
         }).map_err(|e| e.to_string())?;
@@ -404,6 +734,7 @@ pub fn get_all_logs_for_export() -> Result<Vec<ProxyRequestLog>, String> {
             response_body: row.get(9).unwrap_or(None),
             input_tokens: row.get(10).unwrap_or(None),
             output_tokens: row.get(11).unwrap_or(None),
+            cache_read_tokens: None,
             protocol: row.get(14).unwrap_or(None),
         })
     }).map_err(|e| e.to_string())?;
@@ -457,6 +788,7 @@ pub fn get_logs_by_ids(ids: &[String]) -> Result<Vec<ProxyRequestLog>, String> {
             response_body: row.get(9).unwrap_or(None),
             input_tokens: row.get(10).unwrap_or(None),
             output_tokens: row.get(11).unwrap_or(None),
+            cache_read_tokens: None,
             protocol: row.get(14).unwrap_or(None),
         })
     }).map_err(|e| e.to_string())?;
@@ -3,6 +3,10 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use crate::models::QuotaData;
 use crate::modules::config;
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 const QUOTA_API_URL: &str = "https://cloudcode-pa.googleapis.com/v1internal:fetchAvailableModels";
 const USER_AGENT: &str = "antigravity/1.11.3 Darwin/arm64";
@@ -12,6 +16,21 @@ const NEAR_READY_THRESHOLD: i32 = 95;
 const MAX_RETRIES: u32 = 3;
 const RETRY_DELAY_SECS: u64 = 30;
 
+/// Attempts per quota request; only transient errors are retried
+const FETCH_MAX_ATTEMPTS: u32 = 4;
+const FETCH_RETRY_BASE_MS: u64 = 500;
+/// Scheduler polling pause after repeated failures: 1 min, doubling up to 1 h
+const FAILURE_BACKOFF_BASE_SECS: u64 = 60;
+const FAILURE_BACKOFF_MAX_SECS: u64 = 3600;
+
+/// Last successful quota response per account (keyed by email)
+static QUOTA_CACHE: Lazy<DashMap<String, (Instant, QuotaData, Option<String>)>> =
+    Lazy::new(DashMap::new);
+/// Consecutive failed fetches per account and time of the last failure
+static FETCH_FAILURES: Lazy<DashMap<String, (u32, Instant)>> = Lazy::new(DashMap::new);
+/// Per-account fetch locks so concurrent callers share one upstream request
+static FETCH_LOCKS: Lazy<DashMap<String, Arc<tokio::sync::Mutex<()>>>> = Lazy::new(DashMap::new);
+
 #[derive(Debug, Serialize, Deserialize)]
 struct QuotaResponse {
     models: std::collections::HashMap<String, ModelInfo>,
@@ -53,21 +72,46 @@ struct Tier {
     slug: Option<String>,
 }
 
-/// Get shared HTTP Client (15s timeout)
-fn create_client() -> reqwest::Client {
-    crate::utils::http::get_client()
+/// Get HTTP Client for an account (15s timeout, through the account's upstream proxy)
+fn create_client(email: &str) -> Result<reqwest::Client, String> {
+    crate::utils::http::client_for_account(email)
 }
 
 /// Get shared HTTP Client (60s timeout)
-fn create_warmup_client() -> reqwest::Client {
+fn create_warmup_client() -> Result<reqwest::Client, String> {
     crate::utils::http::get_long_client()
 }
 
+/// Client for calling our own HTTPS proxy over loopback: the proxy may use a self-signed
+/// certificate, so certificate checks are skipped for this local call only.
+/// With mTLS enabled the call presents an internal client certificate from the local CA.
+fn local_tls_client(require_client_cert: bool) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .no_proxy()
+        .timeout(std::time::Duration::from_secs(60));
+    if require_client_cert {
+        let (cert_pem, key_pem) = crate::proxy::tls::internal_client_certificate()?;
+        let identity = reqwest::Identity::from_pkcs8_pem(cert_pem.as_bytes(), key_pem.as_bytes())
+            .map_err(|e| format!("Invalid internal client certificate: {}", e))?;
+        builder = builder.identity(identity);
+    }
+    builder
+        .build()
+        .map_err(|e| format!("Failed to build local TLS client: {}", e))
+}
+
 const CLOUD_CODE_BASE_URL: &str = "https://cloudcode-pa.googleapis.com";
 
 /// Fetch project ID and subscription tier
 async fn fetch_project_id(access_token: &str, email: &str) -> (Option<String>, Option<String>) {
-    let client = create_client();
+    let client = match create_client(email) {
+        Ok(client) => client,
+        Err(e) => {
+            crate::modules::logger::log_warn(&format!("Cannot fetch project id for {}: {}", email, e));
+            return (None, None);
+        }
+    };
     let meta = json!({"metadata": {"ideType": "ANTIGRAVITY"}});
 
     let res = client
@@ -117,11 +161,109 @@ pub async fn fetch_quota(access_token: &str, email: &str) -> crate::error::AppRe
     fetch_quota_with_cache(access_token, email, None).await
 }
 
-/// Fetch quota with cache support
+/// Drop the cached quota response of an account (e.g. after switching its token)
+pub fn invalidate_quota_cache(email: &str) {
+    QUOTA_CACHE.remove(email);
+}
+
+fn cached_quota(email: &str, ttl: Duration) -> Option<(QuotaData, Option<String>)> {
+    let entry = QUOTA_CACHE.get(email)?;
+    let (fetched_at, quota, project_id) = entry.value();
+    (fetched_at.elapsed() < ttl).then(|| (quota.clone(), project_id.clone()))
+}
+
+/// Fetch quota with cache support: responses younger than `quota_cache_ttl_secs` are reused,
+/// and concurrent fetches for the same account wait for the one already in flight
 pub async fn fetch_quota_with_cache(
     access_token: &str,
     email: &str,
     cached_project_id: Option<&str>,
+) -> crate::error::AppResult<(QuotaData, Option<String>)> {
+    let ttl = Duration::from_secs(
+        config::load_app_config()
+            .map(|c| c.quota_cache_ttl_secs)
+            .unwrap_or(0),
+    );
+    if ttl.is_zero() {
+        return fetch_quota_tracked(access_token, email, cached_project_id).await;
+    }
+
+    let lock = FETCH_LOCKS
+        .entry(email.to_string())
+        .or_insert_with(|| Arc::new(tokio::sync::Mutex::new(())))
+        .clone();
+    let _guard = lock.lock().await;
+
+    if let Some(hit) = cached_quota(email, ttl) {
+        tracing::debug!("Quota cache hit for {}", email);
+        return Ok(hit);
+    }
+
+    let result = fetch_quota_tracked(access_token, email, cached_project_id).await;
+    if let Ok((quota, project_id)) = &result {
+        QUOTA_CACHE.insert(
+            email.to_string(),
+            (Instant::now(), quota.clone(), project_id.clone()),
+        );
+    }
+    result
+}
+
+/// Delay before retry `attempt` (1-based): exponential from FETCH_RETRY_BASE_MS plus up to 50% jitter
+fn retry_backoff(attempt: u32) -> Duration {
+    use rand::Rng;
+    let base = FETCH_RETRY_BASE_MS.saturating_mul(1 << attempt.saturating_sub(1).min(6));
+    let jitter = rand::thread_rng().gen_range(0..=base / 2);
+    Duration::from_millis(base + jitter)
+}
+
+/// Polling pause after `failures` consecutive failed fetches (doubling, capped)
+fn failure_backoff(failures: u32) -> Duration {
+    let secs = FAILURE_BACKOFF_BASE_SECS.saturating_mul(1 << failures.saturating_sub(1).min(10));
+    Duration::from_secs(secs.min(FAILURE_BACKOFF_MAX_SECS))
+}
+
+async fn fetch_quota_tracked(
+    access_token: &str,
+    email: &str,
+    cached_project_id: Option<&str>,
+) -> crate::error::AppResult<(QuotaData, Option<String>)> {
+    let result = fetch_quota_uncached(access_token, email, cached_project_id).await;
+    match &result {
+        Ok(_) => {
+            FETCH_FAILURES.remove(email);
+        }
+        Err(_) => {
+            let mut entry = FETCH_FAILURES
+                .entry(email.to_string())
+                .or_insert((0, Instant::now()));
+            entry.0 += 1;
+            entry.1 = Instant::now();
+            if entry.0 > 1 {
+                crate::modules::logger::log_warn(&format!(
+                    "Quota fetch for {} failed {} times in a row, backing off polling for {}s",
+                    email,
+                    entry.0,
+                    failure_backoff(entry.0).as_secs()
+                ));
+            }
+        }
+    }
+    result
+}
+
+/// Remaining polling pause of an account whose recent quota fetches kept failing;
+/// scheduled refreshes skip it until then (manual refreshes are not affected)
+pub fn fetch_backoff_remaining(email: &str) -> Option<Duration> {
+    let entry = FETCH_FAILURES.get(email)?;
+    let (failures, last_failure) = *entry.value();
+    failure_backoff(failures).checked_sub(last_failure.elapsed())
+}
+
+async fn fetch_quota_uncached(
+    access_token: &str,
+    email: &str,
+    cached_project_id: Option<&str>,
 ) -> crate::error::AppResult<(QuotaData, Option<String>)> {
     use crate::error::AppError;
     
@@ -134,13 +276,13 @@ pub async fn fetch_quota_with_cache(
     
     let final_project_id = project_id.as_deref().unwrap_or("bamboo-precept-lgxtn");
     
-    let client = create_client();
+    let client = create_client(email).map_err(AppError::Config)?;
     let payload = json!({
         "project": final_project_id
     });
     
     let url = QUOTA_API_URL;
-    let max_retries = 3;
+    let max_retries = FETCH_MAX_ATTEMPTS;
     let mut last_error: Option<AppError> = None;
 
     for attempt in 1..=max_retries {
@@ -168,15 +310,16 @@ pub async fn fetch_quota_with_cache(
                         return Ok((q, project_id.clone()));
                     }
                     
-                    // Continue retry logic for other errors
-                    if attempt < max_retries {
-                         let text = response.text().await.unwrap_or_default();
+                    // Only transient errors (5xx / 429) are retried, with exponential backoff
+                    let text = response.text().await.unwrap_or_default();
+                    let transient = status.is_server_error()
+                        || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+                    if transient && attempt < max_retries {
                          crate::modules::logger::log_warn(&format!("API Error: {} - {} (Attempt {}/{})", status, text, attempt, max_retries));
                          last_error = Some(AppError::Unknown(format!("HTTP {} - {}", status, text)));
-                         tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                         tokio::time::sleep(retry_backoff(attempt)).await;
                          continue;
                     } else {
-                         let text = response.text().await.unwrap_or_default();
                          return Err(AppError::Unknown(format!("API Error: {} - {}", status, text)));
                     }
                 }
@@ -191,6 +334,13 @@ pub async fn fetch_quota_with_cache(
                 // Use debug level for detailed info to avoid console noise
                 tracing::debug!("Quota API returned {} models", quota_response.models.len());
 
+                // Feed the upstream model list into new-model detection (file and config I/O, off the runtime)
+                let upstream_models: Vec<String> = quota_response.models.keys().cloned().collect();
+                let discovered_by = email.to_string();
+                tokio::task::spawn_blocking(move || {
+                    crate::modules::model_discovery::observe_models(&discovered_by, &upstream_models)
+                });
+
                 for (name, info) in quota_response.models {
                     if let Some(quota_info) = info.quota_info {
                         let percentage = quota_info.remaining_fraction
@@ -215,7 +365,7 @@ pub async fn fetch_quota_with_cache(
                 crate::modules::logger::log_warn(&format!("Request failed: {} (Attempt {}/{})", e, attempt, max_retries));
                 last_error = Some(AppError::Network(e));
                 if attempt < max_retries {
-                    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                    tokio::time::sleep(retry_backoff(attempt)).await;
                 }
             }
         }
@@ -276,12 +426,12 @@ pub async fn warmup_model_directly(
     email: &str,
     percentage: i32,
 ) -> bool {
-    // Get currently configured proxy port
-    let port = config::load_app_config()
-        .map(|c| c.proxy.port)
-        .unwrap_or(8045);
+    // Get currently configured proxy address
+    let proxy_config = config::load_app_config()
+        .map(|c| c.proxy)
+        .unwrap_or_default();
 
-    let warmup_url = format!("http://127.0.0.1:{}/internal/warmup", port);
+    let warmup_url = format!("{}/internal/warmup", proxy_config.local_base_url());
     let body = json!({
         "email": email,
         "model": model_name,
@@ -289,7 +439,26 @@ pub async fn warmup_model_directly(
         "project_id": project_id
     });
 
-    let client = create_warmup_client();
+    let client = if proxy_config.tls.enabled {
+        match local_tls_client(proxy_config.tls.require_client_cert) {
+            Ok(client) => client,
+            Err(e) => {
+                crate::modules::logger::log_warn(&format!(
+                    "[Warmup] Cannot call the local HTTPS proxy: {}",
+                    e
+                ));
+                return false;
+            }
+        }
+    } else {
+        match create_warmup_client() {
+            Ok(client) => client,
+            Err(e) => {
+                crate::modules::logger::log_warn(&format!("[Warmup] Cannot create HTTP client: {}", e));
+                return false;
+            }
+        }
+    };
     let resp = client
         .post(&warmup_url)
         .header("Content-Type", "application/json")
@@ -302,6 +471,8 @@ pub async fn warmup_model_directly(
             let status = response.status();
             if status.is_success() {
                 crate::modules::logger::log_info(&format!("[Warmup] ✓ Triggered {} for {} (was {}%)", model_name, email, percentage));
+                // The cached response no longer reflects the quota after a warmup
+                invalidate_quota_cache(email);
                 true
             } else {
                 let text = response.text().await.unwrap_or_default();
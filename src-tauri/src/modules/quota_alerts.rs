@@ -0,0 +1,294 @@
+//! 配额阈值告警
+//!
+//! 每次保存账号配额时比较刷新前后的剩余百分比，当受监控模型跌破
+//! (全局或账号级) 告警阈值时推送 "quota://alert" 事件、弹出桌面通知并在托盘上显示告警数；
+//! 同一次跌破只告警一次，额度恢复到阈值以上后解除。
+//! 未解除的告警保存在数据目录的 quota_alerts.json 中，重启后不会重复告警，托盘告警数也会恢复。
+
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::Emitter;
+
+use crate::models::{QuotaData, QuotaProtectionConfig};
+use crate::modules::{account, logger};
+
+const ALERTS_FILE: &str = "quota_alerts.json";
+
+/// Windows 只为已注册 AppUserModelID 的应用显示 toast；借用系统自带 PowerShell 的 ID，
+/// 开发构建和便携版同样可用
+#[cfg(target_os = "windows")]
+const POWERSHELL_APP_ID: &str =
+    "{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\\WindowsPowerShell\\v1.0\\powershell.exe";
+
+static APP_HANDLE: OnceCell<tauri::AppHandle> = OnceCell::new();
+
+type AlertKey = (String, String);
+
+/// 当前处于告警状态的 (账号, 模型)
+static ACTIVE: Lazy<Mutex<BTreeMap<AlertKey, QuotaAlert>>> =
+    Lazy::new(|| Mutex::new(load_alerts()));
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaAlert {
+    pub account_id: String,
+    pub email: String,
+    pub model: String,
+    pub percentage: i32,
+    pub threshold: u32,
+    pub timestamp: i64,
+}
+
+fn get_alerts_path() -> Result<PathBuf, String> {
+    Ok(account::get_data_dir()?.join(ALERTS_FILE))
+}
+
+fn load_alerts() -> BTreeMap<AlertKey, QuotaAlert> {
+    let alerts: Vec<QuotaAlert> = match get_alerts_path() {
+        Ok(path) if path.exists() => std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    alerts
+        .into_iter()
+        .map(|a| ((a.account_id.clone(), a.model.clone()), a))
+        .collect()
+}
+
+fn save_alerts(active: &BTreeMap<AlertKey, QuotaAlert>) -> Result<(), String> {
+    let path = get_alerts_path()?;
+    let alerts: Vec<&QuotaAlert> = active.values().collect();
+    let content =
+        serde_json::to_string_pretty(&alerts).map_err(|e| format!("序列化配额告警失败: {}", e))?;
+    // 先写临时文件再重命名，避免写入中断导致告警状态丢失
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, content).map_err(|e| format!("保存配额告警失败: {}", e))?;
+    std::fs::rename(&tmp_path, &path).map_err(|e| format!("保存配额告警失败: {}", e))
+}
+
+/// 保存 AppHandle，用于推送事件与更新托盘；恢复上次未解除的告警 (丢弃已删除账号的告警)
+pub fn init(app: tauri::AppHandle) {
+    let count = match (ACTIVE.lock(), account::load_account_index()) {
+        (Ok(mut active), Ok(index)) => {
+            let before = active.len();
+            active.retain(|(account_id, _), _| index.accounts.iter().any(|a| &a.id == account_id));
+            if active.len() != before {
+                if let Err(e) = save_alerts(&active) {
+                    logger::log_warn(&e);
+                }
+            }
+            active.len()
+        }
+        (Ok(active), Err(_)) => active.len(),
+        (Err(_), _) => 0,
+    };
+    if count > 0 {
+        crate::modules::tray::set_alert_badge(&app, count);
+    }
+    let _ = APP_HANDLE.set(app);
+}
+
+/// 当前所有未解除的告警
+pub fn active_alerts() -> Vec<QuotaAlert> {
+    ACTIVE
+        .lock()
+        .map(|active| active.values().cloned().collect())
+        .unwrap_or_default()
+}
+
+fn is_monitored(model: &str, monitored: &[String]) -> bool {
+    if monitored.iter().any(|m| m.eq_ignore_ascii_case(model)) {
+        return true;
+    }
+    crate::proxy::common::model_mapping::normalize_to_standard_id(model)
+        .map_or(false, |id| monitored.contains(&id))
+}
+
+/// 找出本次刷新中跌破阈值的模型；没有上次记录的模型视为从阈值以上跌落
+fn detect_crossings(
+    previous: Option<&QuotaData>,
+    current: &QuotaData,
+    threshold: u32,
+    monitored: &[String],
+) -> (Vec<(String, i32)>, Vec<String>) {
+    let threshold = threshold as i32;
+    let mut crossed = Vec::new();
+    let mut recovered = Vec::new();
+    for model in &current.models {
+        if !is_monitored(&model.name, monitored) {
+            continue;
+        }
+        if model.percentage > threshold {
+            recovered.push(model.name.clone());
+            continue;
+        }
+        let was_above = previous
+            .and_then(|p| p.models.iter().find(|m| m.name == model.name))
+            .map_or(true, |m| m.percentage > threshold);
+        if was_above {
+            crossed.push((model.name.clone(), model.percentage));
+        }
+    }
+    (crossed, recovered)
+}
+
+/// 在账号配额更新时调用
+pub fn check(
+    account_id: &str,
+    email: &str,
+    previous: Option<&QuotaData>,
+    current: &QuotaData,
+    config: &QuotaProtectionConfig,
+) {
+    if !config.alert_enabled || current.is_forbidden {
+        return;
+    }
+    let threshold = config.alert_threshold_for(account_id);
+    let (crossed, recovered) =
+        detect_crossings(previous, current, threshold, &config.monitored_models);
+
+    let mut fired = Vec::new();
+    let count = {
+        let mut active = match ACTIVE.lock() {
+            Ok(active) => active,
+            Err(_) => return,
+        };
+        let before = active.len();
+        for model in &recovered {
+            active.remove(&(account_id.to_string(), model.clone()));
+        }
+        let mut changed = active.len() != before;
+        for (model, percentage) in crossed {
+            let key = (account_id.to_string(), model.clone());
+            if active.contains_key(&key) {
+                continue;
+            }
+            let alert = QuotaAlert {
+                account_id: account_id.to_string(),
+                email: email.to_string(),
+                model,
+                percentage,
+                threshold,
+                timestamp: chrono::Utc::now().timestamp(),
+            };
+            active.insert(key, alert.clone());
+            fired.push(alert);
+            changed = true;
+        }
+        if !changed {
+            return;
+        }
+        if let Err(e) = save_alerts(&active) {
+            logger::log_warn(&e);
+        }
+        active.len()
+    };
+
+    for alert in &fired {
+        logger::log_warn(&format!(
+            "[Quota] Alert: {} {} remaining {}% <= {}%",
+            alert.email, alert.model, alert.percentage, alert.threshold
+        ));
+        if config.alert_notification {
+            notify_desktop(
+                "Antigravity Tools",
+                &format!(
+                    "{}: {} 剩余 {}% (阈值 {}%)",
+                    alert.email, alert.model, alert.percentage, alert.threshold
+                ),
+            );
+        }
+    }
+
+    if let Some(app) = APP_HANDLE.get() {
+        for alert in &fired {
+            let _ = app.emit("quota://alert", alert);
+        }
+        crate::modules::tray::set_alert_badge(app, count);
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn escape_applescript(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// 尽力弹出系统通知，失败只记录日志
+pub(crate) fn notify_desktop(title: &str, body: &str) {
+    #[cfg(target_os = "macos")]
+    let result = std::process::Command::new("osascript")
+        .arg("-e")
+        .arg(format!(
+            "display notification \"{}\" with title \"{}\"",
+            escape_applescript(body),
+            escape_applescript(title)
+        ))
+        .spawn();
+
+    #[cfg(target_os = "windows")]
+    let result = {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        let quote = |s: &str| s.replace('\'', "''");
+        let script = format!(
+            "[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] > $null; \
+             $t = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+             $x = $t.GetElementsByTagName('text'); \
+             $x.Item(0).AppendChild($t.CreateTextNode('{}')) > $null; \
+             $x.Item(1).AppendChild($t.CreateTextNode('{}')) > $null; \
+             [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier('{}').Show([Windows.UI.Notifications.ToastNotification]::new($t))",
+            quote(title),
+            quote(body),
+            POWERSHELL_APP_ID
+        );
+        std::process::Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+            .creation_flags(CREATE_NO_WINDOW)
+            .spawn()
+    };
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let result = std::process::Command::new("notify-send")
+        .args(["--app-name", title, title, body])
+        .spawn();
+
+    if let Err(e) = result {
+        logger::log_warn(&format!("Failed to show desktop notification: {}", e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quota(models: &[(&str, i32)]) -> QuotaData {
+        let mut q = QuotaData::new();
+        for (name, pct) in models {
+            q.add_model(name.to_string(), *pct, String::new());
+        }
+        q
+    }
+
+    #[test]
+    fn crossing_fires_once_and_recovers() {
+        let monitored = vec!["gemini-3-flash".to_string()];
+        let prev = quota(&[("gemini-3-flash", 40), ("other", 50)]);
+        let curr = quota(&[("gemini-3-flash", 8), ("other", 1)]);
+
+        let (crossed, _) = detect_crossings(Some(&prev), &curr, 10, &monitored);
+        assert_eq!(crossed, vec![("gemini-3-flash".to_string(), 8)]);
+
+        // 已在阈值以下，不重复告警
+        let next = quota(&[("gemini-3-flash", 5)]);
+        let (crossed, _) = detect_crossings(Some(&curr), &next, 10, &monitored);
+        assert!(crossed.is_empty());
+
+        let back = quota(&[("gemini-3-flash", 80)]);
+        let (_, recovered) = detect_crossings(Some(&next), &back, 10, &monitored);
+        assert_eq!(recovered, vec!["gemini-3-flash".to_string()]);
+    }
+}
@@ -0,0 +1,571 @@
+//! Quota history.
+//!
+//! Every quota snapshot saved for an account is appended to a time-series table (one row
+//! per account and model), so consumption can be charted over days. Queries cover a time
+//! range and can be downsampled into fixed buckets. The recent samples also drive a
+//! linear burn-rate forecast of when each account runs out, and everything can be
+//! exported as CSV or JSON for reconciliation in spreadsheets.
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::models::QuotaData;
+
+/// Samples older than this are dropped
+const RETENTION_DAYS: i64 = 90;
+/// Default upper bound of points per series when no bucket size is given
+const DEFAULT_MAX_POINTS: i64 = 500;
+/// How far back samples are considered for the burn rate
+const FORECAST_WINDOW_SECS: i64 = 6 * 3600;
+/// Minimum time span of samples before a burn rate is trusted
+const FORECAST_MIN_SPAN_SECS: i64 = 10 * 60;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct QuotaHistoryQuery {
+    #[serde(default)]
+    pub account_id: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Unix timestamp (seconds), default 7 days ago
+    #[serde(default)]
+    pub from: Option<i64>,
+    /// Unix timestamp (seconds), default now
+    #[serde(default)]
+    pub to: Option<i64>,
+    /// Fixed bucket size in seconds; derived from `max_points` when absent
+    #[serde(default)]
+    pub bucket_secs: Option<i64>,
+    #[serde(default)]
+    pub max_points: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuotaPoint {
+    /// Bucket start (Unix seconds)
+    pub timestamp: i64,
+    /// Average remaining percentage in the bucket
+    pub percentage: f64,
+    pub min: i32,
+    pub max: i32,
+    pub samples: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaSeries {
+    pub account_id: String,
+    pub model: String,
+    pub points: Vec<QuotaPoint>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaHistory {
+    pub from: i64,
+    pub to: i64,
+    pub bucket_secs: i64,
+    pub series: Vec<QuotaSeries>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuotaExportFormat {
+    Csv,
+    Json,
+}
+
+/// One exported quota row: the current snapshot of a model or a history bucket
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaExportRow {
+    /// "current" or "history"
+    pub kind: &'static str,
+    pub account_id: String,
+    pub email: String,
+    pub model: String,
+    /// Unix seconds (snapshot time or bucket start)
+    pub timestamp: i64,
+    pub percentage: f64,
+    pub min: i32,
+    pub max: i32,
+    pub samples: i64,
+    pub reset_time: String,
+}
+
+/// Estimated depletion of one model of an account
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuotaForecast {
+    pub account_id: String,
+    pub model: String,
+    /// Latest remaining percentage
+    pub percentage: i32,
+    /// Percentage points consumed per hour (0 when idle or not enough data)
+    pub burn_rate_per_hour: f64,
+    /// Estimated Unix time the quota hits 0; None when it is not draining or resets first
+    pub depletes_at: Option<i64>,
+    pub samples: usize,
+}
+
+fn get_db_path() -> Result<PathBuf, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    Ok(data_dir.join("quota_history.db"))
+}
+
+fn connect_db() -> Result<Connection, String> {
+    let conn = Connection::open(get_db_path()?).map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "busy_timeout", 5000)
+        .map_err(|e| e.to_string())?;
+    Ok(conn)
+}
+
+fn create_schema(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS quota_samples (
+            timestamp INTEGER NOT NULL,
+            account_id TEXT NOT NULL,
+            model TEXT NOT NULL,
+            percentage INTEGER NOT NULL,
+            reset_time TEXT
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_quota_samples_series
+         ON quota_samples (account_id, model, timestamp)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_quota_samples_timestamp ON quota_samples (timestamp)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Initialize the history database and drop expired samples
+pub fn init_db() -> Result<(), String> {
+    let conn = connect_db()?;
+    create_schema(&conn)?;
+    let cutoff = chrono::Utc::now().timestamp() - RETENTION_DAYS * 24 * 3600;
+    conn.execute(
+        "DELETE FROM quota_samples WHERE timestamp < ?1",
+        params![cutoff],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn insert_snapshot(
+    conn: &mut Connection,
+    account_id: &str,
+    quota: &QuotaData,
+) -> Result<(), String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for model in &quota.models {
+        tx.execute(
+            "INSERT INTO quota_samples (timestamp, account_id, model, percentage, reset_time)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                quota.last_updated,
+                account_id,
+                model.name,
+                model.percentage,
+                model.reset_time
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// Append a quota snapshot (forbidden or empty snapshots carry no data and are skipped)
+pub fn record_snapshot(account_id: &str, quota: &QuotaData) -> Result<(), String> {
+    if quota.is_forbidden || quota.models.is_empty() {
+        return Ok(());
+    }
+    let mut conn = connect_db()?;
+    insert_snapshot(&mut conn, account_id, quota)
+}
+
+fn query_series(conn: &Connection, query: &QuotaHistoryQuery) -> Result<QuotaHistory, String> {
+    let to = query.to.unwrap_or_else(|| chrono::Utc::now().timestamp());
+    let from = query.from.unwrap_or(to - 7 * 24 * 3600);
+    if from > to {
+        return Err("invalid_time_range".to_string());
+    }
+    let bucket_secs = match query.bucket_secs {
+        Some(secs) if secs > 0 => secs,
+        _ => {
+            let max_points = query
+                .max_points
+                .filter(|n| *n > 0)
+                .unwrap_or(DEFAULT_MAX_POINTS);
+            ((to - from) / max_points).max(1)
+        }
+    };
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT account_id, model, (timestamp / ?1) * ?1 AS bucket,
+                    AVG(percentage), MIN(percentage), MAX(percentage), COUNT(*)
+             FROM quota_samples
+             WHERE timestamp >= ?2 AND timestamp <= ?3
+               AND (?4 IS NULL OR account_id = ?4)
+               AND (?5 IS NULL OR model = ?5)
+             GROUP BY account_id, model, bucket
+             ORDER BY account_id, model, bucket",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(
+            params![bucket_secs, from, to, query.account_id, query.model],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    QuotaPoint {
+                        timestamp: row.get(2)?,
+                        percentage: row.get(3)?,
+                        min: row.get(4)?,
+                        max: row.get(5)?,
+                        samples: row.get(6)?,
+                    },
+                ))
+            },
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut series: Vec<QuotaSeries> = Vec::new();
+    for row in rows {
+        let (account_id, model, point) = row.map_err(|e| e.to_string())?;
+        match series.last_mut() {
+            Some(last) if last.account_id == account_id && last.model == model => {
+                last.points.push(point)
+            }
+            _ => series.push(QuotaSeries {
+                account_id,
+                model,
+                points: vec![point],
+            }),
+        }
+    }
+
+    Ok(QuotaHistory {
+        from,
+        to,
+        bucket_secs,
+        series,
+    })
+}
+
+/// Quota history per account and model over a time range
+pub fn query_history(query: &QuotaHistoryQuery) -> Result<QuotaHistory, String> {
+    let conn = connect_db()?;
+    query_series(&conn, query)
+}
+
+/// Fit a linear burn rate over the samples since the last reset (any increase)
+fn forecast_series(
+    account_id: &str,
+    model: &str,
+    samples: &[(i64, i32, Option<String>)],
+) -> Option<QuotaForecast> {
+    let start = samples
+        .windows(2)
+        .rposition(|w| w[1].1 > w[0].1)
+        .map_or(0, |i| i + 1);
+    let tail = &samples[start..];
+    let (last_ts, last_pct, reset_time) = tail.last()?.clone();
+
+    let mut forecast = QuotaForecast {
+        account_id: account_id.to_string(),
+        model: model.to_string(),
+        percentage: last_pct,
+        burn_rate_per_hour: 0.0,
+        depletes_at: None,
+        samples: tail.len(),
+    };
+    if tail.len() < 2 || last_ts - tail[0].0 < FORECAST_MIN_SPAN_SECS {
+        return Some(forecast);
+    }
+
+    // Least-squares slope in percentage points per second
+    let n = tail.len() as f64;
+    let mean_t = tail.iter().map(|s| (s.0 - tail[0].0) as f64).sum::<f64>() / n;
+    let mean_p = tail.iter().map(|s| s.1 as f64).sum::<f64>() / n;
+    let (mut cov, mut var) = (0.0, 0.0);
+    for (ts, pct, _) in tail {
+        let dt = (ts - tail[0].0) as f64 - mean_t;
+        cov += dt * (*pct as f64 - mean_p);
+        var += dt * dt;
+    }
+    let slope = if var > 0.0 { cov / var } else { 0.0 };
+    if slope >= 0.0 {
+        return Some(forecast);
+    }
+
+    forecast.burn_rate_per_hour = -slope * 3600.0;
+    let depletes_at = last_ts + (last_pct as f64 / -slope).round() as i64;
+    let resets_at = reset_time
+        .as_deref()
+        .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.timestamp());
+    if resets_at.map_or(true, |reset| reset > depletes_at) {
+        forecast.depletes_at = Some(depletes_at);
+    }
+    Some(forecast)
+}
+
+fn query_forecasts(
+    conn: &Connection,
+    account_id: Option<&str>,
+    now: i64,
+) -> Result<Vec<QuotaForecast>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT account_id, model, timestamp, percentage, reset_time
+             FROM quota_samples
+             WHERE timestamp >= ?1 AND (?2 IS NULL OR account_id = ?2)
+             ORDER BY account_id, model, timestamp",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![now - FORECAST_WINDOW_SECS, account_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                (
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, i32>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                ),
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut series: Vec<(String, String, Vec<(i64, i32, Option<String>)>)> = Vec::new();
+    for row in rows {
+        let (account_id, model, sample) = row.map_err(|e| e.to_string())?;
+        match series.last_mut() {
+            Some(last) if last.0 == account_id && last.1 == model => last.2.push(sample),
+            _ => series.push((account_id, model, vec![sample])),
+        }
+    }
+    Ok(series
+        .iter()
+        .filter_map(|(account_id, model, samples)| forecast_series(account_id, model, samples))
+        .collect())
+}
+
+/// Depletion forecast per account and model from the recent samples
+pub fn forecast(account_id: Option<&str>) -> Result<Vec<QuotaForecast>, String> {
+    let conn = connect_db()?;
+    query_forecasts(&conn, account_id, chrono::Utc::now().timestamp())
+}
+
+/// Earliest estimated depletion time across the models of an account
+pub fn account_depletes_at(account_id: &str) -> Result<Option<i64>, String> {
+    Ok(forecast(Some(account_id))?
+        .into_iter()
+        .filter_map(|f| f.depletes_at)
+        .min())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn rows_to_csv(rows: &[QuotaExportRow]) -> String {
+    let mut out = String::from(
+        "kind,account_id,email,model,timestamp,time,percentage,min,max,samples,reset_time\n",
+    );
+    for row in rows {
+        let time = chrono::DateTime::from_timestamp(row.timestamp, 0)
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_default();
+        let fields = [
+            row.kind.to_string(),
+            csv_field(&row.account_id),
+            csv_field(&row.email),
+            csv_field(&row.model),
+            row.timestamp.to_string(),
+            time,
+            format!("{:.1}", row.percentage),
+            row.min.to_string(),
+            row.max.to_string(),
+            row.samples.to_string(),
+            csv_field(&row.reset_time),
+        ];
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// Export the current quota of every account plus its history over the queried period
+/// as CSV or JSON, returns the number of rows written
+pub fn export_quota(
+    file_path: &str,
+    format: QuotaExportFormat,
+    query: &QuotaHistoryQuery,
+) -> Result<usize, String> {
+    let accounts = crate::modules::account::list_accounts()?;
+    let emails: std::collections::HashMap<String, String> = accounts
+        .iter()
+        .map(|a| (a.id.clone(), a.email.clone()))
+        .collect();
+
+    let mut rows = Vec::new();
+    for account in &accounts {
+        if query.account_id.as_ref().map_or(false, |id| *id != account.id) {
+            continue;
+        }
+        let Some(quota) = &account.quota else {
+            continue;
+        };
+        for model in &quota.models {
+            if query.model.as_ref().map_or(false, |m| *m != model.name) {
+                continue;
+            }
+            rows.push(QuotaExportRow {
+                kind: "current",
+                account_id: account.id.clone(),
+                email: account.email.clone(),
+                model: model.name.clone(),
+                timestamp: quota.last_updated,
+                percentage: model.percentage as f64,
+                min: model.percentage,
+                max: model.percentage,
+                samples: 1,
+                reset_time: model.reset_time.clone(),
+            });
+        }
+    }
+
+    let history = query_history(query)?;
+    for series in history.series {
+        let email = emails.get(&series.account_id).cloned().unwrap_or_default();
+        for point in series.points {
+            rows.push(QuotaExportRow {
+                kind: "history",
+                account_id: series.account_id.clone(),
+                email: email.clone(),
+                model: series.model.clone(),
+                timestamp: point.timestamp,
+                percentage: point.percentage,
+                min: point.min,
+                max: point.max,
+                samples: point.samples,
+                reset_time: String::new(),
+            });
+        }
+    }
+
+    let content = match format {
+        QuotaExportFormat::Csv => rows_to_csv(&rows),
+        QuotaExportFormat::Json => serde_json::to_string_pretty(&serde_json::json!({
+            "generated_at": chrono::Utc::now().timestamp(),
+            "from": history.from,
+            "to": history.to,
+            "bucket_secs": history.bucket_secs,
+            "rows": rows,
+        }))
+        .map_err(|e| format!("Failed to serialize quota export: {}", e))?,
+    };
+    std::fs::write(file_path, content).map_err(|e| format!("Failed to write file: {}", e))?;
+    Ok(rows.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(timestamp: i64, percentages: &[(&str, i32)]) -> QuotaData {
+        let mut quota = QuotaData::new();
+        quota.last_updated = timestamp;
+        for (name, percentage) in percentages {
+            quota.add_model(name.to_string(), *percentage, String::new());
+        }
+        quota
+    }
+
+    #[test]
+    fn test_query_downsampling() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        create_schema(&conn).unwrap();
+        insert_snapshot(&mut conn, "a", &snapshot(0, &[("pro", 100), ("flash", 90)])).unwrap();
+        insert_snapshot(&mut conn, "a", &snapshot(30, &[("pro", 80)])).unwrap();
+        insert_snapshot(&mut conn, "a", &snapshot(60, &[("pro", 50)])).unwrap();
+
+        let history = query_series(
+            &conn,
+            &QuotaHistoryQuery {
+                model: Some("pro".to_string()),
+                from: Some(0),
+                to: Some(100),
+                bucket_secs: Some(60),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(history.series.len(), 1);
+        let points = &history.series[0].points;
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].timestamp, 0);
+        assert_eq!(points[0].percentage, 90.0);
+        assert_eq!(
+            (points[0].min, points[0].max, points[0].samples),
+            (80, 100, 2)
+        );
+        assert_eq!(points[1].timestamp, 60);
+        assert_eq!(points[1].percentage, 50.0);
+    }
+
+    #[test]
+    fn test_forecast_burn_rate() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        create_schema(&conn).unwrap();
+        // Earlier cycle, then a reset to 100 and a steady 10 points per hour
+        insert_snapshot(&mut conn, "a", &snapshot(0, &[("pro", 5)])).unwrap();
+        for (i, pct) in [100, 90, 80, 70].iter().enumerate() {
+            insert_snapshot(&mut conn, "a", &snapshot(3600 + i as i64 * 3600, &[("pro", *pct)]))
+                .unwrap();
+        }
+
+        let forecasts = query_forecasts(&conn, Some("a"), 4 * 3600).unwrap();
+        assert_eq!(forecasts.len(), 1);
+        let f = &forecasts[0];
+        assert_eq!((f.percentage, f.samples), (70, 4));
+        assert!((f.burn_rate_per_hour - 10.0).abs() < 1e-6);
+        assert_eq!(f.depletes_at, Some(4 * 3600 + 7 * 3600));
+    }
+
+    #[test]
+    fn test_csv_escaping() {
+        let row = QuotaExportRow {
+            kind: "history",
+            account_id: "a".to_string(),
+            email: "x,\"y\"@example.com".to_string(),
+            model: "pro".to_string(),
+            timestamp: 0,
+            percentage: 42.0,
+            min: 40,
+            max: 44,
+            samples: 3,
+            reset_time: String::new(),
+        };
+        let csv = rows_to_csv(&[row]);
+        let line = csv.lines().nth(1).unwrap();
+        assert_eq!(
+            line,
+            "history,a,\"x,\"\"y\"\"@example.com\",pro,0,1970-01-01T00:00:00+00:00,42.0,40,44,3,"
+        );
+    }
+}
@@ -0,0 +1,372 @@
+//! Quota incident journal.
+//!
+//! Each quota refresh is compared with the previous snapshot of the account. Sudden drops,
+//! resets that arrive before the announced reset time and resets that never arrive are
+//! recorded as incidents, which users can annotate and export to correlate upstream changes.
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::models::QuotaData;
+
+/// Minimum drop (percentage points) between two close refreshes to count as sudden
+const SUDDEN_DROP_POINTS: i32 = 40;
+/// Refreshes further apart than this are not compared for sudden drops
+const SUDDEN_DROP_WINDOW_SECS: i64 = 3600;
+/// Minimum increase (percentage points) to count as a reset
+const RESET_POINTS: i32 = 30;
+/// Tolerance around the announced reset time
+const RESET_GRACE_SECS: i64 = 15 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IncidentKind {
+    /// Remaining quota fell sharply between two close refreshes
+    SuddenDrop,
+    /// Quota was restored well before the announced reset time
+    EarlyReset,
+    /// The announced reset time passed but quota was not restored
+    MissedReset,
+}
+
+impl IncidentKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            IncidentKind::SuddenDrop => "sudden_drop",
+            IncidentKind::EarlyReset => "early_reset",
+            IncidentKind::MissedReset => "missed_reset",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        match value {
+            "sudden_drop" => Some(IncidentKind::SuddenDrop),
+            "early_reset" => Some(IncidentKind::EarlyReset),
+            "missed_reset" => Some(IncidentKind::MissedReset),
+            _ => None,
+        }
+    }
+}
+
+/// A journal entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaIncident {
+    pub id: i64,
+    pub timestamp: i64,
+    pub account_id: String,
+    pub email: String,
+    pub model: String,
+    pub kind: IncidentKind,
+    pub previous_percentage: i32,
+    pub current_percentage: i32,
+    /// Reset time announced by the previous snapshot (RFC 3339)
+    pub expected_reset: Option<String>,
+    pub annotation: Option<String>,
+    pub annotated_at: Option<i64>,
+}
+
+fn get_db_path() -> Result<PathBuf, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    Ok(data_dir.join("quota_journal.db"))
+}
+
+fn connect_db() -> Result<Connection, String> {
+    let conn = Connection::open(get_db_path()?).map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "busy_timeout", 5000)
+        .map_err(|e| e.to_string())?;
+    Ok(conn)
+}
+
+/// Initialize the journal database
+pub fn init_db() -> Result<(), String> {
+    let conn = connect_db()?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS quota_incidents (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp INTEGER NOT NULL,
+            account_id TEXT NOT NULL,
+            email TEXT NOT NULL,
+            model TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            previous_percentage INTEGER NOT NULL,
+            current_percentage INTEGER NOT NULL,
+            expected_reset TEXT,
+            annotation TEXT,
+            annotated_at INTEGER
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_incident_timestamp ON quota_incidents (timestamp DESC)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn parse_reset_time(value: &str) -> Option<i64> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|t| t.timestamp())
+}
+
+/// Compare two snapshots of the same account and return (model, kind, previous, current, expected_reset)
+fn detect(
+    previous: &QuotaData,
+    current: &QuotaData,
+    now: i64,
+) -> Vec<(String, IncidentKind, i32, i32, Option<String>)> {
+    let mut incidents = Vec::new();
+    if previous.is_forbidden || current.is_forbidden {
+        return incidents;
+    }
+    let elapsed = current.last_updated - previous.last_updated;
+    // Same observation (cached response) or out-of-order snapshots: nothing to compare
+    if elapsed <= 0 {
+        return incidents;
+    }
+
+    for model in &current.models {
+        let Some(prev) = previous.models.iter().find(|m| m.name == model.name) else {
+            continue;
+        };
+        let expected_reset = Some(prev.reset_time.clone()).filter(|s| !s.is_empty());
+        let reset_at = parse_reset_time(&prev.reset_time);
+        let delta = model.percentage - prev.percentage;
+
+        let kind = if -delta >= SUDDEN_DROP_POINTS && elapsed <= SUDDEN_DROP_WINDOW_SECS {
+            Some(IncidentKind::SuddenDrop)
+        } else if delta >= RESET_POINTS && reset_at.is_some_and(|t| now < t - RESET_GRACE_SECS) {
+            Some(IncidentKind::EarlyReset)
+        } else if delta <= 0
+            && prev.percentage < 100
+            && reset_at.is_some_and(|t| {
+                // Only the first refresh past the deadline records it, not every later one
+                previous.last_updated <= t + RESET_GRACE_SECS && now > t + RESET_GRACE_SECS
+            })
+        {
+            Some(IncidentKind::MissedReset)
+        } else {
+            None
+        };
+
+        if let Some(kind) = kind {
+            incidents.push((
+                model.name.clone(),
+                kind,
+                prev.percentage,
+                model.percentage,
+                expected_reset,
+            ));
+        }
+    }
+    incidents
+}
+
+/// Record incidents found between the previous and the new quota snapshot of an account
+pub fn record_quota_change(
+    account_id: &str,
+    email: &str,
+    previous: &QuotaData,
+    current: &QuotaData,
+) -> Result<Vec<QuotaIncident>, String> {
+    let now = Utc::now().timestamp();
+    let found = detect(previous, current, now);
+    if found.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let conn = connect_db()?;
+    let mut recorded = Vec::new();
+    for (model, kind, previous_percentage, current_percentage, expected_reset) in found {
+        conn.execute(
+            "INSERT INTO quota_incidents
+                (timestamp, account_id, email, model, kind, previous_percentage, current_percentage, expected_reset)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                now,
+                account_id,
+                email,
+                model,
+                kind.as_str(),
+                previous_percentage,
+                current_percentage,
+                expected_reset
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+
+        crate::modules::logger::log_warn(&format!(
+            "[QuotaJournal] {} {} {}: {}% -> {}%",
+            kind.as_str(),
+            email,
+            model,
+            previous_percentage,
+            current_percentage
+        ));
+        recorded.push(QuotaIncident {
+            id: conn.last_insert_rowid(),
+            timestamp: now,
+            account_id: account_id.to_string(),
+            email: email.to_string(),
+            model,
+            kind,
+            previous_percentage,
+            current_percentage,
+            expected_reset,
+            annotation: None,
+            annotated_at: None,
+        });
+    }
+    Ok(recorded)
+}
+
+/// List journal entries, newest first
+pub fn list_incidents(
+    limit: usize,
+    offset: usize,
+    account_id: Option<&str>,
+) -> Result<Vec<QuotaIncident>, String> {
+    let conn = connect_db()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, timestamp, account_id, email, model, kind, previous_percentage,
+                    current_percentage, expected_reset, annotation, annotated_at
+             FROM quota_incidents
+             WHERE (?1 IS NULL OR account_id = ?1)
+             ORDER BY timestamp DESC, id DESC
+             LIMIT ?2 OFFSET ?3",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map(params![account_id, limit as i64, offset as i64], |row| {
+            let kind: String = row.get(5)?;
+            Ok(QuotaIncident {
+                id: row.get(0)?,
+                timestamp: row.get(1)?,
+                account_id: row.get(2)?,
+                email: row.get(3)?,
+                model: row.get(4)?,
+                kind: IncidentKind::from_str(&kind).unwrap_or(IncidentKind::SuddenDrop),
+                previous_percentage: row.get(6)?,
+                current_percentage: row.get(7)?,
+                expected_reset: row.get(8)?,
+                annotation: row.get(9)?,
+                annotated_at: row.get(10)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Set or clear (empty note) the annotation of an entry
+pub fn annotate_incident(id: i64, note: &str) -> Result<(), String> {
+    let conn = connect_db()?;
+    let note = note.trim();
+    let (annotation, annotated_at) = if note.is_empty() {
+        (None, None)
+    } else {
+        (Some(note), Some(Utc::now().timestamp()))
+    };
+    let updated = conn
+        .execute(
+            "UPDATE quota_incidents SET annotation = ?1, annotated_at = ?2 WHERE id = ?3",
+            params![annotation, annotated_at, id],
+        )
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err(format!("Incident not found: {}", id));
+    }
+    Ok(())
+}
+
+/// Write the whole journal to a JSON file, returns the number of entries
+pub fn export_incidents(file_path: &str) -> Result<usize, String> {
+    let incidents = list_incidents(usize::MAX >> 1, 0, None)?;
+    let json = serde_json::to_string_pretty(&incidents)
+        .map_err(|e| format!("Failed to serialize incidents: {}", e))?;
+    std::fs::write(file_path, json).map_err(|e| format!("Failed to write file: {}", e))?;
+    Ok(incidents.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::quota::ModelQuota;
+
+    fn snapshot(percentage: i32, reset_time: &str, last_updated: i64) -> QuotaData {
+        QuotaData {
+            models: vec![ModelQuota {
+                name: "gemini-3-flash".to_string(),
+                percentage,
+                reset_time: reset_time.to_string(),
+            }],
+            last_updated,
+            is_forbidden: false,
+            subscription_tier: None,
+        }
+    }
+
+    #[test]
+    fn test_detect_incidents() {
+        let now = 1_700_000_000;
+        let reset_later = DateTime::from_timestamp(now + 3 * 3600, 0)
+            .unwrap()
+            .to_rfc3339();
+        let reset_passed = DateTime::from_timestamp(now - 3600, 0)
+            .unwrap()
+            .to_rfc3339();
+
+        let drop = detect(
+            &snapshot(90, &reset_later, now - 600),
+            &snapshot(30, &reset_later, now),
+            now,
+        );
+        assert_eq!(drop[0].1, IncidentKind::SuddenDrop);
+
+        let early = detect(
+            &snapshot(20, &reset_later, now - 600),
+            &snapshot(100, &reset_later, now),
+            now,
+        );
+        assert_eq!(early[0].1, IncidentKind::EarlyReset);
+
+        let missed = detect(
+            &snapshot(20, &reset_passed, now - 7200),
+            &snapshot(20, &reset_passed, now),
+            now,
+        );
+        assert_eq!(missed[0].1, IncidentKind::MissedReset);
+
+        // Later refreshes that still see the reset missing don't record it again
+        let still_missed = detect(
+            &snapshot(20, &reset_passed, now - 600),
+            &snapshot(20, &reset_passed, now),
+            now,
+        );
+        assert!(still_missed.is_empty());
+
+        // A cached response carries the previous snapshot's timestamp
+        let cached = detect(
+            &snapshot(20, &reset_passed, now - 7200),
+            &snapshot(20, &reset_passed, now - 7200),
+            now,
+        );
+        assert!(cached.is_empty());
+
+        let normal = detect(
+            &snapshot(80, &reset_later, now - 600),
+            &snapshot(70, &reset_later, now),
+            now,
+        );
+        assert!(normal.is_empty());
+    }
+}
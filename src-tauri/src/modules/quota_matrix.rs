@@ -0,0 +1,255 @@
+//! Side-by-side quota matrix (accounts x model families) for the comparison table.
+//!
+//! Besides the remaining quota, each row carries what the "which account should I switch
+//! to" view needs in one call: burn rate and forecast depletion, proxy cooldown and the
+//! pause states (quota protection, token budget, fetch backoff).
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::models::Account;
+use crate::modules::quota_history::QuotaForecast;
+use crate::proxy::token_manager::AccountCooldown;
+
+/// Display order of the known families; other models are appended by name
+const FAMILY_ORDER: &[&str] = &["gemini-pro", "gemini-flash", "gemini-image", "claude"];
+
+/// Remaining quota of one account for one model family
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaMatrixCell {
+    /// Lowest remaining percentage across the family's models (the bottleneck)
+    pub remaining: i32,
+    /// Earliest reset time across the family's models
+    pub reset_time: Option<String>,
+    pub models: Vec<String>,
+    /// At least one model of the family is paused by quota protection
+    pub protected: bool,
+    /// Fastest burn rate across the family's models (percentage points per hour)
+    pub burn_rate_per_hour: f64,
+    /// Earliest forecast depletion across the family's models
+    pub depletes_at: Option<i64>,
+}
+
+/// Proxy rate-limit cooldown of an account (longest one when several models are limited)
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaMatrixCooldown {
+    pub reset_at: i64,
+    pub remaining_ms: u64,
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaMatrixRow {
+    pub account_id: String,
+    pub email: String,
+    pub name: Option<String>,
+    pub is_current: bool,
+    pub disabled: bool,
+    pub proxy_disabled: bool,
+    pub is_forbidden: bool,
+    pub subscription_tier: Option<String>,
+    /// Last quota refresh (None when quota was never fetched)
+    pub last_updated: Option<i64>,
+    pub tags: Vec<String>,
+    /// Paused in proxy rotation by quota protection
+    pub quota_protected: bool,
+    /// Used up its manual token budget for the current period
+    pub budget_exhausted: bool,
+    /// Seconds until scheduled quota polling resumes after repeated fetch failures
+    pub fetch_backoff_secs: Option<u64>,
+    /// Active proxy cooldown (None when not rate limited or the proxy is not running)
+    pub cooldown: Option<QuotaMatrixCooldown>,
+    /// Earliest forecast depletion across all models
+    pub depletes_at: Option<i64>,
+    /// Aligned with `QuotaMatrix::families`; None when the account has no model of that family
+    pub cells: Vec<Option<QuotaMatrixCell>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaMatrix {
+    pub families: Vec<String>,
+    pub rows: Vec<QuotaMatrixRow>,
+    pub generated_at: i64,
+}
+
+/// Group a quota model name into a family column
+pub fn model_family(model_name: &str) -> String {
+    let lower = model_name.to_lowercase();
+    if lower.contains("claude") {
+        "claude".to_string()
+    } else if lower.contains("gemini") && lower.contains("image") {
+        "gemini-image".to_string()
+    } else if lower.contains("gemini") && lower.contains("flash") {
+        "gemini-flash".to_string()
+    } else if lower.contains("gemini") && lower.contains("pro") {
+        "gemini-pro".to_string()
+    } else {
+        lower
+    }
+}
+
+fn build_row(
+    account: &Account,
+    families: &[String],
+    current_id: Option<&str>,
+    forecasts: &HashMap<(String, String), QuotaForecast>,
+    cooldowns: &[AccountCooldown],
+) -> QuotaMatrixRow {
+    let mut cells: Vec<Option<QuotaMatrixCell>> = vec![None; families.len()];
+
+    if let Some(quota) = &account.quota {
+        for model in &quota.models {
+            let family = model_family(&model.name);
+            let Some(index) = families.iter().position(|f| *f == family) else {
+                continue;
+            };
+            let protected =
+                crate::proxy::common::model_mapping::normalize_to_standard_id(&model.name)
+                    .is_some_and(|id| account.protected_models.contains(&id));
+            let reset_time = Some(model.reset_time.clone()).filter(|s| !s.is_empty());
+            let forecast = forecasts.get(&(account.id.clone(), model.name.clone()));
+            let burn_rate = forecast.map_or(0.0, |f| f.burn_rate_per_hour);
+            let depletes_at = forecast.and_then(|f| f.depletes_at);
+
+            match &mut cells[index] {
+                Some(cell) => {
+                    cell.remaining = cell.remaining.min(model.percentage);
+                    // RFC 3339 timestamps in the same zone compare lexicographically
+                    if reset_time.is_some()
+                        && (cell.reset_time.is_none() || reset_time < cell.reset_time)
+                    {
+                        cell.reset_time = reset_time;
+                    }
+                    cell.models.push(model.name.clone());
+                    cell.protected |= protected;
+                    cell.burn_rate_per_hour = cell.burn_rate_per_hour.max(burn_rate);
+                    cell.depletes_at = match (cell.depletes_at, depletes_at) {
+                        (Some(a), Some(b)) => Some(a.min(b)),
+                        (a, b) => a.or(b),
+                    };
+                }
+                slot => {
+                    *slot = Some(QuotaMatrixCell {
+                        remaining: model.percentage,
+                        reset_time,
+                        models: vec![model.name.clone()],
+                        protected,
+                        burn_rate_per_hour: burn_rate,
+                        depletes_at,
+                    })
+                }
+            }
+        }
+    }
+
+    QuotaMatrixRow {
+        account_id: account.id.clone(),
+        email: account.email.clone(),
+        name: account.name.clone(),
+        is_current: current_id == Some(account.id.as_str()),
+        disabled: account.disabled,
+        proxy_disabled: account.proxy_disabled,
+        is_forbidden: account.quota.as_ref().is_some_and(|q| q.is_forbidden),
+        subscription_tier: account
+            .quota
+            .as_ref()
+            .and_then(|q| q.subscription_tier.clone()),
+        last_updated: account.quota.as_ref().map(|q| q.last_updated),
+        tags: account.tags.clone(),
+        quota_protected: account.quota_protected,
+        budget_exhausted: crate::modules::token_budget::is_exhausted(&account.id),
+        fetch_backoff_secs: crate::modules::quota::fetch_backoff_remaining(&account.email)
+            .map(|d| d.as_secs()),
+        cooldown: cooldowns
+            .iter()
+            .filter(|c| c.account_id == account.id)
+            .max_by_key(|c| c.remaining_ms)
+            .map(|c| QuotaMatrixCooldown {
+                reset_at: c.reset_at,
+                remaining_ms: c.remaining_ms,
+                model: c.model.clone(),
+            }),
+        depletes_at: cells
+            .iter()
+            .flatten()
+            .filter_map(|c| c.depletes_at)
+            .min(),
+        cells,
+    }
+}
+
+/// Build the matrix for all accounts (or those carrying `tag`) in the user-defined order;
+/// `cooldowns` come from the running proxy and are empty when it is stopped
+pub fn build_quota_matrix(
+    tag: Option<&str>,
+    cooldowns: &[AccountCooldown],
+) -> Result<QuotaMatrix, String> {
+    let accounts: Vec<Account> = crate::modules::account::list_accounts()?
+        .into_iter()
+        .filter(|a| tag.map_or(true, |t| a.tags.iter().any(|x| x.eq_ignore_ascii_case(t))))
+        .collect();
+    let current_id = crate::modules::account::get_current_account_id()?;
+    let forecasts: HashMap<(String, String), QuotaForecast> =
+        match crate::modules::quota_history::forecast(None) {
+            Ok(list) => list
+                .into_iter()
+                .map(|f| ((f.account_id.clone(), f.model.clone()), f))
+                .collect(),
+            Err(e) => {
+                crate::modules::logger::log_warn(&format!("Quota forecast unavailable: {}", e));
+                HashMap::new()
+            }
+        };
+
+    let mut families: Vec<String> = Vec::new();
+    for account in &accounts {
+        for model in account.quota.iter().flat_map(|q| q.models.iter()) {
+            let family = model_family(&model.name);
+            if !families.contains(&family) {
+                families.push(family);
+            }
+        }
+    }
+    families.sort_by_key(|f| {
+        (
+            FAMILY_ORDER
+                .iter()
+                .position(|known| known == f)
+                .unwrap_or(FAMILY_ORDER.len()),
+            f.clone(),
+        )
+    });
+
+    let rows = accounts
+        .iter()
+        .map(|account| {
+            build_row(
+                account,
+                &families,
+                current_id.as_deref(),
+                &forecasts,
+                cooldowns,
+            )
+        })
+        .collect();
+
+    Ok(QuotaMatrix {
+        families,
+        rows,
+        generated_at: chrono::Utc::now().timestamp(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_family() {
+        assert_eq!(model_family("gemini-3-pro-high"), "gemini-pro");
+        assert_eq!(model_family("gemini-3-pro-image"), "gemini-image");
+        assert_eq!(model_family("gemini-3-flash"), "gemini-flash");
+        assert_eq!(model_family("claude-opus-4-5-thinking"), "claude");
+        assert_eq!(model_family("gpt-oss-120b"), "gpt-oss-120b");
+    }
+}
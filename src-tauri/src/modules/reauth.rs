@@ -0,0 +1,99 @@
+//! 重新授权提醒
+//!
+//! 账号的 refresh token 失效 (invalid_grant) 被标记为需要重新授权时，弹出桌面通知、推送
+//! "accounts://needs-reauth" 事件并在托盘的"需要重新授权"菜单中列出该账号；
+//! 点击托盘项、前端通知或打开 `antigravity-tools://reauth/<account_id>` 链接
+//! (scheme 由 deep-link 插件注册，已运行时由单实例插件转发) 直接进入该账号的 OAuth 流程，
+//! login_hint 预填账号邮箱；登录的账号与原账号不一致时拒绝保存。
+
+use once_cell::sync::OnceCell;
+use tauri::{Emitter, Manager};
+
+use crate::modules::logger;
+
+static APP_HANDLE: OnceCell<tauri::AppHandle> = OnceCell::new();
+
+/// 重新授权链接前缀，后接账号 ID
+pub const DEEP_LINK_PREFIX: &str = "antigravity-tools://reauth/";
+
+/// 托盘菜单项 ID 前缀
+pub const TRAY_ITEM_PREFIX: &str = "reauth:";
+
+/// 保存 AppHandle，用于推送事件与更新托盘
+pub fn init(app: tauri::AppHandle) {
+    let _ = APP_HANDLE.set(app);
+}
+
+/// 账号刚被标记为需要重新授权时调用 (已标记过的账号不重复提醒)
+pub fn notify_required(account_id: &str, email: &str) {
+    logger::log_warn(&format!("[Reauth] {} needs re-authorization", email));
+    crate::modules::quota_alerts::notify_desktop(
+        "Antigravity Tools",
+        &format!("{} 授权已失效，请从托盘菜单或通知重新授权", email),
+    );
+
+    if let Some(app) = APP_HANDLE.get() {
+        let _ = app.emit(
+            "accounts://needs-reauth",
+            serde_json::json!({
+                "account_id": account_id,
+                "email": email,
+                "link": format!("{}{}", DEEP_LINK_PREFIX, account_id),
+            }),
+        );
+        crate::modules::tray::update_tray_menus(app);
+    }
+}
+
+/// 从重新授权链接中解析账号 ID
+pub fn parse_deep_link(arg: &str) -> Option<&str> {
+    arg.trim()
+        .strip_prefix(DEEP_LINK_PREFIX)
+        .map(|id| id.trim_end_matches('/'))
+        .filter(|id| !id.is_empty() && !id.contains(['/', '\\', '.']))
+}
+
+/// 显示主窗口并开始指定账号的重新授权
+pub fn start(account_id: &str) {
+    let Some(app) = APP_HANDLE.get() else {
+        return;
+    };
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+
+    let app = app.clone();
+    let account_id = account_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        match crate::commands::reauth_account(app.clone(), account_id.clone()).await {
+            Ok(account) => {
+                let _ = app.emit("accounts://reauthorized", &account.id);
+            }
+            Err(e) => {
+                logger::log_warn(&format!("[Reauth] Re-authorization of {} failed: {}", account_id, e));
+            }
+        }
+    });
+}
+
+/// 处理启动参数中的重新授权链接 (首次启动或单实例转发)
+pub fn handle_args(args: &[String]) {
+    if let Some(account_id) = args.iter().find_map(|a| parse_deep_link(a)) {
+        start(account_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_reauth_link() {
+        assert_eq!(parse_deep_link("antigravity-tools://reauth/abc-123"), Some("abc-123"));
+        assert_eq!(parse_deep_link("antigravity-tools://reauth/abc-123/"), Some("abc-123"));
+        assert_eq!(parse_deep_link("antigravity-tools://reauth/"), None);
+        assert_eq!(parse_deep_link("antigravity-tools://reauth/../x"), None);
+        assert_eq!(parse_deep_link("--minimized"), None);
+    }
+}
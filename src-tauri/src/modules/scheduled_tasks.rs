@@ -0,0 +1,467 @@
+//! 可注册的周期任务
+//!
+//! 每个后台周期任务实现 [`ScheduledTask`]，并在 [`registered_tasks`] 中登记；调度器为每个任务
+//! 统一处理首次延迟、执行间隔、全局停止开关、单独暂停、系统唤醒后的补偿与执行历史，新增任务无需改动调度器内部。
+//! 预热、配额刷新、cron、延时任务与休眠检测依赖调度器内部状态，定义在 `scheduler` 中。
+
+use crate::modules::scheduler::{
+    CronTask, DelayedTaskRunner, QuotaRefreshTask, WakeDetectionTask, WarmupScanTask,
+};
+use crate::modules::{
+    account, config, config_sync, instance, job_history, launch_refresh, logger, process,
+};
+use std::collections::HashSet;
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, Manager};
+use tokio::time::Duration;
+
+/// 任务的默认执行节奏
+#[derive(Debug, Clone, Copy)]
+pub struct TaskSchedule {
+    /// 启动后首次执行前的等待时间
+    pub initial_delay: Duration,
+    pub interval: Duration,
+}
+
+impl TaskSchedule {
+    pub const fn every(secs: u64) -> Self {
+        Self {
+            initial_delay: Duration::ZERO,
+            interval: Duration::from_secs(secs),
+        }
+    }
+
+    pub const fn after(mut self, secs: u64) -> Self {
+        self.initial_delay = Duration::from_secs(secs);
+        self
+    }
+}
+
+/// 系统从休眠中恢复时对任务计时的处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WakeAction {
+    /// 按原周期继续
+    Ignore,
+    /// 从现在起重新计时，不补执行
+    Reschedule,
+    /// 立即补执行一次并从现在起重新计时
+    RunNow,
+}
+
+#[async_trait]
+pub trait ScheduledTask: Send + Sync {
+    /// 任务标识：用作暂停状态的键与执行历史中的任务类型
+    fn id(&self) -> &'static str;
+
+    fn default_schedule(&self) -> TaskSchedule;
+
+    /// 是否受全局停止开关影响；凭据清理等必须执行的任务返回 false
+    fn respects_halt(&self) -> bool {
+        true
+    }
+
+    /// 系统从休眠中恢复时调用，默认按原周期继续
+    async fn on_wake(&self, _handle: &tauri::AppHandle) -> WakeAction {
+        WakeAction::Ignore
+    }
+
+    /// 执行一次。返回 `Ok(None)` 表示本轮无事可做，不写入执行历史；
+    /// `Ok(Some(summary))` 与 `Err` 会记录到执行历史
+    async fn run(&self, handle: &tauri::AppHandle) -> Result<Option<String>, String>;
+}
+
+/// 调度器启动时运行的全部周期任务
+pub fn registered_tasks() -> Vec<Arc<dyn ScheduledTask>> {
+    vec![
+        Arc::new(WarmupScanTask),
+        Arc::new(QuotaRefreshTask::default()),
+        Arc::new(CronTask::default()),
+        Arc::new(DelayedTaskRunner),
+        Arc::new(WakeDetectionTask::default()),
+        Arc::new(InstanceMonitorTask),
+        Arc::new(OrphanDetectionTask::default()),
+        Arc::new(AccountExpiryTask),
+        Arc::new(LaunchRefreshTask),
+        Arc::new(ConfigSyncTask),
+        Arc::new(TokenRefreshTask),
+    ]
+}
+
+/// 实例状态刷新：定期检测所有实例的运行状态，PID 或启动参数变化时写回实例配置
+struct InstanceMonitorTask;
+
+#[async_trait]
+impl ScheduledTask for InstanceMonitorTask {
+    fn id(&self) -> &'static str {
+        "instance_monitor"
+    }
+
+    fn default_schedule(&self) -> TaskSchedule {
+        TaskSchedule::every(5)
+    }
+
+    fn respects_halt(&self) -> bool {
+        false
+    }
+
+    async fn run(&self, _handle: &tauri::AppHandle) -> Result<Option<String>, String> {
+        // 获取所有实例
+        let instances = match crate::modules::instance::list_instances() {
+            Ok(list) => list,
+            Err(_) => return Ok(None),
+        };
+
+        for mut instance in instances {
+            let cached_pid = instance.last_root_pid;
+
+            // 检测实例运行状态
+            let (is_running, new_pid, new_args) = if instance.is_default {
+                let running = crate::modules::process::is_default_instance_running();
+                if running {
+                    if let Some((pid, args)) =
+                        crate::modules::process::get_instance_root_pid_and_args(
+                            &instance.user_data_dir,
+                            true,
+                            cached_pid,
+                        )
+                    {
+                        (true, Some(pid), Some(args))
+                    } else {
+                        (true, None, None)
+                    }
+                } else {
+                    (false, None, None)
+                }
+            } else {
+                // 非默认实例：优先使用缓存 PID 检测
+                if let Some(pid) = cached_pid {
+                    if crate::modules::process::is_pid_valid_instance_root(
+                        pid,
+                        &instance.user_data_dir,
+                        false,
+                    ) {
+                        if let Some((_, args)) =
+                            crate::modules::process::get_instance_root_pid_and_args(
+                                &instance.user_data_dir,
+                                false,
+                                Some(pid),
+                            )
+                        {
+                            (true, Some(pid), Some(args))
+                        } else {
+                            (true, Some(pid), None)
+                        }
+                    } else {
+                        // 缓存 PID 无效，重新检测
+                        let running =
+                            crate::modules::process::is_instance_running(&instance.user_data_dir);
+                        if running {
+                            if let Some((pid, args)) =
+                                crate::modules::process::get_instance_root_pid_and_args(
+                                    &instance.user_data_dir,
+                                    false,
+                                    None,
+                                )
+                            {
+                                (true, Some(pid), Some(args))
+                            } else {
+                                (true, None, None)
+                            }
+                        } else {
+                            (false, None, None)
+                        }
+                    }
+                } else {
+                    let running =
+                        crate::modules::process::is_instance_running(&instance.user_data_dir);
+                    if running {
+                        if let Some((pid, args)) =
+                            crate::modules::process::get_instance_root_pid_and_args(
+                                &instance.user_data_dir,
+                                false,
+                                None,
+                            )
+                        {
+                            (true, Some(pid), Some(args))
+                        } else {
+                            (true, None, None)
+                        }
+                    } else {
+                        (false, None, None)
+                    }
+                }
+            };
+
+            // 更新实例配置（只在 PID 变化时保存）
+            let mut need_save = false;
+
+            if is_running {
+                if new_pid != instance.last_root_pid {
+                    instance.last_root_pid = new_pid;
+                    need_save = true;
+                    logger::log_info(&format!(
+                        "[Instance Monitor] {} detected running, PID: {:?}",
+                        instance.name, new_pid
+                    ));
+                }
+                if let Some(args) = new_args {
+                    let args_str = args.join(" ");
+                    if !args_str.contains("--type=") {
+                        if instance.last_launch_args.as_ref() != Some(&args) {
+                            instance.last_launch_args = Some(args);
+                            need_save = true;
+                        }
+                    }
+                }
+            } else {
+                // 实例未运行，清除缓存的 PID
+                if instance.last_root_pid.is_some() {
+                    instance.last_root_pid = None;
+                    need_save = true;
+                }
+            }
+
+            if need_save {
+                let _ = crate::modules::instance::save_instance(&instance);
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// 孤儿辅助进程检测：查找父进程已退出的 Antigravity 辅助进程，发现新的孤儿时通知前端由用户确认清理
+#[derive(Default)]
+struct OrphanDetectionTask {
+    last_reported: Mutex<Vec<u32>>,
+}
+
+#[async_trait]
+impl ScheduledTask for OrphanDetectionTask {
+    fn id(&self) -> &'static str {
+        "orphan_detection"
+    }
+
+    fn default_schedule(&self) -> TaskSchedule {
+        TaskSchedule::every(900).after(120)
+    }
+
+    async fn run(&self, handle: &tauri::AppHandle) -> Result<Option<String>, String> {
+        let orphans = tokio::task::spawn_blocking(process::find_orphaned_helpers)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let pids: Vec<u32> = orphans.iter().map(|o| o.pid).collect();
+        let mut last_reported = self.last_reported.lock().unwrap_or_else(|e| e.into_inner());
+        if !orphans.is_empty() && pids != *last_reported {
+            logger::log_warn(&format!(
+                "[Maintenance] Found {} orphaned Antigravity helper process trees: {:?}",
+                orphans.len(),
+                pids
+            ));
+            let _ = handle.emit("process://orphans", &orphans);
+        }
+        *last_reported = pids;
+        Ok(None)
+    }
+}
+
+/// 临时账号过期清理：到期的临时账号归档并清除凭据，凭据不应在过期后继续保留
+struct AccountExpiryTask;
+
+#[async_trait]
+impl ScheduledTask for AccountExpiryTask {
+    fn id(&self) -> &'static str {
+        "account_expiry"
+    }
+
+    fn default_schedule(&self) -> TaskSchedule {
+        TaskSchedule::every(60)
+    }
+
+    fn respects_halt(&self) -> bool {
+        false
+    }
+
+    async fn run(&self, handle: &tauri::AppHandle) -> Result<Option<String>, String> {
+        let expired = tokio::task::spawn_blocking(account::expire_temporary_accounts)
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| {
+                logger::log_warn(&format!("[Maintenance] Temporary account cleanup failed: {}", e));
+                e
+            })?;
+        // 只记录实际归档了账号的执行，避免每分钟写入一条
+        if expired.is_empty() {
+            return Ok(None);
+        }
+
+        if let Some(state) = handle.try_state::<crate::commands::proxy::ProxyServiceState>() {
+            let _ = crate::commands::proxy::reload_proxy_accounts(state).await;
+        }
+        crate::modules::tray::update_tray_menus(handle);
+        let _ = handle.emit("accounts://expired", &expired);
+        Ok(Some(format!("{} accounts archived", expired.len())))
+    }
+}
+
+/// Antigravity 自动更新检测：可执行文件变化（外部自动更新）后校验所有实例保存的启动参数与可执行文件路径
+struct LaunchRefreshTask;
+
+#[async_trait]
+impl ScheduledTask for LaunchRefreshTask {
+    fn id(&self) -> &'static str {
+        "launch_refresh"
+    }
+
+    fn default_schedule(&self) -> TaskSchedule {
+        TaskSchedule::every(300)
+    }
+
+    fn respects_halt(&self) -> bool {
+        false
+    }
+
+    async fn run(&self, handle: &tauri::AppHandle) -> Result<Option<String>, String> {
+        let result = tokio::task::spawn_blocking(|| {
+            if !launch_refresh::executable_changed() {
+                return Ok(None);
+            }
+            launch_refresh::revalidate_launch_settings().map(Some)
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+
+        match result {
+            Ok(Some(report)) if report.has_changes() => {
+                let _ = handle.emit("instances://launch_settings_refreshed", &report);
+            }
+            Err(e) => {
+                logger::log_warn(&format!("[LaunchRefresh] Revalidation failed: {}", e));
+            }
+            _ => {}
+        }
+        // 校验结果已通过事件通知前端，不写入执行历史
+        Ok(None)
+    }
+}
+
+/// 配置远程同步：按配置的间隔自动同步；冲突时不写入任何一方，通知前端由用户选择
+struct ConfigSyncTask;
+
+#[async_trait]
+impl ScheduledTask for ConfigSyncTask {
+    fn id(&self) -> &'static str {
+        "config_sync"
+    }
+
+    fn default_schedule(&self) -> TaskSchedule {
+        TaskSchedule::every(60).after(90)
+    }
+
+    async fn run(&self, handle: &tauri::AppHandle) -> Result<Option<String>, String> {
+        if !config_sync::is_due() {
+            return Ok(None);
+        }
+        crate::modules::scheduler::apply_jitter().await;
+
+        match config_sync::sync(None).await {
+            Ok((result, pulled)) => {
+                if let Some(config) = pulled {
+                    crate::commands::apply_runtime_config(handle, &config).await;
+                }
+                let _ = handle.emit("config://synced", &result);
+            }
+            Err(e) => {
+                logger::log_warn(&format!("[ConfigSync] Automatic sync failed: {}", e));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// 反代在该时间内 (秒) 用过的账号视为正在使用
+const RECENT_PROXY_USE_SECS: i64 = 1800;
+
+/// Token 提前刷新：运行中实例绑定的账号与最近被反代使用的账号，在过期前按配置的余量刷新，
+/// 避免请求进行到一半时 token 恰好过期
+struct TokenRefreshTask;
+
+#[async_trait]
+impl ScheduledTask for TokenRefreshTask {
+    fn id(&self) -> &'static str {
+        "token_refresh"
+    }
+
+    fn default_schedule(&self) -> TaskSchedule {
+        TaskSchedule::every(60).after(45)
+    }
+
+    async fn run(&self, handle: &tauri::AppHandle) -> Result<Option<String>, String> {
+        let margin = config::load_app_config()?.oauth.proactive_refresh_margin_secs as i64;
+        if margin == 0 {
+            return Ok(None);
+        }
+
+        // 反代中的账号由 TokenManager 刷新，同时更新内存缓存与账号文件
+        let mut refreshed = 0;
+        let mut failed = 0;
+        let mut handled: HashSet<String> = HashSet::new();
+        let state = handle.state::<crate::commands::proxy::ProxyServiceState>();
+        if let Some(instance) = state.instance.read().await.as_ref() {
+            for account_id in instance.token_manager.recently_used_accounts(RECENT_PROXY_USE_SECS) {
+                match instance.token_manager.refresh_ahead(&account_id, margin).await {
+                    Ok(true) => refreshed += 1,
+                    Ok(false) => {}
+                    Err(e) => {
+                        failed += 1;
+                        logger::log_warn(&format!(
+                            "[TokenRefresh] Proactive refresh of {} failed: {}",
+                            account_id, e
+                        ));
+                    }
+                }
+                handled.insert(account_id);
+            }
+        }
+
+        let running_accounts: Vec<String> = tokio::task::spawn_blocking(instance::list_instances)
+            .await
+            .map_err(|e| e.to_string())??
+            .into_iter()
+            .filter(|i| i.last_root_pid.is_some())
+            .filter_map(|i| i.current_account_id)
+            .filter(|id| !handled.contains(id))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        for account_id in running_accounts {
+            match account::refresh_token_ahead(&account_id, margin).await {
+                Ok(true) => refreshed += 1,
+                Ok(false) => {}
+                Err(e) => {
+                    failed += 1;
+                    logger::log_warn(&format!(
+                        "[TokenRefresh] Proactive refresh of {} failed: {}",
+                        account_id, e
+                    ));
+                }
+            }
+        }
+
+        match (refreshed, failed) {
+            (0, 0) => Ok(None),
+            (0, _) => Err(format!("{} token refreshes failed", failed)),
+            _ => Ok(Some(format!("{} tokens refreshed, {} failed", refreshed, failed))),
+        }
+    }
+}
+
+/// 执行一次任务，按约定写入执行历史
+pub(crate) async fn run_once(task: &dyn ScheduledTask, handle: &tauri::AppHandle) {
+    let started_at = chrono::Utc::now().timestamp_millis();
+    let result = task.run(handle).await;
+    if !matches!(result, Ok(None)) {
+        job_history::record(task.id(), None, started_at, &result);
+    }
+}
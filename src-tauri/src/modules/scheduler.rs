@@ -1,11 +1,18 @@
-use crate::models::Account;
-use crate::modules::{account, config, logger, quota};
-use chrono::Utc;
+use crate::models::{Account, CatchUpPolicy, CronJob, CronJobKind};
+use crate::modules::delayed_tasks::{self, DelayedAction, DelayedTask};
+use crate::modules::scheduled_tasks::{self, ScheduledTask, TaskSchedule, WakeAction};
+use crate::modules::{account, config, cron, job_history, logger, quota};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use once_cell::sync::Lazy;
-use std::collections::HashMap;
+use rand::Rng;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-use std::sync::Mutex;
-use tauri::Manager;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, Manager};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use tokio::time::{self, Duration};
 
 // Warmup history: key = "email:model_name:100", value = warmup timestamp
@@ -41,6 +48,252 @@ pub fn record_warmup_history(key: &str, timestamp: i64) {
     save_warmup_history(&history);
 }
 
+/// 将调度器内存状态写回磁盘（退出时调用）
+pub fn persist_state() {
+    if let Ok(history) = WARMUP_HISTORY.lock() {
+        save_warmup_history(&history);
+    }
+}
+
+// 全局停止开关：置位后定时任务跳过执行，正在执行的后台任务会被取消
+static JOBS_HALTED: AtomicBool = AtomicBool::new(false);
+static RUNNING_JOBS: Lazy<Mutex<Vec<tokio::task::AbortHandle>>> =
+    Lazy::new(|| Mutex::new(Vec::new()));
+
+/// 定时任务是否已被全局停止
+pub fn jobs_halted() -> bool {
+    JOBS_HALTED.load(Ordering::SeqCst)
+}
+
+/// 停止所有定时任务并取消正在执行的任务，返回被取消的任务数
+pub fn halt_jobs() -> usize {
+    JOBS_HALTED.store(true, Ordering::SeqCst);
+    let mut jobs = RUNNING_JOBS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut cancelled = 0;
+    for job in jobs.drain(..) {
+        if !job.is_finished() {
+            job.abort();
+            cancelled += 1;
+        }
+    }
+    logger::log_warn(&format!(
+        "[Scheduler] All scheduled jobs halted ({} running jobs cancelled)",
+        cancelled
+    ));
+    cancelled
+}
+
+/// 恢复定时任务
+pub fn resume_jobs() {
+    if JOBS_HALTED.swap(false, Ordering::SeqCst) {
+        logger::log_info("[Scheduler] Scheduled jobs resumed");
+    }
+}
+
+/// 可单独暂停/恢复的定时任务
+pub const PAUSABLE_JOBS: [&str; 6] = [
+    "quota_refresh",
+    "warmup",
+    "config_backup",
+    "config_sync",
+    "update_check",
+    "instance_schedule",
+];
+
+/// 定时任务的暂停状态（内置任务与 cron 任务）
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduledJobState {
+    pub id: String,
+    /// 内置任务为任务名，cron 任务为其类型
+    pub kind: String,
+    pub is_cron: bool,
+    pub paused: bool,
+}
+
+/// 用户是否暂停了某个定时任务
+pub fn job_paused(job: &str) -> bool {
+    config::load_app_config().map_or(false, |c| c.job_paused(job))
+}
+
+/// 列出所有定时任务及其暂停状态
+pub fn list_job_states() -> Result<Vec<ScheduledJobState>, String> {
+    let app_config = config::load_app_config()?;
+    let builtin = PAUSABLE_JOBS.iter().map(|job| ScheduledJobState {
+        id: job.to_string(),
+        kind: job.to_string(),
+        is_cron: false,
+        paused: app_config.job_paused(job),
+    });
+    let cron_jobs = app_config.cron_jobs.iter().map(|job| ScheduledJobState {
+        id: job.id.clone(),
+        kind: job.kind.as_str().to_string(),
+        is_cron: true,
+        paused: !job.enabled,
+    });
+    Ok(builtin.chain(cron_jobs).collect())
+}
+
+/// 暂停或恢复单个定时任务；`job_id` 为内置任务名或 cron 任务 id，状态保存在配置中
+pub fn set_job_paused(job_id: &str, paused: bool) -> Result<(), String> {
+    let mut found = true;
+    config::update_app_config("set_job_paused", |app_config| {
+        if PAUSABLE_JOBS.contains(&job_id) {
+            app_config.paused_jobs.retain(|j| j != job_id);
+            if paused {
+                app_config.paused_jobs.push(job_id.to_string());
+            }
+        } else if let Some(job) = app_config.cron_jobs.iter_mut().find(|j| j.id == job_id) {
+            job.enabled = !paused;
+        } else {
+            found = false;
+        }
+        found
+    })?;
+    if !found {
+        return Err(format!("Unknown scheduled job: {}", job_id));
+    }
+    logger::log_info(&format!(
+        "[Scheduler] Job {} {}",
+        job_id,
+        if paused { "paused" } else { "resumed" }
+    ));
+    Ok(())
+}
+
+/// 定时任务类别：同类长任务排队依次执行，且最多占用 (总并发 - 1) 个名额，始终为短任务留出余量
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JobCategory {
+    /// 配额刷新、实例启停、账号启用等短任务
+    Quick,
+    /// 配置备份、批量预热等耗时任务
+    Long,
+}
+
+/// 可调整容量的信号量：配置变化时原地增减名额，已排队和执行中的任务不受影响
+struct ResizableLimit {
+    semaphore: Arc<Semaphore>,
+    size: usize,
+    /// 缩容时被占用、尚未收回的名额，在任务归还后逐步收回
+    pending_shrink: usize,
+}
+
+impl ResizableLimit {
+    fn new(size: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(size)),
+            size,
+            pending_shrink: 0,
+        }
+    }
+
+    fn resize(&mut self, size: usize) {
+        if size > self.size {
+            // 扩容先抵消未收回的缩容名额，剩余部分再补发
+            let grow = size - self.size;
+            let cancelled = grow.min(self.pending_shrink);
+            self.pending_shrink -= cancelled;
+            self.semaphore.add_permits(grow - cancelled);
+        } else {
+            self.pending_shrink += self.size - size;
+        }
+        self.size = size;
+        self.reclaim();
+    }
+
+    /// 收回已归还的多余名额
+    fn reclaim(&mut self) {
+        if self.pending_shrink > 0 {
+            self.pending_shrink -= self.semaphore.forget_permits(self.pending_shrink);
+        }
+    }
+}
+
+/// (全局名额, 长任务名额)
+static JOB_LIMITS: Lazy<Mutex<Option<(ResizableLimit, ResizableLimit)>>> =
+    Lazy::new(|| Mutex::new(None));
+
+/// 执行许可，释放时归还名额
+struct JobSlot {
+    _category: Option<OwnedSemaphorePermit>,
+    _global: OwnedSemaphorePermit,
+}
+
+fn job_semaphores() -> (Arc<Semaphore>, Arc<Semaphore>) {
+    let app_config = config::load_app_config().unwrap_or_default();
+    let max_parallel = app_config.scheduler_max_parallel_jobs.max(1) as usize;
+    let long_parallel = (app_config.scheduler_long_job_parallel.max(1) as usize)
+        .min(max_parallel.saturating_sub(1).max(1));
+
+    let mut limits = JOB_LIMITS.lock().unwrap_or_else(|e| e.into_inner());
+    let (global, long_sem) = limits.get_or_insert_with(|| {
+        (
+            ResizableLimit::new(max_parallel),
+            ResizableLimit::new(long_parallel),
+        )
+    });
+    global.resize(max_parallel);
+    long_sem.resize(long_parallel);
+    (global.semaphore.clone(), long_sem.semaphore.clone())
+}
+
+/// 排队等待执行名额：长任务先在本类别队列中排队，再占用全局名额
+async fn acquire_job_slot(category: JobCategory, name: &str) -> JobSlot {
+    let (global, long_sem) = job_semaphores();
+    let waiting = global.available_permits() == 0
+        || (category == JobCategory::Long && long_sem.available_permits() == 0);
+    if waiting {
+        logger::log_info(&format!(
+            "[Scheduler] Job {} queued, waiting for a free slot",
+            name
+        ));
+    }
+    let category_permit = match category {
+        JobCategory::Long => long_sem.acquire_owned().await.ok(),
+        JobCategory::Quick => None,
+    };
+    // 信号量不会被关闭，acquire 不会失败
+    let global_permit = global
+        .acquire_owned()
+        .await
+        .expect("scheduler semaphore closed");
+    JobSlot {
+        _category: category_permit,
+        _global: global_permit,
+    }
+}
+
+fn cron_job_category(kind: CronJobKind) -> JobCategory {
+    match kind {
+        CronJobKind::ConfigBackup => JobCategory::Long,
+        _ => JobCategory::Quick,
+    }
+}
+
+/// 休眠检测的采样间隔
+const WAKE_CHECK_SECS: i64 = 10;
+/// 两次采样之间多出的时间超过该值视为系统曾休眠
+const SLEEP_GAP_SECS: i64 = 60;
+/// cron 任务计划时间已过去超过该值视为错过（调度循环每 20 秒检查一次）
+const MISSED_GRACE_SECS: i64 = 90;
+/// RunAll 策略下一次最多补执行的次数
+const MAX_CATCH_UP_RUNS: usize = 24;
+
+/// 系统从休眠中恢复时广播休眠时长 (秒)
+static WAKE_EVENTS: Lazy<tokio::sync::broadcast::Sender<i64>> =
+    Lazy::new(|| tokio::sync::broadcast::channel(8).0);
+
+/// 订阅系统唤醒事件
+fn subscribe_wake() -> tokio::sync::broadcast::Receiver<i64> {
+    WAKE_EVENTS.subscribe()
+}
+
+/// 登记后台任务，以便全局停止时取消
+fn track_job<T>(handle: &tokio::task::JoinHandle<T>) {
+    let mut jobs = RUNNING_JOBS.lock().unwrap_or_else(|e| e.into_inner());
+    jobs.retain(|job| !job.is_finished());
+    jobs.push(handle.abort_handle());
+}
+
 pub fn check_cooldown(key: &str, cooldown_seconds: i64) -> bool {
     let history = WARMUP_HISTORY.lock().unwrap();
     if let Some(&last_ts) = history.get(key) {
@@ -51,360 +304,884 @@ pub fn check_cooldown(key: &str, cooldown_seconds: i64) -> bool {
     }
 }
 
-pub fn start_scheduler(app_handle: tauri::AppHandle) {
-    tauri::async_runtime::spawn(async move {
-        logger::log_info("Smart Warmup Scheduler started. Monitoring quota at 100%...");
+/// scheduler_jitter_secs 的上限，更大的值按此截断，避免延迟超过任务周期
+pub(crate) const MAX_JITTER_SECS: u32 = 300;
 
-        // Scan every 10 minutes
-        let mut interval = time::interval(Duration::from_secs(600));
+/// 配置的随机延迟窗口 (0 表示关闭)
+pub(crate) fn jitter_window() -> Duration {
+    let secs = config::load_app_config()
+        .map_or(0, |c| c.scheduler_jitter_secs)
+        .min(MAX_JITTER_SECS);
+    Duration::from_secs(secs as u64)
+}
 
-        loop {
-            interval.tick().await;
+/// 定时任务执行前的随机延迟 (0 ~ scheduler_jitter_secs)，避免多个任务在同一秒集中请求上游
+pub(crate) async fn apply_jitter() {
+    let max_ms = jitter_window().as_millis() as u64;
+    if max_ms > 0 {
+        let delay = rand::thread_rng().gen_range(0..=max_ms);
+        time::sleep(Duration::from_millis(delay)).await;
+    }
+}
 
-            // Load configuration
-            let Ok(app_config) = config::load_app_config() else {
-                continue;
-            };
+/// 反代中整个账号处于限流冷却的账号 id（仅针对单个模型的冷却不影响配额查询）
+async fn rate_limited_accounts(handle: &tauri::AppHandle) -> HashSet<String> {
+    let state = handle.state::<crate::commands::proxy::ProxyServiceState>();
+    let instance = state.instance.read().await;
+    instance
+        .as_ref()
+        .map(|instance| {
+            instance
+                .token_manager
+                .cooldown_snapshot()
+                .into_iter()
+                .filter(|c| c.model.is_none())
+                .map(|c| c.account_id)
+                .collect()
+        })
+        .unwrap_or_default()
+}
 
-            if !app_config.scheduled_warmup.enabled {
-                continue;
-            }
+/// 配额刷新完成后重新加载反代账号、更新托盘并通知前端
+async fn finish_quota_refresh(handle: &tauri::AppHandle, account_ids: Vec<String>, failed: usize) {
+    let state = handle.state::<crate::commands::proxy::ProxyServiceState>();
+    if let Some(instance) = state.instance.read().await.as_ref() {
+        let _ = instance.token_manager.reload_all_accounts().await;
+    }
+    crate::modules::tray::update_tray_menus(handle);
+    let _ = handle.emit(
+        "quota://refreshed",
+        serde_json::json!({ "account_ids": account_ids, "failed": failed }),
+    );
+}
 
-            // Get all accounts (no longer filtering by level)
-            let Ok(accounts) = account::list_accounts() else {
-                continue;
-            };
+/// 手动刷新全部配额：与定时刷新一样上报 scheduler://job_* 事件 (job_id 为 "manual") 并写入执行历史
+pub async fn refresh_all_quotas_tracked(
+    handle: &tauri::AppHandle,
+) -> Result<account::RefreshStats, String> {
+    let tracker = JobRunTracker::start(handle, "quota_refresh", Some("manual"));
+    let refreshed = match account::list_accounts() {
+        Ok(accounts) => {
+            account::refresh_quotas_with_progress(accounts, |done, total, email| {
+                tracker.progress(done, total, Some(email))
+            })
+            .await
+        }
+        Err(e) => Err(e),
+    };
+    tracker.finish(
+        &refreshed
+            .as_ref()
+            .map(|stats| {
+                Some(format!(
+                    "{}/{} accounts refreshed",
+                    stats.success, stats.total
+                ))
+            })
+            .map_err(Clone::clone),
+    );
+    refreshed
+}
 
-            if accounts.is_empty() {
-                continue;
+/// 扫描 / 预热后的同步刷新：不计入执行历史，完成后通知前端重新加载
+async fn refresh_all_quietly(handle: &tauri::AppHandle) {
+    let Ok(accounts) = account::list_accounts() else {
+        return;
+    };
+    let ids = accounts.iter().map(|a| a.id.clone()).collect();
+    if let Ok(stats) = account::refresh_quotas_logic(accounts).await {
+        finish_quota_refresh(handle, ids, stats.failed).await;
+    }
+}
+
+static NEXT_RUN_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 单次任务执行的进度上报：开始、进度、结束分别发送 scheduler://job_started、
+/// scheduler://job_progress、scheduler://job_finished 事件，结束时写入执行历史
+struct JobRunTracker {
+    handle: tauri::AppHandle,
+    run_id: u64,
+    job_type: String,
+    job_id: Option<String>,
+    started_at: i64,
+}
+
+impl JobRunTracker {
+    fn start(handle: &tauri::AppHandle, job_type: &str, job_id: Option<&str>) -> Self {
+        let tracker = Self {
+            handle: handle.clone(),
+            run_id: NEXT_RUN_ID.fetch_add(1, Ordering::Relaxed),
+            job_type: job_type.to_string(),
+            job_id: job_id.map(str::to_string),
+            started_at: Utc::now().timestamp_millis(),
+        };
+        let _ = handle.emit(
+            "scheduler://job_started",
+            serde_json::json!({
+                "run_id": tracker.run_id,
+                "job_type": tracker.job_type,
+                "job_id": tracker.job_id,
+                "started_at": tracker.started_at,
+            }),
+        );
+        tracker
+    }
+
+    /// 上报进度：已完成 `done` / 共 `total` 项
+    fn progress(&self, done: usize, total: usize, message: Option<&str>) {
+        let _ = self.handle.emit(
+            "scheduler://job_progress",
+            serde_json::json!({
+                "run_id": self.run_id,
+                "job_type": self.job_type,
+                "job_id": self.job_id,
+                "done": done,
+                "total": total,
+                "message": message,
+            }),
+        );
+    }
+
+    fn finish(self, result: &Result<Option<String>, String>) {
+        job_history::record(&self.job_type, self.job_id.as_deref(), self.started_at, result);
+        let _ = self.handle.emit(
+            "scheduler://job_finished",
+            serde_json::json!({
+                "run_id": self.run_id,
+                "job_type": self.job_type,
+                "job_id": self.job_id,
+                "success": result.is_ok(),
+                "detail": result.as_ref().ok().cloned().flatten(),
+                "error": result.as_ref().err(),
+                "duration_ms": Utc::now().timestamp_millis() - self.started_at,
+            }),
+        );
+    }
+}
+
+/// 执行配额刷新、配置备份或实例启停，成功时返回执行摘要
+async fn execute_job(
+    kind: CronJobKind,
+    instance_id: Option<String>,
+    handle: &tauri::AppHandle,
+    tracker: &JobRunTracker,
+) -> Result<Option<String>, String> {
+    match kind {
+        CronJobKind::QuotaRefresh => {
+            let rate_limited = rate_limited_accounts(handle).await;
+            let accounts: Vec<Account> = account::list_accounts()?
+                .into_iter()
+                .filter(|a| !rate_limited.contains(&a.id))
+                .collect();
+            let ids = accounts.iter().map(|a| a.id.clone()).collect();
+            let stats = account::refresh_quotas_with_progress(accounts, |done, total, email| {
+                tracker.progress(done, total, Some(email))
+            })
+            .await?;
+            finish_quota_refresh(handle, ids, stats.failed).await;
+            Ok(Some(format!("{}/{} accounts refreshed", stats.success, stats.total)))
+        }
+        CronJobKind::ConfigBackup => tokio::task::spawn_blocking(config::backup_app_config)
+            .await
+            .unwrap_or_else(|e| Err(e.to_string()))
+            .map(|_| None),
+        CronJobKind::InstanceStart | CronJobKind::InstanceStop => {
+            let id = instance_id.ok_or("job has no instance_id")?;
+            if kind == CronJobKind::InstanceStart {
+                crate::commands::start_instance(id).await.map(|_| None)
+            } else {
+                crate::commands::stop_instance(id).await.map(|_| None)
             }
+        }
+    }
+}
 
+/// 计划时间 `next` 已到时本轮应执行的次数：按时触发执行一次，错过（如系统休眠）时按补偿策略决定
+fn catch_up_runs(job: &CronJob, next: DateTime<Utc>, now: DateTime<Utc>) -> usize {
+    if (now - next).num_seconds() <= MISSED_GRACE_SECS {
+        return 1;
+    }
+    match job.catch_up {
+        CatchUpPolicy::RunOnce => 1,
+        CatchUpPolicy::Skip => {
+            logger::log_info(&format!(
+                "[Scheduler] Skipping missed run of cron job {} (scheduled at {})",
+                job.id, next
+            ));
+            job_history::record_skipped(
+                job.kind.as_str(),
+                Some(&job.id),
+                &format!("missed run scheduled at {}", next.to_rfc3339()),
+            );
+            0
+        }
+        CatchUpPolicy::RunAll => {
+            let missed = cron::upcoming(
+                &job.expression,
+                &job.timezone,
+                next - chrono::Duration::seconds(1),
+                MAX_CATCH_UP_RUNS,
+            )
+            .map(|runs| runs.into_iter().filter(|t| *t <= now).count())
+            .unwrap_or(1)
+            .max(1);
             logger::log_info(&format!(
-                "[Scheduler] Scanning {} accounts for 100% quota models...",
-                accounts.len()
+                "[Scheduler] Catching up {} missed runs of cron job {}",
+                missed, job.id
             ));
+            missed
+        }
+    }
+}
 
-            let mut warmup_tasks = Vec::new();
-            let mut skipped_cooldown = 0;
+/// 执行一次 cron 任务
+fn run_cron_job(job: CronJob, handle: tauri::AppHandle) {
+    logger::log_info(&format!(
+        "[Scheduler] Running cron job {} ({:?})",
+        job.id, job.kind
+    ));
+    let task = tokio::spawn(async move {
+        apply_jitter().await;
+        let _slot = acquire_job_slot(cron_job_category(job.kind), job.kind.as_str()).await;
+        let tracker = JobRunTracker::start(&handle, job.kind.as_str(), Some(&job.id));
+        let result = execute_job(job.kind, job.instance_id.clone(), &handle, &tracker).await;
+        if let Err(e) = &result {
+            logger::log_warn(&format!("[Scheduler] Cron job {} failed: {}", job.id, e));
+        }
+        tracker.finish(&result);
+        let _ = handle.emit(
+            "scheduler://cron_job_finished",
+            serde_json::json!({
+                "id": job.id,
+                "kind": job.kind,
+                "success": result.is_ok(),
+                "error": result.err(),
+            }),
+        );
+    });
+    track_job(&task);
+}
+
+/// 执行一次到期的延时任务
+fn run_delayed_task(task: DelayedTask, handle: tauri::AppHandle) {
+    logger::log_info(&format!(
+        "[Scheduler] Running delayed task {} ({})",
+        task.id,
+        task.action.as_str()
+    ));
+    let job = tokio::spawn(async move {
+        let category = match task.action {
+            DelayedAction::ConfigBackup => JobCategory::Long,
+            _ => JobCategory::Quick,
+        };
+        let _slot = acquire_job_slot(category, task.action.as_str()).await;
+        let tracker = JobRunTracker::start(&handle, task.action.as_str(), Some(&task.id));
+        let result = match task.action.clone() {
+            DelayedAction::EnableAccount { account_id } | DelayedAction::DisableAccount { account_id } => {
+                let enable = matches!(task.action, DelayedAction::EnableAccount { .. });
+                let reason = if enable { "定时任务启用" } else { "定时任务禁用" };
+                crate::commands::toggle_proxy_status(
+                    handle.clone(),
+                    handle.state::<crate::commands::proxy::ProxyServiceState>(),
+                    account_id,
+                    enable,
+                    Some(reason.to_string()),
+                )
+                .await
+                .map(|_| None)
+            }
+            DelayedAction::InstanceStart { instance_id } => {
+                execute_job(CronJobKind::InstanceStart, Some(instance_id), &handle, &tracker).await
+            }
+            DelayedAction::InstanceStop { instance_id } => {
+                execute_job(CronJobKind::InstanceStop, Some(instance_id), &handle, &tracker).await
+            }
+            DelayedAction::QuotaRefresh => execute_job(CronJobKind::QuotaRefresh, None, &handle, &tracker).await,
+            DelayedAction::ConfigBackup => execute_job(CronJobKind::ConfigBackup, None, &handle, &tracker).await,
+        };
+        if let Err(e) = &result {
+            logger::log_warn(&format!("[Scheduler] Delayed task {} failed: {}", task.id, e));
+        }
+        tracker.finish(&result);
+        let _ = handle.emit(
+            "scheduler://delayed_task_finished",
+            serde_json::json!({
+                "task": task,
+                "success": result.is_ok(),
+                "error": result.err(),
+            }),
+        );
+    });
+    track_job(&job);
+}
 
-            // Scan each model for each account
-            for account in &accounts {
-                // Skip disabled accounts
-                if account.proxy_disabled {
+/// 启动全部已注册的周期任务 (见 `scheduled_tasks::registered_tasks`)：每个任务一个循环，
+/// 统一处理首次延迟、执行间隔、全局停止开关、单独暂停与系统唤醒后的补偿
+pub fn start_scheduler(app_handle: tauri::AppHandle) {
+    logger::log_info("[Scheduler] Started");
+    for task in scheduled_tasks::registered_tasks() {
+        let handle = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            let schedule = task.default_schedule();
+            let mut interval = time::interval_at(
+                time::Instant::now() + schedule.initial_delay,
+                schedule.interval,
+            );
+            let mut wake = subscribe_wake();
+
+            loop {
+                // 休眠期间计时器停走：唤醒后由任务决定立即补执行、重新计时或按原周期继续
+                let woke = tokio::select! {
+                    _ = interval.tick() => false,
+                    _ = wake.recv() => true,
+                };
+                if woke {
+                    match task.on_wake(&handle).await {
+                        WakeAction::Ignore => continue,
+                        WakeAction::Reschedule => {
+                            interval.reset();
+                            continue;
+                        }
+                        WakeAction::RunNow => interval.reset(),
+                    }
+                }
+                if (task.respects_halt() && jobs_halted())
+                    || (PAUSABLE_JOBS.contains(&task.id()) && job_paused(task.id()))
+                {
                     continue;
                 }
+                scheduled_tasks::run_once(task.as_ref(), &handle).await;
+            }
+        });
+    }
+}
 
-                // Get valid token
-                let Ok((token, pid)) = quota::get_valid_token_for_warmup(account).await else {
-                    continue;
-                };
+/// 智能预热：扫描各账号配额，为额度已满 (100%) 且在监控列表中的模型触发预热
+pub(crate) struct WarmupScanTask;
 
-                // Get fresh quota
-                let Ok((fresh_quota, _)) =
-                    quota::fetch_quota_with_cache(&token, &account.email, Some(&pid)).await
-                else {
-                    continue;
-                };
+#[async_trait]
+impl ScheduledTask for WarmupScanTask {
+    fn id(&self) -> &'static str {
+        "warmup"
+    }
 
-                let now_ts = Utc::now().timestamp();
+    // Scan every 10 minutes; each scan (including the first, 30s after launch) is delayed
+    // with jitter so it does not coincide with the frontend's initial quota refresh
+    fn default_schedule(&self) -> TaskSchedule {
+        TaskSchedule::every(600).after(30)
+    }
 
-                for model in fresh_quota.models {
-                    // Core logic: detect 100% quota
-                    if model.percentage == 100 {
-                        let model_to_ping = model.name.clone();
+    async fn on_wake(&self, _handle: &tauri::AppHandle) -> WakeAction {
+        if config::load_app_config().map_or(false, |c| {
+            c.catch_up_policy("warmup") == CatchUpPolicy::Skip
+        }) {
+            WakeAction::Reschedule
+        } else {
+            WakeAction::RunNow
+        }
+    }
 
-                        // Only warmup models configured by user (allowlist)
-                        if !app_config
-                            .scheduled_warmup
-                            .monitored_models
-                            .contains(&model_to_ping)
-                        {
-                            continue;
-                        }
+    /// 预热批次由后台任务执行并自行记录执行历史，扫描本身不记录
+    async fn run(&self, handle: &tauri::AppHandle) -> Result<Option<String>, String> {
+        // Load configuration
+        let Ok(app_config) = config::load_app_config() else {
+            return Ok(None);
+        };
+
+        if !app_config.scheduled_warmup.enabled || app_config.job_paused("warmup") {
+            return Ok(None);
+        }
+        apply_jitter().await;
+        let rate_limited = rate_limited_accounts(handle).await;
+
+        // Get all accounts (no longer filtering by level)
+        let Ok(accounts) = account::list_accounts() else {
+            return Ok(None);
+        };
+
+        if accounts.is_empty() {
+            return Ok(None);
+        }
+
+        logger::log_info(&format!(
+            "[Scheduler] Scanning {} accounts for 100% quota models...",
+            accounts.len()
+        ));
 
-                        // Use mapped name as key
-                        let history_key = format!("{}:{}:100", account.email, model_to_ping);
-
-                        // Check cooldown: do not repeat warmup within 4 hours
-                        {
-                            let history = WARMUP_HISTORY.lock().unwrap();
-                            if let Some(&last_warmup_ts) = history.get(&history_key) {
-                                let cooldown_seconds = 14400;
-                                if now_ts - last_warmup_ts < cooldown_seconds {
-                                    skipped_cooldown += 1;
-                                    continue;
-                                }
+        let mut warmup_tasks = Vec::new();
+        let mut skipped_cooldown = 0;
+
+        // Scan each model for each account
+        for account in &accounts {
+            if jobs_halted() {
+                break;
+            }
+            // Skip disabled accounts
+            if account.proxy_disabled {
+                continue;
+            }
+            // 连续拉取失败的账号按退避时间暂停轮询，反代限流冷却中的账号也不查询
+            if quota::fetch_backoff_remaining(&account.email).is_some()
+                || rate_limited.contains(&account.id)
+            {
+                continue;
+            }
+
+            // Get valid token
+            let Ok((token, pid)) = quota::get_valid_token_for_warmup(account).await else {
+                continue;
+            };
+
+            // Get fresh quota
+            let Ok((fresh_quota, _)) =
+                quota::fetch_quota_with_cache(&token, &account.email, Some(&pid)).await
+            else {
+                continue;
+            };
+
+            let now_ts = Utc::now().timestamp();
+
+            for model in fresh_quota.models {
+                // Core logic: detect 100% quota
+                if model.percentage == 100 {
+                    let model_to_ping = model.name.clone();
+
+                    // Only warmup models configured by user (allowlist)
+                    if !app_config
+                        .scheduled_warmup
+                        .monitored_models
+                        .contains(&model_to_ping)
+                    {
+                        continue;
+                    }
+
+                    // Use mapped name as key
+                    let history_key = format!("{}:{}:100", account.email, model_to_ping);
+
+                    // Check cooldown: do not repeat warmup within 4 hours
+                    {
+                        let history = WARMUP_HISTORY.lock().unwrap();
+                        if let Some(&last_warmup_ts) = history.get(&history_key) {
+                            let cooldown_seconds = 14400;
+                            if now_ts - last_warmup_ts < cooldown_seconds {
+                                skipped_cooldown += 1;
+                                continue;
                             }
                         }
+                    }
 
-                        warmup_tasks.push((
-                            account.email.clone(),
-                            model_to_ping.clone(),
-                            token.clone(),
-                            pid.clone(),
-                            model.percentage,
-                            history_key.clone(),
-                        ));
+                    warmup_tasks.push((
+                        account.email.clone(),
+                        model_to_ping.clone(),
+                        token.clone(),
+                        pid.clone(),
+                        model.percentage,
+                        history_key.clone(),
+                    ));
 
+                    logger::log_info(&format!(
+                        "[Scheduler] ✓ Scheduled warmup: {} @ {} (quota at 100%)",
+                        model_to_ping, account.email
+                    ));
+                } else if model.percentage < 100 {
+                    // Quota not full, clear history, need to map name first
+                    let model_to_ping = model.name.clone();
+                    let history_key = format!("{}:{}:100", account.email, model_to_ping);
+
+                    let mut history = WARMUP_HISTORY.lock().unwrap();
+                    if history.remove(&history_key).is_some() {
+                        save_warmup_history(&history);
                         logger::log_info(&format!(
-                            "[Scheduler] ✓ Scheduled warmup: {} @ {} (quota at 100%)",
-                            model_to_ping, account.email
+                            "[Scheduler] Cleared history for {} @ {} (quota: {}%)",
+                            model_to_ping, account.email, model.percentage
                         ));
-                    } else if model.percentage < 100 {
-                        // Quota not full, clear history, need to map name first
-                        let model_to_ping = model.name.clone();
-                        let history_key = format!("{}:{}:100", account.email, model_to_ping);
-
-                        let mut history = WARMUP_HISTORY.lock().unwrap();
-                        if history.remove(&history_key).is_some() {
-                            save_warmup_history(&history);
-                            logger::log_info(&format!(
-                                "[Scheduler] Cleared history for {} @ {} (quota: {}%)",
-                                model_to_ping, account.email, model.percentage
-                            ));
-                        }
                     }
                 }
             }
+        }
 
-            // Execute warmup tasks
-            if !warmup_tasks.is_empty() {
-                let total = warmup_tasks.len();
-                if skipped_cooldown > 0 {
-                    logger::log_info(&format!(
-                        "[Scheduler] Skipped {} models in cooldown, will warmup {}",
-                        skipped_cooldown, total
-                    ));
-                }
+        if jobs_halted() {
+            return Ok(None);
+        }
+
+        // Execute warmup tasks
+        if !warmup_tasks.is_empty() {
+            let total = warmup_tasks.len();
+            if skipped_cooldown > 0 {
                 logger::log_info(&format!(
-                    "[Scheduler] 🔥 Triggering {} warmup tasks...",
-                    total
+                    "[Scheduler] Skipped {} models in cooldown, will warmup {}",
+                    skipped_cooldown, total
                 ));
+            }
+            logger::log_info(&format!(
+                "[Scheduler] 🔥 Triggering {} warmup tasks...",
+                total
+            ));
 
-                let handle_for_warmup = app_handle.clone();
-                tokio::spawn(async move {
-                    let mut success = 0;
-                    let batch_size = 3;
-                    let now_ts = chrono::Utc::now().timestamp();
-
-                    for (batch_idx, batch) in warmup_tasks.chunks(batch_size).enumerate() {
-                        let mut handles = Vec::new();
-
-                        for (task_idx, (email, model, token, pid, pct, history_key)) in
-                            batch.iter().enumerate()
-                        {
-                            let global_idx = batch_idx * batch_size + task_idx + 1;
-                            let email = email.clone();
-                            let model = model.clone();
-                            let token = token.clone();
-                            let pid = pid.clone();
-                            let pct = *pct;
-                            let history_key = history_key.clone();
-
-                            logger::log_info(&format!(
-                                "[Warmup {}/{}] {} @ {} ({}%)",
-                                global_idx, total, model, email, pct
-                            ));
-
-                            let handle = tokio::spawn(async move {
-                                let result =
-                                    quota::warmup_model_directly(&token, &model, &pid, &email, pct)
-                                        .await;
-                                (result, history_key)
-                            });
-                            handles.push(handle);
-                        }
+            let handle_for_warmup = handle.clone();
+            let job = tokio::spawn(async move {
+                let _slot = acquire_job_slot(JobCategory::Long, "warmup").await;
+                let tracker = JobRunTracker::start(&handle_for_warmup, "warmup", None);
+                let mut success = 0;
+                let mut done = 0;
+                let batch_size = 3;
+                let now_ts = chrono::Utc::now().timestamp();
 
-                        for handle in handles {
-                            match handle.await {
-                                Ok((true, history_key)) => {
-                                    success += 1;
-                                    record_warmup_history(&history_key, now_ts);
-                                }
-                                _ => {}
-                            }
-                        }
+                for (batch_idx, batch) in warmup_tasks.chunks(batch_size).enumerate() {
+                    let mut handles = Vec::new();
+
+                    for (task_idx, (email, model, token, pid, pct, history_key)) in
+                        batch.iter().enumerate()
+                    {
+                        let global_idx = batch_idx * batch_size + task_idx + 1;
+                        let email = email.clone();
+                        let model = model.clone();
+                        let token = token.clone();
+                        let pid = pid.clone();
+                        let pct = *pct;
+                        let history_key = history_key.clone();
+
+                        logger::log_info(&format!(
+                            "[Warmup {}/{}] {} @ {} ({}%)",
+                            global_idx, total, model, email, pct
+                        ));
+
+                        let handle = tokio::spawn(async move {
+                            let result =
+                                quota::warmup_model_directly(&token, &model, &pid, &email, pct)
+                                    .await;
+                            (result, history_key)
+                        });
+                        handles.push(handle);
+                    }
 
-                        if batch_idx < (warmup_tasks.len() + batch_size - 1) / batch_size - 1 {
-                            tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+                    for handle in handles {
+                        match handle.await {
+                            Ok((true, history_key)) => {
+                                success += 1;
+                                record_warmup_history(&history_key, now_ts);
+                            }
+                            _ => {}
                         }
+                        done += 1;
+                        tracker.progress(done, total, None);
                     }
 
-                    logger::log_info(&format!(
-                        "[Scheduler] ✅ Warmup completed: {}/{} successful",
-                        success, total
-                    ));
+                    if batch_idx < (warmup_tasks.len() + batch_size - 1) / batch_size - 1 {
+                        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+                    }
+                }
 
-                    // Refresh quota, sync to frontend
-                    tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-                    let state =
-                        handle_for_warmup.state::<crate::commands::proxy::ProxyServiceState>();
-                    let _ = crate::commands::refresh_all_quotas(state).await;
-                });
-            } else if skipped_cooldown > 0 {
                 logger::log_info(&format!(
-                    "[Scheduler] Scan completed, all 100% models are in cooldown, skipped {}",
-                    skipped_cooldown
+                    "[Scheduler] ✅ Warmup completed: {}/{} successful",
+                    success, total
                 ));
-            } else {
-                logger::log_info(
-                    "[Scheduler] Scan completed, no models with 100% quota need warmup",
-                );
-            }
+                let summary = format!("{}/{} models warmed up", success, total);
+                let result = if success == 0 {
+                    Err(summary)
+                } else {
+                    Ok(Some(summary))
+                };
+                tracker.finish(&result);
 
-            // Refresh frontend display after scan (ensure UI has latest data)
-            let handle_inner = app_handle.clone();
-            tokio::spawn(async move {
-                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-                let state = handle_inner.state::<crate::commands::proxy::ProxyServiceState>();
-                let _ = crate::commands::refresh_all_quotas(state).await;
-                logger::log_info("[Scheduler] Quota data synced to frontend");
+                // Refresh quota, sync to frontend
+                tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+                refresh_all_quietly(&handle_for_warmup).await;
             });
+            track_job(&job);
+        } else if skipped_cooldown > 0 {
+            logger::log_info(&format!(
+                "[Scheduler] Scan completed, all 100% models are in cooldown, skipped {}",
+                skipped_cooldown
+            ));
+        } else {
+            logger::log_info("[Scheduler] Scan completed, no models with 100% quota need warmup");
+        }
 
-            // Regularly clean up history (keep last 24 hours)
-            {
-                let now_ts = Utc::now().timestamp();
-                let mut history = WARMUP_HISTORY.lock().unwrap();
-                let cutoff = now_ts - 86400; // 24 hours ago
-                history.retain(|_, &mut ts| ts > cutoff);
-            }
+        // Refresh frontend display after scan (ensure UI has latest data)
+        let handle_inner = handle.clone();
+        let job = tokio::spawn(async move {
+            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+            refresh_all_quietly(&handle_inner).await;
+            logger::log_info("[Scheduler] Quota data synced to frontend");
+        });
+        track_job(&job);
+
+        // Regularly clean up history (keep last 24 hours)
+        {
+            let now_ts = Utc::now().timestamp();
+            let mut history = WARMUP_HISTORY.lock().unwrap();
+            let cutoff = now_ts - 86400; // 24 hours ago
+            history.retain(|_, &mut ts| ts > cutoff);
         }
-    });
+        Ok(None)
+    }
+}
 
-    // ============== 实例状态刷新后台任务 ==============
-    // 定期刷新所有实例的运行状态和启动参数
-    tauri::async_runtime::spawn(async move {
-        logger::log_info("[Instance Monitor] Background instance status refresh started");
+/// 按账号/标签的配额刷新节奏：配置了刷新规则时由后端按各账号自己的间隔刷新
+/// （前端不再按全局间隔刷新全部账号）
+#[derive(Default)]
+pub(crate) struct QuotaRefreshTask {
+    /// 上次尝试刷新的时间，避免刷新失败的账号每分钟重试
+    last_attempt: Mutex<HashMap<String, i64>>,
+}
 
-        // 每 5 秒刷新一次实例状态
-        let mut interval = time::interval(Duration::from_secs(5));
+#[async_trait]
+impl ScheduledTask for QuotaRefreshTask {
+    fn id(&self) -> &'static str {
+        "quota_refresh"
+    }
 
-        loop {
-            interval.tick().await;
+    fn default_schedule(&self) -> TaskSchedule {
+        TaskSchedule::every(60).after(60)
+    }
 
-            // 获取所有实例
-            let instances = match crate::modules::instance::list_instances() {
-                Ok(list) => list,
-                Err(_) => continue,
-            };
+    /// 唤醒后按 Skip 策略放弃休眠期间到期的刷新，各账号从现在起重新计时；
+    /// 其他策略下一轮检查时自然补上到期的账号
+    async fn on_wake(&self, _handle: &tauri::AppHandle) -> WakeAction {
+        if config::load_app_config().map_or(false, |c| {
+            c.catch_up_policy("quota_refresh") == CatchUpPolicy::Skip
+        }) {
+            let now = Utc::now().timestamp();
+            if let Ok(accounts) = account::list_accounts() {
+                let mut last_attempt = self.last_attempt.lock().unwrap_or_else(|e| e.into_inner());
+                for a in accounts {
+                    last_attempt.insert(a.id, now);
+                }
+            }
+            job_history::record_skipped(
+                "quota_refresh",
+                None,
+                "missed while the system was asleep",
+            );
+        }
+        WakeAction::Ignore
+    }
 
-            for mut instance in instances {
-                let cached_pid = instance.last_root_pid;
-
-                // 检测实例运行状态
-                let (is_running, new_pid, new_args) = if instance.is_default {
-                    let running = crate::modules::process::is_default_instance_running();
-                    if running {
-                        if let Some((pid, args)) =
-                            crate::modules::process::get_instance_root_pid_and_args(
-                                &instance.user_data_dir,
-                                true,
-                                cached_pid,
-                            )
-                        {
-                            (true, Some(pid), Some(args))
-                        } else {
-                            (true, None, None)
-                        }
-                    } else {
-                        (false, None, None)
-                    }
-                } else {
-                    // 非默认实例：优先使用缓存 PID 检测
-                    if let Some(pid) = cached_pid {
-                        if crate::modules::process::is_pid_valid_instance_root(
-                            pid,
-                            &instance.user_data_dir,
-                            false,
-                        ) {
-                            if let Some((_, args)) =
-                                crate::modules::process::get_instance_root_pid_and_args(
-                                    &instance.user_data_dir,
-                                    false,
-                                    Some(pid),
-                                )
-                            {
-                                (true, Some(pid), Some(args))
-                            } else {
-                                (true, Some(pid), None)
-                            }
-                        } else {
-                            // 缓存 PID 无效，重新检测
-                            let running = crate::modules::process::is_instance_running(
-                                &instance.user_data_dir,
-                            );
-                            if running {
-                                if let Some((pid, args)) =
-                                    crate::modules::process::get_instance_root_pid_and_args(
-                                        &instance.user_data_dir,
-                                        false,
-                                        None,
-                                    )
-                                {
-                                    (true, Some(pid), Some(args))
-                                } else {
-                                    (true, None, None)
-                                }
-                            } else {
-                                (false, None, None)
-                            }
-                        }
-                    } else {
-                        let running =
-                            crate::modules::process::is_instance_running(&instance.user_data_dir);
-                        if running {
-                            if let Some((pid, args)) =
-                                crate::modules::process::get_instance_root_pid_and_args(
-                                    &instance.user_data_dir,
-                                    false,
-                                    None,
-                                )
-                            {
-                                (true, Some(pid), Some(args))
-                            } else {
-                                (true, None, None)
-                            }
-                        } else {
-                            (false, None, None)
-                        }
+    async fn run(&self, handle: &tauri::AppHandle) -> Result<Option<String>, String> {
+        let Ok(app_config) = config::load_app_config() else {
+            return Ok(None);
+        };
+        if !app_config.auto_refresh || app_config.quota_refresh_rules.is_empty() {
+            return Ok(None);
+        }
+        let Ok(accounts) = tokio::task::spawn_blocking(account::list_accounts)
+            .await
+            .unwrap_or_else(|e| Err(e.to_string()))
+        else {
+            return Ok(None);
+        };
+        let rate_limited = rate_limited_accounts(handle).await;
+
+        let now = Utc::now().timestamp();
+        let due: Vec<Account> = {
+            let mut last_attempt = self.last_attempt.lock().unwrap_or_else(|e| e.into_inner());
+            let due: Vec<Account> = accounts
+                .into_iter()
+                .filter(|a| {
+                    let minutes = app_config.quota_refresh_interval_for(&a.id, &a.tags);
+                    if minutes <= 0
+                        || quota::fetch_backoff_remaining(&a.email).is_some()
+                        || rate_limited.contains(&a.id)
+                    {
+                        return false;
                     }
-                };
+                    let last_updated = a.quota.as_ref().map_or(0, |q| q.last_updated);
+                    let last = last_updated.max(last_attempt.get(&a.id).copied().unwrap_or(0));
+                    now - last >= minutes * 60
+                })
+                .collect();
+            for a in &due {
+                last_attempt.insert(a.id.clone(), now);
+            }
+            due
+        };
+        if due.is_empty() {
+            return Ok(None);
+        }
+
+        logger::log_info(&format!(
+            "[Scheduler] Refreshing quota of {} accounts due by their refresh interval",
+            due.len()
+        ));
+        let ids: Vec<String> = due.iter().map(|a| a.id.clone()).collect();
+        let handle = handle.clone();
+        // 刷新在后台任务中排队执行并自行记录执行历史，不阻塞下一轮检查
+        let job = tokio::spawn(async move {
+            let _slot = acquire_job_slot(JobCategory::Quick, "quota_refresh").await;
+            let tracker = JobRunTracker::start(&handle, "quota_refresh", None);
+            // 随机延迟按账号分摊：同时到期的账号在窗口内错开请求
+            let refreshed =
+                account::refresh_quotas_spread(due, jitter_window(), |done, total, email| {
+                    tracker.progress(done, total, Some(email))
+                })
+                .await;
+            let stats = match refreshed {
+                Ok(stats) => stats,
+                Err(e) => {
+                    logger::log_warn(&format!("[Scheduler] Quota refresh failed: {}", e));
+                    tracker.finish(&Err(e));
+                    return;
+                }
+            };
+            tracker.finish(&Ok(Some(format!(
+                "{}/{} accounts refreshed",
+                stats.success, stats.total
+            ))));
+            finish_quota_refresh(&handle, ids, stats.failed).await;
+        });
+        track_job(&job);
+        Ok(None)
+    }
+}
 
-                // 更新实例配置（只在 PID 变化时保存）
-                let mut need_save = false;
+/// Cron 定时任务：按配置中的 cron 表达式执行配额刷新、配置备份与实例启停；
+/// 每轮重新读取配置，运行时修改即时生效
+#[derive(Default)]
+pub(crate) struct CronTask {
+    /// 任务 id -> (表达式与时区, 下次执行时间)；表达式无效或不会再触发时为 None
+    next_runs: Mutex<HashMap<String, (String, Option<DateTime<Utc>>)>>,
+}
 
-                if is_running {
-                    if new_pid != instance.last_root_pid {
-                        instance.last_root_pid = new_pid;
-                        need_save = true;
-                        logger::log_info(&format!(
-                            "[Instance Monitor] {} detected running, PID: {:?}",
-                            instance.name, new_pid
-                        ));
-                    }
-                    if let Some(args) = new_args {
-                        let args_str = args.join(" ");
-                        if !args_str.contains("--type=") {
-                            if instance.last_launch_args.as_ref() != Some(&args) {
-                                instance.last_launch_args = Some(args);
-                                need_save = true;
-                            }
+#[async_trait]
+impl ScheduledTask for CronTask {
+    fn id(&self) -> &'static str {
+        "cron"
+    }
+
+    fn default_schedule(&self) -> TaskSchedule {
+        TaskSchedule::every(20)
+    }
+
+    /// 全局停止期间仍按表达式推进下次执行时间，只跳过执行，恢复后不会补执行
+    fn respects_halt(&self) -> bool {
+        false
+    }
+
+    async fn run(&self, handle: &tauri::AppHandle) -> Result<Option<String>, String> {
+        let Ok(app_config) = config::load_app_config() else {
+            return Ok(None);
+        };
+        let now = Utc::now();
+        let mut next_runs = self.next_runs.lock().unwrap_or_else(|e| e.into_inner());
+        next_runs.retain(|id, _| {
+            app_config
+                .cron_jobs
+                .iter()
+                .any(|job| job.enabled && &job.id == id)
+        });
+
+        for job in app_config.cron_jobs.iter().filter(|job| job.enabled) {
+            let signature = format!("{}|{}", job.expression, job.timezone);
+            let scheduled = next_runs
+                .get(&job.id)
+                .filter(|(sig, _)| *sig == signature)
+                .map(|(_, next)| *next);
+            match scheduled {
+                Some(None) => continue,
+                Some(Some(next)) if next > now => continue,
+                Some(Some(next)) => {
+                    // 所属任务被暂停时跳过本次执行，仍按表达式计算下一次
+                    if !jobs_halted() && !app_config.job_paused(job.kind.pause_group()) {
+                        for _ in 0..catch_up_runs(job, next, now) {
+                            run_cron_job(job.clone(), handle.clone());
                         }
                     }
-                } else {
-                    // 实例未运行，清除缓存的 PID
-                    if instance.last_root_pid.is_some() {
-                        instance.last_root_pid = None;
-                        need_save = true;
-                    }
-                }
-
-                if need_save {
-                    let _ = crate::modules::instance::save_instance(&instance);
                 }
+                // 新任务或表达式被修改：只计算下次执行时间
+                None => {}
             }
+
+            let next = cron::CronSchedule::parse(&job.expression)
+                .and_then(|schedule| {
+                    cron::CronTimezone::parse(&job.timezone).map(|tz| schedule.next_after(now, tz))
+                })
+                .unwrap_or_else(|e| {
+                    logger::log_warn(&format!(
+                        "[Scheduler] Cron job {} is invalid: {}",
+                        job.id, e
+                    ));
+                    None
+                });
+            next_runs.insert(job.id.clone(), (signature, next));
         }
-    });
+        Ok(None)
+    }
+}
+
+/// 一次性延时任务：到期即执行并从持久化列表删除；全局停止期间到期的任务保留到恢复后执行
+pub(crate) struct DelayedTaskRunner;
+
+#[async_trait]
+impl ScheduledTask for DelayedTaskRunner {
+    fn id(&self) -> &'static str {
+        "delayed_tasks"
+    }
+
+    fn default_schedule(&self) -> TaskSchedule {
+        TaskSchedule::every(15)
+    }
+
+    async fn run(&self, handle: &tauri::AppHandle) -> Result<Option<String>, String> {
+        for task in delayed_tasks::take_due(Utc::now().timestamp()) {
+            run_delayed_task(task, handle.clone());
+        }
+        Ok(None)
+    }
+}
+
+/// 系统休眠检测：两次采样之间墙上时钟或单调时钟多走了很久，说明系统刚从休眠/挂起中恢复，
+/// 通知各定时任务补偿
+pub(crate) struct WakeDetectionTask {
+    /// 上次采样的 (墙上时钟, 单调时钟)
+    last_sample: Mutex<(DateTime<Utc>, std::time::Instant)>,
+}
+
+impl Default for WakeDetectionTask {
+    fn default() -> Self {
+        Self {
+            last_sample: Mutex::new((Utc::now(), std::time::Instant::now())),
+        }
+    }
+}
+
+#[async_trait]
+impl ScheduledTask for WakeDetectionTask {
+    fn id(&self) -> &'static str {
+        "wake_detection"
+    }
+
+    fn default_schedule(&self) -> TaskSchedule {
+        TaskSchedule::every(WAKE_CHECK_SECS as u64)
+    }
+
+    /// 停止期间也要持续采样，否则恢复时会被误判为刚从休眠中醒来
+    fn respects_halt(&self) -> bool {
+        false
+    }
+
+    async fn run(&self, handle: &tauri::AppHandle) -> Result<Option<String>, String> {
+        let slept = {
+            let mut last = self.last_sample.lock().unwrap_or_else(|e| e.into_inner());
+            let wall_elapsed = (Utc::now() - last.0).num_seconds();
+            let mono_elapsed = last.1.elapsed().as_secs() as i64;
+            *last = (Utc::now(), std::time::Instant::now());
+            wall_elapsed.max(mono_elapsed) - WAKE_CHECK_SECS
+        };
+
+        if slept >= SLEEP_GAP_SECS {
+            logger::log_info(&format!(
+                "[Scheduler] System resumed after about {}s of sleep, catching up missed jobs",
+                slept
+            ));
+            let _ = WAKE_EVENTS.send(slept);
+            let _ = handle.emit(
+                "system://resumed",
+                serde_json::json!({ "slept_secs": slept }),
+            );
+        }
+        Ok(None)
+    }
 }
 
 /// Trigger immediate smart warmup check for a single account
@@ -0,0 +1,306 @@
+//! Command line self-update for headless / service installs.
+//!
+//! `antigravity_tools self-update [--check] [--channel stable|beta]` downloads the signed
+//! artifact from the same updater manifest the GUI uses, replaces the installed binary
+//! (or `.app` bundle / AppImage) in place and starts the new binary once to make sure it
+//! runs. If that probe fails the previous version is restored; a successful update keeps
+//! it as `<name>.old` so `self-update --rollback` can go back.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::modules::update_checker::{self, UpdateChannel};
+use crate::modules::{logger, login_item};
+
+const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+const PROBE_TIMEOUT: Duration = Duration::from_secs(20);
+
+struct SelfUpdateArgs {
+    check_only: bool,
+    rollback: bool,
+    channel: Option<UpdateChannel>,
+}
+
+fn parse_args(args: &[String]) -> Result<SelfUpdateArgs, String> {
+    let mut parsed = SelfUpdateArgs {
+        check_only: false,
+        rollback: false,
+        channel: None,
+    };
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--check" => parsed.check_only = true,
+            "--rollback" => parsed.rollback = true,
+            "--channel" => {
+                parsed.channel = match iter.next().map(|s| s.as_str()) {
+                    Some("stable") => Some(UpdateChannel::Stable),
+                    Some("beta") => Some(UpdateChannel::Beta),
+                    other => return Err(format!("Unknown update channel: {:?}", other)),
+                }
+            }
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+    Ok(parsed)
+}
+
+/// Handle command line subcommands that run without the GUI.
+/// Returns the process exit code when one was handled.
+pub fn handle_cli() -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(|s| s.as_str()) {
+        Some("--version") => {
+            println!("{}", CURRENT_VERSION);
+            Some(0)
+        }
+        Some("self-update") => {
+            let result = parse_args(&args[1..])
+                .and_then(|parsed| tauri::async_runtime::block_on(run(parsed)));
+            match result {
+                Ok(message) => {
+                    println!("{}", message);
+                    Some(0)
+                }
+                Err(e) => {
+                    logger::log_error(&format!("Self-update failed: {}", e));
+                    eprintln!("self-update failed: {}", e);
+                    Some(1)
+                }
+            }
+        }
+        _ => None,
+    }
+}
+
+async fn run(args: SelfUpdateArgs) -> Result<String, String> {
+    let target = install_target()?;
+    if args.rollback {
+        rollback(&target)?;
+        return Ok(format!("Restored previous version at {}", target.display()));
+    }
+
+    let channel = match args.channel {
+        Some(channel) => channel,
+        None => update_checker::load_update_settings()
+            .ok()
+            .and_then(|s| s.channel)
+            .unwrap_or_default(),
+    };
+    let manifest = update_checker::fetch_update_manifest(channel).await?;
+    if !manifest.is_newer() {
+        return Ok(format!("Already up to date ({})", CURRENT_VERSION));
+    }
+    if args.check_only {
+        return Ok(format!(
+            "Update available: {} -> {}\n{}",
+            CURRENT_VERSION,
+            manifest.version(),
+            manifest.notes
+        )
+        .trim_end()
+        .to_string());
+    }
+
+    let artifact = manifest
+        .artifact_for_current_platform()
+        .ok_or_else(|| "No update artifact for this platform".to_string())?;
+    println!("Downloading {} ...", manifest.version());
+    let bytes = update_checker::download_verified(artifact).await?;
+
+    let staged = stage(&target, &artifact.url, &bytes)?;
+    install(&target, &staged, manifest.version())?;
+    logger::log_info(&format!(
+        "Self-update installed {} -> {} at {:?}",
+        CURRENT_VERSION,
+        manifest.version(),
+        target
+    ));
+    Ok(format!(
+        "Updated {} -> {}. Restart the service to use the new version.",
+        CURRENT_VERSION,
+        manifest.version()
+    ))
+}
+
+/// What gets replaced: the `.app` bundle on macOS, the AppImage or binary elsewhere
+fn install_target() -> Result<PathBuf, String> {
+    let exe = login_item::executable_path()?;
+    if cfg!(target_os = "macos") {
+        if let Some(bundle) = exe
+            .ancestors()
+            .find(|p| p.extension().is_some_and(|e| e == "app"))
+        {
+            return Ok(bundle.to_path_buf());
+        }
+    }
+    Ok(exe)
+}
+
+fn sibling(target: &Path, suffix: &str) -> PathBuf {
+    let name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    target.with_file_name(format!("{}{}", name, suffix))
+}
+
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else if path.exists() {
+        std::fs::remove_file(path)
+    } else {
+        Ok(())
+    }
+}
+
+/// Unpack the artifact next to the target so the final swap is a rename
+fn stage(target: &Path, url: &str, bytes: &[u8]) -> Result<PathBuf, String> {
+    let staging = sibling(target, ".update");
+    remove_path(&staging).map_err(|e| format!("Failed to clean staging dir: {}", e))?;
+
+    if url.ends_with(".tar.gz") {
+        std::fs::create_dir_all(&staging)
+            .map_err(|e| format!("Failed to create staging dir: {}", e))?;
+        tar::Archive::new(flate2::read::GzDecoder::new(bytes))
+            .unpack(&staging)
+            .map_err(|e| format!("Failed to unpack update: {}", e))?;
+        // The archive holds a single entry: the bundle or the AppImage
+        let mut entries: Vec<PathBuf> = std::fs::read_dir(&staging)
+            .map_err(|e| format!("Failed to read staging dir: {}", e))?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .collect();
+        if entries.len() != 1 {
+            return Err(format!(
+                "Unexpected update archive layout ({} entries)",
+                entries.len()
+            ));
+        }
+        return Ok(entries.remove(0));
+    }
+
+    if cfg!(target_os = "windows") || url.ends_with(".zip") || url.ends_with(".msi") {
+        return Err(format!(
+            "Update artifact {} is an installer and cannot be applied in place",
+            url
+        ));
+    }
+
+    std::fs::write(&staging, bytes).map_err(|e| format!("Failed to write update: {}", e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&staging, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Failed to set permissions: {}", e))?;
+    }
+    Ok(staging)
+}
+
+/// Swap the staged version in, probe it and restore the old one on failure
+fn install(target: &Path, staged: &Path, expected_version: &str) -> Result<(), String> {
+    let backup = sibling(target, ".old");
+    remove_path(&backup).map_err(|e| format!("Failed to remove old backup: {}", e))?;
+    std::fs::rename(target, &backup)
+        .map_err(|e| format!("Failed to back up current version: {}", e))?;
+
+    let swapped = std::fs::rename(staged, target)
+        .map_err(|e| format!("Failed to install update: {}", e))
+        .and_then(|_| probe(target, expected_version));
+    // Leftover staging dir of an archive
+    let _ = remove_path(&sibling(target, ".update"));
+
+    if let Err(e) = swapped {
+        let _ = remove_path(target);
+        std::fs::rename(&backup, target).map_err(|restore| {
+            format!(
+                "{}; restoring the previous version also failed: {} (backup at {})",
+                e,
+                restore,
+                backup.display()
+            )
+        })?;
+        return Err(format!("{}; previous version restored", e));
+    }
+    Ok(())
+}
+
+/// Start the new binary with `--version` and check it reports the expected version
+fn probe(target: &Path, expected_version: &str) -> Result<(), String> {
+    let exe = if target.is_dir() {
+        login_item::executable_path()?
+    } else {
+        target.to_path_buf()
+    };
+    let mut child = Command::new(&exe)
+        .arg("--version")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("New version failed to start: {}", e))?;
+
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if started.elapsed() > PROBE_TIMEOUT => {
+                let _ = child.kill();
+                return Err("New version did not exit in time".to_string());
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(200)),
+            Err(e) => return Err(format!("New version failed to start: {}", e)),
+        }
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("New version failed to start: {}", e))?;
+    let reported = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || reported != expected_version {
+        return Err(format!(
+            "New version failed the start check (exit: {}, version: {:?})",
+            output.status, reported
+        ));
+    }
+    Ok(())
+}
+
+/// Restore the version kept by the last successful update
+fn rollback(target: &Path) -> Result<(), String> {
+    let backup = sibling(target, ".old");
+    if !backup.exists() {
+        return Err(format!("No previous version found at {}", backup.display()));
+    }
+    let failed = sibling(target, ".failed");
+    remove_path(&failed).map_err(|e| format!("Failed to clean up: {}", e))?;
+    std::fs::rename(target, &failed)
+        .map_err(|e| format!("Failed to move current version: {}", e))?;
+    if let Err(e) = std::fs::rename(&backup, target) {
+        let _ = std::fs::rename(&failed, target);
+        return Err(format!("Failed to restore previous version: {}", e));
+    }
+    let _ = remove_path(&failed);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_install_restores_on_failed_probe() {
+        let dir = std::env::temp_dir().join(format!("ag-self-update-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("app");
+        let staged = dir.join("app.update");
+        std::fs::write(&target, b"old").unwrap();
+        std::fs::write(&staged, b"new").unwrap();
+
+        // The staged file is not an executable, so the probe cannot start it and fails
+        assert!(install(&target, &staged, "0.0.0-never").is_err());
+        assert_eq!(std::fs::read(&target).unwrap(), b"old");
+        assert!(!sibling(&target, ".old").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
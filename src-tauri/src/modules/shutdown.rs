@@ -0,0 +1,116 @@
+//! 应用退出编排
+//!
+//! 退出顺序：排空反代 -> 停止 MITM -> 刷新日志/统计写入 -> 持久化调度器状态 -> (可选) 关闭本次启动的实例
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::Manager;
+
+use crate::modules::{config, instance, logger, process, proxy_db, scheduler, token_stats};
+
+/// 是否已经开始退出流程（防止重复执行 / 再次拦截退出事件）
+static SHUTDOWN_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// 后台数据库写入的最长等待时间
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 退出流程结果（写入日志，便于排查退出时的数据丢失问题）
+#[derive(Debug, Default, Serialize)]
+pub struct ShutdownReport {
+    /// 排空超时后被强制断开的反代连接数
+    pub proxy_connections_aborted: usize,
+    /// 超时未完成的后台数据库写入
+    pub pending_writes_abandoned: usize,
+    pub stopped_instances: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// 标记退出流程开始；返回 false 表示已经在退出中
+pub fn begin() -> bool {
+    !SHUTDOWN_STARTED.swap(true, Ordering::SeqCst)
+}
+
+/// 按顺序执行退出清理
+pub async fn run(app: &tauri::AppHandle) -> ShutdownReport {
+    let mut report = ShutdownReport::default();
+    let shutdown_config = config::load_app_config()
+        .map(|c| c.shutdown)
+        .unwrap_or_default();
+
+    logger::log_info("Graceful shutdown started");
+
+    // 1. 排空反代：停止接收新连接，等待进行中的请求完成
+    if let Some(state) = app.try_state::<crate::commands::proxy::ProxyServiceState>() {
+        let instance = state.instance.write().await.take();
+        if let Some(instance) = instance {
+            let token_manager = instance.token_manager.clone();
+            let remaining = instance
+                .axum_server
+                .drain(Duration::from_secs(shutdown_config.drain_timeout_secs))
+                .await;
+            if remaining > 0 {
+                logger::log_warn(&format!(
+                    "Proxy drain timed out, force-closed {} connections",
+                    remaining
+                ));
+            }
+            let _ = instance.server_handle.await;
+            report.proxy_connections_aborted = remaining;
+            if let Err(e) = crate::proxy::warm_state::save(&token_manager) {
+                report.errors.push(format!("proxy warm state: {}", e));
+            }
+        }
+    }
+
+    // 2. 停止 MITM 代理，释放端口
+    if let Some(state) = app.try_state::<crate::mitm::MitmServiceState>() {
+        let mut lock = state.instance.write().await;
+        if lock.is_some() {
+            crate::mitm::server::stop_mitm_service(&mut lock).await;
+        }
+    }
+
+    // 3. 刷新日志与 token 统计
+    report.pending_writes_abandoned =
+        crate::proxy::monitor::wait_for_pending_writes(FLUSH_TIMEOUT).await;
+    if report.pending_writes_abandoned > 0 {
+        logger::log_warn(&format!(
+            "{} background DB writes did not finish before exit",
+            report.pending_writes_abandoned
+        ));
+    }
+    if let Err(e) = proxy_db::checkpoint() {
+        report.errors.push(format!("proxy_db checkpoint: {}", e));
+    }
+    if let Err(e) = token_stats::checkpoint() {
+        report.errors.push(format!("token_stats checkpoint: {}", e));
+    }
+
+    // 4. 持久化调度器状态
+    scheduler::persist_state();
+
+    // 5. 可选：关闭本次由管理器启动的实例
+    if shutdown_config.stop_instances_on_exit {
+        for instance_id in process::get_launched_instance_ids() {
+            let Ok(inst) = instance::load_instance(&instance_id) else {
+                continue;
+            };
+            let running = if inst.is_default {
+                process::is_default_instance_running()
+            } else {
+                process::is_instance_running(&inst.user_data_dir)
+            };
+            if !running {
+                continue;
+            }
+            match process::close_instance(&inst.user_data_dir, 10) {
+                Ok(()) => report.stopped_instances.push(inst.id.clone()),
+                Err(e) => report.errors.push(format!("stop instance {}: {}", inst.name, e)),
+            }
+        }
+    }
+
+    logger::log_info(&format!("Graceful shutdown finished: {:?}", report));
+    report
+}
@@ -0,0 +1,167 @@
+//! Manual token budgets.
+//!
+//! Users can cap how many tokens the proxy spends on an account per day, week or month,
+//! independent of the upstream quota. Usage comes from the manager's own token stats;
+//! accounts that used up their budget are skipped by proxy account selection until the
+//! next period starts.
+
+use chrono::{Datelike, Local, NaiveTime, TimeZone};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::models::{Account, BudgetPeriod, TokenBudget};
+use crate::modules::{account, logger, token_stats};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenBudgetStatus {
+    pub account_id: String,
+    pub email: String,
+    pub budget: TokenBudget,
+    /// Tokens used since `period_start`
+    pub used_tokens: u64,
+    /// Unix seconds, local-time start of the current period
+    pub period_start: i64,
+    pub exhausted: bool,
+}
+
+/// Budget status per account id (only accounts that have a budget)
+static STATUS: Lazy<RwLock<HashMap<String, TokenBudgetStatus>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Start of the period containing `now` (days and weeks start at local midnight, weeks on Monday)
+fn period_start_at(period: BudgetPeriod, now: chrono::DateTime<Local>) -> i64 {
+    let today = now.date_naive();
+    let date = match period {
+        BudgetPeriod::Day => today,
+        BudgetPeriod::Week => {
+            today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64)
+        }
+        BudgetPeriod::Month => today.with_day(1).unwrap_or(today),
+    };
+    Local
+        .from_local_datetime(&date.and_time(NaiveTime::MIN))
+        .earliest()
+        .map_or(now.timestamp(), |t| t.timestamp())
+}
+
+fn evaluate(account: &Account, budget: &TokenBudget) -> Result<TokenBudgetStatus, String> {
+    let period_start = period_start_at(budget.period, Local::now());
+    let used_tokens = token_stats::get_account_usage_since(&account.email, period_start)?;
+    Ok(TokenBudgetStatus {
+        account_id: account.id.clone(),
+        email: account.email.clone(),
+        budget: budget.clone(),
+        used_tokens,
+        period_start,
+        exhausted: used_tokens >= budget.limit_tokens,
+    })
+}
+
+/// Re-evaluate the budget of one account (drops its status when it has no budget)
+pub fn refresh_account(account: &Account) {
+    let Some(budget) = &account.token_budget else {
+        STATUS.write().remove(&account.id);
+        return;
+    };
+    match evaluate(account, budget) {
+        Ok(status) => {
+            let was_exhausted = STATUS
+                .read()
+                .get(&account.id)
+                .map_or(false, |s| s.exhausted);
+            if status.exhausted && !was_exhausted {
+                logger::log_warn(&format!(
+                    "[Budget] {} used {} of {} tokens this {:?}, skipping it in proxy rotation",
+                    status.email, status.used_tokens, status.budget.limit_tokens, budget.period
+                ));
+            }
+            STATUS.write().insert(account.id.clone(), status);
+        }
+        Err(e) => logger::log_warn(&format!(
+            "[Budget] Failed to evaluate budget of {}: {}",
+            account.email, e
+        )),
+    }
+}
+
+/// Evaluate the budgets of all accounts; call once at startup
+pub fn init() {
+    match account::list_accounts() {
+        Ok(accounts) => {
+            for account in accounts.iter().filter(|a| a.token_budget.is_some()) {
+                refresh_account(account);
+            }
+        }
+        Err(e) => logger::log_warn(&format!("[Budget] Failed to load accounts: {}", e)),
+    }
+}
+
+/// Called after usage was recorded for an account
+pub fn on_usage(account_email: &str) {
+    let account_id = STATUS
+        .read()
+        .values()
+        .find(|s| s.email == account_email)
+        .map(|s| s.account_id.clone());
+    if let Some(account_id) = account_id {
+        if let Ok(account) = account::load_account(&account_id) {
+            refresh_account(&account);
+        }
+    }
+}
+
+/// Whether the account used up its budget for the current period
+pub fn is_exhausted(account_id: &str) -> bool {
+    let status = STATUS.read();
+    let Some(status) = status.get(account_id) else {
+        return false;
+    };
+    // An exhaustion evaluated in an earlier period no longer counts
+    status.exhausted
+        && status.period_start >= period_start_at(status.budget.period, Local::now())
+}
+
+/// Budget status of every account that has a budget
+pub fn list_status() -> Vec<TokenBudgetStatus> {
+    let mut list: Vec<TokenBudgetStatus> = STATUS.read().values().cloned().collect();
+    list.sort_by(|a, b| a.email.cmp(&b.email));
+    list
+}
+
+/// Set or clear the budget of an account
+pub fn set_budget(account_id: &str, budget: Option<TokenBudget>) -> Result<Account, String> {
+    if budget.as_ref().map_or(false, |b| b.limit_tokens == 0) {
+        return Err("limit_tokens must be greater than 0".to_string());
+    }
+    let mut account = account::load_account(account_id)?;
+    account.token_budget = budget;
+    account::save_account(&account)?;
+    refresh_account(&account);
+    Ok(account)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_period_start() {
+        // Thursday 2024-05-16 15:30 local time
+        let now = Local
+            .with_ymd_and_hms(2024, 5, 16, 15, 30, 0)
+            .earliest()
+            .unwrap();
+        let at = |y, m, d| {
+            Local
+                .with_ymd_and_hms(y, m, d, 0, 0, 0)
+                .earliest()
+                .unwrap()
+                .timestamp()
+        };
+        assert_eq!(period_start_at(BudgetPeriod::Day, now), at(2024, 5, 16));
+        assert_eq!(period_start_at(BudgetPeriod::Week, now), at(2024, 5, 13));
+        assert_eq!(period_start_at(BudgetPeriod::Month, now), at(2024, 5, 1));
+    }
+}
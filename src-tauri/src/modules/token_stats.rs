@@ -1,8 +1,12 @@
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
 use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
 
-/// Token usage statistics record
+/// Token usage statistics record (one row per proxy request)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenUsageRecord {
     pub id: i64,
@@ -12,8 +16,33 @@ pub struct TokenUsageRecord {
     pub input_tokens: u32,
     pub output_tokens: u32,
     pub total_tokens: u32,
+    pub cache_read_tokens: u32,
+    pub latency_ms: u64,
+    /// HTTP status returned to the client (0 when the outcome is unknown)
+    pub status: u16,
+    pub outcome: String,
+    pub api_key_id: Option<String>,
+    pub estimated_cost: f64,
 }
 
+/// Usage of one finished proxy request, recorded when its intent completes
+#[derive(Debug, Clone, Default)]
+pub struct RequestUsage {
+    /// Serving account; None when no account was used (rejected, cache hit)
+    pub account_email: Option<String>,
+    pub model: String,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub cache_read_tokens: u32,
+    pub latency_ms: u64,
+    pub status: u16,
+    /// Estimated spend (USD); 0 when the model has no configured price
+    pub estimated_cost: f64,
+}
+
+/// Account name recorded for requests that never reached an account
+const NO_ACCOUNT: &str = "unknown";
+
 /// Aggregated token statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenStatsAggregated {
@@ -22,6 +51,8 @@ pub struct TokenStatsAggregated {
     pub total_output_tokens: u64,
     pub total_tokens: u64,
     pub request_count: u64,
+    #[serde(default)]
+    pub estimated_cost: f64,
 }
 
 /// Per-account token statistics
@@ -32,6 +63,9 @@ pub struct AccountTokenStats {
     pub total_output_tokens: u64,
     pub total_tokens: u64,
     pub request_count: u64,
+    /// Estimated spend (USD) from the configured pricing table
+    #[serde(default)]
+    pub estimated_cost: f64,
 }
 
 /// Summary statistics
@@ -42,6 +76,32 @@ pub struct TokenStatsSummary {
     pub total_tokens: u64,
     pub total_requests: u64,
     pub unique_accounts: u64,
+    /// Requests that were in flight during a crash (tokens estimated from the request)
+    #[serde(default)]
+    pub unknown_outcome_requests: u64,
+    /// Input tokens served from the upstream prompt cache
+    #[serde(default)]
+    pub total_cache_read_tokens: u64,
+    /// Requests answered with an HTTP error status
+    #[serde(default)]
+    pub error_requests: u64,
+    #[serde(default)]
+    pub avg_latency_ms: u64,
+    /// Estimated spend (USD) from the configured pricing table
+    #[serde(default)]
+    pub estimated_cost: f64,
+}
+
+/// Per-API-key token statistics (requests authenticated with a managed client key)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyTokenStats {
+    pub api_key_id: String,
+    pub total_input_tokens: u64,
+    pub total_output_tokens: u64,
+    pub total_tokens: u64,
+    pub request_count: u64,
+    #[serde(default)]
+    pub estimated_cost: f64,
 }
 
 /// Per-model token statistics
@@ -52,6 +112,8 @@ pub struct ModelTokenStats {
     pub total_output_tokens: u64,
     pub total_tokens: u64,
     pub request_count: u64,
+    #[serde(default)]
+    pub estimated_cost: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -90,7 +152,19 @@ fn connect_db() -> Result<Connection, String> {
 /// Initialize the token stats database
 pub fn init_db() -> Result<(), String> {
     let conn = connect_db()?;
+    init_schema(&conn)
+}
+
+/// Add a column to an existing table; a column left by an earlier startup is not an error
+fn add_column(conn: &Connection, table: &str, column: &str) -> Result<(), String> {
+    match conn.execute(&format!("ALTER TABLE {} ADD COLUMN {}", table, column), []) {
+        Ok(_) => Ok(()),
+        Err(e) if e.to_string().contains("duplicate column name") => Ok(()),
+        Err(e) => Err(format!("Failed to add column to {}: {}", table, e)),
+    }
+}
 
+fn init_schema(conn: &Connection) -> Result<(), String> {
     // Create main usage table
     conn.execute(
         "CREATE TABLE IF NOT EXISTS token_usage (
@@ -134,42 +208,383 @@ pub fn init_db() -> Result<(), String> {
     )
     .map_err(|e| e.to_string())?;
 
+    // Outcome of each usage row: "completed" or "unknown" (recovered after a crash)
+    add_column(
+        conn,
+        "token_usage",
+        "outcome TEXT NOT NULL DEFAULT 'completed'",
+    )?;
+
+    // Managed client API key that made the request (NULL for the global key / unauthenticated)
+    add_column(conn, "token_usage", "api_key_id TEXT")?;
+
+    // Write-ahead usage intents for requests currently in flight
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS usage_intents (
+            id TEXT PRIMARY KEY,
+            started_at INTEGER NOT NULL,
+            account_email TEXT,
+            model TEXT NOT NULL,
+            estimated_input_tokens INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    add_column(conn, "usage_intents", "api_key_id TEXT")?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_token_api_key ON token_usage (api_key_id, timestamp)",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    // Per-request details: every proxied model request gets a row, including failed ones
+    add_column(
+        conn,
+        "token_usage",
+        "cache_read_tokens INTEGER NOT NULL DEFAULT 0",
+    )?;
+    add_column(conn, "token_usage", "latency_ms INTEGER NOT NULL DEFAULT 0")?;
+    add_column(conn, "token_usage", "status INTEGER NOT NULL DEFAULT 0")?;
+    add_column(
+        conn,
+        "token_stats_hourly",
+        "total_cache_read_tokens INTEGER NOT NULL DEFAULT 0",
+    )?;
+    add_column(
+        conn,
+        "token_stats_hourly",
+        "error_count INTEGER NOT NULL DEFAULT 0",
+    )?;
+
+    // Estimated spend, priced at request time so later pricing edits don't rewrite history
+    add_column(
+        conn,
+        "token_usage",
+        "estimated_cost REAL NOT NULL DEFAULT 0",
+    )?;
+    add_column(
+        conn,
+        "token_stats_hourly",
+        "total_cost REAL NOT NULL DEFAULT 0",
+    )?;
+
     Ok(())
 }
 
-/// Record token usage from a request
-pub fn record_usage(
-    account_email: &str,
-    model: &str,
-    input_tokens: u32,
-    output_tokens: u32,
+fn insert_usage(
+    conn: &Connection,
+    timestamp: i64,
+    usage: &RequestUsage,
+    outcome: &str,
+    api_key_id: Option<&str>,
 ) -> Result<(), String> {
-    let conn = connect_db()?;
-    let timestamp = chrono::Utc::now().timestamp();
-    let total_tokens = input_tokens + output_tokens;
+    let account_email = usage.account_email.as_deref().unwrap_or(NO_ACCOUNT);
+    let total_tokens = usage.input_tokens + usage.output_tokens;
+    let error_count = u32::from(usage.status >= 400);
 
     // Insert into raw usage table
     conn.execute(
-        "INSERT INTO token_usage (timestamp, account_email, model, input_tokens, output_tokens, total_tokens)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        params![timestamp, account_email, model, input_tokens, output_tokens, total_tokens],
+        "INSERT INTO token_usage (timestamp, account_email, model, input_tokens, output_tokens, total_tokens, outcome, api_key_id, cache_read_tokens, latency_ms, status, estimated_cost)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            timestamp,
+            account_email,
+            usage.model,
+            usage.input_tokens,
+            usage.output_tokens,
+            total_tokens,
+            outcome,
+            api_key_id,
+            usage.cache_read_tokens,
+            usage.latency_ms as i64,
+            usage.status,
+            usage.estimated_cost
+        ],
     ).map_err(|e| e.to_string())?;
 
-    let hour_bucket = chrono::Utc::now().format("%Y-%m-%d %H:00").to_string();
+    // Requests that never reached an account (rejected, cache hits) only get a usage row:
+    // the hourly aggregates are per account and would otherwise pile them up under NO_ACCOUNT
+    let Some(account_email) = usage.account_email.as_deref() else {
+        return Ok(());
+    };
+    let hour_bucket = chrono::DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_else(chrono::Utc::now)
+        .format("%Y-%m-%d %H:00")
+        .to_string();
     conn.execute(
-        "INSERT INTO token_stats_hourly (hour_bucket, account_email, total_input_tokens, total_output_tokens, total_tokens, request_count)
-         VALUES (?1, ?2, ?3, ?4, ?5, 1)
+        "INSERT INTO token_stats_hourly (hour_bucket, account_email, total_input_tokens, total_output_tokens, total_tokens, request_count, total_cache_read_tokens, error_count, total_cost)
+         VALUES (?1, ?2, ?3, ?4, ?5, 1, ?6, ?7, ?8)
          ON CONFLICT(hour_bucket, account_email) DO UPDATE SET
             total_input_tokens = total_input_tokens + ?3,
             total_output_tokens = total_output_tokens + ?4,
             total_tokens = total_tokens + ?5,
-            request_count = request_count + 1",
-        params![hour_bucket, account_email, input_tokens, output_tokens, total_tokens],
+            request_count = request_count + 1,
+            total_cache_read_tokens = total_cache_read_tokens + ?6,
+            error_count = error_count + ?7,
+            total_cost = total_cost + ?8",
+        params![
+            hour_bucket,
+            account_email,
+            usage.input_tokens,
+            usage.output_tokens,
+            total_tokens,
+            usage.cache_read_tokens,
+            error_count,
+            usage.estimated_cost
+        ],
     ).map_err(|e| e.to_string())?;
 
     Ok(())
 }
 
+/// Write-ahead intent operations, applied in order by a single background writer so the
+/// request path never waits on SQLite
+enum IntentOp {
+    Begin {
+        id: String,
+        started_at: i64,
+        model: String,
+        estimated_input_tokens: u32,
+        api_key_id: Option<String>,
+    },
+    SetAccount {
+        id: String,
+        account_email: String,
+    },
+    Complete {
+        id: String,
+        usage: Option<RequestUsage>,
+    },
+    Abandon {
+        id: String,
+    },
+}
+
+/// Upper bound of operations written in one transaction
+const MAX_INTENT_BATCH: usize = 256;
+
+/// Queued intent operations not yet committed (awaited before exit)
+static PENDING_INTENT_OPS: AtomicUsize = AtomicUsize::new(0);
+
+static INTENT_WRITER: Lazy<Mutex<Sender<IntentOp>>> = Lazy::new(|| {
+    let (tx, rx) = mpsc::channel();
+    // If the thread can't be spawned the receiver is dropped and enqueue() reports the failure
+    let _ = std::thread::Builder::new()
+        .name("usage-intents".to_string())
+        .spawn(move || run_intent_writer(rx));
+    Mutex::new(tx)
+});
+
+fn enqueue(op: IntentOp) {
+    PENDING_INTENT_OPS.fetch_add(1, Ordering::SeqCst);
+    if INTENT_WRITER.lock().send(op).is_err() {
+        PENDING_INTENT_OPS.fetch_sub(1, Ordering::SeqCst);
+        tracing::warn!("Usage intent writer is not running, dropping token stats update");
+    }
+}
+
+fn run_intent_writer(rx: Receiver<IntentOp>) {
+    let mut conn: Option<Connection> = None;
+    while let Ok(first) = rx.recv() {
+        let mut batch = vec![first];
+        while batch.len() < MAX_INTENT_BATCH {
+            match rx.try_recv() {
+                Ok(op) => batch.push(op),
+                Err(_) => break,
+            }
+        }
+
+        if conn.is_none() {
+            conn = connect_db()
+                .map_err(|e| tracing::debug!("Failed to open token stats db: {}", e))
+                .ok();
+        }
+        if let Some(c) = conn.as_mut() {
+            match apply_intent_ops(c, &batch) {
+                Ok(served) => {
+                    for account_email in served {
+                        crate::modules::token_budget::on_usage(&account_email);
+                    }
+                }
+                Err(e) => {
+                    tracing::debug!("Failed to record token stats: {}", e);
+                    // Reconnect on the next batch in case the connection went bad
+                    conn = None;
+                }
+            }
+        }
+        PENDING_INTENT_OPS.fetch_sub(batch.len(), Ordering::SeqCst);
+    }
+}
+
+/// Apply a batch of intent operations in one transaction; returns the accounts that were charged
+fn apply_intent_ops(conn: &mut Connection, ops: &[IntentOp]) -> Result<Vec<String>, String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut served = Vec::new();
+    for op in ops {
+        match op {
+            IntentOp::Begin {
+                id,
+                started_at,
+                model,
+                estimated_input_tokens,
+                api_key_id,
+            } => {
+                tx.execute(
+                    "INSERT OR REPLACE INTO usage_intents (id, started_at, model, estimated_input_tokens, api_key_id)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![id, started_at, model, estimated_input_tokens, api_key_id],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+            IntentOp::SetAccount { id, account_email } => {
+                tx.execute(
+                    "UPDATE usage_intents SET account_email = ?2 WHERE id = ?1",
+                    params![id, account_email],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+            IntentOp::Complete { id, usage } => {
+                if let Some(usage) = usage {
+                    let api_key_id: Option<String> = tx
+                        .query_row(
+                            "SELECT api_key_id FROM usage_intents WHERE id = ?1",
+                            [id],
+                            |row| row.get(0),
+                        )
+                        .unwrap_or(None);
+                    let timestamp = chrono::Utc::now().timestamp();
+                    insert_usage(&tx, timestamp, usage, "completed", api_key_id.as_deref())?;
+                    if let Some(account_email) = &usage.account_email {
+                        served.push(account_email.clone());
+                    }
+                }
+                tx.execute("DELETE FROM usage_intents WHERE id = ?1", [id])
+                    .map_err(|e| e.to_string())?;
+            }
+            IntentOp::Abandon { id } => {
+                book_unknown_intents(&tx, Some(id))?;
+            }
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    served.sort();
+    served.dedup();
+    Ok(served)
+}
+
+/// Record a usage intent before the request is dispatched upstream
+pub fn begin_usage_intent(
+    intent_id: &str,
+    model: &str,
+    estimated_input_tokens: u32,
+    api_key_id: Option<&str>,
+) {
+    enqueue(IntentOp::Begin {
+        id: intent_id.to_string(),
+        started_at: chrono::Utc::now().timestamp(),
+        model: model.to_string(),
+        estimated_input_tokens,
+        api_key_id: api_key_id.map(str::to_string),
+    });
+}
+
+/// Attach the serving account once it is known (response headers received)
+pub fn set_usage_intent_account(intent_id: &str, account_email: &str) {
+    enqueue(IntentOp::SetAccount {
+        id: intent_id.to_string(),
+        account_email: account_email.to_string(),
+    });
+}
+
+/// Finalize an intent: record the request's usage row (if any) and drop the intent atomically
+pub fn complete_usage_intent(intent_id: &str, usage: Option<RequestUsage>) {
+    enqueue(IntentOp::Complete {
+        id: intent_id.to_string(),
+        usage,
+    });
+}
+
+/// Finalize an intent whose request was cancelled before it could be logged (client went away,
+/// task dropped): book it as "unknown outcome" usage, the same way a crash is recovered
+pub fn abandon_usage_intent(intent_id: &str) {
+    enqueue(IntentOp::Abandon {
+        id: intent_id.to_string(),
+    });
+}
+
+/// Number of queued intent operations not yet written
+pub fn pending_intent_writes() -> usize {
+    PENDING_INTENT_OPS.load(Ordering::SeqCst)
+}
+
+/// Total tokens used by an account since the given Unix timestamp
+pub fn get_account_usage_since(account_email: &str, since: i64) -> Result<u64, String> {
+    let conn = connect_db()?;
+    conn.query_row(
+        "SELECT COALESCE(SUM(total_tokens), 0) FROM token_usage
+         WHERE account_email = ?1 AND timestamp >= ?2",
+        params![account_email, since],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|total| total.max(0) as u64)
+    .map_err(|e| e.to_string())
+}
+
+/// Book pending intents (all, or only `intent_id`) as "unknown outcome" usage with their
+/// estimated input tokens and drop them
+fn book_unknown_intents(conn: &Connection, intent_id: Option<&str>) -> Result<usize, String> {
+    #[allow(clippy::type_complexity)]
+    let intents: Vec<(i64, Option<String>, String, u32, Option<String>)> = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT started_at, account_email, model, estimated_input_tokens, api_key_id FROM usage_intents
+                 WHERE ?1 IS NULL OR id = ?1",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([intent_id], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        rows.filter_map(|r| r.ok()).collect()
+    };
+
+    for (started_at, account_email, model, estimated_input_tokens, api_key_id) in &intents {
+        let usage = RequestUsage {
+            account_email: account_email.clone(),
+            model: model.clone(),
+            input_tokens: *estimated_input_tokens,
+            ..Default::default()
+        };
+        insert_usage(conn, *started_at, &usage, "unknown", api_key_id.as_deref())?;
+    }
+    conn.execute(
+        "DELETE FROM usage_intents WHERE ?1 IS NULL OR id = ?1",
+        [intent_id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(intents.len())
+}
+
+/// Book intents left over from a previous run (app crashed or was killed mid-request)
+/// as "unknown outcome" usage with their estimated input tokens. Call once at startup.
+pub fn recover_unfinished_intents() -> Result<usize, String> {
+    let mut conn = connect_db()?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let recovered = book_unknown_intents(&tx, None)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(recovered)
+}
+
 /// Get hourly aggregated stats for a time range
 pub fn get_hourly_stats(hours: i64) -> Result<Vec<TokenStatsAggregated>, String> {
     let conn = connect_db()?;
@@ -182,7 +597,8 @@ pub fn get_hourly_stats(hours: i64) -> Result<Vec<TokenStatsAggregated>, String>
                 SUM(total_input_tokens) as input, 
                 SUM(total_output_tokens) as output,
                 SUM(total_tokens) as total,
-                SUM(request_count) as count
+                SUM(request_count) as count,
+                SUM(total_cost) as cost
          FROM token_stats_hourly 
          WHERE hour_bucket >= ?1
          GROUP BY hour_bucket
@@ -198,6 +614,7 @@ pub fn get_hourly_stats(hours: i64) -> Result<Vec<TokenStatsAggregated>, String>
                 total_output_tokens: row.get(2)?,
                 total_tokens: row.get(3)?,
                 request_count: row.get(4)?,
+                estimated_cost: row.get(5)?,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -221,7 +638,8 @@ pub fn get_daily_stats(days: i64) -> Result<Vec<TokenStatsAggregated>, String> {
                 SUM(total_input_tokens) as input, 
                 SUM(total_output_tokens) as output,
                 SUM(total_tokens) as total,
-                SUM(request_count) as count
+                SUM(request_count) as count,
+                SUM(total_cost) as cost
          FROM token_stats_hourly 
          WHERE substr(hour_bucket, 1, 10) >= ?1
          GROUP BY day_bucket
@@ -237,6 +655,7 @@ pub fn get_daily_stats(days: i64) -> Result<Vec<TokenStatsAggregated>, String> {
                 total_output_tokens: row.get(2)?,
                 total_tokens: row.get(3)?,
                 request_count: row.get(4)?,
+                estimated_cost: row.get(5)?,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -260,7 +679,8 @@ pub fn get_weekly_stats(weeks: i64) -> Result<Vec<TokenStatsAggregated>, String>
                 SUM(input_tokens) as input, 
                 SUM(output_tokens) as output,
                 SUM(total_tokens) as total,
-                COUNT(*) as count
+                COUNT(*) as count,
+                SUM(estimated_cost) as cost
          FROM token_usage 
          WHERE timestamp >= ?1
          GROUP BY week_bucket
@@ -276,6 +696,7 @@ pub fn get_weekly_stats(weeks: i64) -> Result<Vec<TokenStatsAggregated>, String>
                 total_output_tokens: row.get(2)?,
                 total_tokens: row.get(3)?,
                 request_count: row.get(4)?,
+                estimated_cost: row.get(5)?,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -299,7 +720,8 @@ pub fn get_account_stats(hours: i64) -> Result<Vec<AccountTokenStats>, String> {
                 SUM(total_input_tokens) as input, 
                 SUM(total_output_tokens) as output,
                 SUM(total_tokens) as total,
-                SUM(request_count) as count
+                SUM(request_count) as count,
+                SUM(total_cost) as cost
          FROM token_stats_hourly 
          WHERE hour_bucket >= ?1
          GROUP BY account_email
@@ -315,6 +737,47 @@ pub fn get_account_stats(hours: i64) -> Result<Vec<AccountTokenStats>, String> {
                 total_output_tokens: row.get(2)?,
                 total_tokens: row.get(3)?,
                 request_count: row.get(4)?,
+                estimated_cost: row.get(5)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(result)
+}
+
+/// Get per-API-key statistics for a time range
+pub fn get_api_key_stats(hours: i64) -> Result<Vec<ApiKeyTokenStats>, String> {
+    let conn = connect_db()?;
+    let cutoff = (chrono::Utc::now() - chrono::Duration::hours(hours)).timestamp();
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT api_key_id,
+                SUM(input_tokens) as input,
+                SUM(output_tokens) as output,
+                SUM(total_tokens) as total,
+                COUNT(*) as count,
+                SUM(estimated_cost) as cost
+         FROM token_usage
+         WHERE timestamp >= ?1 AND api_key_id IS NOT NULL
+         GROUP BY api_key_id
+         ORDER BY total DESC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map([cutoff], |row| {
+            Ok(ApiKeyTokenStats {
+                api_key_id: row.get(0)?,
+                total_input_tokens: row.get(1)?,
+                total_output_tokens: row.get(2)?,
+                total_tokens: row.get(3)?,
+                request_count: row.get(4)?,
+                estimated_cost: row.get(5)?,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -332,23 +795,55 @@ pub fn get_summary_stats(hours: i64) -> Result<TokenStatsSummary, String> {
     let cutoff = chrono::Utc::now() - chrono::Duration::hours(hours);
     let cutoff_bucket = cutoff.format("%Y-%m-%d %H:00").to_string();
 
-    let (total_input, total_output, total, requests): (u64, u64, u64, u64) = conn
+    #[allow(clippy::type_complexity)]
+    let (total_input, total_output, total, requests, cache_read, errors, cost): (u64, u64, u64, u64, u64, u64, f64) = conn
         .query_row(
             "SELECT COALESCE(SUM(total_input_tokens), 0),
                 COALESCE(SUM(total_output_tokens), 0),
                 COALESCE(SUM(total_tokens), 0),
-                COALESCE(SUM(request_count), 0)
+                COALESCE(SUM(request_count), 0),
+                COALESCE(SUM(total_cache_read_tokens), 0),
+                COALESCE(SUM(error_count), 0),
+                COALESCE(SUM(total_cost), 0.0)
          FROM token_stats_hourly 
          WHERE hour_bucket >= ?1",
             [&cutoff_bucket],
-            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                    row.get(6)?,
+                ))
+            },
         )
         .map_err(|e| e.to_string())?;
 
     let unique_accounts: u64 = conn
         .query_row(
-            "SELECT COUNT(DISTINCT account_email) FROM token_stats_hourly WHERE hour_bucket >= ?1",
-            [&cutoff_bucket],
+            "SELECT COUNT(DISTINCT account_email) FROM token_stats_hourly WHERE hour_bucket >= ?1 AND account_email != ?2",
+            params![cutoff_bucket, NO_ACCOUNT],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let unknown_outcome_requests: u64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM token_usage WHERE outcome = 'unknown' AND timestamp >= ?1",
+            [cutoff.timestamp()],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    // Rows written before latency was recorded have latency_ms = 0
+    let avg_latency_ms: f64 = conn
+        .query_row(
+            "SELECT COALESCE(AVG(latency_ms), 0) FROM token_usage
+             WHERE outcome = 'completed' AND latency_ms > 0 AND timestamp >= ?1",
+            [cutoff.timestamp()],
             |row| row.get(0),
         )
         .map_err(|e| e.to_string())?;
@@ -359,9 +854,64 @@ pub fn get_summary_stats(hours: i64) -> Result<TokenStatsSummary, String> {
         total_tokens: total,
         total_requests: requests,
         unique_accounts,
+        unknown_outcome_requests,
+        total_cache_read_tokens: cache_read,
+        error_requests: errors,
+        avg_latency_ms: avg_latency_ms.round() as u64,
+        estimated_cost: cost,
     })
 }
 
+/// Most recent per-request usage rows, newest first
+pub fn get_usage_records(hours: i64, limit: usize) -> Result<Vec<TokenUsageRecord>, String> {
+    let conn = connect_db()?;
+    let cutoff = (chrono::Utc::now() - chrono::Duration::hours(hours)).timestamp();
+    load_usage_records(&conn, cutoff, limit)
+}
+
+fn load_usage_records(
+    conn: &Connection,
+    cutoff: i64,
+    limit: usize,
+) -> Result<Vec<TokenUsageRecord>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, timestamp, account_email, model, input_tokens, output_tokens, total_tokens,
+                cache_read_tokens, latency_ms, status, outcome, api_key_id, estimated_cost
+         FROM token_usage
+         WHERE timestamp >= ?1
+         ORDER BY timestamp DESC, id DESC
+         LIMIT ?2",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map(params![cutoff, limit as i64], |row| {
+            Ok(TokenUsageRecord {
+                id: row.get(0)?,
+                timestamp: row.get(1)?,
+                account_email: row.get(2)?,
+                model: row.get(3)?,
+                input_tokens: row.get(4)?,
+                output_tokens: row.get(5)?,
+                total_tokens: row.get(6)?,
+                cache_read_tokens: row.get(7)?,
+                latency_ms: row.get::<_, i64>(8)?.max(0) as u64,
+                status: row.get(9)?,
+                outcome: row.get(10)?,
+                api_key_id: row.get(11)?,
+                estimated_cost: row.get(12)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(result)
+}
+
 /// Clean up old data (keep last N days of raw data)
 pub fn cleanup_old_data(days: i64) -> Result<usize, String> {
     let conn = connect_db()?;
@@ -386,6 +936,13 @@ pub fn cleanup_old_data(days: i64) -> Result<usize, String> {
     Ok(deleted)
 }
 
+/// Merge the WAL back into the main database file (called on app exit)
+pub fn checkpoint() -> Result<(), String> {
+    let conn = connect_db()?;
+    conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")
+        .map_err(|e| e.to_string())
+}
+
 pub fn get_model_stats(hours: i64) -> Result<Vec<ModelTokenStats>, String> {
     let conn = connect_db()?;
     let cutoff = chrono::Utc::now().timestamp() - (hours * 3600);
@@ -396,7 +953,8 @@ pub fn get_model_stats(hours: i64) -> Result<Vec<ModelTokenStats>, String> {
                 SUM(input_tokens) as input,
                 SUM(output_tokens) as output,
                 SUM(total_tokens) as total,
-                COUNT(*) as count
+                COUNT(*) as count,
+                SUM(estimated_cost) as cost
          FROM token_usage
          WHERE timestamp >= ?1
          GROUP BY model
@@ -412,6 +970,7 @@ pub fn get_model_stats(hours: i64) -> Result<Vec<ModelTokenStats>, String> {
                 total_output_tokens: row.get(2)?,
                 total_tokens: row.get(3)?,
                 request_count: row.get(4)?,
+                estimated_cost: row.get(5)?,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -539,7 +1098,10 @@ pub fn get_account_trend_hourly(hours: i64) -> Result<Vec<AccountTrendPoint>, St
 
     Ok(trend_map
         .into_iter()
-        .map(|(period, account_data)| AccountTrendPoint { period, account_data })
+        .map(|(period, account_data)| AccountTrendPoint {
+            period,
+            account_data,
+        })
         .collect())
 }
 
@@ -579,7 +1141,10 @@ pub fn get_account_trend_daily(days: i64) -> Result<Vec<AccountTrendPoint>, Stri
 
     Ok(trend_map
         .into_iter()
-        .map(|(period, account_data)| AccountTrendPoint { period, account_data })
+        .map(|(period, account_data)| AccountTrendPoint {
+            period,
+            account_data,
+        })
         .collect())
 }
 
@@ -593,4 +1158,252 @@ mod tests {
         // For now, just verify the module compiles
         assert!(true);
     }
+
+    fn memory_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn
+    }
+
+    fn begin(id: &str, estimated_input_tokens: u32, api_key_id: Option<&str>) -> IntentOp {
+        IntentOp::Begin {
+            id: id.to_string(),
+            started_at: 1_700_000_000,
+            model: "gemini-2.5-pro".to_string(),
+            estimated_input_tokens,
+            api_key_id: api_key_id.map(str::to_string),
+        }
+    }
+
+    fn set_account(id: &str, account_email: &str) -> IntentOp {
+        IntentOp::SetAccount {
+            id: id.to_string(),
+            account_email: account_email.to_string(),
+        }
+    }
+
+    fn intent_count(conn: &Connection) -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM usage_intents", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn schema_migration_is_idempotent() {
+        let conn = memory_db();
+        init_schema(&conn).unwrap();
+    }
+
+    #[test]
+    fn add_column_reports_real_errors() {
+        let conn = memory_db();
+        assert!(add_column(&conn, "token_usage", "status INTEGER").is_ok());
+        assert!(add_column(&conn, "missing_table", "note TEXT").is_err());
+    }
+
+    #[test]
+    fn completed_intent_records_usage_with_api_key() {
+        let mut conn = memory_db();
+        let usage = RequestUsage {
+            account_email: Some("a@example.com".to_string()),
+            model: "gemini-2.5-pro".to_string(),
+            input_tokens: 100,
+            output_tokens: 20,
+            status: 200,
+            ..Default::default()
+        };
+        let served = apply_intent_ops(
+            &mut conn,
+            &[
+                begin("req-1", 80, Some("key-1")),
+                set_account("req-1", "a@example.com"),
+                IntentOp::Complete {
+                    id: "req-1".to_string(),
+                    usage: Some(usage),
+                },
+            ],
+        )
+        .unwrap();
+        assert_eq!(served, vec!["a@example.com".to_string()]);
+
+        let row: (String, u32, String, Option<String>) = conn
+            .query_row(
+                "SELECT account_email, total_tokens, outcome, api_key_id FROM token_usage",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            row,
+            (
+                "a@example.com".to_string(),
+                120,
+                "completed".to_string(),
+                Some("key-1".to_string())
+            )
+        );
+        assert_eq!(intent_count(&conn), 0);
+    }
+
+    #[test]
+    fn abandoned_intent_is_booked_as_unknown() {
+        let mut conn = memory_db();
+        let served = apply_intent_ops(
+            &mut conn,
+            &[
+                begin("req-1", 50, None),
+                set_account("req-1", "b@example.com"),
+                IntentOp::Abandon {
+                    id: "req-1".to_string(),
+                },
+            ],
+        )
+        .unwrap();
+        assert!(served.is_empty());
+
+        let row: (String, u32, String) = conn
+            .query_row(
+                "SELECT account_email, input_tokens, outcome FROM token_usage",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            row,
+            ("b@example.com".to_string(), 50, "unknown".to_string())
+        );
+        assert_eq!(intent_count(&conn), 0);
+    }
+
+    #[test]
+    fn abandon_only_books_its_own_intent() {
+        let mut conn = memory_db();
+        apply_intent_ops(
+            &mut conn,
+            &[
+                begin("req-1", 10, None),
+                begin("req-2", 20, None),
+                IntentOp::Abandon {
+                    id: "req-1".to_string(),
+                },
+            ],
+        )
+        .unwrap();
+        assert_eq!(intent_count(&conn), 1);
+
+        // Whatever is left is recovered on the next start
+        assert_eq!(book_unknown_intents(&conn, None).unwrap(), 1);
+        assert_eq!(intent_count(&conn), 0);
+    }
+
+    fn complete(id: &str, usage: RequestUsage) -> IntentOp {
+        IntentOp::Complete {
+            id: id.to_string(),
+            usage: Some(usage),
+        }
+    }
+
+    fn hourly_rows(conn: &Connection) -> Vec<(String, u32, u32, u32, f64)> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT account_email, request_count, total_cache_read_tokens, error_count, total_cost
+                 FROM token_stats_hourly ORDER BY account_email",
+            )
+            .unwrap();
+        stmt.query_map([], |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+            ))
+        })
+        .unwrap()
+        .map(|row| row.unwrap())
+        .collect()
+    }
+
+    #[test]
+    fn usage_records_carry_request_details() {
+        let mut conn = memory_db();
+        let served = RequestUsage {
+            account_email: Some("a@example.com".to_string()),
+            model: "gemini-2.5-pro".to_string(),
+            input_tokens: 100,
+            output_tokens: 20,
+            cache_read_tokens: 40,
+            latency_ms: 850,
+            status: 200,
+            estimated_cost: 0.25,
+        };
+        let failed = RequestUsage {
+            account_email: Some("a@example.com".to_string()),
+            model: "gemini-2.5-pro".to_string(),
+            latency_ms: 120,
+            status: 429,
+            ..Default::default()
+        };
+        apply_intent_ops(
+            &mut conn,
+            &[
+                begin("req-1", 80, Some("key-1")),
+                complete("req-1", served),
+                begin("req-2", 80, None),
+                complete("req-2", failed),
+            ],
+        )
+        .unwrap();
+
+        let records = load_usage_records(&conn, 0, 10).unwrap();
+        assert_eq!(records.len(), 2);
+        // Same timestamp: newest row id first
+        assert_eq!(records[0].status, 429);
+        assert_eq!(records[0].latency_ms, 120);
+        assert_eq!(records[0].api_key_id, None);
+        assert_eq!(records[1].cache_read_tokens, 40);
+        assert_eq!(records[1].latency_ms, 850);
+        assert_eq!(records[1].estimated_cost, 0.25);
+        assert_eq!(records[1].outcome, "completed");
+        assert_eq!(records[1].api_key_id.as_deref(), Some("key-1"));
+
+        assert_eq!(load_usage_records(&conn, 0, 1).unwrap().len(), 1);
+        let future = chrono::Utc::now().timestamp() + 3600;
+        assert!(load_usage_records(&conn, future, 10).unwrap().is_empty());
+
+        assert_eq!(
+            hourly_rows(&conn),
+            vec![("a@example.com".to_string(), 2, 40, 1, 0.25)]
+        );
+    }
+
+    #[test]
+    fn requests_without_account_skip_hourly_aggregates() {
+        let mut conn = memory_db();
+        let cache_hit = RequestUsage {
+            model: "gemini-2.5-pro".to_string(),
+            status: 200,
+            ..Default::default()
+        };
+        let rejected = RequestUsage {
+            model: "gemini-2.5-pro".to_string(),
+            status: 503,
+            ..Default::default()
+        };
+        let served = apply_intent_ops(
+            &mut conn,
+            &[
+                begin("req-1", 10, None),
+                complete("req-1", cache_hit),
+                begin("req-2", 10, None),
+                complete("req-2", rejected),
+            ],
+        )
+        .unwrap();
+        assert!(served.is_empty());
+
+        let records = load_usage_records(&conn, 0, 10).unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|r| r.account_email == NO_ACCOUNT));
+        assert!(hourly_rows(&conn).is_empty());
+    }
 }
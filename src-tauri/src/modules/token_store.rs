@@ -0,0 +1,535 @@
+//! Refresh token storage backends.
+//!
+//! With the default `file` backend the refresh token stays inline in the account file.
+//! The other backends keep the secret outside the data directory and write a
+//! `secret-ref:<backend>` placeholder into the account file instead, which is resolved
+//! again whenever the account is loaded.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+
+use crate::models::config::{TokenStorageBackend, TokenStorageConfig};
+
+/// Placeholder prefix written to the account file for externally stored tokens
+const SECRET_REF_PREFIX: &str = "secret-ref:";
+
+/// Service / label name used in the OS credential store
+#[cfg(not(target_os = "windows"))]
+const KEYCHAIN_SERVICE: &str = "antigravity-tools";
+
+/// Resolved tokens per account, so external stores are not queried on every account load
+static RESOLVED: Lazy<RwLock<HashMap<String, (TokenStorageBackend, String)>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// A place where refresh tokens are persisted
+pub trait TokenStore {
+    fn backend(&self) -> TokenStorageBackend;
+
+    /// Store the token and return the value to write into the account file
+    fn persist(&self, account_id: &str, refresh_token: &str) -> Result<String, String>;
+
+    /// Resolve the token from the value found in the account file
+    fn load(&self, account_id: &str, stored: &str) -> Result<String, String>;
+
+    /// Remove the token from the store
+    fn delete(&self, account_id: &str) -> Result<(), String>;
+}
+
+/// Plain text, inline in the account file
+pub struct FileTokenStore;
+
+impl TokenStore for FileTokenStore {
+    fn backend(&self) -> TokenStorageBackend {
+        TokenStorageBackend::File
+    }
+
+    fn persist(&self, _account_id: &str, refresh_token: &str) -> Result<String, String> {
+        Ok(refresh_token.to_string())
+    }
+
+    fn load(&self, _account_id: &str, stored: &str) -> Result<String, String> {
+        Ok(stored.to_string())
+    }
+
+    fn delete(&self, _account_id: &str) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// OS credential store: macOS Keychain, Secret Service (`secret-tool`) on Linux,
+/// DPAPI-encrypted files (current user scope) on Windows
+pub struct KeychainTokenStore;
+
+impl TokenStore for KeychainTokenStore {
+    fn backend(&self) -> TokenStorageBackend {
+        TokenStorageBackend::Keychain
+    }
+
+    fn persist(&self, account_id: &str, refresh_token: &str) -> Result<String, String> {
+        keychain_set(account_id, refresh_token)?;
+        Ok(secret_ref(self.backend()))
+    }
+
+    fn load(&self, account_id: &str, _stored: &str) -> Result<String, String> {
+        keychain_get(account_id)
+    }
+
+    fn delete(&self, account_id: &str) -> Result<(), String> {
+        keychain_delete(account_id)
+    }
+}
+
+/// External secrets manager (`pass`, 1Password CLI, ...) driven by shell commands.
+/// `{account_id}` in the commands is replaced with the shell-quoted account id, which is
+/// also exported as `ACCOUNT_ID`.
+pub struct CommandTokenStore {
+    config: TokenStorageConfig,
+}
+
+impl CommandTokenStore {
+    fn run(&self, template: &str, account_id: &str, input: Option<&str>) -> Result<String, String> {
+        if template.trim().is_empty() {
+            return Err("token_store_command_not_configured".to_string());
+        }
+        let command_line = template.replace("{account_id}", &shell_quote(account_id));
+
+        #[cfg(target_os = "windows")]
+        let mut cmd = {
+            use std::os::windows::process::CommandExt;
+            const CREATE_NO_WINDOW: u32 = 0x08000000;
+            let mut cmd = Command::new("cmd");
+            cmd.args(["/C", &command_line]);
+            cmd.creation_flags(CREATE_NO_WINDOW);
+            cmd
+        };
+        #[cfg(not(target_os = "windows"))]
+        let mut cmd = {
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", &command_line]);
+            cmd
+        };
+
+        cmd.env("ACCOUNT_ID", account_id);
+        run_with_stdin(&mut cmd, input)
+    }
+}
+
+/// Quote a value so the shell passes it through as a single literal argument
+#[cfg(not(target_os = "windows"))]
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// `cmd` has no reliable escaping; anything but plain id characters is dropped
+#[cfg(target_os = "windows")]
+fn shell_quote(value: &str) -> String {
+    let safe: String = value
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@'))
+        .collect();
+    format!("\"{}\"", safe)
+}
+
+impl TokenStore for CommandTokenStore {
+    fn backend(&self) -> TokenStorageBackend {
+        TokenStorageBackend::Command
+    }
+
+    fn persist(&self, account_id: &str, refresh_token: &str) -> Result<String, String> {
+        self.run(&self.config.set_command, account_id, Some(refresh_token))?;
+        Ok(secret_ref(self.backend()))
+    }
+
+    fn load(&self, account_id: &str, _stored: &str) -> Result<String, String> {
+        // Like `pass`, the secret is the first line of the output
+        let output = self.run(&self.config.get_command, account_id, None)?;
+        let token = output.lines().next().unwrap_or("").trim().to_string();
+        if token.is_empty() {
+            return Err("token_store_empty_secret".to_string());
+        }
+        Ok(token)
+    }
+
+    fn delete(&self, account_id: &str) -> Result<(), String> {
+        if self.config.delete_command.trim().is_empty() {
+            return Ok(());
+        }
+        self.run(&self.config.delete_command, account_id, None)
+            .map(|_| ())
+    }
+}
+
+/// Build the store for a backend (command templates come from `config`)
+pub fn store_for(backend: TokenStorageBackend, config: &TokenStorageConfig) -> Box<dyn TokenStore> {
+    match backend {
+        TokenStorageBackend::File => Box::new(FileTokenStore),
+        TokenStorageBackend::Keychain => Box::new(KeychainTokenStore),
+        TokenStorageBackend::Command => Box::new(CommandTokenStore {
+            config: config.clone(),
+        }),
+    }
+}
+
+fn current_config() -> TokenStorageConfig {
+    crate::modules::config::load_app_config()
+        .map(|c| c.token_storage)
+        .unwrap_or_default()
+}
+
+fn secret_ref(backend: TokenStorageBackend) -> String {
+    let name = match backend {
+        TokenStorageBackend::File => "file",
+        TokenStorageBackend::Keychain => "keychain",
+        TokenStorageBackend::Command => "command",
+    };
+    format!("{}{}", SECRET_REF_PREFIX, name)
+}
+
+/// Backend referenced by a value from the account file (`None` for inline tokens)
+fn referenced_backend(stored: &str) -> Option<TokenStorageBackend> {
+    match stored.strip_prefix(SECRET_REF_PREFIX)? {
+        "keychain" => Some(TokenStorageBackend::Keychain),
+        "command" => Some(TokenStorageBackend::Command),
+        _ => None,
+    }
+}
+
+/// Store a refresh token with the configured backend and return the value for the account file
+pub fn persist(account_id: &str, refresh_token: &str) -> Result<String, String> {
+    let config = current_config();
+    if config.backend == TokenStorageBackend::File || refresh_token.is_empty() {
+        return Ok(refresh_token.to_string());
+    }
+
+    // Account files are rewritten often (quota updates); only touch the store when the token changed
+    let unchanged = RESOLVED
+        .read()
+        .get(account_id)
+        .is_some_and(|(backend, token)| *backend == config.backend && token == refresh_token);
+    if unchanged {
+        return Ok(secret_ref(config.backend));
+    }
+
+    let stored = store_for(config.backend, &config).persist(account_id, refresh_token)?;
+    RESOLVED.write().insert(
+        account_id.to_string(),
+        (config.backend, refresh_token.to_string()),
+    );
+    Ok(stored)
+}
+
+/// Resolve the refresh token from the value found in the account file
+pub fn resolve(account_id: &str, stored: &str) -> Result<String, String> {
+    let Some(backend) = referenced_backend(stored) else {
+        return Ok(stored.to_string());
+    };
+
+    if let Some((cached_backend, token)) = RESOLVED.read().get(account_id) {
+        if *cached_backend == backend {
+            return Ok(token.clone());
+        }
+    }
+
+    let token = store_for(backend, &current_config())
+        .load(account_id, stored)
+        .map_err(|e| format!("failed_to_resolve_refresh_token: {}", e))?;
+    RESOLVED
+        .write()
+        .insert(account_id.to_string(), (backend, token.clone()));
+    Ok(token)
+}
+
+/// Remove the externally stored token of an account file that is about to be deleted
+pub fn forget(account_id: &str, account_path: &Path) {
+    RESOLVED.write().remove(account_id);
+
+    let Some(backend) = read_stored_token(account_path)
+        .as_deref()
+        .and_then(referenced_backend)
+    else {
+        return;
+    };
+    if let Err(e) = store_for(backend, &current_config()).delete(account_id) {
+        crate::modules::logger::log_warn(&format!(
+            "Failed to remove stored refresh token of {}: {}",
+            account_id, e
+        ));
+    }
+}
+
+/// Raw `token.refresh_token` value of an account file
+fn read_stored_token(account_path: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(account_path).ok()?;
+    let json: serde_json::Value = serde_json::from_str(&content).ok()?;
+    json.get("token")?
+        .get("refresh_token")?
+        .as_str()
+        .map(|s| s.to_string())
+}
+
+/// Rewrite all account files with the configured backend.
+/// Tokens left behind in the previous external store are removed.
+pub fn migrate_accounts() -> Result<usize, String> {
+    use crate::modules::account;
+
+    let config = current_config();
+    let accounts_dir = account::get_accounts_dir()?;
+    let mut migrated = 0;
+
+    for summary in account::list_account_summaries()? {
+        let account_path = accounts_dir.join(format!("{}.json", summary.id));
+        let previous = read_stored_token(&account_path)
+            .as_deref()
+            .and_then(referenced_backend)
+            .unwrap_or(TokenStorageBackend::File);
+        if previous == config.backend {
+            continue;
+        }
+
+        let account = account::load_account(&summary.id)?;
+        account::save_account(&account)?;
+        if previous != TokenStorageBackend::File {
+            if let Err(e) = store_for(previous, &config).delete(&summary.id) {
+                crate::modules::logger::log_warn(&format!(
+                    "Failed to remove old refresh token of {}: {}",
+                    summary.id, e
+                ));
+            }
+        }
+        migrated += 1;
+    }
+
+    crate::modules::logger::log_info(&format!(
+        "Migrated {} account token(s) to {:?} storage",
+        migrated, config.backend
+    ));
+    Ok(migrated)
+}
+
+fn run_with_stdin(cmd: &mut Command, input: Option<&str>) -> Result<String, String> {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("token_store_spawn_failed: {}", e))?;
+
+    {
+        let mut stdin = child.stdin.take().ok_or("token_store_stdin_unavailable")?;
+        if let Some(input) = input {
+            stdin
+                .write_all(input.as_bytes())
+                .map_err(|e| format!("token_store_write_failed: {}", e))?;
+        }
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("token_store_wait_failed: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "token_store_command_failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn keychain_set(account_id: &str, refresh_token: &str) -> Result<(), String> {
+    // Interactive mode keeps the secret out of the process arguments. `security -i` tokenizes
+    // the script itself, so values that could break out of their quotes are refused.
+    if !is_keychain_safe(account_id) || !is_keychain_safe(refresh_token) {
+        return Err("token_store_unsupported_characters".to_string());
+    }
+    let script = format!(
+        "add-generic-password -U -s \"{}\" -a \"{}\" -w \"{}\"\n",
+        KEYCHAIN_SERVICE, account_id, refresh_token
+    );
+    run_with_stdin(Command::new("security").arg("-i"), Some(&script)).map(|_| ())
+}
+
+/// OAuth refresh tokens and account ids only use URL-safe characters
+#[cfg(any(target_os = "macos", test))]
+fn is_keychain_safe(value: &str) -> bool {
+    !value.is_empty()
+        && value.chars().all(|c| {
+            c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '~' | '/' | '+' | '=' | '@')
+        })
+}
+
+#[cfg(target_os = "macos")]
+fn keychain_get(account_id: &str) -> Result<String, String> {
+    let output = run_with_stdin(
+        Command::new("security").args([
+            "find-generic-password",
+            "-s",
+            KEYCHAIN_SERVICE,
+            "-a",
+            account_id,
+            "-w",
+        ]),
+        None,
+    )?;
+    Ok(output.trim().to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn keychain_delete(account_id: &str) -> Result<(), String> {
+    run_with_stdin(
+        Command::new("security").args([
+            "delete-generic-password",
+            "-s",
+            KEYCHAIN_SERVICE,
+            "-a",
+            account_id,
+        ]),
+        None,
+    )
+    .map(|_| ())
+}
+
+#[cfg(target_os = "linux")]
+fn keychain_set(account_id: &str, refresh_token: &str) -> Result<(), String> {
+    let label = format!("Antigravity Tools ({})", account_id);
+    run_with_stdin(
+        Command::new("secret-tool").args([
+            "store",
+            "--label",
+            &label,
+            "service",
+            KEYCHAIN_SERVICE,
+            "account",
+            account_id,
+        ]),
+        Some(refresh_token),
+    )
+    .map(|_| ())
+}
+
+#[cfg(target_os = "linux")]
+fn keychain_get(account_id: &str) -> Result<String, String> {
+    let output = run_with_stdin(
+        Command::new("secret-tool").args([
+            "lookup",
+            "service",
+            KEYCHAIN_SERVICE,
+            "account",
+            account_id,
+        ]),
+        None,
+    )?;
+    let token = output.trim().to_string();
+    if token.is_empty() {
+        return Err("token_store_empty_secret".to_string());
+    }
+    Ok(token)
+}
+
+#[cfg(target_os = "linux")]
+fn keychain_delete(account_id: &str) -> Result<(), String> {
+    run_with_stdin(
+        Command::new("secret-tool").args([
+            "clear",
+            "service",
+            KEYCHAIN_SERVICE,
+            "account",
+            account_id,
+        ]),
+        None,
+    )
+    .map(|_| ())
+}
+
+#[cfg(target_os = "windows")]
+fn dpapi_path(account_id: &str) -> Result<std::path::PathBuf, String> {
+    let dir = crate::modules::account::get_data_dir()?.join("secrets");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("failed_to_create_secrets_dir: {}", e))?;
+    Ok(dir.join(format!("{}.dpapi", account_id)))
+}
+
+#[cfg(target_os = "windows")]
+fn powershell(script: &str, input: &str) -> Result<String, String> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+    let mut cmd = Command::new("powershell");
+    cmd.args(["-NoProfile", "-NonInteractive", "-Command", script]);
+    cmd.creation_flags(CREATE_NO_WINDOW);
+    run_with_stdin(&mut cmd, Some(input))
+}
+
+#[cfg(target_os = "windows")]
+fn keychain_set(account_id: &str, refresh_token: &str) -> Result<(), String> {
+    let protected = powershell(
+        "Add-Type -AssemblyName System.Security; \
+         $t = [Console]::In.ReadToEnd(); \
+         [Convert]::ToBase64String([Security.Cryptography.ProtectedData]::Protect(\
+         [Text.Encoding]::UTF8.GetBytes($t), $null, 'CurrentUser'))",
+        refresh_token,
+    )?;
+    std::fs::write(dpapi_path(account_id)?, protected.trim())
+        .map_err(|e| format!("failed_to_write_secret: {}", e))
+}
+
+#[cfg(target_os = "windows")]
+fn keychain_get(account_id: &str) -> Result<String, String> {
+    let protected = std::fs::read_to_string(dpapi_path(account_id)?)
+        .map_err(|e| format!("failed_to_read_secret: {}", e))?;
+    let token = powershell(
+        "Add-Type -AssemblyName System.Security; \
+         $b = [Convert]::FromBase64String([Console]::In.ReadToEnd().Trim()); \
+         [Console]::Out.Write([Text.Encoding]::UTF8.GetString(\
+         [Security.Cryptography.ProtectedData]::Unprotect($b, $null, 'CurrentUser')))",
+        &protected,
+    )?;
+    Ok(token.trim().to_string())
+}
+
+#[cfg(target_os = "windows")]
+fn keychain_delete(account_id: &str) -> Result<(), String> {
+    let path = dpapi_path(account_id)?;
+    if path.exists() {
+        std::fs::remove_file(path).map_err(|e| format!("failed_to_delete_secret: {}", e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_ref_round_trip() {
+        assert_eq!(
+            referenced_backend(&secret_ref(TokenStorageBackend::Keychain)),
+            Some(TokenStorageBackend::Keychain)
+        );
+        assert_eq!(
+            referenced_backend(&secret_ref(TokenStorageBackend::Command)),
+            Some(TokenStorageBackend::Command)
+        );
+        assert_eq!(referenced_backend("1//0gplain-refresh-token"), None);
+        assert_eq!(resolve("acc", "1//0gplain").unwrap(), "1//0gplain");
+    }
+
+    #[test]
+    fn test_keychain_values_cannot_break_out_of_script() {
+        assert!(is_keychain_safe("1//0gAbC-def_ghi.jkl~"));
+        assert!(!is_keychain_safe("token\" -a other"));
+        assert!(!is_keychain_safe("token\ndelete-keychain"));
+        assert!(!is_keychain_safe(""));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn test_shell_quote_keeps_value_literal() {
+        assert_eq!(shell_quote("abc-123"), "'abc-123'");
+        assert_eq!(shell_quote("a'; rm -rf ~"), "'a'\\''; rm -rf ~'");
+    }
+}
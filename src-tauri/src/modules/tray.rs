@@ -1,11 +1,36 @@
 use tauri::{
     image::Image,
-    menu::{Menu, MenuItem, PredefinedMenuItem},
+    menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu},
     tray::{MouseButton, TrayIconBuilder, TrayIconEvent},
     Manager, Runtime, Emitter, Listener,
 };
 use crate::modules;
 
+/// Menu id prefix of the per-job pause toggles
+const JOB_TOGGLE_PREFIX: &str = "job_toggle:";
+
+/// Submenu with one check item per pausable scheduled job (checked = running)
+fn build_jobs_submenu<R: Runtime>(
+    app: &tauri::AppHandle<R>,
+    texts: &modules::i18n::TrayTexts,
+    config: &crate::models::AppConfig,
+) -> tauri::Result<Submenu<R>> {
+    let submenu = Submenu::with_id(app, "scheduled_jobs", &texts.scheduled_jobs, true)?;
+    for job in modules::scheduler::PAUSABLE_JOBS {
+        let label = texts.jobs.get(job).map(String::as_str).unwrap_or(job);
+        let item = CheckMenuItem::with_id(
+            app,
+            format!("{}{}", JOB_TOGGLE_PREFIX, job),
+            label,
+            true,
+            !config.job_paused(job),
+            None::<&str>,
+        )?;
+        submenu.append(&item)?;
+    }
+    Ok(submenu)
+}
+
 pub fn create_tray<R: Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<()> {
     // 1. Load config to get language settings
     let config = modules::load_app_config().unwrap_or_default();
@@ -29,6 +54,8 @@ pub fn create_tray<R: Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<()> {
     // Quick actions area
     let switch_next = MenuItem::with_id(app, "switch_next", &texts.switch_next, true, None::<&str>)?;
     let refresh_curr = MenuItem::with_id(app, "refresh_curr", &texts.refresh_current, true, None::<&str>)?;
+    let stop_all = MenuItem::with_id(app, "stop_all", &texts.stop_all, true, None::<&str>)?;
+    let jobs = build_jobs_submenu(app, &texts, &config)?;
     
     // System functions
     let show_i = MenuItem::with_id(app, "show", &texts.show_window, true, None::<&str>)?;
@@ -45,6 +72,8 @@ pub fn create_tray<R: Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<()> {
         &sep1,
         &switch_next,
         &refresh_curr,
+        &stop_all,
+        &jobs,
         &sep2,
         &show_i,
         &sep3,
@@ -70,6 +99,13 @@ pub fn create_tray<R: Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<()> {
                 "quit" => {
                     app.exit(0);
                 }
+                "stop_all" => {
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = modules::kill_switch::halt_all(&app_handle).await {
+                            modules::logger::log_warn(&format!("Tray stop-all skipped: {}", e));
+                        }
+                    });
+                }
                 "refresh_curr" => {
                     // Execute refresh asynchronously
                     tauri::async_runtime::spawn(async move {
@@ -99,7 +135,7 @@ pub fn create_tray<R: Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<()> {
                 "switch_next" => {
                     tauri::async_runtime::spawn(async move {
                          // 1. Get all accounts
-                         if let Ok(accounts) = modules::list_accounts() {
+                         if let Ok(accounts) = modules::list_account_summaries() {
                              if accounts.is_empty() { return; }
                              
                              let current_id = modules::get_current_account_id().unwrap_or(None);
@@ -121,6 +157,23 @@ pub fn create_tray<R: Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<()> {
                          }
                     });
                 }
+                id if id.starts_with(modules::reauth::TRAY_ITEM_PREFIX) => {
+                    modules::reauth::start(&id[modules::reauth::TRAY_ITEM_PREFIX.len()..]);
+                }
+                id if id.starts_with(JOB_TOGGLE_PREFIX) => {
+                    let job = &id[JOB_TOGGLE_PREFIX.len()..];
+                    let paused = !modules::scheduler::job_paused(job);
+                    match modules::scheduler::set_job_paused(job, paused) {
+                        Ok(()) => {
+                            let _ = app_handle.emit("scheduler://jobs_changed", ());
+                        }
+                        Err(e) => {
+                            modules::logger::log_warn(&format!("Tray job toggle failed: {}", e));
+                        }
+                    }
+                    // Rebuild so the check mark reflects the saved state
+                    update_tray_menus(&app_handle);
+                }
                 _ => {}
             }
         })
@@ -157,6 +210,35 @@ pub fn create_tray<R: Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<()> {
     Ok(())
 }
 
+/// Submenu listing accounts whose refresh token was rejected; `None` when there are none
+fn build_reauth_submenu<R: Runtime>(
+    app: &tauri::AppHandle<R>,
+    texts: &modules::i18n::TrayTexts,
+) -> Option<Submenu<R>> {
+    let accounts: Vec<_> = modules::list_account_summaries()
+        .ok()?
+        .into_iter()
+        .filter(|a| a.needs_reauth)
+        .collect();
+    if accounts.is_empty() {
+        return None;
+    }
+    let label = format!("⚠ {} ({})", texts.reauth_required, accounts.len());
+    let submenu = Submenu::with_id(app, "reauth_required", label, true).ok()?;
+    for account in accounts {
+        let item = MenuItem::with_id(
+            app,
+            format!("{}{}", modules::reauth::TRAY_ITEM_PREFIX, account.id),
+            &account.email,
+            true,
+            None::<&str>,
+        )
+        .ok()?;
+        submenu.append(&item).ok()?;
+    }
+    Some(submenu)
+}
+
 /// Helper function to update tray menu
 pub fn update_tray_menus<R: Runtime>(app: &tauri::AppHandle<R>) {
     let app_clone = app.clone();
@@ -221,11 +303,14 @@ pub fn update_tray_menus<R: Runtime>(app: &tauri::AppHandle<R>) {
          
          let switch_next = MenuItem::with_id(&app_clone, "switch_next", &texts.switch_next, true, None::<&str>);
          let refresh_curr = MenuItem::with_id(&app_clone, "refresh_curr", &texts.refresh_current, true, None::<&str>);
+         let stop_all = MenuItem::with_id(&app_clone, "stop_all", &texts.stop_all, true, None::<&str>);
+         let jobs = build_jobs_submenu(&app_clone, &texts, &config).ok();
+         let reauth = build_reauth_submenu(&app_clone, &texts);
          
          let show_i = MenuItem::with_id(&app_clone, "show", &texts.show_window, true, None::<&str>);
          let quit_i = MenuItem::with_id(&app_clone, "quit", &texts.quit, true, None::<&str>);
          
-         if let (Ok(i_u), Ok(s_n), Ok(r_c), Ok(s), Ok(q)) = (info_user, switch_next, refresh_curr, show_i, quit_i) {
+         if let (Ok(i_u), Ok(s_n), Ok(r_c), Ok(s_a), Ok(s), Ok(q)) = (info_user, switch_next, refresh_curr, stop_all, show_i, quit_i) {
              let sep1 = PredefinedMenuItem::separator(&app_clone).ok();
              let sep2 = PredefinedMenuItem::separator(&app_clone).ok();
              let sep3 = PredefinedMenuItem::separator(&app_clone).ok();
@@ -239,6 +324,9 @@ pub fn update_tray_menus<R: Runtime>(app: &tauri::AppHandle<R>) {
              if let Some(ref s) = sep1 { items.push(s); }
              items.push(&s_n);
              items.push(&r_c);
+             items.push(&s_a);
+             if let Some(ref j) = jobs { items.push(j); }
+             if let Some(ref r) = reauth { items.push(r); }
              if let Some(ref s) = sep2 { items.push(s); }
              items.push(&s);
              if let Some(ref s) = sep3 { items.push(s); }
@@ -252,3 +340,17 @@ pub fn update_tray_menus<R: Runtime>(app: &tauri::AppHandle<R>) {
          }
     });
 }
+
+/// Show the number of active quota alerts on the tray icon (tooltip, plus title on macOS)
+pub fn set_alert_badge<R: Runtime>(app: &tauri::AppHandle<R>, count: usize) {
+    if let Some(tray) = app.tray_by_id("main") {
+        let tooltip = if count > 0 {
+            format!("Antigravity Tools - ⚠ {} quota alert(s)", count)
+        } else {
+            "Antigravity Tools".to_string()
+        };
+        let _ = tray.set_tooltip(Some(tooltip));
+        #[cfg(target_os = "macos")]
+        let _ = tray.set_title(if count > 0 { Some(format!("⚠ {}", count)) } else { None });
+    }
+}
@@ -1,10 +1,24 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 use crate::modules::logger;
 
 const GITHUB_API_URL: &str = "https://api.github.com/repos/michaelbarrera21/Antigravity-Manager/releases/latest";
 const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
 const DEFAULT_CHECK_INTERVAL_HOURS: u64 = 24;
+/// Name of the signed updater manifest attached to every release
+const UPDATER_MANIFEST_ASSET: &str = "updater.json";
+/// Updater endpoint and signing key are shared with the GUI updater plugin
+const TAURI_CONF: &str = include_str!("../../tauri.conf.json");
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    /// Includes pre-releases
+    Beta,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateInfo {
@@ -22,6 +36,9 @@ pub struct UpdateSettings {
     pub last_check_time: u64,
     #[serde(default = "default_check_interval")]
     pub check_interval_hours: u64,
+    /// None keeps the stored channel when saving
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<UpdateChannel>,
 }
 
 fn default_check_interval() -> u64 {
@@ -34,6 +51,7 @@ impl Default for UpdateSettings {
             auto_check: true,
             last_check_time: 0,
             check_interval_hours: DEFAULT_CHECK_INTERVAL_HOURS,
+            channel: None,
         }
     }
 }
@@ -46,11 +64,63 @@ struct GitHubRelease {
     published_at: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct GitHubReleaseAssets {
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    assets: Vec<GitHubAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// Signed updater manifest (`updater.json`, same format as the Tauri updater)
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateManifest {
+    pub version: String,
+    #[serde(default)]
+    pub notes: String,
+    pub platforms: HashMap<String, UpdateArtifact>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateArtifact {
+    /// Base64 encoded minisign signature of the artifact
+    pub signature: String,
+    pub url: String,
+}
+
+impl UpdateManifest {
+    pub fn version(&self) -> &str {
+        self.version.trim_start_matches('v')
+    }
+
+    pub fn is_newer(&self) -> bool {
+        compare_versions(self.version(), CURRENT_VERSION)
+    }
+
+    /// Artifact for the running platform (`{os}-{arch}`, AppImage preferred on Linux)
+    pub fn artifact_for_current_platform(&self) -> Option<&UpdateArtifact> {
+        let os = match std::env::consts::OS {
+            "macos" => "darwin",
+            other => other,
+        };
+        let target = format!("{}-{}", os, std::env::consts::ARCH);
+        self.platforms
+            .get(&format!("{}-appimage", target))
+            .filter(|_| os == "linux")
+            .or_else(|| self.platforms.get(&target))
+    }
+}
+
 /// Check for updates from GitHub releases
 pub async fn check_for_updates() -> Result<UpdateInfo, String> {
-    let client = reqwest::Client::builder()
+    let client = crate::utils::http::global_client_builder(10)?
         .user_agent("Antigravity-Manager")
-        .timeout(std::time::Duration::from_secs(10))
         .build()
         .map_err(|e| {
             let err_msg = format!("Failed to create HTTP client: {}", e);
@@ -101,6 +171,146 @@ pub async fn check_for_updates() -> Result<UpdateInfo, String> {
     })
 }
 
+fn http_client(timeout_secs: u64) -> Result<reqwest::Client, String> {
+    crate::utils::http::global_client_builder(timeout_secs)?
+        .user_agent("Antigravity-Manager")
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+fn updater_plugin_config() -> serde_json::Value {
+    serde_json::from_str::<serde_json::Value>(TAURI_CONF)
+        .ok()
+        .and_then(|v| v.pointer("/plugins/updater").cloned())
+        .unwrap_or_default()
+}
+
+fn updater_endpoint() -> Result<String, String> {
+    updater_plugin_config()
+        .pointer("/endpoints/0")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .ok_or_else(|| "No updater endpoint configured".to_string())
+}
+
+/// Releases API of the repository behind a `https://github.com/{owner}/{repo}/releases/...`
+/// updater endpoint, so both channels come from the owner of the signing key
+fn releases_api_url(endpoint: &str) -> Option<String> {
+    let path = endpoint.strip_prefix("https://github.com/")?;
+    let mut parts = path.split('/');
+    let (owner, repo) = (parts.next()?, parts.next()?);
+    if owner.is_empty() || repo.is_empty() || parts.next() != Some("releases") {
+        return None;
+    }
+    Some(format!(
+        "https://api.github.com/repos/{}/{}/releases?per_page=10",
+        owner, repo
+    ))
+}
+
+/// Locate the updater manifest for a channel. Stable uses the updater endpoint,
+/// beta uses the newest release (including pre-releases) of the same repository.
+async fn manifest_url(client: &reqwest::Client, channel: UpdateChannel) -> Result<String, String> {
+    let endpoint = updater_endpoint()?;
+    match channel {
+        UpdateChannel::Stable => Ok(endpoint),
+        UpdateChannel::Beta => {
+            let releases_url = releases_api_url(&endpoint)
+                .ok_or_else(|| format!("Updater endpoint is not a GitHub release: {}", endpoint))?;
+            let releases: Vec<GitHubReleaseAssets> = client
+                .get(&releases_url)
+                .send()
+                .await
+                .map_err(|e| format!("Failed to fetch release info: {}", e))?
+                .error_for_status()
+                .map_err(|e| format!("GitHub API returned error: {}", e))?
+                .json()
+                .await
+                .map_err(|e| format!("Failed to parse release info: {}", e))?;
+            releases
+                .into_iter()
+                .filter(|r| !r.draft)
+                .find_map(|r| {
+                    r.assets
+                        .into_iter()
+                        .find(|a| a.name == UPDATER_MANIFEST_ASSET)
+                        .map(|a| a.browser_download_url)
+                })
+                .ok_or_else(|| "No release with an updater manifest found".to_string())
+        }
+    }
+}
+
+/// Fetch the signed updater manifest for a channel
+pub async fn fetch_update_manifest(channel: UpdateChannel) -> Result<UpdateManifest, String> {
+    let client = http_client(15)?;
+    let url = manifest_url(&client, channel).await?;
+    logger::log_info(&format!("Fetching updater manifest ({:?}): {}", channel, url));
+
+    client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch updater manifest: {}", e))?
+        .error_for_status()
+        .map_err(|e| format!("Updater manifest request failed: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse updater manifest: {}", e))
+}
+
+/// Download an update artifact and verify its signature before returning it
+pub async fn download_verified(artifact: &UpdateArtifact) -> Result<Vec<u8>, String> {
+    let client = http_client(600)?;
+    let bytes = client
+        .get(&artifact.url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download update: {}", e))?
+        .error_for_status()
+        .map_err(|e| format!("Update download failed: {}", e))?
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to download update: {}", e))?;
+
+    let pubkey = updater_plugin_config()
+        .get("pubkey")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .ok_or_else(|| "No updater public key configured".to_string())?;
+    verify_signature(&bytes, &artifact.signature, &pubkey)?;
+    Ok(bytes.to_vec())
+}
+
+/// Verify a minisign signature. Keys and signatures may be given either as the bare
+/// base64 key line or base64 encoded minisign files (the format `tauri signer` emits).
+fn verify_signature(data: &[u8], signature: &str, pubkey: &str) -> Result<(), String> {
+    use base64::Engine;
+    use minisign_verify::{PublicKey, Signature};
+
+    let decode_text = |value: &str| -> Option<String> {
+        base64::engine::general_purpose::STANDARD
+            .decode(value.trim())
+            .ok()
+            .and_then(|b| String::from_utf8(b).ok())
+    };
+
+    let key = match decode_text(pubkey).filter(|t| t.contains('\n')) {
+        Some(text) => PublicKey::decode(&text),
+        None => PublicKey::from_base64(pubkey.trim()),
+    }
+    .map_err(|e| format!("Invalid updater public key: {}", e))?;
+
+    let signature = decode_text(signature)
+        .ok_or_else(|| "Invalid update signature encoding".to_string())
+        .and_then(|text| {
+            Signature::decode(&text).map_err(|e| format!("Invalid update signature: {}", e))
+        })?;
+
+    key.verify(data, &signature, true)
+        .map_err(|e| format!("Update signature verification failed: {}", e))
+}
+
 /// Compare two semantic versions (e.g., "3.3.30" vs "3.3.29")
 fn compare_versions(latest: &str, current: &str) -> bool {
     let parse_version = |v: &str| -> Vec<u32> {
@@ -165,11 +375,15 @@ pub fn load_update_settings() -> Result<UpdateSettings, String> {
 
 /// Save update settings to config file
 pub fn save_update_settings(settings: &UpdateSettings) -> Result<(), String> {
+    let mut settings = settings.clone();
+    if settings.channel.is_none() {
+        settings.channel = load_update_settings().ok().and_then(|s| s.channel);
+    }
     let data_dir = crate::modules::account::get_data_dir()
         .map_err(|e| format!("Failed to get data dir: {}", e))?;
     let settings_path = data_dir.join("update_settings.json");
 
-    let content = serde_json::to_string_pretty(settings)
+    let content = serde_json::to_string_pretty(&settings)
         .map_err(|e| format!("Failed to serialize settings: {}", e))?;
 
     std::fs::write(&settings_path, content)
@@ -199,6 +413,37 @@ mod tests {
         assert!(!compare_versions("3.3.35", "3.3.35"));
     }
 
+    #[test]
+    fn test_artifact_for_current_platform() {
+        let manifest: UpdateManifest = serde_json::from_value(serde_json::json!({
+            "version": "v9.9.9",
+            "platforms": {
+                "linux-x86_64": {"signature": "s", "url": "linux"},
+                "darwin-aarch64": {"signature": "s", "url": "mac"},
+                "windows-x86_64": {"signature": "s", "url": "win"}
+            }
+        }))
+        .unwrap();
+        assert_eq!(manifest.version(), "9.9.9");
+        assert!(manifest.is_newer());
+        if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
+            assert_eq!(manifest.artifact_for_current_platform().unwrap().url, "linux");
+        }
+        assert!(verify_signature(b"data", "not-a-signature", "RWSQvAJOE+udAGSUoZB7Y7EhAGOyZkB1zHaKwRYfldgig5rIF+PVUDbo").is_err());
+    }
+
+    #[test]
+    fn test_releases_api_url() {
+        let endpoint =
+            "https://github.com/lbjlaq/Antigravity-Manager/releases/latest/download/updater.json";
+        assert_eq!(
+            releases_api_url(endpoint).as_deref(),
+            Some("https://api.github.com/repos/lbjlaq/Antigravity-Manager/releases?per_page=10")
+        );
+        assert!(releases_api_url("https://example.com/updater.json").is_none());
+        assert!(releases_api_url(&updater_endpoint().unwrap()).is_some());
+    }
+
     #[test]
     fn test_should_check_for_updates() {
         let mut settings = UpdateSettings::default();
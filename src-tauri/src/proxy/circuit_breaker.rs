@@ -0,0 +1,350 @@
+// 账号熔断器
+//
+// 与 RateLimitTracker 互补：限流跟踪器处理上游明确下发的限流/配额信号，熔断器处理账号持续出错
+// (5xx、网络错误) 的情况。每个账号记录滚动窗口内的请求结果与连续失败次数：
+// - Closed: 正常调度；连续失败达到阈值后打开
+// - Open: 不参与调度，退避时间到期后转为 HalfOpen (每次重新打开时退避时间翻倍，有上限)
+// - HalfOpen: 只放行一个探测请求，成功则关闭，失败则重新打开
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// 熔断器配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+    pub enabled: bool,
+    /// 连续失败多少次后打开熔断
+    pub failure_threshold: u32,
+    /// 首次打开的退避时间 (秒)
+    pub open_secs: u64,
+    /// 退避时间上限 (秒)
+    pub max_open_secs: u64,
+    /// 统计错误率的滚动窗口 (秒)
+    pub window_secs: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            failure_threshold: 5,
+            open_secs: 30,
+            max_open_secs: 600,
+            window_secs: 300,
+        }
+    }
+}
+
+/// 探测请求迟迟没有结果 (客户端中途断开等) 时，超过该时间后允许发起新的探测
+const PROBE_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+/// 账号熔断状态 (供账号列表展示)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CircuitBreakerStatus {
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    /// 滚动窗口内的错误率 (0-1)
+    pub error_rate: f64,
+    /// 滚动窗口内的请求数
+    pub window_requests: u32,
+    /// 熔断打开时距离半开探测的剩余毫秒数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_in_ms: Option<u64>,
+}
+
+struct AccountCircuit {
+    state: CircuitState,
+    consecutive_failures: u32,
+    /// 已连续打开的次数，用于指数退避
+    open_count: u32,
+    open_until: Option<Instant>,
+    probe_started: Option<Instant>,
+    /// 滚动窗口内的请求结果 (时间, 是否失败)
+    outcomes: VecDeque<(Instant, bool)>,
+}
+
+impl AccountCircuit {
+    fn new() -> Self {
+        Self {
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            open_count: 0,
+            open_until: None,
+            probe_started: None,
+            outcomes: VecDeque::new(),
+        }
+    }
+
+    fn record(&mut self, now: Instant, failed: bool, window: Duration) {
+        self.outcomes.push_back((now, failed));
+        while let Some((at, _)) = self.outcomes.front() {
+            if now.saturating_duration_since(*at) <= window {
+                break;
+            }
+            self.outcomes.pop_front();
+        }
+    }
+
+    /// Open 到期后转为 HalfOpen
+    fn refresh(&mut self, now: Instant) {
+        if self.state == CircuitState::Open && self.open_until.is_some_and(|until| now >= until) {
+            self.state = CircuitState::HalfOpen;
+            self.open_until = None;
+            self.probe_started = None;
+        }
+    }
+}
+
+pub struct CircuitBreaker {
+    circuits: DashMap<String, AccountCircuit>,
+    config: RwLock<CircuitBreakerConfig>,
+}
+
+impl CircuitBreaker {
+    pub fn new() -> Self {
+        Self {
+            circuits: DashMap::new(),
+            config: RwLock::new(CircuitBreakerConfig::default()),
+        }
+    }
+
+    /// 更新配置；关闭熔断器时清空所有状态
+    pub fn set_config(&self, config: CircuitBreakerConfig) {
+        if !config.enabled {
+            self.circuits.clear();
+        }
+        *self.config.write() = config;
+    }
+
+    /// 账号当前是否被熔断 (Open，或 HalfOpen 且已有探测请求在途)
+    pub fn is_open(&self, account_id: &str) -> bool {
+        self.is_open_at(account_id, Instant::now())
+    }
+
+    fn is_open_at(&self, account_id: &str, now: Instant) -> bool {
+        if !self.config.read().enabled {
+            return false;
+        }
+        let Some(mut circuit) = self.circuits.get_mut(account_id) else {
+            return false;
+        };
+        circuit.refresh(now);
+        match circuit.state {
+            CircuitState::Closed => false,
+            CircuitState::Open => true,
+            CircuitState::HalfOpen => circuit
+                .probe_started
+                .is_some_and(|started| now.saturating_duration_since(started) < PROBE_TIMEOUT),
+        }
+    }
+
+    /// 账号被选中处理请求；HalfOpen 状态下且没有探测请求在途时，该请求即为探测请求
+    pub fn on_selected(&self, account_id: &str) {
+        self.on_selected_at(account_id, Instant::now());
+    }
+
+    fn on_selected_at(&self, account_id: &str, now: Instant) {
+        if let Some(mut circuit) = self.circuits.get_mut(account_id) {
+            circuit.refresh(now);
+            let probe_in_flight = circuit
+                .probe_started
+                .is_some_and(|started| now.saturating_duration_since(started) < PROBE_TIMEOUT);
+            if circuit.state == CircuitState::HalfOpen && !probe_in_flight {
+                circuit.probe_started = Some(now);
+                tracing::info!("[CircuitBreaker] 账号 {} 半开，发送探测请求", account_id);
+            }
+        }
+    }
+
+    pub fn record_success(&self, account_id: &str) {
+        self.record_success_at(account_id, Instant::now());
+    }
+
+    fn record_success_at(&self, account_id: &str, now: Instant) {
+        let window = Duration::from_secs(self.config.read().window_secs);
+        let mut circuit = self
+            .circuits
+            .entry(account_id.to_string())
+            .or_insert_with(AccountCircuit::new);
+        circuit.record(now, false, window);
+        if circuit.state != CircuitState::Closed {
+            tracing::info!("[CircuitBreaker] 账号 {} 探测成功，熔断关闭", account_id);
+        }
+        circuit.state = CircuitState::Closed;
+        circuit.consecutive_failures = 0;
+        circuit.open_count = 0;
+        circuit.open_until = None;
+        circuit.probe_started = None;
+    }
+
+    /// 不计入统计的请求结果；若该请求是半开探测，则释放探测名额
+    pub fn record_neutral(&self, account_id: &str) {
+        if let Some(mut circuit) = self.circuits.get_mut(account_id) {
+            circuit.probe_started = None;
+        }
+    }
+
+    pub fn record_failure(&self, account_id: &str) {
+        self.record_failure_at(account_id, Instant::now());
+    }
+
+    fn record_failure_at(&self, account_id: &str, now: Instant) {
+        let config = self.config.read().clone();
+        if !config.enabled {
+            return;
+        }
+        let mut circuit = self
+            .circuits
+            .entry(account_id.to_string())
+            .or_insert_with(AccountCircuit::new);
+        circuit.record(now, true, Duration::from_secs(config.window_secs));
+        circuit.refresh(now);
+        circuit.consecutive_failures += 1;
+
+        let should_open = match circuit.state {
+            CircuitState::Closed => circuit.consecutive_failures >= config.failure_threshold.max(1),
+            CircuitState::HalfOpen => true,
+            CircuitState::Open => false,
+        };
+        if should_open {
+            let backoff = config
+                .open_secs
+                .saturating_mul(1u64 << circuit.open_count.min(16))
+                .min(config.max_open_secs.max(config.open_secs));
+            circuit.state = CircuitState::Open;
+            circuit.open_until = Some(now + Duration::from_secs(backoff));
+            circuit.probe_started = None;
+            circuit.open_count += 1;
+            tracing::warn!(
+                "[CircuitBreaker] 账号 {} 连续失败 {} 次，熔断 {} 秒",
+                account_id,
+                circuit.consecutive_failures,
+                backoff
+            );
+        }
+    }
+
+    /// 账号的熔断状态，从未记录过请求结果时返回 None
+    pub fn status(&self, account_id: &str) -> Option<CircuitBreakerStatus> {
+        self.status_at(account_id, Instant::now())
+    }
+
+    fn status_at(&self, account_id: &str, now: Instant) -> Option<CircuitBreakerStatus> {
+        let window = Duration::from_secs(self.config.read().window_secs);
+        let mut circuit = self.circuits.get_mut(account_id)?;
+        circuit.refresh(now);
+        let recent: Vec<bool> = circuit
+            .outcomes
+            .iter()
+            .filter(|(at, _)| now.saturating_duration_since(*at) <= window)
+            .map(|(_, failed)| *failed)
+            .collect();
+        let failures = recent.iter().filter(|failed| **failed).count();
+        Some(CircuitBreakerStatus {
+            state: circuit.state,
+            consecutive_failures: circuit.consecutive_failures,
+            error_rate: if recent.is_empty() {
+                0.0
+            } else {
+                failures as f64 / recent.len() as f64
+            },
+            window_requests: recent.len() as u32,
+            retry_in_ms: circuit
+                .open_until
+                .map(|until| until.saturating_duration_since(now).as_millis() as u64),
+        })
+    }
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(threshold: u32) -> CircuitBreaker {
+        let breaker = CircuitBreaker::new();
+        breaker.set_config(CircuitBreakerConfig {
+            failure_threshold: threshold,
+            open_secs: 10,
+            max_open_secs: 30,
+            ..Default::default()
+        });
+        breaker
+    }
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let cb = breaker(3);
+        let now = Instant::now();
+        cb.record_failure_at("a", now);
+        cb.record_failure_at("a", now);
+        cb.record_success_at("a", now);
+        cb.record_failure_at("a", now);
+        cb.record_failure_at("a", now);
+        assert!(!cb.is_open_at("a", now));
+        cb.record_failure_at("a", now);
+        assert!(cb.is_open_at("a", now));
+
+        let status = cb.status_at("a", now).unwrap();
+        assert_eq!(status.state, CircuitState::Open);
+        assert_eq!(status.window_requests, 6);
+        assert!((status.error_rate - 5.0 / 6.0).abs() < 1e-9);
+        assert_eq!(status.retry_in_ms, Some(10_000));
+    }
+
+    #[test]
+    fn half_open_allows_single_probe() {
+        let cb = breaker(1);
+        let now = Instant::now();
+        cb.record_failure_at("a", now);
+        let later = now + Duration::from_secs(10);
+        assert!(!cb.is_open_at("a", later));
+        assert_eq!(cb.status_at("a", later).unwrap().state, CircuitState::HalfOpen);
+
+        cb.on_selected_at("a", later);
+        assert!(cb.is_open_at("a", later));
+
+        // 探测在途时再次被选中不会重新开始探测计时
+        cb.on_selected_at("a", later + Duration::from_secs(60));
+        assert!(!cb.is_open_at("a", later + PROBE_TIMEOUT));
+
+        cb.record_success_at("a", later);
+        assert!(!cb.is_open_at("a", later));
+        assert_eq!(cb.status_at("a", later).unwrap().state, CircuitState::Closed);
+    }
+
+    #[test]
+    fn failed_probe_reopens_with_longer_backoff() {
+        let cb = breaker(1);
+        let now = Instant::now();
+        cb.record_failure_at("a", now);
+        let probe_at = now + Duration::from_secs(10);
+        cb.record_failure_at("a", probe_at);
+        assert!(cb.is_open_at("a", probe_at + Duration::from_secs(19)));
+        assert!(!cb.is_open_at("a", probe_at + Duration::from_secs(20)));
+
+        // 退避时间不超过上限
+        let mut at = probe_at + Duration::from_secs(20);
+        for _ in 0..5 {
+            cb.record_failure_at("a", at);
+            at += Duration::from_secs(30);
+        }
+        assert_eq!(cb.status_at("a", at - Duration::from_secs(30)).unwrap().retry_in_ms, Some(30_000));
+    }
+}
@@ -0,0 +1,251 @@
+// 客户端 API Key 限流 (令牌桶)
+//
+// 每个客户端 Key 有两个桶：请求数/分钟与 tokens/分钟。请求进入时扣除一个请求令牌，
+// tokens 桶只检查是否仍有余量；响应结束后按实际用量扣除 tokens，允许透支一个周期的额度，
+// 透支期间后续请求会被拒绝直到桶重新回正。
+use dashmap::DashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 令牌桶，容量即每分钟额度，按 容量/60 每秒匀速回填
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    tokens: f64,
+    capacity: u32,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    fn full(capacity: u32, now: Instant) -> Self {
+        Self {
+            tokens: capacity as f64,
+            capacity,
+            updated_at: now,
+        }
+    }
+
+    fn refill(&mut self, capacity: u32, now: Instant) {
+        // 额度调整后按新容量计算：调小时截断余量，从不限制改为限制时从满桶开始
+        if capacity != self.capacity {
+            self.tokens = if self.capacity == 0 {
+                capacity as f64
+            } else {
+                self.tokens.min(capacity as f64)
+            };
+            self.capacity = capacity;
+        }
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        let capacity = capacity as f64;
+        self.tokens = (self.tokens + elapsed * capacity / 60.0).min(capacity);
+        self.updated_at = now;
+    }
+
+    /// 桶内达到 `needed` 个令牌还需等待的时间
+    fn wait_for(&self, capacity: u32, needed: f64) -> Duration {
+        if self.tokens >= needed {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64((needed - self.tokens) * 60.0 / capacity as f64)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct KeyBuckets {
+    requests: TokenBucket,
+    tokens: TokenBucket,
+}
+
+/// 待结算的 tokens 额度：限流中间件放行请求后挂在响应扩展上，
+/// 请求日志解析出实际用量后调用 `settle` 扣除 (缓存命中等未经过限流的响应没有该扩展)
+#[derive(Clone)]
+pub struct PendingTokenCharge {
+    limiter: Arc<ClientRateLimiter>,
+    key_id: String,
+    tokens_per_minute: u32,
+}
+
+impl PendingTokenCharge {
+    pub fn new(limiter: Arc<ClientRateLimiter>, key_id: String, tokens_per_minute: u32) -> Self {
+        Self {
+            limiter,
+            key_id,
+            tokens_per_minute,
+        }
+    }
+
+    pub fn settle(&self, used: u64) {
+        self.limiter
+            .charge_tokens(&self.key_id, self.tokens_per_minute, used);
+    }
+}
+
+/// 按客户端 Key 的限流器，额度为 0 表示不限制
+pub struct ClientRateLimiter {
+    buckets: DashMap<String, KeyBuckets>,
+}
+
+impl Default for ClientRateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClientRateLimiter {
+    pub fn new() -> Self {
+        Self {
+            buckets: DashMap::new(),
+        }
+    }
+
+    /// 尝试放行一个请求；被限流时返回建议的重试等待秒数
+    pub fn try_acquire(
+        &self,
+        key_id: &str,
+        requests_per_minute: u32,
+        tokens_per_minute: u32,
+    ) -> Result<(), u64> {
+        self.try_acquire_at(
+            key_id,
+            requests_per_minute,
+            tokens_per_minute,
+            Instant::now(),
+        )
+    }
+
+    fn try_acquire_at(
+        &self,
+        key_id: &str,
+        requests_per_minute: u32,
+        tokens_per_minute: u32,
+        now: Instant,
+    ) -> Result<(), u64> {
+        if requests_per_minute == 0 && tokens_per_minute == 0 {
+            return Ok(());
+        }
+        let mut entry = self
+            .buckets
+            .entry(key_id.to_string())
+            .or_insert_with(|| KeyBuckets {
+                requests: TokenBucket::full(requests_per_minute, now),
+                tokens: TokenBucket::full(tokens_per_minute, now),
+            });
+        let buckets = entry.value_mut();
+
+        let mut wait = Duration::ZERO;
+        if tokens_per_minute > 0 {
+            buckets.tokens.refill(tokens_per_minute, now);
+            wait = wait.max(buckets.tokens.wait_for(tokens_per_minute, 1.0));
+        }
+        if requests_per_minute > 0 {
+            buckets.requests.refill(requests_per_minute, now);
+            wait = wait.max(buckets.requests.wait_for(requests_per_minute, 1.0));
+        }
+        if !wait.is_zero() {
+            return Err(wait.as_secs_f64().ceil().max(1.0) as u64);
+        }
+
+        if requests_per_minute > 0 {
+            buckets.requests.tokens -= 1.0;
+        }
+        Ok(())
+    }
+
+    /// 只保留仍然有效的 Key 的桶 (吊销或删除的 Key 不再占用内存)
+    pub fn retain_keys(&self, is_active: impl Fn(&str) -> bool) {
+        self.buckets.retain(|key_id, _| is_active(key_id));
+    }
+
+    /// 请求结束后按实际用量扣除 tokens 额度
+    pub fn charge_tokens(&self, key_id: &str, tokens_per_minute: u32, used: u64) {
+        self.charge_tokens_at(key_id, tokens_per_minute, used, Instant::now());
+    }
+
+    fn charge_tokens_at(&self, key_id: &str, tokens_per_minute: u32, used: u64, now: Instant) {
+        if tokens_per_minute == 0 || used == 0 {
+            return;
+        }
+        if let Some(mut entry) = self.buckets.get_mut(key_id) {
+            let bucket = &mut entry.value_mut().tokens;
+            bucket.refill(tokens_per_minute, now);
+            // 最多透支一个周期，避免单个超大请求让 Key 长时间不可用
+            bucket.tokens = (bucket.tokens - used as f64).max(-(tokens_per_minute as f64));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_bucket_refills_over_time() {
+        let limiter = ClientRateLimiter::new();
+        let start = Instant::now();
+        for _ in 0..2 {
+            assert!(limiter.try_acquire_at("k1", 2, 0, start).is_ok());
+        }
+        assert_eq!(limiter.try_acquire_at("k1", 2, 0, start), Err(30));
+        // 2 次/分钟 => 每 30 秒回填一个
+        assert!(limiter
+            .try_acquire_at("k1", 2, 0, start + Duration::from_secs(30))
+            .is_ok());
+        // 其它 Key 不受影响
+        assert!(limiter.try_acquire_at("k2", 2, 0, start).is_ok());
+    }
+
+    #[test]
+    fn token_usage_blocks_until_repaid() {
+        let limiter = ClientRateLimiter::new();
+        let start = Instant::now();
+        assert!(limiter.try_acquire_at("k1", 0, 600, start).is_ok());
+        limiter.charge_tokens_at("k1", 600, 900, start);
+        // 透支 300 tokens (下限 -600)，回填速度 10 tokens/秒
+        assert_eq!(limiter.try_acquire_at("k1", 0, 600, start), Err(31));
+        assert!(limiter
+            .try_acquire_at("k1", 0, 600, start + Duration::from_secs(31))
+            .is_ok());
+    }
+
+    #[test]
+    fn changed_limits_resize_buckets() {
+        let limiter = ClientRateLimiter::new();
+        let start = Instant::now();
+        for _ in 0..10 {
+            assert!(limiter.try_acquire_at("k1", 10, 0, start).is_ok());
+        }
+        // 调大额度后不会立即获得整桶，按新速率回填
+        assert_eq!(limiter.try_acquire_at("k1", 60, 0, start), Err(1));
+        assert!(limiter
+            .try_acquire_at("k1", 60, 0, start + Duration::from_secs(1))
+            .is_ok());
+
+        // 调小额度后余量被截断
+        assert!(limiter.try_acquire_at("k2", 100, 0, start).is_ok());
+        assert!(limiter.try_acquire_at("k2", 1, 0, start).is_ok());
+        assert_eq!(limiter.try_acquire_at("k2", 1, 0, start), Err(60));
+
+        // 从不限制改为限制时从满桶开始
+        assert!(limiter.try_acquire_at("k3", 1, 0, start).is_ok());
+        assert!(limiter.try_acquire_at("k3", 0, 600, start).is_ok());
+    }
+
+    #[test]
+    fn retain_keys_evicts_revoked() {
+        let limiter = ClientRateLimiter::new();
+        let now = Instant::now();
+        assert!(limiter.try_acquire_at("k1", 1, 0, now).is_ok());
+        assert!(limiter.try_acquire_at("k2", 1, 0, now).is_ok());
+        limiter.retain_keys(|id| id == "k2");
+        assert!(limiter.try_acquire_at("k1", 1, 0, now).is_ok());
+        assert!(limiter.try_acquire_at("k2", 1, 0, now).is_err());
+    }
+
+    #[test]
+    fn zero_limits_disable_limiting() {
+        let limiter = ClientRateLimiter::new();
+        let now = Instant::now();
+        for _ in 0..1000 {
+            assert!(limiter.try_acquire_at("k1", 0, 0, now).is_ok());
+        }
+    }
+}
@@ -3,5 +3,10 @@
 // pub mod error;
 // pub mod rate_limiter;
 pub mod model_mapping;
+pub mod model_capabilities;
 pub mod utils;
 pub mod json_schema;
+pub mod redact;
+pub mod prompt_cache;
+pub mod pricing;
+pub mod sse;
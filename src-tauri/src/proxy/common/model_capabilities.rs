@@ -0,0 +1,339 @@
+// 模型能力表
+// 供 guard / mapper 查询上下文窗口、最大输出、thinking/vision/tools 支持情况。
+// 内置默认值随版本发布，用户可在配置 `proxy.model_capabilities` 中按模型名 (支持 * 通配符) 覆盖，
+// 使上游新模型无需等待新版本即可正确处理。
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use super::model_mapping::wildcard_match;
+use crate::proxy::config::ModelCapabilityOverride;
+
+/// 模型能力 (已合并内置值与用户覆盖)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ModelCapability {
+    pub context_window: u32,
+    pub max_output_tokens: u32,
+    /// thinkingBudget 上限，None 表示不额外限制
+    pub max_thinking_budget: Option<u32>,
+    pub supports_thinking: bool,
+    pub supports_vision: bool,
+    pub supports_tools: bool,
+}
+
+impl ModelCapability {
+    fn apply(&mut self, o: &ModelCapabilityOverride) {
+        if let Some(v) = o.context_window {
+            self.context_window = v;
+        }
+        if let Some(v) = o.max_output_tokens {
+            self.max_output_tokens = v;
+        }
+        if let Some(v) = o.max_thinking_budget {
+            self.max_thinking_budget = Some(v);
+        }
+        if let Some(v) = o.supports_thinking {
+            self.supports_thinking = v;
+        }
+        if let Some(v) = o.supports_vision {
+            self.supports_vision = v;
+        }
+        if let Some(v) = o.supports_tools {
+            self.supports_tools = v;
+        }
+    }
+}
+
+/// 未命中任何规则时的默认能力
+const FALLBACK: ModelCapability = ModelCapability {
+    context_window: 1_048_576,
+    max_output_tokens: 65_536,
+    max_thinking_budget: None,
+    supports_thinking: false,
+    supports_vision: true,
+    supports_tools: true,
+};
+
+/// 内置能力表 (按顺序匹配，先命中者生效，因此更具体的规则必须排在前面)
+const BUILTIN: &[(&str, ModelCapability)] = &[
+    (
+        "claude-*",
+        ModelCapability {
+            context_window: 2_097_152,
+            max_output_tokens: 64_000,
+            max_thinking_budget: None,
+            supports_thinking: true,
+            supports_vision: true,
+            supports_tools: true,
+        },
+    ),
+    (
+        "gemini-2.5-flash-thinking*",
+        ModelCapability {
+            context_window: 1_048_576,
+            max_output_tokens: 65_536,
+            max_thinking_budget: Some(24_576),
+            supports_thinking: true,
+            supports_vision: true,
+            supports_tools: true,
+        },
+    ),
+    (
+        "gemini-2.5-flash*",
+        ModelCapability {
+            context_window: 1_048_576,
+            max_output_tokens: 65_536,
+            max_thinking_budget: Some(24_576),
+            supports_thinking: false,
+            supports_vision: true,
+            supports_tools: true,
+        },
+    ),
+    (
+        "gemini-3-pro-image*",
+        ModelCapability {
+            context_window: 65_536,
+            max_output_tokens: 32_768,
+            max_thinking_budget: None,
+            supports_thinking: false,
+            supports_vision: true,
+            supports_tools: false,
+        },
+    ),
+    (
+        "gemini-3-pro*-thinking*",
+        ModelCapability {
+            context_window: 2_097_152,
+            max_output_tokens: 65_536,
+            max_thinking_budget: None,
+            supports_thinking: true,
+            supports_vision: true,
+            supports_tools: true,
+        },
+    ),
+    (
+        "gemini-3-flash*-thinking*",
+        ModelCapability {
+            context_window: 1_048_576,
+            max_output_tokens: 65_536,
+            max_thinking_budget: None,
+            supports_thinking: true,
+            supports_vision: true,
+            supports_tools: true,
+        },
+    ),
+    (
+        "gemini-3-pro*",
+        ModelCapability {
+            context_window: 2_097_152,
+            max_output_tokens: 65_536,
+            max_thinking_budget: None,
+            supports_thinking: false,
+            supports_vision: true,
+            supports_tools: true,
+        },
+    ),
+    (
+        "gemini-3-flash*",
+        ModelCapability {
+            context_window: 1_048_576,
+            max_output_tokens: 65_536,
+            max_thinking_budget: None,
+            supports_thinking: false,
+            supports_vision: true,
+            supports_tools: true,
+        },
+    ),
+    (
+        "*-thinking*",
+        ModelCapability {
+            context_window: 1_048_576,
+            max_output_tokens: 65_536,
+            max_thinking_budget: None,
+            supports_thinking: true,
+            supports_vision: true,
+            supports_tools: true,
+        },
+    ),
+];
+
+/// 用户覆盖表 (由反代启动 / 配置保存时写入)
+static OVERRIDES: Lazy<RwLock<HashMap<String, ModelCapabilityOverride>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// 替换用户覆盖表
+pub fn set_overrides(overrides: HashMap<String, ModelCapabilityOverride>) {
+    *OVERRIDES.write() = overrides;
+}
+
+fn builtin_capability(model: &str) -> ModelCapability {
+    BUILTIN
+        .iter()
+        .find(|(pattern, _)| wildcard_match(pattern, model))
+        .map(|(_, cap)| *cap)
+        .unwrap_or(FALLBACK)
+}
+
+fn lookup_with(
+    model: &str,
+    overrides: &HashMap<String, ModelCapabilityOverride>,
+) -> ModelCapability {
+    let model = model.to_lowercase();
+    let mut cap = builtin_capability(&model);
+
+    // 覆盖优先级：精确匹配 > 更长 (更具体) 的通配符规则
+    let mut matched: Vec<(&String, &ModelCapabilityOverride)> = overrides
+        .iter()
+        .filter(|(pattern, _)| wildcard_match(&pattern.to_lowercase(), &model))
+        .collect();
+    matched.sort_by_key(|(pattern, _)| (!pattern.contains('*'), pattern.len()));
+    for (_, o) in matched {
+        cap.apply(o);
+    }
+    cap
+}
+
+/// 查询模型能力 (已应用用户覆盖)
+pub fn lookup(model: &str) -> ModelCapability {
+    lookup_with(model, &OVERRIDES.read())
+}
+
+/// 目标模型不支持图片输入时，把 contents 中的图片片段 (inlineData / fileData) 替换为文字说明，
+/// 返回替换的数量
+pub fn strip_image_parts(contents: &mut Value) -> usize {
+    let mut stripped = 0;
+    let Some(contents) = contents.as_array_mut() else {
+        return 0;
+    };
+    for part in contents
+        .iter_mut()
+        .filter_map(|c| c.get_mut("parts").and_then(|p| p.as_array_mut()))
+        .flatten()
+    {
+        if part.get("inlineData").is_some() || part.get("fileData").is_some() {
+            *part = json!({"text": "[image omitted: the target model does not accept images]"});
+            stripped += 1;
+        }
+    }
+    stripped
+}
+
+/// 模型是否已被能力表 (内置规则或用户覆盖) 显式覆盖，未覆盖的模型只能使用兜底能力
+pub fn is_known(model: &str) -> bool {
+    let model = model.to_lowercase();
+    BUILTIN.iter().any(|(pattern, _)| wildcard_match(pattern, &model))
+        || OVERRIDES
+            .read()
+            .keys()
+            .any(|pattern| wildcard_match(&pattern.to_lowercase(), &model))
+}
+
+/// 能力表中的一行 (供前端展示)
+#[derive(Debug, Clone, Serialize)]
+pub struct CapabilityEntry {
+    pub pattern: String,
+    /// 是否来自用户配置
+    pub overridden: bool,
+    pub capability: ModelCapability,
+}
+
+/// 列出生效中的能力表：内置规则 + 用户自定义规则
+pub fn list_effective() -> Vec<CapabilityEntry> {
+    let overrides = OVERRIDES.read();
+    let mut entries: Vec<CapabilityEntry> = BUILTIN
+        .iter()
+        .map(|(pattern, _)| CapabilityEntry {
+            pattern: pattern.to_string(),
+            overridden: overrides.contains_key(*pattern),
+            capability: lookup_with(pattern, &overrides),
+        })
+        .collect();
+
+    let mut custom: Vec<&String> = overrides
+        .keys()
+        .filter(|k| !BUILTIN.iter().any(|(p, _)| p == k))
+        .collect();
+    custom.sort();
+    for pattern in custom {
+        entries.push(CapabilityEntry {
+            pattern: pattern.clone(),
+            overridden: true,
+            capability: lookup_with(pattern, &overrides),
+        });
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_lookup() {
+        let empty = HashMap::new();
+        assert!(lookup_with("claude-sonnet-4-5", &empty).supports_thinking);
+        assert!(lookup_with("gemini-2.5-flash-thinking", &empty).supports_thinking);
+        assert!(!lookup_with("gemini-2.5-flash", &empty).supports_thinking);
+        assert_eq!(
+            lookup_with("gemini-2.5-flash", &empty).max_thinking_budget,
+            Some(24_576)
+        );
+        assert!(!lookup_with("gemini-3-pro-image-4k", &empty).supports_tools);
+        assert!(lookup_with("some-new-thinking-model", &empty).supports_thinking);
+        assert_eq!(lookup_with("unknown", &empty), FALLBACK);
+        assert_eq!(lookup_with("claude-opus-4-5", &empty).context_window, 2_097_152);
+
+        // 更具体的 thinking 规则排在 gemini-3 通用规则之前
+        let pro_thinking = lookup_with("gemini-3-pro-high-thinking", &empty);
+        assert!(pro_thinking.supports_thinking);
+        assert_eq!(pro_thinking.context_window, 2_097_152);
+        assert!(lookup_with("gemini-3-flash-thinking", &empty).supports_thinking);
+        assert!(!lookup_with("gemini-3-pro-high", &empty).supports_thinking);
+    }
+
+    #[test]
+    fn test_strip_image_parts() {
+        let mut contents = json!([
+            {"role": "user", "parts": [
+                {"text": "what is this"},
+                {"inlineData": {"mimeType": "image/png", "data": "AAAA"}}
+            ]}
+        ]);
+        assert_eq!(strip_image_parts(&mut contents), 1);
+        assert_eq!(contents[0]["parts"][0]["text"], "what is this");
+        assert!(contents[0]["parts"][1].get("inlineData").is_none());
+        assert_eq!(strip_image_parts(&mut contents), 0);
+    }
+
+    #[test]
+    fn test_overrides_precedence() {
+        let mut overrides = HashMap::new();
+        overrides.insert(
+            "gemini-3-*".to_string(),
+            ModelCapabilityOverride {
+                supports_thinking: Some(true),
+                max_output_tokens: Some(8192),
+                ..Default::default()
+            },
+        );
+        overrides.insert(
+            "gemini-3-flash".to_string(),
+            ModelCapabilityOverride {
+                max_output_tokens: Some(16384),
+                ..Default::default()
+            },
+        );
+
+        let flash = lookup_with("gemini-3-flash", &overrides);
+        assert!(flash.supports_thinking);
+        assert_eq!(flash.max_output_tokens, 16384);
+
+        let pro = lookup_with("gemini-3-pro-high", &overrides);
+        assert!(pro.supports_thinking);
+        assert_eq!(pro.max_output_tokens, 8192);
+        assert_eq!(pro.context_window, 2_097_152);
+    }
+}
@@ -132,24 +132,204 @@ pub async fn get_all_dynamic_models(
 }
 
 /// 通配符匹配辅助函数
-/// 支持简单的 * 通配符匹配
+/// 支持任意数量的 * 通配符
 /// 
 /// # 示例
 /// - `gpt-4*` 匹配 `gpt-4`, `gpt-4-turbo`, `gpt-4-0613` 等
 /// - `claude-3-5-sonnet-*` 匹配所有 3.5 sonnet 版本
 /// - `*-thinking` 匹配所有以 `-thinking` 结尾的模型
-fn wildcard_match(pattern: &str, text: &str) -> bool {
-    if let Some(star_pos) = pattern.find('*') {
-        let prefix = &pattern[..star_pos];
-        let suffix = &pattern[star_pos + 1..];
-        text.starts_with(prefix) && text.ends_with(suffix)
-    } else {
-        pattern == text
+/// - `*haiku*` 匹配名称中包含 `haiku` 的模型
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let first = parts[0];
+    let last = parts[parts.len() - 1];
+    if !text.starts_with(first) || text.len() < first.len() + last.len() || !text.ends_with(last) {
+        return false;
+    }
+
+    // 中间片段按顺序贪婪匹配
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// 正则规则前缀，例如 `re:^claude-3-5-haiku.*$`
+const REGEX_RULE_PREFIX: &str = "re:";
+
+/// 正则缓存上限；路由测试命令可传入任意未保存的规则，超出后整体清空重建
+const MAX_CACHED_REGEXES: usize = 256;
+
+/// 已编译的正则规则缓存 (编译失败记为 None，避免每个请求重复编译与告警)
+static REGEX_CACHE: Lazy<std::sync::Mutex<HashMap<String, Option<regex::Regex>>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+/// 模式规则的显式优先级 (proxy.mapping_priority，由反代启动 / 配置保存时写入)
+static RULE_PRIORITY: Lazy<parking_lot::RwLock<Vec<String>>> =
+    Lazy::new(|| parking_lot::RwLock::new(Vec::new()));
+
+pub fn set_rule_priority(priority: Vec<String>) {
+    *RULE_PRIORITY.write() = priority;
+}
+
+fn regex_match(expr: &str, text: &str) -> bool {
+    let mut cache = REGEX_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if cache.len() >= MAX_CACHED_REGEXES && !cache.contains_key(expr) {
+        cache.clear();
+    }
+    let compiled = cache.entry(expr.to_string()).or_insert_with(|| {
+        regex::Regex::new(expr)
+            .map_err(|e| {
+                crate::modules::logger::log_warn(&format!(
+                    "[Router] 无效的正则路由规则 {}: {}",
+                    expr, e
+                ))
+            })
+            .ok()
+    });
+    compiled.as_ref().map(|re| re.is_match(text)).unwrap_or(false)
+}
+
+/// 路由命中的规则类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteRuleKind {
+    Exact,
+    Wildcard,
+    Regex,
+    /// 未命中自定义规则，使用系统默认映射
+    Default,
+}
+
+/// 路由解析结果 (含命中的规则，供测试命令展示)
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RouteMatch {
+    pub model: String,
+    pub target: String,
+    pub kind: RouteRuleKind,
+    /// 命中的自定义规则原文 (系统默认映射时为空)
+    pub rule: Option<String>,
+}
+
+/// 通配符规则的字面字符数 (不含 *)，越多越具体
+fn wildcard_literal_len(pattern: &str) -> usize {
+    pattern.chars().filter(|c| *c != '*').count()
+}
+
+/// 在以模型名 (支持 * 通配符，不区分大小写) 为键的配置表中查找：精确匹配优先，
+/// 其次取字面字符最多的通配符规则，相同时按规则原文排序，结果不随 HashMap 遍历顺序变化
+pub fn lookup_by_model_pattern<'a, V>(model: &str, table: &'a HashMap<String, V>) -> Option<&'a V> {
+    let model = model.to_lowercase();
+    table
+        .iter()
+        .filter(|(pattern, _)| wildcard_match(&pattern.to_lowercase(), &model))
+        .min_by(|a, b| {
+            a.0.contains('*')
+                .cmp(&b.0.contains('*'))
+                .then_with(|| wildcard_literal_len(b.0).cmp(&wildcard_literal_len(a.0)))
+                .then_with(|| a.0.cmp(b.0))
+        })
+        .map(|(_, value)| value)
+}
+
+/// 按优先级排列的模式规则：先按 `priority` 中列出的顺序，其余规则在后：
+/// 通配符在前 (字面字符越多越具体、越优先)，正则在后 (按规则原文排序)
+fn ordered_pattern_rules<'a>(
+    custom_mapping: &'a std::collections::HashMap<String, String>,
+    priority: &[String],
+) -> Vec<(&'a String, &'a String, RouteRuleKind)> {
+    let mut rules: Vec<(&String, &String, RouteRuleKind)> = custom_mapping
+        .iter()
+        .filter_map(|(pattern, target)| {
+            if pattern.starts_with(REGEX_RULE_PREFIX) {
+                Some((pattern, target, RouteRuleKind::Regex))
+            } else if pattern.contains('*') {
+                Some((pattern, target, RouteRuleKind::Wildcard))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    rules.sort_by(|a, b| {
+        let rank = |kind: RouteRuleKind| if kind == RouteRuleKind::Wildcard { 0 } else { 1 };
+        let position = |p: &String| priority.iter().position(|r| r == p).unwrap_or(usize::MAX);
+        position(a.0)
+            .cmp(&position(b.0))
+            .then_with(|| rank(a.2).cmp(&rank(b.2)))
+            .then_with(|| {
+                if a.2 == RouteRuleKind::Wildcard {
+                    wildcard_literal_len(b.0).cmp(&wildcard_literal_len(a.0))
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+            .then_with(|| a.0.cmp(b.0))
+    });
+    rules
+}
+
+/// 解析模型路由并返回命中的规则 (使用已配置的规则优先级)
+pub fn explain_model_route(
+    original_model: &str,
+    custom_mapping: &std::collections::HashMap<String, String>,
+) -> RouteMatch {
+    explain_model_route_with(original_model, custom_mapping, &RULE_PRIORITY.read())
+}
+
+/// 解析模型路由并返回命中的规则
+/// 优先级：精确匹配 > `priority` 中列出的模式规则 (按列出顺序) > 通配符匹配 (越具体越优先)
+/// > 正则匹配 > 系统默认映射
+pub fn explain_model_route_with(
+    original_model: &str,
+    custom_mapping: &std::collections::HashMap<String, String>,
+    priority: &[String],
+) -> RouteMatch {
+    // 1. 精确匹配 (最高优先级)
+    if let Some(target) = custom_mapping.get(original_model) {
+        return RouteMatch {
+            model: original_model.to_string(),
+            target: target.clone(),
+            kind: RouteRuleKind::Exact,
+            rule: Some(original_model.to_string()),
+        };
+    }
+
+    // 2. 通配符 / 正则匹配
+    for (pattern, target, kind) in ordered_pattern_rules(custom_mapping, priority) {
+        let matched = match kind {
+            RouteRuleKind::Regex => regex_match(&pattern[REGEX_RULE_PREFIX.len()..], original_model),
+            _ => wildcard_match(pattern, original_model),
+        };
+        if matched {
+            return RouteMatch {
+                model: original_model.to_string(),
+                target: target.clone(),
+                kind,
+                rule: Some(pattern.clone()),
+            };
+        }
+    }
+
+    // 3. 系统默认映射
+    RouteMatch {
+        model: original_model.to_string(),
+        target: map_claude_model_to_gemini(original_model),
+        kind: RouteRuleKind::Default,
+        rule: None,
     }
 }
 
 /// 核心模型路由解析引擎
-/// 优先级：精确匹配 > 通配符匹配 > 系统默认映射
+/// 优先级：精确匹配 > 显式优先级中的模式规则 > 通配符匹配 > 正则匹配 > 系统默认映射
 /// 
 /// # 参数
 /// - `original_model`: 原始模型名称
@@ -161,26 +341,27 @@ pub fn resolve_model_route(
     original_model: &str,
     custom_mapping: &std::collections::HashMap<String, String>,
 ) -> String {
-    // 1. 精确匹配 (最高优先级)
-    if let Some(target) = custom_mapping.get(original_model) {
-        crate::modules::logger::log_info(&format!("[Router] 精确映射: {} -> {}", original_model, target));
-        return target.clone();
-    }
-    
-    // 2. 通配符匹配
-    for (pattern, target) in custom_mapping.iter() {
-        if pattern.contains('*') && wildcard_match(pattern, original_model) {
-            crate::modules::logger::log_info(&format!("[Router] 通配符映射: {} -> {} (规则: {})", original_model, target, pattern));
-            return target.clone();
+    let route = explain_model_route(original_model, custom_mapping);
+    match route.kind {
+        RouteRuleKind::Exact => {
+            crate::modules::logger::log_info(&format!("[Router] 精确映射: {} -> {}", original_model, route.target));
+        }
+        RouteRuleKind::Wildcard | RouteRuleKind::Regex => {
+            crate::modules::logger::log_info(&format!(
+                "[Router] {}映射: {} -> {} (规则: {})",
+                if route.kind == RouteRuleKind::Regex { "正则" } else { "通配符" },
+                original_model,
+                route.target,
+                route.rule.as_deref().unwrap_or_default()
+            ));
+        }
+        RouteRuleKind::Default => {
+            if route.target != original_model {
+                crate::modules::logger::log_info(&format!("[Router] 系统默认映射: {} -> {}", original_model, route.target));
+            }
         }
     }
-    
-    // 3. 系统默认映射
-    let result = map_claude_model_to_gemini(original_model);
-    if result != original_model {
-        crate::modules::logger::log_info(&format!("[Router] 系统默认映射: {} -> {}", original_model, result));
-    }
-    result
+    route.target
 }
 
 /// Normalize any physical model name to one of the 3 standard protection IDs.
@@ -233,4 +414,74 @@ mod tests {
             "claude-sonnet-4-5"
         );
     }
+
+    #[test]
+    fn test_lookup_by_model_pattern_is_deterministic() {
+        let mut table = HashMap::new();
+        table.insert("gemini-*".to_string(), 1);
+        table.insert("*-flash".to_string(), 2);
+        table.insert("gemini-*-flash".to_string(), 3);
+        table.insert("Gemini-2.5-Flash".to_string(), 4);
+        table.insert("claude-*".to_string(), 5);
+        table.insert("*-opus-4".to_string(), 6);
+
+        assert_eq!(
+            lookup_by_model_pattern("gemini-2.5-flash", &table),
+            Some(&4)
+        );
+        assert_eq!(lookup_by_model_pattern("gemini-3-flash", &table), Some(&3));
+        assert_eq!(lookup_by_model_pattern("gemini-3-pro", &table), Some(&1));
+        // 字面长度相同时按规则原文排序 ("*-opus-4" < "claude-*")
+        assert_eq!(lookup_by_model_pattern("claude-opus-4", &table), Some(&6));
+        assert_eq!(lookup_by_model_pattern("gpt-4o", &table), None);
+    }
+
+    #[test]
+    fn test_route_rule_priority() {
+        let mut mapping = std::collections::HashMap::new();
+        mapping.insert("claude-3-5-haiku*".to_string(), "gemini-2.5-flash".to_string());
+        mapping.insert("claude-*".to_string(), "claude-sonnet-4-5".to_string());
+        mapping.insert("re:^gpt-4o-(mini|nano)".to_string(), "gemini-2.5-flash-lite".to_string());
+        mapping.insert("*-preview*".to_string(), "gemini-3-flash".to_string());
+        mapping.insert("claude-3-5-haiku-20241022".to_string(), "gemini-3-flash".to_string());
+
+        let exact = explain_model_route("claude-3-5-haiku-20241022", &mapping);
+        assert_eq!(exact.kind, RouteRuleKind::Exact);
+        assert_eq!(exact.target, "gemini-3-flash");
+
+        let specific = explain_model_route("claude-3-5-haiku-latest", &mapping);
+        assert_eq!(specific.kind, RouteRuleKind::Wildcard);
+        assert_eq!(specific.rule.as_deref(), Some("claude-3-5-haiku*"));
+
+        let regex = explain_model_route("gpt-4o-mini", &mapping);
+        assert_eq!(regex.kind, RouteRuleKind::Regex);
+        assert_eq!(regex.target, "gemini-2.5-flash-lite");
+
+        let infix = explain_model_route("gpt-5-preview-0101", &mapping);
+        assert_eq!(infix.rule.as_deref(), Some("*-preview*"));
+
+        let fallback = explain_model_route("gpt-4", &mapping);
+        assert_eq!(fallback.kind, RouteRuleKind::Default);
+        assert_eq!(fallback.target, "gemini-2.5-flash");
+    }
+
+    #[test]
+    fn test_explicit_rule_priority() {
+        let mut mapping = std::collections::HashMap::new();
+        mapping.insert("claude-3-5-haiku*".to_string(), "gemini-2.5-flash".to_string());
+        mapping.insert("re:^claude-".to_string(), "claude-sonnet-4-5".to_string());
+        mapping.insert("claude-3-5-haiku-20241022".to_string(), "gemini-3-flash".to_string());
+
+        // 显式优先级让正则规则排到更具体的通配符之前，但不影响精确匹配
+        let priority = vec!["re:^claude-".to_string()];
+        let route = explain_model_route_with("claude-3-5-haiku-latest", &mapping, &priority);
+        assert_eq!(route.kind, RouteRuleKind::Regex);
+        assert_eq!(route.target, "claude-sonnet-4-5");
+
+        let exact = explain_model_route_with("claude-3-5-haiku-20241022", &mapping, &priority);
+        assert_eq!(exact.kind, RouteRuleKind::Exact);
+
+        let implicit = explain_model_route_with("claude-3-5-haiku-latest", &mapping, &[]);
+        assert_eq!(implicit.rule.as_deref(), Some("claude-3-5-haiku*"));
+    }
 }
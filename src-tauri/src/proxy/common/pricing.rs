@@ -0,0 +1,99 @@
+// 模型价格表
+// 反代本身按账号配额计费，这里按用户配置的价格 (美元 / 1K tokens) 折算每个请求的等价花费，
+// 写入用量统计并通过响应头返回。价格表在配置 `proxy.pricing` 中按模型名 (支持 * 通配符) 设置，
+// 未配置价格的模型不计算花费。
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+
+use super::model_mapping::lookup_by_model_pattern;
+use crate::proxy::config::ModelPricing;
+
+/// 非流式响应中返回本次请求的估算花费 (美元)
+pub const ESTIMATED_COST_HEADER: &str = "X-Estimated-Cost";
+
+/// 用户价格表 (由反代启动 / 配置保存时写入)
+static PRICING: Lazy<RwLock<HashMap<String, ModelPricing>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// 替换价格表
+pub fn set_pricing(pricing: HashMap<String, ModelPricing>) {
+    *PRICING.write() = pricing;
+}
+
+/// 精确匹配优先，其次取最具体的通配符规则
+fn lookup_with(model: &str, pricing: &HashMap<String, ModelPricing>) -> Option<ModelPricing> {
+    lookup_by_model_pattern(model, pricing).cloned()
+}
+
+fn cost_with(
+    price: &ModelPricing,
+    input_tokens: u32,
+    output_tokens: u32,
+    cache_read_tokens: u32,
+) -> f64 {
+    // cache_read_tokens 已包含在 input_tokens 中，单独设置了缓存价格时这部分按缓存价格计
+    let cached = match price.cache_read_per_1k {
+        Some(_) => cache_read_tokens.min(input_tokens),
+        None => 0,
+    };
+    let uncached = (input_tokens - cached) as f64;
+    (uncached * price.input_per_1k
+        + cached as f64 * price.cache_read_per_1k.unwrap_or(0.0)
+        + output_tokens as f64 * price.output_per_1k)
+        / 1000.0
+}
+
+/// 估算一次请求的花费；先按客户端请求的模型名查价格，查不到再按实际路由的模型名，都未配置时返回 None
+pub fn estimate_cost(
+    model: Option<&str>,
+    mapped_model: Option<&str>,
+    input_tokens: u32,
+    output_tokens: u32,
+    cache_read_tokens: u32,
+) -> Option<f64> {
+    let pricing = PRICING.read();
+    if pricing.is_empty() {
+        return None;
+    }
+    let price = model
+        .and_then(|m| lookup_with(m, &pricing))
+        .or_else(|| mapped_model.and_then(|m| lookup_with(m, &pricing)))?;
+    Some(cost_with(&price, input_tokens, output_tokens, cache_read_tokens))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(input: f64, output: f64, cache_read: Option<f64>) -> ModelPricing {
+        ModelPricing {
+            input_per_1k: input,
+            output_per_1k: output,
+            cache_read_per_1k: cache_read,
+        }
+    }
+
+    #[test]
+    fn exact_pattern_wins_over_wildcard() {
+        let mut pricing = HashMap::new();
+        pricing.insert("claude-*".to_string(), price(0.003, 0.015, None));
+        pricing.insert("claude-opus-*".to_string(), price(0.015, 0.075, None));
+        pricing.insert("claude-opus-4-5".to_string(), price(0.005, 0.025, None));
+
+        assert_eq!(lookup_with("claude-sonnet-4-5", &pricing).unwrap().input_per_1k, 0.003);
+        assert_eq!(lookup_with("claude-opus-4-1", &pricing).unwrap().input_per_1k, 0.015);
+        assert_eq!(lookup_with("Claude-Opus-4-5", &pricing).unwrap().input_per_1k, 0.005);
+        assert!(lookup_with("gemini-2.5-pro", &pricing).is_none());
+    }
+
+    #[test]
+    fn cache_reads_use_discounted_price() {
+        let full = cost_with(&price(0.003, 0.015, None), 2000, 1000, 1000);
+        assert!((full - 0.021).abs() < 1e-9);
+
+        let discounted = cost_with(&price(0.003, 0.015, Some(0.0003)), 2000, 1000, 1000);
+        assert!((discounted - 0.0183).abs() < 1e-9);
+    }
+}
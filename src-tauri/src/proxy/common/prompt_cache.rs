@@ -0,0 +1,208 @@
+// Prompt caching 提示处理
+//
+// 上游 (v1internal) 只有隐式缓存：同一账号上重复的请求前缀自动命中，命中量通过
+// usageMetadata.cachedContentTokenCount 返回。客户端的显式缓存提示因此映射为：
+// - Anthropic `cache_control` 断点：以首个断点之前的前缀生成缓存键，与会话指纹组合为粘性会话键，
+//   让同一会话在前缀不变时留在同一账号；断点本身从请求中移除，移除数量通过响应头告知客户端
+// - OpenAI `prompt_cache_key`：直接作为粘性会话键
+// 命中量在响应 usage 中按各协议格式返回 (cache_read_input_tokens / prompt_tokens_details.cached_tokens)。
+use axum::http::HeaderValue;
+use axum::response::Response;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// 告知客户端缓存提示的处理方式
+pub const PROMPT_CACHE_HEADER: &str = "X-Prompt-Cache";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PromptCacheHints {
+    /// 请求中的 cache_control 断点数 (转发前会被移除)
+    pub breakpoints: usize,
+    /// 用于粘性调度的缓存键
+    pub cache_key: Option<String>,
+}
+
+impl PromptCacheHints {
+    pub fn is_empty(&self) -> bool {
+        self.breakpoints == 0 && self.cache_key.is_none()
+    }
+
+    /// 粘性会话键：有缓存键时与会话指纹组合，不同会话即使共享前缀也不会落到同一个会话上
+    pub fn session_key(&self, fingerprint: String) -> String {
+        match &self.cache_key {
+            Some(cache_key) => format!("{}-{}", cache_key, fingerprint),
+            None => fingerprint,
+        }
+    }
+
+    /// 在响应上标注缓存提示的处理结果
+    pub fn apply_header(&self, response: &mut Response) {
+        if self.is_empty() {
+            return;
+        }
+        let value = format!("implicit; stripped_breakpoints={}", self.breakpoints);
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            response.headers_mut().insert(PROMPT_CACHE_HEADER, value);
+        }
+    }
+}
+
+/// 从 Anthropic Messages 请求体提取缓存提示
+pub fn claude_cache_hints(body: &Value) -> PromptCacheHints {
+    // system -> tools -> messages 即上游实际的前缀顺序
+    let sections = [body.get("system"), body.get("tools"), body.get("messages")];
+    let mut breakpoints = 0;
+    let mut prefix: Option<Vec<&Value>> = None;
+    let mut walked: Vec<&Value> = Vec::new();
+    for section in sections.into_iter().flatten() {
+        let items: Vec<&Value> = match section.as_array() {
+            Some(items) => items.iter().collect(),
+            None => vec![section],
+        };
+        for item in items {
+            walked.push(item);
+            let count = count_cache_control(item);
+            if count > 0 && prefix.is_none() {
+                prefix = Some(walked.clone());
+            }
+            breakpoints += count;
+        }
+    }
+
+    // 客户端显式提供的 user_id 优先于缓存前缀
+    let has_user_id = body
+        .pointer("/metadata/user_id")
+        .and_then(|v| v.as_str())
+        .map(|id| !id.is_empty() && !id.contains("session-"))
+        .unwrap_or(false);
+    let cache_key = match prefix {
+        Some(prefix) if !has_user_id => {
+            let mut hasher = Sha256::new();
+            for item in prefix {
+                hasher.update(strip_cache_control(item).to_string().as_bytes());
+            }
+            let hash = format!("{:x}", hasher.finalize());
+            Some(format!("pc-{}", &hash[..16]))
+        }
+        _ => None,
+    };
+
+    PromptCacheHints {
+        breakpoints,
+        cache_key,
+    }
+}
+
+/// 从 OpenAI Chat Completions 请求体提取缓存提示
+pub fn openai_cache_hints(body: &Value) -> PromptCacheHints {
+    let cache_key = body
+        .get("prompt_cache_key")
+        .and_then(|v| v.as_str())
+        .filter(|k| !k.is_empty())
+        .map(|k| format!("pck-{}", k));
+    // 部分兼容客户端会在 content part 上携带 Anthropic 风格的 cache_control
+    let breakpoints = body.get("messages").map(count_cache_control).unwrap_or(0);
+    PromptCacheHints {
+        breakpoints,
+        cache_key,
+    }
+}
+
+fn count_cache_control(value: &Value) -> usize {
+    match value {
+        Value::Object(map) => {
+            let own = usize::from(map.get("cache_control").is_some_and(|v| !v.is_null()));
+            own + map.values().map(count_cache_control).sum::<usize>()
+        }
+        Value::Array(items) => items.iter().map(count_cache_control).sum(),
+        _ => 0,
+    }
+}
+
+/// 断点位置随对话推进而移动，计算前缀哈希时需去掉
+fn strip_cache_control(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(k, _)| k.as_str() != "cache_control")
+                .map(|(k, v)| (k.clone(), strip_cache_control(v)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(strip_cache_control).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn claude_prefix_key_is_stable_across_turns() {
+        let system = json!([{"type": "text", "text": "You are a helpful assistant", "cache_control": {"type": "ephemeral"}}]);
+        let first = json!({
+            "model": "claude-sonnet-4-5",
+            "system": system,
+            "messages": [{"role": "user", "content": [{"type": "text", "text": "hi", "cache_control": {"type": "ephemeral"}}]}]
+        });
+        let second = json!({
+            "model": "claude-sonnet-4-5",
+            "system": system,
+            "messages": [
+                {"role": "user", "content": "hi"},
+                {"role": "assistant", "content": "hello"},
+                {"role": "user", "content": [{"type": "text", "text": "next", "cache_control": {"type": "ephemeral"}}]}
+            ]
+        });
+
+        let a = claude_cache_hints(&first);
+        let b = claude_cache_hints(&second);
+        assert_eq!(a.breakpoints, 2);
+        assert_eq!(b.breakpoints, 2);
+        assert!(a.cache_key.is_some());
+        assert_eq!(a.cache_key, b.cache_key);
+    }
+
+    #[test]
+    fn claude_session_key_combines_prefix_and_fingerprint() {
+        let body = json!({
+            "system": [{"type": "text", "text": "shared", "cache_control": {"type": "ephemeral"}}],
+            "messages": []
+        });
+        let hints = claude_cache_hints(&body);
+        let a = hints.session_key("sid-aaaa".to_string());
+        let b = hints.session_key("sid-bbbb".to_string());
+        assert_ne!(a, b);
+        assert!(a.starts_with("pc-") && a.ends_with("sid-aaaa"));
+        assert_eq!(
+            PromptCacheHints::default().session_key("sid-aaaa".to_string()),
+            "sid-aaaa"
+        );
+    }
+
+    #[test]
+    fn claude_user_id_wins_over_prefix_key() {
+        let body = json!({
+            "system": [{"type": "text", "text": "sys", "cache_control": {"type": "ephemeral"}}],
+            "messages": [],
+            "metadata": {"user_id": "user-42"}
+        });
+        let hints = claude_cache_hints(&body);
+        assert_eq!(hints.breakpoints, 1);
+        assert_eq!(hints.cache_key, None);
+    }
+
+    #[test]
+    fn openai_prompt_cache_key_is_used() {
+        let body = json!({"model": "gpt-4o", "prompt_cache_key": "repo-a", "messages": []});
+        assert_eq!(
+            openai_cache_hints(&body),
+            PromptCacheHints {
+                breakpoints: 0,
+                cache_key: Some("pck-repo-a".to_string())
+            }
+        );
+        assert!(openai_cache_hints(&json!({"messages": []})).is_empty());
+    }
+}
@@ -0,0 +1,89 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// JSON 中值为凭据的字段
+static SECRET_FIELD: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?i)"(api[_-]?key|access[_-]?token|refresh[_-]?token|id[_-]?token|client[_-]?secret|password|secret|authorization|x-api-key|x-goog-api-key)"(\s*:\s*)"(?:[^"\\]|\\.)*""#,
+    )
+    .unwrap()
+});
+
+/// URL 查询参数中的凭据 (Gemini 客户端常用 ?key=)
+static SECRET_QUERY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)([?&](?:key|api_key|access_token)=)[^&\s"]+"#).unwrap()
+});
+
+/// 出现在任意文本中的凭据格式: Bearer 头、sk- 密钥、Google access/refresh token、Google API Key
+static SECRET_VALUE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i:bearer\s+)[A-Za-z0-9._~+/=-]{8,}|sk-[A-Za-z0-9_-]{16,}|ya29\.[A-Za-z0-9._-]+|1//[A-Za-z0-9._-]{20,}|AIza[0-9A-Za-z_-]{35}",
+    )
+    .unwrap()
+});
+
+/// 抹除文本中的 API Key、OAuth token 等凭据，用于写入请求日志前
+pub fn redact_secrets(text: &str) -> String {
+    let text = SECRET_FIELD.replace_all(text, format!(r#""$1"$2"{}""#, REDACTED));
+    let text = SECRET_QUERY.replace_all(&text, format!("${{1}}{}", REDACTED));
+    SECRET_VALUE
+        .replace_all(&text, |caps: &regex::Captures| {
+            let matched = &caps[0];
+            if matched.len() > 7 && matched[..7].eq_ignore_ascii_case("bearer ") {
+                format!("Bearer {}", REDACTED)
+            } else {
+                REDACTED.to_string()
+            }
+        })
+        .into_owned()
+}
+
+/// 截断到最多 `max_bytes` 字节 (按字符边界)，0 表示不截断
+pub fn truncate_body(text: String, max_bytes: usize) -> String {
+    if max_bytes == 0 || text.len() <= max_bytes {
+        return text;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}\n...[truncated {} bytes]", &text[..end], text.len() - end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_json_fields_and_inline_tokens() {
+        let body = r#"{"api_key": "abc\"def", "refresh_token":"1//0abcdefghijklmnopqrstuvwxyz", "note": "use sk-0123456789abcdef0123 or Bearer ya29.a0AfH6SMB"}"#;
+        let redacted = redact_secrets(body);
+        assert_eq!(
+            redacted,
+            r#"{"api_key": "[REDACTED]", "refresh_token":"[REDACTED]", "note": "use [REDACTED] or Bearer [REDACTED]"}"#
+        );
+    }
+
+    #[test]
+    fn redacts_query_keys_in_urls() {
+        assert_eq!(
+            redact_secrets("/v1beta/models/gemini-pro:generateContent?alt=sse&key=AIzaXYZ"),
+            "/v1beta/models/gemini-pro:generateContent?alt=sse&key=[REDACTED]"
+        );
+    }
+
+    #[test]
+    fn leaves_plain_text_untouched() {
+        let text = r#"{"model": "gemini-2.5-pro", "messages": [{"role": "user", "content": "my key idea"}]}"#;
+        assert_eq!(redact_secrets(text), text);
+    }
+
+    #[test]
+    fn truncates_on_char_boundary() {
+        let truncated = truncate_body("你好世界".to_string(), 4);
+        assert_eq!(truncated, "你\n...[truncated 9 bytes]");
+        assert_eq!(truncate_body("short".to_string(), 0), "short");
+    }
+}
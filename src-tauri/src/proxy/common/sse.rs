@@ -0,0 +1,39 @@
+// SSE 流公共约定: 保活注释与错误事件格式
+use bytes::Bytes;
+use serde_json::json;
+use std::time::Duration;
+
+/// 流空闲超过该时间即发送一次保活注释，避免客户端 / 中间代理因读超时断开。
+/// 写入保活注释失败也是发现客户端已断开的途径，断开后上游请求随流一起被丢弃
+pub const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// SSE 注释行，客户端按协议忽略
+pub const KEEPALIVE_COMMENT: &str = ": keep-alive\n\n";
+
+/// Anthropic 格式的流内错误事件
+pub fn claude_error_event(message: &str) -> Bytes {
+    let data = json!({
+        "type": "error",
+        "error": {
+            "type": "api_error",
+            "message": message,
+        }
+    });
+    Bytes::from(format!("event: error\ndata: {}\n\n", data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn claude_error_event_is_a_named_sse_event() {
+        let event = claude_error_event("upstream \"reset\"");
+        let text = std::str::from_utf8(&event).unwrap();
+        assert!(text.starts_with("event: error\ndata: {"));
+        assert!(text.ends_with("}\n\n"));
+        let data: serde_json::Value =
+            serde_json::from_str(text.trim_start_matches("event: error\ndata: ").trim()).unwrap();
+        assert_eq!(data["error"]["message"], "upstream \"reset\"");
+    }
+}
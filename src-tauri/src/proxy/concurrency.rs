@@ -0,0 +1,245 @@
+// 并发上限与排队
+//
+// Agent 框架常在短时间内并发发出大量请求，同一账号上过多的在途请求容易触发上游的并发封禁。
+// - 全局上限：模型请求在进入 handler 前占用一个全局名额，名额用尽时进入有界等待队列，
+//   队列已满或等待超时直接返回 503
+// - 账号上限：TokenManager 选号时跳过在途请求已达上限的账号；所有账号都满时等待名额释放
+// 名额随响应体一起释放 (流式响应在流结束或客户端断开时释放)。每个请求同一时间只占用一个账号名额，
+// 重试换号时先释放前一个账号。
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+use crate::proxy::config::ConcurrencyConfig;
+
+/// 等待名额时的最长单次休眠，防止错过唤醒后一直等到超时
+const WAIT_SLICE: Duration = Duration::from_millis(500);
+
+static CONFIG: Lazy<RwLock<ConcurrencyConfig>> =
+    Lazy::new(|| RwLock::new(ConcurrencyConfig::default()));
+
+static LIMITER: Lazy<ConcurrencyLimiter> = Lazy::new(ConcurrencyLimiter::new);
+
+tokio::task_local! {
+    /// 当前请求的账号名额 (由并发中间件建立作用域)
+    static REQUEST_SLOT: Arc<RequestSlot>;
+}
+
+/// 替换并发配置 (由反代启动 / 配置保存时写入)，放宽上限后唤醒等待中的请求
+pub fn set_config(config: ConcurrencyConfig) {
+    *CONFIG.write() = config;
+    LIMITER.global_released.notify_waiters();
+    LIMITER.account_released.notify_waiters();
+}
+
+pub fn config() -> ConcurrencyConfig {
+    CONFIG.read().clone()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueueError {
+    /// 等待队列已满
+    QueueFull,
+    /// 排队超时
+    Timeout,
+}
+
+#[derive(Default)]
+struct GlobalState {
+    in_flight: usize,
+    waiting: usize,
+}
+
+struct ConcurrencyLimiter {
+    global: Mutex<GlobalState>,
+    global_released: Notify,
+    /// account_id -> 在途请求数
+    accounts: Mutex<HashMap<String, usize>>,
+    account_released: Notify,
+}
+
+impl ConcurrencyLimiter {
+    fn new() -> Self {
+        Self {
+            global: Mutex::new(GlobalState::default()),
+            global_released: Notify::new(),
+            accounts: Mutex::new(HashMap::new()),
+            account_released: Notify::new(),
+        }
+    }
+
+    fn try_acquire_global(&self, max_in_flight: usize) -> bool {
+        let mut state = self.global.lock();
+        if max_in_flight == 0 || state.in_flight < max_in_flight {
+            state.in_flight += 1;
+            return true;
+        }
+        false
+    }
+
+    fn release_global(&self) {
+        let mut state = self.global.lock();
+        state.in_flight = state.in_flight.saturating_sub(1);
+        drop(state);
+        self.global_released.notify_one();
+    }
+
+    fn try_acquire_account(&self, account_id: &str, max_per_account: usize) -> bool {
+        let mut accounts = self.accounts.lock();
+        let count = accounts.entry(account_id.to_string()).or_insert(0);
+        if max_per_account == 0 || *count < max_per_account {
+            *count += 1;
+            return true;
+        }
+        false
+    }
+
+    fn release_account(&self, account_id: &str) {
+        let mut accounts = self.accounts.lock();
+        if let Some(count) = accounts.get_mut(account_id) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                accounts.remove(account_id);
+            }
+        }
+        drop(accounts);
+        self.account_released.notify_waiters();
+    }
+}
+
+/// 全局名额，drop 时释放
+pub struct GlobalPermit(());
+
+impl Drop for GlobalPermit {
+    fn drop(&mut self) {
+        LIMITER.release_global();
+    }
+}
+
+/// 获取全局名额，必要时排队等待
+pub async fn acquire_global() -> Result<GlobalPermit, QueueError> {
+    let config = config();
+    if LIMITER.try_acquire_global(config.max_in_flight) {
+        return Ok(GlobalPermit(()));
+    }
+    {
+        let mut state = LIMITER.global.lock();
+        if state.waiting >= config.max_queue {
+            return Err(QueueError::QueueFull);
+        }
+        state.waiting += 1;
+    }
+    // 请求被取消 (客户端断开) 时 future 直接被丢弃，排队计数需在 drop 时归还
+    let _waiting = WaitingGuard;
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(config.queue_timeout_secs);
+    let result = loop {
+        if LIMITER.try_acquire_global(CONFIG.read().max_in_flight) {
+            break Ok(GlobalPermit(()));
+        }
+        let now = tokio::time::Instant::now();
+        if now >= deadline {
+            break Err(QueueError::Timeout);
+        }
+        let wait = (deadline - now).min(WAIT_SLICE);
+        let _ = tokio::time::timeout(wait, LIMITER.global_released.notified()).await;
+    };
+    result
+}
+
+/// 全局排队计数的归还守卫
+struct WaitingGuard;
+
+impl Drop for WaitingGuard {
+    fn drop(&mut self) {
+        LIMITER.global.lock().waiting -= 1;
+    }
+}
+
+/// 请求持有的账号名额
+struct AccountLease(String);
+
+impl Drop for AccountLease {
+    fn drop(&mut self) {
+        LIMITER.release_account(&self.0);
+    }
+}
+
+/// 单个请求的名额槽位：同一时间最多持有一个账号名额，随响应体一起释放
+#[derive(Default)]
+pub struct RequestSlot {
+    lease: Mutex<Option<AccountLease>>,
+}
+
+/// 在请求槽位的作用域内运行 handler，使 TokenManager 选号时能为该请求占用账号名额
+pub async fn scope<F: std::future::Future>(slot: Arc<RequestSlot>, f: F) -> F::Output {
+    REQUEST_SLOT.scope(slot, f).await
+}
+
+/// 账号在途请求是否已达上限
+pub fn account_at_capacity(account_id: &str) -> bool {
+    let max_per_account = CONFIG.read().max_per_account;
+    max_per_account > 0
+        && LIMITER.accounts.lock().get(account_id).copied().unwrap_or(0) >= max_per_account
+}
+
+/// 为当前请求占用账号名额 (替换该请求之前占用的账号)；名额已满时返回 false。
+/// 不在请求作用域内 (后台预热、镜像等) 时不计数
+pub fn lease_account(account_id: &str) -> bool {
+    REQUEST_SLOT
+        .try_with(|slot| {
+            let mut lease = slot.lease.lock();
+            // 先释放本请求之前的账号，重试同一账号时不会被自己占满
+            *lease = None;
+            if LIMITER.try_acquire_account(account_id, CONFIG.read().max_per_account) {
+                *lease = Some(AccountLease(account_id.to_string()));
+                true
+            } else {
+                false
+            }
+        })
+        .unwrap_or(true)
+}
+
+/// 等待任意账号释放名额，最多等到 deadline；返回是否仍在等待期限内
+pub async fn wait_for_account_slot(deadline: tokio::time::Instant) -> bool {
+    let now = tokio::time::Instant::now();
+    if now >= deadline {
+        return false;
+    }
+    let wait = (deadline - now).min(WAIT_SLICE);
+    let _ = tokio::time::timeout(wait, LIMITER.account_released.notified()).await;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn account_slots_respect_cap() {
+        let limiter = ConcurrencyLimiter::new();
+        assert!(limiter.try_acquire_account("a", 2));
+        assert!(limiter.try_acquire_account("a", 2));
+        assert!(!limiter.try_acquire_account("a", 2));
+        assert!(limiter.try_acquire_account("b", 2));
+        limiter.release_account("a");
+        assert!(limiter.try_acquire_account("a", 2));
+
+        // 0 表示不限制
+        assert!(limiter.try_acquire_account("a", 0));
+    }
+
+    #[test]
+    fn global_slots_respect_cap() {
+        let limiter = ConcurrencyLimiter::new();
+        assert!(limiter.try_acquire_global(1));
+        assert!(!limiter.try_acquire_global(1));
+        limiter.release_global();
+        assert!(limiter.try_acquire_global(1));
+        assert!(limiter.try_acquire_global(0));
+    }
+}
@@ -0,0 +1,139 @@
+// 调试诊断响应头
+//
+// 开启 `proxy.debug_headers` 后，模型请求的响应附加以下头部，便于排查多账号调度问题：
+// - X-Antigravity-Account: 处理请求的账号
+// - X-Antigravity-Upstream-Latency-Ms: 最后一次上游调用等待响应头的耗时
+// - X-Antigravity-Retries: 上游重试次数 (换号 / 降级重试)
+// - X-Antigravity-Purification: 上下文清洗策略 (none / soft / aggressive)
+// - X-Antigravity-Estimated-Tokens: 估算的输入 token 数
+// 各项由 handler / 上游客户端在请求作用域内记录，不在作用域内 (后台预热、镜像等) 时忽略。
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+tokio::task_local! {
+    /// 当前请求的诊断记录 (由诊断中间件建立作用域)
+    static DIAGNOSTICS: Arc<RequestDiagnostics>;
+}
+
+/// 开关诊断响应头 (由反代启动 / 配置保存时写入)
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Record {
+    upstream_calls: u32,
+    upstream_latency: Option<Duration>,
+    purification: Option<&'static str>,
+    estimated_tokens: Option<u64>,
+}
+
+/// 单个请求的诊断记录
+#[derive(Default)]
+pub struct RequestDiagnostics {
+    record: Mutex<Record>,
+}
+
+impl RequestDiagnostics {
+    /// 写入诊断响应头；账号取自 handler 已设置的 X-Account-Email
+    pub fn apply_headers(&self, headers: &mut HeaderMap) {
+        let record = self.record.lock().clone();
+        if let Some(account) = headers.get("X-Account-Email").cloned() {
+            headers.insert(HeaderName::from_static("x-antigravity-account"), account);
+        }
+        let mut insert = |name: &'static str, value: Option<String>| {
+            if let Some(value) = value.and_then(|v| HeaderValue::from_str(&v).ok()) {
+                headers.insert(HeaderName::from_static(name), value);
+            }
+        };
+        insert(
+            "x-antigravity-upstream-latency-ms",
+            record.upstream_latency.map(|d| d.as_millis().to_string()),
+        );
+        if record.upstream_calls > 0 {
+            insert(
+                "x-antigravity-retries",
+                Some((record.upstream_calls - 1).to_string()),
+            );
+        }
+        insert(
+            "x-antigravity-purification",
+            record.purification.map(str::to_string),
+        );
+        insert(
+            "x-antigravity-estimated-tokens",
+            record.estimated_tokens.map(|n| n.to_string()),
+        );
+    }
+}
+
+/// 在诊断作用域内运行 handler
+pub async fn scope<F: std::future::Future>(diagnostics: Arc<RequestDiagnostics>, f: F) -> F::Output {
+    DIAGNOSTICS.scope(diagnostics, f).await
+}
+
+fn with_record(f: impl FnOnce(&mut Record)) {
+    let _ = DIAGNOSTICS.try_with(|diagnostics| f(&mut diagnostics.record.lock()));
+}
+
+/// 记录一次上游调用及其等待响应头的耗时
+pub fn record_upstream_call(latency: Duration) {
+    with_record(|r| {
+        r.upstream_calls += 1;
+        r.upstream_latency = Some(latency);
+    });
+}
+
+/// 记录上下文清洗策略
+pub fn record_purification(strategy: &'static str) {
+    with_record(|r| r.purification = Some(strategy));
+}
+
+/// 记录估算的输入 token 数
+pub fn record_estimated_tokens(tokens: u64) {
+    with_record(|r| r.estimated_tokens = Some(tokens));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn records_only_inside_scope() {
+        record_upstream_call(Duration::from_millis(5));
+
+        let diagnostics = Arc::new(RequestDiagnostics::default());
+        scope(diagnostics.clone(), async {
+            record_upstream_call(Duration::from_millis(120));
+            record_upstream_call(Duration::from_millis(80));
+            record_purification("soft");
+            record_estimated_tokens(4096);
+        })
+        .await;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Account-Email", HeaderValue::from_static("a@example.com"));
+        diagnostics.apply_headers(&mut headers);
+        assert_eq!(headers["x-antigravity-account"], "a@example.com");
+        assert_eq!(headers["x-antigravity-upstream-latency-ms"], "80");
+        assert_eq!(headers["x-antigravity-retries"], "1");
+        assert_eq!(headers["x-antigravity-purification"], "soft");
+        assert_eq!(headers["x-antigravity-estimated-tokens"], "4096");
+    }
+
+    #[test]
+    fn omits_unknown_values() {
+        let mut headers = HeaderMap::new();
+        RequestDiagnostics::default().apply_headers(&mut headers);
+        assert!(headers.is_empty());
+    }
+}
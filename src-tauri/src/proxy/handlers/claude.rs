@@ -17,12 +17,12 @@ use crate::proxy::mappers::claude::{
     filter_invalid_thinking_blocks_with_family, close_tool_loop_for_thinking,
     clean_cache_control_from_messages, merge_consecutive_messages,
 };
+use crate::proxy::common::prompt_cache::{claude_cache_hints, PromptCacheHints};
 use crate::proxy::server::AppState;
 use crate::proxy::mappers::context_manager::{ContextManager, PurificationStrategy};
 use axum::http::HeaderMap;
 use std::sync::atomic::Ordering;
 
-const MAX_RETRY_ATTEMPTS: usize = 3;
 const MIN_SIGNATURE_LENGTH: usize = 10;  // 最小有效签名长度
 
 // ===== Model Constants for Background Tasks =====
@@ -109,6 +109,7 @@ fn determine_retry_strategy(
 async fn apply_retry_strategy(
     strategy: RetryStrategy,
     attempt: usize,
+    max_attempts: usize,
     status_code: u16,
     trace_id: &str,
 ) -> bool {
@@ -125,7 +126,7 @@ async fn apply_retry_strategy(
                 trace_id,
                 status_code,
                 attempt + 1,
-                MAX_RETRY_ATTEMPTS,
+                max_attempts,
                 base_ms
             );
             sleep(duration).await;
@@ -139,7 +140,7 @@ async fn apply_retry_strategy(
                 trace_id,
                 status_code,
                 attempt + 1,
-                MAX_RETRY_ATTEMPTS,
+                max_attempts,
                 calculated_ms
             );
             sleep(Duration::from_millis(calculated_ms)).await;
@@ -153,7 +154,7 @@ async fn apply_retry_strategy(
                 trace_id,
                 status_code,
                 attempt + 1,
-                MAX_RETRY_ATTEMPTS,
+                max_attempts,
                 calculated_ms
             );
             sleep(Duration::from_millis(calculated_ms)).await;
@@ -183,6 +184,25 @@ pub async fn handle_messages(
     State(state): State<AppState>,
     headers: HeaderMap,
     Json(body): Json<Value>,
+) -> Response {
+    // cache_control 在解析为结构体时会被丢弃，需先从原始请求体提取
+    let cache_hints = claude_cache_hints(&body);
+    if cache_hints.breakpoints > 0 {
+        debug!(
+            "[PromptCache] Stripping {} cache_control breakpoints (upstream uses implicit caching)",
+            cache_hints.breakpoints
+        );
+    }
+    let mut response = handle_messages_inner(state, headers, body, &cache_hints).await;
+    cache_hints.apply_header(&mut response);
+    response
+}
+
+async fn handle_messages_inner(
+    state: AppState,
+    headers: HeaderMap,
+    body: Value,
+    cache_hints: &PromptCacheHints,
 ) -> Response {
     tracing::debug!("handle_messages called. Body JSON len: {}", body.to_string().len());
     
@@ -314,6 +334,27 @@ pub async fn handle_messages(
     
     // Google Flow 继续使用 request 对象
     // (后续代码不需要再次 filter_invalid_thinking_blocks)
+
+    // url 图片先下载内联 (上游不接受任意链接)；图片在转发前校验大小与类型，
+    // 不合法时直接返回 400 而不是消耗账号重试
+    let images_ready =
+        crate::proxy::mappers::image_input::inline_claude_url_images(&mut request.messages)
+            .await
+            .and_then(|()| {
+                crate::proxy::mappers::image_input::validate_claude_images(&request.messages)
+            });
+    if let Err(e) = images_ready {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "type": "error",
+                "error": {
+                    "type": "invalid_request_error",
+                    "message": e
+                }
+            }))
+        ).into_response();
+    }
     
     // [NEW] 获取上下文缩放配置
     let scaling_enabled = state.experimental.read().await.enable_usage_scaling;
@@ -422,12 +463,14 @@ pub async fn handle_messages(
     
     let pool_size = token_manager.len();
     // [FIX] Ensure max_attempts is at least 2 to allow for internal retries (e.g. stripping signatures)
-    // even if the user has only 1 account.
-    let max_attempts = MAX_RETRY_ATTEMPTS.min(pool_size.saturating_add(1)).max(2);
+    // even if the user has only 1 account. Otherwise every account of the pool gets a chance.
+    let max_attempts = crate::proxy::upstream::failover::max_attempts(pool_size.saturating_add(1), 2);
 
     let mut last_error = String::new();
     let mut retried_without_thinking = false;
     let mut last_email: Option<String> = None;
+    // 请求镜像 (仅首次尝试时抽样)
+    let mut mirror: Option<crate::proxy::mirror::MirrorHandle> = None;
     
     for attempt in 0..max_attempts {
         // 2. 模型路由解析
@@ -445,11 +488,14 @@ pub async fn handle_messages(
 
         // 0. 尝试提取 session_id 用于粘性调度 (Phase 2/3)
         // 使用 SessionManager 生成稳定的会话指纹
-        let session_id_str = crate::proxy::session_manager::SessionManager::extract_session_id(&request_for_body);
+        // 带缓存断点的请求在会话指纹上叠加缓存前缀，提高上游隐式缓存命中率
+        let session_id_str = cache_hints.session_key(
+            crate::proxy::session_manager::SessionManager::extract_session_id(&request_for_body),
+        );
         let session_id = Some(session_id_str.as_str());
 
         let force_rotate_token = attempt > 0;
-        let (access_token, project_id, email) = match token_manager.get_token(&config.request_type, force_rotate_token, session_id, &config.final_model).await {
+        let (access_token, project_id, email, upstream_proxy) = match token_manager.get_token(&config.request_type, force_rotate_token, session_id, &config.final_model).await {
             Ok(t) => t,
             Err(e) => {
                 let safe_message = if e.contains("invalid_grant") {
@@ -518,17 +564,18 @@ pub async fn handle_messages(
         // 对 Pro/Flash 模型进行差异化的上下文管理
         let mut is_purified = false;
         if !retried_without_thinking {
-            // 1. 确定上下文限制 (Flash: ~1M, Pro: ~2M)
-            // Conservatively use 900k for Flash and 1.8M for Pro to check pressure
-            let context_limit = if mapped_model.contains("flash") {
-                1_000_000
-            } else {
-                2_000_000
-            };
+            // 1. 确定上下文限制 (模型能力表: Flash ~1M, Pro ~2M)
+            let context_limit =
+                crate::proxy::common::model_capabilities::lookup(&mapped_model).context_window;
 
             // 2. 估算当前用量
             let estimated_usage = ContextManager::estimate_token_usage(&request_with_mapped);
             let usage_ratio = estimated_usage as f32 / context_limit as f32;
+            if crate::proxy::diagnostics::enabled() {
+                crate::proxy::diagnostics::record_estimated_tokens(
+                    ContextManager::estimate_input_tokens(&request_with_mapped) as u64,
+                );
+            }
 
             // 3. 确定清洗策略
             // > 90%: 激进剥离 (Aggressive) - 移除所有历史 Thinking
@@ -558,6 +605,11 @@ pub async fn handle_messages(
                     debug!("[{}] History purified successfully", trace_id);
                 }
             }
+            crate::proxy::diagnostics::record_purification(match strategy {
+                PurificationStrategy::None => "none",
+                PurificationStrategy::Soft => "soft",
+                PurificationStrategy::Aggressive => "aggressive",
+            });
         }
 
         request_with_mapped.model = mapped_model;
@@ -583,6 +635,18 @@ pub async fn handle_messages(
                 ).into_response();
             }
         };
+
+        if attempt == 0 {
+            mirror = crate::proxy::mirror::begin(
+                &token_manager,
+                &upstream,
+                "anthropic",
+                &request.model,
+                &request_with_mapped.model,
+                &email,
+                &gemini_body,
+            );
+        }
         
     // 4. 上游调用 - 自动转换逻辑
     let client_wants_stream = request.stream;
@@ -605,17 +669,19 @@ pub async fn handle_messages(
 
         // 5. 上游调用
         let response = match upstream
-            .call_v1_internal_with_headers(method, &access_token, gemini_body, query, extra_headers.clone())
+            .call_v1_internal_with_headers(method, &access_token, upstream_proxy.as_deref(), gemini_body, query, extra_headers.clone())
             .await {
             Ok(r) => r,
             Err(e) => {
                 last_error = e.clone();
+                token_manager.record_upstream_outcome(&email, None);
                 debug!("Request failed on attempt {}/{}: {}", attempt + 1, max_attempts, e);
                 continue;
             }
         };
         
         let status = response.status();
+        token_manager.record_upstream_outcome(&email, Some(status.as_u16()));
         
         // 成功
         if status.is_success() {
@@ -643,12 +709,13 @@ pub async fn handle_messages(
                     context_limit
                 );
 
+                let first_byte_timeout = crate::proxy::upstream::timeouts::for_model(&request_with_mapped.model).first_byte;
                 let mut first_data_chunk = None;
                 let mut retry_this_account = false;
 
                 // Loop to skip heartbeats during peek
                 loop {
-                    match tokio::time::timeout(std::time::Duration::from_secs(60), claude_stream.next()).await {
+                    match tokio::time::timeout(first_byte_timeout, claude_stream.next()).await {
                         Ok(Some(Ok(bytes))) => {
                             if bytes.is_empty() {
                                 continue;
@@ -678,7 +745,7 @@ pub async fn handle_messages(
                             break;
                         }
                         Err(_) => {
-                            tracing::warn!("[{}] Timeout waiting for first data (60s), retrying...", trace_id);
+                            tracing::warn!("[{}] Timeout waiting for first data ({}s), retrying...", trace_id, first_byte_timeout.as_secs());
                             last_error = "Timeout waiting for first data".to_string();
                             retry_this_account = true;
                             break;
@@ -698,12 +765,15 @@ pub async fn handle_messages(
                             .chain(stream_rest.map(|result| -> Result<Bytes, std::io::Error> {
                                 match result {
                                     Ok(b) => Ok(b),
-                                    Err(e) => Ok(Bytes::from(format!("data: {{\"error\":\"{}\"}}\n\n", e))),
+                                    Err(e) => Ok(crate::proxy::common::sse::claude_error_event(&e)),
                                 }
                             })));
 
                         // 判断客户端期望的格式
                         if client_wants_stream {
+                            if let Some(m) = mirror.take() {
+                                m.finish::<Value>(200, true, None);
+                            }
                             // 客户端本就要 Stream，直接返回 SSE
                             return Response::builder()
                                 .status(StatusCode::OK)
@@ -722,6 +792,9 @@ pub async fn handle_messages(
                             match collect_stream_to_json(combined_stream).await {
                                 Ok(full_response) => {
                                     info!("[{}] ✓ Stream collected and converted to JSON", trace_id);
+                                    if let Some(m) = mirror.take() {
+                                        m.finish(200, false, Some(&full_response));
+                                    }
                                     return Response::builder()
                                         .status(StatusCode::OK)
                                         .header(header::CONTENT_TYPE, "application/json")
@@ -797,6 +870,9 @@ pub async fn handle_messages(
                     cache_info
                 );
 
+                if let Some(m) = mirror.take() {
+                    m.finish(200, false, Some(&claude_response));
+                }
                 return (StatusCode::OK, [("X-Account-Email", email.as_str()), ("X-Mapped-Model", request_with_mapped.model.as_str())], Json(claude_response)).into_response();
             }
         }
@@ -815,6 +891,9 @@ pub async fn handle_messages(
         if status_code == 429 || status_code == 529 || status_code == 503 || status_code == 500 {
             token_manager.mark_rate_limited_async(&email, status_code, retry_after.as_deref(), &error_text, Some(&request_with_mapped.model)).await;
         }
+        if crate::proxy::upstream::failover::is_auth_failure(status_code) {
+            token_manager.mark_auth_failure(&email, status_code);
+        }
 
         // 4. 处理 400 错误 (Thinking 签名失效 或 块顺序错误)
         if status_code == 400
@@ -896,6 +975,7 @@ pub async fn handle_messages(
             if apply_retry_strategy(
                 RetryStrategy::FixedDelay(Duration::from_millis(100)), 
                 attempt, 
+                max_attempts,
                 status_code, 
                 &trace_id
             ).await {
@@ -912,7 +992,7 @@ pub async fn handle_messages(
         let strategy = determine_retry_strategy(status_code, &error_text, retried_without_thinking);
         
         // 执行退避
-        if apply_retry_strategy(strategy, attempt, status_code, &trace_id).await {
+        if apply_retry_strategy(strategy, attempt, max_attempts, status_code, &trace_id).await {
             // 判断是否需要轮换账号
             if !should_rotate_account(status_code) {
                 debug!("[{}] Keeping same account for status {} (server-side issue)", trace_id, status_code);
@@ -938,6 +1018,9 @@ pub async fn handle_messages(
 
             // 不可重试的错误，直接返回
             error!("[{}] Non-retryable error {}: {}", trace_id, status_code, error_text);
+            if let Some(m) = mirror.take() {
+                m.finish(status_code, false, Some(&error_text));
+            }
             return (status, [("X-Account-Email", email.as_str())], error_text).into_response();
         }
     }
@@ -961,30 +1044,35 @@ pub async fn handle_messages(
     }
 }
 
-/// 列出可用模型
+/// 列出可用模型 (Anthropic 格式，附带路由目标与可用账号数)
 pub async fn handle_list_models(State(state): State<AppState>) -> impl IntoResponse {
-    use crate::proxy::common::model_mapping::get_all_dynamic_models;
-
-    let model_ids = get_all_dynamic_models(
-        &state.custom_mapping,
-    ).await;
-
-    let data: Vec<_> = model_ids.into_iter().map(|id| {
-        json!({
-            "id": id,
-            "object": "model",
-            "created": 1706745600,
-            "owned_by": "antigravity"
+    let data: Vec<_> = crate::proxy::handlers::common::routable_models(&state)
+        .await
+        .into_iter()
+        .map(|m| {
+            json!({
+                "type": "model",
+                "id": m.id,
+                "display_name": m.id,
+                "created_at": "2024-02-01T00:00:00Z",
+                "target": m.target,
+                "alias": m.alias,
+                "available_accounts": m.available_accounts
+            })
         })
-    }).collect();
+        .collect();
 
+    let first_id = data.first().map(|m| m["id"].clone());
+    let last_id = data.last().map(|m| m["id"].clone());
     Json(json!({
-        "object": "list",
-        "data": data
+        "data": data,
+        "has_more": false,
+        "first_id": first_id,
+        "last_id": last_id
     }))
 }
 
-/// 计算 tokens (占位符)
+/// 计算输入 tokens：z.ai 启用时由其上游计数，否则按本地估算
 pub async fn handle_count_tokens(
     State(state): State<AppState>,
     headers: HeaderMap,
@@ -1004,11 +1092,27 @@ pub async fn handle_count_tokens(
         .await;
     }
 
-    Json(json!({
-        "input_tokens": 0,
-        "output_tokens": 0
-    }))
-    .into_response()
+    let request: ClaudeRequest = match serde_json::from_value(body) {
+        Ok(r) => r,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "type": "error",
+                    "error": {
+                        "type": "invalid_request_error",
+                        "message": format!("Invalid request body: {}", e)
+                    }
+                }))
+            ).into_response();
+        }
+    };
+
+    // 上游 (v1internal) 没有 Claude 模型的计数接口，使用本地估算
+    let input_tokens = ContextManager::estimate_input_tokens(&request);
+    debug!("[count_tokens] model={} messages={} input_tokens≈{}", request.model, request.messages.len(), input_tokens);
+
+    Json(json!({ "input_tokens": input_tokens })).into_response()
 }
 
 // 移除已失效的简单单元测试，后续将补全完整的集成测试
@@ -2,6 +2,40 @@ use axum::{extract::State, extract::Json, http::StatusCode, response::IntoRespon
 use serde_json::{json, Value};
 use crate::proxy::server::AppState;
 
+/// A model the proxy can route, as reported by the model listing endpoints
+pub(crate) struct RoutableModel {
+    pub id: String,
+    /// Upstream model the request is routed to
+    pub target: String,
+    /// Defined by a custom mapping rule rather than the built-in table
+    pub alias: bool,
+    /// Accounts currently able to serve the target (entitled to it, not rate limited or quota protected)
+    pub available_accounts: usize,
+}
+
+/// All routable models: built-in names plus exact custom mapping keys
+/// (wildcard and regex rules match arbitrary names and are not listed)
+pub(crate) async fn routable_models(state: &AppState) -> Vec<RoutableModel> {
+    use crate::proxy::common::model_mapping::{explain_model_route, get_all_dynamic_models, RouteRuleKind};
+
+    let model_ids = get_all_dynamic_models(&state.custom_mapping).await;
+    let mapping = state.custom_mapping.read().await;
+
+    model_ids
+        .into_iter()
+        .filter(|id| !id.contains('*') && !id.starts_with("re:"))
+        .map(|id| {
+            let route = explain_model_route(&id, &mapping);
+            RoutableModel {
+                alias: route.kind == RouteRuleKind::Exact,
+                available_accounts: state.token_manager.available_accounts_for(&route.target),
+                target: route.target,
+                id,
+            }
+        })
+        .collect()
+}
+
 /// Detects model capabilities and configuration
 /// POST /v1/models/detect
 pub async fn handle_detect_model(
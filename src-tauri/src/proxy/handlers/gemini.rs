@@ -3,11 +3,11 @@ use axum::{extract::State, extract::{Json, Path}, http::StatusCode, response::In
 use serde_json::{json, Value};
 use tracing::{debug, error, info};
 
+use crate::proxy::mappers::context_manager::ContextManager;
 use crate::proxy::mappers::gemini::{wrap_request, unwrap_response};
 use crate::proxy::server::AppState;
 use crate::proxy::session_manager::SessionManager;
  
-const MAX_RETRY_ATTEMPTS: usize = 3;
  
 /// 处理 generateContent 和 streamGenerateContent
 /// 路径参数: model_name, method (e.g. "gemini-pro", "generateContent")
@@ -35,10 +35,12 @@ pub async fn handle_generate(
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager;
     let pool_size = token_manager.len();
-    let max_attempts = MAX_RETRY_ATTEMPTS.min(pool_size).max(1);
+    let max_attempts = crate::proxy::upstream::failover::max_attempts(pool_size, 1);
     
     let mut last_error = String::new();
     let mut last_email: Option<String> = None;
+    // 请求镜像 (仅首次尝试时抽样)
+    let mut mirror: Option<crate::proxy::mirror::MirrorHandle> = None;
 
     for attempt in 0..max_attempts {
         // 3. 模型路由解析
@@ -66,7 +68,7 @@ pub async fn handle_generate(
         let session_id = SessionManager::extract_gemini_session_id(&body, &model_name);
 
         // 关键：在重试尝试 (attempt > 0) 时强制轮换账号
-        let (access_token, project_id, email) = match token_manager.get_token(&config.request_type, attempt > 0, Some(&session_id), &config.final_model).await {
+        let (access_token, project_id, email, upstream_proxy) = match token_manager.get_token(&config.request_type, attempt > 0, Some(&session_id), &config.final_model).await {
             Ok(t) => t,
             Err(e) => {
                 return Err((StatusCode::SERVICE_UNAVAILABLE, format!("Token error: {}", e)));
@@ -79,23 +81,42 @@ pub async fn handle_generate(
         // 5. 包装请求 (project injection)
         // [FIX #765] Pass session_id to wrap_request for signature injection
         let wrapped_body = wrap_request(&body, &project_id, &mapped_model, Some(&session_id));
+        if crate::proxy::diagnostics::enabled() {
+            crate::proxy::diagnostics::record_estimated_tokens(
+                ContextManager::estimate_gemini_input_tokens(&wrapped_body) as u64,
+            );
+        }
+
+        if attempt == 0 {
+            mirror = crate::proxy::mirror::begin(
+                &token_manager,
+                &upstream,
+                "gemini",
+                &model_name,
+                &mapped_model,
+                &email,
+                &wrapped_body,
+            );
+        }
 
         // 5. 上游调用
         let query_string = if is_stream { Some("alt=sse") } else { None };
         let upstream_method = if is_stream { "streamGenerateContent" } else { "generateContent" };
 
         let response = match upstream
-            .call_v1_internal(upstream_method, &access_token, wrapped_body, query_string)
+            .call_v1_internal(upstream_method, &access_token, upstream_proxy.as_deref(), wrapped_body, query_string)
             .await {
                 Ok(r) => r,
                 Err(e) => {
                     last_error = e.clone();
+                    token_manager.record_upstream_outcome(&email, None);
                     debug!("Gemini Request failed on attempt {}/{}: {}", attempt + 1, max_attempts, e);
                     continue;
                 }
             };
 
         let status = response.status();
+        token_manager.record_upstream_outcome(&email, Some(status.as_u16()));
         if status.is_success() {
             // 6. 响应处理
             if is_stream {
@@ -109,10 +130,11 @@ pub async fn handle_generate(
                 let s_id = session_id.clone(); // Clone for stream closure
 
                 // [FIX #859] Implement peek logic for Gemini stream to prevent 0-token 200 OK
+                let first_byte_timeout = crate::proxy::upstream::timeouts::for_model(&mapped_model).first_byte;
                 let mut first_chunk = None;
                 let mut retry_gemini = false;
 
-                match tokio::time::timeout(std::time::Duration::from_secs(30), response_stream.next()).await {
+                match tokio::time::timeout(first_byte_timeout, response_stream.next()).await {
                     Ok(Some(Ok(bytes))) => {
                         if bytes.is_empty() {
                             tracing::warn!("[Gemini] Empty first chunk received, retrying...");
@@ -226,6 +248,9 @@ pub async fn handle_generate(
                     }
                 };
                 
+                if let Some(m) = mirror.take() {
+                    m.finish::<Value>(200, true, None);
+                }
                 let body = Body::from_stream(stream);
                 return Ok(Response::builder()
                     .header("Content-Type", "text/event-stream")
@@ -266,6 +291,9 @@ pub async fn handle_generate(
             }
 
             let unwrapped = unwrap_response(&gemini_resp);
+            if let Some(m) = mirror.take() {
+                m.finish(200, false, Some(&unwrapped));
+            }
             return Ok((StatusCode::OK, [("X-Account-Email", email.as_str()), ("X-Mapped-Model", mapped_model.as_str())], Json(unwrapped)).into_response());
         }
 
@@ -275,15 +303,13 @@ pub async fn handle_generate(
         let error_text = response.text().await.unwrap_or_else(|_| format!("HTTP {}", status_code));
         last_error = format!("HTTP {}: {}", status_code, error_text);
  
-        // 只有 429 (限流), 529 (过载), 503, 403 (权限) 和 401 (认证失效) 触发账号轮换
-        if status_code == 429 || status_code == 529 || status_code == 503 || status_code == 500 || status_code == 403 || status_code == 401 {
-            // 记录限流信息 (全局同步)
-            token_manager.mark_rate_limited(&email, status_code, retry_after.as_deref(), &error_text);
-
-            // 只有明确包含 "QUOTA_EXHAUSTED" 才停止，避免误判上游的频率限制提示 (如 "check quota")
-            if status_code == 429 && error_text.contains("QUOTA_EXHAUSTED") {
-                error!("Gemini Quota exhausted (429) on account {} attempt {}/{}, stopping to protect pool.", email, attempt + 1, max_attempts);
-                return Ok((status, [("X-Account-Email", email.as_str())], error_text).into_response());
+        // 限流、过载、5xx、403 (权限) 和 401 (认证失效) 触发账号轮换；配额耗尽的账号已被标记，同样换下一个账号
+        if crate::proxy::upstream::failover::is_failover_status(status_code) {
+            // 记录失败信息 (全局同步)
+            if crate::proxy::upstream::failover::is_auth_failure(status_code) {
+                token_manager.mark_auth_failure(&email, status_code);
+            } else {
+                token_manager.mark_rate_limited(&email, status_code, retry_after.as_deref(), &error_text);
             }
 
             tracing::warn!("Gemini Upstream {} on account {} attempt {}/{}, rotating account", status_code, email, attempt + 1, max_attempts);
@@ -292,6 +318,9 @@ pub async fn handle_generate(
  
         // 404 等由于模型配置或路径错误的 HTTP 异常，直接报错，不进行无效轮换
         error!("Gemini Upstream non-retryable error {}: {}", status_code, error_text);
+        if let Some(m) = mirror.take() {
+            m.finish(status_code, false, Some(&error_text));
+        }
         return Ok((status, [("X-Account-Email", email.as_str())], error_text).into_response());
     }
 
@@ -338,7 +367,7 @@ pub async fn handle_get_model(Path(model_name): Path<String>) -> impl IntoRespon
 
 pub async fn handle_count_tokens(State(state): State<AppState>, Path(_model_name): Path<String>, Json(_body): Json<Value>) -> Result<impl IntoResponse, (StatusCode, String)> {
     let model_group = "gemini";
-    let (_access_token, _project_id, _) = state.token_manager.get_token(model_group, false, None, "gemini").await
+    let (_access_token, _project_id, _, _) = state.token_manager.get_token(model_group, false, None, "gemini").await
         .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, format!("Token error: {}", e)))?;
     
     Ok(Json(json!({"totalTokens": 0})))
@@ -19,8 +19,8 @@ fn build_client(
     let mut builder = reqwest::Client::builder()
         .timeout(Duration::from_secs(timeout_secs.max(5)));
 
-    if upstream_proxy.enabled && !upstream_proxy.url.is_empty() {
-        let proxy = reqwest::Proxy::all(&upstream_proxy.url)
+    if let Some(url) = upstream_proxy.proxy_url() {
+        let proxy = reqwest::Proxy::all(&url)
             .map_err(|e| format!("Invalid upstream proxy url: {}", e))?;
         builder = builder.proxy(proxy);
     }
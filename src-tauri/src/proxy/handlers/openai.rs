@@ -9,9 +9,10 @@ use crate::proxy::mappers::openai::{
     transform_openai_request, transform_openai_response, OpenAIRequest,
 };
 // use crate::proxy::upstream::client::UpstreamClient; // 通过 state 获取
+use crate::proxy::common::prompt_cache::{openai_cache_hints, PromptCacheHints};
+use crate::proxy::mappers::context_manager::ContextManager;
 use crate::proxy::server::AppState;
 
-const MAX_RETRY_ATTEMPTS: usize = 3;
 use crate::proxy::session_manager::SessionManager;
 use tokio::time::{sleep, Duration};
 
@@ -41,26 +42,26 @@ fn determine_retry_strategy(status_code: u16, error_text: &str) -> RetryStrategy
     }
 }
 
-async fn apply_retry_strategy(strategy: RetryStrategy, attempt: usize, status_code: u16, trace_id: &str) -> bool {
+async fn apply_retry_strategy(strategy: RetryStrategy, attempt: usize, max_attempts: usize, status_code: u16, trace_id: &str) -> bool {
     match strategy {
         RetryStrategy::NoRetry => {
             debug!("[{}] Non-retryable error {}, stopping", trace_id, status_code);
             false
         }
         RetryStrategy::FixedDelay(duration) => {
-            info!("[{}] ⏱️ Retry with fixed delay: status={}, attempt={}/{}", trace_id, status_code, attempt + 1, MAX_RETRY_ATTEMPTS);
+            info!("[{}] ⏱️ Retry with fixed delay: status={}, attempt={}/{}", trace_id, status_code, attempt + 1, max_attempts);
             sleep(duration).await;
             true
         }
         RetryStrategy::LinearBackoff { base_ms } => {
             let delay = base_ms * (attempt as u64 + 1);
-            info!("[{}] ⏱️ Retry with linear backoff: status={}, attempt={}/{}", trace_id, status_code, attempt + 1, MAX_RETRY_ATTEMPTS);
+            info!("[{}] ⏱️ Retry with linear backoff: status={}, attempt={}/{}", trace_id, status_code, attempt + 1, max_attempts);
             sleep(Duration::from_millis(delay)).await;
             true
         }
         RetryStrategy::ExponentialBackoff { base_ms, max_ms } => {
              let delay = (base_ms * 2_u64.pow(attempt as u32)).min(max_ms);
-             info!("[{}] ⏱️ Retry with exponential backoff: status={}, attempt={}/{}", trace_id, status_code, attempt + 1, MAX_RETRY_ATTEMPTS);
+             info!("[{}] ⏱️ Retry with exponential backoff: status={}, attempt={}/{}", trace_id, status_code, attempt + 1, max_attempts);
              sleep(Duration::from_millis(delay)).await;
              true
         }
@@ -69,7 +70,21 @@ async fn apply_retry_strategy(strategy: RetryStrategy, attempt: usize, status_co
 
 pub async fn handle_chat_completions(
     State(state): State<AppState>,
-    Json(mut body): Json<Value>,
+    Json(body): Json<Value>,
+) -> Response {
+    let cache_hints = openai_cache_hints(&body);
+    let mut response = match handle_chat_completions_inner(state, body, &cache_hints).await {
+        Ok(response) => response.into_response(),
+        Err(error) => error.into_response(),
+    };
+    cache_hints.apply_header(&mut response);
+    response
+}
+
+async fn handle_chat_completions_inner(
+    state: AppState,
+    mut body: Value,
+    cache_hints: &PromptCacheHints,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     // [NEW] 自动检测并转换 Responses 格式
     // 如果请求包含 instructions 或 input 但没有 messages，则认为是 Responses 格式
@@ -122,6 +137,8 @@ pub async fn handle_chat_completions(
 
     let mut openai_req: OpenAIRequest = serde_json::from_value(body)
         .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid request: {}", e)))?;
+    crate::proxy::mappers::image_input::validate_openai_images(&openai_req.messages)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid image: {}", e)))?;
 
     // Safety: Ensure messages is not empty
     if openai_req.messages.is_empty() {
@@ -146,7 +163,7 @@ pub async fn handle_chat_completions(
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager;
     let pool_size = token_manager.len();
-    let max_attempts = MAX_RETRY_ATTEMPTS.min(pool_size).max(1);
+    let max_attempts = crate::proxy::upstream::failover::max_attempts(pool_size, 1);
 
     let mut last_error = String::new();
     let mut last_email: Option<String> = None;
@@ -157,6 +174,9 @@ pub async fn handle_chat_completions(
         &*state.custom_mapping.read().await,
     );
 
+    // 请求镜像 (仅首次尝试时抽样)
+    let mut mirror: Option<crate::proxy::mirror::MirrorHandle> = None;
+
     for attempt in 0..max_attempts {
         // 将 OpenAI 工具转为 Value 数组以便探测联网
         let tools_val: Option<Vec<Value>> = openai_req
@@ -170,11 +190,15 @@ pub async fn handle_chat_completions(
         );
 
         // 3. 提取 SessionId (粘性指纹)
-        let session_id = SessionManager::extract_openai_session_id(&openai_req);
+        // prompt_cache_key 作为粘性会话键，同一缓存键落到同一账号以命中上游隐式缓存
+        let session_id = cache_hints
+            .cache_key
+            .clone()
+            .unwrap_or_else(|| SessionManager::extract_openai_session_id(&openai_req));
 
         // 4. 获取 Token (使用准确的 request_type)
         // 关键：在重试尝试 (attempt > 0) 时强制轮换账号
-        let (access_token, project_id, email) = match token_manager
+        let (access_token, project_id, email, upstream_proxy) = match token_manager
             .get_token(&config.request_type, attempt > 0, Some(&session_id), &openai_req.model)
             .await
         {
@@ -192,12 +216,29 @@ pub async fn handle_chat_completions(
 
         // 4. 转换请求
         let gemini_body = transform_openai_request(&openai_req, &project_id, &mapped_model);
+        if crate::proxy::diagnostics::enabled() {
+            crate::proxy::diagnostics::record_estimated_tokens(
+                ContextManager::estimate_gemini_input_tokens(&gemini_body) as u64,
+            );
+        }
 
         // [New] 打印转换后的报文 (Gemini Body) 供调试
         if let Ok(body_json) = serde_json::to_string_pretty(&gemini_body) {
             debug!("[OpenAI-Request] Transformed Gemini Body:\n{}", body_json);
         }
 
+        if attempt == 0 {
+            mirror = crate::proxy::mirror::begin(
+                &token_manager,
+                &upstream,
+                "openai",
+                &openai_req.model,
+                &mapped_model,
+                &email,
+                &gemini_body,
+            );
+        }
+
         // 5. 发送请求
         let actual_stream = openai_req.stream;
         
@@ -209,12 +250,13 @@ pub async fn handle_chat_completions(
         let query_string = if actual_stream { Some("alt=sse") } else { None };
 
         let response = match upstream
-            .call_v1_internal(method, &access_token, gemini_body, query_string)
+            .call_v1_internal(method, &access_token, upstream_proxy.as_deref(), gemini_body, query_string)
             .await
         {
             Ok(r) => r,
             Err(e) => {
                 last_error = e.clone();
+                token_manager.record_upstream_outcome(&email, None);
                 debug!(
                     "OpenAI Request failed on attempt {}/{}: {}",
                     attempt + 1,
@@ -226,6 +268,7 @@ pub async fn handle_chat_completions(
         };
 
         let status = response.status();
+        token_manager.record_upstream_outcome(&email, Some(status.as_u16()));
         if status.is_success() {
             // 5. 处理流式 vs 非流式
             if actual_stream {
@@ -241,12 +284,13 @@ pub async fn handle_chat_completions(
                 let mut openai_stream =
                     create_openai_sse_stream(Box::pin(gemini_stream), openai_req.model.clone());
                 
+                let first_byte_timeout = crate::proxy::upstream::timeouts::for_model(&mapped_model).first_byte;
                 let mut first_data_chunk = None;
                 let mut retry_this_account = false;
                 
                 // Loop to skip heartbeats during peek
                 loop {
-                    match tokio::time::timeout(std::time::Duration::from_secs(60), openai_stream.next()).await {
+                    match tokio::time::timeout(first_byte_timeout, openai_stream.next()).await {
                         Ok(Some(Ok(bytes))) => {
                             if bytes.is_empty() {
                                 continue;
@@ -284,7 +328,7 @@ pub async fn handle_chat_completions(
                             break;
                         }
                         Err(_) => {
-                            tracing::warn!("[OpenAI] Timeout waiting for first data (60s), retrying...");
+                            tracing::warn!("[OpenAI] Timeout waiting for first data ({}s), retrying...", first_byte_timeout.as_secs());
                             last_error = "Timeout waiting for first data".to_string();
                             retry_this_account = true;
                             break;
@@ -303,6 +347,9 @@ pub async fn handle_chat_completions(
                 .chain(openai_stream);
                 
                 if actual_stream {
+                    if let Some(m) = mirror.take() {
+                        m.finish::<Value>(200, true, None);
+                    }
                     // 客户端请求流式，返回 SSE
                     let body = Body::from_stream(combined_stream);
                     return Ok(Response::builder()
@@ -327,6 +374,9 @@ pub async fn handle_chat_completions(
                 .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Parse error: {}", e)))?;
 
             let openai_response = transform_openai_response(&gemini_resp);
+            if let Some(m) = mirror.take() {
+                m.finish(200, false, Some(&openai_response));
+            }
             return Ok((StatusCode::OK, [("X-Account-Email", email.as_str()), ("X-Mapped-Model", mapped_model.as_str())], Json(openai_response)).into_response());
         }
 
@@ -363,18 +413,7 @@ pub async fn handle_chat_completions(
                 continue;
             }
 
-            // 2. 只有明确包含 "QUOTA_EXHAUSTED" 才停止，避免误判频率提示 (如 "check quota")
-            if error_text.contains("QUOTA_EXHAUSTED") {
-                error!(
-                    "OpenAI Quota exhausted (429) on account {} attempt {}/{}, stopping to protect pool.",
-                    email,
-                    attempt + 1,
-                    max_attempts
-                );
-                return Ok((status, [("X-Account-Email", email.as_str()), ("X-Mapped-Model", mapped_model.as_str())], error_text).into_response());
-            }
-
-            // 3. 其他限流或服务器过载情况，轮换账号
+            // 2. 配额耗尽、其他限流或服务器过载：账号已被标记限流，轮换到下一个账号
             tracing::warn!(
                 "OpenAI Upstream {} on {} attempt {}/{}, rotating account",
                 status_code,
@@ -386,7 +425,8 @@ pub async fn handle_chat_completions(
         }
 
         // 只有 403 (权限/地区限制) 和 401 (认证失效) 触发账号轮换
-        if status_code == 403 || status_code == 401 {
+        if crate::proxy::upstream::failover::is_auth_failure(status_code) {
+            token_manager.mark_auth_failure(&email, status_code);
             tracing::warn!(
                 "OpenAI Upstream {} on account {} attempt {}/{}, rotating account",
                 status_code,
@@ -402,6 +442,9 @@ pub async fn handle_chat_completions(
             "OpenAI Upstream non-retryable error {} on account {}: {}",
             status_code, email, error_text
         );
+        if let Some(m) = mirror.take() {
+            m.finish(status_code, false, Some(&error_text));
+        }
         return Ok((status, [("X-Account-Email", email.as_str()), ("X-Mapped-Model", mapped_model.as_str())], error_text).into_response());
     }
 
@@ -767,6 +810,9 @@ pub async fn handle_completions(
             ).into_response();
         }
     };
+    if let Err(e) = crate::proxy::mappers::image_input::validate_openai_images(&openai_req.messages) {
+        return (StatusCode::BAD_REQUEST, format!("Invalid image: {}", e)).into_response();
+    }
 
     // Safety: Inject empty message if needed
     if openai_req.messages.is_empty() {
@@ -787,7 +833,7 @@ pub async fn handle_completions(
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager;
     let pool_size = token_manager.len();
-    let max_attempts = MAX_RETRY_ATTEMPTS.min(pool_size).max(1);
+    let max_attempts = crate::proxy::upstream::failover::max_attempts(pool_size, 1);
 
     let mut last_error = String::new();
     let mut last_email: Option<String> = None;
@@ -820,7 +866,7 @@ pub async fn handle_completions(
         // 重试时强制轮换，除非只是简单的网络抖动但 Claude 逻辑里 attempt > 0 总是 force_rotate
         let force_rotate = attempt > 0;
 
-        let (access_token, project_id, email) =
+        let (access_token, project_id, email, upstream_proxy) =
             match token_manager.get_token(&config.request_type, force_rotate, session_id, &openai_req.model).await {
                 Ok(t) => t,
                 Err(e) => {
@@ -837,6 +883,11 @@ pub async fn handle_completions(
         info!("✓ Using account: {} (type: {})", email, config.request_type);
 
         let gemini_body = transform_openai_request(&openai_req, &project_id, &mapped_model);
+        if crate::proxy::diagnostics::enabled() {
+            crate::proxy::diagnostics::record_estimated_tokens(
+                ContextManager::estimate_gemini_input_tokens(&gemini_body) as u64,
+            );
+        }
 
         // [New] 打印转换后的报文 (Gemini Body) 供调试 (Codex 路径) ———— 缩减为 simple debug
         debug!("[Codex-Request] Transformed Gemini Body ({} parts)", 
@@ -851,18 +902,20 @@ pub async fn handle_completions(
         let query_string = if list_response { Some("alt=sse") } else { None };
 
         let response = match upstream
-            .call_v1_internal(method, &access_token, gemini_body, query_string)
+            .call_v1_internal(method, &access_token, upstream_proxy.as_deref(), gemini_body, query_string)
             .await
         {
             Ok(r) => r,
             Err(e) => {
                 last_error = e.clone();
+                token_manager.record_upstream_outcome(&email, None);
                 debug!("Codex Request failed on attempt {}/{}: {}", attempt + 1, max_attempts, e);
                 continue;
             }
         };
 
         let status = response.status();
+        token_manager.record_upstream_outcome(&email, Some(status.as_u16()));
         if status.is_success() {
             // [智能限流] 请求成功，重置该账号的连续失败计数
             token_manager.mark_account_success(&email);
@@ -882,12 +935,13 @@ pub async fn handle_completions(
                 };
 
                 // [P1 FIX] Enhanced Peek logic to handle heartbeats and slow start
+                let first_byte_timeout = crate::proxy::upstream::timeouts::for_model(&mapped_model).first_byte;
                 let mut first_data_chunk = None;
                 let mut retry_this_account = false;
                 
                 // Loop to skip heartbeats during peek
                 loop {
-                    match tokio::time::timeout(std::time::Duration::from_secs(60), openai_stream.next()).await {
+                    match tokio::time::timeout(first_byte_timeout, openai_stream.next()).await {
                         Ok(Some(Ok(bytes))) => {
                             if bytes.is_empty() {
                                 continue;
@@ -925,7 +979,7 @@ pub async fn handle_completions(
                             break;
                         }
                         Err(_) => {
-                            tracing::warn!("[OpenAI-Legacy] Timeout waiting for first data (60s), retrying...");
+                            tracing::warn!("[OpenAI-Legacy] Timeout waiting for first data ({}s), retrying...", first_byte_timeout.as_secs());
                             last_error = "Timeout waiting for first data".to_string();
                             retry_this_account = true;
                             break;
@@ -1008,11 +1062,14 @@ pub async fn handle_completions(
         if status_code == 429 || status_code == 529 || status_code == 503 || status_code == 500 {
             token_manager.mark_rate_limited_async(&email, status_code, retry_after.as_deref(), &error_text, Some(&mapped_model)).await;
         }
+        if crate::proxy::upstream::failover::is_auth_failure(status_code) {
+            token_manager.mark_auth_failure(&email, status_code);
+        }
 
         // 确定重试策略
         let strategy = determine_retry_strategy(status_code, &error_text);
         
-        if apply_retry_strategy(strategy, attempt, status_code, &trace_id).await {
+        if apply_retry_strategy(strategy, attempt, max_attempts, status_code, &trace_id).await {
             // 继续重试 (loop 会增加 attempt, 导致 force_rotate=true)
             continue;
         } else {
@@ -1037,26 +1094,38 @@ pub async fn handle_completions(
     }
 }
 
-pub async fn handle_list_models(State(state): State<AppState>) -> impl IntoResponse {
-    use crate::proxy::common::model_mapping::get_all_dynamic_models;
-
-    let model_ids = get_all_dynamic_models(
-        &state.custom_mapping,
-    ).await;
+pub async fn handle_list_models(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> Response {
+    // Anthropic SDK / Claude Code 请求 /v1/models 时带 anthropic-version 头，返回 Anthropic 格式
+    if headers.contains_key("anthropic-version") {
+        return crate::proxy::handlers::claude::handle_list_models(State(state))
+            .await
+            .into_response();
+    }
 
-    let data: Vec<_> = model_ids.into_iter().map(|id| {
-        json!({
-            "id": id,
-            "object": "model",
-            "created": 1706745600,
-            "owned_by": "antigravity"
+    let data: Vec<_> = crate::proxy::handlers::common::routable_models(&state)
+        .await
+        .into_iter()
+        .map(|m| {
+            json!({
+                "id": m.id,
+                "object": "model",
+                "created": 1706745600,
+                "owned_by": "antigravity",
+                "target": m.target,
+                "alias": m.alias,
+                "available_accounts": m.available_accounts
+            })
         })
-    }).collect();
+        .collect();
 
     Json(json!({
         "object": "list",
         "data": data
     }))
+    .into_response()
 }
 
 /// OpenAI Images API: POST /v1/images/generations
@@ -1132,7 +1201,7 @@ pub async fn handle_images_generations(
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager;
 
-    let (access_token, project_id, email) = match token_manager.get_token("image_gen", false, None, "dall-e-3").await
+    let (access_token, project_id, email, upstream_proxy) = match token_manager.get_token("image_gen", false, None, "dall-e-3").await
     {
         Ok(t) => t,
         Err(e) => {
@@ -1151,6 +1220,7 @@ pub async fn handle_images_generations(
     for _ in 0..n {
         let upstream = upstream.clone();
         let access_token = access_token.clone();
+        let upstream_proxy = upstream_proxy.clone();
         let project_id = project_id.clone();
         let final_prompt = final_prompt.clone();
         let aspect_ratio = aspect_ratio.to_string();
@@ -1185,7 +1255,7 @@ pub async fn handle_images_generations(
             });
 
             match upstream
-                .call_v1_internal("generateContent", &access_token, gemini_body, None)
+                .call_v1_internal("generateContent", &access_token, upstream_proxy.as_deref(), gemini_body, None)
                 .await
             {
                 Ok(response) => {
@@ -1386,7 +1456,7 @@ pub async fn handle_images_edits(
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager;
     // Fix: Proper get_token call with correct signature and unwrap (using image_gen quota)
-    let (access_token, project_id, email) = match token_manager.get_token("image_gen", false, None, "dall-e-3").await
+    let (access_token, project_id, email, upstream_proxy) = match token_manager.get_token("image_gen", false, None, "dall-e-3").await
     {
         Ok(t) => t,
         Err(e) => {
@@ -1456,11 +1526,12 @@ pub async fn handle_images_edits(
     for _ in 0..n {
         let upstream = upstream.clone();
         let access_token = access_token.clone();
+        let upstream_proxy = upstream_proxy.clone();
         let body = gemini_body.clone();
 
         tasks.push(tokio::spawn(async move {
             match upstream
-                .call_v1_internal("generateContent", &access_token, body, None)
+                .call_v1_internal("generateContent", &access_token, upstream_proxy.as_deref(), body, None)
                 .await
             {
                 Ok(response) => {
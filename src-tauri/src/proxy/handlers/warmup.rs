@@ -50,11 +50,18 @@ pub async fn handle_warmup(
     );
 
     // ===== 步骤 1: 获取 Token =====
-    let (access_token, project_id) = if let (Some(at), Some(pid)) = (&req.access_token, &req.project_id) {
-        (at.clone(), pid.clone())
+    // 调用方直接提供 Token 时，账号专属代理从账号文件读取
+    let (access_token, project_id, upstream_proxy) = if let (Some(at), Some(pid)) = (&req.access_token, &req.project_id) {
+        let email = req.email.clone();
+        let upstream_proxy = tokio::task::spawn_blocking(move || {
+            crate::modules::account::upstream_proxy_for_email(&email)
+        })
+        .await
+        .unwrap_or(None);
+        (at.clone(), pid.clone(), upstream_proxy)
     } else {
         match state.token_manager.get_token_by_email(&req.email).await {
-            Ok((at, pid, _)) => (at, pid),
+            Ok((at, pid, _, upstream_proxy)) => (at, pid, upstream_proxy),
             Err(e) => {
                 warn!(
                     "[Warmup-API] Step 1 FAILED: Token error for {}: {}",
@@ -103,6 +110,7 @@ pub async fn handle_warmup(
             }),
             thinking: None,
             output_config: None,
+            tool_choice: None,
         };
 
         match crate::proxy::mappers::claude::transform_claude_request_in(
@@ -168,14 +176,14 @@ pub async fn handle_warmup(
 
     let mut result = state
         .upstream
-        .call_v1_internal(method, &access_token, body.clone(), query)
+        .call_v1_internal(method, &access_token, upstream_proxy.as_deref(), body.clone(), query)
         .await;
 
     // 如果流式请求失败，尝试非流式请求
     if result.is_err() && !prefer_non_stream {
         result = state
             .upstream
-            .call_v1_internal("generateContent", &access_token, body, None)
+            .call_v1_internal("generateContent", &access_token, upstream_proxy.as_deref(), body, None)
             .await;
     }
 
@@ -39,11 +39,12 @@ pub fn create_claude_sse_stream(
         state.scaling_enabled = scaling_enabled; // Set scaling enabled flag
         state.context_limit = context_limit;
         let mut buffer = BytesMut::new();
+        let mut errored = false;
 
         loop {
-            // [NEW] 30秒心跳保活: 延长超时时间以兼容长延迟模型
+            // 空闲心跳保活: 上游长时间无数据时发送 SSE 注释
             let next_chunk = tokio::time::timeout(
-                std::time::Duration::from_secs(30),
+                crate::proxy::common::sse::KEEPALIVE_INTERVAL,
                 gemini_stream.next()
             ).await;
 
@@ -70,6 +71,7 @@ pub fn create_claude_sse_stream(
                         }
                         Err(e) => {
                             yield Err(format!("Stream error: {}", e));
+                            errored = true;
                             break;
                         }
                     }
@@ -77,14 +79,16 @@ pub fn create_claude_sse_stream(
                 Ok(None) => break, // Stream 正常结束
                 Err(_) => {
                     // 超时，发送心跳包 (SSE Comment 格式)
-                    yield Ok(Bytes::from(": ping\n\n"));
+                    yield Ok(Bytes::from(crate::proxy::common::sse::KEEPALIVE_COMMENT));
                 }
             }
         }
 
-        // Ensure termination events are sent
-        for chunk in emit_force_stop(&mut state) {
-            yield Ok(chunk);
+        // 正常结束时补齐 message_delta / message_stop；出错时以 error 事件结束 (与 Anthropic 一致)
+        if !errored {
+            for chunk in emit_force_stop(&mut state) {
+                yield Ok(chunk);
+            }
         }
     })
 }
@@ -297,13 +297,29 @@ pub fn merge_consecutive_messages(messages: &mut Vec<Message>) {
 
 /// 转换 Claude 请求为 Gemini v1internal 格式
 
+/// Claude tool_choice → Gemini functionCallingConfig
+/// 未指定 / auto 时沿用 VALIDATED；Gemini 没有禁止并行调用的开关，disable_parallel_tool_use 忽略
+fn build_function_calling_config(tool_choice: Option<&ToolChoice>) -> Value {
+    let Some(choice) = tool_choice else {
+        return json!({ "mode": "VALIDATED" });
+    };
+    match (choice.choice_type.as_str(), choice.name.as_deref()) {
+        ("any", _) | ("tool", None) => json!({ "mode": "ANY" }),
+        ("tool", Some(name)) => json!({ "mode": "ANY", "allowedFunctionNames": [name] }),
+        ("none", _) => json!({ "mode": "NONE" }),
+        _ => json!({ "mode": "VALIDATED" }),
+    }
+}
+
 /// [FIX #709] Reorder serialized Gemini parts to ensure thinking blocks are first
+/// functionResponse 紧随其后 (回应上一轮的调用)，functionCall 放在最后；同类 part 保持原有顺序
 fn reorder_gemini_parts(parts: &mut Vec<Value>) {
     if parts.len() <= 1 {
         return;
     }
 
     let mut thinking_parts = Vec::new();
+    let mut response_parts = Vec::new();
     let mut text_parts = Vec::new();
     let mut tool_parts = Vec::new();
     let mut other_parts = Vec::new();
@@ -311,6 +327,8 @@ fn reorder_gemini_parts(parts: &mut Vec<Value>) {
     for part in parts.drain(..) {
         if part.get("thought").and_then(|t| t.as_bool()) == Some(true) {
             thinking_parts.push(part);
+        } else if part.get("functionResponse").is_some() {
+            response_parts.push(part);
         } else if part.get("functionCall").is_some() {
             tool_parts.push(part);
         } else if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
@@ -324,11 +342,81 @@ fn reorder_gemini_parts(parts: &mut Vec<Value>) {
     }
 
     parts.extend(thinking_parts);
+    parts.extend(response_parts);
     parts.extend(text_parts);
     parts.extend(other_parts);
     parts.extend(tool_parts);
 }
 
+/// 按上一轮 functionCall 的顺序排列 functionResponse 并置于其他 part 之前，
+/// 客户端按完成顺序回传并行调用的结果时，上游仍能按调用顺序对应
+fn order_function_responses(parts: &mut Vec<Value>, call_order: &[String]) {
+    let (mut responses, others): (Vec<Value>, Vec<Value>) = parts
+        .drain(..)
+        .partition(|p| p.get("functionResponse").is_some());
+    responses.sort_by_key(|p| {
+        p["functionResponse"]["id"]
+            .as_str()
+            .and_then(|id| call_order.iter().position(|c| c == id))
+            .unwrap_or(usize::MAX)
+    });
+    parts.extend(responses);
+    parts.extend(others);
+}
+
+/// tool_result 中单个内容块的文本表示；图片 / 文档以占位符保留其位置
+fn tool_result_block_text(block: &Value) -> Option<String> {
+    if let Some(text) = block.get("text").and_then(|v| v.as_str()) {
+        return Some(text.to_string());
+    }
+    match block.get("type").and_then(|v| v.as_str()) {
+        Some("image") => Some("[image omitted to save context]".to_string()),
+        Some("document") => Some("[document omitted to save context]".to_string()),
+        Some("search_result") => {
+            let mut lines = Vec::new();
+            if let Some(title) = block.get("title").and_then(|v| v.as_str()) {
+                lines.push(title.to_string());
+            }
+            if let Some(source) = block.get("source").and_then(|v| v.as_str()) {
+                lines.push(source.to_string());
+            }
+            if let Some(items) = block.get("content").and_then(|v| v.as_array()) {
+                lines.extend(items.iter().filter_map(tool_result_block_text));
+            }
+            Some(lines.join("\n"))
+        }
+        _ if block.is_object() => Some(block.to_string()),
+        _ => None,
+    }
+}
+
+/// functionResponse 只能携带文本：把 tool_result 中的 base64 图片取出，转为紧随其后的 inlineData part，
+/// 原位置留下文字标记。无法转发的图片 (过大、类型不支持) 保留在原处，由后续清洗替换为占位说明
+fn take_tool_result_images(blocks: &mut [Value]) -> Vec<Value> {
+    let mut images = Vec::new();
+    for block in blocks.iter_mut() {
+        let source = match block.get("source") {
+            Some(source)
+                if block.get("type").and_then(|v| v.as_str()) == Some("image")
+                    && source.get("type").and_then(|v| v.as_str()) == Some("base64") =>
+            {
+                source
+            }
+            _ => continue,
+        };
+        let media_type = source.get("media_type").and_then(|v| v.as_str());
+        let data = source.get("data").and_then(|v| v.as_str()).unwrap_or_default();
+        match crate::proxy::mappers::image_input::inline_image_part(media_type, data) {
+            Ok(part) => {
+                images.push(part);
+                *block = json!({"type": "text", "text": "[image attached]"});
+            }
+            Err(e) => tracing::debug!("[Claude-Request] Dropping tool_result image: {}", e),
+        }
+    }
+    images
+}
+
 pub fn transform_claude_request_in(
     claude_req: &ClaudeRequest,
     project_id: &str,
@@ -427,10 +515,10 @@ pub fn transform_claude_request_in(
         });
 
     // [NEW FIX] Check if target model supports thinking
-    // Only models with "-thinking" suffix or Claude models support thinking
-    // Regular Gemini models (gemini-2.5-flash, gemini-2.5-pro) do NOT support thinking
-    let target_model_supports_thinking = mapped_model.contains("-thinking") 
-        || mapped_model.starts_with("claude-");
+    // Driven by the model capability table (built-in: "-thinking" suffix or Claude models;
+    // regular Gemini models such as gemini-2.5-flash do NOT support thinking)
+    let capability = crate::proxy::common::model_capabilities::lookup(&mapped_model);
+    let target_model_supports_thinking = capability.supports_thinking;
     
     if is_thinking_enabled && !target_model_supports_thinking {
         tracing::warn!(
@@ -500,10 +588,10 @@ pub fn transform_claude_request_in(
     }
 
     // 4. Generation Config & Thinking (Pass final is_thinking_enabled)
-    let generation_config = build_generation_config(claude_req, has_web_search_tool, is_thinking_enabled);
+    let generation_config = build_generation_config(claude_req, &capability, is_thinking_enabled);
 
     // 2. Contents (Messages)
-    let contents = build_google_contents(
+    let mut contents = build_google_contents(
         &claude_req.messages,
         claude_req,
         &mut tool_id_to_name,
@@ -513,9 +601,25 @@ pub fn transform_claude_request_in(
         &session_id,
         is_retry,
     )?;
+    if !capability.supports_vision {
+        let stripped = crate::proxy::common::model_capabilities::strip_image_parts(&mut contents);
+        if stripped > 0 {
+            tracing::warn!(
+                "[Claude-Request] Target model '{}' does not accept images, replaced {} image part(s)",
+                mapped_model, stripped
+            );
+        }
+    }
 
     // 3. Tools
-    let tools = build_tools(&claude_req.tools, has_web_search_tool)?;
+    let mut tools = build_tools(&claude_req.tools, has_web_search_tool)?;
+    if tools.is_some() && !capability.supports_tools {
+        tracing::warn!(
+            "[Claude-Request] Target model '{}' does not support tools, dropping tool definitions",
+            mapped_model
+        );
+        tools = None;
+    }
 
     // 5. Safety Settings (configurable via GEMINI_SAFETY_THRESHOLD env var)
     let safety_settings = build_safety_settings();
@@ -539,11 +643,8 @@ pub fn transform_claude_request_in(
 
     if let Some(tools_val) = tools {
         inner_request["tools"] = tools_val;
-        // 显式设置工具配置模式为 VALIDATED
         inner_request["toolConfig"] = json!({
-            "functionCallingConfig": {
-                "mode": "VALIDATED"
-            }
+            "functionCallingConfig": build_function_calling_config(claude_req.tool_choice.as_ref())
         });
     }
 
@@ -946,15 +1047,8 @@ fn build_contents(
                         continue;
                     }
                     ContentBlock::Image { source, .. } => {
-                        if source.source_type == "base64" {
-                            parts.push(json!({
-                                "inlineData": {
-                                    "mimeType": source.media_type,
-                                    "data": source.data
-                                }
-                            }));
-                            saw_non_thinking = true;
-                        }
+                        parts.push(crate::proxy::mappers::image_input::claude_image_part(source)?);
+                        saw_non_thinking = true;
                     }
                     ContentBlock::Document { source, .. } => {
                         if source.source_type == "base64" {
@@ -1117,31 +1211,18 @@ fn build_contents(
                         // [FIX #593] 工具输出压缩: 处理超大工具输出
                         // 使用智能压缩策略(浏览器快照、大文件提示等)
                         let mut compacted_content = content.clone();
+                        let mut image_parts = Vec::new();
                         if let Some(blocks) = compacted_content.as_array_mut() {
+                            image_parts = take_tool_result_images(blocks);
                             tool_result_compressor::sanitize_tool_result_blocks(blocks);
                         }
 
-                        // Smart Truncation: strict image removal
-                        // Remove all Base64 images from historical tool results to save context.
-                        // Only allow text.
+                        // functionResponse 只保留文本，图片已转为 inlineData part
                         let mut merged_content = match &compacted_content {
                             serde_json::Value::String(s) => s.clone(),
                             serde_json::Value::Array(arr) => arr
                                 .iter()
-                                .filter_map(|block| {
-                                    if let Some(text) = block.get("text").and_then(|v| v.as_str()) {
-                                        Some(text.to_string())
-                                    } else if block.get("source").is_some() {
-                                        // If it's an image/document, replace with placeholder
-                                        if block.get("type").and_then(|v| v.as_str()) == Some("image") {
-                                            Some("[image omitted to save context]".to_string())
-                                        } else {
-                                            None
-                                        }
-                                    } else {
-                                        None
-                                    }
-                                })
+                                .filter_map(tool_result_block_text)
                                 .collect::<Vec<_>>()
                                 .join("\n"),
                             _ => content.to_string(),
@@ -1166,10 +1247,16 @@ fn build_contents(
                             }
                         }
 
+                        // 上游约定以 error 字段表示工具执行失败
+                        let response = if is_error.unwrap_or(false) {
+                            json!({"error": merged_content})
+                        } else {
+                            json!({"result": merged_content})
+                        };
                         parts.push(json!({
                             "functionResponse": {
                                 "name": func_name,
-                                "response": {"result": merged_content},
+                                "response": response,
                                 "id": tool_use_id
                             }
                         }));
@@ -1180,6 +1267,7 @@ fn build_contents(
                                 last_part["thoughtSignature"] = json!(sig);
                             }
                         }
+                        parts.extend(image_parts);
 
                         // 标记状态，用于下一条 User 消息的去重判断
                         *previous_was_tool_result = true;
@@ -1218,6 +1306,7 @@ fn build_contents(
                 parts.insert(0, synthetic_part);
             }
         }
+        order_function_responses(&mut parts, pending_tool_use_ids);
         // All pending IDs are now handled (either present or injected)
         pending_tool_use_ids.clear();
     }
@@ -1291,7 +1380,8 @@ fn build_google_content(
     last_user_task_text_normalized: &mut Option<String>,
     previous_was_tool_result: &mut bool,
     existing_tool_result_ids: &std::collections::HashSet<String>,
-) -> Result<Value, String> {
+) -> Result<Vec<Value>, String> {
+    let mut contents = Vec::new();
     let role = if msg.role == "assistant" {
         "model"
     } else {
@@ -1320,8 +1410,9 @@ fn build_google_content(
                 })
             }).collect();
 
+        // 补入的 user 消息放在当前 assistant 消息之前，assistant 消息本身照常转换
         if !synthetic_parts.is_empty() {
-            return Ok(json!({
+            contents.push(json!({
                 "role": "user",
                 "parts": synthetic_parts
             }));
@@ -1347,14 +1438,14 @@ fn build_google_content(
         existing_tool_result_ids,
     )?;
 
-    if parts.is_empty() {
-        return Ok(json!(null)); // Indicate no content to add
+    if !parts.is_empty() {
+        contents.push(json!({
+            "role": role,
+            "parts": parts
+        }));
     }
 
-    Ok(json!({
-        "role": role,
-        "parts": parts
-    }))
+    Ok(contents)
 }
 
 /// 构建 Contents (Messages)
@@ -1394,7 +1485,7 @@ fn build_google_contents(
     }
 
     for (_i, msg) in messages.iter().enumerate() {
-        let google_content = build_google_content(
+        let google_contents = build_google_content(
             msg,
             claude_req,
             is_thinking_enabled,
@@ -1410,9 +1501,7 @@ fn build_google_contents(
             &existing_tool_result_ids,
         )?;
 
-        if !google_content.is_null() {
-            contents.push(google_content);
-        }
+        contents.extend(google_contents);
     }
 
     // [Removed] ensure_last_assistant_has_thinking
@@ -1544,7 +1633,7 @@ fn build_tools(tools: &Option<Vec<Tool>>, has_web_search: bool) -> Result<Option
 /// 构建 Generation Config
 fn build_generation_config(
     claude_req: &ClaudeRequest,
-    has_web_search: bool,
+    capability: &crate::proxy::common::model_capabilities::ModelCapability,
     is_thinking_enabled: bool
 ) -> Value {
     let mut config = json!({});
@@ -1557,11 +1646,9 @@ fn build_generation_config(
 
             if let Some(budget_tokens) = thinking.budget_tokens {
                 let mut budget = budget_tokens;
-                // gemini-2.5-flash 上限 24576
-                let is_flash_model =
-                    has_web_search || claude_req.model.contains("gemini-2.5-flash");
-                if is_flash_model {
-                    budget = budget.min(24576);
+                // 按能力表限制 (如 gemini-2.5-flash 上限 24576)
+                if let Some(max_budget) = capability.max_thinking_budget {
+                    budget = budget.min(max_budget);
                 }
                 thinking_config["thinkingBudget"] = json!(budget);
             }
@@ -1606,13 +1693,24 @@ fn build_generation_config(
 
     // max_tokens 映射为 maxOutputTokens
     // Respect client-provided max_tokens (aligns with OpenAI mapper behavior)
-    let mut final_max_tokens: i64 = claude_req.max_tokens.map(|t| t as i64).unwrap_or(16384);
+    // 不超过目标模型的最大输出 (能力表)
+    let max_output = capability.max_output_tokens as i64;
+    let mut final_max_tokens: i64 = claude_req
+        .max_tokens
+        .map(|t| t as i64)
+        .unwrap_or(16384)
+        .min(max_output);
     
     // [NEW] 确保 maxOutputTokens 大于 thinkingBudget (API 强约束)
-    if let Some(thinking_config) = config.get("thinkingConfig") {
-        if let Some(budget) = thinking_config.get("thinkingBudget").and_then(|t| t.as_u64()) {
+    if let Some(thinking_config) = config.get_mut("thinkingConfig") {
+        if let Some(mut budget) = thinking_config.get("thinkingBudget").and_then(|t| t.as_u64()) {
+            // thinkingBudget 必须小于 maxOutputTokens，超出模型最大输出时收紧到上限以内
+            if budget as i64 >= max_output {
+                budget = (max_output - 1).max(1) as u64;
+                thinking_config["thinkingBudget"] = json!(budget);
+            }
             if final_max_tokens <= budget as i64 {
-                final_max_tokens = (budget + 8192) as i64;
+                final_max_tokens = ((budget + 8192) as i64).min(max_output);
                 tracing::info!(
                     "[Generation-Config] Bumping maxOutputTokens to {} due to thinking budget of {}", 
                     final_max_tokens, budget
@@ -1748,6 +1846,7 @@ mod tests {
             thinking: None,
             metadata: None,
             output_config: None,
+            tool_choice: None,
         };
 
         let result = transform_claude_request_in(&req, "test-project", false);
@@ -1845,6 +1944,7 @@ mod tests {
             thinking: None,
             metadata: None,
             output_config: None,
+            tool_choice: None,
         };
 
         let result = transform_claude_request_in(&req, "test-project", false);
@@ -1899,6 +1999,7 @@ mod tests {
                                 source_type: "base64".to_string(),
                                 media_type: "image/png".to_string(),
                                 data: "iVBORw0KGgo=".to_string(),
+                                url: None,
                             },
                             cache_control: Some(json!({"type": "ephemeral"})), // 这个也应该被清理
                         },
@@ -1915,6 +2016,7 @@ mod tests {
             thinking: None,
             metadata: None,
             output_config: None,
+            tool_choice: None,
         };
 
         let result = transform_claude_request_in(&req, "test-project", false);
@@ -1990,6 +2092,7 @@ mod tests {
             }),
             metadata: None,
             output_config: None,
+            tool_choice: None,
         };
 
         let result = transform_claude_request_in(&req, "test-project", false);
@@ -2039,6 +2142,7 @@ mod tests {
             thinking: None, // 未启用 thinking
             metadata: None,
             output_config: None,
+            tool_choice: None,
         };
 
         let result = transform_claude_request_in(&req, "test-project", false);
@@ -2092,6 +2196,7 @@ mod tests {
             }),
             metadata: None,
             output_config: None,
+            tool_choice: None,
         };
 
         let result = transform_claude_request_in(&req, "test-project", false);
@@ -2132,6 +2237,7 @@ mod tests {
             thinking: None,
             metadata: None,
             output_config: None,
+            tool_choice: None,
         };
 
         let result = transform_claude_request_in(&req, "test-project", false);
@@ -2283,5 +2389,165 @@ mod tests {
             panic!("Expected array content at index 2");
         }
     }
-}
 
+    fn tool_use_ids(blocks: &[ContentBlock]) -> Vec<String> {
+        blocks
+            .iter()
+            .filter_map(|b| match b {
+                ContentBlock::ToolUse { id, .. } => Some(id.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parallel_tool_calls_round_trip() {
+        // Gemini → Claude: 同一轮的多个 functionCall 各自成为 tool_use，顺序不变
+        let gemini_resp: GeminiResponse = serde_json::from_value(json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [
+                    {"text": "Checking both cities."},
+                    {"functionCall": {"name": "get_weather", "args": {"city": "Paris"}}},
+                    {"functionCall": {"name": "get_weather", "args": {"city": "Tokyo"}}},
+                    {"functionCall": {"name": "get_time", "args": {"tz": "UTC"}}}
+                ]},
+                "finishReason": "STOP"
+            }]
+        }))
+        .unwrap();
+        let claude_resp = crate::proxy::mappers::claude::transform_response(
+            &gemini_resp,
+            false,
+            1_000_000,
+            None,
+            "claude-sonnet-4-5".to_string(),
+        )
+        .unwrap();
+        let ids = tool_use_ids(&claude_resp.content);
+        assert_eq!(ids.len(), 3);
+        assert_eq!(claude_resp.stop_reason, "tool_use");
+
+        // Claude → Gemini: 结果乱序回传、含错误与混合内容，并附带一段用户文本
+        let req: ClaudeRequest = serde_json::from_value(json!({
+            "model": "claude-sonnet-4-5",
+            "messages": [
+                {"role": "user", "content": "Weather and time?"},
+                {"role": "assistant", "content": serde_json::to_value(&claude_resp.content).unwrap()},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": ids[2], "content": "12:00"},
+                    {"type": "tool_result", "tool_use_id": ids[0], "content": [
+                        {"type": "text", "text": "Sunny"},
+                        {"type": "document", "source": {"type": "base64", "media_type": "application/pdf", "data": "JVBERi0="}},
+                        {"type": "text", "text": "22C"}
+                    ]},
+                    {"type": "tool_result", "tool_use_id": ids[1], "content": "timeout", "is_error": true},
+                    {"type": "text", "text": "Summarize please"}
+                ]}
+            ],
+            "tools": [
+                {"name": "get_weather", "description": "Weather", "input_schema": {"type": "object", "properties": {"city": {"type": "string"}}}},
+                {"name": "get_time", "description": "Time", "input_schema": {"type": "object", "properties": {"tz": {"type": "string"}}}}
+            ]
+        }))
+        .unwrap();
+        let body = transform_claude_request_in(&req, "test-project", false).unwrap();
+        let contents = body["request"]["contents"].as_array().unwrap();
+
+        let model_parts = contents[1]["parts"].as_array().unwrap();
+        let calls: Vec<&Value> = model_parts
+            .iter()
+            .filter_map(|p| p.get("functionCall"))
+            .collect();
+        assert_eq!(calls.len(), 3);
+        for (call, id) in calls.iter().zip(&ids) {
+            assert_eq!(call["id"].as_str(), Some(id.as_str()));
+        }
+        assert_eq!(calls[0]["args"]["city"], "Paris");
+        assert_eq!(calls[1]["args"]["city"], "Tokyo");
+        assert_eq!(calls[2]["name"], "get_time");
+
+        let user_parts = contents[2]["parts"].as_array().unwrap();
+        let responses: Vec<&Value> = user_parts
+            .iter()
+            .take(3)
+            .map(|p| &p["functionResponse"])
+            .collect();
+        for (resp, id) in responses.iter().zip(&ids) {
+            assert_eq!(resp["id"].as_str(), Some(id.as_str()));
+        }
+        let weather = responses[0]["response"]["result"].as_str().unwrap();
+        assert_eq!(weather, "Sunny\n[document omitted to save context]\n22C");
+        assert_eq!(responses[1]["response"]["error"], "timeout");
+        assert_eq!(responses[2]["response"]["result"], "12:00");
+        assert_eq!(user_parts[3]["text"], "Summarize please");
+    }
+
+    #[test]
+    fn test_tool_result_image_becomes_inline_data() {
+        const PNG_1X1: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==";
+        let req: ClaudeRequest = serde_json::from_value(json!({
+            "model": "claude-sonnet-4-5",
+            "messages": [
+                {"role": "user", "content": "Take a screenshot"},
+                {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "toolu_1", "name": "screenshot", "input": {}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": [
+                        {"type": "text", "text": "Captured"},
+                        {"type": "image", "source": {"type": "base64", "media_type": "image/jpeg", "data": PNG_1X1}}
+                    ]}
+                ]}
+            ],
+            "tools": [
+                {"name": "screenshot", "description": "Screenshot", "input_schema": {"type": "object", "properties": {}}}
+            ]
+        }))
+        .unwrap();
+        let body = transform_claude_request_in(&req, "test-project", false).unwrap();
+        let parts = body["request"]["contents"][2]["parts"].as_array().unwrap();
+
+        assert_eq!(
+            parts[0]["functionResponse"]["response"]["result"],
+            "Captured\n[image attached]"
+        );
+        // 媒体类型按实际内容嗅探
+        assert_eq!(parts[1]["inlineData"]["mimeType"], "image/png");
+        assert_eq!(parts[1]["inlineData"]["data"], PNG_1X1);
+    }
+
+    #[test]
+    fn test_tool_choice_mapping() {
+        let choice = |v: Value| serde_json::from_value::<ToolChoice>(v).unwrap();
+        assert_eq!(build_function_calling_config(None), json!({"mode": "VALIDATED"}));
+        assert_eq!(
+            build_function_calling_config(Some(&choice(json!({"type": "auto"})))),
+            json!({"mode": "VALIDATED"})
+        );
+        assert_eq!(
+            build_function_calling_config(Some(&choice(json!({"type": "any"})))),
+            json!({"mode": "ANY"})
+        );
+        assert_eq!(
+            build_function_calling_config(Some(&choice(json!({"type": "tool", "name": "get_time"})))),
+            json!({"mode": "ANY", "allowedFunctionNames": ["get_time"]})
+        );
+        assert_eq!(
+            build_function_calling_config(Some(&choice(json!({"type": "none"})))),
+            json!({"mode": "NONE"})
+        );
+
+        let req: ClaudeRequest = serde_json::from_value(json!({
+            "model": "claude-sonnet-4-5",
+            "messages": [{"role": "user", "content": "time?"}],
+            "tools": [{"name": "get_time", "description": "Time", "input_schema": {"type": "object", "properties": {}}}],
+            "tool_choice": {"type": "tool", "name": "get_time"}
+        }))
+        .unwrap();
+        let body = transform_claude_request_in(&req, "test-project", false).unwrap();
+        assert_eq!(
+            body["request"]["toolConfig"]["functionCallingConfig"]["allowedFunctionNames"],
+            json!(["get_time"])
+        );
+    }
+}
@@ -8,14 +8,9 @@
 // 已移除未使用的 uppercase_schema_types 函数
 
 /// 根据模型名称获取上下文 Token 限制
+/// (取自模型能力表，支持用户配置覆盖)
 pub fn get_context_limit_for_model(model: &str) -> u32 {
-    if model.contains("pro") {
-        2_097_152 // 2M for Pro
-    } else if model.contains("flash") {
-        1_048_576 // 1M for Flash
-    } else {
-        1_048_576 // Default 1M
-    }
+    crate::proxy::common::model_capabilities::lookup(model).context_window
 }
 
 pub fn to_claude_usage(usage_metadata: &super::models::UsageMetadata, scaling_enabled: bool, context_limit: u32) -> super::models::Usage {
@@ -3,7 +3,7 @@
 //! Responsible for estimating token usage and purifying context (stripping thinking blocks)
 //! to prevent "Prompt is too long" errors and avoid invalid signatures.
 
-use super::claude::models::{ClaudeRequest, Message, MessageContent, ContentBlock, SystemPrompt};
+use super::claude::models::{ClaudeRequest, Message, MessageContent, ContentBlock, DocumentSource, SystemPrompt};
 use tracing::{info, debug};
 
 /// Purification Strategy for Context History
@@ -25,9 +25,176 @@ pub struct ContextStats {
     pub usage_ratio: f32,
 }
 
-/// Helper to estimate tokens from text (approx 3.5 chars per token)
+/// Tokens per PDF page (upstream renders each page as an image tile)
+const TOKENS_PER_DOCUMENT_PAGE: u32 = 258;
+
+/// Rough PDF size per page, used when the page tree is hidden in compressed object streams
+const PDF_BYTES_PER_PAGE: usize = 50 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RunKind {
+    Word,
+    Punct,
+    Space,
+}
+
+fn run_tokens(kind: RunKind, len: u32) -> u32 {
+    match kind {
+        // ~4 chars per token for Latin words, but every word costs at least one
+        RunKind::Word => ((len + 2) / 4).max(1),
+        // Punctuation pairs such as `":` or `);` usually merge into one token
+        RunKind::Punct => len.div_ceil(2),
+        // A single space is merged into the following word
+        RunKind::Space if len > 1 => len.div_ceil(4),
+        RunKind::Space => 0,
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3000}'..='\u{30ff}'   // CJK punctuation, Hiragana, Katakana
+        | '\u{3400}'..='\u{4dbf}' // CJK Extension A
+        | '\u{4e00}'..='\u{9fff}' // CJK Unified Ideographs
+        | '\u{ac00}'..='\u{d7af}' // Hangul syllables
+        | '\u{f900}'..='\u{faff}' // CJK Compatibility Ideographs
+        | '\u{ff00}'..='\u{ffef}' // Fullwidth forms
+    )
+}
+
+/// Helper to estimate tokens from text
+///
+/// Splits the text into runs by script instead of dividing the byte length, which badly
+/// undercounts CJK text (3 bytes but ~1 token per character) and code/JSON (dense punctuation).
 fn estimate_tokens_from_str(s: &str) -> u32 {
-    (s.len() as f32 / 3.5).ceil() as u32
+    let mut total = 0;
+    let mut run: Option<(RunKind, u32)> = None;
+
+    for c in s.chars() {
+        let (kind, weight) = if is_cjk(c) {
+            if let Some((kind, len)) = run.take() {
+                total += run_tokens(kind, len);
+            }
+            total += 1;
+            continue;
+        } else if c.is_ascii_alphanumeric() {
+            (RunKind::Word, 1)
+        } else if c.is_alphabetic() {
+            // Other scripts (Cyrillic, accented Latin, ...) tokenize at ~2 chars per token
+            (RunKind::Word, 2)
+        } else if c == '\n' {
+            (RunKind::Space, 4)
+        } else if c.is_whitespace() {
+            (RunKind::Space, 1)
+        } else if c.is_ascii() {
+            (RunKind::Punct, 1)
+        } else {
+            // Emoji and other symbols usually take at least one token each
+            (RunKind::Punct, 2)
+        };
+
+        run = match run {
+            Some((current, len)) if current == kind => Some((kind, len + weight)),
+            Some((current, len)) => {
+                total += run_tokens(current, len);
+                Some((kind, weight))
+            }
+            None => Some((kind, weight)),
+        };
+    }
+    if let Some((kind, len)) = run {
+        total += run_tokens(kind, len);
+    }
+    total
+}
+
+/// Estimate tokens for a document block: PDFs by page count, plain text by content
+fn estimate_document_tokens(source: &DocumentSource) -> u32 {
+    use base64::{engine::general_purpose, Engine as _};
+
+    if source.source_type == "text" {
+        return estimate_tokens_from_str(&source.data);
+    }
+    let Ok(bytes) = general_purpose::STANDARD.decode(source.data.trim()) else {
+        return TOKENS_PER_DOCUMENT_PAGE;
+    };
+    if source.media_type != "application/pdf" {
+        return estimate_tokens_from_str(&String::from_utf8_lossy(&bytes));
+    }
+    pdf_page_count(&bytes) * TOKENS_PER_DOCUMENT_PAGE
+}
+
+/// Page count of a PDF, from the page objects, else the page tree's `/Count`, else its size
+fn pdf_page_count(bytes: &[u8]) -> u32 {
+    // Count page objects (`/Type /Page`, but not the `/Type /Pages` tree nodes)
+    let pages = [&b"/Type /Page"[..], &b"/Type/Page"[..]]
+        .iter()
+        .map(|marker| {
+            bytes
+                .windows(marker.len() + 1)
+                .filter(|w| w.starts_with(marker) && w[marker.len()] != b's')
+                .count() as u32
+        })
+        .sum::<u32>();
+    if pages > 0 {
+        return pages;
+    }
+
+    // The root of the page tree carries the largest `/Count`
+    let marker = b"/Count";
+    let root_count = bytes
+        .windows(marker.len())
+        .enumerate()
+        .filter(|(_, w)| *w == marker)
+        .filter_map(|(i, _)| {
+            let digits: String = bytes[i + marker.len()..]
+                .iter()
+                .skip_while(|b| b.is_ascii_whitespace())
+                .take_while(|b| b.is_ascii_digit())
+                .map(|&b| b as char)
+                .collect();
+            digits.parse::<u32>().ok()
+        })
+        .max();
+    if let Some(count) = root_count.filter(|c| *c > 0) {
+        return count;
+    }
+
+    (bytes.len().div_ceil(PDF_BYTES_PER_PAGE) as u32).max(1)
+}
+
+/// Estimate tokens for one item of a tool_result content array
+fn estimate_tool_result_item_tokens(item: &serde_json::Value) -> u32 {
+    match item.get("type").and_then(|t| t.as_str()) {
+        Some("image") => item
+            .get("source")
+            .and_then(|s| serde_json::from_value(s.clone()).ok())
+            .map(|source| super::image_input::estimate_image_tokens(&source))
+            .unwrap_or(TOKENS_PER_DOCUMENT_PAGE),
+        Some("document") => item
+            .get("source")
+            .and_then(|s| serde_json::from_value(s.clone()).ok())
+            .map(|source| estimate_document_tokens(&source))
+            .unwrap_or(TOKENS_PER_DOCUMENT_PAGE),
+        _ => item
+            .get("text")
+            .and_then(|t| t.as_str())
+            .map(estimate_tokens_from_str)
+            .unwrap_or(0),
+    }
+}
+
+/// Estimate tokens for one Gemini content part
+fn estimate_gemini_part_tokens(part: &serde_json::Value) -> u32 {
+    if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+        return estimate_tokens_from_str(text);
+    }
+    if part.get("inlineData").is_some() || part.get("fileData").is_some() {
+        return TOKENS_PER_DOCUMENT_PAGE;
+    }
+    if let Some(call) = part.get("functionCall").or_else(|| part.get("functionResponse")) {
+        return 20 + estimate_tokens_from_str(&call.to_string());
+    }
+    0
 }
 
 /// Context Manager implementation
@@ -37,8 +204,26 @@ impl ContextManager {
     /// Estimate token usage for a Claude Request
     /// 
     /// This is a lightweight estimation, not a precise count.
-    /// It iterates through all messages and blocks to sum up estimated tokens.
+    /// Input tokens plus the thinking budget reserved for the response.
     pub fn estimate_token_usage(request: &ClaudeRequest) -> u32 {
+        let mut total = Self::estimate_input_tokens(request);
+
+        // Thinking budget overhead if enabled
+        if let Some(thinking) = &request.thinking {
+             if let Some(budget) = thinking.budget_tokens {
+                 // Reserve budget in estimation
+                 total += budget;
+             }
+        }
+
+        total
+    }
+
+    /// Estimate the input (prompt) tokens of a Claude Request
+    ///
+    /// It iterates through all messages and blocks to sum up estimated tokens.
+    /// Used by `/v1/messages/count_tokens`.
+    pub fn estimate_input_tokens(request: &ClaudeRequest) -> u32 {
         let mut total = 0;
 
         // System prompt
@@ -76,6 +261,13 @@ impl ContextManager {
                             ContentBlock::RedactedThinking { data } => {
                                 total += estimate_tokens_from_str(data);
                             },
+                            ContentBlock::Image { source, .. } => {
+                                // Images are billed by tiles, not by base64 length
+                                total += super::image_input::estimate_image_tokens(source);
+                            },
+                            ContentBlock::Document { source, .. } => {
+                                total += estimate_document_tokens(source);
+                            },
                             ContentBlock::ToolUse { name, input, .. } => {
                                 total += 20; // Function call overhead
                                 total += estimate_tokens_from_str(name);
@@ -90,9 +282,7 @@ impl ContextManager {
                                     total += estimate_tokens_from_str(s);
                                 } else if let Some(arr) = content.as_array() {
                                     for item in arr {
-                                        if let Some(text) = item.get("text").and_then(|t| t.as_str()) {
-                                            total += estimate_tokens_from_str(text);
-                                        }
+                                        total += estimate_tool_result_item_tokens(item);
                                     }
                                 } else {
                                     // Fallback for objects or other types
@@ -116,13 +306,33 @@ impl ContextManager {
                 }
             }
         }
-        
-        // Thinking budget overhead if enabled
-        if let Some(thinking) = &request.thinking {
-             if let Some(budget) = thinking.budget_tokens {
-                 // Reserve budget in estimation
-                 total += budget;
-             }
+
+        total
+    }
+
+    /// Estimate the input tokens of a Gemini / v1internal request body
+    /// (`{"request": {...}}` or a bare `generateContent` body)
+    ///
+    /// Used for the diagnostic headers of the OpenAI and Gemini protocols.
+    pub fn estimate_gemini_input_tokens(body: &serde_json::Value) -> u32 {
+        let request = body.get("request").unwrap_or(body);
+        let mut total = 0;
+
+        let contents = request.get("contents").and_then(|c| c.as_array());
+        let system_parts = request
+            .get("systemInstruction")
+            .and_then(|s| s.get("parts"))
+            .and_then(|p| p.as_array());
+        for content in contents.into_iter().flatten() {
+            // Message overhead
+            total += 4;
+            let parts = content.get("parts").and_then(|p| p.as_array());
+            total += parts.into_iter().flatten().map(estimate_gemini_part_tokens).sum::<u32>();
+        }
+        total += system_parts.into_iter().flatten().map(estimate_gemini_part_tokens).sum::<u32>();
+
+        if let Some(tools) = request.get("tools") {
+            total += estimate_tokens_from_str(&tools.to_string());
         }
 
         total
@@ -214,6 +424,7 @@ mod tests {
             thinking: None,
             metadata: None,
             output_config: None,
+            tool_choice: None,
         }
     }
 
@@ -232,6 +443,91 @@ mod tests {
         assert!(tokens < 50);
     }
 
+    #[test]
+    fn test_estimate_tokens_by_script() {
+        assert_eq!(estimate_tokens_from_str("Hello World"), 2);
+        // CJK characters are roughly one token each, not bytes / 3.5
+        assert_eq!(estimate_tokens_from_str("你好，世界"), 5);
+        // Punctuation-heavy JSON costs more than its word count
+        let json = r#"{"path": "src/main.rs", "line": 42}"#;
+        let tokens = estimate_tokens_from_str(json);
+        assert!((10..=20).contains(&tokens), "json estimate {}", tokens);
+    }
+
+    #[test]
+    fn test_input_tokens_exclude_thinking_budget() {
+        let mut req = create_test_request();
+        req.messages = vec![Message {
+            role: "user".into(),
+            content: MessageContent::String("Hello World".into()),
+        }];
+        let input = ContextManager::estimate_input_tokens(&req);
+        req.thinking = Some(serde_json::from_value(serde_json::json!({
+            "type": "enabled",
+            "budget_tokens": 1024
+        })).unwrap());
+        assert_eq!(ContextManager::estimate_input_tokens(&req), input);
+        assert_eq!(ContextManager::estimate_token_usage(&req), input + 1024);
+    }
+
+    #[test]
+    fn test_pdf_page_count_fallbacks() {
+        assert_eq!(
+            pdf_page_count(b"<< /Type /Pages /Kids [] >> << /Type /Page >> << /Type/Page >>"),
+            2
+        );
+        // Page objects hidden in object streams: use the page tree root's /Count
+        assert_eq!(
+            pdf_page_count(b"<< /Type /Pages /Count 12 >> << /Count 3 >>"),
+            12
+        );
+        // Nothing readable: estimate from the file size
+        assert_eq!(pdf_page_count(&[0u8; PDF_BYTES_PER_PAGE * 3]), 3);
+        assert_eq!(pdf_page_count(b"%PDF-1.7"), 1);
+    }
+
+    #[test]
+    fn test_tool_result_images_are_counted() {
+        let mut req = create_test_request();
+        req.messages = vec![Message {
+            role: "user".into(),
+            content: MessageContent::Array(vec![ContentBlock::ToolResult {
+                tool_use_id: "toolu_1".into(),
+                content: serde_json::json!([{"type": "text", "text": "screenshot"}]),
+                is_error: None,
+            }]),
+        }];
+        let text_only = ContextManager::estimate_input_tokens(&req);
+
+        req.messages[0].content = MessageContent::Array(vec![ContentBlock::ToolResult {
+            tool_use_id: "toolu_1".into(),
+            content: serde_json::json!([
+                {"type": "text", "text": "screenshot"},
+                {"type": "image", "source": {"type": "url", "url": "https://example.com/a.png"}}
+            ]),
+            is_error: None,
+        }]);
+        assert!(ContextManager::estimate_input_tokens(&req) > text_only);
+    }
+
+    #[test]
+    fn test_estimate_gemini_input_tokens() {
+        let body = serde_json::json!({
+            "project": "p",
+            "request": {
+                "systemInstruction": {"parts": [{"text": "Be brief"}]},
+                "contents": [
+                    {"role": "user", "parts": [{"text": "Hello World"}, {"inlineData": {"mimeType": "image/png", "data": "AAAA"}}]}
+                ]
+            }
+        });
+        assert_eq!(ContextManager::estimate_gemini_input_tokens(&body), 4 + 2 + 258 + 2);
+        assert_eq!(
+            ContextManager::estimate_gemini_input_tokens(&body["request"]),
+            ContextManager::estimate_gemini_input_tokens(&body)
+        );
+    }
+
     #[test]
     fn test_purify_history_soft() {
         // Construct history of 6 messages (indices 0-5)
@@ -0,0 +1,519 @@
+// 图片输入映射: Claude image 块 / OpenAI image_url → Gemini inlineData / fileData
+//
+// base64 图片按实际字节嗅探媒体类型 (客户端声明的 media_type 经常与内容不符，上游会直接拒绝)，
+// 并在转发前校验大小。上游不接受任意 http(s) 链接，Claude url 图片由反代下载后内联
+// (只访问公网地址、不跟随重定向)；
+// OpenAI 的 URL 图片以 fileData 转发，媒体类型按扩展名推断。
+use base64::Engine as _;
+use serde_json::{json, Value};
+
+use super::claude::models::{ContentBlock, ImageSource, Message, MessageContent};
+use super::openai::{OpenAIContent, OpenAIContentBlock, OpenAIMessage};
+
+/// 上游单个内联图片的大小上限 (解码后)
+pub const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+
+/// 上游按 768x768 分块计费，每块 258 tokens；两边都不超过 384 像素的小图按一块计
+const TOKENS_PER_TILE: u32 = 258;
+const TILE_SIZE: u32 = 768;
+const SMALL_IMAGE_SIZE: u32 = 384;
+/// 无法读取尺寸 (URL 图片、罕见格式) 时的估算值，约等于一张 1536x1536 的图片
+const DEFAULT_IMAGE_TOKENS: u32 = TOKENS_PER_TILE * 4;
+/// 嗅探类型与读取尺寸只需文件头，最多解码这么多字节 (JPEG 的尺寸可能位于 EXIF 之后)
+const HEADER_PREFIX_BYTES: usize = 64 * 1024;
+
+const SUPPORTED_MIME_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/webp",
+    "image/gif",
+    "image/heic",
+    "image/heif",
+];
+
+/// 由 base64 长度计算解码后的字节数，无需真正解码
+pub fn decoded_len(data: &str) -> usize {
+    let len = data.trim_end().len();
+    let padding = data.trim_end().bytes().rev().take_while(|b| *b == b'=').count();
+    (len / 4 * 3 + (len % 4) * 3 / 4).saturating_sub(padding)
+}
+
+/// 解码 base64 开头的一段，用于嗅探类型与读取尺寸
+fn decode_prefix(data: &str) -> Option<Vec<u8>> {
+    let chars = (HEADER_PREFIX_BYTES / 3 * 4).min(data.len() / 4 * 4);
+    let prefix = data.get(..chars)?;
+    base64::engine::general_purpose::STANDARD
+        .decode(prefix)
+        .ok()
+        .or_else(|| {
+            base64::engine::general_purpose::URL_SAFE
+                .decode(prefix)
+                .ok()
+        })
+}
+
+/// 按文件头识别图片类型
+pub fn sniff_mime(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.len() >= 12 && &bytes[4..8] == b"ftyp" {
+        return match &bytes[8..12] {
+            b"heic" | b"heix" | b"hevc" => Some("image/heic"),
+            b"mif1" | b"msf1" | b"heif" => Some("image/heif"),
+            _ => None,
+        };
+    }
+    match image::guess_format(bytes).ok()? {
+        image::ImageFormat::Png => Some("image/png"),
+        image::ImageFormat::Jpeg => Some("image/jpeg"),
+        image::ImageFormat::WebP => Some("image/webp"),
+        image::ImageFormat::Gif => Some("image/gif"),
+        _ => None,
+    }
+}
+
+/// 按 URL 扩展名推断图片类型
+pub fn mime_from_url(url: &str) -> Option<&'static str> {
+    let path = url.split(['?', '#']).next().unwrap_or(url).to_lowercase();
+    let ext = path.rsplit('.').next()?;
+    match ext {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "webp" => Some("image/webp"),
+        "gif" => Some("image/gif"),
+        "heic" => Some("image/heic"),
+        "heif" => Some("image/heif"),
+        _ => None,
+    }
+}
+
+/// 校验 base64 图片并转换为 Gemini inlineData part
+pub fn inline_image_part(declared_mime: Option<&str>, data: &str) -> Result<Value, String> {
+    let size = decoded_len(data);
+    if size == 0 {
+        return Err("Image data is empty".to_string());
+    }
+    if size > MAX_IMAGE_BYTES {
+        return Err(format!(
+            "Image is too large: {} bytes (max {} bytes)",
+            size, MAX_IMAGE_BYTES
+        ));
+    }
+
+    let sniffed = decode_prefix(data).as_deref().and_then(sniff_mime);
+    let declared = declared_mime
+        .map(|m| m.trim().to_lowercase())
+        .filter(|m| SUPPORTED_MIME_TYPES.contains(&m.as_str()));
+    let mime_type = match (sniffed, declared) {
+        (Some(sniffed), declared) => {
+            if declared.as_deref().is_some_and(|d| d != sniffed) {
+                tracing::debug!(
+                    "[Image-Input] Declared media type {:?} does not match content, using {}",
+                    declared_mime,
+                    sniffed
+                );
+            }
+            sniffed.to_string()
+        }
+        (None, Some(declared)) => declared,
+        (None, None) => {
+            return Err(format!(
+                "Unsupported image media type: {}",
+                declared_mime.unwrap_or("unknown")
+            ))
+        }
+    };
+
+    Ok(json!({
+        "inlineData": { "mimeType": mime_type, "data": data }
+    }))
+}
+
+/// URL 图片转换为 Gemini fileData part
+pub fn url_image_part(url: &str) -> Value {
+    json!({
+        "fileData": { "fileUri": url, "mimeType": mime_from_url(url).unwrap_or("image/jpeg") }
+    })
+}
+
+/// `data:<mime>;base64,<data>` 形式的图片
+pub fn data_url_image_part(url: &str) -> Result<Value, String> {
+    let rest = url
+        .strip_prefix("data:")
+        .ok_or_else(|| "Invalid data URL".to_string())?;
+    let (meta, data) = rest
+        .split_once(',')
+        .ok_or_else(|| "Invalid data URL: missing ','".to_string())?;
+    if !meta.ends_with(";base64") {
+        return Err("Only base64 data URLs are supported for images".to_string());
+    }
+    let mime = meta.split(';').next().filter(|m| !m.is_empty());
+    inline_image_part(mime, data)
+}
+
+/// Claude image 块 (base64 / url 两种 source)
+pub fn claude_image_part(source: &ImageSource) -> Result<Value, String> {
+    match source.source_type.as_str() {
+        "base64" => inline_image_part(Some(&source.media_type), &source.data),
+        "url" => match source.url.as_deref() {
+            Some(url) if url.starts_with("http://") || url.starts_with("https://") => {
+                Ok(url_image_part(url))
+            }
+            Some(url) if url.starts_with("data:") => data_url_image_part(url),
+            _ => Err("Image source of type 'url' requires an http(s) url".to_string()),
+        },
+        other => Err(format!("Unsupported image source type: {}", other)),
+    }
+}
+
+/// 在转发前校验 Claude 请求中的图片，错误信息直接返回给客户端
+pub fn validate_claude_images(messages: &[Message]) -> Result<(), String> {
+    for message in messages {
+        if let MessageContent::Array(blocks) = &message.content {
+            for block in blocks {
+                if let ContentBlock::Image { source, .. } = block {
+                    claude_image_part(source)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 下载 Claude 请求中的 http(s) url 图片并改写为 base64 source；下载失败或超过大小上限时返回错误，
+/// 由 handler 作为 invalid_request_error 返回给客户端
+pub async fn inline_claude_url_images(messages: &mut [Message]) -> Result<(), String> {
+    for message in messages.iter_mut() {
+        let MessageContent::Array(blocks) = &mut message.content else {
+            continue;
+        };
+        for block in blocks.iter_mut() {
+            let ContentBlock::Image { source, .. } = block else {
+                continue;
+            };
+            let url = match source.url.as_deref() {
+                Some(url)
+                    if source.source_type == "url"
+                        && (url.starts_with("http://") || url.starts_with("https://")) =>
+                {
+                    url.to_string()
+                }
+                _ => continue,
+            };
+            let (media_type, bytes) = fetch_image(&url).await?;
+            source.source_type = "base64".to_string();
+            source.media_type = media_type;
+            source.data = base64::engine::general_purpose::STANDARD.encode(bytes);
+            source.url = None;
+        }
+    }
+    Ok(())
+}
+
+/// 下载图片：只访问解析到公网地址的主机，不跟随重定向 (防止客户端借反代探测内网)
+async fn fetch_image(url: &str) -> Result<(String, Vec<u8>), String> {
+    let (host, addrs) = resolve_public_host(url).await?;
+    // 连接固定到已校验的地址，避免下载时再次解析得到内网地址 (DNS rebinding)
+    let client = crate::utils::http::global_client_builder(15)?
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve_to_addrs(&host, &addrs)
+        .build()
+        .map_err(|e| format!("Failed to fetch image {}: {}", url, e))?;
+    download_image(&client, url).await
+}
+
+/// 解析 URL 的主机，任一地址不是公网地址即拒绝；返回 (主机名, 地址列表)
+async fn resolve_public_host(url: &str) -> Result<(String, Vec<std::net::SocketAddr>), String> {
+    let rejected = || format!("Image url {} does not point to a public address", url);
+    let parsed = url::Url::parse(url).map_err(|e| format!("Invalid image url {}: {}", url, e))?;
+    let port = parsed.port_or_known_default().ok_or_else(rejected)?;
+    let (host, addrs): (String, Vec<std::net::SocketAddr>) = match parsed.host() {
+        Some(url::Host::Domain(domain)) => {
+            let addrs = tokio::net::lookup_host((domain, port))
+                .await
+                .map_err(|e| format!("Failed to resolve image host {}: {}", domain, e))?
+                .collect();
+            (domain.to_string(), addrs)
+        }
+        Some(url::Host::Ipv4(ip)) => (ip.to_string(), vec![(ip, port).into()]),
+        Some(url::Host::Ipv6(ip)) => (ip.to_string(), vec![(ip, port).into()]),
+        None => return Err(rejected()),
+    };
+    if addrs.is_empty() || !addrs.iter().all(|addr| is_public_ip(addr.ip())) {
+        return Err(rejected());
+    }
+    Ok((host, addrs))
+}
+
+/// 是否为公网地址：排除回环、私有、链路本地、未指定、CGNAT、组播、广播与文档地址
+fn is_public_ip(ip: std::net::IpAddr) -> bool {
+    match ip {
+        std::net::IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || v4.is_documentation()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        std::net::IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ip(v4.into());
+            }
+            let first = v6.segments()[0];
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// 边读边检查大小；返回 (媒体类型, 内容)，类型取自响应头或 URL 扩展名
+async fn download_image(client: &reqwest::Client, url: &str) -> Result<(String, Vec<u8>), String> {
+    let too_large = || format!("Image at {} is too large (max {} bytes)", url, MAX_IMAGE_BYTES);
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch image {}: {}", url, e))?;
+    if response.status().is_redirection() {
+        return Err(format!(
+            "Failed to fetch image {}: redirects are not followed",
+            url
+        ));
+    }
+    if !response.status().is_success() {
+        return Err(format!(
+            "Failed to fetch image {}: HTTP {}",
+            url,
+            response.status()
+        ));
+    }
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_IMAGE_BYTES as u64)
+    {
+        return Err(too_large());
+    }
+
+    let media_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .map(|v| v.trim().to_lowercase())
+        .filter(|v| v.starts_with("image/"))
+        .or_else(|| mime_from_url(url).map(str::to_string))
+        .unwrap_or_default();
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to fetch image {}: {}", url, e))?
+    {
+        if bytes.len() + chunk.len() > MAX_IMAGE_BYTES {
+            return Err(too_large());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok((media_type, bytes))
+}
+
+/// 在转发前校验 OpenAI 请求中的 data URL 图片
+pub fn validate_openai_images(messages: &[OpenAIMessage]) -> Result<(), String> {
+    for message in messages {
+        if let Some(OpenAIContent::Array(blocks)) = &message.content {
+            for block in blocks {
+                if let OpenAIContentBlock::ImageUrl { image_url } = block {
+                    if image_url.url.starts_with("data:") {
+                        data_url_image_part(&image_url.url)?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn tokens_for_dimensions(width: u32, height: u32) -> u32 {
+    if width <= SMALL_IMAGE_SIZE && height <= SMALL_IMAGE_SIZE {
+        return TOKENS_PER_TILE;
+    }
+    width.div_ceil(TILE_SIZE) * height.div_ceil(TILE_SIZE) * TOKENS_PER_TILE
+}
+
+/// 估算 base64 图片的 token 消耗 (按文件头中的尺寸分块计算)
+pub fn estimate_base64_image_tokens(data: &str) -> u32 {
+    let dimensions = decode_prefix(data).and_then(|bytes| {
+        image::ImageReader::new(std::io::Cursor::new(bytes))
+            .with_guessed_format()
+            .ok()?
+            .into_dimensions()
+            .ok()
+    });
+    match dimensions {
+        Some((width, height)) => tokens_for_dimensions(width, height),
+        None => DEFAULT_IMAGE_TOKENS,
+    }
+}
+
+/// 估算 Claude image 块的 token 消耗
+pub fn estimate_image_tokens(source: &ImageSource) -> u32 {
+    if source.source_type == "base64" {
+        estimate_base64_image_tokens(&source.data)
+    } else {
+        DEFAULT_IMAGE_TOKENS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1x1 PNG
+    const PNG_1X1: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==";
+
+    #[test]
+    fn sniffed_type_overrides_declared_media_type() {
+        let part = inline_image_part(Some("image/jpeg"), PNG_1X1).unwrap();
+        assert_eq!(part["inlineData"]["mimeType"], "image/png");
+        assert_eq!(part["inlineData"]["data"], PNG_1X1);
+
+        // 无法嗅探时使用声明的类型，声明的类型不受支持则拒绝
+        assert_eq!(
+            inline_image_part(Some("image/webp"), "AAAAAAAA").unwrap()["inlineData"]["mimeType"],
+            "image/webp"
+        );
+        assert!(inline_image_part(Some("image/tiff"), "AAAAAAAA").is_err());
+    }
+
+    #[test]
+    fn oversized_images_are_rejected() {
+        let huge = "A".repeat((MAX_IMAGE_BYTES / 3 + 1) * 4);
+        let err = inline_image_part(Some("image/png"), &huge).unwrap_err();
+        assert!(err.contains("too large"));
+        assert_eq!(decoded_len(PNG_1X1), 70);
+    }
+
+    #[test]
+    fn data_and_http_urls_are_mapped() {
+        let part = data_url_image_part(&format!("data:image/png;base64,{}", PNG_1X1)).unwrap();
+        assert_eq!(part["inlineData"]["mimeType"], "image/png");
+        assert!(data_url_image_part("data:image/png,plain").is_err());
+
+        let part = url_image_part("https://example.com/cat.webp?size=large");
+        assert_eq!(part["fileData"]["mimeType"], "image/webp");
+        assert_eq!(part["fileData"]["fileUri"], "https://example.com/cat.webp?size=large");
+
+        let source = ImageSource {
+            source_type: "url".to_string(),
+            media_type: String::new(),
+            data: String::new(),
+            url: Some("https://example.com/a.png".to_string()),
+        };
+        assert_eq!(claude_image_part(&source).unwrap()["fileData"]["mimeType"], "image/png");
+    }
+
+    /// 本地 HTTP 服务，对任意请求返回给定的响应头与内容
+    async fn serve_once(head: String, body: Vec<u8>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf).await;
+            let _ = stream.write_all(head.as_bytes()).await;
+            let _ = stream.write_all(&body).await;
+        });
+        format!("http://{}/cat.png", addr)
+    }
+
+    fn url_image_message(url: &str) -> Vec<Message> {
+        serde_json::from_value(json!([{
+            "role": "user",
+            "content": [{"type": "image", "source": {"type": "url", "url": url}}]
+        }]))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn url_images_are_downloaded() {
+        let png = base64::engine::general_purpose::STANDARD.decode(PNG_1X1).unwrap();
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            png.len()
+        );
+        let url = serve_once(head, png.clone()).await;
+
+        let (media_type, bytes) = download_image(&reqwest::Client::new(), &url).await.unwrap();
+        assert_eq!(media_type, "image/png");
+        assert_eq!(bytes, png);
+    }
+
+    #[tokio::test]
+    async fn oversized_failed_or_redirected_downloads_are_rejected() {
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            MAX_IMAGE_BYTES + 1
+        );
+        let url = serve_once(head, Vec::new()).await;
+        let err = download_image(&client, &url).await.unwrap_err();
+        assert!(err.contains("too large"));
+
+        let url = serve_once(
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+            Vec::new(),
+        )
+        .await;
+        let err = download_image(&client, &url).await.unwrap_err();
+        assert!(err.contains("404"));
+
+        let url = serve_once(
+            "HTTP/1.1 302 Found\r\nLocation: http://169.254.169.254/\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+            Vec::new(),
+        )
+        .await;
+        let err = download_image(&client, &url).await.unwrap_err();
+        assert!(err.contains("redirects"));
+    }
+
+    #[tokio::test]
+    async fn non_public_image_urls_are_rejected() {
+        for url in [
+            "http://127.0.0.1:8045/cat.png",
+            "http://localhost/cat.png",
+            "http://10.0.0.1/cat.png",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/cat.png",
+            "http://[::ffff:192.168.1.1]/cat.png",
+            "http://0.0.0.0/cat.png",
+        ] {
+            let mut messages = url_image_message(url);
+            let err = inline_claude_url_images(&mut messages).await.unwrap_err();
+            assert!(err.contains("public address"), "{}: {}", url, err);
+        }
+
+        assert!(is_public_ip("8.8.8.8".parse().unwrap()));
+        assert!(is_public_ip("2001:4860:4860::8888".parse().unwrap()));
+        assert!(!is_public_ip("100.64.0.1".parse().unwrap()));
+        assert!(!is_public_ip("fd00::1".parse().unwrap()));
+        assert!(!is_public_ip("fe80::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn token_estimate_uses_image_dimensions() {
+        assert_eq!(estimate_base64_image_tokens(PNG_1X1), TOKENS_PER_TILE);
+        assert_eq!(tokens_for_dimensions(1920, 1080), 3 * 2 * TOKENS_PER_TILE);
+        assert_eq!(estimate_base64_image_tokens("not-an-image"), DEFAULT_IMAGE_TOKENS);
+    }
+}
@@ -5,6 +5,7 @@ pub mod claude;
 pub mod common_utils;
 pub mod error_classifier;
 pub mod gemini;
+pub mod image_input;
 pub mod openai;
 pub mod signature_store;
 pub mod tool_result_compressor;
@@ -14,7 +14,7 @@ pub struct OpenAIRequest {
     pub stream: bool,
     #[serde(default)]
     pub n: Option<u32>, // [NEW] 支持多候选结果数量
-    #[serde(rename = "max_tokens")]
+    #[serde(rename = "max_tokens", alias = "max_completion_tokens")]
     pub max_tokens: Option<u32>,
     pub temperature: Option<f32>,
     #[serde(rename = "top_p")]
@@ -35,6 +35,9 @@ pub struct OpenAIRequest {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResponseFormat {
     pub r#type: String,
+    /// Structured output: `{ name, schema, strict }` when `type` is `json_schema`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_schema: Option<Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
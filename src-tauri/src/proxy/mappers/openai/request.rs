@@ -151,19 +151,13 @@ pub fn transform_openai_request(request: &OpenAIRequest, project_id: &str, mappe
                                 }
                                 OpenAIContentBlock::ImageUrl { image_url } => {
                                     if image_url.url.starts_with("data:") {
-                                        if let Some(pos) = image_url.url.find(",") {
-                                            let mime_part = &image_url.url[5..pos];
-                                            let mime_type = mime_part.split(';').next().unwrap_or("image/jpeg");
-                                            let data = &image_url.url[pos + 1..];
-                                            
-                                            parts.push(json!({
-                                                "inlineData": { "mimeType": mime_type, "data": data }
-                                            }));
+                                        // 大小 / 类型已在 handler 中校验，这里只做转换
+                                        match crate::proxy::mappers::image_input::data_url_image_part(&image_url.url) {
+                                            Ok(part) => parts.push(part),
+                                            Err(e) => tracing::warn!("[OpenAI-Request] Skipping invalid image: {}", e),
                                         }
                                     } else if image_url.url.starts_with("http") {
-                                        parts.push(json!({
-                                            "fileData": { "fileUri": &image_url.url, "mimeType": "image/jpeg" }
-                                        }));
+                                        parts.push(crate::proxy::mappers::image_input::url_image_part(&image_url.url));
                                     } else {
                                         // [NEW] 处理本地文件路径 (file:// 或 Windows/Unix 路径)
                                         let file_path = if image_url.url.starts_with("file://") {
@@ -303,12 +297,22 @@ pub fn transform_openai_request(request: &OpenAIRequest, project_id: &str, mappe
         }
         merged_contents.push(msg);
     }
-    let contents = merged_contents;
+    let capability = crate::proxy::common::model_capabilities::lookup(mapped_model);
+    let mut contents = Value::Array(merged_contents);
+    if !capability.supports_vision {
+        let stripped = crate::proxy::common::model_capabilities::strip_image_parts(&mut contents);
+        if stripped > 0 {
+            tracing::warn!(
+                "[OpenAI-Request] Target model '{}' does not accept images, replaced {} image part(s)",
+                mapped_model, stripped
+            );
+        }
+    }
 
     // 3. 构建请求体
 
     let mut gen_config = json!({
-        "maxOutputTokens": request.max_tokens.unwrap_or(16384),
+        "maxOutputTokens": request.max_tokens.unwrap_or(16384).min(capability.max_output_tokens),
         "temperature": request.temperature.unwrap_or(1.0),
         "topP": request.top_p.unwrap_or(1.0), 
     });
@@ -334,8 +338,20 @@ pub fn transform_openai_request(request: &OpenAIRequest, project_id: &str, mappe
     }
 
     if let Some(fmt) = &request.response_format {
-        if fmt.r#type == "json_object" {
-            gen_config["responseMimeType"] = json!("application/json");
+        match fmt.r#type.as_str() {
+            "json_object" => {
+                gen_config["responseMimeType"] = json!("application/json");
+            }
+            // Structured output: the schema is cleaned the same way as tool parameters
+            "json_schema" => {
+                gen_config["responseMimeType"] = json!("application/json");
+                if let Some(mut schema) = fmt.json_schema.as_ref().and_then(|s| s.get("schema")).cloned() {
+                    crate::proxy::common::json_schema::clean_json_schema(&mut schema);
+                    enforce_uppercase_types(&mut schema);
+                    gen_config["responseSchema"] = schema;
+                }
+            }
+            _ => {}
         }
     }
 
@@ -428,6 +444,9 @@ pub fn transform_openai_request(request: &OpenAIRequest, project_id: &str, mappe
         
         if !function_declarations.is_empty() {
             inner_request["tools"] = json!([{ "functionDeclarations": function_declarations }]);
+            if let Some(tool_config) = request.tool_choice.as_ref().and_then(map_tool_choice) {
+                inner_request["toolConfig"] = tool_config;
+            }
         }
     }
     
@@ -486,6 +505,25 @@ pub fn transform_openai_request(request: &OpenAIRequest, project_id: &str, mappe
     })
 }
 
+/// OpenAI `tool_choice` → Gemini `toolConfig`
+/// ("none" / "auto" / "required" / `{"type":"function","function":{"name":..}}`)
+fn map_tool_choice(choice: &Value) -> Option<Value> {
+    let config = match choice {
+        Value::String(mode) => match mode.as_str() {
+            "none" => json!({ "mode": "NONE" }),
+            "auto" => json!({ "mode": "AUTO" }),
+            "required" => json!({ "mode": "ANY" }),
+            _ => return None,
+        },
+        Value::Object(_) => {
+            let name = choice.get("function").and_then(|f| f.get("name")).and_then(|n| n.as_str())?;
+            json!({ "mode": "ANY", "allowedFunctionNames": [name] })
+        }
+        _ => return None,
+    };
+    Some(json!({ "functionCallingConfig": config }))
+}
+
 fn enforce_uppercase_types(value: &mut Value) {
     if let Value::Object(map) = value {
         if let Some(type_val) = map.get_mut("type") {
@@ -553,4 +591,44 @@ mod tests {
         assert_eq!(parts[0]["text"].as_str().unwrap(), "What is in this image?");
         assert_eq!(parts[1]["inlineData"]["mimeType"].as_str().unwrap(), "image/png");
     }
+
+    #[test]
+    fn test_tool_choice_and_json_schema_mapping() {
+        let req: OpenAIRequest = serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "messages": [{ "role": "user", "content": "weather?" }],
+            "max_completion_tokens": 256,
+            "tools": [{
+                "type": "function",
+                "function": {
+                    "name": "get_weather",
+                    "parameters": { "type": "object", "properties": { "city": { "type": "string" } } }
+                }
+            }],
+            "tool_choice": { "type": "function", "function": { "name": "get_weather" } },
+            "response_format": {
+                "type": "json_schema",
+                "json_schema": {
+                    "name": "answer",
+                    "schema": { "type": "object", "properties": { "text": { "type": "string" } } }
+                }
+            }
+        }))
+        .unwrap();
+
+        let result = transform_openai_request(&req, "test-v", "gemini-2.5-flash");
+        let inner = &result["request"];
+        assert_eq!(inner["toolConfig"]["functionCallingConfig"]["mode"], "ANY");
+        assert_eq!(
+            inner["toolConfig"]["functionCallingConfig"]["allowedFunctionNames"][0],
+            "get_weather"
+        );
+        assert_eq!(inner["generationConfig"]["maxOutputTokens"], 256);
+        assert_eq!(inner["generationConfig"]["responseMimeType"], "application/json");
+        assert_eq!(inner["generationConfig"]["responseSchema"]["type"], "OBJECT");
+        assert_eq!(
+            inner["generationConfig"]["responseSchema"]["properties"]["text"]["type"],
+            "STRING"
+        );
+    }
 }
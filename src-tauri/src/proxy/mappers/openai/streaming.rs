@@ -9,6 +9,8 @@ use uuid::Uuid;
 use tracing::debug;
 use rand::Rng;
 
+use crate::proxy::common::sse::{KEEPALIVE_COMMENT, KEEPALIVE_INTERVAL};
+
 // === 全局 ThoughtSignature 存储 ===
 // 用于在流式响应和后续请求之间传递签名，避免嵌入到用户可见的文本中
 static GLOBAL_THOUGHT_SIG: OnceLock<Mutex<Option<String>>> = OnceLock::new();
@@ -98,8 +100,11 @@ pub fn create_openai_sse_stream(
         let mut final_usage: Option<super::models::OpenAIUsage> = None;
         let mut error_occurred = false;  // [FIX] 标志位,避免双重 [DONE]
         
-        // [P2 FIX] 添加心跳定时器
-        let mut heartbeat_interval = tokio::time::interval(std::time::Duration::from_secs(15));
+        // [P2 FIX] 心跳定时器：上游空闲超过 KEEPALIVE_INTERVAL 才发送，收到数据即重置
+        let mut heartbeat_interval = tokio::time::interval_at(
+            tokio::time::Instant::now() + KEEPALIVE_INTERVAL,
+            KEEPALIVE_INTERVAL,
+        );
         heartbeat_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
         
         loop {
@@ -108,6 +113,7 @@ pub fn create_openai_sse_stream(
                 item = gemini_stream.next() => {
                     match item {
                         Some(Ok(bytes)) => {
+                    heartbeat_interval.reset();
                     // Verbose logging for debugging image fragmentation
                     debug!("[OpenAI-SSE] Received chunk: {} bytes", bytes.len());
                     buffer.extend_from_slice(&bytes);
@@ -380,14 +386,26 @@ pub fn create_openai_sse_stream(
                 // [P2 FIX] 发送心跳
                 _ = heartbeat_interval.tick() => {
                     // 发送 SSE 注释作为心跳
-                    yield Ok::<Bytes, String>(Bytes::from(": ping\n\n"));
+                    yield Ok::<Bytes, String>(Bytes::from(KEEPALIVE_COMMENT));
                 }
             }
         }
         
         // [FIX] 只有在没有错误时才发送 [DONE]
-        // usage 已经嵌入到 finish_reason chunk,不需要单独发送
+        // usage 通常已嵌入 finish_reason chunk；上游在结束后才给出 usage (或没有 finish_reason) 时
+        // 补发一个 choices 为空的 usage chunk (与 stream_options.include_usage 的格式一致)
         if !error_occurred {
+            if let Some(usage) = final_usage.take() {
+                let usage_chunk = json!({
+                    "id": &stream_id,
+                    "object": "chat.completion.chunk",
+                    "created": created_ts,
+                    "model": &model,
+                    "choices": [],
+                    "usage": usage
+                });
+                yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&usage_chunk).unwrap_or_default())));
+            }
             yield Ok::<Bytes, String>(Bytes::from("data: [DONE]\n\n"));
         }
     };
@@ -417,8 +435,11 @@ pub fn create_legacy_sse_stream(
         let mut final_usage: Option<super::models::OpenAIUsage> = None;
         let mut error_occurred = false;  // [FIX] 标志位,避免双重 [DONE]
         
-        // [P2 FIX] 添加心跳定时器
-        let mut heartbeat_interval = tokio::time::interval(std::time::Duration::from_secs(15));
+        // [P2 FIX] 心跳定时器：上游空闲超过 KEEPALIVE_INTERVAL 才发送，收到数据即重置
+        let mut heartbeat_interval = tokio::time::interval_at(
+            tokio::time::Instant::now() + KEEPALIVE_INTERVAL,
+            KEEPALIVE_INTERVAL,
+        );
         heartbeat_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
         
         loop {
@@ -427,6 +448,7 @@ pub fn create_legacy_sse_stream(
                 item = gemini_stream.next() => {
                     match item {
                         Some(Ok(bytes)) => {
+                    heartbeat_interval.reset();
                     buffer.extend_from_slice(&bytes);
                     while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
                         let line_raw = buffer.split_to(pos + 1);
@@ -557,14 +579,25 @@ pub fn create_legacy_sse_stream(
                 // [P2 FIX] 发送心跳
                 _ = heartbeat_interval.tick() => {
                     // 发送 SSE 注释作为心跳
-                    yield Ok::<Bytes, String>(Bytes::from(": ping\n\n"));
+                    yield Ok::<Bytes, String>(Bytes::from(KEEPALIVE_COMMENT));
                 }
             }
         }
         
         // [FIX] 只有在没有错误时才发送 [DONE]
-        // usage 已经嵌入到 finish_reason chunk,不需要单独发送
+        // usage 未随 finish_reason chunk 发出时补发 usage chunk
         if !error_occurred {
+            if let Some(usage) = final_usage.take() {
+                let usage_chunk = json!({
+                    "id": &stream_id,
+                    "object": "text_completion",
+                    "created": created_ts,
+                    "model": &model,
+                    "choices": [],
+                    "usage": usage
+                });
+                yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&usage_chunk).unwrap_or_default())));
+            }
             tracing::debug!("Stream finished. Yielding [DONE]");
             yield Ok::<Bytes, String>(Bytes::from("data: [DONE]\n\n"));
             // Final flush delay
@@ -609,20 +642,24 @@ pub fn create_codex_sse_stream(
         let mut accumulated_usage: Option<super::models::OpenAIUsage> = None;
 
         // [P2 FIX] Add heartbeat interval for Codex stream
-        let mut heartbeat_interval = tokio::time::interval(std::time::Duration::from_secs(15));
+        let mut heartbeat_interval = tokio::time::interval_at(
+            tokio::time::Instant::now() + KEEPALIVE_INTERVAL,
+            KEEPALIVE_INTERVAL,
+        );
         heartbeat_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
         loop {
             tokio::select! {
                 // Heartbeat
                 _ = heartbeat_interval.tick() => {
-                    yield Ok::<Bytes, String>(Bytes::from(": ping\n\n"));
+                    yield Ok::<Bytes, String>(Bytes::from(KEEPALIVE_COMMENT));
                 }
 
                 // Upstream data
                 item = gemini_stream.next() => {
                     match item {
                         Some(Ok(bytes)) => {
+                            heartbeat_interval.reset();
                             buffer.extend_from_slice(&bytes);
                             while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
                                 let line_raw = buffer.split_to(pos + 1);
@@ -827,7 +864,9 @@ pub fn create_codex_sse_stream(
                 "finish_reason": last_finish_reason,
                 "usage": accumulated_usage.map(|u| json!({
                     "input_tokens": u.prompt_tokens,
-                    "input_tokens_details": { "cached_tokens": 0 },
+                    "input_tokens_details": {
+                        "cached_tokens": u.prompt_tokens_details.as_ref().and_then(|d| d.cached_tokens).unwrap_or(0)
+                    },
                     "output_tokens": u.completion_tokens,
                     "output_tokens_details": { "reasoning_tokens": 0 },
                     "total_tokens": u.total_tokens
@@ -9,12 +9,13 @@ use axum::{
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::proxy::security::KeyCheck;
 use crate::proxy::{ProxyAuthMode, ProxySecurityConfig};
 
 /// API Key 认证中间件
 pub async fn auth_middleware(
     State(security): State<Arc<RwLock<ProxySecurityConfig>>>,
-    request: Request,
+    mut request: Request,
     next: Next,
 ) -> Result<Response, StatusCode> {
     let method = request.method().clone();
@@ -35,14 +36,6 @@ pub async fn auth_middleware(
     let security = security.read().await.clone();
     let effective_mode = security.effective_auth_mode();
 
-    if matches!(effective_mode, ProxyAuthMode::Off) {
-        return Ok(next.run(request).await);
-    }
-
-    if matches!(effective_mode, ProxyAuthMode::AllExceptHealth) && path == "/healthz" {
-        return Ok(next.run(request).await);
-    }
-    
     // 从 header 中提取 API key
     let api_key = request
         .headers()
@@ -60,20 +53,46 @@ pub async fn auth_middleware(
                 .headers()
                 .get("x-goog-api-key")
                 .and_then(|h| h.to_str().ok())
-        });
+        })
+        .map(|s| s.to_string());
 
-    if security.api_key.is_empty() {
-        tracing::error!("Proxy auth is enabled but api_key is empty; denying request");
-        return Err(StatusCode::UNAUTHORIZED);
+    let check = security.check_key(api_key.as_deref(), &path);
+
+    let auth_required = match effective_mode {
+        ProxyAuthMode::Off => false,
+        ProxyAuthMode::AllExceptHealth => path != "/healthz",
+        _ => true,
+    };
+
+    // 双向 TLS 下已校验过客户端证书的连接视同已认证
+    let has_client_cert = request
+        .extensions()
+        .get::<crate::proxy::tls::ClientIdentity>()
+        .is_some();
+
+    if !auth_required || has_client_cert {
+        // 未强制认证时，携带的有效 Key 仍用于用量归属
+        if let KeyCheck::Allowed(key) = check {
+            request.extensions_mut().insert(key);
+        }
+        return Ok(next.run(request).await);
     }
 
-    // Constant-time compare is unnecessary here, but keep strict equality and avoid leaking values.
-    let authorized = api_key.map(|k| k == security.api_key).unwrap_or(false);
+    if security.api_key.is_empty() && security.api_keys.is_empty() {
+        tracing::error!("Proxy auth is enabled but no api key is configured; denying request");
+        return Err(StatusCode::UNAUTHORIZED);
+    }
 
-    if authorized {
-        Ok(next.run(request).await)
-    } else {
-        Err(StatusCode::UNAUTHORIZED)
+    match check {
+        KeyCheck::Allowed(key) => {
+            request.extensions_mut().insert(key);
+            Ok(next.run(request).await)
+        }
+        KeyCheck::OutOfScope => {
+            tracing::warn!("API key is not allowed to access {}", path);
+            Err(StatusCode::FORBIDDEN)
+        }
+        KeyCheck::Invalid => Err(StatusCode::UNAUTHORIZED),
     }
 }
 
@@ -0,0 +1,64 @@
+// 客户端 API Key 限流中间件
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+
+use crate::proxy::client_rate_limit::PendingTokenCharge;
+use crate::proxy::security::AuthenticatedKey;
+use crate::proxy::server::AppState;
+
+/// 按认证中间件写入的客户端 Key 做令牌桶限流，超限返回 429 + Retry-After；
+/// 放行的请求在响应上挂一个待结算的 tokens 额度，由请求日志按实际用量扣除
+pub async fn client_rate_limit_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(key) = request.extensions().get::<AuthenticatedKey>().cloned() else {
+        return next.run(request).await;
+    };
+    let Some(key_id) = key.key_id.as_deref() else {
+        return next.run(request).await;
+    };
+
+    if let Err(retry_after) =
+        state
+            .client_limiter
+            .try_acquire(key_id, key.requests_per_minute, key.tokens_per_minute)
+    {
+        tracing::warn!(
+            "API key {} exceeded its rate limit, retry after {}s",
+            key_id,
+            retry_after
+        );
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after.to_string())],
+            Json(json!({
+                "error": {
+                    "type": "rate_limit_error",
+                    "message": format!(
+                        "Rate limit exceeded for this API key. Retry after {} seconds.",
+                        retry_after
+                    ),
+                }
+            })),
+        )
+            .into_response();
+    }
+
+    let mut response = next.run(request).await;
+    if key.tokens_per_minute > 0 {
+        response.extensions_mut().insert(PendingTokenCharge::new(
+            state.client_limiter.clone(),
+            key_id.to_string(),
+            key.tokens_per_minute,
+        ));
+    }
+    response
+}
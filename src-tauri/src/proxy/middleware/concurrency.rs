@@ -0,0 +1,72 @@
+// 并发上限中间件
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::StreamExt;
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::proxy::concurrency::{self, QueueError, RequestSlot};
+
+/// 会占用上游账号的模型请求
+fn is_model_route(path: &str) -> bool {
+    matches!(
+        path,
+        "/v1/messages" | "/v1/chat/completions" | "/v1/completions" | "/v1/responses"
+    ) || path.starts_with("/v1/images/")
+        || path.starts_with("/v1/audio/")
+        || (path.starts_with("/v1beta/models/") && !path.ends_with("/countTokens"))
+}
+
+/// 占用全局名额 (排队等待) 后再进入 handler；名额随响应体一起释放
+pub async fn concurrency_middleware(request: Request, next: Next) -> Response {
+    if request.method() != Method::POST || !is_model_route(request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let permit = match concurrency::acquire_global().await {
+        Ok(permit) => permit,
+        Err(e) => {
+            let config = concurrency::config();
+            let message = match e {
+                QueueError::QueueFull => format!(
+                    "Proxy is at its concurrency limit and the wait queue is full ({} requests). Retry later.",
+                    config.max_queue
+                ),
+                QueueError::Timeout => format!(
+                    "Request waited {}s in the proxy queue without getting a slot. Retry later.",
+                    config.queue_timeout_secs
+                ),
+            };
+            tracing::warn!("[Concurrency] Rejecting {}: {:?}", request.uri().path(), e);
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                [(header::RETRY_AFTER, config.queue_timeout_secs.max(1).to_string())],
+                Json(json!({
+                    "error": {
+                        "type": "overloaded_error",
+                        "message": message,
+                    }
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    let slot = Arc::new(RequestSlot::default());
+    let response = concurrency::scope(slot.clone(), next.run(request)).await;
+
+    // 名额跟随响应体：流式响应在流结束或客户端断开 (body 被 drop) 时才释放
+    let (parts, body) = response.into_parts();
+    let held = (permit, slot);
+    let body = Body::from_stream(body.into_data_stream().map(move |chunk| {
+        let _ = &held;
+        chunk
+    }));
+    Response::from_parts(parts, body)
+}
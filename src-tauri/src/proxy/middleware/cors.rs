@@ -1,11 +1,55 @@
 // CORS 中间件
-use tower_http::cors::{CorsLayer, Any};
-use axum::http::Method;
+use tower_http::cors::{AllowHeaders, AllowOrigin, CorsLayer, Any};
+use axum::http::{HeaderName, HeaderValue, Method};
+
+use crate::proxy::common::model_mapping::wildcard_match;
+use crate::proxy::config::CorsConfig;
+
+/// 来源是否命中配置的规则 (不区分大小写，支持 * 通配符)
+fn origin_allowed(patterns: &[String], origin: &str) -> bool {
+    let origin = origin.to_lowercase();
+    patterns
+        .iter()
+        .any(|pattern| wildcard_match(&pattern.to_lowercase(), &origin))
+}
+
+/// 按配置创建 CORS layer
+pub fn cors_layer(config: &CorsConfig) -> CorsLayer {
+    let origins: Vec<String> = config
+        .allowed_origins
+        .iter()
+        .map(|o| o.trim().trim_end_matches('/').to_string())
+        .filter(|o| !o.is_empty())
+        .collect();
+    let allow_origin = if origins.is_empty() || origins.iter().any(|o| o == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::predicate(move |origin: &HeaderValue, _| {
+            origin
+                .to_str()
+                .is_ok_and(|origin| origin_allowed(&origins, origin))
+        })
+    };
+
+    let headers: Vec<HeaderName> = config
+        .allowed_headers
+        .iter()
+        .filter_map(|h| match HeaderName::from_bytes(h.trim().as_bytes()) {
+            Ok(name) => Some(name),
+            Err(_) => {
+                tracing::warn!("[CORS] Ignoring invalid header name: {}", h);
+                None
+            }
+        })
+        .collect();
+    let allow_headers = if headers.is_empty() {
+        AllowHeaders::from(Any)
+    } else {
+        AllowHeaders::list(headers)
+    };
 
-/// 创建 CORS layer
-pub fn cors_layer() -> CorsLayer {
     CorsLayer::new()
-        .allow_origin(Any)
+        .allow_origin(allow_origin)
         .allow_methods([
             Method::GET,
             Method::POST,
@@ -15,9 +59,9 @@ pub fn cors_layer() -> CorsLayer {
             Method::OPTIONS,
             Method::PATCH,
         ])
-        .allow_headers(Any)
+        .allow_headers(allow_headers)
         .allow_credentials(false)
-        .max_age(std::time::Duration::from_secs(3600))
+        .max_age(std::time::Duration::from_secs(config.max_age_secs))
 }
 
 #[cfg(test)]
@@ -26,8 +70,23 @@ mod tests {
 
     #[test]
     fn test_cors_layer_creation() {
-        let _layer = cors_layer();
-        // Layer 创建成功
-        assert!(true);
+        let _layer = cors_layer(&CorsConfig::default());
+        let _layer = cors_layer(&CorsConfig {
+            allowed_origins: vec!["http://localhost:*".to_string()],
+            allowed_headers: vec!["authorization".to_string(), "bad header".to_string()],
+            max_age_secs: 600,
+        });
+    }
+
+    #[test]
+    fn test_origin_patterns() {
+        let patterns = vec![
+            "http://localhost:*".to_string(),
+            "https://chat.example.com".to_string(),
+        ];
+        assert!(origin_allowed(&patterns, "http://localhost:5173"));
+        assert!(origin_allowed(&patterns, "HTTPS://Chat.Example.com"));
+        assert!(!origin_allowed(&patterns, "https://evil.example.com"));
+        assert!(!origin_allowed(&patterns, "http://127.0.0.1:5173"));
     }
 }
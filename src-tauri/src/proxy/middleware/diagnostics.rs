@@ -0,0 +1,17 @@
+// 调试诊断响应头中间件
+use axum::{extract::Request, middleware::Next, response::Response};
+use std::sync::Arc;
+
+use crate::proxy::diagnostics::{self, RequestDiagnostics};
+
+/// 开启调试头时为请求建立诊断作用域，handler 返回后把记录写入响应头
+pub async fn diagnostics_middleware(request: Request, next: Next) -> Response {
+    if !diagnostics::enabled() {
+        return next.run(request).await;
+    }
+
+    let record = Arc::new(RequestDiagnostics::default());
+    let mut response = diagnostics::scope(record.clone(), next.run(request)).await;
+    record.apply_headers(response.headers_mut());
+    response
+}
@@ -1,9 +1,17 @@
 // Middleware 模块 - Axum 中间件
 
 pub mod auth;
+pub mod client_rate_limit;
+pub mod concurrency;
 pub mod cors;
+pub mod diagnostics;
 pub mod logging;
 pub mod monitor;
+pub mod response_cache;
 
 pub use auth::auth_middleware;
+pub use client_rate_limit::client_rate_limit_middleware;
+pub use concurrency::concurrency_middleware;
 pub use cors::cors_layer;
+pub use diagnostics::diagnostics_middleware;
+pub use response_cache::response_cache_middleware;
@@ -1,8 +1,11 @@
+use crate::proxy::client_rate_limit::PendingTokenCharge;
 use crate::proxy::monitor::ProxyRequestLog;
+use crate::proxy::security::AuthenticatedKey;
 use crate::proxy::server::AppState;
 use axum::{
     body::Body,
     extract::{Request, State},
+    http::HeaderValue,
     middleware::Next,
     response::Response,
 };
@@ -13,6 +16,134 @@ use std::time::Instant;
 const MAX_REQUEST_LOG_SIZE: usize = 100 * 1024 * 1024; // 100MB
 const MAX_RESPONSE_LOG_SIZE: usize = 100 * 1024 * 1024; // 100MB for image responses
 
+/// 按实际用量结算客户端 Key 的 tokens/分钟 额度
+fn settle_client_tokens(charge: Option<&PendingTokenCharge>, log: &ProxyRequestLog) {
+    if let Some(charge) = charge {
+        let used = log.input_tokens.unwrap_or(0) as u64 + log.output_tokens.unwrap_or(0) as u64;
+        charge.settle(used);
+    }
+}
+
+/// 从 usage 中读取命中上游 prompt cache 的输入 tokens (Anthropic / OpenAI / Gemini 三种格式)
+fn cache_read_tokens(usage: &Value) -> Option<u32> {
+    usage
+        .get("cache_read_input_tokens")
+        .or(usage.pointer("/prompt_tokens_details/cached_tokens"))
+        .or(usage.pointer("/input_tokens_details/cached_tokens"))
+        .or(usage.get("cachedContentTokenCount"))
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32)
+}
+
+/// 从 usage 中读取的 token 用量；input 统一为包含缓存命中部分的总输入 (OpenAI / Gemini 口径)
+#[derive(Debug, Default, PartialEq)]
+struct UsageTokens {
+    input: Option<u32>,
+    output: Option<u32>,
+    cache_read: Option<u32>,
+}
+
+/// 按协议解析 usage，计价前统一口径
+fn read_usage(usage: &Value, protocol: Option<&str>) -> UsageTokens {
+    let get = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| usage.get(*key))
+            .and_then(|v| v.as_u64())
+            .map(|v| v as u32)
+    };
+    let mut input = get(&["prompt_tokens", "input_tokens", "promptTokenCount"]);
+    let mut output = get(&["completion_tokens", "output_tokens", "candidatesTokenCount"]);
+    let cache_read = cache_read_tokens(usage);
+
+    // Anthropic 的 input_tokens 不含缓存读取 / 写入的部分，补齐后与其他协议一致
+    if protocol == Some("anthropic") {
+        if let Some(input) = input.as_mut() {
+            *input += cache_read.unwrap_or(0) + get(&["cache_creation_input_tokens"]).unwrap_or(0);
+        }
+    }
+    if input.is_none() && output.is_none() {
+        output = get(&["total_tokens", "totalTokenCount"]);
+    }
+    UsageTokens {
+        input,
+        output,
+        cache_read,
+    }
+}
+
+/// 把 usage 写入日志 (缓存命中数只在本次 usage 带有时覆盖)
+fn apply_usage(log: &mut ProxyRequestLog, usage: &Value) {
+    let tokens = read_usage(usage, log.protocol.as_deref());
+    log.input_tokens = tokens.input;
+    log.output_tokens = tokens.output;
+    if tokens.cache_read.is_some() {
+        log.cache_read_tokens = tokens.cache_read;
+    }
+}
+
+/// 按价格表估算本次请求的花费并写入响应头 (仅非流式响应，流式响应的用量在响应头发出后才可知)
+fn insert_cost_header(headers: &mut axum::http::HeaderMap, log: &ProxyRequestLog) {
+    if log.account_email.is_none() || (log.input_tokens.is_none() && log.output_tokens.is_none()) {
+        return;
+    }
+    let cost = crate::proxy::common::pricing::estimate_cost(
+        log.model.as_deref(),
+        log.mapped_model.as_deref(),
+        log.input_tokens.unwrap_or(0),
+        log.output_tokens.unwrap_or(0),
+        log.cache_read_tokens.unwrap_or(0),
+    );
+    if let Some(value) = cost.and_then(|c| HeaderValue::from_str(&format!("{:.6}", c)).ok()) {
+        headers.insert(crate::proxy::common::pricing::ESTIMATED_COST_HEADER, value);
+    }
+}
+
+/// 请求的 usage intent；日志记录时 (log_request) 完成，请求在记录前被取消 (客户端断开、任务被丢弃) 时
+/// 在 Drop 中按 unknown 结果记账，不会留到下次启动才恢复
+struct UsageIntentGuard {
+    id: Option<String>,
+}
+
+impl UsageIntentGuard {
+    /// 没有模型名的请求 (非模型接口) 不记账
+    fn begin(
+        id: &str,
+        model: Option<&str>,
+        estimated_input_tokens: u32,
+        api_key_id: Option<&str>,
+    ) -> Self {
+        let id = model.map(|model| {
+            crate::modules::token_stats::begin_usage_intent(
+                id,
+                model,
+                estimated_input_tokens,
+                api_key_id,
+            );
+            id.to_string()
+        });
+        Self { id }
+    }
+
+    fn set_account(&self, account_email: &str) {
+        if let Some(id) = &self.id {
+            crate::modules::token_stats::set_usage_intent_account(id, account_email);
+        }
+    }
+
+    /// 交给 log_request 完成 intent
+    fn disarm(&mut self) {
+        self.id = None;
+    }
+}
+
+impl Drop for UsageIntentGuard {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            crate::modules::token_stats::abandon_usage_intent(&id);
+        }
+    }
+}
+
 pub async fn monitor_middleware(
     State(state): State<AppState>,
     request: Request,
@@ -28,6 +159,10 @@ pub async fn monitor_middleware(
     }
 
     let start = Instant::now();
+    let api_key_id = request
+        .extensions()
+        .get::<AuthenticatedKey>()
+        .and_then(|k| k.key_id.clone());
 
     let mut model = if uri.contains("/v1beta/models/") {
         uri.split("/v1beta/models/")
@@ -67,6 +202,18 @@ pub async fn monitor_middleware(
         request
     };
 
+    // Write-ahead usage intent: if the app dies mid-request the usage is still booked on next start
+    let log_id = uuid::Uuid::new_v4().to_string();
+    let mut intent = UsageIntentGuard::begin(
+        &log_id,
+        model.as_deref(),
+        request_body_str
+            .as_ref()
+            .map(|b| (b.len() / 4) as u32)
+            .unwrap_or(0),
+        api_key_id.as_deref(),
+    );
+
     let response = next.run(request).await;
 
     let duration = start.elapsed().as_millis() as u64;
@@ -86,6 +233,9 @@ pub async fn monitor_middleware(
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
+    // 缓存命中的响应不经过限流中间件，没有待结算的 tokens 额度
+    let token_charge = response.extensions().get::<PendingTokenCharge>().cloned();
+
     // Extract mapped model from X-Mapped-Model header if present
     let mapped_model = response
         .headers()
@@ -104,9 +254,13 @@ pub async fn monitor_middleware(
         None
     };
 
+    if let Some(email) = account_email.as_deref() {
+        intent.set_account(email);
+    }
+
     let monitor = state.monitor.clone();
     let mut log = ProxyRequestLog {
-        id: uuid::Uuid::new_v4().to_string(),
+        id: log_id,
         timestamp: chrono::Utc::now().timestamp_millis(),
         method,
         url: uri,
@@ -120,6 +274,7 @@ pub async fn monitor_middleware(
         response_body: None,
         input_tokens: None,
         output_tokens: None,
+        cache_read_tokens: None,
         protocol,
     };
 
@@ -131,6 +286,7 @@ pub async fn monitor_middleware(
         tokio::spawn(async move {
             let mut all_stream_data = Vec::new();
             let mut last_few_bytes = Vec::new();
+            let mut client_disconnected = false;
 
             while let Some(chunk_res) = stream.next().await {
                 if let Ok(chunk) = chunk_res {
@@ -144,11 +300,16 @@ pub async fn monitor_middleware(
                             last_few_bytes.drain(0..last_few_bytes.len() - 8192);
                         }
                     }
-                    let _ = tx.send(Ok::<_, axum::Error>(chunk)).await;
+                    // 客户端已断开：丢弃上游流以尽快取消上游请求，而不是继续读完
+                    if tx.send(Ok::<_, axum::Error>(chunk)).await.is_err() {
+                        client_disconnected = true;
+                        break;
+                    }
                 } else if let Err(e) = chunk_res {
                     let _ = tx.send(Err(axum::Error::new(e))).await;
                 }
             }
+            drop(stream);
 
             // Parse and consolidate stream data into readable format
             if let Ok(full_response) = std::str::from_utf8(&all_stream_data) {
@@ -208,26 +369,7 @@ pub async fn monitor_middleware(
                             .or(json.get("usageMetadata"))
                             .or(json.get("response").and_then(|r| r.get("usage")))
                         {
-                            log.input_tokens = usage
-                                .get("prompt_tokens")
-                                .or(usage.get("input_tokens"))
-                                .or(usage.get("promptTokenCount"))
-                                .and_then(|v| v.as_u64())
-                                .map(|v| v as u32);
-                            log.output_tokens = usage
-                                .get("completion_tokens")
-                                .or(usage.get("output_tokens"))
-                                .or(usage.get("candidatesTokenCount"))
-                                .and_then(|v| v.as_u64())
-                                .map(|v| v as u32);
-
-                            if log.input_tokens.is_none() && log.output_tokens.is_none() {
-                                log.output_tokens = usage
-                                    .get("total_tokens")
-                                    .or(usage.get("totalTokenCount"))
-                                    .and_then(|v| v.as_u64())
-                                    .map(|v| v as u32);
-                            }
+                            apply_usage(&mut log, usage);
                         }
                     }
                 }
@@ -284,18 +426,7 @@ pub async fn monitor_middleware(
                                     .or(json.get("usageMetadata"))
                                     .or(json.get("response").and_then(|r| r.get("usage")))
                                 {
-                                    log.input_tokens = usage
-                                        .get("prompt_tokens")
-                                        .or(usage.get("input_tokens"))
-                                        .or(usage.get("promptTokenCount"))
-                                        .and_then(|v| v.as_u64())
-                                        .map(|v| v as u32);
-                                    log.output_tokens = usage
-                                        .get("completion_tokens")
-                                        .or(usage.get("output_tokens"))
-                                        .or(usage.get("candidatesTokenCount"))
-                                        .and_then(|v| v.as_u64())
-                                        .map(|v| v as u32);
+                                    apply_usage(&mut log, usage);
                                     break;
                                 }
                             }
@@ -306,7 +437,11 @@ pub async fn monitor_middleware(
 
             if log.status >= 400 {
                 log.error = Some("Stream Error or Failed".to_string());
+            } else if client_disconnected {
+                log.error = Some("Client disconnected".to_string());
             }
+            settle_client_tokens(token_charge.as_ref(), &log);
+            intent.disarm();
             monitor.log_request(log).await;
         });
 
@@ -315,33 +450,14 @@ pub async fn monitor_middleware(
             Body::from_stream(tokio_stream::wrappers::ReceiverStream::new(rx)),
         )
     } else if content_type.contains("application/json") || content_type.contains("text/") {
-        let (parts, body) = response.into_parts();
+        let (mut parts, body) = response.into_parts();
         match axum::body::to_bytes(body, MAX_RESPONSE_LOG_SIZE).await {
             Ok(bytes) => {
                 if let Ok(s) = std::str::from_utf8(&bytes) {
                     if let Ok(json) = serde_json::from_str::<Value>(&s) {
                         // 支持 OpenAI "usage" 或 Gemini "usageMetadata"
                         if let Some(usage) = json.get("usage").or(json.get("usageMetadata")) {
-                            log.input_tokens = usage
-                                .get("prompt_tokens")
-                                .or(usage.get("input_tokens"))
-                                .or(usage.get("promptTokenCount"))
-                                .and_then(|v| v.as_u64())
-                                .map(|v| v as u32);
-                            log.output_tokens = usage
-                                .get("completion_tokens")
-                                .or(usage.get("output_tokens"))
-                                .or(usage.get("candidatesTokenCount"))
-                                .and_then(|v| v.as_u64())
-                                .map(|v| v as u32);
-
-                            if log.input_tokens.is_none() && log.output_tokens.is_none() {
-                                log.output_tokens = usage
-                                    .get("total_tokens")
-                                    .or(usage.get("totalTokenCount"))
-                                    .and_then(|v| v.as_u64())
-                                    .map(|v| v as u32);
-                            }
+                            apply_usage(&mut log, usage);
                         }
                     }
                     log.response_body = Some(s.to_string());
@@ -352,18 +468,96 @@ pub async fn monitor_middleware(
                 if log.status >= 400 {
                     log.error = log.response_body.clone();
                 }
+                insert_cost_header(&mut parts.headers, &log);
+                settle_client_tokens(token_charge.as_ref(), &log);
+                intent.disarm();
                 monitor.log_request(log).await;
                 Response::from_parts(parts, Body::from(bytes))
             }
             Err(_) => {
                 log.response_body = Some("[Response too large (>100MB)]".to_string());
+                settle_client_tokens(token_charge.as_ref(), &log);
+                intent.disarm();
                 monitor.log_request(log).await;
                 Response::from_parts(parts, Body::empty())
             }
         }
     } else {
         log.response_body = Some(format!("[{}]", content_type));
+        settle_client_tokens(token_charge.as_ref(), &log);
+        intent.disarm();
         monitor.log_request(log).await;
         response
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn anthropic_input_excludes_cache_and_is_normalized() {
+        let usage = json!({
+            "input_tokens": 200,
+            "output_tokens": 50,
+            "cache_read_input_tokens": 800,
+            "cache_creation_input_tokens": 0
+        });
+        assert_eq!(
+            read_usage(&usage, Some("anthropic")),
+            UsageTokens {
+                input: Some(1000),
+                output: Some(50),
+                cache_read: Some(800),
+            }
+        );
+    }
+
+    #[test]
+    fn openai_prompt_tokens_already_include_cache() {
+        let usage = json!({
+            "prompt_tokens": 1000,
+            "completion_tokens": 50,
+            "prompt_tokens_details": {"cached_tokens": 800}
+        });
+        assert_eq!(
+            read_usage(&usage, Some("openai")),
+            UsageTokens {
+                input: Some(1000),
+                output: Some(50),
+                cache_read: Some(800),
+            }
+        );
+
+        let responses = json!({
+            "input_tokens": 1000,
+            "output_tokens": 50,
+            "input_tokens_details": {"cached_tokens": 800}
+        });
+        assert_eq!(read_usage(&responses, Some("openai")).input, Some(1000));
+    }
+
+    #[test]
+    fn gemini_prompt_token_count_already_includes_cache() {
+        let usage = json!({
+            "promptTokenCount": 1000,
+            "candidatesTokenCount": 50,
+            "cachedContentTokenCount": 800
+        });
+        assert_eq!(
+            read_usage(&usage, Some("gemini")),
+            UsageTokens {
+                input: Some(1000),
+                output: Some(50),
+                cache_read: Some(800),
+            }
+        );
+    }
+
+    #[test]
+    fn total_tokens_fallback() {
+        let usage = json!({"totalTokenCount": 42});
+        assert_eq!(read_usage(&usage, Some("gemini")).output, Some(42));
+    }
+}
@@ -0,0 +1,107 @@
+// 响应缓存中间件
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::proxy::response_cache::{self, CachedResponse, RESPONSE_CACHE_HEADER};
+use crate::proxy::security::AuthenticatedKey;
+
+const MAX_REQUEST_BODY_SIZE: usize = 100 * 1024 * 1024;
+
+fn is_cacheable_route(path: &str) -> bool {
+    matches!(
+        path,
+        "/v1/messages" | "/v1/chat/completions" | "/v1/completions"
+    ) || path.starts_with("/v1beta/models/")
+}
+
+/// 命中缓存时直接返回缓存响应；未命中时在成功的 JSON 响应返回后写入缓存
+pub async fn response_cache_middleware(request: Request, next: Next) -> Response {
+    let config = response_cache::config();
+    let path = request.uri().path().to_string();
+    if !config.enabled || request.method() != Method::POST || !is_cacheable_route(&path) {
+        return next.run(request).await;
+    }
+
+    let key_id = request
+        .extensions()
+        .get::<AuthenticatedKey>()
+        .and_then(|k| k.key_id.clone());
+    let (parts, body) = request.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_REQUEST_BODY_SIZE).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                format!("Failed to read request body: {}", e),
+            )
+                .into_response();
+        }
+    };
+    let cache_key = serde_json::from_slice::<Value>(&bytes)
+        .ok()
+        .and_then(|body| {
+            response_cache::cache_key(&path, key_id.as_deref(), &parts.headers, &body)
+        });
+    let request = Request::from_parts(parts, Body::from(bytes));
+    let Some(cache_key) = cache_key else {
+        return next.run(request).await;
+    };
+
+    let cache = response_cache::global();
+    if let Some(cached) = cache.get(&cache_key, Duration::from_secs(config.ttl_secs)) {
+        tracing::debug!("[ResponseCache] Hit for {}", path);
+        let mut response = Response::new(Body::from(cached.body));
+        let headers = response.headers_mut();
+        headers.insert(header::CONTENT_TYPE, cached.content_type);
+        if let Some(mapped_model) = cached.mapped_model {
+            headers.insert("X-Mapped-Model", mapped_model);
+        }
+        headers.insert(RESPONSE_CACHE_HEADER, HeaderValue::from_static("HIT"));
+        return response;
+    }
+
+    let response = next.run(request).await;
+    let content_type = response.headers().get(header::CONTENT_TYPE).cloned();
+    let is_json = content_type
+        .as_ref()
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.contains("application/json"))
+        .unwrap_or(false);
+    if response.status() != StatusCode::OK || !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            return (
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to read response body: {}", e),
+            )
+                .into_response();
+        }
+    };
+    if let Some(content_type) = content_type {
+        cache.insert(
+            cache_key,
+            CachedResponse {
+                body: bytes.clone(),
+                content_type,
+                mapped_model: parts.headers.get("X-Mapped-Model").cloned(),
+            },
+            &config,
+        );
+    }
+    parts
+        .headers
+        .insert(RESPONSE_CACHE_HEADER, HeaderValue::from_static("MISS"));
+    Response::from_parts(parts, Body::from(bytes))
+}
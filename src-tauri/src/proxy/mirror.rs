@@ -0,0 +1,287 @@
+// 请求镜像 (Mirror)
+// 按配置比例抽样请求，在后台以相同的 Gemini 请求体调用另一个模型 / 账号池，
+// 镜像响应不返回给客户端。主请求与镜像请求的状态、耗时、响应内容一并写入
+// proxy_logs.db 的 mirror_samples 表，用于离线比较不同路由选择的效果。
+// 保存的内容先截断再抹除凭据；请求体 (用户 prompt) 仅在 `store_prompts` 开启时保存。
+// 镜像账号只做旁路查询，不推进主调度的轮询、粘性会话与并发计数。
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+use crate::proxy::common::redact::redact_secrets;
+use crate::proxy::config::MirrorConfig;
+use crate::proxy::upstream::client::UpstreamClient;
+use crate::proxy::TokenManager;
+
+/// 单侧响应保存的最大字节数
+const MAX_STORED_RESPONSE_BYTES: usize = 64 * 1024;
+/// 等待主请求结果的最长时间 (流式请求可能持续很久)
+const PRIMARY_WAIT_TIMEOUT: Duration = Duration::from_secs(600);
+
+static CONFIG: Lazy<RwLock<MirrorConfig>> = Lazy::new(|| RwLock::new(MirrorConfig::default()));
+
+/// 镜像账号池轮询游标
+static ACCOUNT_CURSOR: AtomicUsize = AtomicUsize::new(0);
+
+/// 替换镜像配置 (由反代启动 / 配置保存时写入)
+pub fn set_config(config: MirrorConfig) {
+    *CONFIG.write() = config;
+}
+
+/// 一条镜像样本
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirrorSample {
+    pub id: String,
+    pub timestamp: i64,
+    pub protocol: String,
+    /// 客户端请求的模型名
+    pub client_model: String,
+    pub primary_model: String,
+    pub primary_account: String,
+    /// 主请求状态码，None 表示主请求未正常结束 (重试耗尽 / 客户端断开)
+    pub primary_status: Option<u16>,
+    /// 主请求耗时；流式请求为首个数据块到达的耗时
+    pub primary_latency_ms: Option<u64>,
+    pub primary_streamed: bool,
+    pub primary_response: Option<String>,
+    pub mirror_model: String,
+    pub mirror_account: Option<String>,
+    pub mirror_status: Option<u16>,
+    pub mirror_latency_ms: Option<u64>,
+    pub mirror_response: Option<String>,
+    pub mirror_error: Option<String>,
+    pub request_body: Option<String>,
+}
+
+struct PrimaryOutcome {
+    status: u16,
+    latency_ms: u64,
+    streamed: bool,
+    response: Option<String>,
+}
+
+/// 主请求侧的句柄：请求结束时调用 `finish` 上报结果。
+/// 未上报即被丢弃时，样本中的主请求结果记为空。
+pub struct MirrorHandle {
+    started: Instant,
+    tx: Option<oneshot::Sender<PrimaryOutcome>>,
+}
+
+impl MirrorHandle {
+    /// 上报主请求结果 (流式请求不保存响应内容)
+    pub fn finish<T: Serialize>(mut self, status: u16, streamed: bool, response: Option<&T>) {
+        if let Some(tx) = self.tx.take() {
+            let _ = tx.send(PrimaryOutcome {
+                status,
+                latency_ms: self.started.elapsed().as_millis() as u64,
+                streamed,
+                response: response
+                    .and_then(|v| serde_json::to_string(v).ok())
+                    .map(clean),
+            });
+        }
+    }
+}
+
+fn truncate(mut text: String) -> String {
+    if text.len() > MAX_STORED_RESPONSE_BYTES {
+        let mut end = MAX_STORED_RESPONSE_BYTES;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    text
+}
+
+/// 入库前处理：先截断再抹除凭据
+fn clean(text: String) -> String {
+    redact_secrets(&truncate(text))
+}
+
+fn sampled(percent: f64) -> bool {
+    if percent <= 0.0 {
+        return false;
+    }
+    percent >= 100.0 || rand::random::<f64>() * 100.0 < percent
+}
+
+/// 按抽样比例决定是否镜像当前请求；命中时立即在后台发起镜像请求并返回主请求句柄。
+/// `gemini_body` 为发往上游的 v1internal 请求体，镜像请求仅替换其中的 model / project。
+pub fn begin(
+    token_manager: &Arc<TokenManager>,
+    upstream: &Arc<UpstreamClient>,
+    protocol: &str,
+    client_model: &str,
+    primary_model: &str,
+    primary_account: &str,
+    gemini_body: &Value,
+) -> Option<MirrorHandle> {
+    let config = CONFIG.read().clone();
+    if !config.enabled
+        || config.target_model.is_empty()
+        || config.target_model == primary_model
+        || !sampled(config.sample_percent)
+    {
+        return None;
+    }
+
+    let (tx, rx) = oneshot::channel();
+    let sample = MirrorSample {
+        id: uuid::Uuid::new_v4().to_string(),
+        timestamp: chrono::Utc::now().timestamp_millis(),
+        protocol: protocol.to_string(),
+        client_model: client_model.to_string(),
+        primary_model: primary_model.to_string(),
+        primary_account: primary_account.to_string(),
+        primary_status: None,
+        primary_latency_ms: None,
+        primary_streamed: false,
+        primary_response: None,
+        mirror_model: config.target_model.clone(),
+        mirror_account: None,
+        mirror_status: None,
+        mirror_latency_ms: None,
+        mirror_response: None,
+        mirror_error: None,
+        request_body: config.store_prompts.then(|| clean(gemini_body.to_string())),
+    };
+
+    tokio::spawn(run_mirror(
+        token_manager.clone(),
+        upstream.clone(),
+        config,
+        gemini_body.clone(),
+        sample,
+        rx,
+    ));
+
+    Some(MirrorHandle {
+        started: Instant::now(),
+        tx: Some(tx),
+    })
+}
+
+async fn pick_token(
+    token_manager: &TokenManager,
+    config: &MirrorConfig,
+) -> Result<crate::proxy::token_manager::SelectedToken, String> {
+    // 未指定账号池时从当前可服务目标模型的账号中轮询，不经过 get_token 的调度流程
+    let pool = if config.accounts.is_empty() {
+        token_manager.available_emails_for(&config.target_model)
+    } else {
+        config.accounts.clone()
+    };
+    if pool.is_empty() {
+        return Err(format!("No account available for {}", config.target_model));
+    }
+    let index = ACCOUNT_CURSOR.fetch_add(1, Ordering::Relaxed) % pool.len();
+    token_manager.get_token_by_email(&pool[index]).await
+}
+
+async fn run_mirror(
+    token_manager: Arc<TokenManager>,
+    upstream: Arc<UpstreamClient>,
+    config: MirrorConfig,
+    mut body: Value,
+    mut sample: MirrorSample,
+    primary_rx: oneshot::Receiver<PrimaryOutcome>,
+) {
+    match pick_token(&token_manager, &config).await {
+        Ok((access_token, project_id, email, upstream_proxy)) => {
+            body["model"] = Value::String(config.target_model.clone());
+            body["project"] = Value::String(project_id);
+            sample.mirror_account = Some(email);
+
+            let started = Instant::now();
+            match upstream
+                .call_v1_internal(
+                    "generateContent",
+                    &access_token,
+                    upstream_proxy.as_deref(),
+                    body,
+                    None,
+                )
+                .await
+            {
+                Ok(resp) => {
+                    sample.mirror_status = Some(resp.status().as_u16());
+                    let text = resp.text().await.unwrap_or_default();
+                    sample.mirror_latency_ms = Some(started.elapsed().as_millis() as u64);
+                    sample.mirror_response = Some(clean(text));
+                }
+                Err(e) => {
+                    sample.mirror_latency_ms = Some(started.elapsed().as_millis() as u64);
+                    sample.mirror_error = Some(e);
+                }
+            }
+        }
+        Err(e) => sample.mirror_error = Some(format!("Token error: {}", e)),
+    }
+
+    if let Ok(Ok(primary)) = tokio::time::timeout(PRIMARY_WAIT_TIMEOUT, primary_rx).await {
+        sample.primary_status = Some(primary.status);
+        sample.primary_latency_ms = Some(primary.latency_ms);
+        sample.primary_streamed = primary.streamed;
+        sample.primary_response = primary.response;
+    }
+
+    tracing::debug!(
+        "[Mirror] {} -> {} | primary {:?} in {:?}ms, mirror {:?} in {:?}ms",
+        sample.primary_model,
+        sample.mirror_model,
+        sample.primary_status,
+        sample.primary_latency_ms,
+        sample.mirror_status,
+        sample.mirror_latency_ms
+    );
+
+    let max_samples = config.max_samples;
+    let _ = tokio::task::spawn_blocking(move || {
+        if let Err(e) = crate::modules::proxy_db::save_mirror_sample(&sample) {
+            tracing::warn!("[Mirror] Failed to save sample: {}", e);
+            return;
+        }
+        if let Err(e) = crate::modules::proxy_db::limit_mirror_samples(max_samples) {
+            tracing::warn!("[Mirror] Failed to prune samples: {}", e);
+        }
+    })
+    .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampling_bounds() {
+        assert!(!sampled(0.0));
+        assert!(!sampled(-5.0));
+        assert!(sampled(100.0));
+    }
+
+    #[test]
+    fn test_truncate_respects_char_boundary() {
+        let text = "中".repeat(MAX_STORED_RESPONSE_BYTES);
+        let cut = truncate(text);
+        assert!(cut.len() <= MAX_STORED_RESPONSE_BYTES);
+        assert!(cut.chars().all(|c| c == '中'));
+    }
+
+    #[test]
+    fn test_clean_redacts_after_truncating() {
+        let text = format!(
+            "{{\"access_token\":\"ya29.secret\",\"pad\":\"{}\"}}",
+            "x".repeat(MAX_STORED_RESPONSE_BYTES)
+        );
+        let cleaned = clean(text);
+        assert!(!cleaned.contains("ya29.secret"));
+        assert!(cleaned.len() <= MAX_STORED_RESPONSE_BYTES + 16);
+    }
+}
@@ -18,11 +18,19 @@ pub mod zai_vision_mcp;    // Built-in Vision MCP server state
 pub mod zai_vision_tools;  // Built-in Vision MCP tools (z.ai vision API)
 pub mod monitor;           // 监控
 pub mod rate_limit;        // 限流跟踪
+pub mod circuit_breaker;   // 账号熔断
 pub mod sticky_config;     // 粘性调度配置
 pub mod session_manager;   // 会话指纹管理
 pub mod audio;             // 音频处理模块
 pub mod signature_cache;   // Signature Cache (v3.3.16)
 pub mod cli_sync;          // CLI 配置同步 (v3.3.35)
+pub mod warm_state;        // 反代热重启状态持久化
+pub mod mirror;            // 请求镜像 (离线评估)
+pub mod client_rate_limit; // 客户端 API Key 限流
+pub mod response_cache;    // 确定性请求响应缓存
+pub mod concurrency;       // 并发上限与排队
+pub mod tls;               // 反代 HTTPS
+pub mod diagnostics;       // 调试诊断响应头
 
 
 pub use config::ProxyConfig;
@@ -2,7 +2,34 @@ use serde::{Serialize, Deserialize};
 use std::collections::VecDeque;
 use tokio::sync::RwLock;
 use tauri::Emitter;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use once_cell::sync::Lazy;
+use crate::proxy::common::redact::{redact_secrets, truncate_body};
+use crate::proxy::config::TrafficLogConfig;
+
+/// 请求日志内容配置 (由反代启动 / 配置保存时写入)
+static TRAFFIC_LOG: Lazy<parking_lot::RwLock<TrafficLogConfig>> =
+    Lazy::new(|| parking_lot::RwLock::new(TrafficLogConfig::default()));
+
+pub fn set_traffic_log_config(config: TrafficLogConfig) {
+    *TRAFFIC_LOG.write() = config;
+}
+
+/// 尚未完成的后台数据库写入（日志 / token 统计），退出前需等待归零
+static PENDING_DB_WRITES: AtomicUsize = AtomicUsize::new(0);
+
+/// 等待后台数据库写入完成，返回超时后仍未完成的数量
+pub async fn wait_for_pending_writes(timeout: std::time::Duration) -> usize {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let pending = PENDING_DB_WRITES.load(Ordering::SeqCst)
+            + crate::modules::token_stats::pending_intent_writes();
+        if pending == 0 || tokio::time::Instant::now() >= deadline {
+            return pending;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProxyRequestLog {
@@ -20,9 +47,30 @@ pub struct ProxyRequestLog {
     pub response_body: Option<String>,
     pub input_tokens: Option<u32>,
     pub output_tokens: Option<u32>,
+    /// 命中上游 prompt cache 的输入 tokens (仅写入用量统计，不持久化到请求日志)
+    #[serde(default)]
+    pub cache_read_tokens: Option<u32>,
     pub protocol: Option<String>,     // 协议类型: "openai", "anthropic", "gemini"
 }
 
+impl ProxyRequestLog {
+    /// 按日志配置截断或丢弃请求/响应体并抹除凭据 (先截断，避免对超大请求体整体做正则替换)
+    fn sanitize(&mut self, config: &TrafficLogConfig) {
+        self.url = redact_secrets(&self.url);
+        let clean = |body: Option<String>| {
+            body.map(|b| redact_secrets(&truncate_body(b, config.max_body_bytes)))
+        };
+        self.error = clean(self.error.take());
+        if config.capture_bodies {
+            self.request_body = clean(self.request_body.take());
+            self.response_body = clean(self.response_body.take());
+        } else {
+            self.request_body = None;
+            self.response_body = None;
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ProxyStats {
     pub total_requests: u64,
@@ -84,24 +132,45 @@ impl ProxyMonitor {
         self._log_request(log, false).await;
     }
 
-    async fn _log_request(&self, log: ProxyRequestLog, force: bool) {
-        if let (Some(account), Some(input), Some(output)) = (
-            &log.account_email,
-            log.input_tokens,
-            log.output_tokens,
-        ) {
-            let model = log.model.clone().unwrap_or_else(|| "unknown".to_string());
-            let account = account.clone();
-            tokio::spawn(async move {
-                if let Err(e) = crate::modules::token_stats::record_usage(&account, &model, input, output) {
-                    tracing::debug!("Failed to record token stats: {}", e);
+    async fn _log_request(&self, mut log: ProxyRequestLog, force: bool) {
+        // Record the request's usage row and finalize the write-ahead intent (log.id) in one transaction.
+        // Every model request gets a row; tokens are only booked when an account actually served it
+        // (rejected requests and cache hits are recorded with zero tokens).
+        {
+            let usage = log.model.as_ref().map(|model| {
+                let served = log.account_email.is_some();
+                let tokens = |t: Option<u32>| if served { t.unwrap_or(0) } else { 0 };
+                let (input_tokens, output_tokens, cache_read_tokens) = (
+                    tokens(log.input_tokens),
+                    tokens(log.output_tokens),
+                    tokens(log.cache_read_tokens),
+                );
+                let estimated_cost = crate::proxy::common::pricing::estimate_cost(
+                    Some(model),
+                    log.mapped_model.as_deref(),
+                    input_tokens,
+                    output_tokens,
+                    cache_read_tokens,
+                )
+                .unwrap_or(0.0);
+                crate::modules::token_stats::RequestUsage {
+                    account_email: log.account_email.clone(),
+                    model: model.clone(),
+                    input_tokens,
+                    output_tokens,
+                    cache_read_tokens,
+                    latency_ms: log.duration,
+                    status: log.status,
+                    estimated_cost,
                 }
             });
+            crate::modules::token_stats::complete_usage_intent(&log.id, usage);
         }
 
         if !force && !self.is_enabled() {
             return;
         }
+        log.sanitize(&TRAFFIC_LOG.read());
         tracing::info!("[Monitor] Logging request: {} {}", log.method, log.url);
         // Update stats
         {
@@ -125,10 +194,12 @@ impl ProxyMonitor {
 
         // Save to DB
         let log_to_save = log.clone();
+        PENDING_DB_WRITES.fetch_add(1, Ordering::SeqCst);
         tokio::spawn(async move {
             if let Err(e) = crate::modules::proxy_db::save_log(&log_to_save) {
                 tracing::error!("Failed to save proxy log to DB: {}", e);
             }
+            PENDING_DB_WRITES.fetch_sub(1, Ordering::SeqCst);
         });
 
         // Emit event (send summary only, without body to reduce memory)
@@ -148,6 +219,7 @@ impl ProxyMonitor {
                 response_body: None, // Don't send body in event
                 input_tokens: log.input_tokens,
                 output_tokens: log.output_tokens,
+                cache_read_tokens: log.cache_read_tokens,
                 protocol: log.protocol.clone(),
             };
             let _ = app.emit("proxy://request", &log_summary);
@@ -2,7 +2,8 @@ use serde_json::Value;
 
 /// 使用 Antigravity 的 loadCodeAssist API 获取 project_id
 /// 这是获取 cloudaicompanionProject 的正确方式
-pub async fn fetch_project_id(access_token: &str) -> Result<String, String> {
+/// `upstream_proxy` 为账号专属代理，为空时走全局上游代理
+pub async fn fetch_project_id(access_token: &str, upstream_proxy: Option<&str>) -> Result<String, String> {
     let url = "https://cloudcode-pa.googleapis.com/v1internal:loadCodeAssist";
     
     let request_body = serde_json::json!({
@@ -11,7 +12,7 @@ pub async fn fetch_project_id(access_token: &str) -> Result<String, String> {
         }
     });
     
-    let client = crate::utils::http::get_client();
+    let client = crate::utils::http::client_for_proxy(upstream_proxy)?;
     let response = client
         .post(url)
         .bearer_auth(access_token)
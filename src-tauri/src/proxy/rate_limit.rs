@@ -1,9 +1,10 @@
 use dashmap::DashMap;
 use std::time::{SystemTime, Duration};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 
 /// 限流原因类型
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum RateLimitReason {
     /// 配额耗尽 (QUOTA_EXHAUSTED)
     QuotaExhausted,
@@ -13,6 +14,8 @@ pub enum RateLimitReason {
     ModelCapacityExhausted,
     /// 服务器错误 (5xx)
     ServerError,
+    /// 账号无权访问 (403)
+    Forbidden,
     /// 未知原因
     Unknown,
 }
@@ -38,6 +41,38 @@ pub struct RateLimitInfo {
     pub model: Option<String>,
 }
 
+/// 可持久化的限流记录（时间均为 Unix 秒），用于反代热重启
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersistedRateLimit {
+    pub key: String,
+    pub reset_at: u64,
+    pub retry_after_sec: u64,
+    pub detected_at: u64,
+    pub reason: RateLimitReason,
+    pub model: Option<String>,
+}
+
+/// 可持久化的连续失败计数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersistedFailureCount {
+    pub key: String,
+    pub count: u32,
+    pub last_failure_at: u64,
+}
+
+fn to_unix_secs(t: SystemTime) -> u64 {
+    t.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn from_unix_secs(secs: u64) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+/// 上游提示的等待时间下限 (毫秒)，防止极高频无效重试
+const MIN_RETRY_MS: u64 = 2000;
+
 /// 失败计数过期时间：1小时（超过此时间未失败则重置计数）
 const FAILURE_COUNT_EXPIRY_SECONDS: u64 = 3600;
 
@@ -172,25 +207,14 @@ impl RateLimitTracker {
             RateLimitReason::ServerError
         };
         
-        let mut retry_after_sec = None;
-        
-        // 2. 从 Retry-After header 提取
-        if let Some(retry_after) = retry_after_header {
-            if let Ok(seconds) = retry_after.parse::<u64>() {
-                retry_after_sec = Some(seconds);
-            }
-        }
-        
-        // 3. 从错误消息提取 (优先尝试 JSON 解析，再试正则)
-        if retry_after_sec.is_none() {
-            retry_after_sec = self.parse_retry_time_from_body(body);
-        }
+        // 2. 解析上游给出的精确等待时间 (毫秒)
+        let retry_after_ms = self.parse_retry_hint_ms(retry_after_header, body);
         
-        // 4. 处理默认值与软避让逻辑（根据限流类型设置不同默认值）
-        let retry_sec = match retry_after_sec {
-            Some(s) => {
-                // 设置安全缓冲区：最小 2 秒，防止极高频无效重试
-                if s < 2 { 2 } else { s }
+        // 3. 处理默认值与软避让逻辑（根据限流类型设置不同默认值）
+        let retry_ms = match retry_after_ms {
+            Some(ms) => {
+                // 设置安全缓冲区：最小 2 秒，防止极高频无效重试；最长不超过 24 小时
+                ms.clamp(MIN_RETRY_MS, crate::proxy::upstream::retry::MAX_RETRY_AFTER_MS)
             },
             None => {
                 // 获取连续失败次数，用于指数退避（带自动过期逻辑）
@@ -208,7 +232,7 @@ impl RateLimitTracker {
                     entry.0
                 };
                 
-                match reason {
+                let default_sec = match reason {
                     RateLimitReason::QuotaExhausted => {
                         // [智能限流] 根据连续失败次数动态调整锁定时间
                         // 第1次: 60s, 第2次: 5min, 第3次: 30min, 第4次+: 2h
@@ -248,17 +272,23 @@ impl RateLimitTracker {
                         tracing::warn!("检测到 5xx 错误 ({}), 执行 20s 软避让...", status);
                         20
                     },
+                    RateLimitReason::Forbidden => {
+                        // 403 由 TokenManager::mark_auth_failure 直接设置冷却，不经过此处
+                        600
+                    },
                     RateLimitReason::Unknown => {
                         // 未知原因：使用中等默认值（60秒）
                         tracing::debug!("无法解析 429 限流原因, 使用默认值 60秒");
                         60
                     }
-                }
+                };
+                default_sec * 1000
             }
         };
+        let retry_sec = retry_ms.div_ceil(1000);
         
         let info = RateLimitInfo {
-            reset_time: SystemTime::now() + Duration::from_secs(retry_sec),
+            reset_time: SystemTime::now() + Duration::from_millis(retry_ms),
             retry_after_sec: retry_sec,
             detected_at: SystemTime::now(),
             reason,
@@ -279,6 +309,20 @@ impl RateLimitTracker {
         Some(info)
     }
     
+    /// 解析上游给出的等待时间提示 (毫秒)
+    /// 优先级：Retry-After 头 > RetryInfo.retryDelay / quotaResetDelay > 错误文本中的提示
+    fn parse_retry_hint_ms(&self, retry_after_header: Option<&str>, body: &str) -> Option<u64> {
+        retry_after_header
+            .and_then(crate::proxy::upstream::retry::parse_retry_after_header)
+            .or_else(|| crate::proxy::upstream::retry::parse_retry_delay(body.trim()))
+            .or_else(|| self.parse_retry_time_from_body(body).map(|s| s * 1000))
+    }
+
+    /// 上游响应是否带有可用的等待时间提示
+    pub fn has_retry_hint(&self, retry_after_header: Option<&str>, body: &str) -> bool {
+        self.parse_retry_hint_ms(retry_after_header, body).is_some()
+    }
+
     /// 解析限流原因类型
     fn parse_rate_limit_reason(&self, body: &str) -> RateLimitReason {
         // 尝试从 JSON 中提取 reason 字段
@@ -503,6 +547,68 @@ impl RateLimitTracker {
         self.limits.remove(account_id).is_some()
     }
     
+    /// 导出未过期的限流记录与失败计数（用于反代热重启）
+    pub fn export_state(&self) -> (Vec<PersistedRateLimit>, Vec<PersistedFailureCount>) {
+        let now = SystemTime::now();
+        let limits = self
+            .limits
+            .iter()
+            .filter(|entry| entry.reset_time > now)
+            .map(|entry| PersistedRateLimit {
+                key: entry.key().clone(),
+                reset_at: to_unix_secs(entry.reset_time),
+                retry_after_sec: entry.retry_after_sec,
+                detected_at: to_unix_secs(entry.detected_at),
+                reason: entry.reason,
+                model: entry.model.clone(),
+            })
+            .collect();
+        let failures = self
+            .failure_counts
+            .iter()
+            .filter(|entry| {
+                now.duration_since(entry.1).unwrap_or(Duration::ZERO).as_secs()
+                    < FAILURE_COUNT_EXPIRY_SECONDS
+            })
+            .map(|entry| PersistedFailureCount {
+                key: entry.key().clone(),
+                count: entry.0,
+                last_failure_at: to_unix_secs(entry.1),
+            })
+            .collect();
+        (limits, failures)
+    }
+
+    /// 恢复持久化的限流记录（已过期的条目直接丢弃），返回恢复的限流记录数
+    pub fn import_state(&self, limits: Vec<PersistedRateLimit>, failures: Vec<PersistedFailureCount>) -> usize {
+        let now = SystemTime::now();
+        let mut restored = 0;
+        for item in limits {
+            let reset_time = from_unix_secs(item.reset_at);
+            if reset_time <= now {
+                continue;
+            }
+            self.limits.insert(
+                item.key,
+                RateLimitInfo {
+                    reset_time,
+                    retry_after_sec: item.retry_after_sec,
+                    detected_at: from_unix_secs(item.detected_at),
+                    reason: item.reason,
+                    model: item.model,
+                },
+            );
+            restored += 1;
+        }
+        for item in failures {
+            let last = from_unix_secs(item.last_failure_at);
+            if now.duration_since(last).unwrap_or(Duration::ZERO).as_secs() < FAILURE_COUNT_EXPIRY_SECONDS {
+                self.failure_counts.insert(item.key, (item.count, last));
+            }
+        }
+        restored
+    }
+
     /// 清除所有限流记录 (乐观重置策略)
     /// 
     /// 用于乐观重置机制,当所有账号都被限流但等待时间很短时,
@@ -566,6 +672,24 @@ mod tests {
         assert!(wait > 25 && wait <= 30);
     }
 
+    #[test]
+    fn test_retry_info_in_later_detail() {
+        let tracker = RateLimitTracker::new();
+        let body = r#"{
+            "error": {
+                "details": [
+                    { "@type": "type.googleapis.com/google.rpc.ErrorInfo", "reason": "RATE_LIMIT_EXCEEDED" },
+                    { "@type": "type.googleapis.com/google.rpc.RetryInfo", "retryDelay": "12.5s" }
+                ]
+            }
+        }"#;
+        assert!(tracker.has_retry_hint(None, body));
+        let info = tracker.parse_from_error("acc1", 429, None, body, None).unwrap();
+        assert_eq!(info.retry_after_sec, 13);
+        let wait = tracker.get_remaining_wait("acc1");
+        assert!(wait >= 11 && wait <= 12);
+    }
+
     #[test]
     fn test_safety_buffer() {
         let tracker = RateLimitTracker::new();
@@ -585,4 +709,19 @@ mod tests {
         // 应该被识别为 RateLimitExceeded，而不是 QuotaExhausted
         assert_eq!(reason, RateLimitReason::RateLimitExceeded);
     }
+
+    #[test]
+    fn test_export_import_state_roundtrip() {
+        let tracker = RateLimitTracker::new();
+        tracker.set_lockout_until("acc1", SystemTime::now() + Duration::from_secs(120), RateLimitReason::QuotaExhausted, None);
+        tracker.set_lockout_until("acc2", SystemTime::now() - Duration::from_secs(1), RateLimitReason::Unknown, None);
+
+        let (limits, failures) = tracker.export_state();
+        assert_eq!(limits.len(), 1);
+
+        let restored = RateLimitTracker::new();
+        assert_eq!(restored.import_state(limits, failures), 1);
+        assert!(restored.is_rate_limited("acc1"));
+        assert!(!restored.is_rate_limited("acc2"));
+    }
 }
@@ -0,0 +1,334 @@
+// 确定性请求的响应缓存
+//
+// temperature 为 0 的非流式请求，在短时间内被重试较激进的 Agent 重复发送时直接返回缓存的响应，
+// 不再消耗上游配额。缓存键为 (协议路径, 客户端 Key, 影响生成结果的请求头, 规范化请求体) 的哈希；规范化时去掉
+// stream/metadata/user 等不影响生成结果的字段 (serde_json 未启用 preserve_order，序列化时键已排序)。
+// 条目按 TTL 过期，条目数超出上限时淘汰最早写入的条目，超过单条大小上限的响应不缓存。
+
+use axum::http::{HeaderMap, HeaderValue};
+use bytes::Bytes;
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::proxy::config::ResponseCacheConfig;
+
+/// 标注响应是否来自缓存 (HIT / MISS)
+pub const RESPONSE_CACHE_HEADER: &str = "X-Response-Cache";
+
+/// 会改变上游请求 (beta 功能、API 版本) 的请求头，计入缓存键
+const KEYED_HEADERS: &[&str] = &["anthropic-beta", "anthropic-version", "openai-beta"];
+
+/// 不影响生成结果、计算缓存键时忽略的请求字段
+const IGNORED_FIELDS: &[&str] = &[
+    "stream",
+    "stream_options",
+    "metadata",
+    "user",
+    "prompt_cache_key",
+];
+
+static CONFIG: Lazy<RwLock<ResponseCacheConfig>> =
+    Lazy::new(|| RwLock::new(ResponseCacheConfig::default()));
+
+static CACHE: Lazy<ResponseCache> = Lazy::new(ResponseCache::new);
+
+/// 替换缓存配置 (由反代启动 / 配置保存时写入)，关闭缓存时清空已有条目
+pub fn set_config(config: ResponseCacheConfig) {
+    if !config.enabled {
+        CACHE.clear();
+    }
+    *CONFIG.write() = config;
+}
+
+pub fn config() -> ResponseCacheConfig {
+    CONFIG.read().clone()
+}
+
+pub fn global() -> &'static ResponseCache {
+    &CACHE
+}
+
+/// 计算缓存键；请求不是确定性的 (非 temperature=0、流式) 时返回 None
+pub fn cache_key(
+    path: &str,
+    key_id: Option<&str>,
+    headers: &HeaderMap,
+    body: &Value,
+) -> Option<String> {
+    if !is_deterministic(path, body) {
+        return None;
+    }
+    let mut normalized = body.clone();
+    if let Some(obj) = normalized.as_object_mut() {
+        for field in IGNORED_FIELDS {
+            obj.remove(*field);
+        }
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(path.as_bytes());
+    hasher.update([0]);
+    hasher.update(key_id.unwrap_or("").as_bytes());
+    hasher.update([0]);
+    for name in KEYED_HEADERS {
+        for value in headers.get_all(*name) {
+            hasher.update(value.as_bytes());
+            hasher.update([b',']);
+        }
+        hasher.update([0]);
+    }
+    hasher.update(normalized.to_string().as_bytes());
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+fn is_deterministic(path: &str, body: &Value) -> bool {
+    if body
+        .get("stream")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+    {
+        return false;
+    }
+    let temperature = if path.starts_with("/v1beta/models/") {
+        if !path.ends_with(":generateContent") {
+            return false;
+        }
+        body.pointer("/generationConfig/temperature")
+    } else {
+        body.get("temperature")
+    };
+    temperature.and_then(|v| v.as_f64()) == Some(0.0)
+}
+
+#[derive(Clone)]
+pub struct CachedResponse {
+    pub body: Bytes,
+    pub content_type: HeaderValue,
+    pub mapped_model: Option<HeaderValue>,
+}
+
+struct CacheEntry {
+    response: CachedResponse,
+    stored_at: Instant,
+}
+
+#[derive(Default)]
+struct CacheStore {
+    entries: HashMap<String, CacheEntry>,
+    /// 写入顺序，用于按 TTL 过期与超出条目数时淘汰
+    order: VecDeque<String>,
+}
+
+pub struct ResponseCache {
+    store: Mutex<CacheStore>,
+}
+
+impl ResponseCache {
+    fn new() -> Self {
+        Self {
+            store: Mutex::new(CacheStore::default()),
+        }
+    }
+
+    pub fn get(&self, key: &str, ttl: Duration) -> Option<CachedResponse> {
+        self.get_at(key, ttl, Instant::now())
+    }
+
+    fn get_at(&self, key: &str, ttl: Duration, now: Instant) -> Option<CachedResponse> {
+        let mut store = self.store.lock();
+        let expired = match store.entries.get(key) {
+            Some(entry) if now.saturating_duration_since(entry.stored_at) < ttl => {
+                return Some(entry.response.clone());
+            }
+            Some(_) => true,
+            None => false,
+        };
+        if expired {
+            store.entries.remove(key);
+            store.order.retain(|k| k != key);
+        }
+        None
+    }
+
+    pub fn insert(&self, key: String, response: CachedResponse, config: &ResponseCacheConfig) {
+        self.insert_at(key, response, config, Instant::now());
+    }
+
+    fn insert_at(
+        &self,
+        key: String,
+        response: CachedResponse,
+        config: &ResponseCacheConfig,
+        now: Instant,
+    ) {
+        if config.max_entries == 0 || response.body.len() > config.max_entry_bytes {
+            return;
+        }
+        let ttl = Duration::from_secs(config.ttl_secs);
+        let mut store = self.store.lock();
+
+        // 先清理已过期的条目 (写入顺序即过期顺序)
+        while let Some(oldest) = store.order.front() {
+            let expired = store
+                .entries
+                .get(oldest)
+                .map(|e| now.saturating_duration_since(e.stored_at) >= ttl)
+                .unwrap_or(true);
+            if !expired {
+                break;
+            }
+            if let Some(oldest) = store.order.pop_front() {
+                store.entries.remove(&oldest);
+            }
+        }
+
+        if store.entries.contains_key(&key) {
+            store.order.retain(|k| k != &key);
+        }
+        store.entries.insert(
+            key.clone(),
+            CacheEntry {
+                response,
+                stored_at: now,
+            },
+        );
+        store.order.push_back(key);
+
+        while store.entries.len() > config.max_entries {
+            match store.order.pop_front() {
+                Some(oldest) => {
+                    store.entries.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut store = self.store.lock();
+        store.entries.clear();
+        store.order.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn response(body: &'static str) -> CachedResponse {
+        CachedResponse {
+            body: Bytes::from_static(body.as_bytes()),
+            content_type: HeaderValue::from_static("application/json"),
+            mapped_model: None,
+        }
+    }
+
+    fn config(max_entries: usize) -> ResponseCacheConfig {
+        ResponseCacheConfig {
+            enabled: true,
+            ttl_secs: 60,
+            max_entries,
+            max_entry_bytes: 1024,
+        }
+    }
+
+    #[test]
+    fn only_zero_temperature_non_stream_requests_are_cacheable() {
+        let headers = HeaderMap::new();
+        let body = json!({"model": "gpt-4o", "temperature": 0, "messages": [{"role": "user", "content": "hi"}]});
+        let key = cache_key("/v1/chat/completions", None, &headers, &body);
+        assert!(key.is_some());
+
+        // stream / metadata 不影响缓存键
+        let mut with_metadata = body.clone();
+        with_metadata["metadata"] = json!({"user_id": "u1"});
+        assert_eq!(
+            cache_key("/v1/chat/completions", None, &headers, &with_metadata),
+            key
+        );
+
+        // 不同客户端 Key 互不共享
+        assert_ne!(
+            cache_key("/v1/chat/completions", Some("k1"), &headers, &body),
+            key
+        );
+
+        // beta 请求头不同的请求互不共享
+        let mut beta = HeaderMap::new();
+        beta.insert(
+            "anthropic-beta",
+            HeaderValue::from_static("context-1m-2025-08-07"),
+        );
+        assert_ne!(cache_key("/v1/chat/completions", None, &beta, &body), key);
+
+        let mut streaming = body.clone();
+        streaming["stream"] = json!(true);
+        assert_eq!(
+            cache_key("/v1/chat/completions", None, &headers, &streaming),
+            None
+        );
+
+        let mut sampled = body.clone();
+        sampled["temperature"] = json!(0.7);
+        assert_eq!(
+            cache_key("/v1/chat/completions", None, &headers, &sampled),
+            None
+        );
+
+        let gemini = json!({"contents": [], "generationConfig": {"temperature": 0.0}});
+        assert!(cache_key(
+            "/v1beta/models/gemini-2.5-pro:generateContent",
+            None,
+            &headers,
+            &gemini
+        )
+        .is_some());
+        assert_eq!(
+            cache_key(
+                "/v1beta/models/gemini-2.5-pro:streamGenerateContent",
+                None,
+                &headers,
+                &gemini
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn entries_expire_after_ttl() {
+        let cache = ResponseCache::new();
+        let start = Instant::now();
+        let ttl = Duration::from_secs(60);
+        cache.insert_at("a".to_string(), response("{}"), &config(8), start);
+        assert!(cache
+            .get_at("a", ttl, start + Duration::from_secs(59))
+            .is_some());
+        assert!(cache
+            .get_at("a", ttl, start + Duration::from_secs(60))
+            .is_none());
+        assert!(cache.store.lock().entries.is_empty());
+    }
+
+    #[test]
+    fn oldest_entries_are_evicted_and_large_bodies_skipped() {
+        let cache = ResponseCache::new();
+        let now = Instant::now();
+        let ttl = Duration::from_secs(60);
+        for key in ["a", "b", "c"] {
+            cache.insert_at(key.to_string(), response("{}"), &config(2), now);
+        }
+        assert!(cache.get_at("a", ttl, now).is_none());
+        assert!(cache.get_at("b", ttl, now).is_some());
+        assert!(cache.get_at("c", ttl, now).is_some());
+
+        let mut small = config(8);
+        small.max_entry_bytes = 1;
+        cache.insert_at("d".to_string(), response("{}"), &small, now);
+        assert!(cache.get_at("d", ttl, now).is_none());
+    }
+}
@@ -1,10 +1,69 @@
-use crate::proxy::config::{ProxyAuthMode, ProxyConfig};
+use crate::proxy::config::{ClientRateLimitConfig, ProxyApiKey, ProxyAuthMode, ProxyConfig};
 
 #[derive(Debug, Clone)]
 pub struct ProxySecurityConfig {
     pub auth_mode: ProxyAuthMode,
     pub api_key: String,
     pub allow_lan_access: bool,
+    /// 未吊销的客户端 API Key
+    pub api_keys: Vec<ProxyApiKey>,
+    pub client_rate_limit: ClientRateLimitConfig,
+}
+
+/// 认证通过的调用方，写入请求扩展供监控与用量统计归属
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthenticatedKey {
+    /// 客户端 API Key 的 id；使用全局 api_key 时为 None
+    pub key_id: Option<String>,
+    /// 生效的限流额度 (0 表示不限制)，全局 api_key 不限流
+    pub requests_per_minute: u32,
+    pub tokens_per_minute: u32,
+}
+
+impl AuthenticatedKey {
+    fn global() -> Self {
+        Self {
+            key_id: None,
+            requests_per_minute: 0,
+            tokens_per_minute: 0,
+        }
+    }
+}
+
+/// 认证结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyCheck {
+    Allowed(AuthenticatedKey),
+    /// Key 有效但无权访问该协议
+    OutOfScope,
+    Invalid,
+}
+
+/// 任何有效 Key 都可访问的路径，不受 scope 限制
+const SCOPE_FREE_PATHS: &[&str] = &["/healthz"];
+
+/// 请求路径所属的协议 scope，不属于任何协议的路径 (如 /internal/*) 返回空列表，
+/// 受 scope 限制的 Key 默认无权访问。
+/// 模型列表 `/v1/models` 同时服务 OpenAI 与 Anthropic 客户端，两种 scope 均可访问。
+pub fn scopes_for_path(path: &str) -> &'static [&'static str] {
+    if path == "/v1/models" || path == "/v1/models/" {
+        &["openai", "anthropic"]
+    } else if path.starts_with("/v1/messages") || path.starts_with("/v1/models/claude") {
+        &["anthropic"]
+    } else if path.starts_with("/v1beta/") || path == "/v1beta" {
+        &["gemini"]
+    } else if path.starts_with("/mcp/") {
+        &["mcp"]
+    } else if path.starts_with("/v1/") {
+        &["openai"]
+    } else {
+        &[]
+    }
+}
+
+/// 定长比较，耗时不随首个不同字节的位置变化
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
 }
 
 impl ProxySecurityConfig {
@@ -12,14 +71,22 @@ impl ProxySecurityConfig {
         Self {
             auth_mode: config.auth_mode.clone(),
             api_key: config.api_key.clone(),
-            allow_lan_access: config.allow_lan_access,
+            allow_lan_access: config.exposes_beyond_localhost(),
+            api_keys: config
+                .api_keys
+                .iter()
+                .filter(|k| !k.revoked)
+                .cloned()
+                .collect(),
+            client_rate_limit: config.client_rate_limit.clone(),
         }
     }
 
     pub fn effective_auth_mode(&self) -> ProxyAuthMode {
         match self.auth_mode {
             ProxyAuthMode::Auto => {
-                if self.allow_lan_access {
+                // 一旦创建了客户端 Key，本机访问同样需要认证
+                if self.allow_lan_access || !self.api_keys.is_empty() {
                     ProxyAuthMode::AllExceptHealth
                 } else {
                     ProxyAuthMode::Off
@@ -28,6 +95,41 @@ impl ProxySecurityConfig {
             ref other => other.clone(),
         }
     }
+
+    /// 校验请求携带的 Key 是否可访问给定路径
+    pub fn check_key(&self, presented: Option<&str>, path: &str) -> KeyCheck {
+        let Some(presented) = presented.filter(|k| !k.is_empty()) else {
+            return KeyCheck::Invalid;
+        };
+        if !self.api_key.is_empty()
+            && constant_time_eq(presented.as_bytes(), self.api_key.as_bytes())
+        {
+            return KeyCheck::Allowed(AuthenticatedKey::global());
+        }
+        // 只保存了哈希，比较哈希值即可 (长度固定)
+        let presented_hash = ProxyApiKey::hash_key(presented);
+        let matched = self.api_keys.iter().find(|k| {
+            !k.key_hash.is_empty()
+                && constant_time_eq(k.key_hash.as_bytes(), presented_hash.as_bytes())
+        });
+        match matched {
+            Some(key)
+                if SCOPE_FREE_PATHS.contains(&path) || key.allows_scopes(scopes_for_path(path)) =>
+            {
+                KeyCheck::Allowed(AuthenticatedKey {
+                    key_id: Some(key.id.clone()),
+                    requests_per_minute: key
+                        .requests_per_minute
+                        .unwrap_or(self.client_rate_limit.requests_per_minute),
+                    tokens_per_minute: key
+                        .tokens_per_minute
+                        .unwrap_or(self.client_rate_limit.tokens_per_minute),
+                })
+            }
+            Some(_) => KeyCheck::OutOfScope,
+            None => KeyCheck::Invalid,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -40,6 +142,8 @@ mod tests {
             auth_mode: ProxyAuthMode::Auto,
             api_key: "sk-test".to_string(),
             allow_lan_access: false,
+            api_keys: Vec::new(),
+            client_rate_limit: ClientRateLimitConfig::default(),
         };
         assert!(matches!(s.effective_auth_mode(), ProxyAuthMode::Off));
     }
@@ -50,11 +154,128 @@ mod tests {
             auth_mode: ProxyAuthMode::Auto,
             api_key: "sk-test".to_string(),
             allow_lan_access: true,
+            api_keys: Vec::new(),
+            client_rate_limit: ClientRateLimitConfig::default(),
         };
         assert!(matches!(
             s.effective_auth_mode(),
             ProxyAuthMode::AllExceptHealth
         ));
     }
-}
 
+    fn managed_key(id: &str, key: &str, scopes: &[&str]) -> ProxyApiKey {
+        ProxyApiKey {
+            id: id.to_string(),
+            name: id.to_string(),
+            key_hash: ProxyApiKey::hash_key(key),
+            key_prefix: ProxyApiKey::key_prefix_of(key),
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+            created_at: 0,
+            revoked: false,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+        }
+    }
+
+    #[test]
+    fn auto_mode_requires_auth_locally_once_keys_exist() {
+        let s = ProxySecurityConfig {
+            auth_mode: ProxyAuthMode::Auto,
+            api_key: "sk-test".to_string(),
+            allow_lan_access: false,
+            api_keys: vec![managed_key("k1", "sk-client", &[])],
+            client_rate_limit: ClientRateLimitConfig::default(),
+        };
+        assert!(matches!(
+            s.effective_auth_mode(),
+            ProxyAuthMode::AllExceptHealth
+        ));
+    }
+
+    #[test]
+    fn revoked_keys_are_dropped() {
+        let mut revoked = managed_key("k1", "sk-client", &[]);
+        revoked.revoked = true;
+        let config = ProxyConfig {
+            api_keys: vec![revoked],
+            ..ProxyConfig::default()
+        };
+        let s = ProxySecurityConfig::from_proxy_config(&config);
+        assert_eq!(s.check_key(Some("sk-client"), "/v1/chat/completions"), KeyCheck::Invalid);
+    }
+
+    #[test]
+    fn managed_keys_are_scoped_by_protocol() {
+        let s = ProxySecurityConfig {
+            auth_mode: ProxyAuthMode::Strict,
+            api_key: "sk-test".to_string(),
+            allow_lan_access: false,
+            api_keys: vec![managed_key("k1", "sk-client", &["anthropic"])],
+            client_rate_limit: ClientRateLimitConfig {
+                requests_per_minute: 60,
+                tokens_per_minute: 0,
+            },
+        };
+        assert_eq!(
+            s.check_key(Some("sk-client"), "/v1/messages"),
+            KeyCheck::Allowed(AuthenticatedKey {
+                key_id: Some("k1".to_string()),
+                requests_per_minute: 60,
+                tokens_per_minute: 0,
+            })
+        );
+        assert_eq!(
+            s.check_key(Some("sk-client"), "/v1/chat/completions"),
+            KeyCheck::OutOfScope
+        );
+        assert_eq!(
+            s.check_key(Some("sk-test"), "/v1/chat/completions"),
+            KeyCheck::Allowed(AuthenticatedKey::global())
+        );
+        assert_eq!(s.check_key(None, "/v1/messages"), KeyCheck::Invalid);
+        assert!(matches!(
+            s.check_key(Some("sk-client"), "/v1/models"),
+            KeyCheck::Allowed(_)
+        ));
+        assert_eq!(s.check_key(Some("sk-clienT"), "/v1/messages"), KeyCheck::Invalid);
+    }
+
+    #[test]
+    fn model_listing_is_shared_by_openai_and_anthropic() {
+        assert_eq!(scopes_for_path("/v1/models"), &["openai", "anthropic"]);
+        assert_eq!(scopes_for_path("/v1/models/claude"), &["anthropic"]);
+        assert_eq!(scopes_for_path("/v1/chat/completions"), &["openai"]);
+        assert!(scopes_for_path("/healthz").is_empty());
+    }
+
+    #[test]
+    fn scoped_keys_are_denied_on_unknown_paths() {
+        let s = ProxySecurityConfig {
+            auth_mode: ProxyAuthMode::Strict,
+            api_key: String::new(),
+            allow_lan_access: false,
+            api_keys: vec![
+                managed_key("scoped", "sk-scoped", &["openai", "anthropic"]),
+                managed_key("all", "sk-all", &["*"]),
+                managed_key("unscoped", "sk-unscoped", &[]),
+            ],
+            client_rate_limit: ClientRateLimitConfig::default(),
+        };
+        assert_eq!(
+            s.check_key(Some("sk-scoped"), "/internal/warmup"),
+            KeyCheck::OutOfScope
+        );
+        assert!(matches!(
+            s.check_key(Some("sk-all"), "/internal/warmup"),
+            KeyCheck::Allowed(_)
+        ));
+        assert!(matches!(
+            s.check_key(Some("sk-unscoped"), "/internal/warmup"),
+            KeyCheck::Allowed(_)
+        ));
+        assert!(matches!(
+            s.check_key(Some("sk-scoped"), "/healthz"),
+            KeyCheck::Allowed(_)
+        ));
+    }
+}
@@ -6,12 +6,16 @@ use axum::{
     routing::{any, get, post},
     Router,
 };
+use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::sync::oneshot;
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinSet;
 use tower_http::trace::TraceLayer;
 use tracing::{debug, error};
 use tokio::sync::RwLock;
-use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::watch;
 
 /// Axum 应用状态
 #[derive(Clone)]
@@ -30,16 +34,196 @@ pub struct AppState {
     pub zai_vision_mcp: Arc<crate::proxy::zai_vision_mcp::ZaiVisionMcpState>,
     pub monitor: Arc<crate::proxy::monitor::ProxyMonitor>,
     pub experimental: Arc<RwLock<crate::proxy::config::ExperimentalConfig>>,
+    pub client_limiter: Arc<crate::proxy::client_rate_limit::ClientRateLimiter>,
+}
+
+/// 连接计数守卫：连接任务结束时自动递减
+struct ConnectionGuard(Arc<AtomicUsize>);
+
+impl ConnectionGuard {
+    fn new(counter: Arc<AtomicUsize>) -> Self {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Self(counter)
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// 重新绑定监听地址的请求 (新地址, 新地址对应的 TLS acceptor, 结果回传)
+type RebindRequest = (
+    Vec<SocketAddr>,
+    Option<tokio_rustls::TlsAcceptor>,
+    oneshot::Sender<Result<(), String>>,
+);
+
+/// 接收循环共享的上下文
+#[derive(Clone)]
+struct ListenerContext {
+    app: Router,
+    active_connections: Arc<AtomicUsize>,
+    drain_rx: watch::Receiver<bool>,
+    /// 强制关闭所有连接 (排空超时或紧急停止)
+    abort_rx: watch::Receiver<bool>,
+    /// 启用 HTTPS 时的 TLS acceptor
+    tls: Option<tokio_rustls::TlsAcceptor>,
+}
+
+/// 一组正在运行的接收循环
+struct Listeners {
+    stop_tx: watch::Sender<bool>,
+    tasks: JoinSet<()>,
+}
+
+impl Listeners {
+    fn spawn(listeners: Vec<TcpListener>, ctx: &ListenerContext) -> Self {
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let mut tasks = JoinSet::new();
+        for listener in listeners {
+            tasks.spawn(accept_loop(listener, ctx.clone(), stop_rx.clone()));
+        }
+        Self { stop_tx, tasks }
+    }
+
+    /// 停止接收新连接并释放监听端口 (已建立的连接不受影响)
+    async fn shutdown(mut self) {
+        let _ = self.stop_tx.send(true);
+        while self.tasks.join_next().await.is_some() {}
+    }
+}
+
+/// 绑定所有监听地址，任一地址失败时整体失败 (已绑定的随之释放)
+async fn bind_listeners(addrs: &[SocketAddr]) -> Result<Vec<TcpListener>, String> {
+    let mut listeners = Vec::with_capacity(addrs.len());
+    for addr in addrs {
+        let listener = TcpListener::bind(addr).await.map_err(|e| {
+            if e.kind() == std::io::ErrorKind::AddrInUse {
+                format!("地址 {} 已被占用 (端口被其他程序或另一个监听地址使用)", addr)
+            } else {
+                format!("地址 {} 绑定失败: {}", addr, e)
+            }
+        })?;
+        tracing::info!("反代服务器监听 {}", addr);
+        listeners.push(listener);
+    }
+    Ok(listeners)
+}
+
+/// 强制关闭后等待连接任务结束的最长时间
+const CONNECTION_ABORT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// TLS 握手的最长时间，防止半开连接长期占用
+const TLS_HANDSHAKE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// 单个监听地址的接收循环，收到停止信号后退出
+async fn accept_loop(listener: TcpListener, ctx: ListenerContext, mut stop_rx: watch::Receiver<bool>) {
+    loop {
+        tokio::select! {
+            res = listener.accept() => {
+                match res {
+                    Ok((stream, _)) => {
+                        let guard = ConnectionGuard::new(ctx.active_connections.clone());
+                        let ctx = ctx.clone();
+                        let mut abort_rx = ctx.abort_rx.clone();
+
+                        tokio::task::spawn(async move {
+                            let _guard = guard;
+                            let serve = async move {
+                                match ctx.tls.clone() {
+                                    Some(acceptor) => {
+                                        match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                                            Ok(Ok(stream)) => {
+                                                // 双向 TLS 时记录客户端证书身份，供鉴权中间件使用
+                                                let identity = crate::proxy::tls::ClientIdentity::from_connection(stream.get_ref().1);
+                                                serve_connection(stream, ctx, identity).await
+                                            }
+                                            Ok(Err(e)) => debug!("TLS 握手失败: {}", e),
+                                            Err(_) => debug!("TLS 握手超时"),
+                                        }
+                                    }
+                                    None => serve_connection(stream, ctx, None).await,
+                                }
+                            };
+                            tokio::select! {
+                                _ = serve => {}
+                                // 强制关闭：丢弃连接 future 即断开连接 (服务器实例已释放时不触发)
+                                _ = async {
+                                    if abort_rx.wait_for(|aborted| *aborted).await.is_err() {
+                                        std::future::pending::<()>().await;
+                                    }
+                                } => {}
+                            }
+                        });
+                    }
+                    Err(e) => {
+                        error!("接收连接失败: {:?}", e);
+                    }
+                }
+            }
+            _ = stop_rx.changed() => break,
+        }
+    }
+}
+
+/// 在一条连接上处理 HTTP/1.1 请求
+async fn serve_connection<I>(
+    io: I,
+    ctx: ListenerContext,
+    identity: Option<crate::proxy::tls::ClientIdentity>,
+) where
+    I: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    use hyper::server::conn::http1;
+    use hyper_util::rt::TokioIo;
+    use hyper_util::service::TowerToHyperService;
+    use tower::ServiceExt;
+
+    let io = TokioIo::new(io);
+    let app = ctx.app.map_request(move |mut req: axum::http::Request<hyper::body::Incoming>| {
+        if let Some(identity) = &identity {
+            req.extensions_mut().insert(identity.clone());
+        }
+        req
+    });
+    let service = TowerToHyperService::new(app);
+    let mut drain_rx = ctx.drain_rx;
+    let conn = http1::Builder::new()
+        .serve_connection(io, service)
+        .with_upgrades(); // 支持 WebSocket (如果以后需要)
+    tokio::pin!(conn);
+
+    let result = tokio::select! {
+        res = conn.as_mut() => res,
+        _ = drain_rx.changed() => {
+            // 进入排空阶段：处理完当前请求后关闭 keep-alive 连接
+            conn.as_mut().graceful_shutdown();
+            conn.await
+        }
+    };
+    if let Err(err) = result {
+        debug!("连接处理结束或出错: {:?}", err);
+    }
 }
 
 /// Axum 服务器实例
 pub struct AxumServer {
     shutdown_tx: Option<oneshot::Sender<()>>,
+    rebind_tx: mpsc::Sender<RebindRequest>,
+    /// 通知已建立的连接在当前请求完成后关闭
+    drain_tx: watch::Sender<bool>,
+    /// 通知所有连接立即关闭
+    abort_tx: watch::Sender<bool>,
+    active_connections: Arc<AtomicUsize>,
     custom_mapping: Arc<tokio::sync::RwLock<std::collections::HashMap<String, String>>>,
     proxy_state: Arc<tokio::sync::RwLock<crate::proxy::config::UpstreamProxyConfig>>,
+    upstream: Arc<crate::proxy::upstream::client::UpstreamClient>,
     security_state: Arc<RwLock<crate::proxy::ProxySecurityConfig>>,
     zai_state: Arc<RwLock<crate::proxy::ZaiConfig>>,
     experimental: Arc<RwLock<crate::proxy::config::ExperimentalConfig>>,
+    client_limiter: Arc<crate::proxy::client_rate_limit::ClientRateLimiter>,
 }
 
 impl AxumServer {
@@ -53,6 +237,7 @@ impl AxumServer {
 
     /// 更新代理配置
     pub async fn update_proxy(&self, new_config: crate::proxy::config::UpstreamProxyConfig) {
+        self.upstream.set_proxy(new_config.clone());
         let mut proxy = self.proxy_state.write().await;
         *proxy = new_config;
         tracing::info!("上游代理配置已热更新");
@@ -61,6 +246,9 @@ impl AxumServer {
     pub async fn update_security(&self, config: &crate::proxy::config::ProxyConfig) {
         let mut sec = self.security_state.write().await;
         *sec = crate::proxy::ProxySecurityConfig::from_proxy_config(config);
+        // 吊销或删除的 Key 不再需要限流状态
+        self.client_limiter
+            .retain_keys(|key_id| sec.api_keys.iter().any(|k| k.id == key_id));
         tracing::info!("反代服务安全配置已热更新");
     }
 
@@ -77,8 +265,7 @@ impl AxumServer {
     }
     /// 启动 Axum 服务器
     pub async fn start(
-        host: String,
-        port: u16,
+        listen_addrs: Vec<SocketAddr>,
         token_manager: Arc<TokenManager>,
         custom_mapping: std::collections::HashMap<String, String>,
         _request_timeout: u64,
@@ -87,6 +274,8 @@ impl AxumServer {
         zai_config: crate::proxy::ZaiConfig,
         monitor: Arc<crate::proxy::monitor::ProxyMonitor>,
         experimental_config: crate::proxy::config::ExperimentalConfig,
+        cors_config: crate::proxy::config::CorsConfig,
+        tls: Option<tokio_rustls::TlsAcceptor>,
 
     ) -> Result<(Self, tokio::task::JoinHandle<()>), String> {
         let custom_mapping_state = Arc::new(tokio::sync::RwLock::new(custom_mapping));
@@ -97,6 +286,10 @@ impl AxumServer {
 	        let zai_vision_mcp_state =
 	            Arc::new(crate::proxy::zai_vision_mcp::ZaiVisionMcpState::new());
 	        let experimental_state = Arc::new(RwLock::new(experimental_config));
+	        let upstream = Arc::new(crate::proxy::upstream::client::UpstreamClient::new(
+	            Some(upstream_proxy.clone()),
+	        ));
+        let client_limiter = Arc::new(crate::proxy::client_rate_limit::ClientRateLimiter::new());
 
 	        let state = AppState {
 	            token_manager: token_manager.clone(),
@@ -106,14 +299,13 @@ impl AxumServer {
                 std::collections::HashMap::new(),
             )),
             upstream_proxy: proxy_state.clone(),
-            upstream: Arc::new(crate::proxy::upstream::client::UpstreamClient::new(Some(
-                upstream_proxy.clone(),
-            ))),
+            upstream: upstream.clone(),
             zai: zai_state.clone(),
             provider_rr: provider_rr.clone(),
             zai_vision_mcp: zai_vision_mcp_state,
             monitor: monitor.clone(),
             experimental: experimental_state.clone(),
+            client_limiter: client_limiter.clone(),
         };
 
 
@@ -184,65 +376,98 @@ impl AxumServer {
             .route("/v1/api/event_logging", post(silent_ok_handler))
             .route("/healthz", get(health_check_handler))
             .layer(DefaultBodyLimit::max(100 * 1024 * 1024))
+            .layer(axum::middleware::from_fn(
+                crate::proxy::middleware::concurrency_middleware,
+            ))
+            .layer(axum::middleware::from_fn(
+                crate::proxy::middleware::diagnostics_middleware,
+            ))
+            .layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                crate::proxy::middleware::client_rate_limit_middleware,
+            ))
+            // 缓存在限流之外：命中缓存的请求不消耗客户端 Key 的请求数与 tokens 额度
+            .layer(axum::middleware::from_fn(
+                crate::proxy::middleware::response_cache_middleware,
+            ))
             .layer(axum::middleware::from_fn_with_state(state.clone(), crate::proxy::middleware::monitor::monitor_middleware))
             .layer(TraceLayer::new_for_http())
             .layer(axum::middleware::from_fn_with_state(
                 security_state.clone(),
                 crate::proxy::middleware::auth_middleware,
             ))
-            .layer(crate::proxy::middleware::cors_layer())
+            .layer(crate::proxy::middleware::cors_layer(&cors_config))
             .with_state(state);
 
         // 绑定地址
-        let addr = format!("{}:{}", host, port);
-        let listener = tokio::net::TcpListener::bind(&addr)
-            .await
-            .map_err(|e| format!("地址 {} 绑定失败: {}", addr, e))?;
-
-        tracing::info!("反代服务器启动在 http://{}", addr);
+        let listeners = bind_listeners(&listen_addrs).await?;
 
         // 创建关闭通道
         let (shutdown_tx, mut shutdown_rx) = oneshot::channel::<()>();
+        let (rebind_tx, mut rebind_rx) = mpsc::channel::<RebindRequest>(1);
+        let (drain_tx, drain_rx) = watch::channel(false);
+        let (abort_tx, abort_rx) = watch::channel(false);
+        let active_connections = Arc::new(AtomicUsize::new(0));
 
         let server_instance = Self {
             shutdown_tx: Some(shutdown_tx),
+            rebind_tx,
+            drain_tx,
+            abort_tx,
+            active_connections: active_connections.clone(),
             custom_mapping: custom_mapping_state.clone(),
             proxy_state,
+            upstream,
             security_state,
             zai_state,
             experimental: experimental_state.clone(),
+            client_limiter,
+        };
+
+        let mut ctx = ListenerContext {
+            app,
+            active_connections,
+            drain_rx,
+            abort_rx,
+            tls,
         };
 
         // 在新任务中启动服务器
         let handle = tokio::spawn(async move {
-            use hyper::server::conn::http1;
-            use hyper_util::rt::TokioIo;
-            use hyper_util::service::TowerToHyperService;
+            let mut current_addrs = listen_addrs;
+            let mut running = Listeners::spawn(listeners, &ctx);
 
             loop {
                 tokio::select! {
-                    res = listener.accept() => {
-                        match res {
-                            Ok((stream, _)) => {
-                                let io = TokioIo::new(stream);
-                                let service = TowerToHyperService::new(app.clone());
-
-                                tokio::task::spawn(async move {
-                                    if let Err(err) = http1::Builder::new()
-                                        .serve_connection(io, service)
-                                        .with_upgrades() // 支持 WebSocket (如果以后需要)
-                                        .await
-                                    {
-                                        debug!("连接处理结束或出错: {:?}", err);
-                                    }
-                                });
+                    Some((addrs, tls, reply)) = rebind_rx.recv() => {
+                        // 先释放旧端口，新地址可能与旧地址相同
+                        running.shutdown().await;
+                        let result = match bind_listeners(&addrs).await {
+                            Ok(listeners) => {
+                                // 仅在绑定成功后切换证书，恢复原地址时沿用原证书
+                                ctx.tls = tls;
+                                running = Listeners::spawn(listeners, &ctx);
+                                current_addrs = addrs;
+                                Ok(())
                             }
                             Err(e) => {
-                                error!("接收连接失败: {:?}", e);
+                                tracing::error!("重新绑定监听地址失败: {}，恢复原监听地址", e);
+                                match bind_listeners(&current_addrs).await {
+                                    Ok(listeners) => {
+                                        running = Listeners::spawn(listeners, &ctx);
+                                        Err(e)
+                                    }
+                                    Err(restore_err) => {
+                                        running = Listeners::spawn(Vec::new(), &ctx);
+                                        Err(format!("{}；恢复原监听地址也失败: {}", e, restore_err))
+                                    }
+                                }
                             }
-                        }
+                        };
+                        let _ = reply.send(result);
                     }
                     _ = &mut shutdown_rx => {
+                        running.shutdown().await;
                         tracing::info!("反代服务器停止监听");
                         break;
                     }
@@ -253,12 +478,79 @@ impl AxumServer {
         Ok((server_instance, handle))
     }
 
+    /// 重新绑定监听地址并换用对应的 TLS acceptor，已建立的连接不受影响；
+    /// 新地址绑定失败时恢复原地址与原证书
+    pub async fn rebind(
+        &self,
+        listen_addrs: Vec<SocketAddr>,
+        tls: Option<tokio_rustls::TlsAcceptor>,
+    ) -> Result<(), String> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.rebind_tx
+            .send((listen_addrs, tls, reply_tx))
+            .await
+            .map_err(|_| "反代服务器已停止".to_string())?;
+        reply_rx
+            .await
+            .map_err(|_| "反代服务器已停止".to_string())?
+    }
+
     /// 停止服务器
     pub fn stop(mut self) {
         if let Some(tx) = self.shutdown_tx.take() {
             let _ = tx.send(());
         }
     }
+
+    /// 当前活动连接数
+    pub fn active_connections(&self) -> usize {
+        self.active_connections.load(Ordering::SeqCst)
+    }
+
+    /// 立即停止：停止接收新连接并断开所有已建立的连接。返回被断开的连接数
+    pub async fn abort(mut self) -> usize {
+        if let Some(tx) = self.shutdown_tx.take() {
+            let _ = tx.send(());
+        }
+        self.close_connections().await
+    }
+
+    /// 优雅停止：停止接收新连接，通知已有连接处理完当前请求后关闭，
+    /// 最多等待 timeout，超时后强制断开剩余连接。返回被强制断开的连接数
+    pub async fn drain(mut self, timeout: std::time::Duration) -> usize {
+        if let Some(tx) = self.shutdown_tx.take() {
+            let _ = tx.send(());
+        }
+        let _ = self.drain_tx.send(true);
+
+        if self.wait_for_connections(timeout).await == 0 {
+            return 0;
+        }
+        self.close_connections().await
+    }
+
+    /// 断开所有连接并等待连接任务结束，返回断开前的连接数
+    async fn close_connections(&self) -> usize {
+        let open = self.active_connections();
+        let _ = self.abort_tx.send(true);
+        let remaining = self.wait_for_connections(CONNECTION_ABORT_TIMEOUT).await;
+        if remaining > 0 {
+            tracing::warn!("{} 个连接在强制关闭后仍未结束", remaining);
+        }
+        open
+    }
+
+    /// 等待所有连接结束，最多等待 timeout。返回仍未结束的连接数
+    async fn wait_for_connections(&self, timeout: std::time::Duration) -> usize {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let remaining = self.active_connections();
+            if remaining == 0 || tokio::time::Instant::now() >= deadline {
+                return remaining;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+    }
 }
 
 // ===== API 处理器 (旧代码已移除，由 src/proxy/handlers/* 接管) =====
@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Mutex, OnceLock};
 use std::time::{Duration, SystemTime};
@@ -31,6 +32,61 @@ impl<T> CacheEntry<T> {
     }
 }
 
+/// Serializable copy of one cache layer: (key, value, stored_at unix secs)
+pub type SignatureLayerSnapshot = Vec<(String, String, u64)>;
+
+/// Serializable copy of all cache layers, used to survive proxy restarts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SignatureCacheSnapshot {
+    pub tool_signatures: SignatureLayerSnapshot,
+    pub thinking_families: SignatureLayerSnapshot,
+    pub session_signatures: SignatureLayerSnapshot,
+}
+
+fn snapshot_layer(layer: &Mutex<HashMap<String, CacheEntry<String>>>) -> SignatureLayerSnapshot {
+    let Ok(cache) = layer.lock() else {
+        return Vec::new();
+    };
+    cache
+        .iter()
+        .filter(|(_, entry)| !entry.is_expired())
+        .map(|(key, entry)| {
+            let stored_at = entry
+                .timestamp
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            (key.clone(), entry.data.clone(), stored_at)
+        })
+        .collect()
+}
+
+fn restore_layer(
+    layer: &Mutex<HashMap<String, CacheEntry<String>>>,
+    items: SignatureLayerSnapshot,
+    limit: usize,
+) -> usize {
+    let Ok(mut cache) = layer.lock() else {
+        return 0;
+    };
+    let mut restored = 0;
+    for (key, data, stored_at) in items {
+        if cache.len() >= limit {
+            break;
+        }
+        let entry = CacheEntry {
+            data,
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(stored_at),
+        };
+        // Never overwrite live entries with older persisted ones
+        if !entry.is_expired() && !cache.contains_key(&key) {
+            cache.insert(key, entry);
+            restored += 1;
+        }
+    }
+    restored
+}
+
 /// Triple-layer signature cache to handle:
 /// 1. Signature recovery for tool calls (when clients strip them)
 /// 2. Cross-model compatibility checks (preventing Claude signatures on Gemini models)
@@ -207,6 +263,22 @@ impl SignatureCache {
         None
     }
 
+    /// Copy all non-expired entries for persistence
+    pub fn snapshot(&self) -> SignatureCacheSnapshot {
+        SignatureCacheSnapshot {
+            tool_signatures: snapshot_layer(&self.tool_signatures),
+            thinking_families: snapshot_layer(&self.thinking_families),
+            session_signatures: snapshot_layer(&self.session_signatures),
+        }
+    }
+
+    /// Load persisted entries (expired ones are skipped). Returns the number restored.
+    pub fn restore(&self, snapshot: SignatureCacheSnapshot) -> usize {
+        restore_layer(&self.tool_signatures, snapshot.tool_signatures, TOOL_CACHE_LIMIT)
+            + restore_layer(&self.thinking_families, snapshot.thinking_families, FAMILY_CACHE_LIMIT)
+            + restore_layer(&self.session_signatures, snapshot.session_signatures, SESSION_CACHE_LIMIT)
+    }
+
     /// Clear all caches (for testing or manual reset)
     #[allow(dead_code)] // Used in tests
     pub fn clear(&self) {
@@ -25,6 +25,8 @@ pub struct StickySessionConfig {
     pub mode: SchedulingMode,
     /// 缓存优先模式下的最大等待时间 (秒)
     pub max_wait_seconds: u64,
+    /// 账号熔断 (连续 5xx / 网络错误后暂时移出调度)
+    pub circuit_breaker: crate::proxy::circuit_breaker::CircuitBreakerConfig,
 }
 
 impl Default for StickySessionConfig {
@@ -32,6 +34,7 @@ impl Default for StickySessionConfig {
         Self {
             mode: SchedulingMode::Balance,
             max_wait_seconds: 60,
+            circuit_breaker: Default::default(),
         }
     }
 }
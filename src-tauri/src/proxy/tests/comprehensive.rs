@@ -36,6 +36,7 @@ mod tests {
             }),
             metadata: None,
             output_config: None,
+            tool_choice: None,
         };
 
         // 2. 执行转换
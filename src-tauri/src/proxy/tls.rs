@@ -0,0 +1,461 @@
+// 反代 HTTPS
+//
+// 使用用户提供的 PEM 证书链与私钥；未提供时在数据目录下生成自签名证书并复用，
+// 证书未覆盖当前监听地址或临近过期时重新生成。客户端需要信任该证书 (或关闭证书校验)。
+//
+// 可选的 mTLS 模式：只接受持有本地 CA 签发的客户端证书的连接。本地 CA 在首次签发客户端证书
+// 或启用 mTLS 时生成，保存在同一目录；删除 CA 文件即可使所有已签发的客户端证书失效。
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use serde::Serialize;
+use std::io::BufReader;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio_rustls::TlsAcceptor;
+
+use crate::proxy::config::ProxyTlsConfig;
+
+const TLS_DIR: &str = "proxy_tls";
+const CERT_FILE: &str = "cert.pem";
+const KEY_FILE: &str = "key.pem";
+const CLIENT_CA_CERT_FILE: &str = "client_ca.pem";
+const CLIENT_CA_KEY_FILE: &str = "client_ca.key.pem";
+/// 本地 CA 有效期
+const CA_VALIDITY_DAYS: i64 = 3650;
+/// 客户端证书默认有效期
+pub const DEFAULT_CLIENT_CERT_DAYS: u32 = 365;
+/// 主流系统与浏览器接受的证书最长有效期
+const VALIDITY_DAYS: i64 = 825;
+/// 剩余有效期不足时重新生成
+const RENEW_BEFORE_DAYS: i64 = 30;
+
+/// 自签名证书所在目录
+pub fn self_signed_dir() -> Result<PathBuf, String> {
+    Ok(crate::modules::account::get_data_dir()?.join(TLS_DIR))
+}
+
+/// 按配置创建 TLS acceptor，未启用 HTTPS 时返回 None
+pub fn build_acceptor(
+    config: &ProxyTlsConfig,
+    listen_addrs: &[SocketAddr],
+) -> Result<Option<TlsAcceptor>, String> {
+    if !config.enabled {
+        if config.require_client_cert {
+            return Err("客户端证书认证 (mTLS) 需要先启用 HTTPS".to_string());
+        }
+        return Ok(None);
+    }
+
+    let cert_path = config.cert_path.trim();
+    let key_path = config.key_path.trim();
+    let (certs, key) = match (cert_path.is_empty(), key_path.is_empty()) {
+        (false, false) => load_pem(Path::new(cert_path), Path::new(key_path))?,
+        (true, true) => {
+            let dir = self_signed_dir()?;
+            let names = certificate_names(config, listen_addrs);
+            ensure_self_signed(&dir, &names)?;
+            tracing::info!(
+                "反代 HTTPS 使用自签名证书: {}",
+                dir.join(CERT_FILE).display()
+            );
+            load_pem(&dir.join(CERT_FILE), &dir.join(KEY_FILE))?
+        }
+        _ => return Err("HTTPS 证书与私钥路径需同时设置 (或同时留空以使用自签名证书)".to_string()),
+    };
+
+    let builder = rustls::ServerConfig::builder();
+    let builder = if config.require_client_cert {
+        let ca = load_or_create_client_ca(&self_signed_dir()?)?;
+        let mut roots = rustls::RootCertStore::empty();
+        for cert in rustls_pemfile::certs(&mut BufReader::new(ca.cert_pem.as_bytes())) {
+            let cert = cert.map_err(|e| format!("解析本地 CA 证书失败: {}", e))?;
+            roots
+                .add(cert)
+                .map_err(|e| format!("加载本地 CA 失败: {}", e))?;
+        }
+        let verifier = rustls::server::WebPkiClientVerifier::builder(Arc::new(roots))
+            .build()
+            .map_err(|e| format!("配置客户端证书校验失败: {}", e))?;
+        tracing::info!("反代已启用 mTLS，仅接受本地 CA 签发的客户端证书");
+        builder.with_client_cert_verifier(verifier)
+    } else {
+        builder.with_no_client_auth()
+    };
+    let mut server_config = builder
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("HTTPS 证书配置失败: {}", e))?;
+    server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(Some(TlsAcceptor::from(Arc::new(server_config))))
+}
+
+fn load_pem(
+    cert_path: &Path,
+    key_path: &Path,
+) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>), String> {
+    let cert_file = std::fs::File::open(cert_path)
+        .map_err(|e| format!("无法打开证书文件 {}: {}", cert_path.display(), e))?;
+    let certs: Vec<CertificateDer<'static>> =
+        rustls_pemfile::certs(&mut BufReader::new(cert_file))
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("解析证书文件 {} 失败: {}", cert_path.display(), e))?;
+    if certs.is_empty() {
+        return Err(format!("证书文件 {} 中没有证书", cert_path.display()));
+    }
+
+    let key_file = std::fs::File::open(key_path)
+        .map_err(|e| format!("无法打开私钥文件 {}: {}", key_path.display(), e))?;
+    let key = rustls_pemfile::private_key(&mut BufReader::new(key_file))
+        .map_err(|e| format!("解析私钥文件 {} 失败: {}", key_path.display(), e))?
+        .ok_or_else(|| format!("私钥文件 {} 中没有私钥", key_path.display()))?;
+    Ok((certs, key))
+}
+
+/// 自签名证书需要覆盖的主机名与地址
+fn certificate_names(config: &ProxyTlsConfig, listen_addrs: &[SocketAddr]) -> Vec<String> {
+    let mut names: Vec<String> = vec!["localhost".into(), "127.0.0.1".into(), "::1".into()];
+    let extra = listen_addrs
+        .iter()
+        .map(|a| a.ip())
+        .filter(|ip| !ip.is_unspecified())
+        .map(|ip| ip.to_string())
+        .chain(config.subject_alt_names.iter().map(|n| n.trim().to_string()));
+    for name in extra {
+        if !name.is_empty() && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// 已有证书覆盖所有名称且未临近过期时复用，否则重新生成
+fn ensure_self_signed(dir: &Path, names: &[String]) -> Result<(), String> {
+    let cert_path = dir.join(CERT_FILE);
+    let key_path = dir.join(KEY_FILE);
+    if key_path.exists() {
+        if let Ok(pem) = std::fs::read(&cert_path) {
+            if certificate_is_reusable(&pem, names, time::OffsetDateTime::now_utc()) {
+                return Ok(());
+            }
+        }
+    }
+
+    let (cert_pem, key_pem) = generate_self_signed(names)?;
+    std::fs::create_dir_all(dir).map_err(|e| format!("创建证书目录失败: {}", e))?;
+    std::fs::write(&key_path, key_pem).map_err(|e| format!("写入私钥失败: {}", e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(&key_path, std::fs::Permissions::from_mode(0o600));
+    }
+    std::fs::write(&cert_path, cert_pem).map_err(|e| format!("写入证书失败: {}", e))?;
+    tracing::info!("已生成反代自签名证书，覆盖: {}", names.join(", "));
+    Ok(())
+}
+
+fn certificate_is_reusable(pem: &[u8], names: &[String], now: time::OffsetDateTime) -> bool {
+    let Some(Ok(der)) = rustls_pemfile::certs(&mut BufReader::new(pem)).next() else {
+        return false;
+    };
+    let Ok((_, cert)) = x509_parser::parse_x509_certificate(&der) else {
+        return false;
+    };
+    let renew_at = cert.validity().not_after.timestamp()
+        - time::Duration::days(RENEW_BEFORE_DAYS).whole_seconds();
+    if now.unix_timestamp() >= renew_at {
+        return false;
+    }
+
+    let Ok(Some(san)) = cert.subject_alternative_name() else {
+        return false;
+    };
+    let covered: Vec<String> = san
+        .value
+        .general_names
+        .iter()
+        .filter_map(|name| match name {
+            x509_parser::extensions::GeneralName::DNSName(dns) => Some(dns.to_string()),
+            x509_parser::extensions::GeneralName::IPAddress(bytes) => match bytes.len() {
+                4 => Some(IpAddr::from(<[u8; 4]>::try_from(*bytes).ok()?).to_string()),
+                16 => Some(IpAddr::from(<[u8; 16]>::try_from(*bytes).ok()?).to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    names.iter().all(|name| covered.contains(name))
+}
+
+fn generate_self_signed(names: &[String]) -> Result<(String, String), String> {
+    let mut params = rcgen::CertificateParams::new(names.to_vec())
+        .map_err(|e| format!("无效的证书名称: {}", e))?;
+    params
+        .distinguished_name
+        .push(rcgen::DnType::CommonName, "Antigravity Proxy");
+    let now = time::OffsetDateTime::now_utc();
+    params.not_before = now - time::Duration::days(1);
+    params.not_after = now + time::Duration::days(VALIDITY_DAYS);
+
+    let key_pair = rcgen::KeyPair::generate().map_err(|e| format!("生成私钥失败: {}", e))?;
+    let cert = params
+        .self_signed(&key_pair)
+        .map_err(|e| format!("生成自签名证书失败: {}", e))?;
+    Ok((cert.pem(), key_pair.serialize_pem()))
+}
+
+/// 通过 mTLS 校验的客户端身份，作为请求扩展传给中间件
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientIdentity {
+    /// 客户端证书的 CN
+    pub name: String,
+}
+
+impl ClientIdentity {
+    /// 从握手完成的连接中读取客户端证书 (未启用 mTLS 时没有)
+    pub fn from_connection(conn: &rustls::ServerConnection) -> Option<Self> {
+        let cert = conn.peer_certificates()?.first()?;
+        Self::from_der(cert)
+    }
+
+    fn from_der(der: &[u8]) -> Option<Self> {
+        let (_, cert) = x509_parser::parse_x509_certificate(der).ok()?;
+        let name = cert
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|cn| cn.as_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        Some(Self { name })
+    }
+}
+
+/// 本地 CA (用于签发与校验客户端证书)
+struct ClientCa {
+    cert: rcgen::Certificate,
+    key_pair: rcgen::KeyPair,
+    /// 保存在磁盘上的 CA 证书
+    cert_pem: String,
+}
+
+fn load_or_create_client_ca(dir: &Path) -> Result<ClientCa, String> {
+    let cert_path = dir.join(CLIENT_CA_CERT_FILE);
+    let key_path = dir.join(CLIENT_CA_KEY_FILE);
+
+    if cert_path.exists() && key_path.exists() {
+        let cert_pem = std::fs::read_to_string(&cert_path)
+            .map_err(|e| format!("读取本地 CA 证书失败: {}", e))?;
+        let key_pem = std::fs::read_to_string(&key_path)
+            .map_err(|e| format!("读取本地 CA 私钥失败: {}", e))?;
+        let key_pair =
+            rcgen::KeyPair::from_pem(&key_pem).map_err(|e| format!("解析本地 CA 私钥失败: {}", e))?;
+        // 以原参数重新自签，得到可用于签发的 CA 对象 (公钥与主题不变，已签发的证书仍然有效)
+        let cert = rcgen::CertificateParams::from_ca_cert_pem(&cert_pem)
+            .map_err(|e| format!("解析本地 CA 证书失败: {}", e))?
+            .self_signed(&key_pair)
+            .map_err(|e| format!("加载本地 CA 失败: {}", e))?;
+        return Ok(ClientCa { cert, key_pair, cert_pem });
+    }
+
+    let mut params = rcgen::CertificateParams::default();
+    params
+        .distinguished_name
+        .push(rcgen::DnType::CommonName, "Antigravity Proxy Client CA");
+    params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Constrained(0));
+    params.key_usages = vec![
+        rcgen::KeyUsagePurpose::KeyCertSign,
+        rcgen::KeyUsagePurpose::CrlSign,
+    ];
+    let now = time::OffsetDateTime::now_utc();
+    params.not_before = now - time::Duration::days(1);
+    params.not_after = now + time::Duration::days(CA_VALIDITY_DAYS);
+
+    let key_pair = rcgen::KeyPair::generate().map_err(|e| format!("生成 CA 私钥失败: {}", e))?;
+    let cert = params
+        .self_signed(&key_pair)
+        .map_err(|e| format!("生成本地 CA 失败: {}", e))?;
+
+    std::fs::create_dir_all(dir).map_err(|e| format!("创建证书目录失败: {}", e))?;
+    std::fs::write(&key_path, key_pair.serialize_pem())
+        .map_err(|e| format!("写入 CA 私钥失败: {}", e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(&key_path, std::fs::Permissions::from_mode(0o600));
+    }
+    let cert_pem = cert.pem();
+    std::fs::write(&cert_path, &cert_pem).map_err(|e| format!("写入 CA 证书失败: {}", e))?;
+    tracing::info!("已生成反代客户端证书 CA: {}", cert_path.display());
+    Ok(ClientCa { cert, key_pair, cert_pem })
+}
+
+/// 签发的客户端证书 (PEM)，私钥只在签发时返回一次，不在本地保存
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientCertificateBundle {
+    pub name: String,
+    pub cert_pem: String,
+    pub key_pem: String,
+    /// 本地 CA 证书，客户端无需安装，仅供核对
+    pub ca_cert_pem: String,
+    /// 过期时间 (Unix 秒)
+    pub expires_at: i64,
+}
+
+/// 本应用内部回环调用 (预热等) 使用的客户端证书名
+pub const INTERNAL_CLIENT_NAME: &str = "antigravity-internal";
+/// 内部客户端证书有效期，临近过期时重新签发
+const INTERNAL_CLIENT_CERT_DAYS: u32 = 7;
+
+/// 已签发的内部客户端证书 (仅保存在内存中)
+static INTERNAL_CLIENT_CERT: once_cell::sync::Lazy<parking_lot::Mutex<Option<ClientCertificateBundle>>> =
+    once_cell::sync::Lazy::new(|| parking_lot::Mutex::new(None));
+
+/// 内部客户端证书 (证书 PEM, 私钥 PEM)，启用 mTLS 时应用自身调用本地反代需要携带
+pub fn internal_client_certificate() -> Result<(String, String), String> {
+    let mut cached = INTERNAL_CLIENT_CERT.lock();
+    let now = chrono::Utc::now().timestamp();
+    let renew_before = time::Duration::days(1).whole_seconds();
+    let bundle = match cached.as_ref() {
+        Some(bundle) if bundle.expires_at - renew_before > now => bundle.clone(),
+        _ => {
+            let bundle = issue_client_certificate(INTERNAL_CLIENT_NAME, INTERNAL_CLIENT_CERT_DAYS)?;
+            *cached = Some(bundle.clone());
+            bundle
+        }
+    };
+    Ok((bundle.cert_pem, bundle.key_pem))
+}
+
+/// 用本地 CA 签发一张客户端证书 (CA 不存在时先生成)
+pub fn issue_client_certificate(name: &str, valid_days: u32) -> Result<ClientCertificateBundle, String> {
+    issue_client_certificate_in(&self_signed_dir()?, name, valid_days)
+}
+
+fn issue_client_certificate_in(
+    dir: &Path,
+    name: &str,
+    valid_days: u32,
+) -> Result<ClientCertificateBundle, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("客户端名称不能为空".to_string());
+    }
+    let ca = load_or_create_client_ca(dir)?;
+
+    let mut params = rcgen::CertificateParams::default();
+    params.distinguished_name.push(rcgen::DnType::CommonName, name);
+    params.is_ca = rcgen::IsCa::ExplicitNoCa;
+    params.key_usages = vec![rcgen::KeyUsagePurpose::DigitalSignature];
+    params.extended_key_usages = vec![rcgen::ExtendedKeyUsagePurpose::ClientAuth];
+    let mut serial = [0u8; 16];
+    rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut serial);
+    serial[0] &= 0x7f; // 序列号须为正数
+    params.serial_number = Some(rcgen::SerialNumber::from_slice(&serial));
+    let now = time::OffsetDateTime::now_utc();
+    params.not_before = now - time::Duration::days(1);
+    params.not_after = now + time::Duration::days(valid_days.max(1) as i64);
+    let expires_at = params.not_after.unix_timestamp();
+
+    let key_pair = rcgen::KeyPair::generate().map_err(|e| format!("生成客户端私钥失败: {}", e))?;
+    let cert = params
+        .signed_by(&key_pair, &ca.cert, &ca.key_pair)
+        .map_err(|e| format!("签发客户端证书失败: {}", e))?;
+    tracing::info!("已签发反代客户端证书: {}", name);
+
+    Ok(ClientCertificateBundle {
+        name: name.to_string(),
+        cert_pem: cert.pem(),
+        key_pem: key_pair.serialize_pem(),
+        ca_cert_pem: ca.cert_pem,
+        expires_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_include_specific_listen_addresses() {
+        let config = ProxyTlsConfig {
+            subject_alt_names: vec!["proxy.lan".to_string(), "localhost".to_string()],
+            ..Default::default()
+        };
+        let addrs: Vec<SocketAddr> = vec![
+            "0.0.0.0:8045".parse().unwrap(),
+            "192.168.1.10:8045".parse().unwrap(),
+        ];
+        assert_eq!(
+            certificate_names(&config, &addrs),
+            vec!["localhost", "127.0.0.1", "::1", "192.168.1.10", "proxy.lan"]
+        );
+    }
+
+    #[test]
+    fn generated_certificate_is_reused_until_names_change() {
+        let names = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+        let (cert_pem, _) = generate_self_signed(&names).unwrap();
+        let now = time::OffsetDateTime::now_utc();
+
+        assert!(certificate_is_reusable(cert_pem.as_bytes(), &names, now));
+        assert!(!certificate_is_reusable(
+            cert_pem.as_bytes(),
+            &["192.168.1.10".to_string()],
+            now
+        ));
+        // 临近过期时重新生成
+        let near_expiry = now + time::Duration::days(VALIDITY_DAYS - RENEW_BEFORE_DAYS + 1);
+        assert!(!certificate_is_reusable(cert_pem.as_bytes(), &names, near_expiry));
+    }
+
+    #[test]
+    fn client_certificates_are_signed_by_the_persisted_ca() {
+        let _ = rustls::crypto::CryptoProvider::install_default(
+            rustls::crypto::ring::default_provider(),
+        );
+        let dir = std::env::temp_dir().join(format!("ag_proxy_tls_test_{}", uuid::Uuid::new_v4()));
+        let first = issue_client_certificate_in(&dir, " laptop ", 30).unwrap();
+        // 第二次签发复用磁盘上的 CA
+        let second = issue_client_certificate_in(&dir, "phone", 30).unwrap();
+        assert_eq!(first.ca_cert_pem, second.ca_cert_pem);
+
+        let der_of = |pem: &str| {
+            rustls_pemfile::certs(&mut BufReader::new(pem.as_bytes()))
+                .next()
+                .unwrap()
+                .unwrap()
+        };
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(der_of(&first.ca_cert_pem)).unwrap();
+        let verifier = rustls::server::WebPkiClientVerifier::builder(Arc::new(roots))
+            .build()
+            .unwrap();
+        for bundle in [&first, &second] {
+            verifier
+                .verify_client_cert(
+                    &der_of(&bundle.cert_pem),
+                    &[],
+                    rustls::pki_types::UnixTime::now(),
+                )
+                .unwrap();
+        }
+
+        // 其他 CA 签发的证书被拒绝
+        let other_dir = dir.join("other");
+        let foreign = issue_client_certificate_in(&other_dir, "foreign", 30).unwrap();
+        assert!(verifier
+            .verify_client_cert(
+                &der_of(&foreign.cert_pem),
+                &[],
+                rustls::pki_types::UnixTime::now(),
+            )
+            .is_err());
+
+        assert_eq!(
+            ClientIdentity::from_der(&der_of(&first.cert_pem)),
+            Some(ClientIdentity { name: "laptop".to_string() })
+        );
+        assert!(issue_client_certificate_in(&dir, "  ", 30).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -2,12 +2,36 @@
 use dashmap::DashMap;
 use std::collections::HashSet;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 
+use crate::proxy::circuit_breaker::{CircuitBreaker, CircuitBreakerStatus};
 use crate::proxy::rate_limit::RateLimitTracker;
 use crate::proxy::sticky_config::StickySessionConfig;
 
+/// 单次 OAuth 刷新的超时时间
+const TOKEN_REFRESH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+/// 请求时 token 剩余有效期低于该值 (秒) 即刷新
+const TOKEN_REFRESH_MARGIN_SECS: i64 = 300;
+
+/// 进行中的刷新任务，所有等待者共享同一个结果
+type RefreshFlight = futures::future::Shared<
+    futures::future::BoxFuture<
+        'static,
+        Result<crate::modules::oauth::TokenResponse, crate::modules::oauth::RefreshError>,
+    >,
+>;
+
+/// 选中的账号: (access_token, project_id, email, 账号专属上游代理)
+pub type SelectedToken = (String, String, String, Option<String>);
+
+/// 配额保护是否启用 (由反代启动 / 配置保存时写入)，供只读查询使用，避免每次读取配置文件
+static QUOTA_PROTECTION_ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quota_protection_enabled(enabled: bool) {
+    QUOTA_PROTECTION_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
 #[derive(Debug, Clone)]
 pub struct ProxyToken {
     pub account_id: String,
@@ -21,8 +45,31 @@ pub struct ProxyToken {
     pub subscription_tier: Option<String>, // "FREE" | "PRO" | "ULTRA"
     pub remaining_quota: Option<i32>, // [FIX #563] Remaining quota for priority sorting
     pub protected_models: HashSet<String>, // [NEW #621]
+    pub quota_models: HashSet<String>, // 账号配额中列出的模型，即该账号有权使用的模型 (无配额数据时为空)
+    pub depletes_at: Option<i64>, // 预计配额耗尽时间 (用于优先选择能撑过工作时段的账号)
+    pub upstream_proxy: Option<String>, // 账号专属上游代理 (为空时使用全局代理)
+}
+
+
+/// 账号限流冷却状态 (供前端展示剩余冷却时间)
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AccountCooldown {
+    pub account_id: String,
+    pub email: String,
+    /// 剩余冷却时间 (毫秒)
+    pub remaining_ms: u64,
+    /// 冷却结束时间 (Unix 毫秒)
+    pub reset_at: i64,
+    pub retry_after_sec: u64,
+    pub reason: crate::proxy::rate_limit::RateLimitReason,
+    pub model: Option<String>,
 }
 
+/// 403 (权限/地区限制) 后账号的冷却时间
+const AUTH_FAILURE_COOLDOWN_SECS: u64 = 600;
+
+/// 排序时要求账号至少还能撑过的时长 (按配额耗尽预测)
+const FORECAST_HORIZON_SECS: i64 = 4 * 3600;
 
 pub struct TokenManager {
     tokens: Arc<DashMap<String, ProxyToken>>,  // account_id -> ProxyToken
@@ -33,6 +80,9 @@ pub struct TokenManager {
     sticky_config: Arc<tokio::sync::RwLock<StickySessionConfig>>, // 新增：调度配置
     session_accounts: Arc<DashMap<String, String>>, // 新增：会话与账号映射 (SessionID -> AccountID)
     preferred_account_id: Arc<tokio::sync::RwLock<Option<String>>>, // [FIX #820] 优先使用的账号ID（固定账号模式）
+    refresh_flights: Arc<DashMap<String, RefreshFlight>>, // 进行中的 token 刷新 (account_id -> 共享结果)
+    last_used_at: Arc<DashMap<String, i64>>, // 账号最近一次被反代选中的时间 (account_id -> Unix 秒)
+    circuit_breaker: Arc<CircuitBreaker>, // 账号熔断 (连续 5xx / 网络错误)
 }
 
 impl TokenManager {
@@ -47,6 +97,9 @@ impl TokenManager {
             sticky_config: Arc::new(tokio::sync::RwLock::new(StickySessionConfig::default())),
             session_accounts: Arc::new(DashMap::new()),
             preferred_account_id: Arc::new(tokio::sync::RwLock::new(None)), // [FIX #820]
+            refresh_flights: Arc::new(DashMap::new()),
+            last_used_at: Arc::new(DashMap::new()),
+            circuit_breaker: Arc::new(CircuitBreaker::new()),
         }
     }
 
@@ -126,7 +179,11 @@ impl TokenManager {
                 self.tokens.insert(account_id.to_string(), token);
                 Ok(())
             }
-            Ok(None) => Err("账号加载失败".to_string()),
+            Ok(None) => {
+                // 账号已被禁用或因配额保护暂停，移出轮换池
+                self.tokens.remove(account_id);
+                Err("账号加载失败".to_string())
+            }
             Err(e) => Err(format!("同步账号失败: {}", e)),
         }
     }
@@ -168,6 +225,24 @@ impl TokenManager {
             return Ok(None);
         }
 
+        // 所有受监控模型都已低于保护阈值的账号暂停参与轮换 (配额刷新后自动恢复)
+        let quota_paused = account
+            .get("quota_protected")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        if quota_paused
+            && crate::modules::config::load_app_config()
+                .map(|cfg| cfg.quota_protection.enabled)
+                .unwrap_or(false)
+        {
+            tracing::debug!(
+                "Skipping quota-paused account file: {:?} (email={})",
+                path,
+                account.get("email").and_then(|v| v.as_str()).unwrap_or("<unknown>")
+            );
+            return Ok(None);
+        }
+
         // 检查主动禁用状态
         if account
             .get("proxy_disabled")
@@ -198,8 +273,9 @@ impl TokenManager {
             .to_string();
         
         let refresh_token = token_obj["refresh_token"].as_str()
-            .ok_or("缺少 refresh_token")?
-            .to_string();
+            .ok_or("缺少 refresh_token")?;
+        // refresh_token 可能保存在外部密钥存储中，账号文件内只有引用
+        let refresh_token = crate::modules::token_store::resolve(&account_id, refresh_token)?;
         
         let expires_in = token_obj["expires_in"].as_i64()
             .ok_or("缺少 expires_in")?;
@@ -234,6 +310,26 @@ impl TokenManager {
                     .collect()
             })
             .unwrap_or_default();
+
+        let quota_models: HashSet<String> = account
+            .pointer("/quota/models")
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|m| m.get("name").and_then(|n| n.as_str()))
+                    .map(|s| s.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let depletes_at = account.get("depletes_at").and_then(|v| v.as_i64());
+        // 账号专属代理在账号文件中加密保存
+        let upstream_proxy = account
+            .get("upstream_proxy")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.trim().is_empty())
+            .map(crate::modules::config_secrets::open_value)
+            .transpose()?;
         
         Ok(Some(ProxyToken {
             account_id,
@@ -247,6 +343,9 @@ impl TokenManager {
             subscription_tier,
             remaining_quota,
             protected_models,
+            quota_models,
+            depletes_at,
+            upstream_proxy,
         }))
     }
 
@@ -478,15 +577,79 @@ impl TokenManager {
         force_rotate: bool, 
         session_id: Option<&str>,
         target_model: &str,
-    ) -> Result<(String, String, String), String> {
+    ) -> Result<SelectedToken, String> {
         // 【优化 Issue #284】添加 5 秒超时，防止死锁
         let timeout_duration = std::time::Duration::from_secs(5);
-        match tokio::time::timeout(timeout_duration, self.get_token_internal(quota_group, force_rotate, session_id, target_model)).await {
-            Ok(result) => result,
-            Err(_) => Err("Token acquisition timeout (5s) - system too busy or deadlock detected".to_string()),
+        let concurrency = crate::proxy::concurrency::config();
+        let queue_deadline = tokio::time::Instant::now()
+            + std::time::Duration::from_secs(concurrency.queue_timeout_secs);
+        let mut force_rotate = force_rotate;
+        loop {
+            let result = match tokio::time::timeout(timeout_duration, self.get_token_internal(quota_group, force_rotate, session_id, target_model)).await {
+                Ok(result) => result,
+                Err(_) => return Err("Token acquisition timeout (5s) - system too busy or deadlock detected".to_string()),
+            };
+            match result {
+                Ok((access_token, project_id, email, upstream_proxy)) => {
+                    if let Some(account_id) = self.email_to_account_id(&email) {
+                        // 选号与占用名额之间可能被并发请求抢先占满，此时换号重选
+                        if !crate::proxy::concurrency::lease_account(&account_id) {
+                            force_rotate = true;
+                            if !crate::proxy::concurrency::wait_for_account_slot(queue_deadline).await {
+                                return Err(Self::all_busy_error(&concurrency));
+                            }
+                            continue;
+                        }
+                        self.circuit_breaker.on_selected(&account_id);
+                        self.last_used_at.insert(account_id, chrono::Utc::now().timestamp());
+                    }
+                    return Ok((access_token, project_id, email, upstream_proxy));
+                }
+                Err(e) => {
+                    // 账号都因并发上限被跳过时排队等待名额释放，其余错误直接返回
+                    let busy = concurrency.max_per_account > 0
+                        && self.all_eligible_at_capacity(target_model);
+                    if !busy {
+                        return Err(e);
+                    }
+                    if !crate::proxy::concurrency::wait_for_account_slot(queue_deadline).await {
+                        return Err(Self::all_busy_error(&concurrency));
+                    }
+                }
+            }
         }
     }
 
+    /// 其余条件都满足的账号 (未超预算、未限流、未熔断、目标模型未被配额保护) 是否全部已达并发上限；
+    /// 只有这种情况下排队等待名额才有意义
+    fn all_eligible_at_capacity(&self, target_model: &str) -> bool {
+        let quota_protection_enabled = crate::modules::config::load_app_config()
+            .map(|cfg| cfg.quota_protection.enabled)
+            .unwrap_or(false);
+        let normalized_target = crate::proxy::common::model_mapping::normalize_to_standard_id(target_model)
+            .unwrap_or_else(|| target_model.to_string());
+        let mut eligible = self
+            .tokens
+            .iter()
+            .filter(|entry| {
+                let token = entry.value();
+                !crate::modules::token_budget::is_exhausted(&token.account_id)
+                    && !self.rate_limit_tracker.is_rate_limited(&token.account_id)
+                    && !self.circuit_breaker.is_open(&token.account_id)
+                    && !(quota_protection_enabled && token.protected_models.contains(&normalized_target))
+            })
+            .peekable();
+        eligible.peek().is_some()
+            && eligible.all(|entry| crate::proxy::concurrency::account_at_capacity(entry.key()))
+    }
+
+    fn all_busy_error(config: &crate::proxy::config::ConcurrencyConfig) -> String {
+        format!(
+            "All accounts are busy (max {} in-flight requests per account); waited {}s for a free slot",
+            config.max_per_account, config.queue_timeout_secs
+        )
+    }
+
     /// 内部实现：获取 Token 的核心逻辑
     async fn get_token_internal(
         &self, 
@@ -494,17 +657,23 @@ impl TokenManager {
         force_rotate: bool, 
         session_id: Option<&str>,
         target_model: &str,
-    ) -> Result<(String, String, String), String> {
+    ) -> Result<SelectedToken, String> {
         let mut tokens_snapshot: Vec<ProxyToken> = self.tokens.iter().map(|e| e.value().clone()).collect();
+        if tokens_snapshot.is_empty() {
+            return Err("Token pool is empty".to_string());
+        }
+        // 超出手动 Token 预算的账号在本周期内不参与选择
+        tokens_snapshot.retain(|t| !crate::modules::token_budget::is_exhausted(&t.account_id));
         let total = tokens_snapshot.len();
         if total == 0 {
-            return Err("Token pool is empty".to_string());
+            return Err("All accounts have used up their token budget".to_string());
         }
 
         // ===== 【优化】根据订阅等级和剩余配额排序 =====
         // [FIX #563] 优先级: ULTRA > PRO > FREE, 同tier内优先高配额账号
         // 理由: ULTRA/PRO 重置快，优先消耗；FREE 重置慢，用于兜底
         //       高配額账号优先使用，避免低配额账号被用光
+        let workday_end = chrono::Utc::now().timestamp() + FORECAST_HORIZON_SECS;
         tokens_snapshot.sort_by(|a, b| {
             let tier_priority = |tier: &Option<String>| match tier.as_deref() {
                 Some("ULTRA") => 0,
@@ -520,6 +689,13 @@ impl TokenManager {
             if tier_cmp != std::cmp::Ordering::Equal {
                 return tier_cmp;
             }
+
+            // 预计在工作时段内耗尽的账号排在能撑过去的账号之后
+            let lasts = |t: &ProxyToken| t.depletes_at.map_or(true, |at| at >= workday_end);
+            let lasts_cmp = lasts(b).cmp(&lasts(a));
+            if lasts_cmp != std::cmp::Ordering::Equal {
+                return lasts_cmp;
+            }
             
             // [FIX #563] Second: compare by remaining quota percentage (higher is better)
             // Accounts with unknown/zero percentage go last within their tier
@@ -555,7 +731,7 @@ impl TokenManager {
                 let normalized_target = crate::proxy::common::model_mapping::normalize_to_standard_id(target_model)
                     .unwrap_or_else(|| target_model.to_string());
 
-                let is_rate_limited = self.is_rate_limited_by_account_id(&preferred_token.account_id);
+                let is_rate_limited = self.is_account_unavailable(&preferred_token.account_id);
                 let is_quota_protected = quota_protection_enabled && preferred_token.protected_models.contains(&normalized_target);
 
                 if !is_rate_limited && !is_quota_protected {
@@ -571,18 +747,11 @@ impl TokenManager {
                     let now = chrono::Utc::now().timestamp();
                     if now >= token.timestamp - 300 {
                         tracing::debug!("账号 {} 的 token 即将过期，正在刷新...", token.email);
-                        match crate::modules::oauth::refresh_access_token(&token.refresh_token).await {
+                        match self.refresh_token_single_flight(&token.account_id, &token.refresh_token, TOKEN_REFRESH_MARGIN_SECS).await {
                             Ok(token_response) => {
                                 token.access_token = token_response.access_token.clone();
                                 token.expires_in = token_response.expires_in;
                                 token.timestamp = now + token_response.expires_in;
-
-                                if let Some(mut entry) = self.tokens.get_mut(&token.account_id) {
-                                    entry.access_token = token.access_token.clone();
-                                    entry.expires_in = token.expires_in;
-                                    entry.timestamp = token.timestamp;
-                                }
-                                let _ = self.save_refreshed_token(&token.account_id, &token_response).await;
                             }
                             Err(e) => {
                                 tracing::warn!("Preferred account token refresh failed: {}", e);
@@ -595,7 +764,7 @@ impl TokenManager {
                     let project_id = if let Some(pid) = &token.project_id {
                         pid.clone()
                     } else {
-                        match crate::proxy::project_resolver::fetch_project_id(&token.access_token).await {
+                        match crate::proxy::project_resolver::fetch_project_id(&token.access_token, token.upstream_proxy.as_deref()).await {
                             Ok(pid) => {
                                 if let Some(mut entry) = self.tokens.get_mut(&token.account_id) {
                                     entry.project_id = Some(pid.clone());
@@ -607,7 +776,7 @@ impl TokenManager {
                         }
                     };
 
-                    return Ok((token.access_token, project_id, token.email));
+                    return Ok((token.access_token, project_id, token.email, token.upstream_proxy));
                 } else {
                     if is_rate_limited {
                         tracing::warn!("🔒 [FIX #820] Preferred account {} is rate-limited, falling back to round-robin", preferred_token.email);
@@ -655,7 +824,7 @@ impl TokenManager {
                     if let Some(bound_token) = tokens_snapshot.iter().find(|t| t.account_id == bound_id) {
                         let key = self.email_to_account_id(&bound_token.email).unwrap_or_else(|| bound_token.account_id.clone());
                         let reset_sec = self.rate_limit_tracker.get_remaining_wait(&key);
-                        if reset_sec > 0 {
+                        if reset_sec > 0 || self.circuit_breaker.is_open(&key) {
                             // 【修复 Issue #284】立即解绑并切换账号，不再阻塞等待
                             // 原因：阻塞等待会导致并发请求时客户端 socket 超时 (UND_ERR_SOCKET)
                             tracing::debug!(
@@ -688,12 +857,12 @@ impl TokenManager {
                     if last_time.elapsed().as_secs() < 60 && !attempted.contains(account_id) {
                         if let Some(found) = tokens_snapshot.iter().find(|t| &t.account_id == account_id) {
                             // 【修复】检查限流状态和配额保护，避免复用已被锁定的账号
-                            if !self.is_rate_limited_by_account_id(&found.account_id) && !(quota_protection_enabled && found.protected_models.contains(&normalized_target)) {
+                            if !self.is_account_unavailable(&found.account_id) && !(quota_protection_enabled && found.protected_models.contains(&normalized_target)) {
                                 tracing::debug!("60s Window: Force reusing last account: {}", found.email);
                                 target_token = Some(found.clone());
                             } else {
-                                if self.is_rate_limited_by_account_id(&found.account_id) {
-                                    tracing::debug!("60s Window: Last account {} is rate-limited, skipping", found.email);
+                                if self.is_account_unavailable(&found.account_id) {
+                                    tracing::debug!("60s Window: Last account {} is rate-limited or circuit-broken, skipping", found.email);
                                 } else {
                                     tracing::debug!("60s Window: Last account {} is quota-protected for model {} [{}], skipping", found.email, normalized_target, target_model);
                                 }
@@ -719,7 +888,7 @@ impl TokenManager {
                         }
 
                         // 【新增】主动避开限流或 5xx 锁定的账号 (高可用优化)
-                        if self.is_rate_limited_by_account_id(&candidate.account_id) { // Changed to account_id
+                        if self.is_account_unavailable(&candidate.account_id) { // Changed to account_id
                             continue;
                         }
 
@@ -757,7 +926,7 @@ impl TokenManager {
                     }
 
                     // 【新增】主动避开限流或 5xx 锁定的账号
-                    if self.is_rate_limited_by_account_id(&candidate.account_id) { // Changed to account_id
+                    if self.is_account_unavailable(&candidate.account_id) { // Changed to account_id
                         tracing::info!("  ⏳ {} - SKIP: rate-limited", candidate.email);
                         continue;
                     }
@@ -796,7 +965,7 @@ impl TokenManager {
                             
                             // 重新尝试选择账号
                             let retry_token = tokens_snapshot.iter()
-                                .find(|t| !attempted.contains(&t.account_id) && !self.is_rate_limited_by_account_id(&t.account_id)); // Changed to account_id
+                                .find(|t| !attempted.contains(&t.account_id) && !self.is_account_unavailable(&t.account_id)); // Changed to account_id
                             
                             if let Some(t) = retry_token {
                                 tracing::info!("✅ Buffer delay successful! Found available account: {}", t.email);
@@ -842,37 +1011,25 @@ impl TokenManager {
             if now >= token.timestamp - 300 {
                 tracing::debug!("账号 {} 的 token 即将过期，正在刷新...", token.email);
 
-                // 调用 OAuth 刷新 token
-                match crate::modules::oauth::refresh_access_token(&token.refresh_token).await {
+                // 调用 OAuth 刷新 token (单飞：并发请求共享同一次刷新)
+                match self.refresh_token_single_flight(&token.account_id, &token.refresh_token, TOKEN_REFRESH_MARGIN_SECS).await {
                     Ok(token_response) => {
                         tracing::debug!("Token 刷新成功！");
 
-                        // 更新本地内存对象供后续使用
+                        // 更新本地内存对象供后续使用 (共享缓存与落盘已在单飞刷新中完成)
                         token.access_token = token_response.access_token.clone();
                         token.expires_in = token_response.expires_in;
                         token.timestamp = now + token_response.expires_in;
-
-                        // 同步更新跨线程共享的 DashMap
-                        if let Some(mut entry) = self.tokens.get_mut(&token.account_id) {
-                            entry.access_token = token.access_token.clone();
-                            entry.expires_in = token.expires_in;
-                            entry.timestamp = token.timestamp;
-                        }
-
-                        // 同步落盘（避免重启后继续使用过期 timestamp 导致频繁刷新）
-                        if let Err(e) = self.save_refreshed_token(&token.account_id, &token_response).await {
-                            tracing::debug!("保存刷新后的 token 失败 ({}): {}", token.email, e);
-                        }
                     }
                     Err(e) => {
                         tracing::error!("Token 刷新失败 ({}): {}，尝试下一个账号", token.email, e);
-                        if e.contains("\"invalid_grant\"") || e.contains("invalid_grant") {
+                        if e.is_invalid_grant() {
                             tracing::error!(
                                 "Disabling account due to invalid_grant ({}): refresh_token likely revoked/expired",
                                 token.email
                             );
                             let _ = self
-                                .disable_account(&token.account_id, &format!("invalid_grant: {}", e))
+                                .disable_account(&token.account_id, &e.to_string())
                                 .await;
                             self.tokens.remove(&token.account_id);
                         }
@@ -896,7 +1053,7 @@ impl TokenManager {
                 pid.clone()
             } else {
                 tracing::debug!("账号 {} 缺少 project_id，尝试获取...", token.email);
-                match crate::proxy::project_resolver::fetch_project_id(&token.access_token).await {
+                match crate::proxy::project_resolver::fetch_project_id(&token.access_token, token.upstream_proxy.as_deref()).await {
                     Ok(pid) => {
                         if let Some(mut entry) = self.tokens.get_mut(&token.account_id) {
                             entry.project_id = Some(pid.clone());
@@ -933,7 +1090,7 @@ impl TokenManager {
                 }
             }
 
-            return Ok((token.access_token, project_id, token.email));
+            return Ok((token.access_token, project_id, token.email, token.upstream_proxy));
         }
 
         Err(last_error.unwrap_or_else(|| "All accounts failed".to_string()))
@@ -957,9 +1114,21 @@ impl TokenManager {
         content["disabled"] = serde_json::Value::Bool(true);
         content["disabled_at"] = serde_json::Value::Number(now.into());
         content["disabled_reason"] = serde_json::Value::String(truncate_reason(reason, 800));
+        let newly_flagged = crate::modules::oauth::is_invalid_grant(reason)
+            && content["needs_reauth"].as_bool() != Some(true);
+        if crate::modules::oauth::is_invalid_grant(reason) {
+            content["needs_reauth"] = serde_json::Value::Bool(true);
+        }
 
         std::fs::write(&path, serde_json::to_string_pretty(&content).unwrap())
             .map_err(|e| format!("写入文件失败: {}", e))?;
+        // 标记落盘后再提醒，前端收到事件时读到的已是最新状态
+        if newly_flagged {
+            crate::modules::reauth::notify_required(
+                account_id,
+                content["email"].as_str().unwrap_or(account_id),
+            );
+        }
         
         // 【修复 Issue #3】从内存中移除禁用的账号，防止被60s锁定逻辑继续使用
         self.tokens.remove(account_id);
@@ -988,13 +1157,151 @@ impl TokenManager {
         Ok(())
     }
     
+    /// 刷新账号 token (单飞)
+    ///
+    /// 同一账号的并发请求只会发起一次 OAuth 刷新，其余请求等待同一结果，
+    /// 避免并发刷新互相作废。刷新失败 / 超时的错误会传递给所有等待者。
+    /// 若缓存中的 token 已被其他请求刷新（剩余有效期超过 `margin_secs`），则直接返回缓存中的 token。
+    /// 刷新在独立任务中执行，内存缓存与落盘也在其中完成，发起请求被取消时也不会丢失刷新结果。
+    /// 请求路径只尝试一次 (受 get_token 的超时约束)，临时性失败的退避重试转入后台继续，
+    /// 当前请求则换号。
+    async fn refresh_token_single_flight(
+        &self,
+        account_id: &str,
+        refresh_token: &str,
+        margin_secs: i64,
+    ) -> Result<crate::modules::oauth::TokenResponse, crate::modules::oauth::RefreshError> {
+        use crate::modules::oauth::{self, RefreshError};
+        use futures::FutureExt;
+
+        let now = chrono::Utc::now().timestamp();
+        if let Some(entry) = self.tokens.get(account_id) {
+            if now < entry.timestamp - margin_secs {
+                return Ok(crate::modules::oauth::TokenResponse {
+                    access_token: entry.access_token.clone(),
+                    expires_in: entry.timestamp - now,
+                    token_type: "Bearer".to_string(),
+                    refresh_token: None,
+                });
+            }
+        }
+
+        let mut is_leader = false;
+        let flight = self
+            .refresh_flights
+            .entry(account_id.to_string())
+            .or_insert_with(|| {
+                is_leader = true;
+                let refresh_token = refresh_token.to_string();
+                let email = self.tokens.get(account_id).map(|e| e.email.clone());
+                let flights = self.refresh_flights.clone();
+                let tokens = self.tokens.clone();
+                let id = account_id.to_string();
+                let task = tokio::spawn(async move {
+                    // 通过账号配置的上游代理刷新（未配置时使用全局代理）
+                    let refresh = oauth::refresh_token_once(&refresh_token, email.as_deref());
+                    let result = match tokio::time::timeout(TOKEN_REFRESH_TIMEOUT, refresh).await {
+                        Ok(result) => result,
+                        Err(_) => Err(RefreshError::Transient(format!(
+                            "Token refresh timed out after {}s",
+                            TOKEN_REFRESH_TIMEOUT.as_secs()
+                        ))),
+                    };
+                    match &result {
+                        Ok(token_response) => Self::store_refreshed_token(&tokens, &id, token_response),
+                        Err(RefreshError::Transient(_)) => {
+                            Self::spawn_refresh_retry(tokens.clone(), id.clone(), refresh_token, email)
+                        }
+                        Err(_) => {}
+                    }
+                    flights.remove(&id);
+                    result
+                });
+                async move {
+                    task.await.unwrap_or_else(|e| {
+                        Err(RefreshError::Transient(format!("Token refresh task failed: {}", e)))
+                    })
+                }
+                .boxed()
+                .shared()
+            })
+            .clone();
+
+        if !is_leader {
+            tracing::debug!("账号 {} 的 token 正在刷新中，等待已有刷新结果", account_id);
+        }
+
+        flight.await
+    }
+
+    /// 更新内存中的 token 并同步落盘（避免重启后继续使用过期 timestamp 导致频繁刷新）
+    fn store_refreshed_token(
+        tokens: &DashMap<String, ProxyToken>,
+        account_id: &str,
+        token_response: &crate::modules::oauth::TokenResponse,
+    ) {
+        let account_path = tokens.get_mut(account_id).map(|mut entry| {
+            entry.access_token = token_response.access_token.clone();
+            entry.expires_in = token_response.expires_in;
+            entry.timestamp = chrono::Utc::now().timestamp() + token_response.expires_in;
+            entry.account_path.clone()
+        });
+        if let Some(path) = account_path {
+            if let Err(e) = Self::save_refreshed_token(&path, token_response) {
+                tracing::debug!("保存刷新后的 token 失败 ({}): {}", account_id, e);
+            }
+        }
+    }
+
+    /// 临时性刷新失败后在后台按退避继续重试，不占用请求的选号时间
+    fn spawn_refresh_retry(
+        tokens: Arc<DashMap<String, ProxyToken>>,
+        account_id: String,
+        refresh_token: String,
+        email: Option<String>,
+    ) {
+        tokio::spawn(async move {
+            match crate::modules::oauth::retry_refresh_token(&refresh_token, email.as_deref()).await {
+                Ok(token_response) => {
+                    tracing::info!("账号 {} 的 token 在后台重试后刷新成功", account_id);
+                    Self::store_refreshed_token(&tokens, &account_id, &token_response);
+                }
+                Err(e) => {
+                    tracing::warn!("账号 {} 的 token 后台重试刷新失败: {}", account_id, e);
+                }
+            }
+        });
+    }
+
+    /// 最近 `within_secs` 秒内被反代选中过的账号
+    pub fn recently_used_accounts(&self, within_secs: i64) -> Vec<String> {
+        let cutoff = chrono::Utc::now().timestamp() - within_secs;
+        self.last_used_at.retain(|id, used_at| *used_at >= cutoff && self.tokens.contains_key(id));
+        self.last_used_at.iter().map(|e| e.key().clone()).collect()
+    }
+
+    /// token 剩余有效期不足 `margin_secs` 时提前刷新，返回是否发起了刷新
+    pub async fn refresh_ahead(&self, account_id: &str, margin_secs: i64) -> Result<bool, String> {
+        let (refresh_token, expires_at) = match self.tokens.get(account_id) {
+            Some(entry) => (entry.refresh_token.clone(), entry.timestamp),
+            None => return Ok(false),
+        };
+        if chrono::Utc::now().timestamp() < expires_at - margin_secs {
+            return Ok(false);
+        }
+        match self.refresh_token_single_flight(account_id, &refresh_token, margin_secs).await {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                if e.is_invalid_grant() {
+                    let _ = self.disable_account(account_id, &e.to_string()).await;
+                }
+                Err(e.into())
+            }
+        }
+    }
+
     /// 保存刷新后的 token 到账号文件
-    async fn save_refreshed_token(&self, account_id: &str, token_response: &crate::modules::oauth::TokenResponse) -> Result<(), String> {
-        let entry = self.tokens.get(account_id)
-            .ok_or("账号不存在")?;
-        
-        let path = &entry.account_path;
-        
+    fn save_refreshed_token(path: &std::path::Path, token_response: &crate::modules::oauth::TokenResponse) -> Result<(), String> {
         let mut content: serde_json::Value = serde_json::from_str(
             &std::fs::read_to_string(path).map_err(|e| format!("读取文件失败: {}", e))?
         ).map_err(|e| format!("解析 JSON 失败: {}", e))?;
@@ -1008,7 +1315,7 @@ impl TokenManager {
         std::fs::write(path, serde_json::to_string_pretty(&content).unwrap())
             .map_err(|e| format!("写入文件失败: {}", e))?;
         
-        tracing::debug!("已保存刷新后的 token 到账号 {}", account_id);
+        tracing::debug!("已保存刷新后的 token 到账号文件 {:?}", path);
         Ok(())
     }
     
@@ -1018,7 +1325,7 @@ impl TokenManager {
 
     /// 通过 email 获取指定账号的 Token（用于预热等需要指定账号的场景）
     /// 此方法会自动刷新过期的 token
-    pub async fn get_token_by_email(&self, email: &str) -> Result<(String, String, String), String> {
+    pub async fn get_token_by_email(&self, email: &str) -> Result<SelectedToken, String> {
         // 查找账号信息
         let token_info = {
             let mut found = None;
@@ -1033,6 +1340,7 @@ impl TokenManager {
                         token.expires_in,
                         chrono::Utc::now().timestamp(),
                         token.project_id.clone(),
+                        token.upstream_proxy.clone(),
                     ));
                     break;
                 }
@@ -1048,6 +1356,7 @@ impl TokenManager {
             expires_in,
             now,
             project_id_opt,
+            upstream_proxy,
         ) = match token_info {
             Some(info) => info,
             None => return Err(format!("未找到账号: {}", email)),
@@ -1057,28 +1366,16 @@ impl TokenManager {
         
         // 检查是否过期 (提前5分钟)
         if now < timestamp + expires_in - 300 {
-            return Ok((current_access_token, project_id, email.to_string()));
+            return Ok((current_access_token, project_id, email.to_string(), upstream_proxy));
         }
 
         tracing::info!("[Warmup] Token for {} is expiring, refreshing...", email);
 
         // 调用 OAuth 刷新 token
-        match crate::modules::oauth::refresh_access_token(&refresh_token).await {
+        match self.refresh_token_single_flight(&account_id, &refresh_token, TOKEN_REFRESH_MARGIN_SECS).await {
             Ok(token_response) => {
                 tracing::info!("[Warmup] Token refresh successful for {}", email);
-                let new_now = chrono::Utc::now().timestamp();
-                
-                // 更新缓存
-                if let Some(mut entry) = self.tokens.get_mut(&account_id) {
-                    entry.access_token = token_response.access_token.clone();
-                    entry.expires_in = token_response.expires_in;
-                    entry.timestamp = new_now;
-                }
-
-                // 保存到磁盘
-                let _ = self.save_refreshed_token(&account_id, &token_response).await;
-
-                Ok((token_response.access_token, project_id, email.to_string()))
+                Ok((token_response.access_token, project_id, email.to_string(), upstream_proxy))
             }
             Err(e) => Err(format!("[Warmup] Token refresh failed for {}: {}", email, e)),
         }
@@ -1107,10 +1404,49 @@ impl TokenManager {
     }
     
 
+    /// 当前可服务该模型的账号数 (有权使用、未限流，且开启配额保护时该模型未被保护)
+    pub fn available_accounts_for(&self, target_model: &str) -> usize {
+        self.available_emails_for(target_model).len()
+    }
+
+    /// 当前可服务该模型的账号邮箱 (只读查询，不影响调度状态)
+    pub fn available_emails_for(&self, target_model: &str) -> Vec<String> {
+        let quota_protection_enabled = QUOTA_PROTECTION_ENABLED.load(Ordering::Relaxed);
+        let normalized_target = crate::proxy::common::model_mapping::normalize_to_standard_id(target_model)
+            .unwrap_or_else(|| target_model.to_string());
+        let entitled = |token: &ProxyToken| {
+            token.quota_models.contains(target_model) || token.quota_models.contains(&normalized_target)
+        };
+        // 只有出现在某个账号配额列表中的模型才按账号判断权限；未被任何账号列出的模型
+        // (或账号尚无配额数据) 无从判断，视为可用
+        let tracked = self.tokens.iter().any(|entry| entitled(entry.value()));
+        let mut emails: Vec<String> = self
+            .tokens
+            .iter()
+            .filter(|entry| {
+                let token = entry.value();
+                (!tracked || token.quota_models.is_empty() || entitled(token))
+                    && !self.is_account_unavailable(&token.account_id)
+                    && !(quota_protection_enabled && token.protected_models.contains(&normalized_target))
+            })
+            .map(|entry| entry.value().email.clone())
+            .collect();
+        // DashMap 的遍历顺序不稳定，排序后轮询游标才有意义
+        emails.sort();
+        emails
+    }
+
     /// 检查账号是否在限流中 (直接使用 account_id)
     pub fn is_rate_limited_by_account_id(&self, account_id: &str) -> bool {
         self.rate_limit_tracker.is_rate_limited(account_id)
     }
+
+    /// 检查账号是否暂不可调度 (直接使用 account_id)：限流冷却中、已被熔断或在途请求已达并发上限
+    pub fn is_account_unavailable(&self, account_id: &str) -> bool {
+        self.is_rate_limited_by_account_id(account_id)
+            || self.circuit_breaker.is_open(account_id)
+            || crate::proxy::concurrency::account_at_capacity(account_id)
+    }
     
     /// 获取距离限流重置还有多少秒
     #[allow(dead_code)]
@@ -1118,6 +1454,35 @@ impl TokenManager {
         self.rate_limit_tracker.get_reset_seconds(account_id)
     }
     
+    /// 各账号当前的限流冷却状态 (仅包含仍在冷却中的账号)
+    pub fn cooldown_snapshot(&self) -> Vec<AccountCooldown> {
+        let now = std::time::SystemTime::now();
+        let mut result: Vec<AccountCooldown> = self
+            .tokens
+            .iter()
+            .filter_map(|entry| {
+                let token = entry.value();
+                let info = self.rate_limit_tracker.get(&token.account_id)?;
+                let remaining = info.reset_time.duration_since(now).ok()?;
+                Some(AccountCooldown {
+                    account_id: token.account_id.clone(),
+                    email: token.email.clone(),
+                    remaining_ms: remaining.as_millis() as u64,
+                    reset_at: info
+                        .reset_time
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|d| d.as_millis() as i64)
+                        .unwrap_or(0),
+                    retry_after_sec: info.retry_after_sec,
+                    reason: info.reason,
+                    model: info.model.clone(),
+                })
+            })
+            .collect();
+        result.sort_by_key(|c| c.remaining_ms);
+        result
+    }
+
     /// 清除过期的限流记录
     #[allow(dead_code)]
     pub fn clean_expired_rate_limits(&self) {
@@ -1138,6 +1503,56 @@ impl TokenManager {
         self.rate_limit_tracker.clear(account_id)
     }
     
+    /// 记录账号认证/权限失败 (故障转移时跳过该账号)：
+    /// 401 让缓存的 access token 立即失效，下次使用时重新刷新；403 冷却一段时间
+    pub fn mark_auth_failure(&self, email: &str, status: u16) {
+        let Some(account_id) = self.email_to_account_id(email) else {
+            return;
+        };
+        if status == 401 {
+            if let Some(mut entry) = self.tokens.get_mut(&account_id) {
+                entry.timestamp = 0;
+            }
+            tracing::warn!("账号 {} 返回 401，下次使用时刷新 Token", email);
+            return;
+        }
+        self.rate_limit_tracker.set_lockout_until(
+            &account_id,
+            std::time::SystemTime::now() + std::time::Duration::from_secs(AUTH_FAILURE_COOLDOWN_SECS),
+            crate::proxy::rate_limit::RateLimitReason::Forbidden,
+            None,
+        );
+        tracing::warn!("账号 {} 返回 {}，冷却 {} 秒", email, status, AUTH_FAILURE_COOLDOWN_SECS);
+    }
+
+    /// 记录上游请求结果供熔断器统计 (参数为 email)
+    /// status 为 None 表示网络错误；5xx 与网络错误计为失败。429 由限流跟踪器处理，
+    /// 400 (请求本身有误) 与 401/403 (凭据问题，由认证冷却处理) 不反映账号能否访问上游，均不计入；
+    /// 其余状态码说明账号可正常访问上游
+    pub fn record_upstream_outcome(&self, email: &str, status: Option<u16>) {
+        let Some(account_id) = self.email_to_account_id(email) else {
+            return;
+        };
+        match status {
+            Some(400 | 401 | 403 | 429) => self.circuit_breaker.record_neutral(&account_id),
+            Some(code) if code < 500 => self.circuit_breaker.record_success(&account_id),
+            _ => self.circuit_breaker.record_failure(&account_id),
+        }
+    }
+
+    /// 各账号的熔断状态 (account_id -> 状态)，仅包含有请求记录的账号
+    pub fn circuit_snapshot(&self) -> std::collections::HashMap<String, CircuitBreakerStatus> {
+        self.tokens
+            .iter()
+            .filter_map(|entry| {
+                let account_id = entry.key();
+                self.circuit_breaker
+                    .status(account_id)
+                    .map(|status| (account_id.clone(), status))
+            })
+            .collect()
+    }
+
     /// 标记账号请求成功，重置连续失败计数
     /// 
     /// 在请求成功完成后调用，将该账号的失败计数归零，
@@ -1177,7 +1592,7 @@ impl TokenManager {
             let token = entry.value();
             
             // 1. 检查是否被限流
-            if self.is_rate_limited_by_account_id(&token.account_id) {
+            if self.is_account_unavailable(&token.account_id) {
                 tracing::debug!(
                     "[Fallback Check] Account {} is rate-limited, skipping",
                     token.email
@@ -1358,8 +1773,9 @@ impl TokenManager {
         model: Option<&str>,  // 🆕 新增模型参数
     ) {
         // 检查 API 是否返回了精确的重试时间
-        let has_explicit_retry_time = retry_after_header.is_some() || 
-            error_body.contains("quotaResetDelay");
+        let has_explicit_retry_time = self
+            .rate_limit_tracker
+            .has_retry_hint(retry_after_header, error_body);
         
         if has_explicit_retry_time {
             // API 返回了精确时间(quotaResetDelay),直接使用,无需实时刷新
@@ -1425,6 +1841,7 @@ impl TokenManager {
 
     /// 更新调度配置
     pub async fn update_sticky_config(&self, new_config: StickySessionConfig) {
+        self.circuit_breaker.set_config(new_config.circuit_breaker.clone());
         let mut config = self.sticky_config.write().await;
         *config = new_config;
         tracing::debug!("Scheduling configuration updated: {:?}", *config);
@@ -1441,6 +1858,31 @@ impl TokenManager {
         self.session_accounts.clear();
     }
 
+    /// 导出粘性会话绑定 (SessionID -> AccountID)，用于反代热重启
+    pub fn export_session_bindings(&self) -> std::collections::HashMap<String, String> {
+        self.session_accounts
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect()
+    }
+
+    /// 恢复粘性会话绑定（仅保留仍已加载的账号），返回恢复数量
+    pub fn restore_session_bindings(&self, bindings: std::collections::HashMap<String, String>) -> usize {
+        let mut restored = 0;
+        for (session_id, account_id) in bindings {
+            if self.tokens.contains_key(&account_id) {
+                self.session_accounts.entry(session_id).or_insert(account_id);
+                restored += 1;
+            }
+        }
+        restored
+    }
+
+    /// 限流跟踪器（热重启时导出/恢复冷却状态）
+    pub fn rate_limit_tracker(&self) -> &RateLimitTracker {
+        &self.rate_limit_tracker
+    }
+
     // ===== [FIX #820] 固定账号模式相关方法 =====
 
     /// 设置优先使用的账号ID（固定账号模式）
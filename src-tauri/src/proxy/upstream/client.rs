@@ -1,6 +1,8 @@
 // 上游客户端实现
 // 基于高性能通讯接口封装
 
+use dashmap::DashMap;
+use parking_lot::RwLock;
 use reqwest::{header, Client, Response, StatusCode};
 use serde_json::Value;
 use tokio::time::Duration;
@@ -15,32 +17,66 @@ const V1_INTERNAL_BASE_URL_FALLBACKS: [&str; 2] = [
 ];
 
 pub struct UpstreamClient {
-    http_client: Client,
+    /// 走全局上游代理的客户端 (及其代理地址)，代理配置变化时整体替换；
+    /// 代理地址无效时保存错误，请求直接失败而不是绕过代理直连
+    http_client: RwLock<(Option<String>, Result<Client, String>)>,
+    /// 账号专属上游代理的客户端 (代理地址 -> 客户端)
+    proxy_clients: DashMap<String, Client>,
 }
 
 impl UpstreamClient {
     pub fn new(proxy_config: Option<crate::proxy::config::UpstreamProxyConfig>) -> Self {
-        let mut builder = Client::builder()
+        let proxy_url = proxy_config.and_then(|config| config.proxy_url());
+        Self {
+            http_client: RwLock::new((
+                proxy_url.clone(),
+                Self::build_http_client(proxy_url.as_deref()),
+            )),
+            proxy_clients: DashMap::new(),
+        }
+    }
+
+    fn build_http_client(proxy_url: Option<&str>) -> Result<Client, String> {
+        if let Some(url) = proxy_url {
+            tracing::info!(
+                "UpstreamClient enabled proxy: {}",
+                crate::utils::http::redact_proxy_url(url)
+            );
+        }
+
+        // 600 秒仅是客户端默认值，v1internal 请求按模型单独设置总时长 (见 timeouts 模块)
+        crate::utils::http::client_builder(600, proxy_url)?
             // Connection settings (优化连接复用，减少建立开销)
-            .connect_timeout(Duration::from_secs(20))
+            .connect_timeout(super::timeouts::connect_timeout())
             .pool_max_idle_per_host(16)                  // 每主机最多 16 个空闲连接
             .pool_idle_timeout(Duration::from_secs(90))  // 空闲连接保持 90 秒
             .tcp_keepalive(Duration::from_secs(60))      // TCP 保活探测 60 秒
-            .timeout(Duration::from_secs(600))
-            .user_agent("antigravity/1.11.9 windows/amd64");
-
-        if let Some(config) = proxy_config {
-            if config.enabled && !config.url.is_empty() {
-                if let Ok(proxy) = reqwest::Proxy::all(&config.url) {
-                    builder = builder.proxy(proxy);
-                    tracing::info!("UpstreamClient enabled proxy: {}", config.url);
-                }
-            }
-        }
+            .user_agent("antigravity/1.11.9 windows/amd64")
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))
+    }
 
-        let http_client = builder.build().expect("Failed to create HTTP client");
+    /// 热更新全局上游代理 (进行中的请求不受影响)；地址未变化时保留现有连接池
+    pub fn set_proxy(&self, proxy_config: crate::proxy::config::UpstreamProxyConfig) {
+        let proxy_url = proxy_config.proxy_url();
+        if self.http_client.read().0 == proxy_url {
+            return;
+        }
+        let client = Self::build_http_client(proxy_url.as_deref());
+        *self.http_client.write() = (proxy_url, client);
+    }
 
-        Self { http_client }
+    /// 请求使用的客户端：账号配置了专属代理时走该代理，否则走全局代理
+    fn client_for(&self, upstream_proxy: Option<&str>) -> Result<Client, String> {
+        let Some(url) = upstream_proxy.filter(|u| !u.trim().is_empty()) else {
+            return self.http_client.read().1.clone();
+        };
+        if let Some(client) = self.proxy_clients.get(url) {
+            return Ok(client.clone());
+        }
+        let client = Self::build_http_client(Some(url))?;
+        self.proxy_clients.insert(url.to_string(), client.clone());
+        Ok(client)
     }
 
     /// 构建 v1internal URL
@@ -71,14 +107,16 @@ impl UpstreamClient {
     /// 调用 v1internal API（基础方法）
     /// 
     /// 发起基础网络请求，支持多端点自动 Fallback
+    /// `upstream_proxy` 为选中账号的专属代理 (随 Token 一起由 TokenManager 返回)
     pub async fn call_v1_internal(
         &self,
         method: &str,
         access_token: &str,
+        upstream_proxy: Option<&str>,
         body: Value,
         query_string: Option<&str>,
     ) -> Result<Response, String> {
-        self.call_v1_internal_with_headers(method, access_token, body, query_string, std::collections::HashMap::new()).await
+        self.call_v1_internal_with_headers(method, access_token, upstream_proxy, body, query_string, std::collections::HashMap::new()).await
     }
 
     /// [FIX #765] 调用 v1internal API，支持透传额外的 Headers
@@ -86,6 +124,7 @@ impl UpstreamClient {
         &self,
         method: &str,
         access_token: &str,
+        upstream_proxy: Option<&str>,
         body: Value,
         query_string: Option<&str>,
         extra_headers: std::collections::HashMap<String, String>,
@@ -115,6 +154,12 @@ impl UpstreamClient {
             }
         }
 
+        // 按实际请求的模型取超时；流式请求额外限制等待响应头的时间
+        let timeouts = super::timeouts::for_model(body.get("model").and_then(|m| m.as_str()).unwrap_or(""));
+        let is_stream = query_string.is_some_and(|qs| qs.contains("alt=sse"));
+
+        let http_client = self.client_for(upstream_proxy)?;
+        let started = std::time::Instant::now();
         let mut last_err: Option<String> = None;
 
         // 遍历所有端点，失败时自动切换
@@ -122,13 +167,23 @@ impl UpstreamClient {
             let url = Self::build_url(base_url, method, query_string);
             let has_next = idx + 1 < V1_INTERNAL_BASE_URL_FALLBACKS.len();
 
-            let response = self
-                .http_client
+            let request = http_client
                 .post(&url)
                 .headers(headers.clone())
                 .json(&body)
-                .send()
-                .await;
+                .timeout(timeouts.total)
+                .send();
+            let response = if is_stream {
+                match tokio::time::timeout(timeouts.first_byte, request).await {
+                    Ok(r) => r.map_err(|e| e.to_string()),
+                    Err(_) => Err(format!(
+                        "no response within first-byte timeout ({}s)",
+                        timeouts.first_byte.as_secs()
+                    )),
+                }
+            } else {
+                request.await.map_err(|e| e.to_string())
+            };
 
             match response {
                 Ok(resp) => {
@@ -145,6 +200,7 @@ impl UpstreamClient {
                         } else {
                             tracing::debug!("✓ Upstream request succeeded | Endpoint: {} | Status: {}", base_url, status);
                         }
+                        crate::proxy::diagnostics::record_upstream_call(started.elapsed());
                         return Ok(resp);
                     }
 
@@ -161,6 +217,7 @@ impl UpstreamClient {
                     }
 
                     // 不可重试的错误或已是最后一个端点，直接返回
+                    crate::proxy::diagnostics::record_upstream_call(started.elapsed());
                     return Ok(resp);
                 }
                 Err(e) => {
@@ -177,6 +234,7 @@ impl UpstreamClient {
             }
         }
 
+        crate::proxy::diagnostics::record_upstream_call(started.elapsed());
         Err(last_err.unwrap_or_else(|| "All endpoints failed".to_string()))
     }
 
@@ -203,7 +261,11 @@ impl UpstreamClient {
     /// 
     /// 获取远端模型列表，支持多端点自动 Fallback
     #[allow(dead_code)] // API ready for future model discovery feature
-    pub async fn fetch_available_models(&self, access_token: &str) -> Result<Value, String> {
+    pub async fn fetch_available_models(
+        &self,
+        access_token: &str,
+        upstream_proxy: Option<&str>,
+    ) -> Result<Value, String> {
         let mut headers = header::HeaderMap::new();
         headers.insert(
             header::CONTENT_TYPE,
@@ -219,14 +281,14 @@ impl UpstreamClient {
             header::HeaderValue::from_static("antigravity/1.11.9 windows/amd64"),
         );
 
+        let http_client = self.client_for(upstream_proxy)?;
         let mut last_err: Option<String> = None;
 
         // 遍历所有端点，失败时自动切换
         for (idx, base_url) in V1_INTERNAL_BASE_URL_FALLBACKS.iter().enumerate() {
             let url = Self::build_url(base_url, "fetchAvailableModels", None);
 
-            let response = self
-                .http_client
+            let response = http_client
                 .post(&url)
                 .headers(headers.clone())
                 .json(&serde_json::json!({}))
@@ -0,0 +1,48 @@
+// 上游失败时的账号故障转移
+//
+// 各协议 handler 的重试循环共用：429 / 401 / 403 / 5xx 视为账号或后端暂时性失败，
+// 记录到该账号后换下一个可用账号重试，直到候选账号用尽才把错误返回客户端。
+// 流式请求的故障转移仅覆盖上游返回错误状态码，以及首包预读阶段 (跳过心跳后读取第一个数据块)
+// 出现的错误或空响应；首包转发给客户端之后的中途断流不会重试，错误直接随流返回客户端。
+
+/// 单个请求最多尝试的账号数，避免大账号池下一个坏请求耗时过长
+pub const MAX_FAILOVER_ATTEMPTS: usize = 8;
+
+/// 本次请求的最大尝试次数：覆盖整个账号池 (上限 MAX_FAILOVER_ATTEMPTS)，至少 `min` 次
+pub fn max_attempts(pool_size: usize, min: usize) -> usize {
+    pool_size.min(MAX_FAILOVER_ATTEMPTS).max(min)
+}
+
+/// 换账号可能成功的失败 (账号级限流/认证/权限，或后端暂时性错误)
+pub fn is_failover_status(status: u16) -> bool {
+    matches!(status, 401 | 403 | 429 | 500 | 502 | 503 | 504 | 529)
+}
+
+/// 认证/权限失败：需要单独记录到账号 (刷新 token 或短暂冷却)
+pub fn is_auth_failure(status: u16) -> bool {
+    matches!(status, 401 | 403)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attempts_cover_pool_with_cap() {
+        assert_eq!(max_attempts(0, 1), 1);
+        assert_eq!(max_attempts(3, 1), 3);
+        assert_eq!(max_attempts(1, 2), 2);
+        assert_eq!(max_attempts(50, 1), MAX_FAILOVER_ATTEMPTS);
+    }
+
+    #[test]
+    fn classifies_statuses() {
+        assert!(is_failover_status(429));
+        assert!(is_failover_status(401));
+        assert!(is_failover_status(503));
+        assert!(!is_failover_status(400));
+        assert!(!is_failover_status(404));
+        assert!(is_auth_failure(403));
+        assert!(!is_auth_failure(429));
+    }
+}
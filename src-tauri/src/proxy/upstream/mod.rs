@@ -3,4 +3,6 @@
 
 pub mod client;
 pub mod retry;
+pub mod failover;
 pub mod models;
+pub mod timeouts;
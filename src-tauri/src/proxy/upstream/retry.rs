@@ -66,6 +66,25 @@ pub fn parse_retry_delay(error_text: &str) -> Option<u64> {
     None
 }
 
+/// 上游要求的等待时间上限 (24 小时)：防止异常值把账号锁定数年或使时间计算溢出
+pub const MAX_RETRY_AFTER_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// 解析 HTTP Retry-After 头，返回毫秒 (不超过 MAX_RETRY_AFTER_MS)
+/// 支持秒数 (允许小数，如 "1.5") 与 HTTP-date (如 "Wed, 21 Oct 2015 07:28:00 GMT") 两种格式
+pub fn parse_retry_after_header(value: &str) -> Option<u64> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<f64>() {
+        if secs.is_finite() && secs >= 0.0 {
+            return Some((secs * 1000.0).min(MAX_RETRY_AFTER_MS as f64).round() as u64);
+        }
+        return None;
+    }
+
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delta = at.timestamp_millis() - chrono::Utc::now().timestamp_millis();
+    Some((delta.max(0) as u64).min(MAX_RETRY_AFTER_MS))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -91,4 +110,23 @@ mod tests {
 
         assert_eq!(parse_retry_delay(error_json), Some(1204));
     }
+
+    #[test]
+    fn test_parse_retry_after_header() {
+        assert_eq!(parse_retry_after_header("30"), Some(30_000));
+        assert_eq!(parse_retry_after_header(" 1.5 "), Some(1500));
+        assert_eq!(parse_retry_after_header("-1"), None);
+        assert_eq!(parse_retry_after_header("NaN"), None);
+        assert_eq!(parse_retry_after_header("inf"), None);
+        assert_eq!(parse_retry_after_header("1e18"), Some(MAX_RETRY_AFTER_MS));
+        assert_eq!(
+            parse_retry_after_header("Fri, 31 Dec 9999 23:59:59 GMT"),
+            Some(MAX_RETRY_AFTER_MS)
+        );
+        assert_eq!(parse_retry_after_header("Wed, 21 Oct 2015 07:28:00 GMT"), Some(0));
+
+        let future = (chrono::Utc::now() + chrono::Duration::seconds(120)).to_rfc2822();
+        let ms = parse_retry_after_header(&future).unwrap();
+        assert!(ms > 115_000 && ms <= 120_000);
+    }
 }
@@ -0,0 +1,108 @@
+// 上游请求分阶段超时
+// - 连接超时：创建上游 HTTP 客户端时设置
+// - 首字节超时：流式请求等待响应头、handler 预读首个数据块时使用
+// - 总时长：作为单次请求的超时，覆盖完整的流式响应
+// 首字节与总时长支持在配置 `proxy.upstream_timeouts.model_overrides` 中按模型名 (支持 * 通配符) 覆盖，
+// 使长时间思考的模型不必与其他模型共用同一个超时。
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::time::Duration;
+
+use crate::proxy::common::model_mapping::lookup_by_model_pattern;
+use crate::proxy::config::{UpstreamTimeoutConfig, UpstreamTimeoutOverride};
+
+static CONFIG: Lazy<RwLock<UpstreamTimeoutConfig>> =
+    Lazy::new(|| RwLock::new(UpstreamTimeoutConfig::default()));
+
+/// 替换超时配置 (由反代启动 / 配置保存时写入)
+pub fn set_config(config: UpstreamTimeoutConfig) {
+    *CONFIG.write() = config;
+}
+
+/// 连接超时
+pub fn connect_timeout() -> Duration {
+    Duration::from_secs(CONFIG.read().connect_secs.max(1))
+}
+
+/// 某个模型生效的超时
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelTimeouts {
+    pub first_byte: Duration,
+    pub total: Duration,
+}
+
+fn resolve_with(model: &str, config: &UpstreamTimeoutConfig) -> ModelTimeouts {
+    let o = lookup_by_model_pattern(model, &config.model_overrides);
+    let first_byte = o
+        .and_then(|o| o.first_byte_secs)
+        .unwrap_or(config.first_byte_secs);
+    let total = o.and_then(|o| o.total_secs).unwrap_or(config.total_secs);
+    ModelTimeouts {
+        first_byte: Duration::from_secs(first_byte.max(1)),
+        // 总时长不应短于首字节超时
+        total: Duration::from_secs(total.max(first_byte).max(1)),
+    }
+}
+
+/// 查询模型 (实际请求上游的模型名) 的首字节与总时长超时
+pub fn for_model(model: &str) -> ModelTimeouts {
+    resolve_with(model, &CONFIG.read())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> UpstreamTimeoutConfig {
+        let mut config = UpstreamTimeoutConfig {
+            first_byte_secs: 60,
+            total_secs: 600,
+            ..Default::default()
+        };
+        config.model_overrides.insert(
+            "*-thinking".to_string(),
+            UpstreamTimeoutOverride {
+                first_byte_secs: Some(180),
+                total_secs: Some(3600),
+            },
+        );
+        config.model_overrides.insert(
+            "claude-opus-4-5-thinking".to_string(),
+            UpstreamTimeoutOverride {
+                first_byte_secs: None,
+                total_secs: Some(7200),
+            },
+        );
+        config
+    }
+
+    #[test]
+    fn falls_back_to_global_values() {
+        let t = resolve_with("gemini-2.5-flash", &config());
+        assert_eq!(t.first_byte, Duration::from_secs(60));
+        assert_eq!(t.total, Duration::from_secs(600));
+    }
+
+    #[test]
+    fn most_specific_override_wins_per_field() {
+        let t = resolve_with("claude-sonnet-4-5-thinking", &config());
+        assert_eq!(t.first_byte, Duration::from_secs(180));
+        assert_eq!(t.total, Duration::from_secs(3600));
+
+        // 精确规则只覆盖 total，first_byte 沿用全局值
+        let t = resolve_with("Claude-Opus-4-5-Thinking", &config());
+        assert_eq!(t.first_byte, Duration::from_secs(60));
+        assert_eq!(t.total, Duration::from_secs(7200));
+    }
+
+    #[test]
+    fn total_never_shorter_than_first_byte() {
+        let config = UpstreamTimeoutConfig {
+            first_byte_secs: 120,
+            total_secs: 30,
+            ..Default::default()
+        };
+        assert_eq!(resolve_with("any", &config).total, Duration::from_secs(120));
+    }
+}
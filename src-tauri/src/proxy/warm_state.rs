@@ -0,0 +1,100 @@
+// 反代热重启状态
+//
+// 反代停止（配置变更重启 / 应用退出）以及运行期间定期把粘性会话绑定、限流冷却和签名缓存
+// 写入 proxy_db，下次启动时恢复，避免重启后故障转移状态全部归零。
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use crate::modules::proxy_db;
+use crate::proxy::rate_limit::{PersistedFailureCount, PersistedRateLimit};
+use crate::proxy::signature_cache::SignatureCacheSnapshot;
+use crate::proxy::{SignatureCache, TokenManager};
+
+const WARM_STATE_KEY: &str = "warm_state";
+/// 超过该时长的快照不再恢复（会话早已结束，冷却也大多已过期）
+const WARM_STATE_MAX_AGE_SECS: i64 = 2 * 60 * 60;
+/// 运行期间自动保存间隔（用于崩溃恢复）
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ProxyWarmState {
+    #[serde(default)]
+    pub session_accounts: HashMap<String, String>,
+    #[serde(default)]
+    pub rate_limits: Vec<PersistedRateLimit>,
+    #[serde(default)]
+    pub failure_counts: Vec<PersistedFailureCount>,
+    #[serde(default)]
+    pub signatures: SignatureCacheSnapshot,
+}
+
+/// 恢复结果统计
+#[derive(Debug, Default, Serialize)]
+pub struct WarmRestoreStats {
+    pub sessions: usize,
+    pub rate_limits: usize,
+    pub signatures: usize,
+}
+
+/// 保存当前反代状态
+pub fn save(token_manager: &TokenManager) -> Result<(), String> {
+    let (rate_limits, failure_counts) = token_manager.rate_limit_tracker().export_state();
+    let state = ProxyWarmState {
+        session_accounts: token_manager.export_session_bindings(),
+        rate_limits,
+        failure_counts,
+        signatures: SignatureCache::global().snapshot(),
+    };
+    let json = serde_json::to_string(&state).map_err(|e| e.to_string())?;
+    proxy_db::save_proxy_state(WARM_STATE_KEY, &json)
+}
+
+/// 恢复上次保存的反代状态（需在账号加载完成后调用）
+pub fn restore(token_manager: &TokenManager) -> Result<WarmRestoreStats, String> {
+    let Some((json, updated_at)) = proxy_db::load_proxy_state(WARM_STATE_KEY)? else {
+        return Ok(WarmRestoreStats::default());
+    };
+
+    if chrono::Utc::now().timestamp() - updated_at > WARM_STATE_MAX_AGE_SECS {
+        tracing::info!("[WarmState] Saved proxy state is stale, skipping restore");
+        return Ok(WarmRestoreStats::default());
+    }
+
+    let state: ProxyWarmState = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    let stats = WarmRestoreStats {
+        sessions: token_manager.restore_session_bindings(state.session_accounts),
+        rate_limits: token_manager
+            .rate_limit_tracker()
+            .import_state(state.rate_limits, state.failure_counts),
+        signatures: SignatureCache::global().restore(state.signatures),
+    };
+
+    tracing::info!(
+        "[WarmState] Restored {} sticky sessions, {} cooldowns, {} cached signatures",
+        stats.sessions,
+        stats.rate_limits,
+        stats.signatures
+    );
+    Ok(stats)
+}
+
+/// 定期保存状态；反代停止（TokenManager 被释放）后自动退出
+pub fn spawn_autosave(token_manager: &Arc<TokenManager>) {
+    let weak: Weak<TokenManager> = Arc::downgrade(token_manager);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(AUTOSAVE_INTERVAL);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let Some(token_manager) = weak.upgrade() else {
+                break;
+            };
+            if let Err(e) = save(&token_manager) {
+                tracing::warn!("[WarmState] Autosave failed: {}", e);
+            }
+        }
+    });
+}
@@ -1,59 +1,155 @@
-use reqwest::{Client, Proxy};
+use reqwest::{Client, ClientBuilder, Proxy};
 use crate::modules::config::load_app_config;
+use dashmap::DashMap;
 use once_cell::sync::Lazy;
+use parking_lot::RwLock;
 
-/// Global shared HTTP client (15s timeout)
-/// Client has a built-in connection pool; cloning it is light and shares the pool
-pub static SHARED_CLIENT: Lazy<Client> = Lazy::new(|| {
-    create_base_client(15)
-});
+/// Global shared HTTP clients (15s timeout, and 60s for warmup etc.) built with the current
+/// global upstream proxy. Client has a built-in connection pool; cloning it is light and shares the pool.
+/// An unusable proxy URL is kept as the error, so requests fail instead of going out directly
+struct SharedClients {
+    proxy_url: Option<String>,
+    client: Result<Client, String>,
+    long_client: Result<Client, String>,
+}
 
-/// Global shared HTTP client (Long timeout: 60s, for warmup etc.)
-pub static SHARED_CLIENT_LONG: Lazy<Client> = Lazy::new(|| {
-    create_base_client(60)
-});
+impl SharedClients {
+    fn build(proxy_url: Option<String>) -> Self {
+        if let Some(url) = &proxy_url {
+            tracing::info!("HTTP shared client enabled upstream proxy: {}", redact_proxy_url(url));
+        }
+        Self {
+            client: create_base_client(15, proxy_url.as_deref()),
+            long_client: create_base_client(60, proxy_url.as_deref()),
+            proxy_url,
+        }
+    }
+}
 
-/// Base client creation logic
-fn create_base_client(timeout_secs: u64) -> Client {
+static SHARED_CLIENTS: Lazy<RwLock<SharedClients>> =
+    Lazy::new(|| RwLock::new(SharedClients::build(global_proxy_url())));
+
+/// Clients routed through a specific proxy (proxy URL -> client), shared like the global clients
+static PROXY_CLIENTS: Lazy<DashMap<String, Client>> = Lazy::new(DashMap::new);
+
+/// Global upstream proxy URL from the config, if enabled
+fn global_proxy_url() -> Option<String> {
+    load_app_config().ok()?.proxy.upstream_proxy.proxy_url()
+}
+
+/// Rebuild the shared clients when the global upstream proxy changes (called on config save)
+pub fn set_global_proxy(config: &crate::proxy::config::UpstreamProxyConfig) {
+    let proxy_url = config.proxy_url();
+    if SHARED_CLIENTS.read().proxy_url == proxy_url {
+        return;
+    }
+    *SHARED_CLIENTS.write() = SharedClients::build(proxy_url);
+}
+
+/// Proxy URL with the password hidden, for logs
+pub fn redact_proxy_url(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(mut parsed) if parsed.password().is_some() => {
+            let _ = parsed.set_password(Some("***"));
+            parsed.to_string()
+        }
+        _ => url.to_string(),
+    }
+}
+
+/// Proxy schemes reqwest can route through
+const PROXY_SCHEMES: &[&str] = &["http", "https", "socks5", "socks5h"];
+
+/// Parse a proxy URL (http://, https://, socks5://, socks5h://)
+fn parse_proxy(url: &str) -> Result<Proxy, String> {
+    let invalid = |e: String| format!("invalid_proxy_url: {}: {}", redact_proxy_url(url), e);
+    let parsed = url::Url::parse(url).map_err(|e| invalid(e.to_string()))?;
+    if !PROXY_SCHEMES.contains(&parsed.scheme()) {
+        return Err(invalid(format!("unsupported scheme {}", parsed.scheme())));
+    }
+    if parsed.host_str().map_or(true, str::is_empty) {
+        return Err(invalid("missing host".to_string()));
+    }
+    Proxy::all(url).map_err(|e| invalid(e.to_string()))
+}
+
+/// Check a proxy URL before it is saved
+pub fn validate_proxy_url(url: &str) -> Result<(), String> {
+    parse_proxy(url.trim()).map(|_| ())
+}
+
+/// Common client builder used by oauth, quota and the proxy upstream client:
+/// timeout plus the optional proxy. An invalid proxy URL is an error rather than a direct
+/// connection, so traffic never bypasses a configured egress proxy
+pub fn client_builder(timeout_secs: u64, proxy_url: Option<&str>) -> Result<ClientBuilder, String> {
     let mut builder = Client::builder()
         .timeout(std::time::Duration::from_secs(timeout_secs));
 
-    if let Ok(config) = load_app_config() {
-        let proxy_config = config.proxy.upstream_proxy;
-        if proxy_config.enabled && !proxy_config.url.is_empty() {
-            match Proxy::all(&proxy_config.url) {
-                Ok(proxy) => {
-                    builder = builder.proxy(proxy);
-                    tracing::info!("HTTP shared client enabled upstream proxy: {}", proxy_config.url);
-                }
-                Err(e) => {
-                    tracing::error!("invalid_proxy_url: {}, error: {}", proxy_config.url, e);
-                }
-            }
-        }
+    if let Some(url) = proxy_url.filter(|u| !u.is_empty()) {
+        let proxy = parse_proxy(url).map_err(|e| {
+            tracing::error!("{}", e);
+            e
+        })?;
+        builder = builder.proxy(proxy);
+    }
+
+    Ok(builder)
+}
+
+/// Client builder routed through the global upstream proxy, for one-off clients that need
+/// their own settings (user agent etc.)
+pub fn global_client_builder(timeout_secs: u64) -> Result<ClientBuilder, String> {
+    let proxy_url = SHARED_CLIENTS.read().proxy_url.clone();
+    client_builder(timeout_secs, proxy_url.as_deref())
+}
+
+/// Base client creation logic
+fn create_base_client(timeout_secs: u64, proxy_url: Option<&str>) -> Result<Client, String> {
+    client_builder(timeout_secs, proxy_url)?
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+/// Client routed through `proxy_url` (15s timeout); the shared client (global upstream proxy)
+/// when no proxy is given
+pub fn client_for_proxy(proxy_url: Option<&str>) -> Result<Client, String> {
+    let Some(url) = proxy_url.filter(|u| !u.trim().is_empty()) else {
+        return get_client();
+    };
+    if let Some(client) = PROXY_CLIENTS.get(url) {
+        return Ok(client.clone());
     }
+    let client = create_base_client(15, Some(url))?;
+    PROXY_CLIENTS.insert(url.to_string(), client.clone());
+    Ok(client)
+}
 
-    builder.build().unwrap_or_else(|_| Client::new())
+/// Client for requests made on behalf of an account: its own upstream proxy when configured,
+/// otherwise the global fallback proxy
+pub fn client_for_account(email: &str) -> Result<Client, String> {
+    client_for_proxy(crate::modules::account::upstream_proxy_for_email(email).as_deref())
 }
 
 /// Get uniformly configured HTTP client (15s timeout)
-pub fn get_client() -> Client {
-    SHARED_CLIENT.clone()
+pub fn get_client() -> Result<Client, String> {
+    SHARED_CLIENTS.read().client.clone()
 }
 
 /// Get long timeout HTTP client (60s timeout)
-pub fn get_long_client() -> Client {
-    SHARED_CLIENT_LONG.clone()
+pub fn get_long_client() -> Result<Client, String> {
+    SHARED_CLIENTS.read().long_client.clone()
 }
 
 /// Backward compatibility interface: Create uniformly configured HTTP client
-pub fn create_client(timeout_secs: u64) -> Client {
+pub fn create_client(timeout_secs: u64) -> Result<Client, String> {
     if timeout_secs == 15 {
         get_client()
     } else if timeout_secs == 60 {
         get_long_client()
     } else {
-        create_base_client(timeout_secs)
+        global_client_builder(timeout_secs)?
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))
     }
 }
 
@@ -61,22 +157,7 @@ pub fn create_client(timeout_secs: u64) -> Client {
 pub fn create_client_with_proxy(
     timeout_secs: u64, 
     proxy_config: Option<crate::proxy::config::UpstreamProxyConfig>
-) -> Client {
-    let mut builder = Client::builder()
-        .timeout(std::time::Duration::from_secs(timeout_secs));
-
-    if let Some(config) = proxy_config {
-        if config.enabled && !config.url.is_empty() {
-            match Proxy::all(&config.url) {
-                Ok(proxy) => {
-                    builder = builder.proxy(proxy);
-                }
-                Err(e) => {
-                    tracing::error!("invalid_proxy_url: {}, error: {}", config.url, e);
-                }
-            }
-        }
-    }
-
-    builder.build().unwrap_or_else(|_| Client::new())
+) -> Result<Client, String> {
+    let proxy_url = proxy_config.and_then(|config| config.proxy_url());
+    create_base_client(timeout_secs, proxy_url.as_deref())
 }
@@ -17,6 +17,9 @@ import { useAccountStore } from './stores/useAccountStore';
 import { useTranslation } from 'react-i18next';
 import { listen } from '@tauri-apps/api/event';
 import { invoke } from '@tauri-apps/api/core';
+import { ask } from '@tauri-apps/plugin-dialog';
+import { reauthAccount } from './services/accountService';
+import { showToast } from './components/common/ToastContainer';
 
 const router = createBrowserRouter([
   {
@@ -62,7 +65,7 @@ const router = createBrowserRouter([
 function App() {
   const { config, loadConfig } = useConfigStore();
   const { fetchCurrentAccount, fetchAccounts } = useAccountStore();
-  const { i18n } = useTranslation();
+  const { t, i18n } = useTranslation();
 
   useEffect(() => {
     loadConfig();
@@ -97,13 +100,40 @@ function App() {
       })
     );
 
+    // 监听授权失效事件：提示用户并直接进入该账号的重新授权流程
+    unlistenPromises.push(
+      listen<{ account_id: string; email: string }>('accounts://needs-reauth', async (event) => {
+        const { account_id, email } = event.payload;
+        fetchAccounts();
+        const confirmed = await ask(t('accounts.reauth.prompt', { email }), {
+          title: t('accounts.reauth.title'),
+          kind: 'warning',
+        });
+        if (!confirmed) return;
+        try {
+          await reauthAccount(account_id);
+          showToast(t('accounts.reauth.success', { email }), 'success');
+        } catch (error) {
+          showToast(`${t('common.error')}: ${error}`, 'error');
+        }
+      })
+    );
+
+    // 托盘或链接发起的重新授权完成后刷新列表
+    unlistenPromises.push(
+      listen('accounts://reauthorized', () => {
+        fetchCurrentAccount();
+        fetchAccounts();
+      })
+    );
+
     // Cleanup
     return () => {
       Promise.all(unlistenPromises).then(unlisteners => {
         unlisteners.forEach(unlisten => unlisten());
       });
     };
-  }, [fetchCurrentAccount, fetchAccounts]);
+  }, [fetchCurrentAccount, fetchAccounts, t]);
 
   // Update notification state
   const [showUpdateNotification, setShowUpdateNotification] = useState(false);
@@ -1,14 +1,22 @@
 import { useEffect, useRef } from 'react';
+import { listen } from '@tauri-apps/api/event';
 import { useConfigStore } from '../../stores/useConfigStore';
 import { useAccountStore } from '../../stores/useAccountStore';
 
 function BackgroundTaskRunner() {
-    const { config } = useConfigStore();
-    const { refreshAllQuotas } = useAccountStore();
+    const { config, loadConfig } = useConfigStore();
+    const { refreshAllQuotas, fetchAccounts } = useAccountStore();
 
     // Use refs to track previous state to detect "off -> on" transitions
     const prevAutoRefreshRef = useRef(false);
     const prevAutoSyncRef = useRef(false);
+    // 启动时的首次刷新按 scheduler_jitter_secs 随机延迟，避免多实例同时启动时集中请求上游
+    const isStartupRef = useRef(true);
+    const startupRefreshRef = useRef<ReturnType<typeof setTimeout> | null>(null);
+
+    useEffect(() => () => {
+        if (startupRefreshRef.current) clearTimeout(startupRefreshRef.current);
+    }, []);
 
     // Auto Refresh Quota Effect
     useEffect(() => {
@@ -16,15 +24,29 @@ function BackgroundTaskRunner() {
 
         let intervalId: ReturnType<typeof setTimeout> | null = null;
         const { auto_refresh, refresh_interval } = config;
+        // 配置了按账号/标签的刷新规则时由后端调度器按各自间隔刷新
+        const backendScheduled = (config.quota_refresh_rules?.length ?? 0) > 0;
+        // 用户在设置或托盘中暂停了配额刷新
+        const paused = config.paused_jobs?.includes('quota_refresh') ?? false;
 
         // Check if we just turned it on
-        if (auto_refresh && !prevAutoRefreshRef.current) {
-            console.log('[BackgroundTask] Auto-refresh enabled, executing immediately...');
-            refreshAllQuotas();
+        if (auto_refresh && !paused && !prevAutoRefreshRef.current) {
+            if (isStartupRef.current) {
+                const delayMs = Math.random() * Math.min(config.scheduler_jitter_secs ?? 30, 300) * 1000;
+                console.log(`[BackgroundTask] Auto-refresh on startup, executing in ${Math.round(delayMs / 1000)}s...`);
+                startupRefreshRef.current = setTimeout(() => {
+                    startupRefreshRef.current = null;
+                    refreshAllQuotas();
+                }, delayMs);
+            } else {
+                console.log('[BackgroundTask] Auto-refresh enabled, executing immediately...');
+                refreshAllQuotas();
+            }
         }
         prevAutoRefreshRef.current = auto_refresh;
+        isStartupRef.current = false;
 
-        if (auto_refresh && refresh_interval > 0) {
+        if (auto_refresh && refresh_interval > 0 && !backendScheduled && !paused) {
             console.log(`[BackgroundTask] Starting auto-refresh quota timer: ${refresh_interval} mins`);
             intervalId = setInterval(() => {
                 console.log('[BackgroundTask] Auto-refreshing all quotas...');
@@ -38,7 +60,33 @@ function BackgroundTaskRunner() {
                 clearInterval(intervalId);
             }
         };
-    }, [config?.auto_refresh, config?.refresh_interval]);
+    }, [
+        config?.auto_refresh,
+        config?.refresh_interval,
+        config?.quota_refresh_rules?.length,
+        config?.paused_jobs?.includes('quota_refresh'),
+    ]);
+
+    // Reload accounts after the backend refreshed quotas on its own schedule
+    useEffect(() => {
+        const unlisten = listen('quota://refreshed', () => {
+            console.log('[BackgroundTask] Backend quota refresh finished, reloading accounts...');
+            fetchAccounts();
+        });
+        return () => {
+            unlisten.then(fn => fn());
+        };
+    }, [fetchAccounts]);
+
+    // Reload config after a scheduled job was paused or resumed (e.g. from the tray)
+    useEffect(() => {
+        const unlisten = listen('scheduler://jobs_changed', () => {
+            loadConfig();
+        });
+        return () => {
+            unlisten.then(fn => fn());
+        };
+    }, [loadConfig]);
 
     // Auto Sync Current Account Effect
     useEffect(() => {
@@ -0,0 +1,132 @@
+import { useState } from 'react';
+import { useTranslation } from 'react-i18next';
+import { save } from '@tauri-apps/plugin-dialog';
+import { ShieldCheck, Loader2, Copy, Download, X } from 'lucide-react';
+import { request as invoke } from '../../utils/request';
+import { showToast } from '../common/ToastContainer';
+import { issueProxyClientCertificate } from '../../services/proxyService';
+import { ClientCertificateBundle } from '../../types/config';
+
+interface ClientCertificateCardProps {
+    requireClientCert: boolean;
+    className?: string;
+}
+
+const DEFAULT_VALID_DAYS = 365;
+
+export const ClientCertificateCard = ({ requireClientCert, className }: ClientCertificateCardProps) => {
+    const { t } = useTranslation();
+    const [name, setName] = useState('');
+    const [validDays, setValidDays] = useState(DEFAULT_VALID_DAYS);
+    const [issuing, setIssuing] = useState(false);
+    const [issued, setIssued] = useState<ClientCertificateBundle | null>(null);
+
+    const handleIssue = async () => {
+        if (!name.trim()) {
+            showToast(t('proxy.client_cert.name_required'), 'warning');
+            return;
+        }
+        setIssuing(true);
+        try {
+            const bundle = await issueProxyClientCertificate(name.trim(), validDays);
+            setIssued(bundle);
+            setName('');
+        } catch (error: any) {
+            showToast(t('proxy.client_cert.issue_error', { error: error.toString() }), 'error');
+        } finally {
+            setIssuing(false);
+        }
+    };
+
+    const copy = async (content: string) => {
+        await navigator.clipboard.writeText(content);
+        showToast(t('proxy.client_cert.copied'), 'success');
+    };
+
+    // 证书与私钥写入同一个 PEM 文件，大多数客户端 (curl --cert、requests) 可直接使用
+    const saveBundle = async (bundle: ClientCertificateBundle) => {
+        const path = await save({
+            filters: [{ name: 'PEM', extensions: ['pem'] }],
+            defaultPath: `${bundle.name}.pem`
+        });
+        if (!path) return;
+        try {
+            await invoke('save_text_file', { path, content: `${bundle.cert_pem}${bundle.key_pem}` });
+            showToast(t('proxy.client_cert.saved', { path }), 'success');
+        } catch (error: any) {
+            showToast(error.toString(), 'error');
+        }
+    };
+
+    return (
+        <div className={`bg-white dark:bg-base-100 rounded-xl shadow-sm border border-gray-100 dark:border-base-200 p-4 ${className || ''}`}>
+            <div className="flex items-center gap-3 mb-3">
+                <div className="w-8 h-8 rounded-lg bg-emerald-50 dark:bg-emerald-900/20 flex items-center justify-center">
+                    <ShieldCheck size={16} className="text-emerald-600 dark:text-emerald-400" />
+                </div>
+                <div>
+                    <h3 className="text-sm font-bold text-gray-900 dark:text-gray-100">{t('proxy.client_cert.title')}</h3>
+                    <p className="text-xs text-gray-500 dark:text-gray-400">
+                        {requireClientCert ? t('proxy.client_cert.subtitle_required') : t('proxy.client_cert.subtitle')}
+                    </p>
+                </div>
+            </div>
+
+            <div className="flex flex-col sm:flex-row gap-2">
+                <input
+                    type="text"
+                    className="input input-sm input-bordered flex-1"
+                    placeholder={t('proxy.client_cert.name_placeholder')}
+                    value={name}
+                    onChange={(e) => setName(e.target.value)}
+                />
+                <input
+                    type="number"
+                    min={1}
+                    className="input input-sm input-bordered w-28"
+                    title={t('proxy.client_cert.valid_days')}
+                    value={validDays}
+                    onChange={(e) => setValidDays(Math.max(1, parseInt(e.target.value) || DEFAULT_VALID_DAYS))}
+                />
+                <button
+                    className="btn btn-sm btn-primary gap-1"
+                    onClick={handleIssue}
+                    disabled={issuing}
+                >
+                    {issuing ? <Loader2 size={14} className="animate-spin" /> : <ShieldCheck size={14} />}
+                    {t('proxy.client_cert.btn_issue')}
+                </button>
+            </div>
+
+            {issued && (
+                <div className="mt-3 rounded-lg border border-amber-200 dark:border-amber-700/40 bg-amber-50/60 dark:bg-amber-900/10 p-3">
+                    <div className="flex items-start justify-between gap-2 mb-2">
+                        <p className="text-xs text-amber-700 dark:text-amber-400">
+                            {t('proxy.client_cert.key_once_warning', {
+                                name: issued.name,
+                                expires: new Date(issued.expires_at * 1000).toLocaleDateString()
+                            })}
+                        </p>
+                        <button className="btn btn-ghost btn-xs" onClick={() => setIssued(null)}>
+                            <X size={14} />
+                        </button>
+                    </div>
+                    <div className="flex flex-wrap gap-2">
+                        <button className="btn btn-xs gap-1" onClick={() => saveBundle(issued)}>
+                            <Download size={12} />
+                            {t('proxy.client_cert.btn_save')}
+                        </button>
+                        <button className="btn btn-xs gap-1" onClick={() => copy(issued.cert_pem)}>
+                            <Copy size={12} />
+                            {t('proxy.client_cert.btn_copy_cert')}
+                        </button>
+                        <button className="btn btn-xs gap-1" onClick={() => copy(issued.key_pem)}>
+                            <Copy size={12} />
+                            {t('proxy.client_cert.btn_copy_key')}
+                        </button>
+                    </div>
+                </div>
+            )}
+        </div>
+    );
+};
@@ -2,6 +2,7 @@ import { useState, useEffect, useMemo, useRef } from 'react';
 import { save, open, ask } from '@tauri-apps/plugin-dialog';
 import { request as invoke } from '../utils/request';
 import { join } from '@tauri-apps/api/path';
+import { listen } from '@tauri-apps/api/event';
 import { Search, RefreshCw, Download, Upload, Trash2, LayoutGrid, List, ToggleLeft, ToggleRight, Sparkles } from 'lucide-react';
 import { useAccountStore } from '../stores/useAccountStore';
 import { useConfigStore } from '../stores/useConfigStore';
@@ -14,6 +15,7 @@ import ModalDialog from '../components/common/ModalDialog';
 import Pagination from '../components/common/Pagination';
 import { showToast } from '../components/common/ToastContainer';
 import { Account } from '../types/account';
+import { JobProgressEvent } from '../types/config';
 import { Instance } from '../types/instance';
 import { listInstances, switchAccountInInstance, getInstanceStatus, startInstance, switchAccountHot } from '../services/instanceService';
 import InstanceSelectDialog from '../components/accounts/InstanceSelectDialog';
@@ -543,6 +545,29 @@ function Accounts() {
 
     const [isRefreshing, setIsRefreshing] = useState(false);
     const [isRefreshConfirmOpen, setIsRefreshConfirmOpen] = useState(false);
+    const [refreshProgress, setRefreshProgress] = useState<{ done: number; total: number } | null>(null);
+
+    // 刷新全部时后端逐账号上报进度：更新按钮计数，并取消已完成账号的加载状态
+    useEffect(() => {
+        const unlisten = listen<JobProgressEvent>('scheduler://job_progress', (event) => {
+            const { job_type, job_id, done, total, message } = event.payload;
+            if (job_type !== 'quota_refresh' || job_id !== 'manual') return;
+            setRefreshProgress({ done, total });
+            if (message) {
+                const finished = useAccountStore.getState().accounts.find(a => a.email === message);
+                if (finished) {
+                    setRefreshingIds(prev => {
+                        const next = new Set(prev);
+                        next.delete(finished.id);
+                        return next;
+                    });
+                }
+            }
+        });
+        return () => {
+            unlisten.then(fn => fn());
+        };
+    }, []);
 
     const handleRefreshClick = () => {
         setIsRefreshConfirmOpen(true);
@@ -598,6 +623,7 @@ function Accounts() {
         } finally {
             setIsRefreshing(false);
             setRefreshingIds(new Set());
+            setRefreshProgress(null);
         }
     };
 
@@ -911,7 +937,7 @@ function Accounts() {
                     >
                         <RefreshCw className={`w-3.5 h-3.5 ${isRefreshing ? 'animate-spin' : ''}`} />
                         <span className="hidden xl:inline">
-                            {isRefreshing ? t('common.loading') : (selectedIds.size > 0 ? t('accounts.refresh_selected', { count: selectedIds.size }) : t('accounts.refresh_all'))}
+                            {isRefreshing ? (refreshProgress ? `${t('common.loading')} ${refreshProgress.done}/${refreshProgress.total}` : t('common.loading')) : (selectedIds.size > 0 ? t('accounts.refresh_selected', { count: selectedIds.size }) : t('accounts.refresh_all'))}
                         </span>
                     </button>
 
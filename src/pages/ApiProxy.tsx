@@ -1,6 +1,7 @@
 import { useState, useEffect, useMemo } from 'react';
 import { useTranslation } from 'react-i18next';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import {
     Power,
     Copy,
@@ -30,12 +31,14 @@ import { cn } from '../utils/cn';
 import { useProxyModels } from '../hooks/useProxyModels';
 import GroupedSelect, { SelectOption } from '../components/common/GroupedSelect';
 import { CliSyncCard } from '../components/proxy/CliSyncCard';
+import { ClientCertificateCard } from '../components/proxy/ClientCertificateCard';
 
 interface ProxyStatus {
     running: boolean;
     port: number;
     base_url: string;
     active_accounts: number;
+    listen_addresses?: string[];
 }
 
 
@@ -197,6 +200,18 @@ export default function ApiProxy() {
         return () => clearInterval(interval);
     }, []);
 
+    // 新模型被自动添加直通别名后重新加载配置，避免本页随后保存时覆盖掉新映射
+    useEffect(() => {
+        const unlisten = listen<Array<{ model: string; info: { alias_added: boolean } }>>('models://discovered', (event) => {
+            if (event.payload.some(m => m.info.alias_added)) {
+                loadConfig();
+            }
+        });
+        return () => {
+            unlisten.then(fn => fn());
+        };
+    }, []);
+
     // [FIX #820] Load available accounts for fixed account mode
     const loadAccounts = async () => {
         try {
@@ -382,6 +397,25 @@ export default function ApiProxy() {
         saveConfig(newConfig);
     };
 
+    // 运行中切换局域网访问时原地重新绑定监听地址，无需重启服务
+    const handleLanAccessChange = async (enabled: boolean) => {
+        if (!appConfig) return;
+        const newConfig = {
+            ...appConfig,
+            proxy: { ...appConfig.proxy, allow_lan_access: enabled }
+        };
+        try {
+            await invoke('save_config', { config: newConfig });
+            setAppConfig(newConfig);
+            if (status.running) {
+                setStatus(await invoke<ProxyStatus>('restart_proxy_listeners'));
+            }
+        } catch (error) {
+            console.error('切换局域网访问失败:', error);
+            showToast(`${t('common.error')}: ${error}`, 'error');
+        }
+    };
+
     const updateSchedulingConfig = (updates: Partial<StickySessionConfig>) => {
         if (!appConfig) return;
         const currentScheduling = appConfig.proxy.scheduling || { mode: 'Balance', max_wait_seconds: 60 };
@@ -583,8 +617,9 @@ export default function ApiProxy() {
 
     const getPythonExample = (modelId: string) => {
         const port = status.running ? status.port : (appConfig?.proxy.port || 8045);
-        // 推荐使用 127.0.0.1 以避免部分环境 IPv6 解析延迟问题
-        const baseUrl = `http://127.0.0.1:${port}/v1`;
+        // 推荐使用 127.0.0.1 以避免部分环境 IPv6 解析延迟问题 (运行中时使用服务返回的地址，含 https)
+        const rootUrl = status.running && status.base_url ? status.base_url : `http://127.0.0.1:${port}`;
+        const baseUrl = `${rootUrl}/v1`;
         const apiKey = appConfig?.proxy.api_key || 'YOUR_API_KEY';
 
         // 1. Anthropic Protocol
@@ -593,7 +628,7 @@ export default function ApiProxy() {
  
  client = Anthropic(
      # 推荐使用 127.0.0.1
-     base_url="${`http://127.0.0.1:${port}`}",
+     base_url="${rootUrl}",
      api_key="${apiKey}"
  )
  
@@ -609,7 +644,7 @@ export default function ApiProxy() {
 
         // 2. Gemini Protocol (Native)
         if (selectedProtocol === 'gemini') {
-            const rawBaseUrl = `http://127.0.0.1:${port}`;
+            const rawBaseUrl = rootUrl;
             return `# 需要安装: pip install google-generativeai
 import google.generativeai as genai
 
@@ -850,7 +885,7 @@ print(response.text)`;
                                                 type="checkbox"
                                                 className="toggle toggle-sm bg-gray-200 dark:bg-gray-700 border-gray-300 dark:border-gray-600 checked:bg-blue-500 checked:border-blue-500"
                                                 checked={appConfig.proxy.allow_lan_access || false}
-                                                onChange={(e) => updateProxyConfig({ allow_lan_access: e.target.checked })}
+                                                onChange={(e) => handleLanAccessChange(e.target.checked)}
                                             />
                                         </div>
                                         <p className="text-[10px] text-gray-500 dark:text-gray-400">
@@ -863,11 +898,6 @@ print(response.text)`;
                                                 {t('proxy.config.allow_lan_access_warning')}
                                             </p>
                                         )}
-                                        {status.running && (
-                                            <p className="text-[10px] text-blue-600 dark:text-blue-400">
-                                                {t('proxy.config.allow_lan_access_restart_hint')}
-                                            </p>
-                                        )}
                                     </div>
 
                                     {/* 访问授权 */}
@@ -1614,6 +1644,16 @@ print(response.text)`;
                     )
                 }
 
+                {/* 客户端证书 (mTLS) */}
+                {
+                    !configLoading && !configError && appConfig && appConfig.proxy.tls?.enabled && (
+                        <ClientCertificateCard
+                            className="mt-4"
+                            requireClientCert={!!appConfig.proxy.tls.require_client_cert}
+                        />
+                    )
+                }
+
                 {/* 多协议支持信息 */}
                 {
                     !configLoading && !configError && appConfig && status.running && (
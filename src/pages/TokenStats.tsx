@@ -10,6 +10,7 @@ interface TokenStatsAggregated {
     total_output_tokens: number;
     total_tokens: number;
     request_count: number;
+    estimated_cost?: number;
 }
 
 interface AccountTokenStats {
@@ -18,6 +19,7 @@ interface AccountTokenStats {
     total_output_tokens: number;
     total_tokens: number;
     request_count: number;
+    estimated_cost?: number;
 }
 
 interface ModelTokenStats {
@@ -26,6 +28,7 @@ interface ModelTokenStats {
     total_output_tokens: number;
     total_tokens: number;
     request_count: number;
+    estimated_cost?: number;
 }
 
 interface ModelTrendPoint {
@@ -44,6 +47,11 @@ interface TokenStatsSummary {
     total_tokens: number;
     total_requests: number;
     unique_accounts: number;
+    unknown_outcome_requests?: number;
+    total_cache_read_tokens?: number;
+    error_requests?: number;
+    avg_latency_ms?: number;
+    estimated_cost?: number;
 }
 
 type TimeRange = 'hourly' | 'daily' | 'weekly';
@@ -44,6 +44,16 @@ export interface RefreshStats {
     success: number;
     failed: number;
     details: string[];
+    errors: RefreshError[];
+    error_summary: Record<string, number>; // 失败原因 (timeout / network / oauth / save ...) -> 数量
+    elapsed_ms: number;
+}
+
+export interface RefreshError {
+    account_id: string;
+    email: string;
+    kind: string;
+    message: string;
 }
 
 export async function refreshAllQuotas(): Promise<RefreshStats> {
@@ -85,6 +95,65 @@ export async function completeOAuthLogin(): Promise<Account> {
     }
 }
 
+export interface BatchOAuthResult {
+    accounts: Account[];
+    errors: string[];
+}
+
+/** 并行打开多个授权会话；每个会话结束时发送 oauth-session-completed 事件 */
+export async function startBatchOAuthLogin(count: number): Promise<BatchOAuthResult> {
+    ensureTauriEnvironment();
+    return await invoke('start_batch_oauth_login', { count });
+}
+
+/** 重新授权账号 (login_hint 预填邮箱)；授权失效提醒通过 accounts://needs-reauth 事件下发 */
+export async function reauthAccount(accountId: string): Promise<Account> {
+    ensureTauriEnvironment();
+    try {
+        return await invoke('reauth_account', { accountId });
+    } catch (error) {
+        if (typeof error === 'string') {
+            if (error.includes('Refresh Token') || error.includes('refresh_token')) {
+                throw error;
+            }
+            throw i18n.t('accounts.add.oauth_error', { error });
+        }
+        throw error;
+    }
+}
+
+/** 设备码授权：代码与链接通过 oauth-device-code 事件下发 */
+export async function startDeviceOAuthLogin(): Promise<Account> {
+    ensureTauriEnvironment();
+    try {
+        return await invoke('start_device_oauth_login');
+    } catch (error) {
+        if (typeof error === 'string') {
+            if (error.includes('Refresh Token') || error.includes('refresh_token')) {
+                throw error;
+            }
+            throw i18n.t('accounts.add.oauth_error', { error });
+        }
+        throw error;
+    }
+}
+
+/** 手动粘贴跳转 URL 或授权码；已有登录调用在等待回调时返回 null，由该调用返回账号 */
+export async function submitOAuthCode(input: string): Promise<Account | null> {
+    ensureTauriEnvironment();
+    try {
+        return await invoke('submit_oauth_code', { input });
+    } catch (error) {
+        if (typeof error === 'string') {
+            if (error.includes('Refresh Token') || error.includes('refresh_token')) {
+                throw error;
+            }
+            throw i18n.t('accounts.add.oauth_error', { error });
+        }
+        throw error;
+    }
+}
+
 export async function cancelOAuthLogin(): Promise<void> {
     ensureTauriEnvironment();
     return await invoke('cancel_oauth_login');
@@ -99,6 +168,11 @@ export async function importFromDb(): Promise<Account> {
     return await invoke('import_from_db');
 }
 
+/** 从已有安装的 user-data-dir 导入登录账号，consent 需由用户明确确认 */
+export async function importFromUserDataDir(userDataDir: string, consent: boolean): Promise<Account> {
+    return await invoke('import_from_user_data_dir', { userDataDir, consent });
+}
+
 export async function importFromCustomDb(path: string): Promise<Account> {
     return await invoke('import_custom_db', { path });
 }
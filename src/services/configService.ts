@@ -1,5 +1,5 @@
 import { request as invoke } from '../utils/request';
-import { AppConfig } from '../types/config';
+import { AppConfig, CreatedProxyApiKey, ProxyApiKey, ProxyApiKeyScope } from '../types/config';
 
 export async function loadConfig(): Promise<AppConfig> {
     return await invoke('load_config');
@@ -8,3 +8,29 @@ export async function loadConfig(): Promise<AppConfig> {
 export async function saveConfig(config: AppConfig): Promise<void> {
     return await invoke('save_config', { config });
 }
+
+export async function listProxyApiKeys(): Promise<ProxyApiKey[]> {
+    return await invoke('list_proxy_api_keys');
+}
+
+export async function createProxyApiKey(
+    name: string,
+    scopes: ProxyApiKeyScope[],
+    requestsPerMinute?: number,
+    tokensPerMinute?: number,
+): Promise<CreatedProxyApiKey> {
+    // 明文 key 只在创建时返回一次
+    return await invoke('create_proxy_api_key', { name, scopes, requestsPerMinute, tokensPerMinute });
+}
+
+export async function updateProxyApiKeyLimits(
+    id: string,
+    requestsPerMinute?: number,
+    tokensPerMinute?: number,
+): Promise<void> {
+    return await invoke('update_proxy_api_key_limits', { id, requestsPerMinute, tokensPerMinute });
+}
+
+export async function revokeProxyApiKey(id: string): Promise<void> {
+    return await invoke('revoke_proxy_api_key', { id });
+}
@@ -0,0 +1,50 @@
+import { request as invoke } from '../utils/request';
+import { ClientCertificateBundle } from '../types/config';
+
+export interface ProxyRequestLog {
+    id: string;
+    timestamp: number;
+    method: string;
+    url: string;
+    status: number;
+    duration: number;
+    model?: string;
+    mapped_model?: string;
+    account_email?: string;
+    error?: string;
+    request_body?: string;
+    response_body?: string;
+    input_tokens?: number;
+    output_tokens?: number;
+    protocol?: string;
+}
+
+export interface ProxyLogQuery {
+    search?: string;
+    model?: string;
+    account_email?: string;
+    protocol?: string;
+    status_min?: number;
+    status_max?: number;
+    since?: number; // 毫秒时间戳 (含)
+    until?: number; // 毫秒时间戳 (不含)
+    include_bodies?: boolean;
+    limit?: number;
+    offset?: number;
+}
+
+export async function queryProxyLogs(query: ProxyLogQuery): Promise<ProxyRequestLog[]> {
+    return await invoke('query_proxy_logs', { query });
+}
+
+export async function purgeProxyLogs(before?: number): Promise<number> {
+    return await invoke('purge_proxy_logs', { before });
+}
+
+/** 用本地 CA 签发反代客户端证书 (mTLS)，私钥只在此时返回一次 */
+export async function issueProxyClientCertificate(
+    name: string,
+    validDays?: number,
+): Promise<ClientCertificateBundle> {
+    return await invoke('issue_proxy_client_certificate', { name, validDays });
+}
@@ -9,10 +9,16 @@ export interface Account {
     disabled?: boolean;
     disabled_reason?: string;
     disabled_at?: number;
+    needs_reauth?: boolean; // refresh token 已失效 (invalid_grant)，需要重新登录授权；临时网络错误不会设置
     proxy_disabled?: boolean;
     proxy_disabled_reason?: string;
     proxy_disabled_at?: number;
     protected_models?: string[];
+    quota_protected?: boolean; // 所有受监控模型均低于保护阈值，暂停参与反代轮换
+    expires_at?: number; // 临时账号的过期时间 (Unix 秒)，到期后自动归档并清除
+    depletes_at?: number; // 按近期消耗速度预计配额耗尽的时间 (Unix 秒)
+    upstream_proxy?: string; // 该账号 OAuth 与配额请求使用的上游代理，为空时使用全局代理
+    token_budget?: TokenBudget; // 手动 Token 预算，超出后反代不再选用该账号
     created_at: number;
     last_used: number;
 }
@@ -39,6 +45,108 @@ export interface ModelQuota {
     reset_time: string;
 }
 
+export interface QuotaHistoryQuery {
+    account_id?: string;
+    model?: string;
+    from?: number; // 秒，默认 7 天前
+    to?: number; // 秒，默认当前时间
+    bucket_secs?: number; // 降采样时间桶
+    max_points?: number; // 未指定时间桶时每条序列的最大点数
+}
+
+export interface QuotaPoint {
+    timestamp: number;
+    percentage: number; // 时间桶内平均剩余百分比
+    min: number;
+    max: number;
+    samples: number;
+}
+
+export interface QuotaHistory {
+    from: number;
+    to: number;
+    bucket_secs: number;
+    series: { account_id: string; model: string; points: QuotaPoint[] }[];
+}
+
+export interface QuotaAlert {
+    account_id: string;
+    email: string;
+    model: string;
+    percentage: number;
+    threshold: number;
+    timestamp: number;
+}
+
+export interface TokenBudget {
+    limit_tokens: number; // 每个周期允许的总 Token 数 (输入 + 输出)
+    period: 'day' | 'week' | 'month';
+}
+
+export type CircuitState = 'closed' | 'open' | 'half_open';
+
+export interface CircuitBreakerStatus {
+    state: CircuitState;
+    consecutive_failures: number;
+    error_rate: number; // 0-1，统计窗口内
+    window_requests: number;
+    retry_in_ms?: number; // 熔断中时距离半开探测的剩余时间
+}
+
+export interface TokenBudgetStatus {
+    account_id: string;
+    email: string;
+    budget: TokenBudget;
+    used_tokens: number;
+    period_start: number; // 当前周期开始时间 (Unix 秒，本地时间)
+    exhausted: boolean;
+}
+
+export interface QuotaMatrixCell {
+    remaining: number; // 该系列各模型中最低的剩余百分比
+    reset_time?: string;
+    models: string[];
+    protected: boolean;
+    burn_rate_per_hour: number;
+    depletes_at?: number;
+}
+
+export interface QuotaMatrixRow {
+    account_id: string;
+    email: string;
+    name?: string;
+    is_current: boolean;
+    disabled: boolean;
+    proxy_disabled: boolean;
+    is_forbidden: boolean;
+    subscription_tier?: string;
+    last_updated?: number;
+    tags: string[];
+    quota_protected: boolean;
+    budget_exhausted: boolean;
+    fetch_backoff_secs?: number; // 连续拉取失败后的轮询暂停剩余秒数
+    cooldown?: { reset_at: number; remaining_ms: number; model?: string }; // 反代限流冷却 (反代未运行时为空)
+    depletes_at?: number;
+    cells: (QuotaMatrixCell | null)[]; // 与 families 对齐
+}
+
+export interface QuotaMatrix {
+    families: string[];
+    rows: QuotaMatrixRow[];
+    generated_at: number;
+}
+
+export type QuotaExportFormat = 'csv' | 'json';
+
+export interface QuotaForecast {
+    account_id: string;
+    model: string;
+    percentage: number;
+    burn_rate_per_hour: number; // 每小时消耗的百分点
+    depletes_at?: number; // 预计耗尽时间 (Unix 秒)，不在消耗或会先重置时为空
+    samples: number;
+}
+
 export interface DeviceProfile {
     machine_id: string;
     mac_machine_id: string;
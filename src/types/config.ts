@@ -1,6 +1,8 @@
 export interface UpstreamProxyConfig {
     enabled: boolean;
-    url: string;
+    url: string; // http://, https://, socks5://, socks5h://
+    username?: string; // 代理认证 (留空则使用地址中的 user:pass@)
+    password?: string;
 }
 
 export interface ProxyConfig {
@@ -8,15 +10,130 @@ export interface ProxyConfig {
     allow_lan_access?: boolean;
     auth_mode?: 'off' | 'strict' | 'all_except_health' | 'auto';
     port: number;
+    bind_address?: string; // 主监听地址，未设置时按 allow_lan_access 选择
+    extra_listeners?: string[]; // 额外监听地址 (host:port)
     api_key: string;
     auto_start: boolean;
     custom_mapping?: Record<string, string>;
+    mapping_priority?: string[]; // 通配符 / 正则规则的显式优先级 (按顺序匹配)
     request_timeout: number;
     enable_logging: boolean;
     upstream_proxy: UpstreamProxyConfig;
     zai?: ZaiConfig;
     scheduling?: StickySessionConfig;
     experimental?: ExperimentalConfig;
+    model_capabilities?: Record<string, ModelCapabilityOverride>;
+    mirror?: MirrorConfig;
+    api_keys?: ProxyApiKey[];
+    client_rate_limit?: ClientRateLimitConfig;
+    traffic_log?: TrafficLogConfig;
+    response_cache?: ResponseCacheConfig;
+    pricing?: Record<string, ModelPricing>; // key 支持 * 通配符
+    concurrency?: ConcurrencyConfig;
+    upstream_timeouts?: UpstreamTimeoutConfig;
+    cors?: CorsConfig; // 修改后需重启反代服务生效
+    tls?: ProxyTlsConfig; // 修改后需重启反代服务生效
+    debug_headers?: boolean; // 响应附加 X-Antigravity-* 调试诊断头
+}
+
+export interface ProxyTlsConfig {
+    enabled: boolean;
+    cert_path: string; // 与 key_path 同时留空时使用自动生成的自签名证书
+    key_path: string;
+    subject_alt_names: string[]; // 自签名证书额外覆盖的主机名 / IP
+    require_client_cert?: boolean; // 双向 TLS：只接受本地 CA 签发的客户端证书，此类请求免 API Key
+}
+
+export interface ClientCertificateBundle {
+    name: string;
+    cert_pem: string;
+    key_pem: string;
+    ca_cert_pem: string;
+    expires_at: number; // Unix 时间戳 (秒)
+}
+
+export interface CorsConfig {
+    allowed_origins: string[]; // 支持 * 通配符，为空时允许任意来源
+    allowed_headers: string[]; // 为空时允许任意请求头
+    max_age_secs: number; // 预检结果缓存时间
+}
+
+export interface UpstreamTimeoutConfig {
+    connect_secs: number; // 修改后需重启反代服务生效
+    first_byte_secs: number; // 流式请求等待首个数据块
+    total_secs: number; // 单次上游请求总时长 (含完整流式响应)
+    model_overrides: Record<string, UpstreamTimeoutOverride>; // key: 模型名，支持 * 通配符
+}
+
+export interface UpstreamTimeoutOverride {
+    first_byte_secs?: number;
+    total_secs?: number;
+}
+
+export interface ConcurrencyConfig {
+    max_in_flight: number; // 全局在途请求上限，0 表示不限制
+    max_per_account: number; // 单账号在途请求上限，0 表示不限制
+    max_queue: number;
+    queue_timeout_secs: number;
+}
+
+export interface ModelPricing {
+    input_per_1k: number; // 美元 / 1K tokens
+    output_per_1k: number;
+    cache_read_per_1k?: number; // 未设置时按 input_per_1k 计
+}
+
+export interface ResponseCacheConfig {
+    enabled: boolean; // 仅缓存 temperature=0 的非流式请求
+    ttl_secs: number;
+    max_entries: number;
+    max_entry_bytes: number;
+}
+
+export interface TrafficLogConfig {
+    capture_bodies: boolean; // 关闭时只记录元数据
+    max_body_bytes: number; // 0 表示不截断
+}
+
+export interface ClientRateLimitConfig {
+    requests_per_minute: number; // 0 表示不限制
+    tokens_per_minute: number;
+}
+
+export type ProxyApiKeyScope = 'openai' | 'anthropic' | 'gemini' | 'mcp';
+
+export interface ProxyApiKey {
+    id: string;
+    name: string;
+    key_hash: string; // 仅保存哈希，明文只在创建时返回
+    key_prefix: string;
+    scopes: ProxyApiKeyScope[]; // 为空表示允许所有协议
+    created_at: number;
+    revoked: boolean;
+    requests_per_minute?: number; // 未设置时使用 client_rate_limit 默认值
+    tokens_per_minute?: number;
+}
+
+export interface CreatedProxyApiKey extends ProxyApiKey {
+    key: string;
+}
+
+export interface MirrorConfig {
+    enabled: boolean;
+    sample_percent: number;
+    target_model: string;
+    accounts: string[];
+    max_samples: number;
+    store_prompts?: boolean; // 样本中保存请求体 (默认不保存)
+}
+
+export interface ModelCapabilityOverride {
+    context_window?: number;
+    max_output_tokens?: number;
+    max_thinking_budget?: number;
+    supports_thinking?: boolean;
+    supports_vision?: boolean;
+    supports_tools?: boolean;
 }
 
 export type SchedulingMode = 'CacheFirst' | 'Balance' | 'PerformanceFirst';
@@ -24,6 +141,15 @@ export type SchedulingMode = 'CacheFirst' | 'Balance' | 'PerformanceFirst';
 export interface StickySessionConfig {
     mode: SchedulingMode;
     max_wait_seconds: number;
+    circuit_breaker?: CircuitBreakerConfig;
+}
+
+export interface CircuitBreakerConfig {
+    enabled: boolean;
+    failure_threshold: number; // 连续失败次数 (5xx / 网络错误)
+    open_secs: number; // 首次熔断时长，之后每次翻倍
+    max_open_secs: number;
+    window_secs: number; // 错误率统计窗口
 }
 
 export type ZaiDispatchMode = 'off' | 'exclusive' | 'pooled' | 'fallback';
@@ -60,6 +186,10 @@ export interface QuotaProtectionConfig {
     enabled: boolean;
     threshold_percentage: number; // 1-99
     monitored_models: string[];
+    alert_enabled?: boolean; // 跌破告警阈值时通知
+    alert_threshold_percentage?: number; // 全局告警阈值 (1-99)
+    account_alert_thresholds?: Record<string, number>; // 账号 ID -> 告警阈值，覆盖全局值
+    alert_notification?: boolean; // 是否弹出桌面通知
 }
 
 export interface PinnedQuotaModelsConfig {
@@ -88,5 +218,238 @@ export interface AppConfig {
     quota_protection: QuotaProtectionConfig; // [NEW] 配额保护配置
     pinned_quota_models: PinnedQuotaModelsConfig; // [NEW] 配额关注列表
     proxy: ProxyConfig;
+    token_storage?: TokenStorageConfig; // [NEW] Refresh Token 存储后端
+    log_level?: string; // [NEW] 日志级别 (info / debug ...)，修改后即时生效
+    remote_sync?: RemoteSyncConfig; // [NEW] 配置远程同步
+    config_backup_retention?: number; // [NEW] 自动保留的配置备份数量
+    quota_refresh_rules?: QuotaRefreshRule[]; // [NEW] 按账号/标签的配额刷新间隔
+    quota_refresh_concurrency?: number; // [NEW] 批量刷新配额的并发数，默认 5
+    quota_refresh_timeout_secs?: number; // [NEW] 批量刷新时单个账号的超时 (秒)，默认 30
+    quota_cache_ttl_secs?: number; // [NEW] 配额响应缓存时间 (秒)，0 表示总是请求上游
+    cron_jobs?: CronJob[]; // [NEW] 按 cron 表达式执行的定时任务
+    job_history_retention_days?: number; // [NEW] 定时任务执行记录保留天数，默认 30
+    paused_jobs?: ScheduledJobName[]; // [NEW] 用户暂停的定时任务
+    scheduler_jitter_secs?: number; // [NEW] 定时任务执行前的随机延迟上限 (秒)，默认 30，0 表示关闭，最大 300
+    scheduler_max_parallel_jobs?: number; // [NEW] 同时执行的定时任务上限，默认 3
+    scheduler_long_job_parallel?: number; // [NEW] 同时执行的耗时任务 (备份/预热) 上限，默认 1
+    job_catch_up?: Partial<Record<ScheduledJobName, CatchUpPolicy>>; // [NEW] 系统休眠唤醒后内置任务的补偿策略
+    oauth?: OAuthConfig; // [NEW] OAuth 登录设置
+}
+
+export interface OAuthConfig {
+    callback_port_start: number; // 本地回调端口范围起点，0 表示随机端口
+    callback_port_end: number; // 端口范围终点 (含)，端口均被占用时回退到随机端口
+    proactive_refresh_margin_secs?: number; // 使用中账号在 token 过期前多少秒提前刷新，默认 600，0 表示关闭
+    browser?: string; // 打开授权链接的浏览器 (可执行文件名/路径，macOS 可填应用名)，为空使用系统默认浏览器
+    browser_profile?: string; // 浏览器配置文件 (Chromium profile 目录或 Firefox profile 名)
+    browser_private?: boolean; // 在隐身/无痕窗口中打开
+    callback_tls?: boolean; // 回调服务使用自签名证书的 HTTPS
+    callback_timeout_minutes?: number; // 授权流程超时 (分钟)，超时后关闭回调服务
+    device_client_id?: string; // 设备码授权使用的 OAuth 客户端 ("TVs and Limited Input devices" 类型)，为空时不可用
+    device_client_secret?: string; // 设备码授权客户端的密钥
+    device_scopes?: string[]; // 设备码授权请求的 scope，为空时使用 openid / email / profile
+}
+
+export type ScheduledJobName =
+    | 'quota_refresh'
+    | 'warmup'
+    | 'config_backup'
+    | 'config_sync'
+    | 'update_check'
+    | 'instance_schedule';
+
+export interface ScheduledJobState {
+    id: string; // 内置任务名或 cron 任务 id
+    kind: string;
+    is_cron: boolean;
+    paused: boolean;
 }
 
+export type CronJobKind = 'quota_refresh' | 'config_backup' | 'instance_start' | 'instance_stop';
+
+export interface CronJob {
+    id: string; // 新建时留空，由后端生成
+    kind: CronJobKind;
+    expression: string; // 五段式 cron 表达式 (分 时 日 月 周) 或 @daily 等别名
+    timezone?: string; // "local" (默认)、"UTC"、IANA 时区名如 "Europe/Berlin" 或固定偏移如 "+08:00"
+    instance_id?: string; // 实例启停任务的目标实例
+    enabled?: boolean;
+    catch_up?: CatchUpPolicy; // 系统休眠期间错过的执行如何补偿，默认 run_once
+}
+
+// run_once: 唤醒后执行一次；skip: 放弃错过的执行；run_all: 补执行每一次错过的 (最多 24 次)
+export type CatchUpPolicy = 'run_once' | 'skip' | 'run_all';
+
+export interface JobRun {
+    id: number;
+    job_type: string; // quota_refresh / config_backup / instance_start / instance_stop / enable_account / disable_account / warmup / account_expiry / token_refresh
+    job_id?: string; // 触发执行的 cron 任务或延时任务 id
+    started_at: number; // 毫秒
+    finished_at: number; // 毫秒
+    status: 'success' | 'failed' | 'skipped';
+    detail?: string;
+    error?: string;
+}
+
+// scheduler://job_started / job_progress / job_finished 事件，同一次执行的 run_id 相同
+export interface JobStartedEvent {
+    run_id: number;
+    job_type: string;
+    job_id?: string;
+    started_at: number; // 毫秒
+}
+
+export interface JobProgressEvent {
+    run_id: number;
+    job_type: string;
+    job_id?: string;
+    done: number;
+    total: number;
+    message?: string; // 如刚完成的账号邮箱
+}
+
+export interface JobFinishedEvent {
+    run_id: number;
+    job_type: string;
+    job_id?: string;
+    success: boolean;
+    detail?: string;
+    error?: string;
+    duration_ms: number;
+}
+
+export type DelayedAction =
+    | { type: 'enable_account'; account_id: string }
+    | { type: 'disable_account'; account_id: string }
+    | { type: 'instance_start'; instance_id: string }
+    | { type: 'instance_stop'; instance_id: string }
+    | { type: 'quota_refresh' }
+    | { type: 'config_backup' };
+
+export interface DelayedTask {
+    id: string;
+    action: DelayedAction;
+    run_at: number; // 执行时间 (Unix 秒)
+    created_at: number;
+    label?: string;
+}
+
+export interface JobHistoryQuery {
+    job_type?: string;
+    job_id?: string;
+    status?: 'success' | 'failed' | 'skipped';
+    from?: number; // 毫秒
+    to?: number; // 毫秒
+    limit?: number; // 默认 200
+}
+
+export interface QuotaRefreshRule {
+    tag?: string;
+    account_ids?: string[]; // 优先于标签规则
+    interval_minutes: number;
+}
+
+export interface RemoteSyncConfig {
+    enabled: boolean;
+    backend: 'webdav' | 's3' | 'git';
+    url: string; // WebDAV 文件 URL / S3 endpoint（AWS 留空）/ Git 仓库地址
+    username: string; // WebDAV 用户名 / S3 Access Key ID
+    password: string; // WebDAV 密码 / S3 Secret Access Key
+    bucket: string;
+    region: string;
+    branch: string;
+    path: string; // S3 对象键 / Git 仓库内文件路径
+    passphrase: string; // 加密密钥与账号，为空时只同步普通配置
+    include_accounts: boolean;
+    interval_minutes: number; // 0 = 仅手动同步
+}
+
+export interface ConfigAuditEntry {
+    id: number;
+    timestamp: number; // 秒
+    source: string; // 写入配置的命令或任务，外部修改为 external_edit
+    path: string;
+    old_value?: unknown;
+    new_value?: unknown;
+}
+
+export interface ConfigAuditQuery {
+    path?: string; // 设置路径或前缀，如 proxy.port / proxy
+    source?: string;
+    since?: number;
+    until?: number;
+    limit?: number;
+    offset?: number;
+}
+
+export interface ConfigBackup {
+    file_name: string;
+    created_at: number; // 毫秒时间戳
+    size: number;
+    valid: boolean; // 备份能否解析为有效配置
+}
+
+export interface ConfigSyncState {
+    remote_revision?: string;
+    fingerprint?: string;
+    last_sync?: number;
+    last_error?: string;
+}
+
+export interface ConfigSyncResult {
+    status: 'up_to_date' | 'pushed' | 'pulled' | 'conflict';
+    revision?: string;
+    changes: ConfigChange[]; // 本地 -> 远端
+    accounts_added: number;
+    remote_device?: string;
+    remote_updated_at?: number;
+}
+
+export interface TokenStorageConfig {
+    backend: 'file' | 'keychain' | 'command';
+    get_command: string; // 输出 refresh token 到 stdout, 支持 {account_id} 占位符
+    set_command: string; // 从 stdin 读取 refresh token
+    delete_command: string;
+}
+
+
+export interface ConfigIssue {
+    path: string; // 例如 "proxy.port"，根节点为空字符串
+    code: 'unknown_key' | 'wrong_type' | 'invalid_value' | 'invalid_path';
+    severity: 'error' | 'warning';
+    message: string;
+}
+
+export interface ConfigValidation {
+    valid: boolean;
+    issues: ConfigIssue[];
+}
+
+export type ConfigImportStrategy = 'overwrite' | 'merge_keep_local' | 'merge_prefer_import';
+
+export interface ConfigChange {
+    path: string;
+    local?: unknown; // 本地不存在时为空
+    imported?: unknown;
+}
+
+export interface ConfigImportResult {
+    strategy: ConfigImportStrategy;
+    dry_run: boolean;
+    changes: ConfigChange[];
+    validation: ConfigValidation;
+    applied: boolean;
+}
+
+export interface LoginItemOptions {
+    minimized: boolean; // 只在托盘运行
+    start_proxy: boolean; // 同时启动反代服务
+}
+
+export interface LoginItemStatus {
+    enabled: boolean;
+    options: LoginItemOptions;
+    platform: string;
+    location: string;
+    stale: boolean; // 注册的程序路径与当前程序不一致
+}
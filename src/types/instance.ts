@@ -13,9 +13,30 @@ export interface Instance {
   current_account_id?: string;
   is_default: boolean;
   last_launch_args?: string[];
+  last_exe_version?: string;
+  window_placement?: WindowPlacement;
   created_at: number;
 }
 
+export interface WindowPlacement {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+  mode?: 'normal' | 'maximized' | 'fullscreen';
+  monitor?: string; // 显示器名称提示，仅用于展示
+}
+
+export interface MonitorInfo {
+  name?: string;
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+  scale_factor: number;
+  is_primary: boolean;
+}
+
 export interface InstanceSummary {
   id: string;
   name: string;